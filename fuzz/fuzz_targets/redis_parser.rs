@@ -0,0 +1,8 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+// Any byte stream must parse without panicking, blowing up memory or hanging
+fuzz_target!(|data: &[u8]| {
+    workingdb::network::fuzz::redis_commands(data);
+});
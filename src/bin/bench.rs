@@ -0,0 +1,294 @@
+// WorkingDB benchmark harness - SET/GET/INCR mixes against an in-process
+// database or a running server, reporting throughput and latency percentiles
+//
+// Usage: bench [--target inproc|HOST:PORT] [--ops N] [--keys N] [--value-size N]
+//              [--concurrency N] [--mix set=50,get=45,incr=5] [--aof PATH]
+//              [--backend rwlock|sharded]
+use std::process::exit;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use rand::Rng;
+use workingdb::core::state::GlobalState;
+use workingdb::network::client::Client;
+use workingdb::persistence::aof::AppendOnlyFile;
+use workingdb::storage::memory::{MemTable, PartitionBackend};
+
+// BENCHMARK OPERATIONS
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Op {
+    Set,
+    Get,
+    Incr,
+}
+
+// WHERE REQUESTS GO
+enum Target {
+    // GlobalState in this process - no network, no protocol
+    InProcess,
+
+    // Running server over the RESP client
+    Tcp(String, u16),
+}
+
+// WORKLOAD SHAPE
+struct Workload {
+    target: Target,
+    ops: usize,
+    keys: usize,
+    value_size: usize,
+    concurrency: usize,
+    mix: Vec<(Op, u32)>,
+    aof: Option<String>,
+    backend: PartitionBackend,
+}
+
+// PER-WORKER RESULTS - LATENCIES IN NANOSECONDS
+#[derive(Default)]
+struct WorkerResult {
+    latencies: Vec<u64>,
+    errors: usize,
+}
+
+impl Workload {
+    // Pick an operation by weight and a key for it
+    fn next(&self, rng: &mut impl Rng) -> (Op, Vec<u8>) {
+        let total: u32 = self.mix.iter().map(|(_, weight)| weight).sum();
+        let mut roll = rng.random_range(0..total);
+        let op = self.mix.iter()
+            .find(|(_, weight)| {
+                if roll < *weight {
+                    return true;
+                }
+                roll -= weight;
+                false
+            })
+            .map_or(Op::Get, |(op, _)| *op);
+
+        // Counters live apart from string keys so INCR never hits a non-integer
+        let n = rng.random_range(0..self.keys);
+        let key = match op {
+            Op::Incr => format!("bench:counter:{}", n),
+            Op::Set | Op::Get => format!("bench:key:{}", n),
+        };
+        (op, key.into_bytes())
+    }
+
+    // Operations each worker runs (the first workers take the remainder)
+    fn share(&self, worker: usize) -> usize {
+        self.ops / self.concurrency + usize::from(worker < self.ops % self.concurrency)
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    let workload = parse_args().unwrap_or_else(|e| {
+        eprintln!("💥 {}", e);
+        eprintln!("Usage: bench [--target inproc|HOST:PORT] [--ops N] [--keys N] [--value-size N] [--concurrency N] [--mix set=50,get=45,incr=5] [--aof PATH] [--backend rwlock|sharded]");
+        exit(2);
+    });
+
+    println!("🏁 WorkingDB bench - {} ops, {} keys, {}-byte values, {} workers, mix {}",
+        workload.ops, workload.keys, workload.value_size, workload.concurrency, describe_mix(&workload.mix));
+
+    let result = match &workload.target {
+        Target::InProcess => run_in_process(&workload),
+        Target::Tcp(host, port) => run_tcp(&workload, host, *port).await,
+    };
+    match result {
+        Ok((results, elapsed)) => report(results, elapsed),
+        Err(e) => {
+            eprintln!("💥 Benchmark failed: {}", e);
+            exit(1);
+        }
+    }
+}
+
+// IN-PROCESS RUN - ONE OS THREAD PER WORKER AGAINST A SHARED GlobalState
+fn run_in_process(workload: &Workload) -> Result<(Vec<WorkerResult>, Duration), String> {
+    // Fresh AOF in a temp dir unless told otherwise - the write path includes logging
+    let temp = tempfile::tempdir().map_err(|e| e.to_string())?;
+    let aof_path = workload.aof.clone()
+        .unwrap_or_else(|| temp.path().join("bench.aof").display().to_string());
+    let aof = AppendOnlyFile::new(&aof_path).map_err(|e| e.to_string())?;
+    let state = Arc::new(GlobalState::new(Arc::new(MemTable::new().with_backend(workload.backend)), aof));
+
+    // PRELOAD SO GETS HIT
+    let value = vec![b'x'; workload.value_size];
+    let entries = (0..workload.keys)
+        .map(|n| (format!("bench:key:{}", n).into_bytes(), value.clone(), None))
+        .collect();
+    state.set_batch(entries)?;
+
+    let start = Instant::now();
+    let results = std::thread::scope(|scope| {
+        let handles: Vec<_> = (0..workload.concurrency)
+            .map(|worker| {
+                let state = &state;
+                let value = &value;
+                scope.spawn(move || {
+                    let mut rng = rand::rng();
+                    let mut result = WorkerResult::default();
+                    for _ in 0..workload.share(worker) {
+                        let (op, key) = workload.next(&mut rng);
+                        let began = Instant::now();
+                        let outcome = match op {
+                            Op::Set => state.set(&key, value.clone(), None),
+                            Op::Get => state.get_string(&key).map(|_| ()),
+                            Op::Incr => state.incr_by(&key, 1).map(|_| ()),
+                        };
+                        result.latencies.push(began.elapsed().as_nanos() as u64);
+                        result.errors += usize::from(outcome.is_err());
+                    }
+                    result
+                })
+            })
+            .collect();
+        handles.into_iter().map(|handle| handle.join().expect("bench worker panicked")).collect()
+    });
+
+    Ok((results, start.elapsed()))
+}
+
+// TCP RUN - ONE CLIENT CONNECTION PER WORKER
+async fn run_tcp(workload: &Workload, host: &str, port: u16) -> Result<(Vec<WorkerResult>, Duration), String> {
+    // PRELOAD SO GETS HIT
+    let value = vec![b'x'; workload.value_size];
+    let mut client = Client::connect(host, port).await.map_err(|e| format!("connect {}:{}: {}", host, port, e))?;
+    for n in 0..workload.keys {
+        client.set(format!("bench:key:{}", n).as_bytes(), &value).await.map_err(|e| e.to_string())?;
+    }
+
+    let mut clients = Vec::with_capacity(workload.concurrency);
+    for _ in 0..workload.concurrency {
+        clients.push(Client::connect(host, port).await.map_err(|e| e.to_string())?);
+    }
+
+    let start = Instant::now();
+    let tasks: Vec<_> = clients.into_iter()
+        .enumerate()
+        .map(|(worker, mut client)| {
+            // Workers own their inputs so they can run as independent tasks
+            let plan: Vec<(Op, Vec<u8>)> = {
+                let mut rng = rand::rng();
+                (0..workload.share(worker)).map(|_| workload.next(&mut rng)).collect()
+            };
+            let value = value.clone();
+            tokio::spawn(async move {
+                let mut result = WorkerResult::default();
+                for (op, key) in plan {
+                    let began = Instant::now();
+                    let outcome = match op {
+                        Op::Set => client.set(&key, &value).await,
+                        Op::Get => client.get(&key).await.map(|_| ()),
+                        Op::Incr => client.incr(&key).await.map(|_| ()),
+                    };
+                    result.latencies.push(began.elapsed().as_nanos() as u64);
+                    result.errors += usize::from(outcome.is_err());
+                }
+                result
+            })
+        })
+        .collect();
+
+    let mut results = Vec::with_capacity(tasks.len());
+    for task in tasks {
+        results.push(task.await.map_err(|e| e.to_string())?);
+    }
+    Ok((results, start.elapsed()))
+}
+
+// PRINT THROUGHPUT AND LATENCY PERCENTILES
+fn report(results: Vec<WorkerResult>, elapsed: Duration) {
+    let errors: usize = results.iter().map(|r| r.errors).sum();
+    let mut latencies: Vec<u64> = results.into_iter().flat_map(|r| r.latencies).collect();
+    latencies.sort_unstable();
+
+    let ops = latencies.len();
+    println!("⚡ {} ops in {:.3}s - {:.0} ops/sec ({} errors)",
+        ops, elapsed.as_secs_f64(), ops as f64 / elapsed.as_secs_f64().max(f64::EPSILON), errors);
+    if ops == 0 {
+        return;
+    }
+
+    let percentile = |p: f64| latencies[((ops - 1) as f64 * p).round() as usize];
+    let micros = |ns: u64| ns as f64 / 1000.0;
+    println!("⏱️ latency µs - p50 {:.1}  p90 {:.1}  p99 {:.1}  p99.9 {:.1}  max {:.1}",
+        micros(percentile(0.50)), micros(percentile(0.90)), micros(percentile(0.99)),
+        micros(percentile(0.999)), micros(latencies[ops - 1]));
+}
+
+fn describe_mix(mix: &[(Op, u32)]) -> String {
+    mix.iter()
+        .map(|(op, weight)| format!("{:?}={}", op, weight).to_lowercase())
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+// PARSE --FLAG VALUE PAIRS - DEFAULTS FOR ANYTHING MISSING
+fn parse_args() -> Result<Workload, String> {
+    let mut workload = Workload {
+        target: Target::InProcess,
+        ops: 100_000,
+        keys: 10_000,
+        value_size: 64,
+        concurrency: 4,
+        mix: vec![(Op::Set, 50), (Op::Get, 50)],
+        aof: None,
+        backend: PartitionBackend::default(),
+    };
+
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    for pair in args.chunks(2) {
+        let [flag, value] = pair else {
+            return Err(format!("missing value for {}", pair[0]));
+        };
+        let number = || value.parse::<usize>()
+            .ok()
+            .filter(|&n| n > 0)
+            .ok_or_else(|| format!("{} expects a positive integer", flag));
+
+        match flag.as_str() {
+            "--target" if value == "inproc" => workload.target = Target::InProcess,
+            "--target" => {
+                let (host, port) = value.rsplit_once(':')
+                    .and_then(|(host, port)| Some((host.to_string(), port.parse::<u16>().ok()?)))
+                    .ok_or_else(|| format!("bad target '{}' (want inproc or HOST:PORT)", value))?;
+                workload.target = Target::Tcp(host, port);
+            }
+            "--ops" => workload.ops = number()?,
+            "--keys" => workload.keys = number()?,
+            "--value-size" => workload.value_size = number()?,
+            "--concurrency" => workload.concurrency = number()?,
+            "--mix" => workload.mix = parse_mix(value)?,
+            "--aof" => workload.aof = Some(value.clone()),
+            "--backend" => workload.backend = PartitionBackend::from_name(value)
+                .ok_or_else(|| format!("unknown backend '{}' (want rwlock or sharded)", value))?,
+            _ => return Err(format!("unknown flag {}", flag)),
+        }
+    }
+
+    Ok(workload)
+}
+
+// "set=50,get=45,incr=5" -> weighted operations
+fn parse_mix(spec: &str) -> Result<Vec<(Op, u32)>, String> {
+    let mut mix = Vec::new();
+    for part in spec.split(',').filter(|part| !part.is_empty()) {
+        let (name, weight) = part.split_once('=')
+            .ok_or_else(|| format!("bad mix entry '{}'", part))?;
+        let op = match name.to_ascii_lowercase().as_str() {
+            "set" => Op::Set,
+            "get" => Op::Get,
+            "incr" => Op::Incr,
+            _ => return Err(format!("unknown operation '{}'", name)),
+        };
+        let weight = weight.parse::<u32>().map_err(|_| format!("bad weight '{}'", weight))?;
+        mix.push((op, weight));
+    }
+
+    if mix.iter().map(|(_, weight)| weight).sum::<u32>() == 0 {
+        return Err("mix needs at least one non-zero weight".to_string());
+    }
+    Ok(mix)
+}
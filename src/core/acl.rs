@@ -0,0 +1,262 @@
+// Access control - users, passwords and the commands each may run (AUTH, ACL)
+
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::Arc;
+
+/// User every connection starts as
+pub const DEFAULT_USER: &str = "default";
+
+/// Commands in @dangerous besides the admin ones
+const DANGEROUS_COMMANDS: &[&str] = &["flushall", "flushdb", "keys", "client"];
+
+/// Command category a rule can name (`+@read`, `-@dangerous`, ...)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Category {
+    // Every command
+    All,
+
+    // Commands flagged readonly
+    Read,
+
+    // Commands flagged write
+    Write,
+
+    // Commands flagged admin
+    Admin,
+
+    // Admin commands plus ones that can take the server down or wipe data
+    Dangerous,
+}
+
+/// One allow (+) or deny (-) rule; later rules win over earlier ones
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Rule {
+    Category(bool, Category),
+    Command(bool, String),
+}
+
+/// User - Credentials and the commands they may run
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct User {
+    pub name: String,
+
+    // Disabled users can't authenticate
+    pub enabled: bool,
+
+    // Password (None = nopass, any password is accepted)
+    password: Option<String>,
+
+    // Permission rules in file order
+    rules: Vec<Rule>,
+}
+
+/// Acl - Users by name, loaded from an ACL file at startup
+/// File lines look like `user alice on >secret +@read +@write -flushall`; tokens are
+/// on/off, >password, nopass, +@category / -@category, +command / -command,
+/// allcommands and nocommands. Without a file (or without a `default` line) the
+/// `default` user can run everything without a password
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Acl {
+    users: BTreeMap<String, Arc<User>>,
+}
+
+impl Category {
+    /// Parse a category name (without the @)
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "all" => Some(Category::All),
+            "read" => Some(Category::Read),
+            "write" => Some(Category::Write),
+            "admin" => Some(Category::Admin),
+            "dangerous" => Some(Category::Dangerous),
+            _ => None,
+        }
+    }
+
+    /// Whether command `name` with `flags` belongs to this category
+    pub fn contains(self, name: &str, flags: &[&str]) -> bool {
+        match self {
+            Category::All => true,
+            Category::Read => flags.contains(&"readonly"),
+            Category::Write => flags.contains(&"write"),
+            Category::Admin => flags.contains(&"admin"),
+            Category::Dangerous => flags.contains(&"admin") || DANGEROUS_COMMANDS.contains(&name),
+        }
+    }
+}
+
+impl User {
+    /// User with no password and no permissions (enabled)
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            enabled: true,
+            password: None,
+            rules: Vec::new(),
+        }
+    }
+
+    /// Built-in `default` user: no password, every command
+    pub fn default_user() -> Self {
+        let mut user = Self::new(DEFAULT_USER);
+        user.rules.push(Rule::Category(true, Category::All));
+        user
+    }
+
+    /// Apply one ACL token (see `Acl`)
+    pub fn apply(&mut self, token: &str) -> Result<(), String> {
+        match token {
+            "on" => self.enabled = true,
+            "off" => self.enabled = false,
+            "nopass" => self.password = None,
+            "allcommands" => self.rules.push(Rule::Category(true, Category::All)),
+            "nocommands" => self.rules.push(Rule::Category(false, Category::All)),
+            _ => {
+                if let Some(password) = token.strip_prefix('>') {
+                    self.password = Some(password.to_string());
+                    return Ok(());
+                }
+                let (allow, name) = match token.split_at_checked(1) {
+                    Some(("+", name)) => (true, name),
+                    Some(("-", name)) => (false, name),
+                    _ => return Err(format!("Unknown ACL rule '{}'", token)),
+                };
+                let rule = match name.strip_prefix('@') {
+                    Some(category) => Rule::Category(allow, Category::parse(category)
+                        .ok_or_else(|| format!("Unknown ACL category '{}'", category))?),
+                    None if !name.is_empty() => Rule::Command(allow, name.to_ascii_lowercase()),
+                    None => return Err(format!("Unknown ACL rule '{}'", token)),
+                };
+                self.rules.push(rule);
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether `password` unlocks this user
+    pub fn check_password(&self, password: &[u8]) -> bool {
+        self.enabled && self.password.as_ref().is_none_or(|expected| constant_time_eq(expected.as_bytes(), password))
+    }
+
+    /// Whether the user has no password (connections start authenticated as them)
+    pub fn is_nopass(&self) -> bool {
+        self.password.is_none()
+    }
+
+    /// Whether the user may run command `name` (lowercase) with `flags`
+    pub fn allows(&self, name: &str, flags: &[&str]) -> bool {
+        self.rules.iter().fold(false, |allowed, rule| match rule {
+            Rule::Category(allow, category) if category.contains(name, flags) => *allow,
+            Rule::Command(allow, command) if command == name => *allow,
+            _ => allowed,
+        })
+    }
+}
+
+impl Acl {
+    /// Only the built-in `default` user
+    pub fn new() -> Self {
+        let mut users = BTreeMap::new();
+        users.insert(DEFAULT_USER.to_string(), Arc::new(User::default_user()));
+        Self { users }
+    }
+
+    /// Parse ACL file contents; `#` starts a comment line
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut acl = Self::new();
+        for (i, line) in text.lines().enumerate() {
+            let mut tokens = line.split_whitespace();
+            match tokens.next() {
+                None => continue,
+                Some(comment) if comment.starts_with('#') => continue,
+                Some("user") => {}
+                Some(other) => return Err(format!("line {}: expected 'user', got '{}'", i + 1, other)),
+            }
+            let name = tokens.next().ok_or_else(|| format!("line {}: missing user name", i + 1))?;
+
+            // A file entry for `default` replaces the built-in one rather than adding to it
+            let mut user = User::new(name);
+            for token in tokens {
+                user.apply(token).map_err(|e| format!("line {}: {}", i + 1, e))?;
+            }
+            acl.users.insert(name.to_string(), Arc::new(user));
+        }
+        Ok(acl)
+    }
+
+    /// Read and parse an ACL file
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path.as_ref())
+            .map_err(|e| format!("Cannot read ACL file {}: {}", path.as_ref().display(), e))?;
+        Self::parse(&text)
+    }
+
+    /// User a new connection is authenticated as (None = must AUTH first)
+    pub fn initial_user(&self) -> Option<Arc<User>> {
+        self.users.get(DEFAULT_USER).filter(|user| user.enabled && user.is_nopass()).cloned()
+    }
+
+    /// User `name` if `password` matches and the user is enabled
+    pub fn authenticate(&self, name: &str, password: &[u8]) -> Option<Arc<User>> {
+        self.users.get(name).filter(|user| user.check_password(password)).cloned()
+    }
+
+    /// Look up a user
+    pub fn user(&self, name: &str) -> Option<&Arc<User>> {
+        self.users.get(name)
+    }
+
+    /// User names in order
+    pub fn user_names(&self) -> Vec<&str> {
+        self.users.keys().map(String::as_str).collect()
+    }
+}
+
+impl Default for Acl {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Compare secrets without exiting on the first differing byte
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rules_and_auth() {
+        let acl = Acl::parse("
+            # read-only reporting user
+            user reports on >r3ad +@read
+            user ops on >0ps +@all -@dangerous +flushdb
+            user gone off >x +@all
+        ").unwrap();
+
+        // The built-in default user is still there and needs no password
+        let default = acl.initial_user().unwrap();
+        assert!(default.allows("flushall", &["write"]));
+
+        let reports = acl.authenticate("reports", b"r3ad").unwrap();
+        assert!(reports.allows("get", &["readonly", "fast"]));
+        assert!(!reports.allows("set", &["write"]));
+        assert!(acl.authenticate("reports", b"wrong").is_none());
+        assert!(acl.authenticate("gone", b"x").is_none());
+
+        // Later rules override earlier ones
+        let ops = acl.authenticate("ops", b"0ps").unwrap();
+        assert!(ops.allows("set", &["write"]));
+        assert!(!ops.allows("shutdown", &["admin"]) && !ops.allows("flushall", &["write"]));
+        assert!(ops.allows("flushdb", &["write"]));
+
+        // Requiring a password for default means connections start unauthenticated
+        let locked = Acl::parse("user default on >pw +@all").unwrap();
+        assert!(locked.initial_user().is_none());
+        assert!(Acl::parse("user x +@bogus").is_err());
+        assert!(Acl::parse("users x").is_err());
+    }
+}
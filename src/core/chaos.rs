@@ -22,6 +22,9 @@ pub enum ChaosType {
     
     /// Clock skew (time jumps)
     ClockSkew,
+    
+    /// Primary failure (replicas cut off for the duration)
+    PrimaryFailure,
 }
 
 /// ChaosEngine - deliberate fault injector for resilience testing
@@ -86,6 +89,10 @@ impl ChaosEngine {
                     // Simulate sudden time jumps
                     Self::simulate_clock_skew();
                 },
+                ChaosType::PrimaryFailure => {
+                    // Cut replicas off so they see the primary disappear
+                    Self::fail_primary(&state_ref);
+                },
             }
             
             // Wait until duration completes
@@ -93,6 +100,9 @@ impl ChaosEngine {
                 thread::sleep(Duration::from_millis(100));
             }
             
+            // A failed primary comes back once the test ends
+            state_ref.replication().recover();
+            
             println!("🟢 CHAOS TEST COMPLETED - SYSTEM SURVIVED");
             
             // Mark test as complete
@@ -107,6 +117,12 @@ impl ChaosEngine {
         self.active.load(Ordering::Relaxed)
     }
     
+    /// Simulate primary failure - stop replication and close replica connections
+    /// Returns the number of replicas cut off; a no-op without replication
+    pub fn simulate_primary_failure(&self) -> usize {
+        Self::fail_primary(&self.state)
+    }
+    
     // === CHAOS SIMULATION METHODS ===
     
     /// Simulate process kill signals - SIGTERM, SIGKILL etc.
@@ -121,6 +137,17 @@ impl ChaosEngine {
         // TODO: When safe_process_restart is implemented, use it
     }
     
+    /// Fail the primary's replication side - replicas are dropped and refused until recovery
+    fn fail_primary(state: &GlobalState) -> usize {
+        let replication = state.replication();
+        if replication.is_empty() {
+            return 0;
+        }
+        
+        println!("🪦 SIMULATING PRIMARY FAILURE");
+        replication.fail()
+    }
+    
     /// Simulate memory pressure - allocation failures
     fn simulate_memory_pressure() {
         println!("🧠 SIMULATING MEMORY PRESSURE");
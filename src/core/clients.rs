@@ -0,0 +1,95 @@
+// Connected clients - the registry behind CLIENT LIST
+
+use std::collections::BTreeMap;
+use std::net::SocketAddr;
+use std::sync::RwLock;
+use std::time::Instant;
+
+/// One open connection as shown by CLIENT LIST
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClientInfo {
+    // Server-assigned connection id
+    pub id: u64,
+
+    // Peer address
+    pub addr: SocketAddr,
+
+    // When the connection was accepted
+    pub connected_at: Instant,
+}
+
+/// ClientRegistry - Open connections by id, for CLIENT LIST
+pub struct ClientRegistry {
+    // Connection id -> connection details (ordered, so listings follow accept order)
+    clients: RwLock<BTreeMap<u64, ClientInfo>>,
+}
+
+impl ClientRegistry {
+    /// Create empty registry
+    pub fn new() -> Self {
+        Self {
+            clients: RwLock::new(BTreeMap::new()),
+        }
+    }
+
+    /// Register an accepted connection
+    pub fn register(&self, id: u64, addr: SocketAddr) {
+        if let Ok(mut guard) = self.clients.write() {
+            guard.insert(id, ClientInfo { id, addr, connected_at: Instant::now() });
+        }
+    }
+
+    /// Remove a closed connection
+    pub fn remove(&self, id: u64) -> bool {
+        self.clients.write()
+            .map(|mut guard| guard.remove(&id).is_some())
+            .unwrap_or(false)
+    }
+
+    /// Details of connection `id`, if it is open
+    pub fn get(&self, id: u64) -> Option<ClientInfo> {
+        self.clients.read().ok().and_then(|guard| guard.get(&id).cloned())
+    }
+    
+    /// Open connections in id order
+    pub fn list(&self) -> Vec<ClientInfo> {
+        self.clients.read()
+            .map(|guard| guard.values().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// Number of open connections
+    pub fn len(&self) -> usize {
+        self.clients.read().map(|guard| guard.len()).unwrap_or(0)
+    }
+
+    /// Whether no connections are open
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl Default for ClientRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_register_and_remove() {
+        let registry = ClientRegistry::new();
+        let addr: SocketAddr = "127.0.0.1:4000".parse().unwrap();
+        registry.register(2, addr);
+        registry.register(1, addr);
+
+        let ids: Vec<u64> = registry.list().iter().map(|client| client.id).collect();
+        assert_eq!(ids, vec![1, 2]);
+        assert!(registry.remove(1));
+        assert!(!registry.remove(1));
+        assert_eq!(registry.len(), 1);
+    }
+}
@@ -1,3 +1,12 @@
 pub mod state;
+pub mod acl;
 pub mod chaos;
+pub mod replication;
+pub mod pubsub;
+pub mod notify;
+pub mod watchdog;
+pub mod clients;
+pub mod slowlog;
+pub mod sequence;
+pub mod ndjson;
 
@@ -0,0 +1,182 @@
+// Keyspace notifications - __keyspace@<db>__ / __keyevent@<db>__ messages for data changes
+
+use std::hash::{DefaultHasher, Hasher};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+
+use crate::core::pubsub::PubSub;
+
+/// Keyspace event classes (Redis `notify-keyspace-events` flags)
+pub mod class {
+    /// K - publish to __keyspace@<db>__:<key>
+    pub const KEYSPACE: u32 = 1 << 0;
+    /// E - publish to __keyevent@<db>__:<event>
+    pub const KEYEVENT: u32 = 1 << 1;
+    /// g - generic commands (DEL, EXPIRE, ...)
+    pub const GENERIC: u32 = 1 << 2;
+    /// $ - string commands
+    pub const STRING: u32 = 1 << 3;
+    /// l - list commands
+    pub const LIST: u32 = 1 << 4;
+    /// s - set commands
+    pub const SET: u32 = 1 << 5;
+    /// h - hash commands
+    pub const HASH: u32 = 1 << 6;
+    /// z - sorted set commands
+    pub const ZSET: u32 = 1 << 7;
+    /// x - expired events
+    pub const EXPIRED: u32 = 1 << 8;
+    /// e - evicted events
+    pub const EVICTED: u32 = 1 << 9;
+    /// A - alias for g$lshzxe
+    pub const ALL: u32 = GENERIC | STRING | LIST | SET | HASH | ZSET | EXPIRED | EVICTED;
+}
+
+/// Parse a `notify-keyspace-events` flag string into a class bitmask
+pub fn parse_flags(flags: &str) -> Result<u32, String> {
+    let mut mask = 0;
+
+    for c in flags.chars() {
+        mask |= match c {
+            'K' => class::KEYSPACE,
+            'E' => class::KEYEVENT,
+            'g' => class::GENERIC,
+            '$' => class::STRING,
+            'l' => class::LIST,
+            's' => class::SET,
+            'h' => class::HASH,
+            'z' => class::ZSET,
+            'x' => class::EXPIRED,
+            'e' => class::EVICTED,
+            'A' => class::ALL,
+            other => return Err(format!("Invalid keyspace event class: {}", other)),
+        };
+    }
+
+    Ok(mask)
+}
+
+/// Slots of per-table change counters - tables sharing a slot just invalidate each other
+const TABLE_CHANGE_SLOTS: usize = 256;
+
+/// KeyspaceNotifier - Publishes keyspace/keyevent messages for data changes
+/// Every change is also counted against its key's table (the part before the first
+/// `:`), so cached query results can tell when a table changed by any route
+pub struct KeyspaceNotifier {
+    // Hub messages are published through
+    pubsub: Arc<PubSub>,
+
+    // Enabled event classes (0 = disabled)
+    flags: AtomicU32,
+
+    // Changes per table slot, counted whether or not events are published
+    table_changes: Box<[AtomicU64]>,
+}
+
+impl KeyspaceNotifier {
+    /// Create notifier publishing through `pubsub`, disabled by default
+    pub fn new(pubsub: Arc<PubSub>) -> Self {
+        Self {
+            pubsub,
+            flags: AtomicU32::new(0),
+            table_changes: (0..TABLE_CHANGE_SLOTS).map(|_| AtomicU64::new(0)).collect(),
+        }
+    }
+
+    /// Replace enabled event classes from a flag string
+    pub fn set_flags(&self, flags: &str) -> Result<(), String> {
+        let mask = parse_flags(flags)?;
+        self.flags.store(mask, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// Currently enabled event class mask
+    pub fn flags(&self) -> u32 {
+        self.flags.load(Ordering::Relaxed)
+    }
+
+    /// Changes counted so far against `table` - any later change to it moves this on
+    pub fn table_changes(&self, table: &str) -> u64 {
+        self.table_changes[table_slot(table.as_bytes())].load(Ordering::Acquire)
+    }
+
+    /// Count a change against every table (FLUSHALL, which fires no per-key events)
+    pub fn record_flush(&self) {
+        for changes in self.table_changes.iter() {
+            changes.fetch_add(1, Ordering::AcqRel);
+        }
+    }
+
+    /// Publish `event` on `key` if its class is enabled
+    pub fn notify(&self, event_class: u32, event: &str, key: &[u8]) {
+        let table = key.split(|&b| b == b':').next().unwrap_or(key);
+        self.table_changes[table_slot(table)].fetch_add(1, Ordering::AcqRel);
+
+        let flags = self.flags.load(Ordering::Relaxed);
+
+        // Cheap exit for the common disabled case
+        if flags & event_class == 0 || !self.pubsub.has_subscribers() {
+            return;
+        }
+
+        let db = 0;
+
+        if flags & class::KEYSPACE != 0 {
+            let mut channel = format!("__keyspace@{}__:", db).into_bytes();
+            channel.extend_from_slice(key);
+            self.pubsub.publish(&channel, event.as_bytes());
+        }
+
+        if flags & class::KEYEVENT != 0 {
+            let channel = format!("__keyevent@{}__:{}", db, event);
+            self.pubsub.publish(channel.as_bytes(), key);
+        }
+    }
+}
+
+/// Change counter slot of a table name
+fn table_slot(table: &[u8]) -> usize {
+    let mut hasher = DefaultHasher::new();
+    hasher.write(table);
+    hasher.finish() as usize % TABLE_CHANGE_SLOTS
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_notify_respects_flags() {
+        let hub = Arc::new(PubSub::new());
+        let notifier = KeyspaceNotifier::new(hub.clone());
+        let mut sub = hub.subscription();
+        sub.psubscribe(b"__key*__:*");
+
+        // Disabled by default
+        notifier.notify(class::STRING, "set", b"k");
+        assert!(sub.receiver.try_recv().is_err());
+
+        // Keyevent only, expired class only
+        notifier.set_flags("Ex").unwrap();
+        notifier.notify(class::STRING, "set", b"k");
+        assert!(sub.receiver.try_recv().is_err());
+        notifier.notify(class::EXPIRED, "expired", b"k");
+        let msg = sub.receiver.try_recv().unwrap();
+        assert_eq!(msg.channel, b"__keyevent@0__:expired".to_vec());
+        assert_eq!(msg.payload, b"k".to_vec());
+
+        assert!(notifier.set_flags("KQ").is_err());
+    }
+
+    #[test]
+    fn test_table_changes() {
+        let notifier = KeyspaceNotifier::new(Arc::new(PubSub::new()));
+        let before = notifier.table_changes("users");
+
+        // Counted even with events disabled, against the table the key belongs to
+        notifier.notify(class::STRING, "set", b"users:1");
+        assert_eq!(notifier.table_changes("users"), before + 1);
+        notifier.record_flush();
+        assert_eq!(notifier.table_changes("users"), before + 2);
+    }
+}
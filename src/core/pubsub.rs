@@ -0,0 +1,253 @@
+// Pub/sub hub - channel and pattern subscriptions and message delivery
+
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use std::sync::atomic::{AtomicU64, Ordering};
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+
+use crate::util::glob::glob_match;
+
+/// Message delivered to a subscriber
+#[derive(Debug, Clone)]
+pub struct PubSubMessage {
+    // Pattern that matched (None for direct channel subscriptions)
+    pub pattern: Option<Vec<u8>>,
+
+    // Channel the message was published to
+    pub channel: Vec<u8>,
+
+    // Message payload
+    pub payload: Vec<u8>,
+}
+
+/// Subscriber id -> delivery channel
+type Subscribers = HashMap<u64, UnboundedSender<PubSubMessage>>;
+
+/// PubSub - Channel and pattern subscription hub
+pub struct PubSub {
+    // Subscriber id generator
+    next_id: AtomicU64,
+
+    // Channel name -> subscribers
+    channels: RwLock<HashMap<Vec<u8>, Subscribers>>,
+
+    // Glob pattern -> subscribers
+    patterns: RwLock<HashMap<Vec<u8>, Subscribers>>,
+}
+
+/// Per-connection subscription handle
+/// Unregisters from the hub when dropped
+pub struct Subscription {
+    // Hub this subscription belongs to
+    hub: Arc<PubSub>,
+
+    // Subscriber id
+    id: u64,
+
+    // Sending half handed to the hub on subscribe
+    sender: UnboundedSender<PubSubMessage>,
+
+    // Incoming messages
+    pub receiver: UnboundedReceiver<PubSubMessage>,
+
+    // Subscribed channels
+    channels: Vec<Vec<u8>>,
+
+    // Subscribed patterns
+    patterns: Vec<Vec<u8>>,
+}
+
+impl PubSub {
+    /// Create empty hub
+    pub fn new() -> Self {
+        Self {
+            next_id: AtomicU64::new(1),
+            channels: RwLock::new(HashMap::new()),
+            patterns: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Create a subscription handle for a connection
+    pub fn subscription(self: &Arc<Self>) -> Subscription {
+        let (sender, receiver) = mpsc::unbounded_channel();
+
+        Subscription {
+            hub: self.clone(),
+            id: self.next_id.fetch_add(1, Ordering::Relaxed),
+            sender,
+            receiver,
+            channels: Vec::new(),
+            patterns: Vec::new(),
+        }
+    }
+
+    /// Publish message, returning the number of receivers
+    pub fn publish(&self, channel: &[u8], payload: &[u8]) -> usize {
+        let mut delivered = 0;
+
+        if let Ok(guard) = self.channels.read()
+            && let Some(subscribers) = guard.get(channel)
+        {
+            for sender in subscribers.values() {
+                let message = PubSubMessage {
+                    pattern: None,
+                    channel: channel.to_vec(),
+                    payload: payload.to_vec(),
+                };
+                if sender.send(message).is_ok() {
+                    delivered += 1;
+                }
+            }
+        }
+
+        if let Ok(guard) = self.patterns.read() {
+            for (pattern, subscribers) in guard.iter() {
+                if !glob_match(pattern, channel) {
+                    continue;
+                }
+
+                for sender in subscribers.values() {
+                    let message = PubSubMessage {
+                        pattern: Some(pattern.clone()),
+                        channel: channel.to_vec(),
+                        payload: payload.to_vec(),
+                    };
+                    if sender.send(message).is_ok() {
+                        delivered += 1;
+                    }
+                }
+            }
+        }
+
+        delivered
+    }
+
+    /// Whether anyone is subscribed to anything (cheap pre-check for publishers)
+    pub fn has_subscribers(&self) -> bool {
+        let channels = self.channels.read().map(|g| !g.is_empty()).unwrap_or(false);
+        let patterns = self.patterns.read().map(|g| !g.is_empty()).unwrap_or(false);
+        channels || patterns
+    }
+
+    // === PRIVATE HELPERS ===
+
+    /// Add subscriber to a channel or pattern table
+    fn add(table: &RwLock<HashMap<Vec<u8>, Subscribers>>, name: &[u8], id: u64, sender: &UnboundedSender<PubSubMessage>) {
+        if let Ok(mut guard) = table.write() {
+            guard.entry(name.to_vec())
+                .or_default()
+                .insert(id, sender.clone());
+        }
+    }
+
+    /// Remove subscriber from a channel or pattern table
+    fn remove(table: &RwLock<HashMap<Vec<u8>, Subscribers>>, name: &[u8], id: u64) {
+        if let Ok(mut guard) = table.write()
+            && let Some(subscribers) = guard.get_mut(name)
+        {
+            subscribers.remove(&id);
+            if subscribers.is_empty() {
+                guard.remove(name);
+            }
+        }
+    }
+}
+
+impl Default for PubSub {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Subscription {
+    /// Subscribe to channel, returning the subscription count
+    pub fn subscribe(&mut self, channel: &[u8]) -> usize {
+        if !self.channels.iter().any(|c| c == channel) {
+            PubSub::add(&self.hub.channels, channel, self.id, &self.sender);
+            self.channels.push(channel.to_vec());
+        }
+        self.count()
+    }
+
+    /// Unsubscribe from channel, returning the subscription count
+    pub fn unsubscribe(&mut self, channel: &[u8]) -> usize {
+        if let Some(pos) = self.channels.iter().position(|c| c == channel) {
+            PubSub::remove(&self.hub.channels, channel, self.id);
+            self.channels.remove(pos);
+        }
+        self.count()
+    }
+
+    /// Subscribe to pattern, returning the subscription count
+    pub fn psubscribe(&mut self, pattern: &[u8]) -> usize {
+        if !self.patterns.iter().any(|p| p == pattern) {
+            PubSub::add(&self.hub.patterns, pattern, self.id, &self.sender);
+            self.patterns.push(pattern.to_vec());
+        }
+        self.count()
+    }
+
+    /// Unsubscribe from pattern, returning the subscription count
+    pub fn punsubscribe(&mut self, pattern: &[u8]) -> usize {
+        if let Some(pos) = self.patterns.iter().position(|p| p == pattern) {
+            PubSub::remove(&self.hub.patterns, pattern, self.id);
+            self.patterns.remove(pos);
+        }
+        self.count()
+    }
+
+    /// Currently subscribed channels
+    pub fn channels(&self) -> &[Vec<u8>] {
+        &self.channels
+    }
+
+    /// Currently subscribed patterns
+    pub fn patterns(&self) -> &[Vec<u8>] {
+        &self.patterns
+    }
+
+    /// Total channel + pattern subscriptions
+    pub fn count(&self) -> usize {
+        self.channels.len() + self.patterns.len()
+    }
+}
+
+impl Drop for Subscription {
+    fn drop(&mut self) {
+        // Unregister everything when the connection goes away
+        for channel in &self.channels {
+            PubSub::remove(&self.hub.channels, channel, self.id);
+        }
+        for pattern in &self.patterns {
+            PubSub::remove(&self.hub.patterns, pattern, self.id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_publish_subscribe() {
+        let hub = Arc::new(PubSub::new());
+        let mut direct = hub.subscription();
+        let mut pattern = hub.subscription();
+
+        assert_eq!(direct.subscribe(b"news"), 1);
+        assert_eq!(pattern.psubscribe(b"n*"), 1);
+
+        // Both the channel and the pattern subscriber receive it
+        assert_eq!(hub.publish(b"news", b"hello"), 2);
+        let msg = direct.receiver.try_recv().unwrap();
+        assert_eq!(msg.payload, b"hello".to_vec());
+        let msg = pattern.receiver.try_recv().unwrap();
+        assert_eq!(msg.pattern, Some(b"n*".to_vec()));
+
+        // Dropped subscriptions are unregistered
+        drop(direct);
+        assert_eq!(hub.publish(b"news", b"again"), 1);
+        assert_eq!(pattern.punsubscribe(b"n*"), 0);
+        assert!(!hub.has_subscribers());
+    }
+}
@@ -0,0 +1,227 @@
+// Replication roles and replica acknowledgments - what WAIT and INFO replication read
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, PoisonError, RwLock};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+/// Role - Whether this node takes client writes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Role {
+    // Accepts writes and feeds replicas
+    #[default]
+    Primary,
+    
+    // Applies the primary's stream; client writes get -READONLY
+    Replica,
+}
+
+/// PrimaryLink - A replica's connection to its primary, for INFO replication
+/// Whatever applies the replication stream reports in here
+pub struct PrimaryLink {
+    // Primary address as host:port (None on a primary)
+    addr: Option<String>,
+    
+    // Primary's AOF, read as the replication stream (None on a primary)
+    aof: Option<PathBuf>,
+    
+    // Whether the stream is currently connected
+    up: AtomicBool,
+    
+    // Last time anything arrived from the primary
+    last_io: Mutex<Option<Instant>>,
+}
+
+/// ReplicaRegistry - Tracks acknowledged AOF offsets per connected replica
+/// Used by WAIT to decide how many replicas have caught up with a write
+pub struct ReplicaRegistry {
+    // Replica id -> highest acknowledged AOF offset
+    replicas: RwLock<HashMap<u64, u64>>,
+    
+    // Set while the primary is failed - replicas can neither attach nor ack
+    failed: AtomicBool,
+}
+
+impl ReplicaRegistry {
+    /// Create empty registry
+    pub fn new() -> Self {
+        Self {
+            replicas: RwLock::new(HashMap::new()),
+            failed: AtomicBool::new(false),
+        }
+    }
+
+    /// Register a newly connected replica
+    pub fn register(&self, replica_id: u64) {
+        if self.is_failed() {
+            return;
+        }
+        if let Ok(mut guard) = self.replicas.write() {
+            guard.insert(replica_id, 0);
+        }
+    }
+
+    /// Remove a disconnected replica
+    pub fn remove(&self, replica_id: u64) -> bool {
+        self.replicas.write()
+            .map(|mut guard| guard.remove(&replica_id).is_some())
+            .unwrap_or(false)
+    }
+
+    /// Record a replica acknowledgment (offsets only move forward)
+    pub fn ack(&self, replica_id: u64, offset: u64) {
+        if self.is_failed() {
+            return;
+        }
+        if let Ok(mut guard) = self.replicas.write()
+            && let Some(acked) = guard.get_mut(&replica_id)
+        {
+            *acked = (*acked).max(offset);
+        }
+    }
+
+    /// Count replicas that acknowledged at least `offset`
+    pub fn count_acked(&self, offset: u64) -> usize {
+        self.replicas.read()
+            .map(|guard| guard.values().filter(|&&acked| acked >= offset).count())
+            .unwrap_or(0)
+    }
+
+    /// Number of connected replicas
+    pub fn len(&self) -> usize {
+        self.replicas.read().map(|guard| guard.len()).unwrap_or(0)
+    }
+
+    /// Whether no replicas are connected
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    
+    /// Act as a failed primary: drop every replica and refuse new ones and acks
+    /// Returns the number of replicas disconnected
+    pub fn fail(&self) -> usize {
+        self.failed.store(true, Ordering::Release);
+        self.replicas.write()
+            .map(|mut guard| guard.drain().count())
+            .unwrap_or(0)
+    }
+    
+    /// Let replicas attach again after `fail`
+    pub fn recover(&self) {
+        self.failed.store(false, Ordering::Release);
+    }
+    
+    /// Whether the primary is currently failed
+    pub fn is_failed(&self) -> bool {
+        self.failed.load(Ordering::Acquire)
+    }
+}
+
+impl Default for ReplicaRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Role {
+    /// Name as shown by INFO replication (Redis calls them master/slave)
+    pub fn info_name(self) -> &'static str {
+        match self {
+            Role::Primary => "master",
+            Role::Replica => "slave",
+        }
+    }
+}
+
+impl PrimaryLink {
+    /// Link to the primary at `addr` whose AOF is `aof` (None = this node is not a
+    /// replica), initially down
+    pub fn new(addr: Option<String>, aof: Option<PathBuf>) -> Self {
+        Self {
+            addr,
+            aof,
+            up: AtomicBool::new(false),
+            last_io: Mutex::new(None),
+        }
+    }
+    
+    /// Primary address as host:port
+    pub fn addr(&self) -> Option<&str> {
+        self.addr.as_deref()
+    }
+    
+    /// Primary's AOF the replication stream is read from
+    pub fn aof(&self) -> Option<&Path> {
+        self.aof.as_deref()
+    }
+    
+    /// Record that the stream connected (true) or dropped (false)
+    pub fn set_up(&self, up: bool) {
+        self.up.store(up, Ordering::Release);
+        if up {
+            self.touch();
+        }
+    }
+    
+    /// Record traffic from the primary
+    pub fn touch(&self) {
+        *self.last_io.lock().unwrap_or_else(PoisonError::into_inner) = Some(Instant::now());
+    }
+    
+    /// Whether the stream is connected
+    pub fn is_up(&self) -> bool {
+        self.up.load(Ordering::Acquire)
+    }
+    
+    /// Time since the primary was last heard from (None = never)
+    pub fn last_io(&self) -> Option<Duration> {
+        self.last_io.lock().unwrap_or_else(PoisonError::into_inner).map(|at| at.elapsed())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_count_acked() {
+        let registry = ReplicaRegistry::new();
+        registry.register(1);
+        registry.register(2);
+
+        registry.ack(1, 100);
+        registry.ack(2, 50);
+        assert_eq!(registry.count_acked(50), 2);
+        assert_eq!(registry.count_acked(100), 1);
+
+        // Stale acks never move an offset backwards
+        registry.ack(1, 10);
+        assert_eq!(registry.count_acked(100), 1);
+
+        assert!(registry.remove(1));
+        assert_eq!(registry.count_acked(50), 1);
+        assert_eq!(registry.len(), 1);
+    }
+    
+    #[test]
+    fn test_fail_disconnects_replicas() {
+        let registry = ReplicaRegistry::new();
+        registry.register(1);
+        registry.register(2);
+        registry.ack(1, 100);
+        
+        assert_eq!(registry.fail(), 2);
+        assert!(registry.is_empty());
+        
+        // A failed primary accepts neither reconnects nor acks
+        registry.register(3);
+        registry.ack(3, 100);
+        assert!(registry.is_empty());
+        assert_eq!(registry.count_acked(0), 0);
+        
+        registry.recover();
+        registry.register(3);
+        assert_eq!(registry.len(), 1);
+    }
+}
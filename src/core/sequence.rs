@@ -0,0 +1,103 @@
+// Id sequences - SEQ NEXT hands out ids from blocks reserved on a durable counter
+
+use std::collections::HashMap;
+use std::ops::Range;
+use std::sync::{Mutex, PoisonError};
+
+/// Ids reserved per trip to the durable counter (and the most one SEQ NEXT may take)
+pub const SEQ_BLOCK_SIZE: u64 = 1000;
+
+/// SequenceAllocator - Hands out ids from blocks reserved on a durable counter
+/// Reserving a block raises the counter's high-water mark once; ids inside the block are
+/// then handed out from memory. A restart discards unused ids rather than reissuing them.
+/// The mark must only ever grow - GlobalState keeps it out of reach of user commands
+pub struct SequenceAllocator {
+    // Sequence name -> ids reserved but not yet handed out (`end` stays past the last id issued)
+    blocks: Mutex<HashMap<Vec<u8>, Range<u64>>>,
+}
+
+impl SequenceAllocator {
+    /// Create allocator with no reserved blocks
+    pub fn new() -> Self {
+        Self {
+            blocks: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Take `count` consecutive ids from sequence `name`, returning the first
+    /// When the block runs short, `reserve(n)` must durably raise the counter by at least
+    /// `n` and return the new high-water mark; the leftover of the old block is dropped.
+    /// A block overlapping ids already issued is refused rather than handed out
+    pub fn next(
+        &self,
+        name: &[u8],
+        count: u64,
+        reserve: impl FnOnce(u64) -> Result<u64, String>,
+    ) -> Result<u64, String> {
+        if count == 0 || count > SEQ_BLOCK_SIZE {
+            return Err(format!("count must be between 1 and {}", SEQ_BLOCK_SIZE));
+        }
+
+        // Held across the reservation so two callers never reserve for the same gap
+        let mut blocks = self.blocks.lock().unwrap_or_else(PoisonError::into_inner);
+        let block = blocks.entry(name.to_vec()).or_insert(0..0);
+        if block.end - block.start < count {
+            let high_water = reserve(SEQ_BLOCK_SIZE)?;
+            let start = high_water + 1 - SEQ_BLOCK_SIZE;
+            if start < block.end {
+                return Err(format!("sequence counter is below ids already issued (up to {})", block.end - 1));
+            }
+            *block = start..high_water + 1;
+        }
+
+        let first = block.start;
+        block.start += count;
+        Ok(first)
+    }
+
+}
+
+impl Default for SequenceAllocator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_block_reservation() {
+        let allocator = SequenceAllocator::new();
+        let mut counter = 0;
+        let mut reservations = 0;
+        let mut next = |count| allocator.next(b"orders", count, |n| {
+            reservations += 1;
+            counter += n;
+            Ok(counter)
+        });
+
+        // One reservation serves a whole block
+        assert_eq!(next(1), Ok(1));
+        assert_eq!(next(998), Ok(2));
+        assert_eq!(next(1), Ok(1000));
+
+        // A request that doesn't fit skips the remainder and reserves again
+        assert_eq!(next(1), Ok(1001));
+        assert_eq!(next(SEQ_BLOCK_SIZE), Ok(2001));
+        assert!(next(0).is_err() && next(SEQ_BLOCK_SIZE + 1).is_err());
+        assert_eq!((reservations, counter), (3, 3000));
+
+        // A failed reservation hands out nothing
+        let failing = SequenceAllocator::new();
+        assert!(failing.next(b"orders", 1, |_| Err("OOM".to_string())).is_err());
+        assert_eq!(failing.next(b"orders", 1, Ok), Ok(1));
+
+        // A reservation that doesn't get past the ids issued is refused rather than reissued
+        let lowered = SequenceAllocator::new();
+        assert_eq!(lowered.next(b"ids", SEQ_BLOCK_SIZE, Ok), Ok(1));
+        assert!(lowered.next(b"ids", 1, Ok).is_err());
+        assert_eq!(lowered.next(b"ids", 1, |n| Ok(2 * n)), Ok(SEQ_BLOCK_SIZE + 1));
+    }
+}
@@ -0,0 +1,157 @@
+// Slow log - recent commands that ran past a threshold, with their arguments (SLOWLOG)
+
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, PoisonError};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Default slowlog-log-slower-than, as in Redis
+pub const DEFAULT_SLOWLOG_THRESHOLD: Duration = Duration::from_millis(10);
+
+/// Default slowlog-max-len, as in Redis
+pub const DEFAULT_SLOWLOG_MAX_LEN: usize = 128;
+
+/// Most arguments kept per entry - the last slot says how many were dropped
+const MAX_ARGS: usize = 32;
+
+/// Most bytes kept per argument
+const MAX_ARG_LEN: usize = 128;
+
+/// SlowLog - Bounded log of commands that ran past a threshold (SLOWLOG)
+/// Unlike the watchdog this records completed commands with their arguments
+pub struct SlowLog {
+    // Commands taking at least this long are recorded (None = off)
+    threshold: Option<Duration>,
+
+    // Most entries kept - the oldest are dropped first
+    max_len: usize,
+
+    // Newest entry first
+    entries: Mutex<VecDeque<SlowLogEntry>>,
+
+    // Id for the next entry (ids survive SLOWLOG RESET)
+    next_id: AtomicU64,
+}
+
+/// One slow command
+#[derive(Debug, Clone, PartialEq)]
+pub struct SlowLogEntry {
+    pub id: u64,
+
+    // Unix time in seconds when the command finished
+    pub timestamp: u64,
+
+    pub duration: Duration,
+
+    // Command name and arguments, truncated to MAX_ARGS / MAX_ARG_LEN
+    pub args: Vec<Vec<u8>>,
+
+    // Peer address of the connection (empty when unknown)
+    pub client_addr: String,
+}
+
+impl SlowLog {
+    /// Create a slowlog keeping `max_len` entries slower than `threshold` (None = off)
+    pub fn new(threshold: Option<Duration>, max_len: usize) -> Self {
+        Self {
+            threshold,
+            max_len,
+            entries: Mutex::new(VecDeque::new()),
+            next_id: AtomicU64::new(0),
+        }
+    }
+
+    /// Whether a command that took `elapsed` belongs in the log
+    pub fn is_slow(&self, elapsed: Duration) -> bool {
+        self.max_len > 0 && self.threshold.is_some_and(|threshold| elapsed >= threshold)
+    }
+
+    /// Record a slow command (callers check `is_slow` first)
+    pub fn record(&self, duration: Duration, name: &str, args: &[Vec<u8>], client_addr: String) {
+        let mut kept = Vec::with_capacity(args.len().min(MAX_ARGS) + 1);
+        kept.push(name.as_bytes().to_vec());
+        for (i, arg) in args.iter().enumerate() {
+            // Like Redis, the last slot stands in for the arguments that don't fit
+            if i + 1 == MAX_ARGS && args.len() > MAX_ARGS {
+                kept.push(format!("... ({} more arguments)", args.len() - i).into_bytes());
+                break;
+            }
+            if arg.len() > MAX_ARG_LEN {
+                let mut truncated = arg[..MAX_ARG_LEN].to_vec();
+                truncated.extend_from_slice(format!("... ({} more bytes)", arg.len() - MAX_ARG_LEN).as_bytes());
+                kept.push(truncated);
+            } else {
+                kept.push(arg.clone());
+            }
+        }
+
+        let entry = SlowLogEntry {
+            id: self.next_id.fetch_add(1, Ordering::Relaxed),
+            timestamp: SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs(),
+            duration,
+            args: kept,
+            client_addr,
+        };
+        let mut entries = self.lock();
+        entries.push_front(entry);
+        entries.truncate(self.max_len);
+    }
+
+    /// Newest `count` entries, newest first (None = all)
+    pub fn get(&self, count: Option<usize>) -> Vec<SlowLogEntry> {
+        let entries = self.lock();
+        entries.iter().take(count.unwrap_or(entries.len())).cloned().collect()
+    }
+
+    /// Number of entries
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    /// Whether the log is empty
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Drop every entry (SLOWLOG RESET)
+    pub fn reset(&self) {
+        self.lock().clear();
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, VecDeque<SlowLogEntry>> {
+        self.entries.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ring_and_truncation() {
+        let slowlog = SlowLog::new(Some(Duration::from_millis(5)), 2);
+        assert!(!slowlog.is_slow(Duration::from_millis(4)));
+        assert!(slowlog.is_slow(Duration::from_millis(5)));
+        assert!(!SlowLog::new(None, 2).is_slow(Duration::from_secs(60)));
+
+        // Oldest entries fall off the end
+        for key in ["a", "b", "c"] {
+            slowlog.record(Duration::from_millis(6), "get", &[key.as_bytes().to_vec()], String::new());
+        }
+        let entries = slowlog.get(None);
+        assert_eq!(entries.iter().map(|entry| entry.id).collect::<Vec<_>>(), vec![2, 1]);
+        assert_eq!(entries[0].args, vec![b"get".to_vec(), b"c".to_vec()]);
+        assert_eq!(slowlog.get(Some(1)).len(), 1);
+
+        // Long arguments and long argument lists are cut down
+        let args: Vec<Vec<u8>> = (0..40).map(|_| vec![b'x'; 200]).collect();
+        slowlog.record(Duration::from_millis(6), "mset", &args, String::new());
+        let entry = &slowlog.get(Some(1))[0];
+        assert_eq!(entry.args.len(), MAX_ARGS + 1);
+        assert!(entry.args[1].ends_with(b"... (72 more bytes)"));
+        assert_eq!(entry.args[MAX_ARGS], b"... (9 more arguments)".to_vec());
+
+        slowlog.reset();
+        assert!(slowlog.is_empty());
+    }
+}
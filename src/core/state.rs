@@ -1,20 +1,162 @@
-use std::sync::Arc;
-use std::time::{Duration, Instant};
-use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, MutexGuard};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::hash::{DefaultHasher, Hasher};
+use std::net::SocketAddr;
+use std::ops::Bound;
+use std::path::{Path, PathBuf};
+use tokio::sync::watch;
 
-use crate::storage::memory::MemTable;
-use crate::persistence::aof::AppendOnlyFile;
+use crate::storage::encoding::EncodingConfig;
+use crate::storage::engine::{ScanEntry, StorageEngine};
+use crate::storage::memory::{EntryView, EvictionPolicy, KeyspaceSummary, MaxMemoryPolicy, MemTable, PartitionStat, SnapshotIter};
+use crate::storage::value::{Applied, Mutation, SetOp, ValueKind, ZAddFlags};
+use crate::storage::zset::OrderedFloat;
+use crate::persistence::aof::{AppendOnlyFile, ReplayStats, MAX_KEY_SIZE, MAX_VALUE_SIZE};
+use crate::core::replication::{PrimaryLink, ReplicaRegistry, Role};
+use crate::core::acl::Acl;
+use crate::core::clients::ClientRegistry;
+use crate::core::sequence::SequenceAllocator;
+use crate::core::pubsub::PubSub;
+use crate::core::notify::{self, KeyspaceNotifier};
+use crate::core::watchdog::Watchdog;
+use crate::core::slowlog::{SlowLog, DEFAULT_SLOWLOG_MAX_LEN, DEFAULT_SLOWLOG_THRESHOLD};
+use crate::persistence::snapshot::SnapshotManager;
+use crate::util::glob::glob_match;
+use crate::util::rate::RateCounter;
+
+/// Logical databases reported when none are configured (Redis' default)
+pub const DEFAULT_DATABASES: usize = 16;
+
+/// DEBUG subcommands some clients send on connect, answered +OK without effect
+pub const DEFAULT_DEBUG_NOOPS: [&str; 4] = ["QUICKLIST-PACKED-THRESHOLD", "STRINGMATCH-LEN", "JMAP", "SET-ACTIVE-EXPIRE"];
+
+/// Error for writes refused at the memory limit
+pub const OOM_ERROR: &str = "OOM command not allowed when used memory > maxmemory";
+
+/// How often the auto-rewrite monitor compares the AOF size against its base
+const AOF_REWRITE_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How often a replica polls the primary's AOF for new entries
+const REPLICA_POLL_INTERVAL: Duration = Duration::from_millis(100);
 
 /// GlobalState - Central database state manager
 /// Core abstraction maintaining atomic consistency across components
-pub struct GlobalState {
+/// Generic over the storage engine; strings, deletes and eviction work on any engine,
+/// collections and snapshots need MemTable
+pub struct GlobalState<E: StorageEngine = MemTable> {
     // Core storage engine - primary data substrate
-    mem_table: Arc<MemTable>,
+    mem_table: Arc<E>,
     
     // Persistence layer - durability mechanism
     // CRITICAL FIX: Change to interior mutability pattern with Arc<Mutex<>>
     aof: std::sync::Mutex<AppendOnlyFile>,
     
+    // AOF offset after the most recent write - replication progress marker
+    aof_offset: AtomicU64,
+    
+    // AOF offset up to which writes are fsynced (WAITAOF), shared with the AOF
+    aof_synced: Arc<AtomicU64>,
+    
+    // Connected replicas and their acknowledged offsets
+    replication: ReplicaRegistry,
+    
+    // Primary, or a replica that refuses client writes
+    role: Role,
+    
+    // A replica's connection to its primary
+    primary_link: PrimaryLink,
+    
+    // Pub/sub channel hub
+    pubsub: Arc<PubSub>,
+    
+    // Keyspace event publisher
+    notifier: Arc<KeyspaceNotifier>,
+    
+    // Snapshot manager for SAVE/BGSAVE (None = snapshots disabled)
+    snapshots: Option<Arc<SnapshotManager>>,
+    
+    // Background save currently running
+    bgsave_in_progress: AtomicBool,
+    
+    // Unix timestamp (seconds) of the last successful save
+    last_save: AtomicU64,
+    
+    // AOF rewrite currently running
+    aof_rewrite_in_progress: AtomicBool,
+    
+    // AOF size at startup or after the last rewrite - growth is measured from it
+    aof_base_size: AtomicU64,
+    
+    // Auto rewrite once the AOF has grown this many percent past its base (0 = off)
+    // and is at least aof_rewrite_min_size bytes
+    aof_rewrite_percentage: u64,
+    aof_rewrite_min_size: u64,
+    
+    // Flipped to true once a shutdown has been requested
+    shutdown: watch::Sender<bool>,
+    
+    // Outcome of the startup AOF replay
+    recovery: ReplayStats,
+    
+    // Startup AOF replay stopped on an error
+    replay_failed: bool,
+    
+    // Client listener is bound and accepting connections
+    listening: AtomicBool,
+    
+    // Most recent AOF append or fsync failed
+    aof_failed: AtomicBool,
+    
+    // The AOF's background flush thread hit an error, shared with the AOF
+    aof_flush_failed: Arc<AtomicBool>,
+    
+    // Largest accepted string key and value, checked before the memory write
+    max_key_size: usize,
+    max_value_size: usize,
+    
+    // Memory limit in bytes for growing writes (0 = no limit)
+    memory_limit: usize,
+    
+    // What a write does when the limit is reached
+    maxmemory_policy: MaxMemoryPolicy,
+    
+    // Whether DEBUG subcommands with effects (RELOAD) may run
+    debug_commands_enabled: bool,
+    
+    // Uppercase DEBUG subcommands answered +OK as no-ops
+    debug_noops: Vec<String>,
+    
+    // Whether new connections start with command/reply tracing on
+    trace_commands: bool,
+    
+    // Reports commands that run past a threshold (None = off)
+    watchdog: Option<Arc<Watchdog>>,
+    
+    // Completed commands that ran past a threshold (SLOWLOG)
+    slowlog: SlowLog,
+    
+    // Last connection id handed out
+    next_client_id: AtomicU64,
+    
+    // Open connections by id (CLIENT LIST)
+    clients: ClientRegistry,
+    
+    // Id blocks reserved for SEQ NEXT
+    sequences: SequenceAllocator,
+    
+    // Users and their command permissions (AUTH)
+    acl: Acl,
+    
+    // Number of logical databases (INFO keyspace reports db0..dbN-1)
+    databases: usize,
+    
+    // Keys the startup warmer pre-touches, hottest first (None = warmer off)
+    warm_keys: Option<usize>,
+    
+    // Thresholds OBJECT ENCODING reports against (CONFIG SET *-max-*)
+    encodings: EncodingConfig,
+    
     // System statistics - performance telemetry
     stats: Statistics,
 }
@@ -32,19 +174,118 @@ struct Statistics {
     // Performance metrics - latency tracking
     write_latency_ns: AtomicU64,
     read_latency_ns: AtomicU64,
+    
+    // Connection counters - client tracking
+    connections_received: AtomicU64,
+    connected_clients: AtomicU64,
+    rejected_connections: AtomicU64,
+    
+    // Command counters - dispatch throughput
+    commands_processed: AtomicU64,
+    command_rate: RateCounter,
 }
 
-impl GlobalState {
+/// Cursor to resume a collection scan from (0 = done) and the page of items read
+pub type ScanPage<T> = (usize, Vec<T>);
+
+/// Connection and command counters for INFO / stats
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ConnectionStats {
+    // Connections accepted since startup
+    pub total_connections: u64,
+    
+    // Currently open connections
+    pub connected_clients: u64,
+    
+    // Connections refused by the max-connections limit
+    pub rejected_connections: u64,
+    
+    // Commands dispatched since startup
+    pub total_commands: u64,
+    
+    // Commands per second over the recent sliding window
+    pub ops_per_sec: u64,
+}
+
+impl<E: StorageEngine> GlobalState<E> {
     /// Create new global state with provided storage components
-    pub fn new(mem_table: Arc<MemTable>, mut aof: AppendOnlyFile) -> Self {
+    pub fn new(mem_table: Arc<E>, aof: AppendOnlyFile) -> Self {
+        Self::with_replay_progress(mem_table, aof, |_, _, _| {})
+    }
+    
+    /// Create global state, reporting AOF replay progress as
+    /// `progress(replayed, bytes_done, bytes_total)`
+    pub fn with_replay_progress(
+        mem_table: Arc<E>,
+        aof: AppendOnlyFile,
+        progress: impl FnMut(usize, u64, u64),
+    ) -> Self {
+        Self::replayed_from(0, mem_table, aof, progress)
+    }
+    
+    /// Replay the AOF from logical offset `from` into `mem_table` and wrap both in state
+    fn replayed_from(
+        from: u64,
+        mem_table: Arc<E>,
+        mut aof: AppendOnlyFile,
+        progress: impl FnMut(usize, u64, u64),
+    ) -> Self {
         // Replay AOF entries into memtable before creating state
-        if let Err(e) = aof.replay_existing_entries(&mem_table) {
-            eprintln!("AOF replay error: {}", e);
-        }
+        let (recovery, replay_failed) = match aof.replay_from(from, &*mem_table, progress) {
+            Ok(recovery) => (recovery, false),
+            Err(e) => {
+                eprintln!("AOF replay error: {}", e);
+                (ReplayStats::default(), true)
+            }
+        };
+
+        // Expired keys reaped by GC fire keyspace events
+        let pubsub = Arc::new(PubSub::new());
+        let notifier = Arc::new(KeyspaceNotifier::new(pubsub.clone()));
+        let listener = notifier.clone();
+        mem_table.set_expired_listener(Box::new(move |key| {
+            listener.notify(notify::class::EXPIRED, "expired", key);
+        }));
 
         Self {
             mem_table,
+            pubsub,
+            notifier,
+            aof_offset: AtomicU64::new(aof.logical_len()),
+            aof_synced: aof.synced_offset(),
+            aof_flush_failed: aof.failure_flag(),
+            aof_base_size: AtomicU64::new(aof.size()),
             aof: std::sync::Mutex::new(aof),
+            replication: ReplicaRegistry::new(),
+            role: Role::Primary,
+            primary_link: PrimaryLink::new(None, None),
+            snapshots: None,
+            bgsave_in_progress: AtomicBool::new(false),
+            last_save: AtomicU64::new(Self::unix_time_secs()),
+            aof_rewrite_in_progress: AtomicBool::new(false),
+            aof_rewrite_percentage: 0,
+            aof_rewrite_min_size: 0,
+            shutdown: watch::channel(false).0,
+            recovery,
+            replay_failed,
+            listening: AtomicBool::new(false),
+            aof_failed: AtomicBool::new(false),
+            max_key_size: MAX_KEY_SIZE,
+            max_value_size: MAX_VALUE_SIZE,
+            memory_limit: 0,
+            maxmemory_policy: MaxMemoryPolicy::default(),
+            debug_commands_enabled: true,
+            debug_noops: DEFAULT_DEBUG_NOOPS.iter().map(|sub| sub.to_string()).collect(),
+            trace_commands: false,
+            watchdog: None,
+            slowlog: SlowLog::new(Some(DEFAULT_SLOWLOG_THRESHOLD), DEFAULT_SLOWLOG_MAX_LEN),
+            next_client_id: AtomicU64::new(0),
+            clients: ClientRegistry::new(),
+            sequences: SequenceAllocator::new(),
+            acl: Acl::new(),
+            databases: DEFAULT_DATABASES,
+            warm_keys: None,
+            encodings: EncodingConfig::new(),
             stats: Statistics {
                 start_time: Instant::now(),
                 reads: AtomicU64::new(0),
@@ -52,21 +293,63 @@ impl GlobalState {
                 deletes: AtomicU64::new(0),
                 write_latency_ns: AtomicU64::new(0),
                 read_latency_ns: AtomicU64::new(0),
+                connections_received: AtomicU64::new(0),
+                connected_clients: AtomicU64::new(0),
+                rejected_connections: AtomicU64::new(0),
+                commands_processed: AtomicU64::new(0),
+                command_rate: RateCounter::new(),
             },
         }
     }
     
+    /// Statistics from the startup AOF replay
+    pub fn recovery_stats(&self) -> ReplayStats {
+        self.recovery
+    }
+    
+    /// Whether the startup AOF replay stopped on an error (the keyspace may be partial)
+    pub fn recovery_failed(&self) -> bool {
+        self.replay_failed
+    }
+    
+    /// Storage engine holding the keyspace
+    pub fn engine(&self) -> &Arc<E> {
+        &self.mem_table
+    }
+    
+    /// Limit string key and value sizes; clamped to what the AOF can store
+    pub fn with_size_limits(mut self, max_key_size: usize, max_value_size: usize) -> Self {
+        self.max_key_size = max_key_size.min(MAX_KEY_SIZE);
+        self.max_value_size = max_value_size.min(MAX_VALUE_SIZE);
+        self
+    }
+    
+    /// Cap memory used by keys (0 = no limit) and choose what happens at the cap
+    pub fn with_memory_limit(mut self, limit: usize, policy: MaxMemoryPolicy) -> Self {
+        self.memory_limit = limit;
+        self.maxmemory_policy = policy;
+        self
+    }
+    
+    /// Estimated bytes used by keys and values
+    pub fn used_memory(&self) -> usize {
+        self.mem_table.used_memory()
+    }
+    
+    /// Configured memory limit (0 = none) and policy
+    pub fn memory_limit(&self) -> (usize, MaxMemoryPolicy) {
+        (self.memory_limit, self.maxmemory_policy)
+    }
+    
     /// Get value from storage
-    pub fn get(&self, key: &[u8]) -> Option<Vec<u8>> {
+    pub fn get(&self, key: &[u8]) -> Option<Arc<[u8]>> {
         let start = Instant::now();
         
         // Core read operation
         let result = self.mem_table.get(key);
         
         // Update metrics
-        let elapsed = start.elapsed().as_nanos() as u64;
-        self.stats.reads.fetch_add(1, Ordering::Relaxed);
-        self.stats.read_latency_ns.fetch_add(elapsed, Ordering::Relaxed);
+        self.record_read(start);
         
         result
     }
@@ -76,17 +359,23 @@ impl GlobalState {
     pub fn set(&self, key: &[u8], value: Vec<u8>, ttl: Option<Duration>) -> Result<(), String> {
         let start = Instant::now();
         
-        // Core write operation
-        let result = match self.mem_table.set(key, value.clone(), ttl) {
-            Ok(_) => {
-                // Log to AOF for durability - use Mutex lock
-                // CRITICAL FIX: Access AOF through mutex
-                if let Ok(mut aof_guard) = self.aof.lock() {
-                    if let Err(e) = aof_guard.append_set(key, &value, ttl) {
-                        return Err(format!("AOF write failed: {}", e));
-                    }
-                } else {
-                    return Err("Failed to acquire AOF lock".to_string());
+        // Reject oversized writes before anything is applied
+        self.check_size(key, &value)?;
+        self.ensure_memory()?;
+        
+        // Hold the AOF lock across the write so the log order matches memory
+        let mut aof = self.lock_aof()?;
+        
+        // Core write operation - large values may be stored (and logged) compressed
+        let result = match self.mem_table.set_string(key, value, ttl) {
+            Ok(value) => {
+                // Log to AOF for durability
+                self.log_string(&mut aof, key, &value, ttl)?;
+                drop(aof);
+                
+                self.notifier.notify(notify::class::STRING, "set", key);
+                if ttl.is_some() {
+                    self.notifier.notify(notify::class::GENERIC, "expire", key);
                 }
                 Ok(())
             }
@@ -94,9 +383,7 @@ impl GlobalState {
         };
         
         // Update metrics
-        let elapsed = start.elapsed().as_nanos() as u64;
-        self.stats.writes.fetch_add(1, Ordering::Relaxed);
-        self.stats.write_latency_ns.fetch_add(elapsed, Ordering::Relaxed);
+        self.record_write(start);
         
         result
     }
@@ -104,17 +391,36 @@ impl GlobalState {
     /// Delete value from storage
     // CRITICAL FIX: Same signature, using interior mutability
     pub fn delete(&self, key: &[u8]) -> Result<bool, String> {
+        self.remove(key, false)
+    }
+    
+    /// Delete value from storage, freeing large values in the background
+    pub fn unlink(&self, key: &[u8]) -> Result<bool, String> {
+        self.remove(key, true)
+    }
+    
+    /// Shared DEL/UNLINK path - identical AOF logging either way
+    fn remove(&self, key: &[u8], lazy: bool) -> Result<bool, String> {
+        // Hold the AOF lock across the delete, as in `set`
+        let mut aof_guard = self.lock_aof()?;
+        let removed = if lazy {
+            self.mem_table.unlink(key)
+        } else {
+            self.mem_table.delete(key)
+        };
+        
         // Core delete operation
-        let exists = match self.mem_table.delete(key) {
+        let exists = match removed {
             Ok(exists) => {
-                // Log to AOF for durability
-                // CRITICAL FIX: Access AOF through mutex
-                if let Ok(mut aof_guard) = self.aof.lock() {
-                    if let Err(e) = aof_guard.append_delete(key) {
-                        return Err(format!("AOF delete failed: {}", e));
-                    }
-                } else {
-                    return Err("Failed to acquire AOF lock".to_string());
+                // Log to AOF for durability - a missing key leaves the log (and WAIT) alone
+                if exists {
+                    self.record_aof(aof_guard.append_delete(key), "delete")?;
+                    self.aof_offset.store(aof_guard.logical_len(), Ordering::Release);
+                }
+                drop(aof_guard);
+                
+                if exists {
+                    self.notifier.notify(notify::class::GENERIC, "del", key);
                 }
                 exists
             }
@@ -127,26 +433,1955 @@ impl GlobalState {
         Ok(exists)
     }
     
-    /// Get system statistics
-    pub fn get_stats(&self) -> (Duration, u64, u64, u64, u64, u64) {
-        let uptime = self.stats.start_time.elapsed();
+    /// Evict one key chosen by `policy`, logging it as a delete so replay agrees
+    pub fn evict(&self, policy: EvictionPolicy) -> Result<Option<Vec<u8>>, String> {
+        let mut aof = self.lock_aof()?;
+        let Some(key) = self.mem_table.evict(policy) else {
+            return Ok(None);
+        };
         
-        let reads = self.stats.reads.load(Ordering::Relaxed);
-        let writes = self.stats.writes.load(Ordering::Relaxed);
-        let deletes = self.stats.deletes.load(Ordering::Relaxed);
+        self.record_aof(aof.append_delete(&key), "delete")?;
+        self.aof_offset.store(aof.logical_len(), Ordering::Release);
+        drop(aof);
         
-        let avg_write_latency = if writes > 0 {
-            self.stats.write_latency_ns.load(Ordering::Relaxed) / writes
-        } else {
-            0
-        };
+        self.notifier.notify(notify::class::EVICTED, "evicted", &key);
+        Ok(Some(key))
+    }
+    
+    /// Iterate over live entries (key, value, remaining TTL) through the engine
+    pub fn scan(&self) -> Box<dyn Iterator<Item = ScanEntry> + '_> {
+        self.mem_table.scan()
+    }
+    
+    /// Number of keys, counting expired ones not yet collected
+    pub fn len(&self) -> usize {
+        self.mem_table.len()
+    }
+    
+    /// Whether the keyspace is empty
+    pub fn is_empty(&self) -> bool {
+        self.mem_table.is_empty()
+    }
+    
+    /// Drop expired entries now, returning how many were removed
+    pub fn gc(&self) -> usize {
+        self.mem_table.gc()
+    }
+    
+    /// Make every write logged so far durable - flushes buffered AOF entries and fsyncs,
+    /// whatever the fsync policy
+    pub fn flush(&self) -> Result<(), String> {
+        let result = self.lock_aof()?.sync();
+        self.record_aof(result, "fsync")
+    }
+    
+    /// Count a read and its latency
+    fn record_read(&self, start: Instant) {
+        let elapsed = start.elapsed().as_nanos() as u64;
+        self.stats.reads.fetch_add(1, Ordering::Relaxed);
+        self.stats.read_latency_ns.fetch_add(elapsed, Ordering::Relaxed);
+    }
+    
+    /// Count a write and its latency
+    fn record_write(&self, start: Instant) {
+        let elapsed = start.elapsed().as_nanos() as u64;
+        self.stats.writes.fetch_add(1, Ordering::Relaxed);
+        self.stats.write_latency_ns.fetch_add(elapsed, Ordering::Relaxed);
+    }
+    
+    /// Make room for a growing write under the memory limit
+    /// Evicts per the policy until under the limit; OOM if it can't (or mustn't)
+    fn ensure_memory(&self) -> Result<(), String> {
+        if self.memory_limit == 0 {
+            return Ok(());
+        }
         
-        let avg_read_latency = if reads > 0 {
-            self.stats.read_latency_ns.load(Ordering::Relaxed) / reads
-        } else {
-            0
+        while self.mem_table.used_memory() > self.memory_limit {
+            let evicted = match self.maxmemory_policy {
+                MaxMemoryPolicy::Evict(policy) => self.evict(policy)?,
+                MaxMemoryPolicy::NoEviction => None,
+            };
+            if evicted.is_none() {
+                return Err(OOM_ERROR.to_string());
+            }
+        }
+        Ok(())
+    }
+    
+    /// Longest argument any write can store - parsers skip longer ones unread
+    pub fn max_arg_len(&self) -> usize {
+        self.max_key_size.max(self.max_value_size)
+    }
+    
+    /// Check a string write against the configured size limits
+    fn check_size(&self, key: &[u8], value: &[u8]) -> Result<(), String> {
+        if key.len() > self.max_key_size {
+            return Err("key too large".to_string());
+        }
+        if value.len() > self.max_value_size {
+            return Err("value too large".to_string());
+        }
+        Ok(())
+    }
+    
+    /// Append a SET to the AOF and advance the replication offset
+    fn log_set(&self, aof: &mut AppendOnlyFile, key: &[u8], value: &[u8], ttl: Option<Duration>) -> Result<(), String> {
+        self.record_aof(aof.append_set(key, value, ttl), "write")?;
+        self.aof_offset.store(aof.logical_len(), Ordering::Release);
+        
+        Ok(())
+    }
+    
+    /// Log a string value - plain strings as SET, compressed ones with their encoding
+    fn log_string(&self, aof: &mut AppendOnlyFile, key: &[u8], value: &ValueKind, ttl: Option<Duration>) -> Result<(), String> {
+        match value {
+            ValueKind::String(bytes) => self.log_set(aof, key, bytes, ttl),
+            value => self.log_value(aof, key, value, ttl),
+        }
+    }
+    
+    /// Log a whole typed value
+    fn log_value(&self, aof: &mut AppendOnlyFile, key: &[u8], value: &ValueKind, ttl: Option<Duration>) -> Result<(), String> {
+        self.record_aof(aof.append_value(key, value, ttl), "write")?;
+        self.aof_offset.store(aof.logical_len(), Ordering::Release);
+        Ok(())
+    }
+    
+    /// Note whether an AOF `action` succeeded (readiness) and turn its error into a reply
+    fn record_aof<T>(&self, result: std::io::Result<T>, action: &str) -> Result<T, String> {
+        self.aof_failed.store(result.is_err(), Ordering::Relaxed);
+        result.map_err(|e| format!("AOF {} failed: {}", action, e))
+    }
+    
+    /// Acquire the AOF mutex
+    fn lock_aof(&self) -> Result<MutexGuard<'_, AppendOnlyFile>, String> {
+        self.aof.lock()
+            .map_err(|_| "Failed to acquire AOF lock".to_string())
+    }
+    
+    /// Current unix time in seconds
+    fn unix_time_secs() -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs()
+    }
+}
+
+impl GlobalState {
+    /// Create global state from the newest snapshot matching the AOF plus the AOF
+    /// entries written after it; the whole AOF is replayed when no snapshot lines up
+    /// (none saved yet, or the AOF was rewritten since). `snapshots` then serves SAVE/BGSAVE
+    pub fn with_snapshot_recovery(
+        mem_table: Arc<MemTable>,
+        aof: AppendOnlyFile,
+        snapshots: SnapshotManager,
+        progress: impl FnMut(usize, u64, u64),
+    ) -> Self {
+        let from = match snapshots.restore_latest(|mark| aof.matches_mark(mark)) {
+            Ok(Some((path, mark, loaded))) => {
+                println!("Loaded {} keys from snapshot {}", loaded, path.display());
+                mark.offset
+            }
+            Ok(None) => 0,
+            Err(e) => {
+                eprintln!("Snapshot load error, replaying the whole AOF: {}", e);
+                mem_table.clear();
+                0
+            }
+        };
+        Self::replayed_from(from, mem_table, aof, progress).with_snapshot_manager(snapshots)
+    }
+    
+    /// Readiness sub-checks and whether each passes - ready when all do
+    pub fn readiness(&self) -> Vec<(&'static str, bool)> {
+        // Atomics only, so a probe never waits behind a slow append or rewrite
+        let aof_ok = !self.aof_failed.load(Ordering::Relaxed) && !self.aof_flush_failed.load(Ordering::Relaxed);
+        vec![
+            ("aof_replay", !self.recovery_failed()),
+            ("listener", self.listening.load(Ordering::Acquire) && !self.is_shutting_down()),
+            ("memory", self.memory_limit == 0 || self.used_memory() <= self.memory_limit),
+            ("aof_writes", aof_ok),
+        ]
+    }
+    
+    /// Gate effectful DEBUG subcommands and set the no-op allowlist
+    pub fn with_debug_commands(mut self, enabled: bool, noops: &[String]) -> Self {
+        self.debug_commands_enabled = enabled;
+        self.debug_noops = noops.iter().map(|sub| sub.to_ascii_uppercase()).collect();
+        self
+    }
+    
+    /// Trace every command and reply on new connections (DEBUG TRACE toggles one connection)
+    pub fn with_trace_commands(mut self, enabled: bool) -> Self {
+        self.trace_commands = enabled;
+        self
+    }
+    
+    /// Users connections can AUTH as (default: only the open `default` user)
+    pub fn with_acl(mut self, acl: Acl) -> Self {
+        self.acl = acl;
+        self
+    }
+    
+    /// Users and their command permissions
+    pub fn acl(&self) -> &Acl {
+        &self.acl
+    }
+    
+    /// Number of logical databases (at least 1)
+    pub fn with_databases(mut self, databases: usize) -> Self {
+        self.databases = databases.max(1);
+        self
+    }
+    
+    /// Number of logical databases
+    pub fn databases(&self) -> usize {
+        self.databases
+    }
+    
+    /// Warm up to `budget` keys on `warm` (None = off); snapshots then also save the
+    /// `budget` most recently accessed keys as the access order for the next start
+    pub fn with_warmer(mut self, budget: Option<usize>) -> Self {
+        self.warm_keys = budget;
+        self
+    }
+    
+    /// Pre-touch the working set after recovery so the first requests don't pay to fault
+    /// it in: the keys of the saved access order, else the keyspace in scan order, up to
+    /// the budget. Compressed strings are decompressed once on the way
+    /// Returns the keys touched and whether the saved access order was used
+    pub fn warm(&self) -> (usize, bool) {
+        let Some(budget) = self.warm_keys else {
+            return (0, false);
+        };
+        let prefetch = |value: &ValueKind| {
+            std::hint::black_box(value.as_string().map(|bytes| bytes.len()).ok());
         };
         
-        (uptime, reads, writes, deletes, avg_read_latency, avg_write_latency)
+        let saved = self.snapshots.as_ref().and_then(|manager| manager.load_access_log().unwrap_or_else(|e| {
+            eprintln!("Ignoring access log: {}", e);
+            None
+        }));
+        match saved {
+            Some(keys) => {
+                let warmed = keys.iter()
+                    .take(budget)
+                    .filter(|key| self.mem_table.read_value(key, prefetch).is_some())
+                    .count();
+                (warmed, true)
+            }
+            None => {
+                let warmed = self.mem_table.snapshot_iter()
+                    .take(budget)
+                    .map(|(_, value, _)| prefetch(&value))
+                    .count();
+                (warmed, false)
+            }
+        }
+    }
+    
+    /// Log commands that run longer than `threshold` (None = off)
+    pub fn with_watchdog(mut self, threshold: Option<Duration>) -> Self {
+        self.watchdog = threshold.map(|threshold| {
+            let watchdog = Arc::new(Watchdog::new(threshold));
+            watchdog.spawn();
+            watchdog
+        });
+        self
+    }
+    
+    /// Rewrite the AOF automatically once it grows `percentage` percent past its size
+    /// after the last rewrite and is at least `min_size` bytes (0% = off, like Redis
+    /// auto-aof-rewrite-percentage / auto-aof-rewrite-min-size)
+    /// Takes effect once `spawn_auto_aof_rewrite` is called
+    pub fn with_auto_aof_rewrite(mut self, percentage: u64, min_size: u64) -> Self {
+        self.aof_rewrite_percentage = percentage;
+        self.aof_rewrite_min_size = min_size;
+        self
+    }
+    
+    /// Slow-command watchdog, if enabled
+    pub fn watchdog(&self) -> Option<&Arc<Watchdog>> {
+        self.watchdog.as_ref()
+    }
+    
+    /// Keep the last `max_len` commands slower than `threshold` in the SLOWLOG (None = off)
+    pub fn with_slowlog(mut self, threshold: Option<Duration>, max_len: usize) -> Self {
+        self.slowlog = SlowLog::new(threshold, max_len);
+        self
+    }
+    
+    /// SLOWLOG entries
+    pub fn slowlog(&self) -> &SlowLog {
+        &self.slowlog
+    }
+    
+    /// Attach snapshot manager used by SAVE/BGSAVE
+    pub fn with_snapshot_manager(mut self, manager: SnapshotManager) -> Self {
+        self.snapshots = Some(Arc::new(manager));
+        self
+    }
+    
+    /// Get string value, or WRONGTYPE if the key holds a collection
+    pub fn get_string(&self, key: &[u8]) -> Result<Option<Arc<[u8]>>, String> {
+        let start = Instant::now();
+        let result = self.mem_table.get_string(key);
+        self.record_read(start);
+        result
+    }
+    
+    /// Value plus TTL, type and last access of a key, read atomically
+    pub fn get_full(&self, key: &[u8]) -> Option<EntryView> {
+        let start = Instant::now();
+        let result = self.mem_table.get_full(key);
+        self.record_read(start);
+        result
+    }
+    
+    /// Key counts by type and TTL (walks every partition)
+    pub fn keyspace_summary(&self) -> KeyspaceSummary {
+        self.mem_table.keyspace_summary()
+    }
+    
+    /// Keyspace summary per logical database, indexed by DB number
+    /// Every key lives in DB 0 (there is no SELECT), so the others report empty
+    pub fn keyspace_summaries(&self) -> Vec<KeyspaceSummary> {
+        let mut summaries = vec![KeyspaceSummary::default(); self.databases];
+        summaries[0] = self.keyspace_summary();
+        summaries
+    }
+    
+    /// Iterate over live entries (key, value, remaining TTL) as of the call
+    pub fn snapshot_iter(&self) -> SnapshotIter<'_> {
+        self.mem_table.snapshot_iter()
+    }
+    
+    /// Live string keys starting with `prefix` (values are not read)
+    pub fn count_strings_with_prefix(&self, prefix: &[u8]) -> usize {
+        self.mem_table.count_strings_with_prefix(prefix)
+    }
+    
+    /// Lock acquisitions and contention per MemTable partition
+    pub fn partition_stats(&self) -> Vec<PartitionStat> {
+        self.mem_table.partition_stats()
+    }
+    
+    /// TYPE name of a key (None if missing)
+    pub fn value_type(&self, key: &[u8]) -> Option<&'static str> {
+        self.mem_table.value_type(key)
+    }
+    
+    /// SADD - returns how many members were new
+    pub fn sadd(&self, key: &[u8], members: Vec<Vec<u8>>) -> Result<usize, String> {
+        self.mutate(key, Mutation::SAdd(members), notify::class::SET, "sadd")
+            .map(|applied| applied.added)
+    }
+    
+    /// SREM - returns how many members were removed
+    pub fn srem(&self, key: &[u8], members: Vec<Vec<u8>>) -> Result<usize, String> {
+        self.mutate(key, Mutation::SRem(members), notify::class::SET, "srem")
+            .map(|applied| applied.removed)
+    }
+    
+    /// SMEMBERS - all members of a set (empty for missing keys)
+    pub fn smembers(&self, key: &[u8]) -> Result<Vec<Vec<u8>>, String> {
+        let start = Instant::now();
+        let result = self.mem_table
+            .read_value(key, |value| value.as_set().map(|set| set.iter().cloned().collect()))
+            .unwrap_or_else(|| Ok(Vec::new()));
+        self.record_read(start);
+        result
+    }
+    
+    /// SISMEMBER
+    pub fn sismember(&self, key: &[u8], member: &[u8]) -> Result<bool, String> {
+        let start = Instant::now();
+        let result = self.mem_table
+            .read_value(key, |value| value.as_set().map(|set| set.contains(member)))
+            .unwrap_or(Ok(false));
+        self.record_read(start);
+        result
+    }
+    
+    /// SCARD - set size (0 for missing keys)
+    pub fn scard(&self, key: &[u8]) -> Result<usize, String> {
+        self.mem_table
+            .read_value(key, |value| value.as_set().map(|set| set.len()))
+            .unwrap_or(Ok(0))
+    }
+    
+    /// ZADD - returns members added (plus updated ones with `ch`)
+    pub fn zadd(&self, key: &[u8], members: Vec<(f64, Vec<u8>)>, flags: ZAddFlags, ch: bool) -> Result<usize, String> {
+        let applied = self.mutate(key, Mutation::ZAdd(members, flags), notify::class::ZSET, "zadd")?;
+        Ok(if ch { applied.added + applied.updated } else { applied.added })
+    }
+    
+    /// ZINCRBY / ZADD INCR - new score, or None if a ZADD flag blocked the update
+    pub fn zincrby(&self, key: &[u8], delta: f64, member: Vec<u8>, flags: ZAddFlags) -> Result<Option<f64>, String> {
+        let start = Instant::now();
+        let flags = ZAddFlags { incr: true, ..flags };
+        let mutation = Mutation::ZAdd(vec![(delta, member.clone())], flags);
+        self.ensure_memory()?;
+        
+        // Read the score back before releasing the AOF lock so it matches the log
+        let mut aof = self.lock_aof()?;
+        let applied = self.apply_logged(&mut aof, key, &mutation)?;
+        let score = self.mem_table
+            .read_value(key, |value| value.as_sorted_set().ok().and_then(|zset| zset.score(&member)))
+            .flatten();
+        drop(aof);
+        
+        if applied.changed() {
+            self.notifier.notify(notify::class::ZSET, "zincr", key);
+        }
+        
+        self.record_write(start);
+        Ok(if applied.skipped > 0 { None } else { score })
+    }
+    
+    /// ZREM - returns how many members were removed
+    pub fn zrem(&self, key: &[u8], members: Vec<Vec<u8>>) -> Result<usize, String> {
+        self.mutate(key, Mutation::ZRem(members), notify::class::ZSET, "zrem")
+            .map(|applied| applied.removed)
+    }
+    
+    /// SSCAN - matching members among the next `count` from `cursor`, and the cursor to resume from (0 = done)
+    pub fn sscan(&self, key: &[u8], cursor: usize, pattern: Option<&[u8]>, count: usize) -> Result<ScanPage<Vec<u8>>, String> {
+        let start = Instant::now();
+        let result = self.mem_table
+            .read_value(key, |value| value.as_set().map(|set| {
+                let members = set.iter().map(|member| (scan_position(member), member));
+                let (next, page) = scan_page(members, cursor, count, |member| pattern.is_none_or(|p| glob_match(p, member)));
+                (next, page.into_iter().cloned().collect())
+            }))
+            .unwrap_or(Ok((0, Vec::new())));
+        self.record_read(start);
+        result
+    }
+    
+    /// ZSCAN - like SSCAN, with each member's score
+    pub fn zscan(&self, key: &[u8], cursor: usize, pattern: Option<&[u8]>, count: usize) -> Result<ScanPage<(Vec<u8>, f64)>, String> {
+        let start = Instant::now();
+        let result = self.mem_table
+            .read_value(key, |value| value.as_sorted_set().map(|zset| {
+                let members = zset.iter().map(|(member, score)| (scan_position(member), (member.clone(), score)));
+                scan_page(members, cursor, count, |(member, _)| pattern.is_none_or(|p| glob_match(p, member)))
+            }))
+            .unwrap_or(Ok((0, Vec::new())));
+        self.record_read(start);
+        result
+    }
+    
+    /// ZSCORE
+    pub fn zscore(&self, key: &[u8], member: &[u8]) -> Result<Option<f64>, String> {
+        let start = Instant::now();
+        let result = self.mem_table
+            .read_value(key, |value| value.as_sorted_set().map(|zset| zset.score(member)))
+            .unwrap_or(Ok(None));
+        self.record_read(start);
+        result
+    }
+    
+    /// ZRANK/ZREVRANK
+    pub fn zrank(&self, key: &[u8], member: &[u8], reverse: bool) -> Result<Option<usize>, String> {
+        let start = Instant::now();
+        let result = self.mem_table
+            .read_value(key, |value| value.as_sorted_set().map(|zset| zset.rank(member, reverse)))
+            .unwrap_or(Ok(None));
+        self.record_read(start);
+        result
+    }
+    
+    /// ZRANGE/ZREVRANGE by index
+    pub fn zrange(&self, key: &[u8], start: i64, stop: i64, reverse: bool) -> Result<Vec<(Vec<u8>, f64)>, String> {
+        let started = Instant::now();
+        let result = self.mem_table
+            .read_value(key, |value| value.as_sorted_set().map(|zset| zset.range(start, stop, reverse)))
+            .unwrap_or_else(|| Ok(Vec::new()));
+        self.record_read(started);
+        result
+    }
+    
+    /// ZRANGEBYSCORE / ZREVRANGEBYSCORE - members within score bounds, paged by offset/count
+    pub fn zrange_by_score(
+        &self,
+        key: &[u8],
+        min: Bound<OrderedFloat>,
+        max: Bound<OrderedFloat>,
+        reverse: bool,
+        offset: usize,
+        count: Option<usize>,
+    ) -> Result<Vec<(Vec<u8>, f64)>, String> {
+        let started = Instant::now();
+        let result = self.mem_table
+            .read_value(key, |value| value.as_sorted_set().map(|zset| zset.range_by_score(min, max, reverse, offset, count)))
+            .unwrap_or_else(|| Ok(Vec::new()));
+        self.record_read(started);
+        result
+    }
+    
+    /// ZCARD - sorted set size (0 for missing keys)
+    pub fn zcard(&self, key: &[u8]) -> Result<usize, String> {
+        self.mem_table
+            .read_value(key, |value| value.as_sorted_set().map(|zset| zset.len()))
+            .unwrap_or(Ok(0))
+    }
+    
+    /// SINTER/SUNION/SDIFF
+    pub fn set_combine(&self, op: SetOp, keys: &[&[u8]]) -> Result<Vec<Vec<u8>>, String> {
+        let start = Instant::now();
+        let result = self.mem_table.set_combine(op, keys)
+            .map(|set| set.into_iter().collect());
+        self.record_read(start);
+        result
+    }
+    
+    /// SINTERCARD - intersection size, capped at `limit` (0 = no cap)
+    pub fn set_intercard(&self, keys: &[&[u8]], limit: usize) -> Result<usize, String> {
+        let start = Instant::now();
+        let result = self.mem_table.set_intercard(keys, limit);
+        self.record_read(start);
+        result
+    }
+    
+    /// SINTERSTORE/SUNIONSTORE/SDIFFSTORE - returns the stored cardinality
+    pub fn set_combine_store(&self, op: SetOp, dst: &[u8], keys: &[&[u8]]) -> Result<usize, String> {
+        let start = Instant::now();
+        
+        self.ensure_memory()?;
+        
+        // The result is logged whole, under the AOF lock to keep log order
+        let mut aof = self.lock_aof()?;
+        let (result, removed) = self.mem_table.set_combine_store(op, dst, keys)?;
+        let len = result.len();
+        
+        // An empty result into a missing destination changes nothing
+        if len == 0 && !removed {
+            drop(aof);
+            self.record_write(start);
+            return Ok(0);
+        }
+        
+        let logged = if result.is_empty() {
+            aof.append_delete(dst)
+        } else {
+            aof.append_value(dst, &ValueKind::Set(result), None)
+        };
+        self.record_aof(logged, "write")?;
+        self.aof_offset.store(aof.logical_len(), Ordering::Release);
+        drop(aof);
+        
+        let event = match op {
+            _ if len == 0 => "del",
+            SetOp::Inter => "sinterstore",
+            SetOp::Union => "sunionstore",
+            SetOp::Diff => "sdiffstore",
+        };
+        self.notifier.notify(if len == 0 { notify::class::GENERIC } else { notify::class::SET }, event, dst);
+        
+        self.record_write(start);
+        Ok(len)
+    }
+    
+    /// LPUSH / RPUSH - returns the list's new length
+    pub fn push(&self, key: &[u8], elements: Vec<Vec<u8>>, head: bool) -> Result<usize, String> {
+        let start = Instant::now();
+        let (mutation, event) = if head {
+            (Mutation::LPush(elements), "lpush")
+        } else {
+            (Mutation::RPush(elements), "rpush")
+        };
+        self.ensure_memory()?;
+        
+        // Read the length back before releasing the AOF lock so it matches the log
+        let mut aof = self.lock_aof()?;
+        self.apply_logged(&mut aof, key, &mutation)?;
+        let len = self.mem_table.list_len(key)?;
+        drop(aof);
+        self.notifier.notify(notify::class::LIST, event, key);
+        
+        self.record_write(start);
+        Ok(len)
+    }
+    
+    /// LRANGE
+    pub fn list_range(&self, key: &[u8], start: i64, stop: i64) -> Result<Vec<Vec<u8>>, String> {
+        let start_time = Instant::now();
+        let result = self.mem_table.list_range(key, start, stop);
+        self.record_read(start_time);
+        result
+    }
+    
+    /// LLEN
+    pub fn list_len(&self, key: &[u8]) -> Result<usize, String> {
+        let start = Instant::now();
+        let result = self.mem_table.list_len(key);
+        self.record_read(start);
+        result
+    }
+    
+    /// LPOS - matching indexes (see `MemTable::list_pos`)
+    pub fn list_pos(&self, key: &[u8], element: &[u8], rank: i64, count: usize) -> Result<Vec<usize>, String> {
+        let start = Instant::now();
+        let result = self.mem_table.list_pos(key, element, rank, count);
+        self.record_read(start);
+        result
+    }
+    
+    /// LINSERT - new length, -1 without the pivot, 0 without the key
+    pub fn list_insert(&self, key: &[u8], before: bool, pivot: &[u8], element: &[u8]) -> Result<i64, String> {
+        let start = Instant::now();
+        self.ensure_memory()?;
+        
+        let mut aof = self.lock_aof()?;
+        let (len, written) = self.mem_table.list_insert(key, before, pivot, element)?;
+        if let Some((value, ttl)) = written {
+            self.log_value(&mut aof, key, &value, ttl)?;
+            drop(aof);
+            self.notifier.notify(notify::class::LIST, "linsert", key);
+        }
+        
+        self.record_write(start);
+        Ok(len)
+    }
+    
+    /// LSET
+    pub fn list_set(&self, key: &[u8], index: i64, element: &[u8]) -> Result<(), String> {
+        let start = Instant::now();
+        self.ensure_memory()?;
+        
+        let mut aof = self.lock_aof()?;
+        if let Some((value, ttl)) = self.mem_table.list_set(key, index, element)? {
+            self.log_value(&mut aof, key, &value, ttl)?;
+        }
+        drop(aof);
+        self.notifier.notify(notify::class::LIST, "lset", key);
+        
+        self.record_write(start);
+        Ok(())
+    }
+    
+    /// Set many string values at once (MSET)
+    /// One lock per partition in the MemTable, and one AOF lock for the whole batch
+    pub fn set_batch(&self, entries: Vec<(Vec<u8>, Vec<u8>, Option<Duration>)>) -> Result<(), String> {
+        self.store_batch(entries, false).map(|_| ())
+    }
+    
+    /// Set many string values only if none of the keys exists (MSETNX) - returns whether
+    /// they were set. The check and the writes happen under the same locks
+    pub fn set_batch_nx(&self, entries: Vec<(Vec<u8>, Vec<u8>, Option<Duration>)>) -> Result<bool, String> {
+        self.store_batch(entries, true)
+    }
+    
+    /// `set_batch`, or with `nx` `set_batch_nx`
+    fn store_batch(&self, entries: Vec<(Vec<u8>, Vec<u8>, Option<Duration>)>, nx: bool) -> Result<bool, String> {
+        let start = Instant::now();
+        
+        for (key, value, _) in &entries {
+            self.check_size(key, value)?;
+        }
+        self.ensure_memory()?;
+        
+        let entries: Vec<_> = entries.into_iter()
+            .map(|(key, value, ttl)| (key, self.mem_table.string_value(value), ttl))
+            .collect();
+        
+        let mut aof = self.lock_aof()?;
+        let keys: Vec<&[u8]> = entries.iter().map(|(key, _, _)| key.as_slice()).collect();
+        let mut guard = self.mem_table.lock_partitions(&keys)?;
+        if nx && keys.iter().any(|key| guard.get(key).is_some()) {
+            return Ok(false);
+        }
+        for (key, value, ttl) in &entries {
+            guard.set(key, value.clone(), *ttl);
+        }
+        drop(guard);
+        for (key, value, ttl) in &entries {
+            self.log_string(&mut aof, key, value, *ttl)?;
+        }
+        drop(aof);
+        
+        for (key, _, ttl) in &entries {
+            self.notifier.notify(notify::class::STRING, "set", key);
+            if ttl.is_some() {
+                self.notifier.notify(notify::class::GENERIC, "expire", key);
+            }
+        }
+        
+        self.record_write(start);
+        Ok(true)
+    }
+    
+    /// Move a key and its TTL to `dst`, replacing it (RENAME)
+    /// With `nx`, nothing happens if `dst` exists; returns whether the key moved
+    pub fn rename(&self, src: &[u8], dst: &[u8], nx: bool) -> Result<bool, String> {
+        let start = Instant::now();
+        
+        // Both partitions stay locked from the existence check to the move
+        let mut aof = self.lock_aof()?;
+        let mut guard = self.mem_table.lock_partitions(&[src, dst])?;
+        let Some(value) = guard.get(src) else {
+            return Err("no such key".to_string());
+        };
+        if nx && guard.get(dst).is_some() {
+            return Ok(false);
+        }
+        if src == dst {
+            return Ok(true);
+        }
+        
+        let ttl = guard.ttl(src);
+        self.record_aof(aof.append_value(dst, value, ttl).and_then(|_| aof.append_delete(src)), "write")?;
+        self.aof_offset.store(aof.logical_len(), Ordering::Release);
+        
+        let value = guard.remove(src).expect("source checked under lock");
+        guard.set(dst, value, ttl);
+        drop(guard);
+        drop(aof);
+        
+        self.notifier.notify(notify::class::GENERIC, "rename_from", src);
+        self.notifier.notify(notify::class::GENERIC, "rename_to", dst);
+        
+        self.record_write(start);
+        Ok(true)
+    }
+    
+    /// Atomically add `delta` to an integer value (INCR/INCRBY/DECR/DECRBY)
+    pub fn incr_by(&self, key: &[u8], delta: i64) -> Result<i64, String> {
+        let start = Instant::now();
+        
+        self.ensure_memory()?;
+        
+        // Hold the AOF lock across the update so concurrent increments are logged in order
+        let mut aof = self.lock_aof()?;
+        let (value, ttl) = self.mem_table.incr_by(key, delta)?;
+        self.log_set(&mut aof, key, value.to_string().as_bytes(), ttl)?;
+        drop(aof);
+        self.notifier.notify(notify::class::STRING, "incrby", key);
+        
+        self.record_write(start);
+        Ok(value)
+    }
+    
+    /// SEQ NEXT - `count` consecutive ids from the sequence stored at `name`, returning the first
+    /// Ids come from blocks reserved past the sequence's high-water mark, which is logged as
+    /// its own AOF record and fsynced before the ids are handed out. The key only mirrors
+    /// the mark: DEL, SET, FLUSHALL or eviction lowering it never makes an id come round again
+    pub fn seq_next(&self, name: &[u8], count: u64) -> Result<u64, String> {
+        self.sequences.next(name, count, |block| {
+            self.ensure_memory()?;
+            
+            // A counter raised by hand moves the sequence on; a lowered one is ignored
+            let mut aof = self.lock_aof()?;
+            let (counter, ttl) = self.mem_table.incr_by(name, 0)?;
+            let high_water = self.mem_table.sequence_mark(name)
+                .max(counter.max(0) as u64)
+                .checked_add(block)
+                .filter(|&high_water| high_water <= i64::MAX as u64)
+                .ok_or_else(|| "increment or decrement would overflow".to_string())?;
+            
+            let value = high_water.to_string().into_bytes();
+            self.mem_table.set(name, value.clone(), ttl)?;
+            self.log_set(&mut aof, name, &value, ttl)?;
+            self.record_aof(aof.append_sequence(name, high_water), "write")?;
+            self.aof_offset.store(aof.logical_len(), Ordering::Release);
+            self.mem_table.raise_sequence_mark(name, high_water);
+            drop(aof);
+            self.notifier.notify(notify::class::STRING, "incrby", name);
+            
+            self.flush()?;
+            Ok(high_water)
+        })
+    }
+    
+    /// GETRANGE - substring by inclusive byte indices
+    pub fn get_range(&self, key: &[u8], start: i64, end: i64) -> Result<Vec<u8>, String> {
+        let start_time = Instant::now();
+        let result = self.mem_table.get_range(key, start, end);
+        self.record_read(start_time);
+        result
+    }
+    
+    /// STRLEN - byte length of a string value
+    pub fn strlen(&self, key: &[u8]) -> Result<usize, String> {
+        let start = Instant::now();
+        let result = self.mem_table.strlen(key);
+        self.record_read(start);
+        result
+    }
+    
+    /// BITCOUNT - set bits in a string, optionally over a byte range
+    pub fn bit_count(&self, key: &[u8], range: Option<(i64, i64)>) -> Result<u64, String> {
+        let start = Instant::now();
+        let result = self.mem_table.bit_count(key, range);
+        self.record_read(start);
+        result
+    }
+    
+    /// GETBIT - single bit of a string value
+    pub fn get_bit(&self, key: &[u8], offset: usize) -> Result<u8, String> {
+        let start = Instant::now();
+        let result = self.mem_table.get_bit(key, offset);
+        self.record_read(start);
+        result
+    }
+    
+    /// SETBIT - set or clear one bit, returning the previous bit
+    pub fn set_bit(&self, key: &[u8], offset: usize, on: bool) -> Result<u8, String> {
+        let start = Instant::now();
+        
+        self.check_size(key, b"")?;
+        if offset / 8 >= self.max_value_size {
+            return Err("string exceeds maximum allowed size".to_string());
+        }
+        self.ensure_memory()?;
+        
+        let mut aof = self.lock_aof()?;
+        let (old, value, ttl) = self.mem_table.set_bit(key, offset, on)?;
+        self.log_set(&mut aof, key, &value, ttl)?;
+        drop(aof);
+        self.notifier.notify(notify::class::STRING, "setbit", key);
+        
+        self.record_write(start);
+        Ok(old)
+    }
+    
+    /// SETRANGE - patch a string in place, returning its new length
+    pub fn set_range(&self, key: &[u8], offset: usize, patch: &[u8]) -> Result<usize, String> {
+        let start = Instant::now();
+        
+        self.check_size(key, b"")?;
+        if !patch.is_empty() && offset.saturating_add(patch.len()) > self.max_value_size {
+            return Err("string exceeds maximum allowed size".to_string());
+        }
+        self.ensure_memory()?;
+        
+        let mut aof = self.lock_aof()?;
+        let (len, written) = self.mem_table.set_range(key, offset, patch)?;
+        let Some((value, ttl)) = written else {
+            return Ok(len);
+        };
+        self.log_set(&mut aof, key, &value, ttl)?;
+        drop(aof);
+        self.notifier.notify(notify::class::STRING, "setrange", key);
+        
+        self.record_write(start);
+        Ok(len)
+    }
+    
+    /// PFADD - returns whether the HyperLogLog changed
+    pub fn pf_add(&self, key: &[u8], elements: &[Vec<u8>]) -> Result<bool, String> {
+        let start = Instant::now();
+        
+        self.check_size(key, b"")?;
+        self.ensure_memory()?;
+        let mut aof = self.lock_aof()?;
+        let (changed, written) = self.mem_table.pf_add(key, elements)?;
+        let Some((value, ttl)) = written else {
+            return Ok(changed);
+        };
+        self.log_set(&mut aof, key, &value, ttl)?;
+        drop(aof);
+        self.notifier.notify(notify::class::STRING, "pfadd", key);
+        
+        self.record_write(start);
+        Ok(changed)
+    }
+    
+    /// PFCOUNT - approximate distinct elements across the keys' union
+    pub fn pf_count(&self, keys: &[&[u8]]) -> Result<u64, String> {
+        let start = Instant::now();
+        let result = self.mem_table.pf_count(keys);
+        self.record_read(start);
+        result
+    }
+    
+    /// PFMERGE - store the union of `sources` and `dst` in `dst`
+    pub fn pf_merge(&self, dst: &[u8], sources: &[&[u8]]) -> Result<(), String> {
+        let start = Instant::now();
+        
+        self.check_size(dst, b"")?;
+        self.ensure_memory()?;
+        let mut aof = self.lock_aof()?;
+        let (value, ttl) = self.mem_table.pf_merge(dst, sources)?;
+        self.log_set(&mut aof, dst, &value, ttl)?;
+        drop(aof);
+        self.notifier.notify(notify::class::STRING, "pfadd", dst);
+        
+        self.record_write(start);
+        Ok(())
+    }
+    
+    /// Memcached incr/decr - unsigned, wrapping on incr and clamped at zero on decr
+    /// Returns None when the key does not exist
+    pub fn incr_by_unsigned(&self, key: &[u8], delta: u64, decrement: bool) -> Result<Option<u64>, String> {
+        let start = Instant::now();
+        
+        self.ensure_memory()?;
+        
+        let mut aof = self.lock_aof()?;
+        let Some((value, ttl)) = self.mem_table.incr_by_unsigned(key, delta, decrement)? else {
+            return Ok(None);
+        };
+        self.log_set(&mut aof, key, value.to_string().as_bytes(), ttl)?;
+        drop(aof);
+        self.notifier.notify(notify::class::STRING, if decrement { "decrby" } else { "incrby" }, key);
+        
+        self.record_write(start);
+        Ok(Some(value))
+    }
+    
+    /// Replace a key's TTL (None removes it), returning whether the key exists
+    pub fn set_expiry(&self, key: &[u8], ttl: Option<Duration>) -> Result<bool, String> {
+        let start = Instant::now();
+        
+        let mut aof = self.lock_aof()?;
+        if !self.mem_table.set_expiry(key, ttl) {
+            return Ok(false);
+        }
+        self.record_aof(aof.append_expire(key, ttl), "write")?;
+        self.aof_offset.store(aof.logical_len(), Ordering::Release);
+        drop(aof);
+        
+        self.notifier.notify(notify::class::GENERIC, if ttl.is_some() { "expire" } else { "persist" }, key);
+        
+        self.record_write(start);
+        Ok(true)
+    }
+    
+    /// Remove every key (FLUSHDB / flush_all), returning how many were dropped
+    pub fn flush_all(&self) -> Result<usize, String> {
+        // Clear under the AOF lock so the marker lands exactly where memory was emptied
+        let mut aof = self.lock_aof()?;
+        let removed = self.mem_table.clear();
+        self.notifier.record_flush();
+        self.record_aof(aof.append_flush(), "write")?;
+        self.aof_offset.store(aof.logical_len(), Ordering::Release);
+        
+        Ok(removed)
+    }
+    
+    /// Refresh last access time of a key without reading it
+    pub fn touch(&self, key: &[u8]) -> bool {
+        self.mem_table.touch(key)
+    }
+    
+    /// Estimated memory used by a key (MEMORY USAGE), sampling `samples` collection elements
+    pub fn memory_usage(&self, key: &[u8], samples: usize) -> Option<usize> {
+        self.mem_table.entry_size_sampled(key, samples)
+    }
+    
+    /// Encoding thresholds (CONFIG GET/SET)
+    pub fn encodings(&self) -> &EncodingConfig {
+        &self.encodings
+    }
+    
+    /// Encoding name Redis would report for a key (OBJECT ENCODING) - not an access
+    pub fn object_encoding(&self, key: &[u8]) -> Option<&'static str> {
+        self.mem_table.inspect_value(key, |value| self.encodings.encoding(value))
+    }
+    
+    /// Logarithmic access frequency of a key (OBJECT FREQ)
+    pub fn access_frequency(&self, key: &[u8]) -> Option<u8> {
+        self.mem_table.access_frequency(key)
+    }
+    
+    /// Strings stored compressed, with their original and compressed byte totals
+    pub fn compression_stats(&self) -> (u64, u64, u64) {
+        self.mem_table.compression_stats()
+    }
+    
+    /// Random live key, if any
+    pub fn random_key(&self) -> Option<Vec<u8>> {
+        self.mem_table.random_key()
+    }
+    
+    /// Pub/sub channel hub
+    pub fn pubsub(&self) -> &Arc<PubSub> {
+        &self.pubsub
+    }
+    
+    /// Configure keyspace notifications (`notify-keyspace-events` flags)
+    pub fn set_notify_keyspace_events(&self, flags: &str) -> Result<(), String> {
+        self.notifier.set_flags(flags)
+    }
+    
+    /// Changes made to `table`'s keys so far, by any command (see `KeyspaceNotifier`)
+    pub fn table_changes(&self, table: &str) -> u64 {
+        self.notifier.table_changes(table)
+    }
+    
+    /// Create snapshot synchronously (SAVE)
+    pub fn save(&self) -> Result<PathBuf, String> {
+        if self.bgsave_in_progress.load(Ordering::Acquire) {
+            return Err("Background save already in progress".to_string());
+        }
+        
+        let manager = self.snapshots.as_ref()
+            .ok_or_else(|| "Snapshots are not configured".to_string())?;
+        
+        let path = self.create_snapshot(manager)?;
+        self.last_save.store(Self::unix_time_secs(), Ordering::Release);
+        
+        Ok(path)
+    }
+    
+    /// Snapshot the keyspace marked with the AOF position it matches
+    /// Both are taken under the AOF lock; the snapshot is written after releasing it
+    fn create_snapshot(&self, manager: &SnapshotManager) -> Result<PathBuf, String> {
+        let (entries, mark) = {
+            let mut aof = self.lock_aof()?;
+            let mark = aof.mark().map_err(|e| format!("AOF fsync failed: {}", e))?;
+            self.log_sequence_marks(&mut aof)?;
+            (self.mem_table.snapshot_iter(), mark)
+        };
+        let path = manager.create_snapshot_with(entries, Some(mark))
+            .map_err(|e| format!("Snapshot failed: {}", e))?;
+        
+        // The snapshot stands on its own - a failed access log only costs the next warmup
+        if let Some(budget) = self.warm_keys
+            && let Err(e) = manager.save_access_log(&self.mem_table.hottest_keys(budget)) {
+            eprintln!("Failed to save access log: {}", e);
+        }
+        Ok(path)
+    }
+    
+    /// Log every SEQ high-water mark again and fsync them
+    /// Snapshot recovery replays from the mark and a rewrite keeps only what follows its
+    /// start, so marks logged before either would otherwise be lost
+    fn log_sequence_marks(&self, aof: &mut AppendOnlyFile) -> Result<(), String> {
+        let marks = self.mem_table.sequence_marks();
+        if marks.is_empty() {
+            return Ok(());
+        }
+        
+        for (name, high_water) in marks {
+            self.record_aof(aof.append_sequence(&name, high_water), "write")?;
+        }
+        self.aof_offset.store(aof.logical_len(), Ordering::Release);
+        self.record_aof(aof.sync(), "fsync")
+    }
+    
+    /// Start snapshot on a background thread (BGSAVE)
+    pub fn bgsave(self: &Arc<Self>) -> Result<(), String> {
+        let manager = self.snapshots.clone()
+            .ok_or_else(|| "Snapshots are not configured".to_string())?;
+        
+        // Only one background save at a time
+        if self.bgsave_in_progress.swap(true, Ordering::AcqRel) {
+            return Err("Background save already in progress".to_string());
+        }
+        
+        let state = self.clone();
+        std::thread::spawn(move || {
+            match state.create_snapshot(&manager) {
+                Ok(_) => state.last_save.store(Self::unix_time_secs(), Ordering::Release),
+                Err(e) => eprintln!("Background save failed: {}", e),
+            }
+            
+            state.bgsave_in_progress.store(false, Ordering::Release);
+        });
+        
+        Ok(())
+    }
+    
+    /// Compact the AOF on a background thread (BGREWRITEAOF)
+    pub fn bgrewriteaof(self: &Arc<Self>) -> Result<(), String> {
+        if self.aof_rewrite_in_progress.swap(true, Ordering::AcqRel) {
+            return Err("Background append only file rewriting already in progress".to_string());
+        }
+        
+        let state = self.clone();
+        std::thread::spawn(move || {
+            if let Err(e) = state.run_aof_rewrite() {
+                eprintln!("Background AOF rewrite failed: {}", e);
+            }
+            state.aof_rewrite_in_progress.store(false, Ordering::Release);
+        });
+        
+        Ok(())
+    }
+    
+    /// Compact the AOF on this thread - returns the new size
+    pub fn rewrite_aof(&self) -> Result<u64, String> {
+        if self.aof_rewrite_in_progress.swap(true, Ordering::AcqRel) {
+            return Err("Background append only file rewriting already in progress".to_string());
+        }
+        
+        let result = self.run_aof_rewrite();
+        self.aof_rewrite_in_progress.store(false, Ordering::Release);
+        result
+    }
+    
+    /// Whether the AOF has grown enough past its base size to auto rewrite
+    pub fn aof_rewrite_due(&self) -> bool {
+        if self.aof_rewrite_percentage == 0 || self.aof_rewrite_in_progress() {
+            return false;
+        }
+        
+        let (current, base) = (self.aof_current_size(), self.aof_base_size.load(Ordering::Acquire));
+        current >= self.aof_rewrite_min_size
+            && current.saturating_sub(base) * 100 >= base.max(1) * self.aof_rewrite_percentage
+    }
+    
+    /// Start the thread that triggers background rewrites per `with_auto_aof_rewrite`
+    /// It exits once the state is dropped; does nothing when auto rewrite is off
+    pub fn spawn_auto_aof_rewrite(self: &Arc<Self>) {
+        if self.aof_rewrite_percentage == 0 {
+            return;
+        }
+        
+        let state = Arc::downgrade(self);
+        std::thread::spawn(move || loop {
+            std::thread::sleep(AOF_REWRITE_CHECK_INTERVAL);
+            let Some(state) = state.upgrade() else {
+                return;
+            };
+            if state.aof_rewrite_due() && state.bgrewriteaof().is_ok() {
+                println!("Starting automatic AOF rewrite ({} bytes)", state.aof_current_size());
+            }
+        });
+    }
+    
+    /// Whether an AOF rewrite is running
+    pub fn aof_rewrite_in_progress(&self) -> bool {
+        self.aof_rewrite_in_progress.load(Ordering::Acquire)
+    }
+    
+    /// AOF size on disk in bytes
+    pub fn aof_current_size(&self) -> u64 {
+        self.aof.lock()
+            .map(|aof| aof.size())
+            .unwrap_or(0)
+    }
+    
+    /// AOF size at startup or after the last rewrite
+    pub fn aof_base_size(&self) -> u64 {
+        self.aof_base_size.load(Ordering::Acquire)
+    }
+    
+    /// Write the live keyspace to a new AOF and swap it in
+    /// The snapshot and its start offset are taken together under the AOF lock, so
+    /// the entries appended meanwhile are exactly the ones carried over at the swap.
+    /// Offsets carry on from where the old files ended, so marks and WAITAOF targets stay valid
+    fn run_aof_rewrite(&self) -> Result<u64, String> {
+        let (path, from_position, snapshot) = {
+            let mut aof = self.lock_aof()?;
+            let taken = (aof.path().to_path_buf(), aof.logical_len(), self.mem_table.snapshot_iter());
+            
+            // Logged past the start, so they are carried over into the new file
+            self.log_sequence_marks(&mut aof)?;
+            taken
+        };
+        
+        let mut rewritten = path.into_os_string();
+        rewritten.push(".rewrite");
+        let rewritten = PathBuf::from(rewritten);
+        
+        let installed = AppendOnlyFile::write_compacted(&rewritten, snapshot)
+            .and_then(|_| {
+                let mut aof = self.aof.lock()
+                    .map_err(|_| std::io::Error::other("Failed to acquire AOF lock"))?;
+                aof.install_rewrite(&rewritten, from_position)?;
+                self.aof_offset.store(aof.logical_len(), Ordering::Release);
+                self.aof_base_size.store(aof.size(), Ordering::Release);
+                Ok(aof.size())
+            });
+        if installed.is_err() {
+            let _ = std::fs::remove_file(&rewritten);
+        }
+        installed.map_err(|e| format!("AOF rewrite failed: {}", e))
+    }
+    
+    /// Whether effectful DEBUG subcommands may run
+    pub fn debug_commands_enabled(&self) -> bool {
+        self.debug_commands_enabled
+    }
+    
+    /// Whether new connections start traced
+    pub fn trace_commands(&self) -> bool {
+        self.trace_commands
+    }
+    
+    /// Whether a DEBUG subcommand is an allowlisted no-op
+    pub fn is_debug_noop(&self, subcommand: &[u8]) -> bool {
+        self.debug_noops.iter().any(|noop| noop.as_bytes().eq_ignore_ascii_case(subcommand))
+    }
+    
+    /// DEBUG RELOAD - snapshot, then rebuild the MemTable from that snapshot
+    /// Writers are held off on the AOF lock so nothing lands between save and load
+    pub fn debug_reload(&self) -> Result<usize, String> {
+        if self.bgsave_in_progress.load(Ordering::Acquire) {
+            return Err("Background save already in progress".to_string());
+        }
+        
+        let manager = self.snapshots.as_ref()
+            .ok_or_else(|| "Snapshots are not configured".to_string())?;
+        
+        let mut aof = self.lock_aof()?;
+        let mark = aof.mark().map_err(|e| format!("AOF fsync failed: {}", e))?;
+        self.log_sequence_marks(&mut aof)?;
+        let path = manager.create_snapshot_with(self.mem_table.snapshot_iter(), Some(mark))
+            .map_err(|e| format!("Snapshot failed: {}", e))?;
+        self.last_save.store(Self::unix_time_secs(), Ordering::Release);
+        
+        let loaded = manager.restore_from_snapshot(&path)
+            .map_err(|e| format!("Error trying to load the snapshot: {}", e))?;
+        drop(aof);
+        
+        Ok(loaded)
+    }
+    
+    /// SHUTDOWN - optionally snapshot, fsync the AOF, then signal the server to stop
+    /// Nothing is signalled if persisting fails, so the server keeps running
+    pub fn shutdown(&self, save: bool) -> Result<(), String> {
+        if save && self.snapshots.is_some() {
+            self.save()?;
+        }
+        
+        self.flush()?;
+        self.request_shutdown();
+        Ok(())
+    }
+    
+    /// Mark the client listener bound (or closed) for readiness
+    pub fn set_listening(&self, listening: bool) {
+        self.listening.store(listening, Ordering::Release);
+    }
+    
+    /// Signal the server to stop accepting connections and drain
+    pub fn request_shutdown(&self) {
+        self.shutdown.send_replace(true);
+    }
+    
+    /// Whether a shutdown has been requested
+    pub fn is_shutting_down(&self) -> bool {
+        *self.shutdown.borrow()
+    }
+    
+    /// Receiver that observes shutdown requests
+    pub fn shutdown_signal(&self) -> watch::Receiver<bool> {
+        self.shutdown.subscribe()
+    }
+    
+    /// Whether a background save is running
+    pub fn bgsave_in_progress(&self) -> bool {
+        self.bgsave_in_progress.load(Ordering::Acquire)
+    }
+    
+    /// Unix timestamp of the last successful save (LASTSAVE)
+    pub fn last_save(&self) -> u64 {
+        self.last_save.load(Ordering::Acquire)
+    }
+    
+    /// AOF offset after the most recent write
+    pub fn aof_offset(&self) -> u64 {
+        self.aof_offset.load(Ordering::Acquire)
+    }
+    
+    /// Replica acknowledgment registry
+    pub fn replication(&self) -> &ReplicaRegistry {
+        &self.replication
+    }
+    
+    /// Run as a read-only replica of the primary at `primary` (host:port), whose AOF
+    /// `primary_aof` is the replication stream (a shared mount of its data path).
+    /// Client writes are refused; `spawn_replica_applier` applies the stream through
+    /// the AOF replay paths, which never go through dispatch
+    pub fn with_replica_of(mut self, primary: String, primary_aof: PathBuf) -> Self {
+        self.role = Role::Replica;
+        self.primary_link = PrimaryLink::new(Some(primary), Some(primary_aof));
+        self
+    }
+    
+    /// Start the thread that follows the primary's AOF on a replica, reporting the
+    /// link as up while the AOF can be read. It exits once the state is dropped;
+    /// does nothing on a primary
+    pub fn spawn_replica_applier(self: &Arc<Self>) {
+        let Some(path) = self.primary_link.aof().map(Path::to_path_buf) else {
+            return;
+        };
+        
+        let state = Arc::downgrade(self);
+        std::thread::spawn(move || {
+            // Reader of the primary's AOF and the offset applied up to (None = resync)
+            let mut reader = None;
+            let mut failing = false;
+            loop {
+                let Some(state) = state.upgrade() else {
+                    return;
+                };
+                match state.apply_replication(&path, &mut reader) {
+                    Ok(()) => {
+                        state.primary_link.set_up(true);
+                        failing = false;
+                    }
+                    Err(e) => {
+                        // Logged once per outage, not on every retry
+                        if !failing {
+                            eprintln!("Replication from {} failed: {}", path.display(), e);
+                        }
+                        state.primary_link.set_up(false);
+                        failing = true;
+                        reader = None;
+                    }
+                }
+                drop(state);
+                std::thread::sleep(REPLICA_POLL_INTERVAL);
+            }
+        });
+    }
+    
+    /// One replica poll: apply what the primary appended since the last one. Without
+    /// an open reader this is a full resync - the keyspace is rebuilt from the whole AOF
+    fn apply_replication(&self, path: &Path, reader: &mut Option<(AppendOnlyFile, u64)>) -> std::io::Result<()> {
+        if reader.is_none() {
+            let mut aof = AppendOnlyFile::open_readonly(path)?;
+            self.mem_table.recover_clear();
+            aof.replay_existing_entries(&*self.mem_table)?;
+            let offset = aof.logical_len();
+            self.notifier.record_flush();
+            *reader = Some((aof, offset));
+        }
+        let Some((aof, offset)) = reader else {
+            return Ok(());
+        };
+        
+        // Applied entries skip the notifier, so cached queries are dropped wholesale
+        let applied = aof.replay_since(*offset, &*self.mem_table)?;
+        if applied != *offset {
+            self.notifier.record_flush();
+            *offset = applied;
+        }
+        Ok(())
+    }
+    
+    /// Primary or replica
+    pub fn role(&self) -> Role {
+        self.role
+    }
+    
+    /// Connection to the primary (meaningful on a replica)
+    pub fn primary_link(&self) -> &PrimaryLink {
+        &self.primary_link
+    }
+    
+    /// AOF offset up to which writes are fsynced - compare with `aof_offset`
+    pub fn aof_synced_offset(&self) -> u64 {
+        self.aof_synced.load(Ordering::Acquire)
+    }
+    
+    /// Number of AOF entries written but not yet fsynced
+    pub fn aof_pending_fsync(&self) -> u64 {
+        self.aof.lock()
+            .map(|aof| aof.pending_fsync())
+            .unwrap_or(0)
+    }
+    
+    /// Get system statistics
+    pub fn get_stats(&self) -> (Duration, u64, u64, u64, u64, u64) {
+        let uptime = self.stats.start_time.elapsed();
+        
+        let reads = self.stats.reads.load(Ordering::Relaxed);
+        let writes = self.stats.writes.load(Ordering::Relaxed);
+        let deletes = self.stats.deletes.load(Ordering::Relaxed);
+        
+        let avg_write_latency = if writes > 0 {
+            self.stats.write_latency_ns.load(Ordering::Relaxed) / writes
+        } else {
+            0
+        };
+        
+        let avg_read_latency = if reads > 0 {
+            self.stats.read_latency_ns.load(Ordering::Relaxed) / reads
+        } else {
+            0
+        };
+        
+        (uptime, reads, writes, deletes, avg_read_latency, avg_write_latency)
+    }
+    
+    /// Apply a collection mutation and log it, firing `event` if anything changed
+    fn mutate(&self, key: &[u8], mutation: Mutation, event_class: u32, event: &str) -> Result<Applied, String> {
+        let start = Instant::now();
+        
+        // Removals are always allowed - they are how memory gets freed
+        if matches!(mutation, Mutation::SAdd(_) | Mutation::ZAdd(..)) {
+            self.ensure_memory()?;
+        }
+        
+        let mut aof = self.lock_aof()?;
+        let applied = self.apply_logged(&mut aof, key, &mutation)?;
+        drop(aof);
+        
+        if applied.changed() {
+            self.notifier.notify(event_class, event, key);
+        }
+        
+        self.record_write(start);
+        Ok(applied)
+    }
+    
+    /// Apply a mutation under the held AOF lock, logging it only if it changed something
+    fn apply_logged(&self, aof: &mut AppendOnlyFile, key: &[u8], mutation: &Mutation) -> Result<Applied, String> {
+        let applied = self.mem_table.apply_mutation(key, mutation)?;
+        if applied.changed() {
+            self.record_aof(aof.append_mutation(key, mutation), "write")?;
+            self.aof_offset.store(aof.logical_len(), Ordering::Release);
+        }
+        
+        Ok(applied)
+    }
+    
+    /// Register a new connection from `addr` unless `max_clients` are already connected
+    /// (0 = no limit) - returns its id, or None (counting a rejection) at the limit
+    pub fn try_open_connection(&self, max_clients: usize, addr: SocketAddr) -> Option<u64> {
+        let connected = self.stats.connected_clients.fetch_add(1, Ordering::AcqRel);
+        
+        if max_clients > 0 && connected >= max_clients as u64 {
+            self.stats.connected_clients.fetch_sub(1, Ordering::AcqRel);
+            self.stats.rejected_connections.fetch_add(1, Ordering::Relaxed);
+            return None;
+        }
+        
+        self.stats.connections_received.fetch_add(1, Ordering::Relaxed);
+        let id = self.next_client_id();
+        self.clients.register(id, addr);
+        Some(id)
+    }
+    
+    /// Unique id for a new connection (starting at 1)
+    pub fn next_client_id(&self) -> u64 {
+        self.next_client_id.fetch_add(1, Ordering::Relaxed) + 1
+    }
+    
+    /// Unregister connection `id` opened with `try_open_connection`
+    pub fn close_connection(&self, id: u64) {
+        self.clients.remove(id);
+        self.stats.connected_clients.fetch_sub(1, Ordering::AcqRel);
+    }
+    
+    /// Open connections (CLIENT LIST)
+    pub fn clients(&self) -> &ClientRegistry {
+        &self.clients
+    }
+    
+    /// Count a dispatched command
+    pub fn record_command(&self) {
+        self.stats.commands_processed.fetch_add(1, Ordering::Relaxed);
+        self.stats.command_rate.record();
+    }
+    
+    /// Connection and command counters
+    pub fn connection_stats(&self) -> ConnectionStats {
+        ConnectionStats {
+            total_connections: self.stats.connections_received.load(Ordering::Relaxed),
+            connected_clients: self.stats.connected_clients.load(Ordering::Acquire),
+            rejected_connections: self.stats.rejected_connections.load(Ordering::Relaxed),
+            total_commands: self.stats.commands_processed.load(Ordering::Relaxed),
+            ops_per_sec: self.stats.command_rate.per_second(),
+        }
+    }
+}
+
+/// Where a member sorts in a collection scan - a fixed hash, so the order survives
+/// rehashing and doesn't depend on which other members are present
+fn scan_position(member: &[u8]) -> usize {
+    let mut hasher = DefaultHasher::new();
+    hasher.write(member);
+    hasher.finish() as usize
+}
+
+/// One page of a collection scan over `(scan_position, item)` pairs: the items passing
+/// `keep` among the `count` from position `cursor` on, and the position to resume from
+/// (0 once the collection is exhausted)
+/// Members present for the whole scan are returned at least once whatever is added or
+/// removed between calls. Each page is one pass over the collection under its key lock
+fn scan_page<T>(
+    items: impl Iterator<Item = (usize, T)>,
+    cursor: usize,
+    count: usize,
+    keep: impl Fn(&T) -> bool,
+) -> ScanPage<T> {
+    let mut items: Vec<_> = items.filter(|(position, _)| *position >= cursor).collect();
+    
+    // Stop short of the first position that doesn't fit, so its members share a page
+    let next = if items.len() > count {
+        items.select_nth_unstable_by_key(count, |(position, _)| *position);
+        let end = items[count].0;
+        if items[..count].iter().any(|(position, _)| *position < end) {
+            items.retain(|(position, _)| *position < end);
+            end
+        } else {
+            // One position fills the whole page - take all of it
+            items.retain(|(position, _)| *position == end);
+            end.wrapping_add(1)
+        }
+    } else {
+        0
+    };
+    
+    items.sort_unstable_by_key(|(position, _)| *position);
+    (next, items.into_iter().map(|(_, item)| item).filter(|item| keep(item)).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+    use crate::core::sequence::SEQ_BLOCK_SIZE;
+    
+    #[test]
+    fn test_rename_does_not_deadlock() {
+        let dir = tempfile::tempdir().unwrap();
+        let aof = AppendOnlyFile::new(dir.path().join("rename.aof")).unwrap();
+        let state = Arc::new(GlobalState::new(Arc::new(MemTable::with_partitions(16)), aof));
+        state.set(b"a", b"value".to_vec(), None).unwrap();
+        
+        // Opposite directions lock the same two partitions from either side
+        let (done, finished) = mpsc::channel();
+        for (src, dst) in [(b"a", b"b"), (b"b", b"a")] {
+            let (state, done) = (state.clone(), done.clone());
+            std::thread::spawn(move || {
+                for _ in 0..2_000 {
+                    let _ = state.rename(src, dst, false);
+                }
+                done.send(()).unwrap();
+            });
+        }
+        for _ in 0..2 {
+            finished.recv_timeout(Duration::from_secs(10)).expect("RENAME deadlocked");
+        }
+        
+        // The value moved back and forth but was never lost or duplicated
+        let live: Vec<_> = [b"a", b"b"].iter().filter_map(|key| state.get(*key)).collect();
+        assert_eq!(live.len(), 1);
+        assert_eq!(&*live[0], b"value");
+        assert_eq!(state.rename(b"missing", b"a", false), Err("no such key".to_string()));
+        
+        // RENAMENX leaves an existing destination alone
+        state.set(b"c", b"other".to_vec(), None).unwrap();
+        let src: &[u8] = if state.get(b"a").is_some() { b"a" } else { b"b" };
+        assert!(!state.rename(src, b"c", true).unwrap());
+        assert!(state.rename(src, b"c", false).unwrap());
+        assert_eq!(state.get(b"c").as_deref(), Some(b"value".as_slice()));
+    }
+    
+    #[test]
+    fn test_sscan_zscan() {
+        let dir = tempfile::tempdir().unwrap();
+        let state = GlobalState::new(Arc::new(MemTable::new()), AppendOnlyFile::new(dir.path().join("scan.aof")).unwrap());
+        let members: Vec<Vec<u8>> = (0..25).map(|i| format!("m{}", i).into_bytes()).collect();
+        state.sadd(b"s", members.clone()).unwrap();
+        
+        // Walking the cursor to 0 visits every member exactly once
+        let (mut cursor, mut seen) = (0, Vec::new());
+        loop {
+            let (next, page) = state.sscan(b"s", cursor, None, 10).unwrap();
+            seen.extend(page);
+            cursor = next;
+            if cursor == 0 {
+                break;
+            }
+        }
+        seen.sort();
+        let mut expected = members.clone();
+        expected.sort();
+        assert_eq!(seen, expected);
+        
+        // MATCH filters within a page; missing keys are an empty, finished scan
+        let (next, page) = state.sscan(b"s", 0, Some(b"m1*"), 100).unwrap();
+        assert_eq!((next, page.len()), (0, 11));
+        assert_eq!(state.sscan(b"missing", 0, None, 10).unwrap(), (0, Vec::new()));
+        
+        // Members removed and added mid-scan don't cost any that stay
+        let (mut cursor, mut seen, mut pages) = (0, Vec::new(), 0);
+        loop {
+            let (next, page) = state.sscan(b"s", cursor, None, 3).unwrap();
+            seen.extend(page);
+            pages += 1;
+            if pages <= 5 {
+                state.srem(b"s", vec![format!("m{}", pages).into_bytes()]).unwrap();
+                state.sadd(b"s", (0..50).map(|i| format!("n{}-{}", pages, i).into_bytes()).collect()).unwrap();
+            }
+            cursor = next;
+            if cursor == 0 {
+                break;
+            }
+        }
+        let kept = members.iter().filter(|m| state.sismember(b"s", m).unwrap());
+        assert!(kept.clone().count() > 0);
+        assert!(kept.into_iter().all(|m| seen.contains(m)));
+        
+        let scored = members.into_iter().enumerate().map(|(i, m)| (i as f64, m)).collect();
+        state.zadd(b"z", scored, ZAddFlags::default(), false).unwrap();
+        let (mut cursor, mut seen) = (0, Vec::new());
+        loop {
+            let (next, page) = state.zscan(b"z", cursor, None, 10).unwrap();
+            seen.extend(page);
+            cursor = next;
+            if cursor == 0 {
+                break;
+            }
+        }
+        seen.sort_by(|a, b| a.1.total_cmp(&b.1));
+        assert_eq!(seen.len(), 25);
+        assert_eq!(seen[20], (b"m20".to_vec(), 20.0));
+        assert!(state.zscan(b"s", 0, None, 10).is_err());
+    }
+    
+    #[test]
+    fn test_set_store_events() {
+        let dir = tempfile::tempdir().unwrap();
+        let state = GlobalState::new(Arc::new(MemTable::new()), AppendOnlyFile::new(dir.path().join("store.aof")).unwrap());
+        state.set_notify_keyspace_events("EA").unwrap();
+        let mut sub = state.pubsub().subscription();
+        sub.psubscribe(b"__keyevent@0__:*");
+        state.sadd(b"a", vec![b"1".to_vec()]).unwrap();
+        state.sadd(b"b", vec![b"2".to_vec()]).unwrap();
+        assert_eq!(sub.receiver.try_recv().unwrap().channel, b"__keyevent@0__:sadd".to_vec());
+        assert_eq!(sub.receiver.try_recv().unwrap().channel, b"__keyevent@0__:sadd".to_vec());
+        
+        // Nothing stored and nothing removed - no event, nothing logged
+        let offset = state.aof_offset();
+        assert_eq!(state.set_combine_store(SetOp::Inter, b"dst", &[b"a", b"b"]).unwrap(), 0);
+        assert!(sub.receiver.try_recv().is_err());
+        assert_eq!(state.aof_offset(), offset);
+        
+        assert_eq!(state.set_combine_store(SetOp::Union, b"dst", &[b"a", b"b"]).unwrap(), 2);
+        assert_eq!(sub.receiver.try_recv().unwrap().channel, b"__keyevent@0__:sunionstore".to_vec());
+        assert_eq!(state.set_combine_store(SetOp::Inter, b"dst", &[b"a", b"b"]).unwrap(), 0);
+        assert_eq!(sub.receiver.try_recv().unwrap().channel, b"__keyevent@0__:del".to_vec());
+    }
+    
+    #[test]
+    fn test_flush_makes_writes_durable() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("flush.aof");
+        let state = GlobalState::new(Arc::new(MemTable::new()), AppendOnlyFile::new(&path).unwrap());
+        state.set(b"a", b"1".to_vec(), None).unwrap();
+        state.set(b"b", b"2".to_vec(), None).unwrap();
+        
+        state.flush().unwrap();
+        assert_eq!(state.aof_pending_fsync(), 0);
+        let logged = AppendOnlyFile::iter_entries(&path).unwrap().filter_map(Result::ok).count();
+        assert_eq!(logged, 2);
+    }
+    
+    #[test]
+    fn test_restart_replays_before_serving() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("restart.aof");
+        {
+            let state = GlobalState::new(Arc::new(MemTable::new()), AppendOnlyFile::new(&path).unwrap());
+            state.set(b"kept", b"v".to_vec(), None).unwrap();
+            state.set(b"dropped", b"v".to_vec(), None).unwrap();
+            state.delete(b"dropped").unwrap();
+        }
+        
+        // A fresh process sees the persisted data as soon as its state exists
+        let state = GlobalState::new(Arc::new(MemTable::new()), AppendOnlyFile::new(&path).unwrap());
+        assert_eq!(state.recovery_stats().applied, 3);
+        assert_eq!(state.get(b"kept").as_deref(), Some(&b"v"[..]));
+        assert_eq!(state.get(b"dropped"), None);
+    }
+    
+    #[test]
+    fn test_seq_next_survives_restart() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("seq.aof");
+        let open = || {
+            let mem_table = Arc::new(MemTable::new());
+            let snapshots = SnapshotManager::new(dir.path().join("snapshots"), mem_table.clone()).unwrap();
+            GlobalState::with_snapshot_recovery(mem_table, AppendOnlyFile::new(&path).unwrap(), snapshots, |_, _, _| {})
+        };
+        {
+            let state = open();
+            assert_eq!(state.seq_next(b"ids", 1), Ok(1));
+            assert_eq!(state.seq_next(b"ids", 10), Ok(2));
+            assert!(state.seq_next(b"ids", 0).is_err());
+        }
+        
+        // Unused ids of the reserved block are skipped, never reissued
+        let state = open();
+        assert_eq!(state.seq_next(b"ids", 1), Ok(SEQ_BLOCK_SIZE + 1));
+        assert_eq!(state.seq_next(b"ids", SEQ_BLOCK_SIZE - 1), Ok(SEQ_BLOCK_SIZE + 2));
+        
+        // Lowering or dropping the counter doesn't let the next block start over
+        state.delete(b"ids").unwrap();
+        assert_eq!(state.seq_next(b"ids", SEQ_BLOCK_SIZE), Ok(2 * SEQ_BLOCK_SIZE + 1));
+        state.set(b"ids", b"5".to_vec(), None).unwrap();
+        assert_eq!(state.seq_next(b"ids", SEQ_BLOCK_SIZE), Ok(3 * SEQ_BLOCK_SIZE + 1));
+        state.flush_all().unwrap();
+        assert_eq!(state.seq_next(b"ids", SEQ_BLOCK_SIZE), Ok(4 * SEQ_BLOCK_SIZE + 1));
+        assert_eq!(state.evict(EvictionPolicy::AllKeysLru), Ok(Some(b"ids".to_vec())));
+        assert_eq!(state.seq_next(b"ids", SEQ_BLOCK_SIZE), Ok(5 * SEQ_BLOCK_SIZE + 1));
+        
+        // Raising it moves the sequence on
+        state.set(b"ids", b"10000".to_vec(), None).unwrap();
+        assert_eq!(state.seq_next(b"ids", 1), Ok(10_001));
+        
+        // The mark outlives the key across a snapshot and a restart...
+        state.delete(b"ids").unwrap();
+        state.save().unwrap();
+        drop(state);
+        let state = open();
+        assert_eq!(state.get(b"ids"), None);
+        assert_eq!(state.seq_next(b"ids", 1), Ok(11_001));
+        
+        // ...and across a rewrite of the emptied keyspace
+        state.flush_all().unwrap();
+        state.rewrite_aof().unwrap();
+        drop(state);
+        let state = open();
+        assert_eq!(state.seq_next(b"ids", 1), Ok(12_001));
+        
+        state.set(b"text", b"abc".to_vec(), None).unwrap();
+        assert!(state.seq_next(b"text", 1).is_err());
+    }
+    
+    #[test]
+    fn test_snapshot_recovery_replays_aof_tail() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("hybrid.aof");
+        let open = || {
+            let mem_table = Arc::new(MemTable::new());
+            let snapshots = SnapshotManager::new(dir.path().join("snapshots"), mem_table.clone()).unwrap();
+            GlobalState::with_snapshot_recovery(mem_table, AppendOnlyFile::new(&path).unwrap(), snapshots, |_, _, _| {})
+        };
+        {
+            let state = open();
+            state.incr_by(b"counter", 5).unwrap();
+            state.set(b"old", b"v".to_vec(), None).unwrap();
+            state.save().unwrap();
+            state.incr_by(b"counter", 2).unwrap();
+            state.set(b"new", b"v".to_vec(), None).unwrap();
+            state.delete(b"old").unwrap();
+        }
+        
+        // Only the writes after the snapshot are replayed - none twice, none missed
+        let state = open();
+        assert_eq!(state.recovery_stats().applied, 3);
+        assert_eq!(state.get(b"counter").as_deref(), Some(&b"7"[..]));
+        assert!(state.get(b"new").is_some() && state.get(b"old").is_none());
+        
+        // After a rewrite the snapshot no longer lines up and the whole AOF is replayed
+        state.rewrite_aof().unwrap();
+        state.incr_by(b"counter", 1).unwrap();
+        drop(state);
+        let state = open();
+        assert_eq!(state.get(b"counter").as_deref(), Some(&b"8"[..]));
+        assert!(state.get(b"new").is_some() && state.get(b"old").is_none());
+    }
+    
+    #[test]
+    fn test_snapshot_at_end_of_aof_keeps_log() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("end.aof");
+        let open = || {
+            let mem_table = Arc::new(MemTable::new());
+            let snapshots = SnapshotManager::new(dir.path().join("snapshots"), mem_table.clone()).unwrap();
+            GlobalState::with_snapshot_recovery(mem_table, AppendOnlyFile::new(&path).unwrap(), snapshots, |_, _, _| {})
+        };
+        {
+            let state = open();
+            state.set(b"a", b"1".to_vec(), None).unwrap();
+            state.set(b"b", b"2".to_vec(), None).unwrap();
+            state.save().unwrap();
+        }
+        
+        // Nothing to replay after the snapshot, yet new writes must still land at the end
+        {
+            let state = open();
+            assert_eq!(state.recovery_stats().applied, 0);
+            state.set(b"c", b"3".to_vec(), None).unwrap();
+        }
+        let state = open();
+        let aof_only = GlobalState::new(Arc::new(MemTable::new()), AppendOnlyFile::new(dir.path().join("end.aof")).unwrap());
+        for (key, value) in [(&b"a"[..], &b"1"[..]), (b"b", b"2"), (b"c", b"3")] {
+            assert_eq!(state.get(key).as_deref(), Some(value));
+            assert_eq!(aof_only.get(key).as_deref(), Some(value));
+        }
+    }
+    
+    #[test]
+    fn test_warmer_uses_saved_access_order() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("warm.aof");
+        let open = || {
+            let mem_table = Arc::new(MemTable::new());
+            let snapshots = SnapshotManager::new(dir.path().join("snapshots"), mem_table.clone()).unwrap();
+            GlobalState::with_snapshot_recovery(mem_table, AppendOnlyFile::new(&path).unwrap(), snapshots, |_, _, _| {})
+                .with_warmer(Some(2))
+        };
+        {
+            // Without a saved order the warmer scans the keyspace up to its budget
+            let state = open();
+            for key in [&b"a"[..], b"b", b"c"] {
+                state.set(key, b"v".to_vec(), None).unwrap();
+            }
+            assert_eq!(state.warm(), (2, false));
+            
+            // c, then a, are the most recently read
+            std::thread::sleep(Duration::from_millis(5));
+            state.get(b"c");
+            std::thread::sleep(Duration::from_millis(5));
+            state.get(b"a");
+            assert_eq!(state.mem_table.hottest_keys(2), vec![b"a".to_vec(), b"c".to_vec()]);
+            state.save().unwrap();
+        }
+        
+        let state = open();
+        let manager = state.snapshots.clone().unwrap();
+        assert_eq!(manager.load_access_log().unwrap(), Some(vec![b"a".to_vec(), b"c".to_vec()]));
+        assert_eq!(state.warm(), (2, true));
+        
+        // A deleted key is skipped; a damaged log falls back to scanning
+        state.delete(b"c").unwrap();
+        assert_eq!(state.warm(), (1, true));
+        std::fs::write(dir.path().join("snapshots").join("access-order.wdb"), b"junk").unwrap();
+        assert_eq!(state.warm(), (2, false));
+    }
+    
+    #[test]
+    fn test_aof_rewrite() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("rewrite.aof");
+        let state = GlobalState::new(Arc::new(MemTable::new()), AppendOnlyFile::new(&path).unwrap())
+            .with_auto_aof_rewrite(100, 1024);
+        for i in 0..200 {
+            state.set(b"k", i.to_string().into_bytes(), None).unwrap();
+        }
+        state.sadd(b"s", vec![b"m".to_vec()]).unwrap();
+        assert!(state.aof_rewrite_due());
+        let before = state.aof_offset();
+        
+        // The file shrinks, but the offset keeps counting up
+        let size = state.rewrite_aof().unwrap();
+        assert_eq!((state.aof_current_size(), state.aof_base_size()), (size, size));
+        assert!(size < before);
+        assert_eq!(state.aof_offset(), before + size);
+        assert!(!state.aof_rewrite_due());
+        state.set(b"after", b"v".to_vec(), None).unwrap();
+        assert!(state.aof_offset() > before + size);
+        let offset = state.aof_offset();
+        drop(state);
+        
+        let state = GlobalState::new(Arc::new(MemTable::new()), AppendOnlyFile::new(&path).unwrap());
+        assert_eq!(state.recovery_stats().applied, 3);
+        assert_eq!(state.aof_offset(), offset);
+        assert_eq!(state.get(b"k").as_deref(), Some(&b"199"[..]));
+        assert_eq!(state.get(b"after").as_deref(), Some(&b"v"[..]));
+        assert!(state.sismember(b"s", b"m").unwrap());
+    }
+    
+    #[test]
+    fn test_replica_follows_primary() {
+        let dir = tempfile::tempdir().unwrap();
+        let primary_path = dir.path().join("primary.aof");
+        let primary = GlobalState::new(Arc::new(MemTable::new()), AppendOnlyFile::new(&primary_path).unwrap());
+        primary.set(b"before", b"1".to_vec(), None).unwrap();
+        primary.flush().unwrap();
+        
+        let replica = Arc::new(
+            GlobalState::new(Arc::new(MemTable::new()), AppendOnlyFile::new(dir.path().join("replica.aof")).unwrap())
+                .with_replica_of("10.0.0.1:7777".to_string(), primary_path.clone()),
+        );
+        assert!(!replica.primary_link().is_up());
+        replica.spawn_replica_applier();
+        let wait_for = |key: &[u8], value: Option<&[u8]>| {
+            let deadline = Instant::now() + Duration::from_secs(10);
+            while replica.get(key).as_deref() != value || !replica.primary_link().is_up() {
+                assert!(Instant::now() < deadline, "replica never caught up");
+                std::thread::sleep(Duration::from_millis(10));
+            }
+        };
+        wait_for(b"before", Some(b"1"));
+        
+        // New writes stream over, across a rewrite of the primary's log
+        primary.set(b"after", b"2".to_vec(), None).unwrap();
+        primary.delete(b"before").unwrap();
+        primary.rewrite_aof().unwrap();
+        primary.set(b"rewritten", b"3".to_vec(), None).unwrap();
+        primary.flush().unwrap();
+        wait_for(b"rewritten", Some(b"3"));
+        assert_eq!(replica.get(b"after").as_deref(), Some(&b"2"[..]));
+        assert_eq!(replica.get(b"before"), None);
+    }
+    
+    #[test]
+    fn test_memory_limit() {
+        let dir = tempfile::tempdir().unwrap();
+        let aof = AppendOnlyFile::new(dir.path().join("test.aof")).unwrap();
+        let state = GlobalState::new(Arc::new(MemTable::new()), aof)
+            .with_memory_limit(4096, MaxMemoryPolicy::NoEviction);
+        
+        // The write that crosses the limit lands; the next growing write is refused
+        state.set(b"n", b"1".to_vec(), None).unwrap();
+        state.set(b"a", vec![0; 3000], None).unwrap();
+        state.set(b"b", vec![0; 3000], None).unwrap();
+        assert_eq!(state.set(b"c", b"v".to_vec(), None).unwrap_err(), OOM_ERROR);
+        assert!(state.sadd(b"s", vec![b"m".to_vec()]).is_err());
+        assert_eq!(state.incr_by_unsigned(b"n", 1, false).unwrap_err(), OOM_ERROR);
+        
+        // Reads and deletes still work, and deleting frees room
+        assert!(state.get(b"a").is_some());
+        state.delete(b"b").unwrap();
+        state.set(b"c", b"v".to_vec(), None).unwrap();
+        
+        // With eviction the write succeeds by dropping other keys
+        let aof = AppendOnlyFile::new(dir.path().join("evict.aof")).unwrap();
+        let state = GlobalState::new(Arc::new(MemTable::new()), aof)
+            .with_memory_limit(4096, MaxMemoryPolicy::Evict(EvictionPolicy::AllKeysLru));
+        state.set(b"a", vec![0; 3000], None).unwrap();
+        state.set(b"b", vec![0; 3000], None).unwrap();
+        state.set(b"c", b"v".to_vec(), None).unwrap();
+        assert!(state.used_memory() <= 4096 + 100);
+        assert!(state.get(b"c").is_some());
+    }
+    
+    #[test]
+    fn test_size_limits_reject_before_write() {
+        let dir = tempfile::tempdir().unwrap();
+        let aof = AppendOnlyFile::new(dir.path().join("limits.aof")).unwrap();
+        let state = GlobalState::new(Arc::new(MemTable::new()), aof).with_size_limits(4, 8);
+        state.set(b"k", b"small".to_vec(), None).unwrap();
+        
+        assert_eq!(state.set(b"k", vec![0; 9], None), Err("value too large".to_string()));
+        assert_eq!(state.set(b"long-key", b"v".to_vec(), None), Err("key too large".to_string()));
+        
+        // Nothing from a rejected batch is applied
+        let batch = vec![(b"a".to_vec(), b"1".to_vec(), None), (b"b".to_vec(), vec![0; 9], None)];
+        assert!(state.set_batch(batch).is_err());
+        assert_eq!(state.get(b"k").as_deref(), Some(b"small".as_slice()));
+        assert_eq!(state.get(b"a"), None);
+        assert_eq!(state.aof_offset(), state.lock_aof().unwrap().logical_len());
+    }
+    
+    #[test]
+    fn test_set_batch_nx() {
+        let dir = tempfile::tempdir().unwrap();
+        let aof = AppendOnlyFile::new(dir.path().join("msetnx.aof")).unwrap();
+        let state = Arc::new(GlobalState::new(Arc::new(MemTable::with_partitions(16)), aof));
+        let batch = |value: &str| vec![(b"a".to_vec(), value.as_bytes().to_vec(), None), (b"b".to_vec(), value.as_bytes().to_vec(), None)];
+        
+        // Racing batches over the same keys - exactly one lands, whole
+        let winners: usize = (0..8)
+            .map(|i| {
+                let state = state.clone();
+                std::thread::spawn(move || state.set_batch_nx(batch(&i.to_string())).unwrap())
+            })
+            .collect::<Vec<_>>()
+            .into_iter()
+            .map(|thread| usize::from(thread.join().unwrap()))
+            .sum();
+        assert_eq!(winners, 1);
+        assert_eq!(state.get(b"a"), state.get(b"b"));
+        
+        // One existing key is enough to refuse the batch
+        state.delete(b"a").unwrap();
+        assert!(!state.set_batch_nx(batch("x")).unwrap());
+        assert_eq!(state.get(b"a"), None);
     }
 }
@@ -0,0 +1,167 @@
+// Command watchdog - flags commands still running past a threshold, for diagnosing stalls
+
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, PoisonError, Weak};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Watchdog - Reports commands that run longer than a threshold
+/// Each connection owns a Slot it stamps around every command; a background
+/// thread scans the slots and logs stalls. Diagnostics only - nothing is killed
+pub struct Watchdog {
+    // Commands running longer than this are reported
+    threshold: Duration,
+
+    // One slot per live connection (dead ones are pruned on scan)
+    slots: Mutex<Vec<Weak<Slot>>>,
+
+    // Reference point for slot start times
+    epoch: Instant,
+}
+
+/// Slot - One connection's in-flight command, as seen by the watchdog
+pub struct Slot {
+    // Connection the slot belongs to
+    client_id: u64,
+
+    // Microseconds since the watchdog epoch when the command started, plus one (0 = idle)
+    started_us: AtomicU64,
+
+    // Name of the running command
+    command: Mutex<String>,
+
+    // Whether the running command was already reported
+    reported: AtomicBool,
+
+    // Watchdog epoch, copied so begin/end need no back reference
+    epoch: Instant,
+}
+
+/// A command found running past the threshold
+#[derive(Debug, Clone, PartialEq)]
+pub struct Stall {
+    pub client_id: u64,
+    pub command: String,
+    pub elapsed: Duration,
+}
+
+impl Watchdog {
+    /// Create a watchdog reporting commands slower than `threshold`
+    pub fn new(threshold: Duration) -> Self {
+        Self {
+            threshold,
+            slots: Mutex::new(Vec::new()),
+            epoch: Instant::now(),
+        }
+    }
+
+    /// Start the scanning thread - it exits once the watchdog is dropped
+    pub fn spawn(self: &Arc<Self>) {
+        let watchdog = Arc::downgrade(self);
+        let interval = (self.threshold / 4).max(Duration::from_millis(10));
+
+        thread::spawn(move || loop {
+            thread::sleep(interval);
+            let Some(watchdog) = watchdog.upgrade() else {
+                return;
+            };
+            for stall in watchdog.scan() {
+                eprintln!("⚠️ WATCHDOG: client {} stuck in {} for {:?}", stall.client_id, stall.command, stall.elapsed);
+            }
+        });
+    }
+
+    /// Threshold past which a command is reported
+    pub fn threshold(&self) -> Duration {
+        self.threshold
+    }
+
+    /// Slot for a new connection
+    pub fn register(&self, client_id: u64) -> Arc<Slot> {
+        let slot = Arc::new(Slot {
+            client_id,
+            started_us: AtomicU64::new(0),
+            command: Mutex::new(String::new()),
+            reported: AtomicBool::new(false),
+            epoch: self.epoch,
+        });
+        self.slots.lock().unwrap_or_else(PoisonError::into_inner).push(Arc::downgrade(&slot));
+        slot
+    }
+
+    /// Commands newly found running past the threshold (each is reported once)
+    pub fn scan(&self) -> Vec<Stall> {
+        let now = self.epoch.elapsed().as_micros() as u64 + 1;
+        let mut stalls = Vec::new();
+
+        let mut slots = self.slots.lock().unwrap_or_else(PoisonError::into_inner);
+        slots.retain(|slot| {
+            let Some(slot) = slot.upgrade() else {
+                return false;
+            };
+
+            let started = slot.started_us.load(Ordering::Acquire);
+            let elapsed = Duration::from_micros(now.saturating_sub(started));
+            if started != 0 && elapsed > self.threshold && !slot.reported.swap(true, Ordering::AcqRel) {
+                stalls.push(Stall {
+                    client_id: slot.client_id,
+                    command: slot.command.lock().unwrap_or_else(PoisonError::into_inner).clone(),
+                    elapsed,
+                });
+            }
+            true
+        });
+
+        stalls
+    }
+}
+
+impl Slot {
+    /// Mark a command as running
+    pub fn begin(&self, command: &str) {
+        {
+            let mut name = self.command.lock().unwrap_or_else(PoisonError::into_inner);
+            name.clear();
+            name.push_str(command);
+        }
+        self.reported.store(false, Ordering::Release);
+        self.started_us.store(self.epoch.elapsed().as_micros() as u64 + 1, Ordering::Release);
+    }
+
+    /// Mark the command finished - returns its run time if the watchdog reported it
+    pub fn end(&self) -> Option<Duration> {
+        let started = self.started_us.swap(0, Ordering::AcqRel);
+        if !self.reported.load(Ordering::Acquire) || started == 0 {
+            return None;
+        }
+        Some(self.epoch.elapsed().saturating_sub(Duration::from_micros(started - 1)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reports_stalls_once() {
+        let watchdog = Watchdog::new(Duration::from_millis(20));
+        let fast = watchdog.register(1);
+        let slow = watchdog.register(2);
+
+        fast.begin("get");
+        slow.begin("keys");
+        assert_eq!(fast.end(), None);
+        thread::sleep(Duration::from_millis(40));
+
+        let stalls = watchdog.scan();
+        assert_eq!(stalls.len(), 1);
+        assert_eq!((stalls[0].client_id, stalls[0].command.as_str()), (2, "keys"));
+        assert!(watchdog.scan().is_empty());
+        assert!(slow.end().is_some_and(|elapsed| elapsed >= Duration::from_millis(40)));
+
+        // Closed connections drop out of the scan
+        drop(slow);
+        assert!(watchdog.scan().is_empty());
+        assert_eq!(watchdog.slots.lock().unwrap().len(), 1);
+    }
+}
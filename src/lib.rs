@@ -13,8 +13,10 @@ pub mod util;
 // Re-export primary public interface
 pub use core::state::GlobalState;
 pub use storage::memory::MemTable;
-pub use persistence::aof::AppendOnlyFile;
+pub use persistence::aof::{AppendOnlyFile, FsyncPolicy, RecoveryMode};
+pub use persistence::snapshot::SnapshotManager;
 pub use network::tcp::TcpServer;
+pub use storage::gc::GarbageCollector;
 
 pub struct WorkingDB {
     // Global state reference
@@ -25,6 +27,12 @@ pub struct WorkingDB {
     
     // Database configuration
     config: Config,
+    
+    // Reaper for expired keys nobody reads again
+    gc: GarbageCollector,
+    
+    // Background GC thread, while running
+    gc_thread: Option<std::thread::JoinHandle<()>>,
 }
 
 /// Database configuration
@@ -42,11 +50,117 @@ pub struct Config {
     // Memory limit in bytes (0 = no limit)
     pub memory_limit: usize,
     
+    // What writes do at the memory limit (noeviction refuses them with OOM)
+    pub maxmemory_policy: storage::memory::MaxMemoryPolicy,
+    
+    // Lock implementation behind each MemTable partition
+    pub partition_backend: storage::memory::PartitionBackend,
+    
     // Enable persistence
     pub persistence_enabled: bool,
     
-    // GC interval in milliseconds
+    // GC interval in milliseconds (0 = no background GC)
     pub gc_interval_ms: u64,
+    
+    // Random fraction of the GC interval added or taken off each sleep (0.0 = fixed)
+    pub gc_jitter: f64,
+    
+    // Sampled expiry passes per second between GC cycles (0 = full GC cycles only)
+    pub active_expire_hz: u32,
+    
+    // AOF fsync policy
+    pub aof_fsync: FsyncPolicy,
+    
+    // AOF preallocation chunk in bytes (0 = disabled)
+    pub aof_preallocate_bytes: u64,
+    
+    // Roll the AOF over into a new segment file past this many bytes (0 = single file)
+    pub aof_segment_size: u64,
+    
+    // Refuse to start on a corrupt AOF (strict) or salvage what replays (lenient)
+    pub aof_recovery_mode: RecoveryMode,
+    
+    // Rewrite the AOF once it grows this many percent past its last rewritten size (0 = off)
+    pub aof_rewrite_percentage: u64,
+    
+    // Smallest AOF that is automatically rewritten
+    pub aof_rewrite_min_size: u64,
+    
+    // Keyspace notification classes (Redis notify-keyspace-events, "" = off)
+    pub notify_keyspace_events: String,
+    
+    // Maximum simultaneously connected clients (0 = no limit)
+    pub max_connections: usize,
+    
+    // How long deleted keys keep a tombstone against replay resurrection (None = off)
+    pub tombstone_ttl: Option<std::time::Duration>,
+    
+    // Disable Nagle's algorithm on client sockets
+    pub tcp_nodelay: bool,
+    
+    // Idle time before TCP keepalive probes (None = off)
+    pub keepalive: Option<std::time::Duration>,
+    
+    // Largest string key and value accepted (defaults to the AOF format limits)
+    pub max_key_size: usize,
+    pub max_value_size: usize,
+    
+    // Largest bulk-string or memcached data block length a client may declare
+    pub max_bulk_len: usize,
+    
+    // Most elements a client may declare in a command array
+    pub max_multibulk_len: usize,
+    
+    // Allow DEBUG subcommands with effects (DEBUG RELOAD)
+    pub debug_commands_enabled: bool,
+    
+    // DEBUG subcommands answered +OK as no-ops, for clients that probe them on connect
+    pub debug_noop_commands: Vec<String>,
+    
+    // Log every command and reply to stderr (per connection: DEBUG TRACE ON|OFF)
+    pub trace_commands: bool,
+    
+    // ACL file defining users and their command permissions (None = open default user)
+    pub acl_file: Option<std::path::PathBuf>,
+    
+    // Port for the JSON admin protocol, on the same host (None = no admin listener)
+    pub admin_port: Option<u16>,
+    
+    // ACL file for admin users, independent of acl_file (required with admin_port)
+    pub admin_acl_file: Option<std::path::PathBuf>,
+    
+    // Port for the HTTP /healthz and /readyz probes, on the same host (None = off)
+    pub health_port: Option<u16>,
+    
+    // Run as a read-only replica of this primary (host:port; None = primary)
+    pub replica_of: Option<String>,
+    
+    // The primary's AOF a replica follows, on a shared mount (required with `replica_of`)
+    pub replica_aof: Option<std::path::PathBuf>,
+    
+    // Number of logical databases INFO keyspace reports on
+    pub databases: usize,
+    
+    // Keys to pre-touch after recovery, hottest first (None = warmer off)
+    pub warm_keys: Option<usize>,
+    
+    // Compress string values over a size threshold (None = off)
+    pub compression: Option<storage::value::Compression>,
+    
+    // Spill idle strings to a disk log (None = everything stays in memory)
+    pub cold_tier: Option<storage::tiered::ColdTierConfig>,
+    
+    // Log commands running longer than this (None = watchdog off)
+    pub watchdog_threshold: Option<std::time::Duration>,
+    
+    // SLOWLOG records commands taking at least this long (None = off)
+    pub slowlog_threshold: Option<std::time::Duration>,
+    
+    // Most SLOWLOG entries kept
+    pub slowlog_max_len: usize,
+    
+    // LATENCY records command, fsync and GC spikes at least this long (None = off)
+    pub latency_monitor_threshold: Option<std::time::Duration>,
 }
 
 impl Default for Config {
@@ -56,8 +170,44 @@ impl Default for Config {
             port: 7777,
             data_path: std::path::PathBuf::from("./data"),
             memory_limit: 0,
+            maxmemory_policy: storage::memory::MaxMemoryPolicy::NoEviction,
+            partition_backend: storage::memory::PartitionBackend::RwLock,
             persistence_enabled: true,
             gc_interval_ms: 1000,
+            gc_jitter: 0.0,
+            active_expire_hz: 10,
+            aof_fsync: FsyncPolicy::EverySecond,
+            aof_preallocate_bytes: 0,
+            aof_segment_size: 0,
+            aof_recovery_mode: RecoveryMode::Strict,
+            aof_rewrite_percentage: 100,
+            aof_rewrite_min_size: 64 * 1024 * 1024,
+            notify_keyspace_events: String::new(),
+            max_connections: network::tcp::DEFAULT_MAX_CONNECTIONS,
+            tombstone_ttl: None,
+            tcp_nodelay: true,
+            keepalive: Some(network::tcp::DEFAULT_TCP_KEEPALIVE),
+            max_key_size: persistence::aof::MAX_KEY_SIZE,
+            max_value_size: persistence::aof::MAX_VALUE_SIZE,
+            max_bulk_len: network::tcp::DEFAULT_MAX_BULK_LEN,
+            max_multibulk_len: network::tcp::DEFAULT_MAX_MULTIBULK_LEN,
+            debug_commands_enabled: true,
+            debug_noop_commands: core::state::DEFAULT_DEBUG_NOOPS.iter().map(|sub| sub.to_string()).collect(),
+            trace_commands: false,
+            acl_file: None,
+            admin_port: None,
+            admin_acl_file: None,
+            health_port: None,
+            replica_of: None,
+            replica_aof: None,
+            databases: core::state::DEFAULT_DATABASES,
+            warm_keys: None,
+            compression: None,
+            cold_tier: None,
+            watchdog_threshold: None,
+            slowlog_threshold: Some(core::slowlog::DEFAULT_SLOWLOG_THRESHOLD),
+            slowlog_max_len: core::slowlog::DEFAULT_SLOWLOG_MAX_LEN,
+            latency_monitor_threshold: None,
         }
     }
 }
@@ -70,17 +220,97 @@ impl WorkingDB {
     
     /// Create new WorkingDB instance with custom configuration
     pub fn with_config(config: Config) -> Self {
+        let mut aof = AppendOnlyFile::with_fsync_policy(&config.data_path, config.aof_fsync)
+            .unwrap_or_else(|e| {
+                eprintln!("Failed to initialize AOF: {}", e);
+                std::process::exit(1);
+            });
+        aof.set_preallocation(config.aof_preallocate_bytes);
+        aof.set_segment_size(config.aof_segment_size);
+        aof.set_recovery_mode(config.aof_recovery_mode);
+        util::latency::LatencyMonitor::global().set_threshold(config.latency_monitor_threshold);
+        
+        let mut mem_table = MemTable::new()
+            .with_backend(config.partition_backend)
+            .with_tombstone_ttl(config.tombstone_ttl)
+            .with_compression(config.compression);
+        if let Some(cold_tier) = &config.cold_tier {
+            let cold = cold_tier.open().unwrap_or_else(|e| {
+                eprintln!("Failed to open cold tier {}: {}", cold_tier.path.display(), e);
+                std::process::exit(1);
+            });
+            mem_table = mem_table.with_cold_tier(cold);
+        }
+        let mem_table = std::sync::Arc::new(mem_table);
+        let snapshots = SnapshotManager::new(aof.path().with_file_name("snapshots"), mem_table.clone())
+            .unwrap_or_else(|e| {
+                eprintln!("Failed to initialize snapshots: {}", e);
+                std::process::exit(1);
+            });
+        
+        let mem_table_for_gc = mem_table.clone();
+        let state = GlobalState::with_snapshot_recovery(mem_table, aof, snapshots, |_, _, _| {})
+            .with_size_limits(config.max_key_size, config.max_value_size)
+            .with_memory_limit(config.memory_limit, config.maxmemory_policy)
+            .with_watchdog(config.watchdog_threshold)
+            .with_slowlog(config.slowlog_threshold, config.slowlog_max_len)
+            .with_auto_aof_rewrite(config.aof_rewrite_percentage, config.aof_rewrite_min_size)
+            .with_debug_commands(config.debug_commands_enabled, &config.debug_noop_commands)
+            .with_trace_commands(config.trace_commands)
+            .with_databases(config.databases)
+            .with_warmer(config.warm_keys)
+            .with_acl(config.acl_file.as_ref().map_or(Ok(core::acl::Acl::new()), core::acl::Acl::load)
+                .unwrap_or_else(|e| {
+                    eprintln!("Failed to load ACL: {}", e);
+                    std::process::exit(1);
+                }));
+        if state.recovery_failed() && config.aof_recovery_mode == RecoveryMode::Strict {
+            eprintln!("Refusing to start: AOF replay failed in strict recovery mode");
+            std::process::exit(1);
+        }
+        let state = match (&config.replica_of, &config.replica_aof) {
+            (Some(primary), Some(primary_aof)) => state.with_replica_of(primary.clone(), primary_aof.clone()),
+            (Some(_), None) => {
+                eprintln!("Refusing to start: replica_of needs replica_aof, the primary's AOF to follow");
+                std::process::exit(1);
+            }
+            (None, _) => state,
+        };
+        if let Err(e) = state.set_notify_keyspace_events(&config.notify_keyspace_events) {
+            eprintln!("Ignoring notify_keyspace_events: {}", e);
+        }
+        let state = std::sync::Arc::new(state);
+        state.warm();
+        state.spawn_auto_aof_rewrite();
+        state.spawn_replica_applier();
+        
+        let gc = GarbageCollector::new(mem_table_for_gc)
+            .with_jitter(config.gc_jitter)
+            .with_active_expiry(config.active_expire_hz);
+        
         // Initialize with config, but don't start network server yet
         Self {
-            state: std::sync::Arc::new(GlobalState::new(
-                std::sync::Arc::new(MemTable::new()),
-                AppendOnlyFile::new(&config.data_path).unwrap_or_else(|e| {
-                    eprintln!("Failed to initialize AOF: {}", e);
-                    std::process::exit(1);
-                }),
-            )),
+            state,
             server: None,
             config,
+            gc,
+            gc_thread: None,
+        }
+    }
+    
+    /// Start reaping expired keys every `gc_interval_ms` (no-op if running or disabled)
+    pub fn start_gc(&mut self) {
+        if self.gc_thread.is_none() && self.config.gc_interval_ms > 0 {
+            let interval = std::time::Duration::from_millis(self.config.gc_interval_ms);
+            self.gc_thread = Some(self.gc.start_background_gc(interval));
+        }
+    }
+    
+    /// Stop the background GC and wait for its thread
+    pub fn stop_gc(&mut self) {
+        self.gc.stop();
+        if let Some(thread) = self.gc_thread.take() && thread.join().is_err() {
+            eprintln!("GC thread panicked");
         }
     }
     
@@ -92,7 +322,37 @@ impl WorkingDB {
             self.config.host.clone(),
             self.config.port,
             self.state.clone(),
-        );
+        )
+        .with_max_connections(self.config.max_connections)
+        .with_socket_options(self.config.tcp_nodelay, self.config.keepalive)
+        .with_max_bulk_len(self.config.max_bulk_len)
+        .with_max_multibulk_len(self.config.max_multibulk_len);
+        
+        // Admin commands get their own listener and users
+        if let Some(port) = self.config.admin_port {
+            let acl_file = self.config.admin_acl_file.as_ref()
+                .ok_or("admin_port needs admin_acl_file - admin connections are always authenticated")?;
+            let acl = core::acl::Acl::load(acl_file)?;
+            let admin = network::admin::AdminServer::new(self.config.host.clone(), port, self.state.clone(), acl);
+            tokio::spawn(async move {
+                if let Err(e) = admin.run().await {
+                    eprintln!("Admin server error: {}", e);
+                }
+            });
+        }
+        
+        // Liveness and readiness probes for orchestrators
+        if let Some(port) = self.config.health_port {
+            let health = network::health::HealthServer::new(self.config.host.clone(), port, self.state.clone());
+            tokio::spawn(async move {
+                if let Err(e) = health.run().await {
+                    eprintln!("Health server error: {}", e);
+                }
+            });
+        }
+        
+        // Reap expired keys in the background while serving
+        self.start_gc();
         
         // Start server
         println!("Starting WorkingDB on {}:{}", self.config.host, self.config.port);
@@ -113,12 +373,17 @@ impl WorkingDB {
     /// Shutdown the database server
     pub fn shutdown(&mut self) {
         println!("Shutting down WorkingDB");
+        if let Err(e) = self.state.flush() {
+            eprintln!("Failed to flush AOF: {}", e);
+        }
+        self.state.request_shutdown();
         self.server = None;
+        self.stop_gc();
     }
     
     /// Get key from database
     pub fn get(&self, key: &[u8]) -> Option<Vec<u8>> {
-        self.state.get(key)
+        self.state.get(key).map(|value| value.to_vec())
     }
     
     /// Set key in database
@@ -165,4 +430,51 @@ mod tests {
         let result = db.get(key);
         assert_eq!(result, None);
     }
+    
+    #[test]
+    fn test_background_gc_reclaims_unread_keys() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = Config {
+            data_path: dir.path().join("gc.aof"),
+            gc_interval_ms: 10,
+            ..Config::default()
+        };
+        let mut db = WorkingDB::with_config(config);
+        db.start_gc();
+        
+        for i in 0..100 {
+            db.state.set(format!("k{}", i).as_bytes(), vec![0; 100], Some(std::time::Duration::from_millis(20))).unwrap();
+        }
+        assert!(db.state.used_memory() > 0);
+        
+        // Nothing reads the keys - only the background GC can free them
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(5);
+        while db.state.used_memory() > 0 && std::time::Instant::now() < deadline {
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+        assert_eq!(db.state.used_memory(), 0);
+        
+        db.stop_gc();
+        assert!(db.gc_thread.is_none());
+    }
+    
+    #[tokio::test]
+    async fn test_shutdown_stops_server() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = Config {
+            data_path: dir.path().join("shutdown.aof"),
+            ..Config::default()
+        };
+        let db = WorkingDB::with_config(config);
+        db.set(b"key", b"value".to_vec()).unwrap();
+        
+        let server = TcpServer::new("127.0.0.1".to_string(), 0, db.state.clone());
+        let running = tokio::spawn(async move { server.run().await.is_ok() });
+        
+        // SHUTDOWN SAVE snapshots, fsyncs and stops the accept loop
+        db.state.shutdown(true).unwrap();
+        let stopped = tokio::time::timeout(std::time::Duration::from_secs(5), running).await;
+        assert!(stopped.unwrap().unwrap());
+        assert_eq!(std::fs::read_dir(dir.path().join("snapshots")).unwrap().count(), 1);
+    }
 }
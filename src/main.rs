@@ -1,18 +1,65 @@
 use std::path::PathBuf;
 use std::process::exit;
 use std::sync::Arc;
+use std::time::Duration;
 
 // Import core modules from lib.rs
-use workingdb::core::state::GlobalState;
-use workingdb::network::tcp::TcpServer;
-use workingdb::storage::memory::MemTable; // CRITICAL FIX: Fixed casing
-use workingdb::persistence::aof::AppendOnlyFile;
+use workingdb::core::acl::Acl;
+use workingdb::core::ndjson;
+use workingdb::core::slowlog::{DEFAULT_SLOWLOG_MAX_LEN, DEFAULT_SLOWLOG_THRESHOLD};
+use workingdb::core::state::{GlobalState, DEFAULT_DATABASES, DEFAULT_DEBUG_NOOPS};
+use workingdb::network::admin::AdminServer;
+use workingdb::network::health::HealthServer;
+use workingdb::network::tcp::{TcpServer, DEFAULT_MAX_BULK_LEN, DEFAULT_MAX_CONNECTIONS, DEFAULT_MAX_MULTIBULK_LEN, DEFAULT_TCP_KEEPALIVE};
+use workingdb::storage::gc::GarbageCollector;
+use workingdb::storage::memory::{MaxMemoryPolicy, MemTable, PartitionBackend}; // CRITICAL FIX: Fixed casing
+use workingdb::storage::tiered::{ColdTierConfig, DEFAULT_COLD_TIER_IDLE};
+use workingdb::storage::value::{Codec, Compression};
+use workingdb::persistence::aof::{AppendOnlyFile, FsyncPolicy, RecoveryMode, MAX_KEY_SIZE, MAX_VALUE_SIZE};
+use workingdb::persistence::snapshot::SnapshotManager;
+use workingdb::util::latency::LatencyMonitor;
 use workingdb::util::panic::init_panic_handler;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Initialize custom panic handler
     init_panic_handler();
+    
+    // OFFLINE AOF VERIFICATION - NO SERVER
+    let cli: Vec<String> = std::env::args().collect();
+    if cli.get(1).map(String::as_str) == Some("--check-aof") {
+        let Some(path) = cli.get(2) else {
+            eprintln!("Usage: workingdb --check-aof <path>");
+            exit(2);
+        };
+        exit(check_aof(path));
+    }
+    
+    // OFFLINE POINT-IN-TIME READS - NO SERVER
+    if cli.get(1).map(String::as_str) == Some("--as-of") {
+        let (Some(path), Some(Ok(timestamp_ms))) = (cli.get(2), cli.get(3).map(|ts| ts.parse::<u64>())) else {
+            eprintln!("Usage: workingdb --as-of <path> <timestamp_ms> [key...]");
+            exit(2);
+        };
+        exit(read_as_of(path, timestamp_ms, &cli[4..]));
+    }
+    
+    // OFFLINE KEYSPACE EXPORT/IMPORT AS NDJSON - NO SERVER
+    if cli.get(1).map(String::as_str) == Some("--dump-json") {
+        let Some(path) = cli.get(2) else {
+            eprintln!("Usage: workingdb --dump-json <aof path> [output]");
+            exit(2);
+        };
+        exit(dump_json(path, cli.get(3)));
+    }
+    if cli.get(1).map(String::as_str) == Some("--load-json") {
+        let (Some(path), Some(input)) = (cli.get(2), cli.get(3)) else {
+            eprintln!("Usage: workingdb --load-json <aof path> <input>");
+            exit(2);
+        };
+        exit(load_json(path, input));
+    }
+    
     println!("
 ▗▖ ▗▖ ▗▄▖ ▗▄▄▖ ▗▖ ▗▖▗▄▄▄▖▗▖  ▗▖ ▗▄▄▖▗▄▄▄ ▗▄▄▖ 
 ▐▌ ▐▌▐▌ ▐▌▐▌ ▐▌▐▌▗▞▘  █  ▐▛▚▖▐▌▐▌   ▐▌  █▐▌ ▐▌
@@ -30,19 +77,156 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!("🔌 Storage Path: {}", args.data_path.display());
     println!("🌐 Listening on: {}:{}", args.host, args.port);
     
+    // LATENCY MONITOR - BEFORE THE AOF AND GC START REPORTING
+    LatencyMonitor::global().set_threshold(args.latency_monitor_threshold);
+    
     // INITIALIZE CORE STORAGE ENGINE - MEMORY SUBSTRATE
-    let mem_table = Arc::new(MemTable::new()); // CRITICAL FIX: Fixed casing
-    println!("💾 Memory table initialized with {} partitions", mem_table.partition_count());
+    let mut mem_table = MemTable::new()
+        .with_backend(args.partition_backend)
+        .with_tombstone_ttl(args.tombstone_ttl)
+        .with_compression(args.compression); // CRITICAL FIX: Fixed casing
+    if let Some(cold_tier) = &args.cold_tier {
+        match cold_tier.open() {
+            Ok(cold) => mem_table = mem_table.with_cold_tier(cold),
+            Err(e) => {
+                eprintln!("💥 Cannot open cold tier {}: {}", cold_tier.path.display(), e);
+                std::process::exit(1);
+            }
+        }
+        if args.gc_interval.is_none() {
+            eprintln!("⚠️ WORKINGDB_COLD_TIER_PATH needs background GC to spill - nothing will move to disk");
+        }
+        println!("🧊 Idle strings spill to {} after {:?}", cold_tier.path.display(), cold_tier.idle_threshold);
+    }
+    let mem_table = Arc::new(mem_table);
+    println!("💾 Memory table initialized with {} partitions ({} locks)", mem_table.partition_count(), args.partition_backend.name());
     
     // INITIALIZE PERSISTENCE LAYER - DURABILITY ENGINE
-    let aof = AppendOnlyFile::new(&args.data_path)?;
-    println!("📝 Persistence layer active, {} records recovered", aof.replay_count());
+    let mut aof = AppendOnlyFile::with_fsync_policy(&args.data_path, args.aof_fsync)?;
+    aof.set_preallocation(args.aof_preallocate_bytes);
+    aof.set_segment_size(args.aof_segment_size);
+    aof.set_recovery_mode(args.aof_recovery_mode);
+    println!("📝 Persistence layer active (fsync {}, {} recovery)", args.aof_fsync.name(), args.aof_recovery_mode.name());
+    
+    // INITIALIZE SNAPSHOT MANAGER - POINT-IN-TIME BACKUPS
+    let snapshot_dir = aof.path().with_file_name("snapshots");
+    let snapshots = SnapshotManager::new(&snapshot_dir, mem_table.clone())?;
+    println!("📸 Snapshots stored in {}", snapshot_dir.display());
     
     // CREATE GLOBAL STATE - SHARED CONTEXT
-    let state = Arc::new(GlobalState::new(mem_table, aof));
+    // LOAD NEWEST SNAPSHOT + REPLAY AOF PAST IT - CRASH RECOVERY
+    let mem_table_for_gc = mem_table.clone();
+    let state = GlobalState::with_snapshot_recovery(mem_table, aof, snapshots, |replayed, done, total| {
+        println!("⏳ Replaying AOF: {}% ({} entries)", done * 100 / total.max(1), replayed);
+    });
+    let recovery = state.recovery_stats();
+    println!("♻️ Recovered {} records ({} bytes, {} skipped)", recovery.applied, recovery.bytes_read, recovery.skipped);
+    if recovery.truncated_entries > 0 {
+        println!("⚠️ Dropped {} incomplete entries ({} bytes) from AOF tail", recovery.truncated_entries, recovery.truncated_bytes);
+    }
+    if recovery.corrupt_entries > 0 {
+        println!("⚠️ Skipped {} corrupt AOF entries ({} bytes)", recovery.corrupt_entries, recovery.corrupt_bytes);
+    }
+    
+    // STRICT RECOVERY - A CORRUPT AOF NEVER SERVES A PARTIAL KEYSPACE
+    if state.recovery_failed() && args.aof_recovery_mode == RecoveryMode::Strict {
+        eprintln!("💥 AOF replay failed; refusing to start (set WORKINGDB_AOF_RECOVERY=lenient to salvage it)");
+        exit(1);
+    }
+    
+    // ACCESS CONTROL - USERS FROM THE ACL FILE, ELSE THE OPEN DEFAULT USER
+    let acl = match &args.acl_file {
+        Some(path) => Acl::load(path).unwrap_or_else(|e| {
+            eprintln!("💥 {}", e);
+            exit(1);
+        }),
+        None => Acl::new(),
+    };
+    println!("🔐 ACL users: {}", acl.user_names().join(", "));
+    
+    // ADMIN USERS - SEPARATE FILE, NEVER SHARED WITH THE DATA PORT, REQUIRED WITH THE ADMIN PORT
+    let admin_acl = match (&args.admin_acl_file, args.admin_port) {
+        (Some(path), _) => Acl::load(path).unwrap_or_else(|e| {
+            eprintln!("💥 Admin {}", e);
+            exit(1);
+        }),
+        (None, Some(_)) => {
+            eprintln!("💥 WORKINGDB_ADMIN_PORT needs WORKINGDB_ADMIN_ACLFILE - admin connections are always authenticated");
+            exit(1);
+        }
+        (None, None) => Acl::new(),
+    };
+    
+    // REPLICA MODE - CLIENT WRITES REFUSED, PRIMARY STREAM APPLIED
+    let state = match (args.replica_of.clone(), args.replica_aof.clone()) {
+        (Some(primary), Some(primary_aof)) => {
+            println!("🪞 Read-only replica of {}, following {}", primary, primary_aof.display());
+            state.with_replica_of(primary, primary_aof)
+        }
+        (Some(_), None) => {
+            eprintln!("💥 WORKINGDB_REPLICAOF needs WORKINGDB_REPLICAOF_AOF, the primary's AOF to follow");
+            exit(1);
+        }
+        (None, _) => state,
+    };
+    let state = Arc::new(state
+        .with_acl(acl)
+        .with_size_limits(args.max_key_size, args.max_value_size)
+        .with_memory_limit(args.memory_limit, args.maxmemory_policy)
+        .with_watchdog(args.watchdog_threshold)
+        .with_slowlog(args.slowlog_threshold, args.slowlog_max_len)
+        .with_auto_aof_rewrite(args.aof_rewrite_percentage, args.aof_rewrite_min_size)
+        .with_debug_commands(args.debug_commands_enabled, &args.debug_noop_commands)
+        .with_trace_commands(args.trace_commands)
+        .with_databases(args.databases)
+        .with_warmer(args.warm_keys));
+    
+    // WARM THE WORKING SET - HOTTEST KEYS FROM THE LAST SAVE, ELSE A KEYSPACE SCAN
+    if args.warm_keys.is_some() {
+        let (warmed, from_log) = state.warm();
+        println!("🔥 Warmed {} keys ({})", warmed, if from_log { "saved access order" } else { "keyspace scan" });
+    }
+    state.spawn_auto_aof_rewrite();
+    state.spawn_replica_applier();
+    if let Err(e) = state.set_notify_keyspace_events(&args.notify_keyspace_events) {
+        eprintln!("⚠️ Ignoring keyspace notification flags: {}", e);
+    }
+    
+    // START BACKGROUND GC - REAP EXPIRED KEYS NOBODY READS
+    let gc = GarbageCollector::new(mem_table_for_gc)
+        .with_jitter(args.gc_jitter)
+        .with_active_expiry(args.active_expire_hz);
+    let gc_thread = args.gc_interval.map(|interval| gc.start_background_gc(interval));
+    
+    // ADMIN CONTROL PLANE - JSON LINES ON ITS OWN PORT
+    if let Some(port) = args.admin_port {
+        let admin = AdminServer::new(args.host.clone(), port, state.clone(), admin_acl);
+        println!("🛠️ Admin protocol on {}:{}", args.host, port);
+        tokio::spawn(async move {
+            if let Err(e) = admin.run().await {
+                eprintln!("💥 Admin server error: {}", e);
+            }
+        });
+    }
+    
+    // HEALTH PROBES - /healthz AND /readyz OVER PLAIN HTTP
+    if let Some(port) = args.health_port {
+        let health = HealthServer::new(args.host.clone(), port, state.clone());
+        println!("🩺 Health checks on {}:{}", args.host, port);
+        tokio::spawn(async move {
+            if let Err(e) = health.run().await {
+                eprintln!("💥 Health server error: {}", e);
+            }
+        });
+    }
     
     // INITIALIZE NETWORK STACK - PROTOCOL INTERFACE
-    let server = TcpServer::new(args.host, args.port, state.clone());
+    // RECOVERY IS COMPLETE HERE - THE LISTENER ONLY BINDS INSIDE server.run()
+    let server = TcpServer::new(args.host, args.port, state.clone())
+        .with_max_connections(args.max_connections)
+        .with_socket_options(args.tcp_nodelay, args.keepalive)
+        .with_max_bulk_len(args.max_bulk_len)
+        .with_max_multibulk_len(args.max_multibulk_len);
     println!("🚀 Server initialized, ready to process requests");
     
     // START MAIN EXECUTION LOOP - CONNECTION PROCESSING
@@ -52,14 +236,180 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         exit(1);
     }
     
+    // STOP GC BEFORE EXIT
+    gc.stop();
+    if let Some(thread) = gc_thread {
+        let _ = thread.join();
+    }
+    
+    // SERVER RETURNS ONLY AFTER SHUTDOWN - CLEAN EXIT
+    println!("🛑 WorkingDB shut down cleanly");
+    
     Ok(())
 }
 
+// AOF FSCK - EXIT CODE 0 CLEAN/TRUNCATABLE, 1 CORRUPT
+fn check_aof(path: &str) -> i32 {
+    let check = match AppendOnlyFile::check(path) {
+        Ok(check) => check,
+        Err(e) => {
+            eprintln!("💥 Cannot read AOF {}: {}", path, e);
+            return 1;
+        }
+    };
+    
+    println!("🔍 AOF {}: {} valid entries, {} of {} bytes", path, check.valid_entries, check.valid_bytes, check.file_len);
+    match check.corruption_offset {
+        None => {
+            println!("✅ AOF is valid");
+            0
+        }
+        Some(offset) if check.tail_truncatable => {
+            println!("⚠️ Incomplete entry at offset {} - tail can be truncated to {} bytes", offset, check.valid_bytes);
+            0
+        }
+        Some(offset) => {
+            eprintln!("💥 Corruption at offset {} - tail is not cleanly truncatable", offset);
+            1
+        }
+    }
+}
+
+// REPLAY AOF UP TO A TIMESTAMP - PRINT THE REQUESTED KEYS AS THEY STOOD THEN
+fn read_as_of(path: &str, timestamp_ms: u64, keys: &[String]) -> i32 {
+    let view = match AppendOnlyFile::reconstruct_as_of(path, timestamp_ms) {
+        Ok(view) => view,
+        Err(e) => {
+            eprintln!("💥 Cannot reconstruct AOF {}: {}", path, e);
+            return 1;
+        }
+    };
+    
+    println!("🕰️ AOF {} as of {} ms: {} keys", path, timestamp_ms, view.keyspace_summary().total_keys);
+    for key in keys {
+        let Some(type_name) = view.value_type(key.as_bytes()) else {
+            println!("{}: (nil)", key);
+            continue;
+        };
+        let value = match view.get_string(key.as_bytes()) {
+            Ok(Some(bytes)) => format!("{:?}", String::from_utf8_lossy(&bytes)),
+            _ => format!("<{}>", type_name),
+        };
+        match view.ttl(key.as_bytes()) {
+            Some(ttl) => println!("{}: {} (ttl {} ms)", key, value, ttl.as_millis()),
+            None => println!("{}: {}", key, value),
+        }
+    }
+    0
+}
+
+// REPLAY AOF READ-ONLY - WRITE EVERY KEY AS ONE JSON LINE TO A FILE OR STDOUT
+fn dump_json(path: &str, output: Option<&String>) -> i32 {
+    let aof = match AppendOnlyFile::open_readonly(path) {
+        Ok(aof) => aof,
+        Err(e) => {
+            eprintln!("💥 Cannot open AOF {}: {}", path, e);
+            return 1;
+        }
+    };
+    let state = GlobalState::new(Arc::new(MemTable::new()), aof);
+    
+    let written = match output {
+        Some(output) => std::fs::File::create(output)
+            .and_then(|file| ndjson::dump(&state, std::io::BufWriter::new(file))),
+        None => ndjson::dump(&state, std::io::stdout().lock()),
+    };
+    match written {
+        Ok(count) => {
+            eprintln!("📤 Dumped {} keys from {}", count, path);
+            0
+        }
+        Err(e) => {
+            eprintln!("💥 Dump failed: {}", e);
+            1
+        }
+    }
+}
+
+// APPEND NDJSON KEYS TO AN AOF - THE SERVER MUST NOT BE RUNNING ON IT
+fn load_json(path: &str, input: &str) -> i32 {
+    let loaded = AppendOnlyFile::new(path)
+        .map_err(|e| format!("Cannot open AOF {}: {}", path, e))
+        .and_then(|aof| {
+            let file = std::fs::File::open(input).map_err(|e| format!("Cannot open {}: {}", input, e))?;
+            let state = GlobalState::new(Arc::new(MemTable::new()), aof);
+            let count = ndjson::load(&state, std::io::BufReader::new(file))?;
+            state.flush()?;
+            Ok(count)
+        });
+    match loaded {
+        Ok(count) => {
+            println!("📥 Loaded {} keys into {}", count, path);
+            0
+        }
+        Err(e) => {
+            eprintln!("💥 Load failed: {}", e);
+            1
+        }
+    }
+}
+
+// VALUES THIS LARGE ARE COMPRESSED WHEN COMPRESSION IS ON
+const DEFAULT_COMPRESSION_THRESHOLD: usize = 1024;
+
+// BACKGROUND GC CADENCE UNLESS WORKINGDB_GC_INTERVAL_MS SAYS OTHERWISE
+const DEFAULT_GC_INTERVAL_MS: u64 = 1000;
+
+// SAMPLED EXPIRY PASSES PER SECOND BETWEEN GC CYCLES - REDIS hz
+const DEFAULT_ACTIVE_EXPIRE_HZ: u32 = 10;
+
+// AUTO AOF REWRITE DEFAULTS - SAME AS REDIS (100%, 64MB)
+const DEFAULT_AOF_REWRITE_PERCENTAGE: u64 = 100;
+const DEFAULT_AOF_REWRITE_MIN_SIZE: u64 = 64 * 1024 * 1024;
+
 // CLI ARGUMENT STRUCTURE - EXECUTION CONFIG
 struct Args {
     host: String,
     port: u16,
     data_path: PathBuf,
+    notify_keyspace_events: String,
+    max_connections: usize,
+    tombstone_ttl: Option<Duration>,
+    partition_backend: PartitionBackend,
+    max_key_size: usize,
+    max_value_size: usize,
+    max_bulk_len: usize,
+    max_multibulk_len: usize,
+    memory_limit: usize,
+    maxmemory_policy: MaxMemoryPolicy,
+    tcp_nodelay: bool,
+    keepalive: Option<Duration>,
+    debug_commands_enabled: bool,
+    debug_noop_commands: Vec<String>,
+    trace_commands: bool,
+    acl_file: Option<PathBuf>,
+    admin_port: Option<u16>,
+    admin_acl_file: Option<PathBuf>,
+    health_port: Option<u16>,
+    replica_of: Option<String>,
+    replica_aof: Option<PathBuf>,
+    databases: usize,
+    warm_keys: Option<usize>,
+    compression: Option<Compression>,
+    cold_tier: Option<ColdTierConfig>,
+    watchdog_threshold: Option<Duration>,
+    slowlog_threshold: Option<Duration>,
+    slowlog_max_len: usize,
+    latency_monitor_threshold: Option<Duration>,
+    gc_interval: Option<Duration>,
+    gc_jitter: f64,
+    active_expire_hz: u32,
+    aof_rewrite_percentage: u64,
+    aof_rewrite_min_size: u64,
+    aof_segment_size: u64,
+    aof_recovery_mode: RecoveryMode,
+    aof_fsync: FsyncPolicy,
+    aof_preallocate_bytes: u64,
 }
 
 // PARSE COMMAND LINE ARGS - CONFIG EXTRACTION
@@ -73,5 +423,208 @@ fn parse_args() -> Args {
         .map(PathBuf::from)
         .unwrap_or_else(|_| PathBuf::from("./data"));
     
-    Args { host, port, data_path }
+    let notify_keyspace_events = std::env::var("WORKINGDB_NOTIFY_KEYSPACE_EVENTS")
+        .unwrap_or_default();
+    
+    let max_connections = std::env::var("WORKINGDB_MAX_CONNECTIONS")
+        .map(|n| n.parse::<usize>().unwrap_or(DEFAULT_MAX_CONNECTIONS))
+        .unwrap_or(DEFAULT_MAX_CONNECTIONS);
+    
+    // DELETE TOMBSTONES - OPT-IN, 0/UNSET = OFF
+    let tombstone_ttl = std::env::var("WORKINGDB_TOMBSTONE_TTL_MS")
+        .ok()
+        .and_then(|ms| ms.parse::<u64>().ok())
+        .filter(|&ms| ms > 0)
+        .map(Duration::from_millis);
+    
+    // PARTITION LOCKS - RWLOCK (DEFAULT) OR SHARDED FOR READ-HEAVY LOADS
+    let partition_backend = std::env::var("WORKINGDB_PARTITION_BACKEND")
+        .ok()
+        .and_then(|name| PartitionBackend::from_name(&name))
+        .unwrap_or_default();
+    
+    // SIZE LIMITS - DEFAULT TO AOF FORMAT MAXIMUMS
+    let max_key_size = std::env::var("WORKINGDB_MAX_KEY_SIZE")
+        .map(|n| n.parse::<usize>().unwrap_or(MAX_KEY_SIZE))
+        .unwrap_or(MAX_KEY_SIZE);
+    let max_value_size = std::env::var("WORKINGDB_MAX_VALUE_SIZE")
+        .map(|n| n.parse::<usize>().unwrap_or(MAX_VALUE_SIZE))
+        .unwrap_or(MAX_VALUE_SIZE);
+    
+    // PROTOCOL LIMIT - LARGEST DECLARED BULK LENGTH A CLIENT MAY SEND
+    let max_bulk_len = std::env::var("WORKINGDB_MAX_BULK_LEN")
+        .map(|n| n.parse::<usize>().unwrap_or(DEFAULT_MAX_BULK_LEN))
+        .unwrap_or(DEFAULT_MAX_BULK_LEN);
+    
+    // PROTOCOL LIMIT - MOST ELEMENTS A CLIENT MAY DECLARE IN ONE COMMAND
+    let max_multibulk_len = std::env::var("WORKINGDB_MAX_MULTIBULK_LEN")
+        .map(|n| n.parse::<usize>().unwrap_or(DEFAULT_MAX_MULTIBULK_LEN))
+        .unwrap_or(DEFAULT_MAX_MULTIBULK_LEN);
+    
+    // MEMORY LIMIT - BYTES (0/UNSET = OFF), POLICY DEFAULTS TO NOEVICTION
+    let memory_limit = std::env::var("WORKINGDB_MAXMEMORY")
+        .map(|n| n.parse::<usize>().unwrap_or(0))
+        .unwrap_or(0);
+    let maxmemory_policy = std::env::var("WORKINGDB_MAXMEMORY_POLICY")
+        .ok()
+        .and_then(|name| MaxMemoryPolicy::from_name(&name))
+        .unwrap_or_default();
+    
+    // SOCKET TUNING - NODELAY ON, KEEPALIVE SECONDS (0 = OFF)
+    let tcp_nodelay = std::env::var("WORKINGDB_TCP_NODELAY")
+        .map(|v| v != "0" && !v.eq_ignore_ascii_case("no"))
+        .unwrap_or(true);
+    let keepalive = match std::env::var("WORKINGDB_TCP_KEEPALIVE_SECS") {
+        Ok(secs) => secs.parse::<u64>().ok().filter(|&s| s > 0).map(Duration::from_secs),
+        Err(_) => Some(DEFAULT_TCP_KEEPALIVE),
+    };
+    
+    // DEBUG - EFFECTFUL SUBCOMMANDS ON, COMMA-SEPARATED NO-OP ALLOWLIST
+    let debug_commands_enabled = std::env::var("WORKINGDB_DEBUG_COMMANDS")
+        .map(|v| v != "0" && !v.eq_ignore_ascii_case("no"))
+        .unwrap_or(true);
+    let debug_noop_commands = match std::env::var("WORKINGDB_DEBUG_NOOPS") {
+        Ok(list) => list.split(',').map(str::trim).filter(|s| !s.is_empty()).map(String::from).collect(),
+        Err(_) => DEFAULT_DEBUG_NOOPS.iter().map(|sub| sub.to_string()).collect(),
+    };
+    
+    // COMMAND TRACING - LOG EVERY COMMAND AND REPLY TO STDERR
+    let trace_commands = std::env::var("WORKINGDB_TRACE")
+        .map(|v| v == "1" || v.eq_ignore_ascii_case("yes"))
+        .unwrap_or(false);
+    
+    // ACL FILE - USER LINES LIKE "user alice on >secret +@read"
+    let acl_file = std::env::var("WORKINGDB_ACLFILE").ok().map(PathBuf::from);
+    
+    // ADMIN PORT - JSON ADMIN PROTOCOL, UNSET = OFF; ITS USERS COME FROM THEIR OWN ACL FILE
+    let admin_port = std::env::var("WORKINGDB_ADMIN_PORT").ok().and_then(|port| port.parse::<u16>().ok());
+    let admin_acl_file = std::env::var("WORKINGDB_ADMIN_ACLFILE").ok().map(PathBuf::from);
+    
+    // HEALTH PORT - HTTP /healthz AND /readyz, UNSET = OFF
+    let health_port = std::env::var("WORKINGDB_HEALTH_PORT").ok().and_then(|port| port.parse::<u16>().ok());
+    
+    // REPLICA OF - PRIMARY HOST:PORT, UNSET = PRIMARY
+    let replica_of = std::env::var("WORKINGDB_REPLICAOF").ok().filter(|primary| !primary.is_empty());
+    
+    // REPLICATION STREAM - THE PRIMARY'S AOF ON A SHARED MOUNT, READ-ONLY
+    let replica_aof = std::env::var("WORKINGDB_REPLICAOF_AOF").ok().filter(|path| !path.is_empty()).map(PathBuf::from);
+    
+    // LOGICAL DATABASES - HOW MANY INFO KEYSPACE REPORTS
+    let databases = std::env::var("WORKINGDB_DATABASES")
+        .ok()
+        .and_then(|n| n.parse::<usize>().ok())
+        .filter(|&n| n > 0)
+        .unwrap_or(DEFAULT_DATABASES);
+    
+    // STARTUP WARMER - KEY BUDGET, UNSET OR 0 = OFF
+    let warm_keys = std::env::var("WORKINGDB_WARM_KEYS")
+        .ok()
+        .and_then(|n| n.parse::<usize>().ok())
+        .filter(|&n| n > 0);
+    
+    // VALUE COMPRESSION - OPT-IN CODEC, THRESHOLD IN BYTES
+    let compression = std::env::var("WORKINGDB_COMPRESSION")
+        .ok()
+        .and_then(|name| Codec::from_name(&name))
+        .map(|codec| Compression {
+            codec,
+            threshold: std::env::var("WORKINGDB_COMPRESSION_THRESHOLD")
+                .ok()
+                .and_then(|n| n.parse::<usize>().ok())
+                .unwrap_or(DEFAULT_COMPRESSION_THRESHOLD),
+        });
+    
+    // COLD TIER - LOG PATH (UNSET = OFF), IDLE SECONDS BEFORE SPILLING, O_DIRECT
+    let cold_tier = std::env::var("WORKINGDB_COLD_TIER_PATH")
+        .ok()
+        .filter(|path| !path.is_empty())
+        .map(|path| ColdTierConfig {
+            path: PathBuf::from(path),
+            idle_threshold: std::env::var("WORKINGDB_COLD_TIER_IDLE_SECS")
+                .ok()
+                .and_then(|secs| secs.parse::<u64>().ok())
+                .map_or(DEFAULT_COLD_TIER_IDLE, Duration::from_secs),
+            direct: std::env::var("WORKINGDB_COLD_TIER_DIRECT")
+                .map(|v| v == "1" || v.eq_ignore_ascii_case("yes"))
+                .unwrap_or(false),
+        });
+    
+    // SLOW COMMAND WATCHDOG - MILLISECONDS, 0/UNSET = OFF
+    let watchdog_threshold = std::env::var("WORKINGDB_WATCHDOG_MS")
+        .ok()
+        .and_then(|ms| ms.parse::<u64>().ok())
+        .filter(|&ms| ms > 0)
+        .map(Duration::from_millis);
+    
+    // SLOWLOG - MICROSECONDS (NEGATIVE = OFF, 0 = EVERY COMMAND) AND ENTRIES KEPT
+    let slowlog_threshold = match std::env::var("WORKINGDB_SLOWLOG_SLOWER_THAN_US") {
+        Ok(us) => us.parse::<i64>().ok()
+            .map(|us| u64::try_from(us).ok().map(Duration::from_micros))
+            .unwrap_or(Some(DEFAULT_SLOWLOG_THRESHOLD)),
+        Err(_) => Some(DEFAULT_SLOWLOG_THRESHOLD),
+    };
+    let slowlog_max_len = std::env::var("WORKINGDB_SLOWLOG_MAX_LEN")
+        .ok()
+        .and_then(|n| n.parse::<usize>().ok())
+        .unwrap_or(DEFAULT_SLOWLOG_MAX_LEN);
+    
+    // LATENCY MONITOR - MILLISECONDS, 0/UNSET = OFF
+    let latency_monitor_threshold = std::env::var("WORKINGDB_LATENCY_MONITOR_MS")
+        .ok()
+        .and_then(|ms| ms.parse::<u64>().ok())
+        .filter(|&ms| ms > 0)
+        .map(Duration::from_millis);
+    
+    // BACKGROUND GC - INTERVAL IN MILLISECONDS (0 = OFF), JITTER AS A FRACTION OF IT
+    let gc_interval = std::env::var("WORKINGDB_GC_INTERVAL_MS")
+        .map(|ms| ms.parse::<u64>().unwrap_or(DEFAULT_GC_INTERVAL_MS))
+        .unwrap_or(DEFAULT_GC_INTERVAL_MS);
+    let gc_interval = (gc_interval > 0).then(|| Duration::from_millis(gc_interval));
+    let gc_jitter = std::env::var("WORKINGDB_GC_JITTER")
+        .ok()
+        .and_then(|fraction| fraction.parse::<f64>().ok())
+        .unwrap_or(0.0);
+    
+    // ACTIVE EXPIRY - SAMPLED PASSES PER SECOND ON THE GC THREAD (0 = FULL GC CYCLES ONLY)
+    let active_expire_hz = std::env::var("WORKINGDB_ACTIVE_EXPIRE_HZ")
+        .map(|hz| hz.parse::<u32>().unwrap_or(DEFAULT_ACTIVE_EXPIRE_HZ))
+        .unwrap_or(DEFAULT_ACTIVE_EXPIRE_HZ);
+    
+    // AUTO AOF REWRITE - GROWTH PERCENTAGE (0 = OFF) AND MINIMUM SIZE IN BYTES
+    let aof_rewrite_percentage = std::env::var("WORKINGDB_AOF_REWRITE_PERCENTAGE")
+        .map(|n| n.parse::<u64>().unwrap_or(DEFAULT_AOF_REWRITE_PERCENTAGE))
+        .unwrap_or(DEFAULT_AOF_REWRITE_PERCENTAGE);
+    let aof_rewrite_min_size = std::env::var("WORKINGDB_AOF_REWRITE_MIN_SIZE")
+        .map(|n| n.parse::<u64>().unwrap_or(DEFAULT_AOF_REWRITE_MIN_SIZE))
+        .unwrap_or(DEFAULT_AOF_REWRITE_MIN_SIZE);
+    
+    // AOF SEGMENTS - ROLL OVER PAST THIS MANY BYTES (0 = SINGLE FILE)
+    let aof_segment_size = std::env::var("WORKINGDB_AOF_SEGMENT_SIZE")
+        .map(|n| n.parse::<u64>().unwrap_or(0))
+        .unwrap_or(0);
+    
+    // AOF RECOVERY - STRICT REFUSES CORRUPT ENTRIES, LENIENT SKIPS THEM
+    let aof_recovery_mode = std::env::var("WORKINGDB_AOF_RECOVERY")
+        .ok()
+        .and_then(|name| RecoveryMode::from_name(&name))
+        .unwrap_or_default();
+    
+    // AOF FSYNC - ALWAYS, EVERYSEC OR NO (LEAVE IT TO THE OS)
+    let aof_fsync = std::env::var("WORKINGDB_AOF_FSYNC")
+        .ok()
+        .and_then(|name| FsyncPolicy::from_name(&name))
+        .unwrap_or_default();
+    
+    // AOF PREALLOCATION - GROW THE FILE IN CHUNKS OF THIS MANY BYTES (0 = OFF)
+    let aof_preallocate_bytes = std::env::var("WORKINGDB_AOF_PREALLOCATE_BYTES")
+        .map(|n| n.parse::<u64>().unwrap_or(0))
+        .unwrap_or(0);
+    
+    Args {
+        host, port, data_path, notify_keyspace_events, max_connections, tombstone_ttl, partition_backend,
+        max_key_size, max_value_size, max_bulk_len, max_multibulk_len, memory_limit, maxmemory_policy, tcp_nodelay, keepalive, debug_commands_enabled, debug_noop_commands,
+        trace_commands, compression, cold_tier, watchdog_threshold, slowlog_threshold, slowlog_max_len, latency_monitor_threshold, gc_interval, gc_jitter, aof_rewrite_percentage, aof_rewrite_min_size,
+        aof_segment_size, active_expire_hz, acl_file, admin_port, admin_acl_file, health_port, replica_of, replica_aof, databases, warm_keys,
+        aof_recovery_mode, aof_fsync, aof_preallocate_bytes,
+    }
 }
@@ -0,0 +1,292 @@
+// src/network/admin.rs - ADMIN CONTROL PLANE
+// Operational commands on their own port and users, kept off the data port.
+// One JSON object per line each way:
+//   > {"cmd":"config_set","name":"zset-max-listpack-entries","value":64}
+//   < {"ok":true,"result":null}  or  {"ok":false,"error":"..."}
+
+use std::sync::Arc;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, BufReader};
+use tokio::net::TcpListener;
+
+use crate::core::acl::{Acl, User};
+use crate::core::state::GlobalState;
+use crate::network::tcp::TcpConnection;
+use crate::storage::encoding::EncodingConfig;
+use crate::util::glob::glob_match;
+use crate::util::json::Json;
+
+/// Longest request line read - longer ones get an error and the connection is closed
+const MAX_REQUEST_LINE: usize = 64 * 1024;
+
+/// Admin commands and the ACL flags they're checked against (`+@admin`, `-shutdown`, ...)
+const ADMIN_COMMANDS: &[(&str, &[&str])] = &[
+    ("info", &["admin", "readonly"]),
+    ("client_list", &["admin", "readonly"]),
+    ("config_get", &["admin", "readonly"]),
+    ("config_set", &["admin"]),
+    ("bgsave", &["admin"]),
+    ("bgrewriteaof", &["admin"]),
+    ("shutdown", &["admin"]),
+];
+
+/// AdminServer - Listener for the admin protocol
+#[derive(Clone)]
+pub struct AdminServer {
+    // Address to bind
+    host: String,
+    port: u16,
+    
+    // Database state the commands run against
+    state: Arc<GlobalState>,
+    
+    // Admin users - independent of the data port's ACL, always authenticated
+    acl: Arc<Acl>,
+}
+
+/// One admin connection's authentication
+struct AdminSession {
+    state: Arc<GlobalState>,
+    acl: Arc<Acl>,
+    
+    // Authenticated user (None = must auth first)
+    user: Option<Arc<User>>,
+}
+
+impl AdminServer {
+    /// Create admin server - every connection must `auth` as one of `acl`'s users
+    pub fn new(host: String, port: u16, state: Arc<GlobalState>, acl: Acl) -> Self {
+        Self {
+            host,
+            port,
+            state,
+            acl: Arc::new(acl),
+        }
+    }
+    
+    /// Run the admin listener until a shutdown is requested
+    pub async fn run(&self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let addr = format!("{}:{}", self.host, self.port);
+        let listener = TcpListener::bind(&addr).await?;
+        println!("Admin listening on {}", addr);
+        
+        let mut shutdown = self.state.shutdown_signal();
+        loop {
+            let accepted = tokio::select! {
+                accepted = listener.accept() => accepted,
+                _ = shutdown.wait_for(|requested| *requested) => break,
+            };
+            
+            match accepted {
+                Ok((socket, addr)) => {
+                    println!("Admin connection from {}", addr);
+                    let session = AdminSession::new(self.state.clone(), self.acl.clone());
+                    tokio::spawn(async move {
+                        if let Err(e) = session.serve(TcpConnection::new(socket)).await {
+                            eprintln!("Admin connection error: {}: {}", addr, e);
+                        }
+                    });
+                }
+                Err(e) => eprintln!("Admin accept error: {}", e),
+            }
+        }
+        
+        Ok(())
+    }
+}
+
+impl AdminSession {
+    /// Unauthenticated session - unlike the data port, a passwordless `default` user isn't implied
+    fn new(state: Arc<GlobalState>, acl: Arc<Acl>) -> Self {
+        Self { state, acl, user: None }
+    }
+    
+    /// Answer request lines until the peer closes, a line is too long or the server shuts down
+    async fn serve<S: AsyncRead + AsyncWrite + Unpin>(mut self, conn: TcpConnection<S>) -> std::io::Result<()> {
+        let mut conn = BufReader::new(conn);
+        let mut line = Vec::new();
+        
+        loop {
+            line.clear();
+            let n = (&mut conn).take(MAX_REQUEST_LINE as u64 + 1).read_until(b'\n', &mut line).await?;
+            if n == 0 {
+                return Ok(());
+            }
+            if line.len() > MAX_REQUEST_LINE {
+                let response = error_response("request line too long");
+                return conn.get_mut().write_all(format!("{}\n", response).as_bytes()).await;
+            }
+            
+            let (response, close) = self.handle(&line);
+            conn.get_mut().write_all(format!("{}\n", response).as_bytes()).await?;
+            if close {
+                return Ok(());
+            }
+        }
+    }
+    
+    /// Run one request line - returns the response and whether to close the connection
+    fn handle(&mut self, line: &[u8]) -> (Json, bool) {
+        let request = match std::str::from_utf8(line).map_err(|e| e.to_string()).and_then(Json::parse) {
+            Ok(request) => request,
+            Err(e) => return (error_response(&format!("invalid request: {}", e)), false),
+        };
+        
+        let close = request.get("cmd").and_then(Json::as_str) == Some("shutdown");
+        match self.dispatch(&request) {
+            Ok(result) => (Json::Object(vec![("ok".to_string(), Json::Bool(true)), ("result".to_string(), result)]), close),
+            Err(e) => (error_response(&e), false),
+        }
+    }
+    
+    /// Authenticate or run an admin command the session's user is allowed
+    fn dispatch(&mut self, request: &Json) -> Result<Json, String> {
+        let cmd = string_arg(request, "cmd")?;
+        if cmd == "auth" {
+            let user = self.acl.authenticate(string_arg(request, "user")?, string_arg(request, "password")?.as_bytes())
+                .ok_or("invalid username-password pair or user is disabled")?;
+            self.user = Some(user);
+            return Ok(Json::Null);
+        }
+        
+        let &(name, flags) = ADMIN_COMMANDS.iter()
+            .find(|(name, _)| *name == cmd)
+            .ok_or_else(|| format!("unknown command '{}'", cmd))?;
+        let user = self.user.as_ref().ok_or("authentication required")?;
+        if !user.allows(name, flags) {
+            return Err(format!("user '{}' has no permissions to run '{}'", user.name, name));
+        }
+        
+        let state = &self.state;
+        match name {
+            "info" => {
+                let (uptime, ..) = state.get_stats();
+                let (maxmemory, policy) = state.memory_limit();
+                Ok(object(vec![
+                    ("uptime_seconds", Json::Number(uptime.as_secs() as f64)),
+                    ("connected_clients", Json::Number(state.connection_stats().connected_clients as f64)),
+                    ("used_memory", Json::Number(state.used_memory() as f64)),
+                    ("maxmemory", Json::Number(maxmemory as f64)),
+                    ("maxmemory_policy", Json::String(policy.name().to_string())),
+                    ("aof_current_size", Json::Number(state.aof_current_size() as f64)),
+                    ("aof_rewrite_in_progress", Json::Bool(state.aof_rewrite_in_progress())),
+                    ("bgsave_in_progress", Json::Bool(state.bgsave_in_progress())),
+                ]))
+            }
+            "client_list" => {
+                let clients = state.clients().list().into_iter()
+                    .map(|client| object(vec![
+                        ("id", Json::Number(client.id as f64)),
+                        ("addr", Json::String(client.addr.to_string())),
+                        ("age_seconds", Json::Number(client.connected_at.elapsed().as_secs() as f64)),
+                    ]))
+                    .collect();
+                Ok(Json::Array(clients))
+            }
+            "config_get" => {
+                let pattern = match request.get("pattern") {
+                    Some(pattern) => pattern.as_str().ok_or("\"pattern\" must be a string")?,
+                    None => "*",
+                };
+                let pattern = pattern.to_ascii_lowercase();
+                let params = state.encodings().iter()
+                    .filter(|(name, _)| glob_match(pattern.as_bytes(), name.as_bytes()))
+                    .map(|(name, value)| (name.to_string(), Json::Number(value as f64)))
+                    .collect();
+                Ok(Json::Object(params))
+            }
+            "config_set" => {
+                let value = match request.get("value") {
+                    Some(Json::Number(n)) => n.to_string(),
+                    Some(Json::String(text)) => text.clone(),
+                    _ => return Err("missing \"value\"".to_string()),
+                };
+                let (name, value) = EncodingConfig::parse(string_arg(request, "name")?, &value)?;
+                state.encodings().store(name, value);
+                Ok(Json::Null)
+            }
+            "bgsave" => state.bgsave().map(|_| Json::Null),
+            "bgrewriteaof" => state.bgrewriteaof().map(|_| Json::Null),
+            "shutdown" => {
+                let save = match request.get("save") {
+                    Some(Json::Bool(save)) => *save,
+                    Some(_) => return Err("\"save\" must be true or false".to_string()),
+                    None => true,
+                };
+                state.shutdown(save).map(|_| Json::Null)
+            }
+            _ => unreachable!("every ADMIN_COMMANDS entry is handled"),
+        }
+    }
+}
+
+/// String member `name` of a request
+fn string_arg<'a>(request: &'a Json, name: &str) -> Result<&'a str, String> {
+    request.get(name).and_then(Json::as_str).ok_or_else(|| format!("missing \"{}\"", name))
+}
+
+/// JSON object from static member names
+fn object(members: Vec<(&str, Json)>) -> Json {
+    Json::Object(members.into_iter().map(|(name, value)| (name.to_string(), value)).collect())
+}
+
+/// Failed response line
+fn error_response(message: &str) -> Json {
+    object(vec![("ok", Json::Bool(false)), ("error", Json::String(message.to_string()))])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::AsyncWriteExt;
+    use crate::persistence::aof::AppendOnlyFile;
+    use crate::storage::memory::MemTable;
+    
+    #[tokio::test]
+    async fn test_admin_auth_and_permissions() {
+        let dir = tempfile::tempdir().unwrap();
+        let aof = AppendOnlyFile::new(dir.path().join("admin.aof")).unwrap();
+        let state = Arc::new(GlobalState::new(Arc::new(MemTable::new()), aof));
+        let acl = Acl::parse("user default off\nuser ops on >s3cret +@admin -shutdown").unwrap();
+        
+        let (client, server) = tokio::io::duplex(64 * 1024);
+        let session = AdminSession::new(state.clone(), Arc::new(acl));
+        let handler = tokio::spawn(session.serve(TcpConnection::new(server)));
+        
+        let requests = [
+            r#"{"cmd":"info"}"#,
+            r#"{"cmd":"auth","user":"ops","password":"wrong"}"#,
+            r#"{"cmd":"auth","user":"ops","password":"s3cret"}"#,
+            r#"{"cmd":"config_set","name":"zset-max-ziplist-entries","value":64}"#,
+            r#"{"cmd":"config_get","pattern":"zset-*-entries"}"#,
+            r#"{"cmd":"shutdown"}"#,
+            "not json",
+        ];
+        let mut client = BufReader::new(client);
+        let mut responses = Vec::new();
+        for request in requests {
+            client.get_mut().write_all(format!("{}\n", request).as_bytes()).await.unwrap();
+            let mut line = String::new();
+            client.read_line(&mut line).await.unwrap();
+            responses.push(line.trim_end().to_string());
+        }
+        drop(client);
+        handler.await.unwrap().unwrap();
+        
+        assert_eq!(responses, [
+            r#"{"ok":false,"error":"authentication required"}"#,
+            r#"{"ok":false,"error":"invalid username-password pair or user is disabled"}"#,
+            r#"{"ok":true,"result":null}"#,
+            r#"{"ok":true,"result":null}"#,
+            r#"{"ok":true,"result":{"zset-max-listpack-entries":64}}"#,
+            r#"{"ok":false,"error":"user 'ops' has no permissions to run 'shutdown'"}"#,
+            r#"{"ok":false,"error":"invalid request: JSON unexpected character at byte 0"}"#,
+        ]);
+        assert_eq!(state.encodings().get("zset-max-listpack-entries"), Some(64));
+        assert!(!state.is_shutting_down());
+        
+        // Even an open `default` user has to auth first
+        let mut session = AdminSession::new(state.clone(), Arc::new(Acl::new()));
+        let (response, _) = session.handle(br#"{"cmd":"info"}"#);
+        assert_eq!(response.to_string(), r#"{"ok":false,"error":"authentication required"}"#);
+    }
+}
@@ -0,0 +1,307 @@
+// src/network/client.rs - RESP CLIENT
+// Async Redis-protocol client for integration tests and embedders
+
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, BufReader};
+
+use crate::network::tcp::TcpConnection;
+
+/// Most bulk bytes allocated before they arrive - longer replies grow as read
+const BULK_PREALLOC: usize = 64 * 1024;
+
+/// Most aggregate elements allocated before they arrive
+const AGGREGATE_PREALLOC: usize = 1024;
+
+/// Decoded RESP2/RESP3 reply value
+#[derive(Debug, Clone, PartialEq)]
+pub enum RespValue {
+    // +OK
+    Simple(String),
+
+    // -ERR message (also RESP3 blob errors)
+    Error(String),
+
+    // :42
+    Integer(i64),
+
+    // $5\r\nhello
+    Bulk(Vec<u8>),
+
+    // *N / RESP3 sets
+    Array(Vec<RespValue>),
+
+    // $-1, *-1, RESP3 _
+    Null,
+
+    // RESP3 ,3.14
+    Double(f64),
+
+    // RESP3 #t / #f
+    Boolean(bool),
+
+    // RESP3 (big number, kept as text
+    BigNumber(String),
+
+    // RESP3 %N key/value pairs
+    Map(Vec<(RespValue, RespValue)>),
+
+    // RESP3 >N out-of-band push frame
+    Push(Vec<RespValue>),
+}
+
+/// Async client speaking the Redis protocol
+pub struct Client {
+    // Buffered connection to the server
+    conn: BufReader<TcpConnection>,
+}
+
+impl Client {
+    /// Connect to server
+    pub async fn connect(host: &str, port: u16) -> io::Result<Self> {
+        let conn = TcpConnection::connect(host, port).await?;
+
+        Ok(Self {
+            conn: BufReader::new(conn),
+        })
+    }
+
+    /// Send raw command and read its reply
+    pub async fn command(&mut self, args: &[&[u8]]) -> io::Result<RespValue> {
+        // Encode as RESP array of bulk strings
+        let mut request = format!("*{}\r\n", args.len()).into_bytes();
+        for arg in args {
+            request.extend_from_slice(format!("${}\r\n", arg.len()).as_bytes());
+            request.extend_from_slice(arg);
+            request.extend_from_slice(b"\r\n");
+        }
+
+        self.conn.get_mut().write_all(&request).await?;
+        self.read_value().await
+    }
+
+    /// GET key
+    pub async fn get(&mut self, key: &[u8]) -> io::Result<Option<Vec<u8>>> {
+        match self.command(&[b"GET", key]).await? {
+            RespValue::Bulk(value) => Ok(Some(value)),
+            RespValue::Null => Ok(None),
+            other => Err(Self::unexpected(other)),
+        }
+    }
+
+    /// SET key value
+    pub async fn set(&mut self, key: &[u8], value: &[u8]) -> io::Result<()> {
+        match self.command(&[b"SET", key, value]).await? {
+            RespValue::Simple(_) => Ok(()),
+            other => Err(Self::unexpected(other)),
+        }
+    }
+
+    /// DEL key - returns whether the key existed
+    pub async fn del(&mut self, key: &[u8]) -> io::Result<bool> {
+        match self.command(&[b"DEL", key]).await? {
+            RespValue::Integer(n) => Ok(n > 0),
+            other => Err(Self::unexpected(other)),
+        }
+    }
+
+    /// PING - returns the server's reply text
+    pub async fn ping(&mut self) -> io::Result<String> {
+        match self.command(&[b"PING"]).await? {
+            RespValue::Simple(s) => Ok(s),
+            RespValue::Bulk(b) => Ok(String::from_utf8_lossy(&b).into_owned()),
+            other => Err(Self::unexpected(other)),
+        }
+    }
+
+    /// INCR key - returns the new value
+    pub async fn incr(&mut self, key: &[u8]) -> io::Result<i64> {
+        match self.command(&[b"INCR", key]).await? {
+            RespValue::Integer(n) => Ok(n),
+            other => Err(Self::unexpected(other)),
+        }
+    }
+
+    /// Read one reply value from the connection
+    pub fn read_value(&mut self) -> Pin<Box<dyn Future<Output = io::Result<RespValue>> + Send + '_>> {
+        Box::pin(async move {
+            let line = self.read_line().await?;
+            let (kind, body) = line.split_first()
+                .ok_or_else(|| Self::protocol_error("empty reply line"))?;
+            let text = String::from_utf8_lossy(body).into_owned();
+
+            match kind {
+                b'+' => Ok(RespValue::Simple(text)),
+                b'-' => Ok(RespValue::Error(text)),
+                b':' => Ok(RespValue::Integer(Self::parse_len(&text)?)),
+                b'_' => Ok(RespValue::Null),
+                b'#' => Ok(RespValue::Boolean(text == "t")),
+                b',' => text.parse::<f64>()
+                    .map(RespValue::Double)
+                    .map_err(|_| Self::protocol_error("invalid double")),
+                b'(' => Ok(RespValue::BigNumber(text)),
+                b'$' | b'=' | b'!' => {
+                    let len = Self::parse_len(&text)?;
+                    if len < 0 {
+                        return Ok(RespValue::Null);
+                    }
+
+                    // Payload followed by CRLF; a length the server never sends costs at most BULK_PREALLOC
+                    let mut data = Vec::with_capacity((len as usize).saturating_add(2).min(BULK_PREALLOC));
+                    (&mut self.conn).take(len as u64 + 2).read_to_end(&mut data).await?;
+                    if data.len() as u64 != len as u64 + 2 {
+                        return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "connection closed"));
+                    }
+                    data.truncate(len as usize);
+
+                    match kind {
+                        b'!' => Ok(RespValue::Error(String::from_utf8_lossy(&data).into_owned())),
+                        // Verbatim strings carry a 4-byte "txt:" style prefix
+                        b'=' if data.len() >= 4 => Ok(RespValue::Bulk(data[4..].to_vec())),
+                        _ => Ok(RespValue::Bulk(data)),
+                    }
+                }
+                b'*' | b'~' | b'>' => {
+                    let len = Self::parse_len(&text)?;
+                    if len < 0 {
+                        return Ok(RespValue::Null);
+                    }
+
+                    let mut items = Vec::with_capacity((len as usize).min(AGGREGATE_PREALLOC));
+                    for _ in 0..len {
+                        items.push(self.read_value().await?);
+                    }
+
+                    if *kind == b'>' {
+                        Ok(RespValue::Push(items))
+                    } else {
+                        Ok(RespValue::Array(items))
+                    }
+                }
+                b'%' => {
+                    let len = Self::parse_len(&text)?;
+                    let mut pairs = Vec::with_capacity((len.max(0) as usize).min(AGGREGATE_PREALLOC));
+                    for _ in 0..len {
+                        let key = self.read_value().await?;
+                        let value = self.read_value().await?;
+                        pairs.push((key, value));
+                    }
+
+                    Ok(RespValue::Map(pairs))
+                }
+                b'|' => {
+                    // Attributes precede the actual reply - skip them
+                    let len = Self::parse_len(&text)?;
+                    for _ in 0..len * 2 {
+                        self.read_value().await?;
+                    }
+
+                    self.read_value().await
+                }
+                other => Err(Self::protocol_error(&format!("unknown reply type: {}", *other as char))),
+            }
+        })
+    }
+
+    // === PRIVATE HELPERS ===
+
+    /// Read a CRLF-terminated line (without the CRLF)
+    async fn read_line(&mut self) -> io::Result<Vec<u8>> {
+        let mut line = Vec::new();
+        let n = self.conn.read_until(b'\n', &mut line).await?;
+
+        if n == 0 {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "connection closed"));
+        }
+
+        if !line.ends_with(b"\r\n") {
+            return Err(Self::protocol_error("expected CRLF"));
+        }
+
+        line.truncate(line.len() - 2);
+        Ok(line)
+    }
+
+    /// Parse length/integer field
+    fn parse_len(text: &str) -> io::Result<i64> {
+        text.parse::<i64>()
+            .map_err(|_| Self::protocol_error(&format!("invalid integer: {}", text)))
+    }
+
+    /// Build protocol error
+    fn protocol_error(msg: &str) -> io::Error {
+        io::Error::new(io::ErrorKind::InvalidData, msg.to_string())
+    }
+
+    /// Convert unexpected reply (including server errors) into an error
+    fn unexpected(value: RespValue) -> io::Error {
+        match value {
+            RespValue::Error(msg) => io::Error::other(msg),
+            other => Self::protocol_error(&format!("unexpected reply: {:?}", other)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::time::Duration;
+    use tempfile::tempdir;
+
+    use crate::core::state::GlobalState;
+    use crate::network::tcp::TcpServer;
+    use crate::persistence::aof::AppendOnlyFile;
+    use crate::storage::memory::MemTable;
+
+    #[tokio::test]
+    async fn test_client_round_trip() {
+        let dir = tempdir().unwrap();
+        let aof = AppendOnlyFile::new(dir.path().join("client.aof")).unwrap();
+        let state = Arc::new(GlobalState::new(Arc::new(MemTable::new()), aof));
+
+        // Grab a free port for the server
+        let port = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        let server = TcpServer::new("127.0.0.1".to_string(), port, state);
+        tokio::spawn(async move { server.run().await });
+
+        // Retry until the listener is up
+        let mut client = None;
+        for _ in 0..50 {
+            if let Ok(c) = Client::connect("127.0.0.1", port).await {
+                client = Some(c);
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        let mut client = client.expect("server did not start");
+
+        assert_eq!(client.ping().await.unwrap(), "PONG");
+        client.set(b"key", b"value").await.unwrap();
+        assert_eq!(client.get(b"key").await.unwrap(), Some(b"value".to_vec()));
+        assert_eq!(client.incr(b"counter").await.unwrap(), 1);
+        assert_eq!(client.incr(b"counter").await.unwrap(), 2);
+        assert!(client.del(b"key").await.unwrap());
+        assert_eq!(client.get(b"key").await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_declared_lengths_not_preallocated() {
+        // A server that declares huge replies and then hangs up
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            for reply in [&b"$4000000000\r\nshort"[..], b"*4000000000\r\n:1\r\n"] {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                tokio::io::AsyncWriteExt::write_all(&mut socket, reply).await.unwrap();
+            }
+        });
+
+        for _ in 0..2 {
+            let mut client = Client::connect("127.0.0.1", port).await.unwrap();
+            let err = client.read_value().await.unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+        }
+    }
+}
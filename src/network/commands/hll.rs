@@ -0,0 +1,29 @@
+// HyperLogLog commands - PFADD, PFCOUNT, PFMERGE
+use super::{Builtin, CommandContext, CommandRegistry};
+use crate::network::reply::{RedisError, Reply};
+
+/// Add HyperLogLog commands to the registry
+pub(super) fn register(registry: &mut CommandRegistry) {
+    registry.register(Builtin::new("pfadd", -2, &["write", "fast"], pfadd).keys(1, 1, 1));
+    registry.register(Builtin::new("pfcount", -2, &["readonly"], pfcount).keys(1, -1, 1));
+    registry.register(Builtin::new("pfmerge", -2, &["write"], pfmerge).keys(1, -1, 1));
+}
+
+/// PFADD key [element ...]
+fn pfadd(args: &[Vec<u8>], ctx: &mut CommandContext) -> Result<Reply, RedisError> {
+    let changed = ctx.state.pf_add(&args[0], &args[1..])?;
+    Ok(Reply::Integer(changed as i64))
+}
+
+/// PFCOUNT key [key ...]
+fn pfcount(args: &[Vec<u8>], ctx: &mut CommandContext) -> Result<Reply, RedisError> {
+    let keys: Vec<&[u8]> = args.iter().map(Vec::as_slice).collect();
+    Ok(Reply::Integer(ctx.state.pf_count(&keys)? as i64))
+}
+
+/// PFMERGE destkey [sourcekey ...]
+fn pfmerge(args: &[Vec<u8>], ctx: &mut CommandContext) -> Result<Reply, RedisError> {
+    let sources: Vec<&[u8]> = args[1..].iter().map(Vec::as_slice).collect();
+    ctx.state.pf_merge(&args[0], &sources)?;
+    Ok(Reply::ok())
+}
@@ -0,0 +1,122 @@
+// Keyspace commands - DEL, UNLINK, RENAME, TOUCH, TYPE, MEMORY USAGE, FLUSHDB, ...
+use super::{parse_arg, syntax_error, unknown_subcommand, Builtin, CommandContext, CommandRegistry};
+use crate::network::reply::{RedisError, Reply};
+use crate::storage::value::DEFAULT_MEMORY_SAMPLES;
+
+/// Add keyspace commands to the registry
+pub(super) fn register(registry: &mut CommandRegistry) {
+    registry.register(Builtin::new("del", 2, &["write"], del).keys(1, -1, 1));
+    registry.register(Builtin::new("unlink", -2, &["write", "fast"], unlink).keys(1, -1, 1));
+    registry.register(Builtin::new("rename", 3, &["write"], rename).keys(1, 2, 1));
+    registry.register(Builtin::new("renamenx", 3, &["write", "fast"], renamenx).keys(1, 2, 1));
+    registry.register(Builtin::new("touch", -2, &["readonly", "fast"], touch).keys(1, -1, 1));
+    registry.register(Builtin::new("randomkey", 1, &["readonly"], randomkey));
+    registry.register(Builtin::new("type", 2, &["readonly", "fast"], key_type).keys(1, 1, 1));
+    registry.register(Builtin::new("memory", -2, &["readonly"], memory).keys(2, 2, 1));
+    registry.register(Builtin::new("object", -2, &["readonly"], object).keys(2, 2, 1));
+    registry.register(Builtin::new("flushdb", -1, &["write"], flushdb));
+    registry.register(Builtin::new("flushall", -1, &["write"], flushdb));
+}
+
+/// DEL key
+fn del(args: &[Vec<u8>], ctx: &mut CommandContext) -> Result<Reply, RedisError> {
+    Ok(Reply::Integer(ctx.state.delete(&args[0])? as i64))
+}
+
+/// UNLINK key [key ...]
+fn unlink(args: &[Vec<u8>], ctx: &mut CommandContext) -> Result<Reply, RedisError> {
+    let mut removed = 0;
+    for key in args {
+        match ctx.state.unlink(key) {
+            Ok(true) => removed += 1,
+            Ok(false) => {}
+            Err(e) => {
+                // Keys unlinked before the failure still count for WAIT
+                if removed > 0 {
+                    ctx.last_write_offset = ctx.state.aof_offset();
+                }
+                return Err(e.into());
+            }
+        }
+    }
+    Ok(Reply::Integer(removed))
+}
+
+/// RENAME key newkey
+fn rename(args: &[Vec<u8>], ctx: &mut CommandContext) -> Result<Reply, RedisError> {
+    ctx.state.rename(&args[0], &args[1], false)?;
+    Ok(Reply::ok())
+}
+
+/// RENAMENX key newkey
+fn renamenx(args: &[Vec<u8>], ctx: &mut CommandContext) -> Result<Reply, RedisError> {
+    Ok(Reply::Integer(ctx.state.rename(&args[0], &args[1], true)? as i64))
+}
+
+/// TOUCH key [key ...]
+fn touch(args: &[Vec<u8>], ctx: &mut CommandContext) -> Result<Reply, RedisError> {
+    let touched = args.iter()
+        .filter(|key| ctx.state.touch(key))
+        .count();
+    Ok(Reply::Integer(touched as i64))
+}
+
+/// RANDOMKEY
+fn randomkey(_args: &[Vec<u8>], ctx: &mut CommandContext) -> Result<Reply, RedisError> {
+    Ok(Reply::optional_bulk(ctx.state.random_key()))
+}
+
+/// TYPE key
+fn key_type(args: &[Vec<u8>], ctx: &mut CommandContext) -> Result<Reply, RedisError> {
+    let name = ctx.state.value_type(&args[0]).unwrap_or("none");
+    Ok(Reply::Simple(name.to_string()))
+}
+
+/// MEMORY USAGE key [SAMPLES count]
+fn memory(args: &[Vec<u8>], ctx: &mut CommandContext) -> Result<Reply, RedisError> {
+    if !args[0].eq_ignore_ascii_case(b"USAGE") {
+        return Err(unknown_subcommand(&args[0]));
+    }
+
+    let samples = match &args[1..] {
+        [_] => DEFAULT_MEMORY_SAMPLES,
+        // SAMPLES 0 measures every element of a collection
+        [_, option, count] if option.eq_ignore_ascii_case(b"SAMPLES") => parse_arg::<usize>(count)?,
+        _ => return Err(syntax_error()),
+    };
+
+    match ctx.state.memory_usage(&args[1], samples) {
+        Some(bytes) => Ok(Reply::Integer(bytes as i64)),
+        None => Ok(Reply::Nil),
+    }
+}
+
+/// OBJECT FREQ | ENCODING key
+fn object(args: &[Vec<u8>], ctx: &mut CommandContext) -> Result<Reply, RedisError> {
+    match args {
+        [sub, key] if sub.eq_ignore_ascii_case(b"ENCODING") => {
+            ctx.state.object_encoding(key)
+                .map(|encoding| Reply::Bulk(encoding.as_bytes().to_vec()))
+                .ok_or_else(|| RedisError::from("no such key"))
+        }
+        [sub, key] if sub.eq_ignore_ascii_case(b"FREQ") => {
+            ctx.state.access_frequency(key)
+                .map(|freq| Reply::Integer(freq as i64))
+                .ok_or_else(|| RedisError::from("no such key"))
+        }
+        _ => Err(unknown_subcommand(&args[0])),
+    }
+}
+
+/// FLUSHDB / FLUSHALL [ASYNC|SYNC]
+fn flushdb(args: &[Vec<u8>], ctx: &mut CommandContext) -> Result<Reply, RedisError> {
+    // ASYNC/SYNC accepted for compatibility, the flush is always synchronous
+    match args {
+        [] => {}
+        [mode] if mode.eq_ignore_ascii_case(b"ASYNC") || mode.eq_ignore_ascii_case(b"SYNC") => {}
+        _ => return Err(syntax_error()),
+    }
+
+    ctx.state.flush_all()?;
+    Ok(Reply::ok())
+}
@@ -0,0 +1,83 @@
+// List commands - LPUSH, RPUSH, LRANGE, LLEN, LPOS, LINSERT, LSET
+use super::{parse_arg, syntax_error, Builtin, CommandContext, CommandRegistry};
+use crate::network::reply::{RedisError, Reply};
+
+/// Add list commands to the registry
+pub(super) fn register(registry: &mut CommandRegistry) {
+    registry.register(Builtin::new("lpush", -3, &["write", "fast"], |args, ctx| push(args, ctx, true)).keys(1, 1, 1));
+    registry.register(Builtin::new("rpush", -3, &["write", "fast"], |args, ctx| push(args, ctx, false)).keys(1, 1, 1));
+    registry.register(Builtin::new("lrange", 4, &["readonly"], lrange).keys(1, 1, 1));
+    registry.register(Builtin::new("llen", 2, &["readonly", "fast"], llen).keys(1, 1, 1));
+    registry.register(Builtin::new("lpos", -3, &["readonly"], lpos).keys(1, 1, 1));
+    registry.register(Builtin::new("linsert", 5, &["write"], linsert).keys(1, 1, 1));
+    registry.register(Builtin::new("lset", 4, &["write"], lset).keys(1, 1, 1));
+}
+
+/// LPUSH / RPUSH key element [element ...]
+fn push(args: &[Vec<u8>], ctx: &mut CommandContext, head: bool) -> Result<Reply, RedisError> {
+    let len = ctx.state.push(&args[0], args[1..].to_vec(), head)?;
+    Ok(Reply::Integer(len as i64))
+}
+
+/// LRANGE key start stop
+fn lrange(args: &[Vec<u8>], ctx: &mut CommandContext) -> Result<Reply, RedisError> {
+    let start = parse_arg::<i64>(&args[1])?;
+    let stop = parse_arg::<i64>(&args[2])?;
+    Ok(Reply::bulk_array(ctx.state.list_range(&args[0], start, stop)?))
+}
+
+/// LLEN key
+fn llen(args: &[Vec<u8>], ctx: &mut CommandContext) -> Result<Reply, RedisError> {
+    Ok(Reply::Integer(ctx.state.list_len(&args[0])? as i64))
+}
+
+/// LPOS key element [RANK rank] [COUNT num-matches]
+fn lpos(args: &[Vec<u8>], ctx: &mut CommandContext) -> Result<Reply, RedisError> {
+    let mut rank = 1;
+    let mut count = None;
+
+    for option in args[2..].chunks(2) {
+        let [name, value] = option else {
+            return Err(syntax_error());
+        };
+        match name.to_ascii_uppercase().as_slice() {
+            b"RANK" => {
+                rank = parse_arg::<i64>(value)?;
+                if rank == 0 {
+                    return Err(RedisError::from(
+                        "RANK can't be zero: use 1 to start from the first match, 2 from the second ... or use negative to start from the end of the list",
+                    ));
+                }
+            }
+            b"COUNT" => {
+                let n = parse_arg::<i64>(value)?;
+                count = Some(usize::try_from(n).map_err(|_| RedisError::from("COUNT can't be negative"))?);
+            }
+            _ => return Err(syntax_error()),
+        }
+    }
+
+    // Without COUNT the reply is a single index (or nil)
+    let positions = ctx.state.list_pos(&args[0], &args[1], rank, count.unwrap_or(1))?;
+    Ok(match count {
+        Some(_) => Reply::Array(positions.into_iter().map(|i| Reply::Integer(i as i64)).collect()),
+        None => positions.first().map_or(Reply::Nil, |&i| Reply::Integer(i as i64)),
+    })
+}
+
+/// LINSERT key BEFORE|AFTER pivot element
+fn linsert(args: &[Vec<u8>], ctx: &mut CommandContext) -> Result<Reply, RedisError> {
+    let before = match args[1].to_ascii_uppercase().as_slice() {
+        b"BEFORE" => true,
+        b"AFTER" => false,
+        _ => return Err(syntax_error()),
+    };
+    Ok(Reply::Integer(ctx.state.list_insert(&args[0], before, &args[2], &args[3])?))
+}
+
+/// LSET key index element
+fn lset(args: &[Vec<u8>], ctx: &mut CommandContext) -> Result<Reply, RedisError> {
+    let index = parse_arg::<i64>(&args[1])?;
+    ctx.state.list_set(&args[0], index, &args[2])?;
+    Ok(Reply::ok())
+}
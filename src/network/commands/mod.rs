@@ -0,0 +1,650 @@
+// Redis command table - every command is a Command looked up by name
+use std::collections::HashMap;
+use std::sync::{Arc, OnceLock};
+
+use crate::core::acl::User;
+use crate::core::pubsub::Subscription;
+use crate::core::replication::Role;
+use crate::core::state::GlobalState;
+use crate::network::reply::{RedisError, Reply};
+
+mod hll;
+mod keys;
+mod lists;
+mod pubsub;
+mod server;
+mod sets;
+mod strings;
+mod zsets;
+
+/// Commands a RESP2 connection may still send while subscribed
+const SUBSCRIBE_MODE_COMMANDS: &[&str] = &["subscribe", "psubscribe", "unsubscribe", "punsubscribe", "ping", "quit", "reset"];
+
+/// Command - one entry of the command table
+pub trait Command: Send + Sync {
+    /// Lowercase command name
+    fn name(&self) -> &'static str;
+
+    /// Redis arity: N = exactly N arguments, -N = at least N (both count the name)
+    fn arity(&self) -> i32;
+
+    /// Redis command flags ("write", "readonly", "admin", "pubsub", ...)
+    fn flags(&self) -> &'static [&'static str];
+    
+    /// Which arguments are keys (COMMAND GETKEYS, cluster routing)
+    fn key_spec(&self) -> KeySpec {
+        KeySpec::None
+    }
+
+    /// Run the command - `args` excludes the command name
+    fn execute(&self, args: &[Vec<u8>], ctx: &mut CommandContext) -> Result<Reply, RedisError>;
+}
+
+/// Per-connection state commands run against
+pub struct CommandContext {
+    // Shared database state
+    pub state: Arc<GlobalState>,
+
+    // Connection id, unique for the server's lifetime
+    pub client_id: u64,
+    
+    // AOF offset of this connection's last write (for WAIT)
+    pub last_write_offset: u64,
+
+    // Pub/sub subscriptions (created on first SUBSCRIBE)
+    pub subscription: Option<Subscription>,
+    
+    // Log each command and reply on this connection (DEBUG TRACE ON|OFF)
+    pub trace: bool,
+    
+    // Authenticated user (None = only no-auth commands until AUTH succeeds)
+    pub user: Option<Arc<User>>,
+    
+    // RESP version negotiated with HELLO (2 until then)
+    pub protocol: u8,
+}
+
+/// Work a command hands back to the connection loop because it has to wait
+#[derive(Debug, Clone, PartialEq)]
+pub enum Blocking {
+    // WAIT - until `numreplicas` replicas ack the last write (timeout 0 = forever)
+    Replicas { numreplicas: usize, timeout_ms: u64 },
+    
+    // WAITAOF - until the last write is fsynced locally and acked by `numreplicas` replicas
+    Aof { numlocal: usize, numreplicas: usize, timeout_ms: u64 },
+}
+
+/// Where a command's key arguments are - positions count the command name as 0
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeySpec {
+    // No key arguments
+    None,
+    
+    // Keys from `first` to `last` every `step` (negative `last` counts from the end, -1 = last)
+    Range { first: i32, last: i32, step: i32 },
+    
+    // First argument is a key count and that many keys follow (SINTERCARD)
+    NumKeys,
+}
+
+/// Handler function behind a built-in command
+pub type Handler = fn(&[Vec<u8>], &mut CommandContext) -> Result<Reply, RedisError>;
+
+/// Built-in command - table metadata plus a handler function
+pub struct Builtin {
+    name: &'static str,
+    arity: i32,
+    flags: &'static [&'static str],
+    key_spec: KeySpec,
+    handler: Handler,
+}
+
+/// CommandRegistry - Name -> Command table built once at startup
+pub struct CommandRegistry {
+    commands: HashMap<&'static str, Box<dyn Command>>,
+}
+
+impl CommandContext {
+    /// Create context for connection `client_id` (from `GlobalState::try_open_connection`)
+    pub fn new(state: Arc<GlobalState>, client_id: u64) -> Self {
+        Self {
+            client_id,
+            trace: state.trace_commands(),
+            user: state.acl().initial_user(),
+            state,
+            last_write_offset: 0,
+            subscription: None,
+            protocol: 2,
+        }
+    }
+    
+    /// Whether the connection is in subscribe mode - RESP2 subscribers can't tell
+    /// ordinary replies from messages, so they only get `SUBSCRIBE_MODE_COMMANDS`
+    pub fn in_subscribe_mode(&self) -> bool {
+        self.subscription.is_some() && self.protocol < 3
+    }
+}
+
+impl KeySpec {
+    /// Key arguments of a call - `args` excludes the command name
+    /// Positions past the end are ignored; a bad key count yields an error
+    pub fn keys<'a>(&self, args: &'a [Vec<u8>]) -> Result<Vec<&'a [u8]>, RedisError> {
+        match *self {
+            KeySpec::None => Ok(Vec::new()),
+            KeySpec::Range { first, last, step } => {
+                let argc = args.len() as i32 + 1;
+                let last = if last < 0 { argc + last } else { last.min(argc - 1) };
+                Ok((first..=last).step_by(step.max(1) as usize)
+                    .filter_map(|position| args.get(position as usize - 1))
+                    .map(Vec::as_slice)
+                    .collect())
+            }
+            KeySpec::NumKeys => {
+                let numkeys = args.first()
+                    .and_then(|count| parse_arg::<usize>(count).ok())
+                    .filter(|&numkeys| numkeys > 0 && numkeys < args.len())
+                    .ok_or_else(|| RedisError::from("Invalid arguments specified for command"))?;
+                Ok(args[1..=numkeys].iter().map(Vec::as_slice).collect())
+            }
+        }
+    }
+    
+    /// Legacy COMMAND triple (first, last, step); 0, 0, 0 when keys move with the arguments
+    pub fn positions(&self) -> (i32, i32, i32) {
+        match *self {
+            KeySpec::Range { first, last, step } => (first, last, step),
+            KeySpec::None | KeySpec::NumKeys => (0, 0, 0),
+        }
+    }
+}
+
+impl Builtin {
+    /// Describe a built-in command without key arguments
+    pub const fn new(name: &'static str, arity: i32, flags: &'static [&'static str], handler: Handler) -> Self {
+        Self { name, arity, flags, key_spec: KeySpec::None, handler }
+    }
+    
+    /// Keys at positions `first..=last` every `step` (negative `last` counts from the end)
+    pub const fn keys(mut self, first: i32, last: i32, step: i32) -> Self {
+        self.key_spec = KeySpec::Range { first, last, step };
+        self
+    }
+    
+    /// Keys given by a leading key count
+    pub const fn numkeys(mut self) -> Self {
+        self.key_spec = KeySpec::NumKeys;
+        self
+    }
+}
+
+impl Command for Builtin {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn arity(&self) -> i32 {
+        self.arity
+    }
+
+    fn flags(&self) -> &'static [&'static str] {
+        self.flags
+    }
+    
+    fn key_spec(&self) -> KeySpec {
+        self.key_spec
+    }
+
+    fn execute(&self, args: &[Vec<u8>], ctx: &mut CommandContext) -> Result<Reply, RedisError> {
+        (self.handler)(args, ctx)
+    }
+}
+
+impl CommandRegistry {
+    /// Create empty registry
+    pub fn new() -> Self {
+        Self { commands: HashMap::new() }
+    }
+
+    /// Registry holding every built-in command
+    pub fn with_builtins() -> Self {
+        let mut registry = Self::new();
+        strings::register(&mut registry);
+        keys::register(&mut registry);
+        lists::register(&mut registry);
+        sets::register(&mut registry);
+        hll::register(&mut registry);
+        zsets::register(&mut registry);
+        pubsub::register(&mut registry);
+        server::register(&mut registry);
+        registry
+    }
+
+    /// Process-wide registry of built-in commands
+    pub fn global() -> &'static Self {
+        static REGISTRY: OnceLock<CommandRegistry> = OnceLock::new();
+        REGISTRY.get_or_init(Self::with_builtins)
+    }
+
+    /// Add a command, replacing any command with the same name
+    pub fn register(&mut self, command: impl Command + 'static) {
+        self.commands.insert(command.name(), Box::new(command));
+    }
+
+    /// Look up a command by lowercase name
+    pub fn get(&self, name: &str) -> Option<&dyn Command> {
+        self.commands.get(name).map(|command| command.as_ref())
+    }
+
+    /// Number of registered commands
+    pub fn len(&self) -> usize {
+        self.commands.len()
+    }
+
+    /// Whether no commands are registered
+    pub fn is_empty(&self) -> bool {
+        self.commands.is_empty()
+    }
+
+    /// All registered commands, in no particular order
+    pub fn iter(&self) -> impl Iterator<Item = &dyn Command> {
+        self.commands.values().map(|command| command.as_ref())
+    }
+
+    /// Check arity, the connection's permissions and (for writes) the node's role,
+    /// then run a command; write commands update the WAIT offset
+    pub fn dispatch(&self, name: &str, args: &[Vec<u8>], ctx: &mut CommandContext) -> Result<Reply, RedisError> {
+        let command = self.get(&name.to_ascii_lowercase())
+            .ok_or_else(|| RedisError::Err(format!("unknown command '{}'", name)))?;
+
+        let argc = args.len() as i32 + 1;
+        let arity = command.arity();
+        if (arity > 0 && argc != arity) || argc < arity.abs() {
+            return Err(wrong_arity(command.name()));
+        }
+        
+        let flags = command.flags();
+        if !flags.contains(&"no-auth") {
+            let user = ctx.user.as_ref()
+                .ok_or_else(|| RedisError::NoAuth("Authentication required.".to_string()))?;
+            if !user.allows(command.name(), flags) {
+                return Err(RedisError::NoPerm(format!(
+                    "User {} has no permissions to run the '{}' command", user.name, command.name()
+                )));
+            }
+        }
+        
+        if ctx.in_subscribe_mode() && !SUBSCRIBE_MODE_COMMANDS.contains(&command.name()) {
+            return Err(RedisError::Err(format!(
+                "Can't execute '{}': only (P)SUBSCRIBE / (P)UNSUBSCRIBE / PING / QUIT / RESET are allowed in this context",
+                command.name()
+            )));
+        }
+
+        if flags.contains(&"write") && ctx.state.role() == Role::Replica {
+            return Err(RedisError::ReadOnly("You can't write against a read only replica.".to_string()));
+        }
+        
+        // Only a write that reached the AOF moves the WAIT offset - no-ops leave it alone
+        let before = ctx.state.aof_offset();
+        let reply = command.execute(args, ctx)?;
+        if flags.contains(&"write") && ctx.state.aof_offset() != before {
+            ctx.last_write_offset = ctx.state.aof_offset();
+        }
+        Ok(reply)
+    }
+}
+
+impl Default for CommandRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Error for a command called with the wrong number of arguments
+pub fn wrong_arity(name: &str) -> RedisError {
+    RedisError::Err(format!("wrong number of arguments for '{}' command", name))
+}
+
+/// Parse numeric command argument
+pub fn parse_arg<T: std::str::FromStr>(arg: &[u8]) -> Result<T, RedisError> {
+    std::str::from_utf8(arg)
+        .ok()
+        .and_then(|s| s.parse::<T>().ok())
+        .ok_or_else(|| RedisError::from("value is not an integer or out of range"))
+}
+
+/// Parse SSCAN/ZSCAN arguments after the key: cursor [MATCH pattern] [COUNT count]
+fn parse_scan_args(args: &[Vec<u8>]) -> Result<(usize, Option<&[u8]>, usize), RedisError> {
+    let cursor = std::str::from_utf8(&args[0])
+        .ok()
+        .and_then(|s| s.parse::<usize>().ok())
+        .ok_or_else(|| RedisError::from("invalid cursor"))?;
+    let (mut pattern, mut count) = (None, 10);
+    
+    let mut options = args[1..].iter();
+    while let Some(option) = options.next() {
+        let value = options.next().ok_or_else(syntax_error)?;
+        match option.to_ascii_uppercase().as_slice() {
+            b"MATCH" => pattern = Some(value.as_slice()),
+            b"COUNT" => count = parse_arg::<usize>(value)?,
+            _ => return Err(syntax_error()),
+        }
+    }
+    
+    if count == 0 {
+        return Err(syntax_error());
+    }
+    Ok((cursor, pattern, count))
+}
+
+/// Scan reply: the next cursor, then the page of elements
+fn scan_reply(cursor: usize, items: Vec<Vec<u8>>) -> Reply {
+    Reply::Array(vec![Reply::Bulk(cursor.to_string().into_bytes()), Reply::bulk_array(items)])
+}
+
+/// Error for an unknown subcommand
+fn unknown_subcommand(arg: &[u8]) -> RedisError {
+    RedisError::Err(format!("unknown subcommand '{}'", String::from_utf8_lossy(arg)))
+}
+
+/// Generic syntax error
+fn syntax_error() -> RedisError {
+    RedisError::from("syntax error")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::acl::Acl;
+    use crate::persistence::aof::AppendOnlyFile;
+    use crate::storage::memory::MemTable;
+    
+    /// Fresh state over a temporary AOF, one connection and the builtin registry
+    struct Fixture {
+        _dir: tempfile::TempDir,
+        state: Arc<GlobalState>,
+        ctx: CommandContext,
+        registry: CommandRegistry,
+    }
+    
+    impl Fixture {
+        fn new() -> Self {
+            Self::with_state(|state| state)
+        }
+        
+        /// Fixture whose state is adjusted by `configure` before it's shared
+        fn with_state(configure: impl FnOnce(GlobalState) -> GlobalState) -> Self {
+            let dir = tempfile::tempdir().unwrap();
+            let aof = AppendOnlyFile::new(dir.path().join("commands.aof")).unwrap();
+            let state = Arc::new(configure(GlobalState::new(Arc::new(MemTable::new()), aof)));
+            let ctx = CommandContext::new(state.clone(), state.next_client_id());
+            Self { _dir: dir, state, ctx, registry: CommandRegistry::with_builtins() }
+        }
+        
+        /// Dispatch `name` with string arguments on the fixture's connection
+        fn run(&mut self, name: &str, parts: &[&str]) -> Result<Reply, RedisError> {
+            self.registry.dispatch(name, &args(parts), &mut self.ctx)
+        }
+    }
+    
+    /// Command arguments from strings
+    fn args(parts: &[&str]) -> Vec<Vec<u8>> {
+        parts.iter().map(|p| p.as_bytes().to_vec()).collect()
+    }
+    
+    /// Array of bulk strings
+    fn bulks(parts: &[&str]) -> Reply {
+        Reply::bulk_array(args(parts))
+    }
+
+    #[test]
+    fn test_dispatch() {
+        let mut fx = Fixture::new();
+
+        // Names are case-insensitive; writes advance the WAIT offset
+        assert_eq!(fx.run("SET", &["k", "v"]), Ok(Reply::ok()));
+        let offset = fx.ctx.last_write_offset;
+        assert!(offset > 0);
+        
+        // A no-op write doesn't pick up another connection's offset
+        let mut other = CommandContext::new(fx.state.clone(), fx.state.next_client_id());
+        fx.registry.dispatch("set", &args(&["other", "v"]), &mut other).unwrap();
+        let logged = fx.state.aof_offset();
+        assert_eq!(fx.run("srem", &["missing", "m"]), Ok(Reply::Integer(0)));
+        assert_eq!(fx.run("del", &["missing"]), Ok(Reply::Integer(0)));
+        assert_eq!(fx.state.aof_offset(), logged);
+        assert_eq!(fx.ctx.last_write_offset, offset);
+        fx.registry.dispatch("del", &args(&["other"]), &mut other).unwrap();
+        assert_eq!(fx.run("get", &["k"]), Ok(Reply::SharedBulk(Arc::from(&b"v"[..]))));
+
+        // Arity is enforced before the handler runs
+        assert_eq!(
+            fx.run("get", &[]),
+            Err(RedisError::Err("wrong number of arguments for 'get' command".to_string()))
+        );
+        assert_eq!(
+            fx.run("SADD", &["k", "m"]),
+            Err(RedisError::WrongType)
+        );
+        assert!(fx.run("NOPE", &[]).is_err());
+        
+        // PING with a payload and ECHO reply with bulk strings
+        assert_eq!(fx.run("ping", &[]), Ok(Reply::Simple("PONG".to_string())));
+        assert_eq!(fx.run("ping", &["token"]), Ok(Reply::Bulk(b"token".to_vec())));
+        assert!(fx.run("ping", &["a", "b"]).is_err());
+        assert_eq!(fx.run("echo", &["hi"]), Ok(Reply::Bulk(b"hi".to_vec())));
+        
+        // Allowlisted DEBUG probes are no-ops, unknown ones still fail
+        assert_eq!(fx.run("debug", &["jmap"]), Ok(Reply::ok()));
+        assert!(fx.run("debug", &["segfault"]).is_err());
+        
+        // CLIENT ID is the id the connection was created with
+        assert_eq!(fx.run("client", &["id"]), Ok(Reply::Integer(fx.ctx.client_id as i64)));
+        assert!(fx.run("client", &["kill"]).is_err());
+        
+        // INFO keyspace has a Redis-format line per non-empty database
+        let Ok(Reply::Bulk(info)) = fx.run("info", &[]) else {
+            panic!("INFO should reply with a bulk string");
+        };
+        let info = String::from_utf8(info).unwrap();
+        assert!(info.contains("# Keyspace\r\ndb0:keys=1,expires=0,avg_ttl=0\r\n"));
+        assert!(!info.contains("db1:"));
+    }
+    
+    #[test]
+    fn test_command_getkeys() {
+        let mut fx = Fixture::new();
+        let getkeys = |fx: &mut Fixture, parts: &[&str]| fx.run("command", &[&["getkeys"], parts].concat());
+        
+        // MSET keys sit at every other position; DEL and SINTERSTORE take the rest
+        assert_eq!(getkeys(&mut fx, &["MSET", "a", "1", "b", "2"]), Ok(bulks(&["a", "b"])));
+        assert_eq!(getkeys(&mut fx, &["del", "x"]), Ok(bulks(&["x"])));
+        assert_eq!(getkeys(&mut fx, &["sinterstore", "dst", "s1", "s2"]), Ok(bulks(&["dst", "s1", "s2"])));
+        assert_eq!(getkeys(&mut fx, &["set", "k", "v", "EX", "10"]), Ok(bulks(&["k"])));
+        assert_eq!(getkeys(&mut fx, &["sintercard", "2", "s1", "s2", "LIMIT", "1"]), Ok(bulks(&["s1", "s2"])));
+        
+        assert!(getkeys(&mut fx, &["ping"]).is_err());
+        assert!(getkeys(&mut fx, &["nope", "k"]).is_err());
+        assert!(getkeys(&mut fx, &["get"]).is_err());
+        assert!(getkeys(&mut fx, &["sintercard", "3", "s1"]).is_err());
+        
+        // COMMAND INFO reports the same positions
+        let Ok(Reply::Array(info)) = fx.run("command", &["info", "mset"]) else {
+            panic!("COMMAND INFO should reply with an array");
+        };
+        let Reply::Array(entry) = &info[0] else {
+            panic!("COMMAND INFO entry should be an array");
+        };
+        assert_eq!(entry[3..], [Reply::Integer(1), Reply::Integer(-1), Reply::Integer(2)]);
+    }
+    
+    #[test]
+    fn test_object_encoding_and_config() {
+        let mut fx = Fixture::new();
+        let encoding = |fx: &mut Fixture, key: &str| fx.run("object", &["encoding", key]);
+        let bulk = |s: &str| Ok(Reply::Bulk(s.as_bytes().to_vec()));
+        
+        fx.run("set", &["n", "42"]).unwrap();
+        fx.run("zadd", &["z", "1", "a", "2", "b", "3", "c"]).unwrap();
+        assert_eq!(encoding(&mut fx, "n"), bulk("int"));
+        assert_eq!(encoding(&mut fx, "z"), bulk("listpack"));
+        assert!(encoding(&mut fx, "missing").is_err());
+        
+        // Lowering the threshold changes what's reported for the same value
+        assert_eq!(fx.run("config", &["set", "zset-max-listpack-entries", "2"]), Ok(Reply::ok()));
+        assert_eq!(encoding(&mut fx, "z"), bulk("skiplist"));
+        assert_eq!(
+            fx.run("config", &["get", "zset-max-*-entries"]),
+            Ok(Reply::bulk_array(vec![b"zset-max-listpack-entries".to_vec(), b"2".to_vec()]))
+        );
+        
+        // A bad pair rejects the whole CONFIG SET
+        assert!(fx.run("config", &["set", "zset-max-listpack-entries", "9", "nope", "1"]).is_err());
+        assert!(fx.run("config", &["set", "zset-max-listpack-entries", "9", "set-max-intset-entries", "x"]).is_err());
+        assert_eq!(fx.state.encodings().get("zset-max-listpack-entries"), Some(2));
+    }
+    
+    #[test]
+    fn test_range_queries() {
+        let mut fx = Fixture::new();
+        
+        fx.run("sadd", &["s1", "a", "b", "c"]).unwrap();
+        fx.run("sadd", &["s2", "b", "c", "d"]).unwrap();
+        assert_eq!(fx.run("sintercard", &["2", "s1", "s2"]), Ok(Reply::Integer(2)));
+        assert_eq!(fx.run("sintercard", &["2", "s1", "s2", "LIMIT", "1"]), Ok(Reply::Integer(1)));
+        assert_eq!(fx.run("sintercard", &["2", "s1", "nope"]), Ok(Reply::Integer(0)));
+        assert!(fx.run("sintercard", &["3", "s1", "s2"]).is_err());
+        assert!(fx.run("sintercard", &["0", "s1"]).is_err());
+        
+        fx.run("zadd", &["z", "1", "a", "2", "b", "3", "c", "+inf", "d"]).unwrap();
+        assert_eq!(fx.run("zrangebyscore", &["z", "(1", "3"]), Ok(bulks(&["b", "c"])));
+        assert_eq!(
+            fx.run("zrangebyscore", &["z", "-inf", "+inf", "WITHSCORES", "LIMIT", "2", "5"]),
+            Ok(bulks(&["c", "3", "d", "inf"]))
+        );
+        assert_eq!(fx.run("zrevrangebyscore", &["z", "(3", "-inf", "LIMIT", "0", "1"]), Ok(bulks(&["b"])));
+        assert_eq!(fx.run("zrangebyscore", &["z", "3", "1"]), Ok(bulks(&[])));
+        assert_eq!(
+            fx.run("zrangebyscore", &["z", "x", "1"]),
+            Err(RedisError::Err("min or max is not a float".to_string()))
+        );
+    }
+    
+    #[test]
+    fn test_hello_and_push_frames() {
+        let mut fx = Fixture::new();
+        let confirmation = |kind: &str, name: &str, count| vec![
+            Reply::Bulk(kind.as_bytes().to_vec()),
+            Reply::Bulk(name.as_bytes().to_vec()),
+            Reply::Integer(count),
+        ];
+        
+        // RESP2: HELLO without a version reports the current one, confirmations are arrays
+        let Ok(Reply::Array(fields)) = fx.run("hello", &[]) else {
+            panic!("HELLO under RESP2 should reply with a flat array");
+        };
+        assert!(fields.windows(2).any(|pair| pair == [Reply::Bulk(b"proto".to_vec()), Reply::Integer(2)]));
+        assert_eq!(
+            fx.run("subscribe", &["a"]),
+            Ok(Reply::Many(vec![Reply::Array(confirmation("subscribe", "a", 1))]))
+        );
+        fx.run("unsubscribe", &[]).unwrap();
+        
+        // RESP3: a map from HELLO, push frames carrying the running count afterwards
+        assert!(matches!(fx.run("hello", &["3"]), Ok(Reply::Map(_))));
+        assert_eq!(fx.ctx.protocol, 3);
+        assert_eq!(
+            fx.run("subscribe", &["a"]),
+            Ok(Reply::Many(vec![Reply::Push(confirmation("subscribe", "a", 1))]))
+        );
+        assert_eq!(
+            fx.run("psubscribe", &["n*", "m*"]),
+            Ok(Reply::Many(vec![
+                Reply::Push(confirmation("psubscribe", "n*", 2)),
+                Reply::Push(confirmation("psubscribe", "m*", 3)),
+            ]))
+        );
+        assert_eq!(
+            fx.run("unsubscribe", &[]),
+            Ok(Reply::Many(vec![Reply::Push(confirmation("unsubscribe", "a", 2))]))
+        );
+        
+        assert!(matches!(fx.run("hello", &["4"]), Err(RedisError::NoProto(_))));
+        assert!(fx.run("hello", &["3", "setname", "x"]).is_err());
+        assert_eq!(fx.ctx.protocol, 3);
+    }
+    
+    #[test]
+    fn test_subscribe_mode() {
+        let mut fx = Fixture::new();
+        
+        fx.run("subscribe", &["news"]).unwrap();
+        assert!(fx.ctx.in_subscribe_mode());
+        assert_eq!(
+            fx.run("get", &["k"]),
+            Err(RedisError::Err(
+                "Can't execute 'get': only (P)SUBSCRIBE / (P)UNSUBSCRIBE / PING / QUIT / RESET are allowed in this context".to_string()
+            ))
+        );
+        
+        // PING answers in the message shape so it can't be mistaken for one
+        assert_eq!(
+            fx.run("ping", &[]),
+            Ok(Reply::bulk_array(vec![b"pong".to_vec(), Vec::new()]))
+        );
+        
+        // Dropping the last subscription leaves subscribe mode
+        fx.run("unsubscribe", &[]).unwrap();
+        assert!(!fx.ctx.in_subscribe_mode());
+        assert_eq!(fx.run("get", &["k"]), Ok(Reply::Nil));
+        
+        // So does RESET, which also returns the connection to RESP2
+        fx.run("hello", &["3"]).unwrap();
+        fx.run("psubscribe", &["n*"]).unwrap();
+        assert!(!fx.ctx.in_subscribe_mode());
+        assert_eq!(fx.run("reset", &[]), Ok(Reply::Simple("RESET".to_string())));
+        assert!(fx.ctx.subscription.is_none() && fx.ctx.protocol == 2);
+    }
+    
+    #[test]
+    fn test_replica_rejects_writes() {
+        let mut fx = Fixture::with_state(|state| state.with_replica_of("10.0.0.1:7777".to_string(), "primary.aof".into()));
+        
+        assert_eq!(
+            fx.run("set", &["k", "v"]),
+            Err(RedisError::ReadOnly("You can't write against a read only replica.".to_string()))
+        );
+        assert!(matches!(fx.run("flushall", &[]), Err(RedisError::ReadOnly(_))));
+        
+        // The replication stream still lands, and reads see it
+        fx.state.engine().recover_set(b"k", b"v".to_vec(), None).unwrap();
+        assert_eq!(fx.run("get", &["k"]), Ok(Reply::SharedBulk(Arc::from(&b"v"[..]))));
+        
+        let Ok(Reply::Bulk(info)) = fx.run("info", &[]) else {
+            panic!("INFO should reply with a bulk string");
+        };
+        let info = String::from_utf8(info).unwrap();
+        assert!(info.contains("role:slave\r\nmaster_host:10.0.0.1\r\nmaster_port:7777\r\nmaster_link_status:down\r\n"));
+    }
+    
+    #[test]
+    fn test_acl_checks() {
+        let acl = Acl::parse("user default on >pw +@all\nuser reader on >r +@read").unwrap();
+        let mut fx = Fixture::with_state(|state| state.with_acl(acl));
+        
+        // Nothing but AUTH (and QUIT) runs until the connection authenticates
+        assert!(matches!(fx.run("get", &["k"]), Err(RedisError::NoAuth(_))));
+        assert!(matches!(fx.run("auth", &["reader", "bad"]), Err(RedisError::WrongPass(_))));
+        assert_eq!(fx.run("auth", &["reader", "r"]), Ok(Reply::ok()));
+        assert_eq!(fx.run("get", &["k"]), Ok(Reply::Nil));
+        assert_eq!(
+            fx.run("set", &["k", "v"]),
+            Err(RedisError::NoPerm("User reader has no permissions to run the 'set' command".to_string()))
+        );
+        
+        // One-argument AUTH logs in as default
+        assert_eq!(fx.run("auth", &["pw"]), Ok(Reply::ok()));
+        assert_eq!(fx.run("acl", &["whoami"]), Ok(Reply::Bulk(b"default".to_vec())));
+        assert_eq!(fx.run("set", &["k", "v"]), Ok(Reply::ok()));
+    }
+}
@@ -0,0 +1,107 @@
+// Pub/sub commands - (P)SUBSCRIBE, (P)UNSUBSCRIBE, PUBLISH
+use super::{Builtin, CommandContext, CommandRegistry};
+use crate::network::reply::{RedisError, Reply};
+
+/// Add pub/sub commands to the registry
+pub(super) fn register(registry: &mut CommandRegistry) {
+    registry.register(Builtin::new("subscribe", -2, &["pubsub"], subscribe));
+    registry.register(Builtin::new("psubscribe", -2, &["pubsub"], psubscribe));
+    registry.register(Builtin::new("unsubscribe", -1, &["pubsub"], unsubscribe));
+    registry.register(Builtin::new("punsubscribe", -1, &["pubsub"], punsubscribe));
+    registry.register(Builtin::new("publish", 3, &["pubsub", "fast"], publish));
+}
+
+/// (Un)subscribe confirmation: [kind, name, running subscription count]
+/// Sent as a push frame under RESP3 like the messages that follow it
+fn subscription_reply(kind: &str, name: Option<&[u8]>, count: usize, protocol: u8) -> Reply {
+    Reply::push(vec![
+        Reply::Bulk(kind.as_bytes().to_vec()),
+        Reply::optional_bulk(name.map(<[u8]>::to_vec)),
+        Reply::Integer(count as i64),
+    ], protocol)
+}
+
+/// SUBSCRIBE channel [channel ...]
+fn subscribe(args: &[Vec<u8>], ctx: &mut CommandContext) -> Result<Reply, RedisError> {
+    let pubsub = ctx.state.pubsub().clone();
+    let protocol = ctx.protocol;
+    let subscription = ctx.subscription.get_or_insert_with(|| pubsub.subscription());
+    let replies = args.iter()
+        .map(|channel| {
+            let count = subscription.subscribe(channel);
+            subscription_reply("subscribe", Some(channel), count, protocol)
+        })
+        .collect();
+    Ok(Reply::Many(replies))
+}
+
+/// PSUBSCRIBE pattern [pattern ...]
+fn psubscribe(args: &[Vec<u8>], ctx: &mut CommandContext) -> Result<Reply, RedisError> {
+    let pubsub = ctx.state.pubsub().clone();
+    let protocol = ctx.protocol;
+    let subscription = ctx.subscription.get_or_insert_with(|| pubsub.subscription());
+    let replies = args.iter()
+        .map(|pattern| {
+            let count = subscription.psubscribe(pattern);
+            subscription_reply("psubscribe", Some(pattern), count, protocol)
+        })
+        .collect();
+    Ok(Reply::Many(replies))
+}
+
+/// UNSUBSCRIBE [channel ...]
+fn unsubscribe(args: &[Vec<u8>], ctx: &mut CommandContext) -> Result<Reply, RedisError> {
+    // No arguments means every subscribed channel
+    let channels = match (&ctx.subscription, args.is_empty()) {
+        (Some(subscription), true) => subscription.channels().to_vec(),
+        _ => args.to_vec(),
+    };
+
+    let mut replies = Vec::new();
+    if channels.is_empty() {
+        let count = ctx.subscription.as_ref().map_or(0, |s| s.count());
+        replies.push(subscription_reply("unsubscribe", None, count, ctx.protocol));
+    }
+
+    for channel in channels {
+        let count = ctx.subscription.as_mut()
+            .map_or(0, |s| s.unsubscribe(&channel));
+        replies.push(subscription_reply("unsubscribe", Some(&channel), count, ctx.protocol));
+    }
+
+    if ctx.subscription.as_ref().is_some_and(|s| s.count() == 0) {
+        ctx.subscription = None;
+    }
+    Ok(Reply::Many(replies))
+}
+
+/// PUNSUBSCRIBE [pattern ...]
+fn punsubscribe(args: &[Vec<u8>], ctx: &mut CommandContext) -> Result<Reply, RedisError> {
+    let patterns = match (&ctx.subscription, args.is_empty()) {
+        (Some(subscription), true) => subscription.patterns().to_vec(),
+        _ => args.to_vec(),
+    };
+
+    let mut replies = Vec::new();
+    if patterns.is_empty() {
+        let count = ctx.subscription.as_ref().map_or(0, |s| s.count());
+        replies.push(subscription_reply("punsubscribe", None, count, ctx.protocol));
+    }
+
+    for pattern in patterns {
+        let count = ctx.subscription.as_mut()
+            .map_or(0, |s| s.punsubscribe(&pattern));
+        replies.push(subscription_reply("punsubscribe", Some(&pattern), count, ctx.protocol));
+    }
+
+    if ctx.subscription.as_ref().is_some_and(|s| s.count() == 0) {
+        ctx.subscription = None;
+    }
+    Ok(Reply::Many(replies))
+}
+
+/// PUBLISH channel message
+fn publish(args: &[Vec<u8>], ctx: &mut CommandContext) -> Result<Reply, RedisError> {
+    let receivers = ctx.state.pubsub().publish(&args[0], &args[1]);
+    Ok(Reply::Integer(receivers as i64))
+}
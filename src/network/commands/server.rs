@@ -0,0 +1,535 @@
+// Server commands - PING, ECHO, QUIT, RESET, AUTH, HELLO, ACL, INFO, CLIENT, CONFIG, SLOWLOG, LATENCY, persistence, SHUTDOWN, WAIT and COMMAND introspection
+use std::collections::BTreeMap;
+
+use super::{parse_arg, syntax_error, unknown_subcommand, wrong_arity, Blocking, Builtin, Command, CommandContext, CommandRegistry, KeySpec};
+use crate::core::acl::DEFAULT_USER;
+use crate::core::replication::Role;
+use crate::core::state::GlobalState;
+use crate::network::reply::{RedisError, Reply};
+use crate::storage::encoding::EncodingConfig;
+use crate::storage::memory::{KeyspaceSummary, TypeSummary, TTL_BUCKETS};
+use crate::util::glob::glob_match;
+use crate::util::latency::LatencyMonitor;
+
+/// Add server commands to the registry
+pub(super) fn register(registry: &mut CommandRegistry) {
+    registry.register(Builtin::new("ping", -1, &["fast", "stale"], ping));
+    registry.register(Builtin::new("echo", 2, &["fast"], echo));
+    registry.register(Builtin::new("quit", -1, &["fast", "loading", "stale", "no-auth"], quit));
+    registry.register(Builtin::new("reset", 1, &["fast", "loading", "stale", "no-auth"], reset));
+    registry.register(Builtin::new("auth", -2, &["fast", "loading", "stale", "no-auth"], auth));
+    registry.register(Builtin::new("hello", -1, &["fast", "loading", "stale", "no-auth"], hello));
+    registry.register(Builtin::new("acl", -2, &["admin", "loading", "stale"], acl));
+    registry.register(Builtin::new("info", -1, &["loading", "stale"], info));
+    registry.register(Builtin::new("client", -2, &["loading", "stale"], client));
+    registry.register(Builtin::new("config", -2, &["admin", "loading", "stale"], config));
+    registry.register(Builtin::new("slowlog", -2, &["admin", "loading", "stale"], slowlog));
+    registry.register(Builtin::new("latency", -2, &["admin", "loading", "stale"], latency));
+    registry.register(Builtin::new("save", 1, &["admin"], save));
+    registry.register(Builtin::new("bgsave", -1, &["admin"], bgsave));
+    registry.register(Builtin::new("bgrewriteaof", 1, &["admin"], bgrewriteaof));
+    registry.register(Builtin::new("lastsave", 1, &["fast"], lastsave));
+    registry.register(Builtin::new("shutdown", -1, &["admin", "loading", "stale"], shutdown));
+    registry.register(Builtin::new("debug", -2, &["admin"], debug));
+    registry.register(Builtin::new("keyspace", 2, &["readonly"], keyspace));
+    registry.register(Builtin::new("wait", 3, &[], wait));
+    registry.register(Builtin::new("waitaof", 4, &[], waitaof));
+    registry.register(Builtin::new("command", -1, &["loading", "stale"], command));
+}
+
+/// INFO keyspace lines - Redis-style db0 totals plus per-type counts and memory
+/// Redis-format `dbN:keys=..,expires=..,avg_ttl=..` per non-empty database (index = DB
+/// number), then key counts and sizes by type across all of them
+fn keyspace_info(databases: &[KeyspaceSummary]) -> String {
+    let mut info = String::new();
+    let mut by_type: BTreeMap<&str, TypeSummary> = BTreeMap::new();
+    for (db, summary) in databases.iter().enumerate().filter(|(_, summary)| summary.total_keys > 0) {
+        info.push_str(&format!(
+            "db{}:keys={},expires={},avg_ttl={}\r\n",
+            db, summary.total_keys, summary.with_ttl, summary.avg_ttl_ms()
+        ));
+        for (kind, types) in &summary.by_type {
+            let total = by_type.entry(kind).or_default();
+            total.keys += types.keys;
+            total.bytes += types.bytes;
+        }
+    }
+    for (kind, total) in by_type {
+        info.push_str(&format!("keys_{}:{}\r\nbytes_{}:{}\r\n", kind, total.keys, kind, total.bytes));
+    }
+    info
+}
+
+/// INFO replication lines - the role, then replicas (primary) or the primary link (replica)
+fn replication_info(state: &GlobalState) -> String {
+    let mut info = format!("role:{}\r\n", state.role().info_name());
+    match state.role() {
+        Role::Primary => info.push_str(&format!("connected_slaves:{}\r\n", state.replication().len())),
+        Role::Replica => {
+            let link = state.primary_link();
+            let (host, port) = link.addr()
+                .and_then(|addr| addr.rsplit_once(':'))
+                .unwrap_or((link.addr().unwrap_or(""), ""));
+            info.push_str(&format!(
+                "master_host:{}\r\nmaster_port:{}\r\nmaster_link_status:{}\r\nmaster_last_io_seconds_ago:{}\r\n",
+                host, port,
+                if link.is_up() { "up" } else { "down" },
+                link.last_io().map_or(-1, |elapsed| elapsed.as_secs() as i64)
+            ));
+        }
+    }
+    info.push_str(&format!("master_repl_offset:{}\r\n", state.aof_offset()));
+    info
+}
+
+/// PING [message]
+fn ping(args: &[Vec<u8>], ctx: &mut CommandContext) -> Result<Reply, RedisError> {
+    // Subscribed RESP2 clients get ["pong", message] like a pub/sub message
+    if ctx.in_subscribe_mode() && args.len() <= 1 {
+        return Ok(Reply::bulk_array(vec![b"pong".to_vec(), args.first().cloned().unwrap_or_default()]));
+    }
+    
+    // A message is echoed back as a bulk string instead of PONG
+    match args {
+        [] => Ok(Reply::Simple("PONG".to_string())),
+        [message] => Ok(Reply::Bulk(message.clone())),
+        _ => Err(wrong_arity("ping")),
+    }
+}
+
+/// ECHO message
+fn echo(args: &[Vec<u8>], _ctx: &mut CommandContext) -> Result<Reply, RedisError> {
+    Ok(Reply::Bulk(args[0].clone()))
+}
+
+/// QUIT - acknowledge, then close the connection
+fn quit(_args: &[Vec<u8>], _ctx: &mut CommandContext) -> Result<Reply, RedisError> {
+    Ok(Reply::Many(vec![Reply::ok(), Reply::Close]))
+}
+
+/// RESET - drop subscriptions and go back to RESP2 as the initial user
+fn reset(_args: &[Vec<u8>], ctx: &mut CommandContext) -> Result<Reply, RedisError> {
+    ctx.subscription = None;
+    ctx.protocol = 2;
+    ctx.user = ctx.state.acl().initial_user();
+    Ok(Reply::Simple("RESET".to_string()))
+}
+
+/// AUTH [username] password - switch the connection to that user
+fn auth(args: &[Vec<u8>], ctx: &mut CommandContext) -> Result<Reply, RedisError> {
+    let (name, password) = match args {
+        [password] => {
+            // Like Redis, the one-argument form needs a password on the default user
+            if ctx.state.acl().user(DEFAULT_USER).is_some_and(|user| user.is_nopass()) {
+                return Err(RedisError::from(
+                    "AUTH <password> called without any password configured for the default user"
+                ));
+            }
+            (DEFAULT_USER.to_string(), password)
+        }
+        [name, password] => (String::from_utf8_lossy(name).into_owned(), password),
+        _ => return Err(syntax_error()),
+    };
+    
+    log_in(ctx, &name, password)?;
+    Ok(Reply::ok())
+}
+
+/// Switch the connection to user `name` if `password` matches
+fn log_in(ctx: &mut CommandContext, name: &str, password: &[u8]) -> Result<(), RedisError> {
+    let user = ctx.state.acl().authenticate(name, password)
+        .ok_or_else(|| RedisError::WrongPass("invalid username-password pair or user is disabled.".to_string()))?;
+    ctx.user = Some(user);
+    Ok(())
+}
+
+/// HELLO [protover [AUTH username password]]
+fn hello(args: &[Vec<u8>], ctx: &mut CommandContext) -> Result<Reply, RedisError> {
+    let protocol = match args.first() {
+        Some(version) => match parse_arg::<i64>(version) {
+            Ok(version @ 2..=3) => version as u8,
+            _ => return Err(RedisError::NoProto("unsupported protocol version".to_string())),
+        },
+        None => ctx.protocol,
+    };
+    match args.get(1..).unwrap_or_default() {
+        [] => {}
+        [option, name, password] if option.eq_ignore_ascii_case(b"AUTH") => {
+            log_in(ctx, &String::from_utf8_lossy(name), password)?;
+        }
+        _ => return Err(syntax_error()),
+    }
+    if ctx.user.is_none() {
+        return Err(RedisError::NoAuth(
+            "HELLO must be called with the client already authenticated, otherwise the HELLO <proto> AUTH <user> <pass> \
+             option can be used to authenticate the client and select the RESP protocol version at the same time".to_string()
+        ));
+    }
+    
+    ctx.protocol = protocol;
+    let field = |name: &str| Reply::Bulk(name.as_bytes().to_vec());
+    Ok(Reply::map(vec![
+        (field("server"), field("workingdb")),
+        (field("version"), field("0.1.0")),
+        (field("proto"), Reply::Integer(protocol as i64)),
+        (field("id"), Reply::Integer(ctx.client_id as i64)),
+        (field("mode"), field("standalone")),
+        (field("role"), field(ctx.state.role().info_name())),
+        (field("modules"), Reply::Array(Vec::new())),
+    ], protocol))
+}
+
+/// ACL WHOAMI | USERS
+fn acl(args: &[Vec<u8>], ctx: &mut CommandContext) -> Result<Reply, RedisError> {
+    match args {
+        [sub] if sub.eq_ignore_ascii_case(b"WHOAMI") => {
+            let name = ctx.user.as_ref().map_or(DEFAULT_USER, |user| user.name.as_str());
+            Ok(Reply::Bulk(name.as_bytes().to_vec()))
+        }
+        [sub] if sub.eq_ignore_ascii_case(b"USERS") => {
+            let names = ctx.state.acl().user_names().into_iter().map(|name| name.as_bytes().to_vec()).collect();
+            Ok(Reply::bulk_array(names))
+        }
+        _ => Err(unknown_subcommand(&args[0])),
+    }
+}
+
+/// INFO
+fn info(_args: &[Vec<u8>], ctx: &mut CommandContext) -> Result<Reply, RedisError> {
+    // Get system info
+    let (uptime, reads, writes, deletes, read_lat, write_lat) =
+        ctx.state.get_stats();
+
+    let clients = ctx.state.connection_stats();
+    
+    // Original bytes per stored byte across compressed strings
+    let (compressed, raw_bytes, stored_bytes) = ctx.state.compression_stats();
+    let ratio = if stored_bytes == 0 { 1.0 } else { raw_bytes as f64 / stored_bytes as f64 };
+    let (maxmemory, policy) = ctx.state.memory_limit();
+    
+    // Most contended partition (ties go to the busiest)
+    let partitions = ctx.state.partition_stats();
+    let contended: u64 = partitions.iter().map(|p| p.contended).sum();
+    let hottest = partitions.iter()
+        .max_by_key(|p| (p.contended, p.acquisitions))
+        .copied()
+        .unwrap_or_default();
+
+    let info = format!(
+        "# Server\r\nworkingdb_version:0.1.0\r\nuptime_seconds:{}\r\n\
+         # Clients\r\nconnected_clients:{}\r\n\
+         # Stats\r\ntotal_reads:{}\r\ntotal_writes:{}\r\n\
+         total_deletes:{}\r\navg_read_latency_ns:{}\r\n\
+         avg_write_latency_ns:{}\r\n\
+         total_connections_received:{}\r\nrejected_connections:{}\r\n\
+         total_commands_processed:{}\r\ninstantaneous_ops_per_sec:{}\r\n\
+         partitions:{}\r\npartition_lock_contended:{}\r\n\
+         hottest_partition:{}\r\nhottest_partition_keys:{}\r\n\
+         hottest_partition_acquisitions:{}\r\nhottest_partition_contended:{}\r\n\
+         # Memory\r\nused_memory:{}\r\nmaxmemory:{}\r\nmaxmemory_policy:{}\r\n\
+         compressed_values:{}\r\ncompression_ratio:{:.2}\r\n\
+         # Persistence\r\naof_pending_fsync:{}\r\naof_current_size:{}\r\n\
+         aof_base_size:{}\r\naof_rewrite_in_progress:{}\r\n\
+         # Replication\r\n{}\
+         # Keyspace\r\n{}",
+        uptime.as_secs(), clients.connected_clients,
+        reads, writes, deletes, read_lat, write_lat,
+        clients.total_connections, clients.rejected_connections,
+        clients.total_commands, clients.ops_per_sec,
+        partitions.len(), contended,
+        hottest.index, hottest.keys, hottest.acquisitions, hottest.contended,
+        ctx.state.used_memory(), maxmemory, policy.name(),
+        compressed, ratio,
+        ctx.state.aof_pending_fsync(), ctx.state.aof_current_size(),
+        ctx.state.aof_base_size(), u8::from(ctx.state.aof_rewrite_in_progress()),
+        replication_info(&ctx.state),
+        keyspace_info(&ctx.state.keyspace_summaries())
+    );
+
+    Ok(Reply::Bulk(info.into_bytes()))
+}
+
+/// SAVE
+fn save(_args: &[Vec<u8>], ctx: &mut CommandContext) -> Result<Reply, RedisError> {
+    // Snapshot synchronously on this connection
+    ctx.state.save()?;
+    Ok(Reply::ok())
+}
+
+/// BGSAVE
+fn bgsave(_args: &[Vec<u8>], ctx: &mut CommandContext) -> Result<Reply, RedisError> {
+    ctx.state.bgsave()?;
+    Ok(Reply::Simple("Background saving started".to_string()))
+}
+
+/// BGREWRITEAOF
+fn bgrewriteaof(_args: &[Vec<u8>], ctx: &mut CommandContext) -> Result<Reply, RedisError> {
+    ctx.state.bgrewriteaof()?;
+    Ok(Reply::Simple("Background append only file rewriting started".to_string()))
+}
+
+/// LASTSAVE
+fn lastsave(_args: &[Vec<u8>], ctx: &mut CommandContext) -> Result<Reply, RedisError> {
+    Ok(Reply::Integer(ctx.state.last_save() as i64))
+}
+
+/// SHUTDOWN [NOSAVE|SAVE]
+fn shutdown(args: &[Vec<u8>], ctx: &mut CommandContext) -> Result<Reply, RedisError> {
+    // Saving is the default
+    let save = match args {
+        [] => true,
+        [mode] if mode.eq_ignore_ascii_case(b"SAVE") => true,
+        [mode] if mode.eq_ignore_ascii_case(b"NOSAVE") => false,
+        _ => return Err(syntax_error()),
+    };
+
+    // Success is not acknowledged - the connection just closes
+    if let Err(e) = ctx.state.shutdown(save) {
+        eprintln!("SHUTDOWN failed: {}", e);
+        return Err(RedisError::from("Errors trying to SHUTDOWN. Check logs."));
+    }
+    Ok(Reply::Close)
+}
+
+/// DEBUG RELOAD, plus allowlisted no-op subcommands
+fn debug(args: &[Vec<u8>], ctx: &mut CommandContext) -> Result<Reply, RedisError> {
+    // Clients probing tunables on connect get +OK rather than a disconnect-worthy error
+    if ctx.state.is_debug_noop(&args[0]) {
+        return Ok(Reply::ok());
+    }
+    if !ctx.state.debug_commands_enabled() {
+        return Err(RedisError::from("DEBUG command not allowed. Set debug_commands_enabled to enable it."));
+    }
+    
+    match args {
+        [sub] if sub.eq_ignore_ascii_case(b"RELOAD") => {
+            ctx.state.debug_reload()?;
+            Ok(Reply::ok())
+        }
+        [sub, mode] if sub.eq_ignore_ascii_case(b"TRACE") => {
+            ctx.trace = match mode.to_ascii_uppercase().as_slice() {
+                b"ON" => true,
+                b"OFF" => false,
+                _ => return Err(syntax_error()),
+            };
+            Ok(Reply::ok())
+        }
+        _ => Err(unknown_subcommand(&args[0])),
+    }
+}
+
+/// KEYSPACE SUMMARY
+fn keyspace(args: &[Vec<u8>], ctx: &mut CommandContext) -> Result<Reply, RedisError> {
+    if !args[0].eq_ignore_ascii_case(b"SUMMARY") {
+        return Err(unknown_subcommand(&args[0]));
+    }
+
+    let summary = ctx.state.keyspace_summary();
+    let mut report = keyspace_info(std::slice::from_ref(&summary));
+    report.push_str(&format!(
+        "keys_with_ttl:{}\r\nkeys_without_ttl:{}\r\n",
+        summary.with_ttl, summary.without_ttl
+    ));
+    for ((label, _), count) in TTL_BUCKETS.iter().zip(summary.ttl_histogram) {
+        report.push_str(&format!("ttl_{}:{}\r\n", label, count));
+    }
+
+    Ok(Reply::Bulk(report.into_bytes()))
+}
+
+/// CLIENT ID | LIST
+fn client(args: &[Vec<u8>], ctx: &mut CommandContext) -> Result<Reply, RedisError> {
+    match args {
+        [sub] if sub.eq_ignore_ascii_case(b"ID") => Ok(Reply::Integer(ctx.client_id as i64)),
+        [sub] if sub.eq_ignore_ascii_case(b"LIST") => {
+            let list: String = ctx.state.clients().list().iter()
+                .map(|client| format!("id={} addr={} age={}\n", client.id, client.addr, client.connected_at.elapsed().as_secs()))
+                .collect();
+            Ok(Reply::Bulk(list.into_bytes()))
+        }
+        _ => Err(unknown_subcommand(&args[0])),
+    }
+}
+
+/// CONFIG GET pattern [pattern ...] | SET parameter value [parameter value ...]
+/// Only the encoding thresholds are runtime parameters
+fn config(args: &[Vec<u8>], ctx: &mut CommandContext) -> Result<Reply, RedisError> {
+    let encodings = ctx.state.encodings();
+    match args {
+        [sub, patterns @ ..] if sub.eq_ignore_ascii_case(b"GET") && !patterns.is_empty() => {
+            let pairs = encodings.iter()
+                .filter(|(name, _)| patterns.iter().any(|pattern| glob_match(&pattern.to_ascii_lowercase(), name.as_bytes())))
+                .map(|(name, value)| (Reply::Bulk(name.as_bytes().to_vec()), Reply::Bulk(value.to_string().into_bytes())))
+                .collect();
+            Ok(Reply::map(pairs, ctx.protocol))
+        }
+        [sub, pairs @ ..] if sub.eq_ignore_ascii_case(b"SET") && !pairs.is_empty() && pairs.len().is_multiple_of(2) => {
+            // Validate every pair before applying any
+            let mut updates = Vec::with_capacity(pairs.len() / 2);
+            for pair in pairs.chunks(2) {
+                let name = String::from_utf8_lossy(&pair[0]);
+                if EncodingConfig::canonical_name(&name).is_none() {
+                    return Err(RedisError::Err(format!("Unknown option or number of arguments for CONFIG SET - '{}'", name)));
+                }
+                let update = EncodingConfig::parse(&name, &String::from_utf8_lossy(&pair[1])).map_err(|e| {
+                    RedisError::Err(format!("CONFIG SET failed (possibly related to argument '{}') - {}", name, e))
+                })?;
+                updates.push(update);
+            }
+            
+            for (name, value) in updates {
+                encodings.store(name, value);
+            }
+            Ok(Reply::ok())
+        }
+        [sub, ..] if sub.eq_ignore_ascii_case(b"GET") || sub.eq_ignore_ascii_case(b"SET") => Err(wrong_arity("config")),
+        _ => Err(unknown_subcommand(&args[0])),
+    }
+}
+
+/// SLOWLOG GET [count] | LEN | RESET
+fn slowlog(args: &[Vec<u8>], ctx: &mut CommandContext) -> Result<Reply, RedisError> {
+    let slowlog = ctx.state.slowlog();
+    match args {
+        [sub, rest @ ..] if sub.eq_ignore_ascii_case(b"GET") && rest.len() <= 1 => {
+            // Default 10 like Redis; a negative count returns everything
+            let count = match rest.first() {
+                Some(count) => usize::try_from(parse_arg::<i64>(count)?).ok(),
+                None => Some(10),
+            };
+            let entries = slowlog.get(count).into_iter()
+                .map(|entry| Reply::Array(vec![
+                    Reply::Integer(entry.id as i64),
+                    Reply::Integer(entry.timestamp as i64),
+                    Reply::Integer(entry.duration.as_micros() as i64),
+                    Reply::bulk_array(entry.args),
+                    Reply::Bulk(entry.client_addr.into_bytes()),
+                    Reply::Bulk(Vec::new()),
+                ]))
+                .collect();
+            Ok(Reply::Array(entries))
+        }
+        [sub] if sub.eq_ignore_ascii_case(b"LEN") => Ok(Reply::Integer(slowlog.len() as i64)),
+        [sub] if sub.eq_ignore_ascii_case(b"RESET") => {
+            slowlog.reset();
+            Ok(Reply::ok())
+        }
+        _ => Err(unknown_subcommand(&args[0])),
+    }
+}
+
+/// LATENCY LATEST | HISTORY event | RESET [event ...]
+fn latency(args: &[Vec<u8>], _ctx: &mut CommandContext) -> Result<Reply, RedisError> {
+    let monitor = LatencyMonitor::global();
+    match args {
+        [sub] if sub.eq_ignore_ascii_case(b"LATEST") => {
+            let rows = monitor.latest().into_iter()
+                .map(|sample| Reply::Array(vec![
+                    Reply::Bulk(sample.event.as_bytes().to_vec()),
+                    Reply::Integer(sample.timestamp as i64),
+                    Reply::Integer(sample.latest_ms as i64),
+                    Reply::Integer(sample.max_ms as i64),
+                ]))
+                .collect();
+            Ok(Reply::Array(rows))
+        }
+        [sub, event] if sub.eq_ignore_ascii_case(b"HISTORY") => {
+            let samples = monitor.history(&String::from_utf8_lossy(event)).into_iter()
+                .map(|(timestamp, ms)| Reply::Array(vec![Reply::Integer(timestamp as i64), Reply::Integer(ms as i64)]))
+                .collect();
+            Ok(Reply::Array(samples))
+        }
+        [sub, events @ ..] if sub.eq_ignore_ascii_case(b"RESET") => {
+            let events: Vec<String> = events.iter().map(|event| String::from_utf8_lossy(event).into_owned()).collect();
+            let events: Vec<&str> = events.iter().map(String::as_str).collect();
+            Ok(Reply::Integer(monitor.reset(&events) as i64))
+        }
+        _ => Err(unknown_subcommand(&args[0])),
+    }
+}
+
+/// WAIT numreplicas timeout
+fn wait(args: &[Vec<u8>], _ctx: &mut CommandContext) -> Result<Reply, RedisError> {
+    let numreplicas = parse_arg::<usize>(&args[0])?;
+    let timeout = parse_arg::<i64>(&args[1])?;
+    if timeout < 0 {
+        return Err(RedisError::from("timeout is negative"));
+    }
+
+    // The connection loop does the waiting
+    Ok(Reply::Blocked(Blocking::Replicas { numreplicas, timeout_ms: timeout as u64 }))
+}
+
+/// WAITAOF numlocal numreplicas timeout
+fn waitaof(args: &[Vec<u8>], _ctx: &mut CommandContext) -> Result<Reply, RedisError> {
+    let numlocal = parse_arg::<usize>(&args[0])?;
+    let numreplicas = parse_arg::<usize>(&args[1])?;
+    let timeout = parse_arg::<i64>(&args[2])?;
+    if timeout < 0 {
+        return Err(RedisError::from("timeout is negative"));
+    }
+    
+    Ok(Reply::Blocked(Blocking::Aof { numlocal, numreplicas, timeout_ms: timeout as u64 }))
+}
+
+/// COMMAND entry: [name, arity, [flags], first key, last key, key step]
+fn command_info(command: &dyn Command) -> Reply {
+    let key_spec = command.key_spec();
+    let mut flags: Vec<Reply> = command.flags().iter().map(|f| Reply::Simple(f.to_string())).collect();
+    if key_spec == KeySpec::NumKeys {
+        flags.push(Reply::Simple("movablekeys".to_string()));
+    }
+    
+    let (first, last, step) = key_spec.positions();
+    Reply::Array(vec![
+        Reply::Bulk(command.name().as_bytes().to_vec()),
+        Reply::Integer(command.arity() as i64),
+        Reply::Array(flags),
+        Reply::Integer(first as i64),
+        Reply::Integer(last as i64),
+        Reply::Integer(step as i64),
+    ])
+}
+
+/// COMMAND GETKEYS command [arg ...] - the key arguments of a call, without running it
+fn command_getkeys(call: &[Vec<u8>]) -> Result<Reply, RedisError> {
+    let name = String::from_utf8_lossy(&call[0]).to_ascii_lowercase();
+    let command = CommandRegistry::global().get(&name)
+        .ok_or_else(|| RedisError::from("Invalid command specified"))?;
+    
+    let (argc, arity) = (call.len() as i32, command.arity());
+    if (arity > 0 && argc != arity) || argc < arity.abs() {
+        return Err(RedisError::from("Invalid number of arguments specified for command"));
+    }
+    
+    let keys = command.key_spec().keys(&call[1..])?;
+    if keys.is_empty() {
+        return Err(RedisError::from("The command has no key arguments"));
+    }
+    Ok(Reply::bulk_array(keys.into_iter().map(<[u8]>::to_vec).collect()))
+}
+
+/// COMMAND [COUNT | INFO name [name ...] | GETKEYS command [arg ...]]
+fn command(args: &[Vec<u8>], _ctx: &mut CommandContext) -> Result<Reply, RedisError> {
+    let registry = CommandRegistry::global();
+
+    match args.first().map(|sub| sub.to_ascii_uppercase()).as_deref() {
+        None => {
+            let mut commands: Vec<&dyn Command> = registry.iter().collect();
+            commands.sort_by_key(|command| command.name());
+            Ok(Reply::Array(commands.into_iter().map(command_info).collect()))
+        }
+        Some(b"COUNT") if args.len() == 1 => Ok(Reply::Integer(registry.len() as i64)),
+        Some(b"GETKEYS") if args.len() >= 2 => command_getkeys(&args[1..]),
+        Some(b"INFO") => {
+            let entries = args[1..].iter()
+                .map(|name| {
+                    let name = String::from_utf8_lossy(name).to_ascii_lowercase();
+                    registry.get(&name).map_or(Reply::Nil, command_info)
+                })
+                .collect();
+            Ok(Reply::Array(entries))
+        }
+        _ => Err(unknown_subcommand(&args[0])),
+    }
+}
@@ -0,0 +1,90 @@
+// Set commands - SADD, SREM, SMEMBERS, SSCAN, SINTERCARD and the SINTER/SUNION/SDIFF family
+use super::{parse_arg, parse_scan_args, scan_reply, syntax_error, Builtin, CommandContext, CommandRegistry};
+use crate::network::reply::{RedisError, Reply};
+use crate::storage::value::SetOp;
+
+/// Add set commands to the registry
+pub(super) fn register(registry: &mut CommandRegistry) {
+    registry.register(Builtin::new("sadd", -3, &["write", "fast"], sadd).keys(1, 1, 1));
+    registry.register(Builtin::new("srem", -3, &["write", "fast"], srem).keys(1, 1, 1));
+    registry.register(Builtin::new("smembers", 2, &["readonly"], smembers).keys(1, 1, 1));
+    registry.register(Builtin::new("sismember", 3, &["readonly", "fast"], sismember).keys(1, 1, 1));
+    registry.register(Builtin::new("scard", 2, &["readonly", "fast"], scard).keys(1, 1, 1));
+    registry.register(Builtin::new("sscan", -3, &["readonly"], sscan).keys(1, 1, 1));
+    registry.register(Builtin::new("sintercard", -3, &["readonly"], sintercard).numkeys());
+    registry.register(Builtin::new("sinter", -2, &["readonly"], |args, ctx| combine(SetOp::Inter, args, ctx)).keys(1, -1, 1));
+    registry.register(Builtin::new("sunion", -2, &["readonly"], |args, ctx| combine(SetOp::Union, args, ctx)).keys(1, -1, 1));
+    registry.register(Builtin::new("sdiff", -2, &["readonly"], |args, ctx| combine(SetOp::Diff, args, ctx)).keys(1, -1, 1));
+    registry.register(Builtin::new("sinterstore", -3, &["write"], |args, ctx| combine_store(SetOp::Inter, args, ctx)).keys(1, -1, 1));
+    registry.register(Builtin::new("sunionstore", -3, &["write"], |args, ctx| combine_store(SetOp::Union, args, ctx)).keys(1, -1, 1));
+    registry.register(Builtin::new("sdiffstore", -3, &["write"], |args, ctx| combine_store(SetOp::Diff, args, ctx)).keys(1, -1, 1));
+}
+
+/// SADD key member [member ...]
+fn sadd(args: &[Vec<u8>], ctx: &mut CommandContext) -> Result<Reply, RedisError> {
+    let added = ctx.state.sadd(&args[0], args[1..].to_vec())?;
+    Ok(Reply::Integer(added as i64))
+}
+
+/// SREM key member [member ...]
+fn srem(args: &[Vec<u8>], ctx: &mut CommandContext) -> Result<Reply, RedisError> {
+    let removed = ctx.state.srem(&args[0], args[1..].to_vec())?;
+    Ok(Reply::Integer(removed as i64))
+}
+
+/// SMEMBERS key
+fn smembers(args: &[Vec<u8>], ctx: &mut CommandContext) -> Result<Reply, RedisError> {
+    Ok(Reply::bulk_array(ctx.state.smembers(&args[0])?))
+}
+
+/// SISMEMBER key member
+fn sismember(args: &[Vec<u8>], ctx: &mut CommandContext) -> Result<Reply, RedisError> {
+    Ok(Reply::Integer(ctx.state.sismember(&args[0], &args[1])? as i64))
+}
+
+/// SCARD key
+fn scard(args: &[Vec<u8>], ctx: &mut CommandContext) -> Result<Reply, RedisError> {
+    Ok(Reply::Integer(ctx.state.scard(&args[0])? as i64))
+}
+
+/// SSCAN key cursor [MATCH pattern] [COUNT count]
+fn sscan(args: &[Vec<u8>], ctx: &mut CommandContext) -> Result<Reply, RedisError> {
+    let (cursor, pattern, count) = parse_scan_args(&args[1..])?;
+    let (next, members) = ctx.state.sscan(&args[0], cursor, pattern, count)?;
+    Ok(scan_reply(next, members))
+}
+
+/// SINTER / SUNION / SDIFF key [key ...]
+fn combine(op: SetOp, args: &[Vec<u8>], ctx: &mut CommandContext) -> Result<Reply, RedisError> {
+    let keys: Vec<&[u8]> = args.iter().map(|k| k.as_slice()).collect();
+    Ok(Reply::bulk_array(ctx.state.set_combine(op, &keys)?))
+}
+
+/// SINTERCARD numkeys key [key ...] [LIMIT limit]
+fn sintercard(args: &[Vec<u8>], ctx: &mut CommandContext) -> Result<Reply, RedisError> {
+    let numkeys = parse_arg::<i64>(&args[0])
+        .ok()
+        .filter(|&n| n > 0)
+        .ok_or_else(|| RedisError::from("numkeys should be greater than 0"))? as usize;
+    if numkeys > args.len() - 1 {
+        return Err(RedisError::from("Number of keys can't be greater than number of args"));
+    }
+    
+    let keys: Vec<&[u8]> = args[1..=numkeys].iter().map(|k| k.as_slice()).collect();
+    let limit = match &args[numkeys + 1..] {
+        [] => 0,
+        [option, limit] if option.eq_ignore_ascii_case(b"LIMIT") => parse_arg::<i64>(limit)
+            .ok()
+            .filter(|&n| n >= 0)
+            .ok_or_else(|| RedisError::from("LIMIT can't be negative"))? as usize,
+        _ => return Err(syntax_error()),
+    };
+    Ok(Reply::Integer(ctx.state.set_intercard(&keys, limit)? as i64))
+}
+
+/// SINTERSTORE / SUNIONSTORE / SDIFFSTORE destination key [key ...]
+fn combine_store(op: SetOp, args: &[Vec<u8>], ctx: &mut CommandContext) -> Result<Reply, RedisError> {
+    let keys: Vec<&[u8]> = args[1..].iter().map(|k| k.as_slice()).collect();
+    let len = ctx.state.set_combine_store(op, &args[0], &keys)?;
+    Ok(Reply::Integer(len as i64))
+}
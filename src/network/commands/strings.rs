@@ -0,0 +1,158 @@
+// String commands - GET, SET, MSET, ranges, bitmaps, the INCR family and SEQ
+use std::time::Duration;
+
+use super::{parse_arg, syntax_error, unknown_subcommand, wrong_arity, Builtin, CommandContext, CommandRegistry};
+use crate::network::reply::{RedisError, Reply};
+
+/// Add string commands to the registry
+pub(super) fn register(registry: &mut CommandRegistry) {
+    registry.register(Builtin::new("get", 2, &["readonly", "fast"], get).keys(1, 1, 1));
+    registry.register(Builtin::new("set", -3, &["write"], set).keys(1, 1, 1));
+    registry.register(Builtin::new("mset", -3, &["write"], mset).keys(1, -1, 2));
+    registry.register(Builtin::new("strlen", 2, &["readonly", "fast"], strlen).keys(1, 1, 1));
+    registry.register(Builtin::new("bitcount", -2, &["readonly"], bitcount).keys(1, 1, 1));
+    registry.register(Builtin::new("getbit", 3, &["readonly", "fast"], getbit).keys(1, 1, 1));
+    registry.register(Builtin::new("setbit", 4, &["write"], setbit).keys(1, 1, 1));
+    registry.register(Builtin::new("getrange", 4, &["readonly"], getrange).keys(1, 1, 1));
+    registry.register(Builtin::new("setrange", 4, &["write"], setrange).keys(1, 1, 1));
+    registry.register(Builtin::new("incr", 2, &["write", "fast"], incr).keys(1, 1, 1));
+    registry.register(Builtin::new("decr", 2, &["write", "fast"], decr).keys(1, 1, 1));
+    registry.register(Builtin::new("incrby", 3, &["write", "fast"], incrby).keys(1, 1, 1));
+    registry.register(Builtin::new("decrby", 3, &["write", "fast"], decrby).keys(1, 1, 1));
+    registry.register(Builtin::new("seq", -3, &["write"], seq).keys(2, 2, 1));
+}
+
+/// GET key
+fn get(args: &[Vec<u8>], ctx: &mut CommandContext) -> Result<Reply, RedisError> {
+    // The value is shared with the MemTable, not copied
+    Ok(ctx.state.get_string(&args[0])?.map_or(Reply::Nil, Reply::SharedBulk))
+}
+
+/// SET key value [EX seconds]
+fn set(args: &[Vec<u8>], ctx: &mut CommandContext) -> Result<Reply, RedisError> {
+    // Check for EX option
+    let mut ttl = None;
+    if args.len() >= 4 && args[2].eq_ignore_ascii_case(b"EX") {
+        let secs = std::str::from_utf8(&args[3])
+            .map_err(|_| RedisError::from("Invalid TTL value"))?;
+        if let Ok(secs) = secs.parse::<u64>() {
+            ttl = Some(Duration::from_secs(secs));
+        }
+    }
+
+    ctx.state.set(&args[0], args[1].clone(), ttl)?;
+    Ok(Reply::ok())
+}
+
+/// MSET key value [key value ...]
+fn mset(args: &[Vec<u8>], ctx: &mut CommandContext) -> Result<Reply, RedisError> {
+    if !args.len().is_multiple_of(2) {
+        return Err(wrong_arity("mset"));
+    }
+
+    let entries = args.chunks(2)
+        .map(|pair| (pair[0].clone(), pair[1].clone(), None))
+        .collect();
+    ctx.state.set_batch(entries)?;
+    Ok(Reply::ok())
+}
+
+/// STRLEN key
+fn strlen(args: &[Vec<u8>], ctx: &mut CommandContext) -> Result<Reply, RedisError> {
+    Ok(Reply::Integer(ctx.state.strlen(&args[0])? as i64))
+}
+
+/// BITCOUNT key [start end]
+fn bitcount(args: &[Vec<u8>], ctx: &mut CommandContext) -> Result<Reply, RedisError> {
+    let range = match args.len() {
+        1 => None,
+        3 => Some((parse_arg::<i64>(&args[1])?, parse_arg::<i64>(&args[2])?)),
+        _ => return Err(syntax_error()),
+    };
+    Ok(Reply::Integer(ctx.state.bit_count(&args[0], range)? as i64))
+}
+
+/// GETBIT key offset
+fn getbit(args: &[Vec<u8>], ctx: &mut CommandContext) -> Result<Reply, RedisError> {
+    let offset = parse_bit_offset(&args[1])?;
+    Ok(Reply::Integer(ctx.state.get_bit(&args[0], offset)? as i64))
+}
+
+/// SETBIT key offset 0|1
+fn setbit(args: &[Vec<u8>], ctx: &mut CommandContext) -> Result<Reply, RedisError> {
+    let offset = parse_bit_offset(&args[1])?;
+    let on = match args[2].as_slice() {
+        b"0" => false,
+        b"1" => true,
+        _ => return Err(RedisError::from("bit is not an integer or out of range")),
+    };
+    Ok(Reply::Integer(ctx.state.set_bit(&args[0], offset, on)? as i64))
+}
+
+/// Bit offsets are unsigned and capped at 2^32 like Redis
+fn parse_bit_offset(arg: &[u8]) -> Result<usize, RedisError> {
+    std::str::from_utf8(arg)
+        .ok()
+        .and_then(|s| s.parse::<u32>().ok())
+        .map(|offset| offset as usize)
+        .ok_or_else(|| RedisError::from("bit offset is not an integer or out of range"))
+}
+
+/// GETRANGE key start end
+fn getrange(args: &[Vec<u8>], ctx: &mut CommandContext) -> Result<Reply, RedisError> {
+    let start = parse_arg::<i64>(&args[1])?;
+    let end = parse_arg::<i64>(&args[2])?;
+    Ok(Reply::Bulk(ctx.state.get_range(&args[0], start, end)?))
+}
+
+/// SETRANGE key offset value
+fn setrange(args: &[Vec<u8>], ctx: &mut CommandContext) -> Result<Reply, RedisError> {
+    let offset = parse_arg::<i64>(&args[1])?;
+    let offset = usize::try_from(offset)
+        .map_err(|_| RedisError::from("offset is out of range"))?;
+    Ok(Reply::Integer(ctx.state.set_range(&args[0], offset, &args[2])? as i64))
+}
+
+/// INCR key
+fn incr(args: &[Vec<u8>], ctx: &mut CommandContext) -> Result<Reply, RedisError> {
+    incr_by(&args[0], 1, ctx)
+}
+
+/// DECR key
+fn decr(args: &[Vec<u8>], ctx: &mut CommandContext) -> Result<Reply, RedisError> {
+    incr_by(&args[0], -1, ctx)
+}
+
+/// INCRBY key delta
+fn incrby(args: &[Vec<u8>], ctx: &mut CommandContext) -> Result<Reply, RedisError> {
+    incr_by(&args[0], parse_arg::<i64>(&args[1])?, ctx)
+}
+
+/// DECRBY key delta
+fn decrby(args: &[Vec<u8>], ctx: &mut CommandContext) -> Result<Reply, RedisError> {
+    let delta = parse_arg::<i64>(&args[1])?
+        .checked_neg()
+        .ok_or_else(|| RedisError::from("decrement would overflow"))?;
+    incr_by(&args[0], delta, ctx)
+}
+
+/// Shared body of the INCR family
+fn incr_by(key: &[u8], delta: i64, ctx: &mut CommandContext) -> Result<Reply, RedisError> {
+    Ok(Reply::Integer(ctx.state.incr_by(key, delta)?))
+}
+
+/// SEQ NEXT name [count] - the next id, or an array of `count` consecutive ids
+fn seq(args: &[Vec<u8>], ctx: &mut CommandContext) -> Result<Reply, RedisError> {
+    if !args[0].eq_ignore_ascii_case(b"NEXT") {
+        return Err(unknown_subcommand(&args[0]));
+    }
+    match &args[1..] {
+        [name] => Ok(Reply::Integer(ctx.state.seq_next(name, 1)? as i64)),
+        [name, count] => {
+            let count = parse_arg::<u64>(count)?;
+            let first = ctx.state.seq_next(name, count)?;
+            Ok(Reply::Array((first..first + count).map(|id| Reply::Integer(id as i64)).collect()))
+        }
+        _ => Err(wrong_arity("seq")),
+    }
+}
@@ -0,0 +1,201 @@
+// Sorted set commands - ZADD, ZINCRBY, ZREM, ZSCORE, ZRANK, ZRANGE, ZRANGEBYSCORE, ZCARD, ZSCAN
+use std::ops::Bound;
+
+use super::{parse_arg, parse_scan_args, scan_reply, syntax_error, Builtin, CommandContext, CommandRegistry};
+use crate::network::reply::{RedisError, Reply};
+use crate::storage::value::ZAddFlags;
+use crate::storage::zset::OrderedFloat;
+
+/// Add sorted set commands to the registry
+pub(super) fn register(registry: &mut CommandRegistry) {
+    registry.register(Builtin::new("zadd", -4, &["write", "fast"], zadd).keys(1, 1, 1));
+    registry.register(Builtin::new("zincrby", 4, &["write", "fast"], zincrby).keys(1, 1, 1));
+    registry.register(Builtin::new("zrem", -3, &["write", "fast"], zrem).keys(1, 1, 1));
+    registry.register(Builtin::new("zscore", 3, &["readonly", "fast"], zscore).keys(1, 1, 1));
+    registry.register(Builtin::new("zrank", 3, &["readonly", "fast"], |args, ctx| zrank(args, ctx, false)).keys(1, 1, 1));
+    registry.register(Builtin::new("zrevrank", 3, &["readonly", "fast"], |args, ctx| zrank(args, ctx, true)).keys(1, 1, 1));
+    registry.register(Builtin::new("zrange", -4, &["readonly"], |args, ctx| zrange(args, ctx, false)).keys(1, 1, 1));
+    registry.register(Builtin::new("zrevrange", -4, &["readonly"], |args, ctx| zrange(args, ctx, true)).keys(1, 1, 1));
+    registry.register(Builtin::new("zrangebyscore", -4, &["readonly"], |args, ctx| zrange_by_score(args, ctx, false)).keys(1, 1, 1));
+    registry.register(Builtin::new("zrevrangebyscore", -4, &["readonly"], |args, ctx| zrange_by_score(args, ctx, true)).keys(1, 1, 1));
+    registry.register(Builtin::new("zcard", 2, &["readonly", "fast"], zcard).keys(1, 1, 1));
+    registry.register(Builtin::new("zscan", -3, &["readonly"], zscan).keys(1, 1, 1));
+}
+
+/// Parse a sorted set score (inf/-inf allowed, NaN rejected)
+fn parse_score(arg: &[u8]) -> Result<f64, RedisError> {
+    std::str::from_utf8(arg)
+        .ok()
+        .and_then(|s| s.parse::<f64>().ok())
+        .filter(|score| !score.is_nan())
+        .ok_or_else(|| RedisError::from("value is not a valid float"))
+}
+
+/// Parse a score range bound: score, (score for exclusive, -inf/+inf
+fn parse_score_bound(arg: &[u8]) -> Result<Bound<OrderedFloat>, RedisError> {
+    let (exclusive, score) = match arg.strip_prefix(b"(") {
+        Some(score) => (true, score),
+        None => (false, arg),
+    };
+    let score = parse_score(score)
+        .ok()
+        .and_then(OrderedFloat::new)
+        .ok_or_else(|| RedisError::from("min or max is not a float"))?;
+    Ok(if exclusive { Bound::Excluded(score) } else { Bound::Included(score) })
+}
+
+/// Members with their scores interleaved when WITHSCORES is given
+fn entries_reply(entries: Vec<(Vec<u8>, f64)>, withscores: bool) -> Reply {
+    let mut reply = Vec::with_capacity(entries.len() * 2);
+    for (member, score) in entries {
+        reply.push(member);
+        if withscores {
+            reply.push(score.to_string().into_bytes());
+        }
+    }
+    Reply::bulk_array(reply)
+}
+
+/// Score as a bulk string, or nil
+fn score_reply(score: Option<f64>) -> Reply {
+    Reply::optional_bulk(score.map(|s| s.to_string().into_bytes()))
+}
+
+/// ZADD key [NX|XX] [GT|LT] [CH] [INCR] score member [score member ...]
+fn zadd(args: &[Vec<u8>], ctx: &mut CommandContext) -> Result<Reply, RedisError> {
+    let mut flags = ZAddFlags::default();
+    let mut ch = false;
+
+    // Options come first; the first non-option starts the pairs
+    let mut idx = 1;
+    while let Some(option) = args.get(idx) {
+        match option.to_ascii_uppercase().as_slice() {
+            b"NX" => flags.nx = true,
+            b"XX" => flags.xx = true,
+            b"GT" => flags.gt = true,
+            b"LT" => flags.lt = true,
+            b"CH" => ch = true,
+            b"INCR" => flags.incr = true,
+            _ => break,
+        }
+        idx += 1;
+    }
+
+    if flags.nx && flags.xx {
+        return Err(RedisError::from("XX and NX options at the same time are not compatible"));
+    }
+    if (flags.gt && flags.lt) || (flags.nx && (flags.gt || flags.lt)) {
+        return Err(RedisError::from("GT, LT, and/or NX options at the same time are not compatible"));
+    }
+
+    let pairs = &args[idx..];
+    if pairs.is_empty() || !pairs.len().is_multiple_of(2) {
+        return Err(syntax_error());
+    }
+    if flags.incr && pairs.len() != 2 {
+        return Err(RedisError::from("INCR option supports a single increment-element pair"));
+    }
+
+    let mut members = Vec::with_capacity(pairs.len() / 2);
+    for pair in pairs.chunks(2) {
+        members.push((parse_score(&pair[0])?, pair[1].clone()));
+    }
+
+    if flags.incr {
+        let (delta, member) = members.pop().expect("single pair");
+        return Ok(score_reply(ctx.state.zincrby(&args[0], delta, member, flags)?));
+    }
+    Ok(Reply::Integer(ctx.state.zadd(&args[0], members, flags, ch)? as i64))
+}
+
+/// ZINCRBY key increment member
+fn zincrby(args: &[Vec<u8>], ctx: &mut CommandContext) -> Result<Reply, RedisError> {
+    let delta = parse_score(&args[1])?;
+    let score = ctx.state.zincrby(&args[0], delta, args[2].clone(), ZAddFlags::default())?;
+    Ok(score_reply(score))
+}
+
+/// ZREM key member [member ...]
+fn zrem(args: &[Vec<u8>], ctx: &mut CommandContext) -> Result<Reply, RedisError> {
+    let removed = ctx.state.zrem(&args[0], args[1..].to_vec())?;
+    Ok(Reply::Integer(removed as i64))
+}
+
+/// ZSCORE key member
+fn zscore(args: &[Vec<u8>], ctx: &mut CommandContext) -> Result<Reply, RedisError> {
+    Ok(score_reply(ctx.state.zscore(&args[0], &args[1])?))
+}
+
+/// ZRANK / ZREVRANK key member
+fn zrank(args: &[Vec<u8>], ctx: &mut CommandContext, reverse: bool) -> Result<Reply, RedisError> {
+    match ctx.state.zrank(&args[0], &args[1], reverse)? {
+        Some(rank) => Ok(Reply::Integer(rank as i64)),
+        None => Ok(Reply::Nil),
+    }
+}
+
+/// ZRANGE key start stop [REV] [WITHSCORES] / ZREVRANGE key start stop [WITHSCORES]
+fn zrange(args: &[Vec<u8>], ctx: &mut CommandContext, mut reverse: bool) -> Result<Reply, RedisError> {
+    let start = parse_arg::<i64>(&args[1])?;
+    let stop = parse_arg::<i64>(&args[2])?;
+    let rev_allowed = !reverse;
+    let mut withscores = false;
+
+    for option in &args[3..] {
+        match option.to_ascii_uppercase().as_slice() {
+            b"WITHSCORES" => withscores = true,
+            b"REV" if rev_allowed => reverse = true,
+            _ => return Err(syntax_error()),
+        }
+    }
+
+    // WITHSCORES interleaves member, score
+    let entries = ctx.state.zrange(&args[0], start, stop, reverse)?;
+    Ok(entries_reply(entries, withscores))
+}
+
+/// ZRANGEBYSCORE key min max / ZREVRANGEBYSCORE key max min [WITHSCORES] [LIMIT offset count]
+fn zrange_by_score(args: &[Vec<u8>], ctx: &mut CommandContext, reverse: bool) -> Result<Reply, RedisError> {
+    let (min, max) = if reverse { (&args[2], &args[1]) } else { (&args[1], &args[2]) };
+    let (min, max) = (parse_score_bound(min)?, parse_score_bound(max)?);
+    let mut withscores = false;
+    let (mut offset, mut count) = (0, None);
+    
+    let mut options = args[3..].iter();
+    while let Some(option) = options.next() {
+        match option.to_ascii_uppercase().as_slice() {
+            b"WITHSCORES" => withscores = true,
+            b"LIMIT" => {
+                let (Some(start), Some(limit)) = (options.next(), options.next()) else {
+                    return Err(syntax_error());
+                };
+                offset = parse_arg::<i64>(start)?;
+                count = Some(parse_arg::<i64>(limit)?);
+            }
+            _ => return Err(syntax_error()),
+        }
+    }
+    
+    // A negative offset matches nothing; a negative count means all the rest
+    if offset < 0 {
+        return Ok(Reply::Array(Vec::new()));
+    }
+    let count = count.and_then(|count| usize::try_from(count).ok());
+    let entries = ctx.state.zrange_by_score(&args[0], min, max, reverse, offset as usize, count)?;
+    Ok(entries_reply(entries, withscores))
+}
+
+/// ZCARD key
+fn zcard(args: &[Vec<u8>], ctx: &mut CommandContext) -> Result<Reply, RedisError> {
+    Ok(Reply::Integer(ctx.state.zcard(&args[0])? as i64))
+}
+
+/// ZSCAN key cursor [MATCH pattern] [COUNT count] - members interleaved with scores
+fn zscan(args: &[Vec<u8>], ctx: &mut CommandContext) -> Result<Reply, RedisError> {
+    let (cursor, pattern, count) = parse_scan_args(&args[1..])?;
+    let (next, entries) = ctx.state.zscan(&args[0], cursor, pattern, count)?;
+    let items = entries.into_iter()
+        .flat_map(|(member, score)| [member, score.to_string().into_bytes()])
+        .collect();
+    Ok(scan_reply(next, items))
+}
@@ -0,0 +1,49 @@
+// Fuzzing entry points - feed arbitrary bytes to the protocol parsers, in memory
+// Built with the `fuzzing` feature for the cargo-fuzz targets under fuzz/
+use std::future::Future;
+
+use tokio::io::{join, sink, Join, Sink};
+
+use crate::network::memcached::MemcachedHandler;
+use crate::network::redis::RedisHandler;
+use crate::network::tcp::{ProtocolError, TcpConnection};
+
+/// Connection reading `data` and discarding writes
+type MemoryConnection<'a> = TcpConnection<Join<&'a [u8], Sink>>;
+
+/// Parse `data` as RESP commands, the way the connection loop would, until it's used up
+pub fn redis_commands(data: &[u8]) {
+    run(data, |mut conn| async move {
+        loop {
+            match RedisHandler::parse_command(&mut conn).await {
+                Ok(Some(_)) => {}
+                Ok(None) => break,
+                Err(e) if e.is::<ProtocolError>() => break,
+                Err(_) => {}
+            }
+        }
+    });
+}
+
+/// Parse `data` as Memcached text commands until it's used up
+pub fn memcached_commands(data: &[u8]) {
+    run(data, |mut conn| async move {
+        loop {
+            match MemcachedHandler::parse_command(&mut conn).await {
+                Ok(Some(_)) => {}
+                Ok(None) => break,
+                Err(e) if e.is::<ProtocolError>() => break,
+                Err(_) => {}
+            }
+        }
+    });
+}
+
+/// Drive `parse` to completion over a connection reading `data`
+/// Every parse either consumes input or ends the loop, so this always returns
+fn run<'a, F: Future<Output = ()>>(data: &'a [u8], parse: impl FnOnce(MemoryConnection<'a>) -> F) {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .expect("current-thread runtime");
+    runtime.block_on(parse(TcpConnection::new(join(data, sink()))));
+}
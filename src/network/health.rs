@@ -0,0 +1,184 @@
+// src/network/health.rs - HEALTH AND READINESS PROBES
+// A tiny HTTP/1.1 listener for orchestrators, one request per connection:
+//   GET /healthz  200 while the process serves requests at all
+//   GET /readyz   200 when every readiness sub-check passes, 503 otherwise
+// Bodies are JSON: {"status":"ready","checks":{"aof_replay":true,...}}
+
+use std::sync::Arc;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::net::TcpListener;
+
+use crate::core::state::GlobalState;
+use crate::util::json::Json;
+
+/// Most bytes of request line and headers read - probes send a few hundred
+const MAX_REQUEST_HEAD: usize = 8 * 1024;
+
+/// HealthServer - Listener for the health and readiness endpoints
+#[derive(Clone)]
+pub struct HealthServer {
+    // Address to bind
+    host: String,
+    port: u16,
+    
+    // Database state the checks read
+    state: Arc<GlobalState>,
+}
+
+impl HealthServer {
+    /// Create health server for `state`
+    pub fn new(host: String, port: u16, state: Arc<GlobalState>) -> Self {
+        Self { host, port, state }
+    }
+    
+    /// Run the health listener until a shutdown is requested
+    pub async fn run(&self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let addr = format!("{}:{}", self.host, self.port);
+        let listener = TcpListener::bind(&addr).await?;
+        println!("Health checks on {}", addr);
+        
+        let mut shutdown = self.state.shutdown_signal();
+        loop {
+            let accepted = tokio::select! {
+                accepted = listener.accept() => accepted,
+                _ = shutdown.wait_for(|requested| *requested) => break,
+            };
+            
+            match accepted {
+                Ok((socket, addr)) => {
+                    let state = self.state.clone();
+                    tokio::spawn(async move {
+                        if let Err(e) = serve(&state, socket).await {
+                            eprintln!("Health connection error: {}: {}", addr, e);
+                        }
+                    });
+                }
+                Err(e) => eprintln!("Health accept error: {}", e),
+            }
+        }
+        
+        Ok(())
+    }
+}
+
+/// Answer one HTTP request and close the connection
+async fn serve<S: AsyncRead + AsyncWrite + Unpin>(state: &GlobalState, stream: S) -> std::io::Result<()> {
+    let mut stream = BufReader::new(stream);
+    let mut head = (&mut stream).take(MAX_REQUEST_HEAD as u64);
+    
+    // Request line, then headers up to the blank line - their contents are ignored
+    let mut request_line = String::new();
+    head.read_line(&mut request_line).await?;
+    let mut complete = false;
+    let mut header = String::new();
+    while head.read_line(&mut header).await? > 0 {
+        if header.trim_end().is_empty() {
+            complete = true;
+            break;
+        }
+        header.clear();
+    }
+    
+    let mut parts = request_line.split_whitespace();
+    let (method, path) = (parts.next().unwrap_or(""), parts.next().unwrap_or(""));
+    let (status, body) = if !complete {
+        ("400 Bad Request", error_body("malformed or oversized request"))
+    } else if method != "GET" && method != "HEAD" {
+        ("405 Method Not Allowed", error_body("only GET and HEAD are supported"))
+    } else {
+        route(state, path.split('?').next().unwrap_or(path))
+    };
+    
+    let body = format!("{}\n", body);
+    let mut response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        status,
+        body.len()
+    );
+    if method != "HEAD" {
+        response.push_str(&body);
+    }
+    stream.get_mut().write_all(response.as_bytes()).await?;
+    stream.get_mut().shutdown().await
+}
+
+/// Status line and body for an endpoint
+fn route(state: &GlobalState, path: &str) -> (&'static str, Json) {
+    match path {
+        "/healthz" => ("200 OK", Json::Object(vec![("status".to_string(), Json::String("ok".to_string()))])),
+        "/readyz" => {
+            let checks = state.readiness();
+            let ready = checks.iter().all(|(_, ok)| *ok);
+            let body = Json::Object(vec![
+                ("status".to_string(), Json::String(if ready { "ready" } else { "not ready" }.to_string())),
+                ("checks".to_string(), Json::Object(checks.into_iter()
+                    .map(|(name, ok)| (name.to_string(), Json::Bool(ok)))
+                    .collect())),
+            ]);
+            (if ready { "200 OK" } else { "503 Service Unavailable" }, body)
+        }
+        _ => ("404 Not Found", error_body("not found")),
+    }
+}
+
+/// Error body
+fn error_body(message: &str) -> Json {
+    Json::Object(vec![("error".to_string(), Json::String(message.to_string()))])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::persistence::aof::AppendOnlyFile;
+    use crate::storage::memory::{MaxMemoryPolicy, MemTable};
+    
+    /// Send a raw request and return the whole response
+    async fn request(state: &GlobalState, raw: &str) -> String {
+        let (mut client, server) = tokio::io::duplex(64 * 1024);
+        client.write_all(raw.as_bytes()).await.unwrap();
+        client.shutdown().await.unwrap();
+        serve(state, server).await.unwrap();
+        let mut response = String::new();
+        client.read_to_string(&mut response).await.unwrap();
+        response
+    }
+    
+    #[tokio::test]
+    async fn test_health_and_readiness() {
+        let dir = tempfile::tempdir().unwrap();
+        let aof = AppendOnlyFile::new(dir.path().join("health.aof")).unwrap();
+        let flush_failed = aof.failure_flag();
+        let state = GlobalState::new(Arc::new(MemTable::new()), aof)
+            .with_memory_limit(1, MaxMemoryPolicy::NoEviction);
+        
+        let response = request(&state, "GET /healthz HTTP/1.1\r\nHost: db\r\n\r\n").await;
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.ends_with("\r\n\r\n{\"status\":\"ok\"}\n"));
+        
+        // Not listening yet
+        let response = request(&state, "GET /readyz HTTP/1.1\r\n\r\n").await;
+        assert!(response.starts_with("HTTP/1.1 503 Service Unavailable\r\n"));
+        assert!(response.contains(r#""checks":{"aof_replay":true,"listener":false,"memory":true,"aof_writes":true}"#));
+        
+        state.set_listening(true);
+        let response = request(&state, "GET /readyz?verbose HTTP/1.1\r\n\r\n").await;
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.contains(r#"{"status":"ready","#));
+        
+        // A failed background flush, read without the AOF lock
+        flush_failed.store(true, std::sync::atomic::Ordering::Relaxed);
+        let response = request(&state, "GET /readyz HTTP/1.1\r\n\r\n").await;
+        assert!(response.contains(r#""aof_writes":false"#));
+        flush_failed.store(false, std::sync::atomic::Ordering::Relaxed);
+        
+        // Over the memory limit
+        state.engine().set(b"key", b"value".to_vec(), None).unwrap();
+        let response = request(&state, "HEAD /readyz HTTP/1.1\r\n\r\n").await;
+        assert!(response.starts_with("HTTP/1.1 503 Service Unavailable\r\n"));
+        assert!(response.ends_with("\r\n\r\n"));
+        
+        assert!(request(&state, "GET /metrics HTTP/1.1\r\n\r\n").await.starts_with("HTTP/1.1 404 Not Found\r\n"));
+        assert!(request(&state, "POST /readyz HTTP/1.1\r\n\r\n").await.starts_with("HTTP/1.1 405 Method Not Allowed\r\n"));
+        assert!(request(&state, "GET /readyz HTTP/1.1\r\n").await.starts_with("HTTP/1.1 400 Bad Request\r\n"));
+    }
+}
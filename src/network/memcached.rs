@@ -1,45 +1,114 @@
 // Memcached protocol implementation for legacy compatibility
 use std::sync::Arc;
-use std::time::Duration;
-use tokio::io::AsyncReadExt;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite};
 
+use crate::core::acl::User;
 use crate::core::state::GlobalState;
-use crate::network::tcp::{TcpConnection, ProtocolHandler};
+use crate::network::commands::CommandRegistry;
+use crate::network::tcp::{trace_bytes, ArgTooLarge, TcpConnection, ProtocolError, ProtocolHandler};
+
+/// Longest command line read before giving up on finding its CRLF
+const MAX_COMMAND_LINE: usize = 2048;
+
+/// Exptimes above this many seconds (30 days) are absolute unix times
+const RELATIVE_EXPTIME_MAX: u32 = 60 * 60 * 24 * 30;
 
 /// Memcached protocol handler
 pub struct MemcachedHandler {
     // Shared database state
     state: Arc<GlobalState>,
+    
+    // Connection id, for trace lines
+    client_id: u64,
+    
+    // ACL user every command runs as - Memcached has no AUTH, so the default user (None = refused)
+    user: Option<Arc<User>>,
+    
+    // Log each command and reply to stderr
+    trace: bool,
 }
 
 /// Memcached command parsed from text protocol
-enum MemcachedCommand {
+/// Keys are kept as the exact bytes the client sent - only whitespace delimits them
+pub(crate) enum MemcachedCommand {
     // get <key>
-    Get(String),
+    Get(Vec<u8>),
     
     // set <key> <flags> <exptime> <bytes> [noreply]\r\n<data>\r\n
-    Set(String, u32, u32, Vec<u8>, bool),
+    Set(Vec<u8>, u32, u32, Vec<u8>, bool),
     
     // delete <key> [noreply]
-    Delete(String, bool),
+    Delete(Vec<u8>, bool),
+    
+    // incr <key> <value> [noreply]
+    Incr(Vec<u8>, u64, bool),
+    
+    // decr <key> <value> [noreply]
+    Decr(Vec<u8>, u64, bool),
+    
+    // touch <key> <exptime> [noreply]
+    Touch(Vec<u8>, u32, bool),
+    
+    // flush_all [delay] [noreply]
+    FlushAll(u32, bool),
     
     // stats
     Stats,
     
     // version
     Version,
+    
+    // quit
+    Quit,
 }
 
 impl MemcachedHandler {
-    /// Create new Memcached protocol handler
-    pub fn new(state: Arc<GlobalState>) -> Self {
-        Self { state }
+    /// Create new Memcached protocol handler for connection `client_id`
+    pub fn new(state: Arc<GlobalState>, client_id: u64) -> Self {
+        let trace = state.trace_commands();
+        let user = state.acl().initial_user();
+        Self { state, client_id, user, trace }
+    }
+    
+    /// Write one whole reply, logging it first when tracing
+    async fn reply<S: AsyncRead + AsyncWrite + Unpin>(
+        &self,
+        conn: &mut TcpConnection<S>,
+        reply: &[u8]
+    ) -> Result<(), std::io::Error> {
+        if self.trace {
+            eprintln!("TRACE client {} < {}", self.client_id, trace_bytes(reply));
+        }
+        conn.write_all(reply).await
     }
     
-    /// Parse Memcached text command line
-    async fn parse_command_line(
-        conn: &mut TcpConnection
-    ) -> Result<Option<String>, Box<dyn std::error::Error + Send + Sync>> {
+    /// Apply incr/decr and reply with the new value or NOT_FOUND
+    async fn incr_decr<S: AsyncRead + AsyncWrite + Unpin>(
+        &self,
+        conn: &mut TcpConnection<S>,
+        key: &[u8],
+        delta: u64,
+        decrement: bool,
+        noreply: bool
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let reply = match self.state.incr_by_unsigned(key, delta, decrement) {
+            Ok(Some(value)) => format!("{}\r\n", value),
+            Ok(None) => "NOT_FOUND\r\n".to_string(),
+            Err(e) => format!("CLIENT_ERROR {}\r\n", e),
+        };
+        
+        if !noreply {
+            self.reply(conn, reply.as_bytes()).await?;
+        }
+        
+        Ok(())
+    }
+    
+    /// Read one Memcached text command line (without the CRLF)
+    async fn parse_command_line<S: AsyncRead + AsyncWrite + Unpin>(
+        conn: &mut TcpConnection<S>
+    ) -> Result<Option<Vec<u8>>, Box<dyn std::error::Error + Send + Sync>> {
         let mut line = Vec::new();
         let mut buf = [0u8; 1];
         
@@ -63,18 +132,24 @@ impl MemcachedHandler {
                 line.truncate(line.len() - 2);
                 break;
             }
+            
+            // Don't buffer an endless line
+            if line.len() > MAX_COMMAND_LINE {
+                return Err(ProtocolError("line too long".to_string()).into());
+            }
         }
         
-        // Convert to string
-        let cmd_line = String::from_utf8(line)
-            .map_err(|_| "Invalid UTF-8 in command")?;
-            
-        Ok(Some(cmd_line))
+        Ok(Some(line))
+    }
+    
+    /// Parse a numeric argument - numbers are ASCII, so non-UTF-8 just fails to parse
+    fn parse_number<T: std::str::FromStr>(arg: &[u8]) -> Option<T> {
+        std::str::from_utf8(arg).ok()?.parse().ok()
     }
     
     /// Parse full command including data for SET
-    async fn parse_command(
-        conn: &mut TcpConnection
+    pub(crate) async fn parse_command<S: AsyncRead + AsyncWrite + Unpin>(
+        conn: &mut TcpConnection<S>
     ) -> Result<Option<MemcachedCommand>, Box<dyn std::error::Error + Send + Sync>> {
         // Read command line
         let line = match Self::parse_command_line(conn).await? {
@@ -82,34 +157,45 @@ impl MemcachedHandler {
             None => return Ok(None),
         };
         
-        // Split into parts
-        let parts: Vec<&str> = line.split_whitespace().collect();
+        // Split into parts on whitespace, leaving every other key byte as sent
+        let parts: Vec<&[u8]> = line.split(u8::is_ascii_whitespace).filter(|part| !part.is_empty()).collect();
         if parts.is_empty() {
             return Err("Empty command".into());
         }
+        let show = |arg: &[u8]| String::from_utf8_lossy(arg).into_owned();
         
         // Parse based on command
-        match parts[0].to_lowercase().as_str() {
-            "get" if parts.len() >= 2 => {
-                Ok(Some(MemcachedCommand::Get(parts[1].to_string())))
+        match parts[0].to_ascii_lowercase().as_slice() {
+            b"get" if parts.len() >= 2 => {
+                Ok(Some(MemcachedCommand::Get(parts[1].to_vec())))
             }
-            "set" if parts.len() >= 5 => {
+            b"set" if parts.len() >= 5 => {
                 // Parse flags, exptime, bytes
-                let flags = parts[2].parse::<u32>()
-                    .map_err(|_| format!("Invalid flags: {}", parts[2]))?;
+                let flags = Self::parse_number::<u32>(parts[2])
+                    .ok_or_else(|| format!("Invalid flags: {}", show(parts[2])))?;
                     
-                let exptime = parts[3].parse::<u32>()
-                    .map_err(|_| format!("Invalid exptime: {}", parts[3]))?;
+                let exptime = Self::parse_number::<u32>(parts[3])
+                    .ok_or_else(|| format!("Invalid exptime: {}", show(parts[3])))?;
                     
-                let bytes = parts[4].parse::<usize>()
-                    .map_err(|_| format!("Invalid bytes: {}", parts[4]))?;
+                let bytes = Self::parse_number::<usize>(parts[4])
+                    .ok_or_else(|| format!("Invalid bytes: {}", show(parts[4])))?;
                 
                 // Check for noreply
-                let noreply = parts.len() >= 6 && parts[5] == "noreply";
+                let noreply = parts.len() >= 6 && parts[5] == b"noreply";
+                
+                // Refuse absurd lengths before allocating for them
+                if conn.check_bulk_len(bytes as u64).is_err() {
+                    return Err(ProtocolError("object too large for cache".to_string()).into());
+                }
+                
+                // Over the value size limit - skip the block and its CRLF, the stream stays framed
+                if conn.arg_too_large(bytes) {
+                    conn.skip_bulk(bytes + 2).await?;
+                    return Err(ArgTooLarge.into());
+                }
                 
                 // Read data
-                let mut data = vec![0u8; bytes];
-                conn.read_exact(&mut data).await?;
+                let data = conn.read_bulk(bytes).await?;
                 
                 // Read trailing \r\n
                 let mut crlf = [0u8; 2];
@@ -120,39 +206,111 @@ impl MemcachedHandler {
                 }
                 
                 Ok(Some(MemcachedCommand::Set(
-                    parts[1].to_string(),
+                    parts[1].to_vec(),
                     flags,
                     exptime,
                     data,
                     noreply
                 )))
             }
-            "delete" if parts.len() >= 2 => {
+            b"delete" if parts.len() >= 2 => {
                 // Check for noreply
-                let noreply = parts.len() >= 3 && parts[2] == "noreply";
+                let noreply = parts.len() >= 3 && parts[2] == b"noreply";
                 
                 Ok(Some(MemcachedCommand::Delete(
-                    parts[1].to_string(),
+                    parts[1].to_vec(),
                     noreply
                 )))
             }
-            "stats" => {
+            b"incr" | b"decr" if parts.len() >= 3 => {
+                let delta = Self::parse_number::<u64>(parts[2])
+                    .ok_or("invalid numeric delta argument")?;
+                
+                // Check for noreply
+                let noreply = parts.len() >= 4 && parts[3] == b"noreply";
+                
+                if parts[0].eq_ignore_ascii_case(b"incr") {
+                    Ok(Some(MemcachedCommand::Incr(parts[1].to_vec(), delta, noreply)))
+                } else {
+                    Ok(Some(MemcachedCommand::Decr(parts[1].to_vec(), delta, noreply)))
+                }
+            }
+            b"touch" if parts.len() >= 3 => {
+                let exptime = Self::parse_number::<u32>(parts[2])
+                    .ok_or_else(|| format!("Invalid exptime: {}", show(parts[2])))?;
+                
+                // Check for noreply
+                let noreply = parts.len() >= 4 && parts[3] == b"noreply";
+                
+                Ok(Some(MemcachedCommand::Touch(parts[1].to_vec(), exptime, noreply)))
+            }
+            b"flush_all" => {
+                // Optional delay, optional noreply
+                let noreply = parts.last() == Some(&&b"noreply"[..]);
+                let delay = match parts.get(1) {
+                    Some(&arg) if arg != b"noreply" => Self::parse_number::<u32>(arg)
+                        .ok_or_else(|| format!("Invalid delay: {}", show(arg)))?,
+                    _ => 0,
+                };
+                
+                Ok(Some(MemcachedCommand::FlushAll(delay, noreply)))
+            }
+            b"stats" => {
                 Ok(Some(MemcachedCommand::Stats))
             }
-            "version" => {
+            b"version" => {
                 Ok(Some(MemcachedCommand::Version))
             }
+            b"quit" => {
+                Ok(Some(MemcachedCommand::Quit))
+            }
             _ => {
-                Err(format!("Unknown command: {}", parts[0]).into())
+                Err(format!("Unknown command: {}", show(parts[0])).into())
             }
         }
     }
 }
 
+impl MemcachedCommand {
+    /// Redis command whose ACL permission the op needs (None = always allowed)
+    fn acl_command(&self) -> Option<&'static str> {
+        match self {
+            MemcachedCommand::Get(_) => Some("get"),
+            // Touch only changes the TTL, but there's no EXPIRE to check it as
+            MemcachedCommand::Set(..) | MemcachedCommand::Touch(..) => Some("set"),
+            MemcachedCommand::Delete(..) => Some("del"),
+            MemcachedCommand::Incr(..) => Some("incrby"),
+            MemcachedCommand::Decr(..) => Some("decrby"),
+            MemcachedCommand::FlushAll(..) => Some("flushall"),
+            MemcachedCommand::Stats => Some("info"),
+            MemcachedCommand::Version | MemcachedCommand::Quit => None,
+        }
+    }
+    
+    /// Command as it appears in a trace line, keys and data rendered by trace_bytes
+    fn trace_line(&self) -> String {
+        let noreply = |noreply: bool| if noreply { " noreply" } else { "" };
+        match self {
+            MemcachedCommand::Get(key) => format!("get {}", trace_bytes(key)),
+            MemcachedCommand::Set(key, flags, exptime, value, nr) => {
+                format!("set {} {} {} {}{}", trace_bytes(key), flags, exptime, trace_bytes(value), noreply(*nr))
+            }
+            MemcachedCommand::Delete(key, nr) => format!("delete {}{}", trace_bytes(key), noreply(*nr)),
+            MemcachedCommand::Incr(key, delta, nr) => format!("incr {} {}{}", trace_bytes(key), delta, noreply(*nr)),
+            MemcachedCommand::Decr(key, delta, nr) => format!("decr {} {}{}", trace_bytes(key), delta, noreply(*nr)),
+            MemcachedCommand::Touch(key, exptime, nr) => format!("touch {} {}{}", trace_bytes(key), exptime, noreply(*nr)),
+            MemcachedCommand::FlushAll(delay, nr) => format!("flush_all {}{}", delay, noreply(*nr)),
+            MemcachedCommand::Stats => "stats".to_string(),
+            MemcachedCommand::Version => "version".to_string(),
+            MemcachedCommand::Quit => "quit".to_string(),
+        }
+    }
+}
+
 impl ProtocolHandler for MemcachedHandler {
-    async fn handle_connection(
+    async fn handle_connection<S: AsyncRead + AsyncWrite + Unpin>(
         &mut self,
-        conn: &mut TcpConnection
+        conn: &mut TcpConnection<S>
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         println!("Handling Memcached protocol connection");
         
@@ -169,45 +327,76 @@ impl ProtocolHandler for MemcachedHandler {
                 Err(e) => {
                     eprintln!("Error parsing command: {}", e);
                     
+                    if e.is::<ArgTooLarge>() {
+                        self.reply(conn, b"SERVER_ERROR object too large for cache\r\n").await?;
+                        continue;
+                    }
+                    
+                    // The data block is still unread - reply and drop the client
+                    if e.is::<ProtocolError>() {
+                        self.reply(conn, format!("SERVER_ERROR {}\r\n", e).as_bytes()).await?;
+                        return Ok(());
+                    }
+                    
                     // CRITICAL FIX: Convert error handling to avoid Send issue
                     // Clone error message to String (which is Send) instead of using e across await
                     let error_message = format!("ERROR {}\r\n", e);
-                    conn.write_all(error_message.as_bytes()).await?;
+                    self.reply(conn, error_message.as_bytes()).await?;
                     continue;
                 }
             };
             
+            if self.trace {
+                eprintln!("TRACE client {} > {}", self.client_id, cmd.trace_line());
+            }
+            
+            // Same ACL checks as dispatch, with flags taken from the matching Redis command
+            let Some(user) = &self.user else {
+                self.reply(conn, b"SERVER_ERROR authentication required\r\n").await?;
+                return Ok(());
+            };
+            if let Some(name) = cmd.acl_command() {
+                let flags = CommandRegistry::global().get(name).map_or(&[][..], |command| command.flags());
+                if !user.allows(name, flags) {
+                    let reply = format!("CLIENT_ERROR NOPERM User {} has no permissions to run the '{}' command\r\n", user.name, name);
+                    self.reply(conn, reply.as_bytes()).await?;
+                    continue;
+                }
+            }
+            
             // Execute command
+            self.state.record_command();
             match cmd {
                 MemcachedCommand::Get(key) => {
                     // Get value from storage
-                    match self.state.get(key.as_bytes()) {
+                    match self.state.get(&key) {
                         Some(value) => {
                             // Format: VALUE <key> <flags> <bytes>\r\n<data>\r\nEND\r\n
-                            conn.write_all(format!("VALUE {} 0 {}\r\n", key, value.len()).as_bytes()).await?;
-                            conn.write_all(&value).await?;
-                            conn.write_all(b"\r\n").await?;
-                            conn.write_all(b"END\r\n").await?;
+                            let mut reply = Vec::with_capacity(key.len() + value.len() + 32);
+                            reply.extend_from_slice(b"VALUE ");
+                            reply.extend_from_slice(&key);
+                            reply.extend_from_slice(format!(" 0 {}\r\n", value.len()).as_bytes());
+                            reply.extend_from_slice(&value);
+                            reply.extend_from_slice(b"\r\nEND\r\n");
+                            self.reply(conn, &reply).await?;
                         }
                         None => {
                             // Item not found - just return END
-                            conn.write_all(b"END\r\n").await?;
+                            self.reply(conn, b"END\r\n").await?;
                         }
                     }
                 }
                 MemcachedCommand::Set(key, _flags, exptime, value, noreply) => {
-                    // Convert exptime to Duration if non-zero
-                    let ttl = if exptime > 0 {
-                        Some(Duration::from_secs(exptime as u64))
-                    } else {
-                        None
+                    // An absolute exptime already past stores an item that is gone at once
+                    let result = match exptime_ttl(exptime) {
+                        Some(ttl) if ttl.is_zero() => self.state.delete(&key).map(|_| ()),
+                        ttl => self.state.set(&key, value, ttl),
                     };
                     
-                    // Set value in storage
-                    match self.state.set(key.as_bytes(), value, ttl) {
+                    match result {
                         Ok(_) => {
                             if !noreply {
-                                conn.write_all(b"STORED\r\n").await?;
+                                self.reply(conn, b"STORED\r\n").await?;
                             }
                         }
                         Err(e) => {
@@ -215,22 +404,22 @@ impl ProtocolHandler for MemcachedHandler {
                                 // CRITICAL FIX: Convert error handling to avoid Send issue
                                 // Create error message string first, then await
                                 let error_message = format!("SERVER_ERROR {}\r\n", e);
-                                conn.write_all(error_message.as_bytes()).await?;
+                                self.reply(conn, error_message.as_bytes()).await?;
                             }
                         }
                     }
                 }
                 MemcachedCommand::Delete(key, noreply) => {
                     // Delete value from storage
-                    match self.state.delete(key.as_bytes()) {
+                    match self.state.delete(&key) {
                         Ok(true) => {
                             if !noreply {
-                                conn.write_all(b"DELETED\r\n").await?;
+                                self.reply(conn, b"DELETED\r\n").await?;
                             }
                         }
                         Ok(false) => {
                             if !noreply {
-                                conn.write_all(b"NOT_FOUND\r\n").await?;
+                                self.reply(conn, b"NOT_FOUND\r\n").await?;
                             }
                         }
                         Err(e) => {
@@ -238,15 +427,61 @@ impl ProtocolHandler for MemcachedHandler {
                                 // CRITICAL FIX: Convert error handling to avoid Send issue
                                 // Create error message string first, then await
                                 let error_message = format!("SERVER_ERROR {}\r\n", e);
-                                conn.write_all(error_message.as_bytes()).await?;
+                                self.reply(conn, error_message.as_bytes()).await?;
                             }
                         }
                     }
                 }
+                MemcachedCommand::Incr(key, delta, noreply) => {
+                    self.incr_decr(conn, &key, delta, false, noreply).await?;
+                }
+                MemcachedCommand::Decr(key, delta, noreply) => {
+                    self.incr_decr(conn, &key, delta, true, noreply).await?;
+                }
+                MemcachedCommand::Touch(key, exptime, noreply) => {
+                    // Zero exptime clears the TTL, same as set
+                    let touched = match exptime_ttl(exptime) {
+                        Some(ttl) if ttl.is_zero() => self.state.delete(&key),
+                        ttl => self.state.set_expiry(&key, ttl),
+                    };
+                    
+                    let reply = match touched {
+                        Ok(true) => "TOUCHED\r\n".to_string(),
+                        Ok(false) => "NOT_FOUND\r\n".to_string(),
+                        Err(e) => format!("SERVER_ERROR {}\r\n", e),
+                    };
+                    
+                    if !noreply {
+                        self.reply(conn, reply.as_bytes()).await?;
+                    }
+                }
+                MemcachedCommand::FlushAll(delay, noreply) => {
+                    let reply = if delay == 0 {
+                        match self.state.flush_all() {
+                            Ok(_) => "OK\r\n".to_string(),
+                            Err(e) => format!("SERVER_ERROR {}\r\n", e),
+                        }
+                    } else {
+                        // Delayed flush runs in the background, reply right away
+                        let state = self.state.clone();
+                        tokio::spawn(async move {
+                            tokio::time::sleep(Duration::from_secs(delay as u64)).await;
+                            if let Err(e) = state.flush_all() {
+                                eprintln!("Delayed flush_all failed: {}", e);
+                            }
+                        });
+                        "OK\r\n".to_string()
+                    };
+                    
+                    if !noreply {
+                        self.reply(conn, reply.as_bytes()).await?;
+                    }
+                }
                 MemcachedCommand::Stats => {
                     // Get system stats
                     let (uptime, reads, writes, deletes, read_lat, write_lat) = 
                         self.state.get_stats();
+                    let clients = self.state.connection_stats();
                         
                     // Format stats response
                     let stats = [
@@ -258,17 +493,25 @@ impl ProtocolHandler for MemcachedHandler {
                         format!("STAT cmd_delete {}\r\n", deletes),
                         format!("STAT read_latency_ns {}\r\n", read_lat),
                         format!("STAT write_latency_ns {}\r\n", write_lat),
+                        format!("STAT curr_connections {}\r\n", clients.connected_clients),
+                        format!("STAT total_connections {}\r\n", clients.total_connections),
+                        format!("STAT rejected_connections {}\r\n", clients.rejected_connections),
+                        format!("STAT total_commands {}\r\n", clients.total_commands),
+                        format!("STAT ops_per_sec {}\r\n", clients.ops_per_sec),
+                        format!("STAT aof_pending_fsync {}\r\n", self.state.aof_pending_fsync()),
                         "END\r\n".to_string(),
                     ];
                     
                     // Send stats
-                    for stat in stats {
-                        conn.write_all(stat.as_bytes()).await?;
-                    }
+                    self.reply(conn, stats.concat().as_bytes()).await?;
                 }
                 MemcachedCommand::Version => {
                     // Send version
-                    conn.write_all(b"VERSION 0.1.0\r\n").await?;
+                    self.reply(conn, b"VERSION 0.1.0\r\n").await?;
+                }
+                MemcachedCommand::Quit => {
+                    // No reply - the server just closes the connection
+                    break;
                 }
             }
         }
@@ -276,3 +519,103 @@ impl ProtocolHandler for MemcachedHandler {
         Ok(())
     }
 }
+
+/// TTL a memcached exptime asks for (None = never expires)
+/// Past 30 days it's an absolute unix time; one already past gives a zero TTL
+fn exptime_ttl(exptime: u32) -> Option<Duration> {
+    if exptime == 0 {
+        return None;
+    }
+    if exptime <= RELATIVE_EXPTIME_MAX {
+        return Some(Duration::from_secs(exptime as u64));
+    }
+    
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+    Some(Duration::from_secs(exptime as u64).saturating_sub(now))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::acl::Acl;
+    use crate::persistence::aof::AppendOnlyFile;
+    use crate::storage::memory::MemTable;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::{TcpListener, TcpStream};
+    
+    #[tokio::test]
+    async fn test_binary_keys_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let aof = AppendOnlyFile::new(dir.path().join("memcached.aof")).unwrap();
+        let state = Arc::new(GlobalState::new(Arc::new(MemTable::new()), aof));
+        
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let mut client = TcpStream::connect(listener.local_addr().unwrap()).await.unwrap();
+        let (socket, _) = listener.accept().await.unwrap();
+        let mut handler = MemcachedHandler::new(state.clone(), 1);
+        let server = tokio::spawn(async move {
+            let mut conn = TcpConnection::new(socket);
+            handler.handle_connection(&mut conn).await.unwrap();
+        });
+        
+        // Not valid UTF-8, and stored under exactly these bytes
+        let key = b"caf\xe9:\x01";
+        client.write_all(&[&b"set "[..], key, b" 0 0 2\r\nhi\r\nget ", key, b"\r\nquit\r\n"].concat()).await.unwrap();
+        let mut replies = Vec::new();
+        client.read_to_end(&mut replies).await.unwrap();
+        server.await.unwrap();
+        
+        assert_eq!(replies, [&b"STORED\r\nVALUE "[..], key, b" 0 2\r\nhi\r\nEND\r\n"].concat());
+        assert_eq!(state.get(key).as_deref(), Some(&b"hi"[..]));
+    }
+    
+    #[tokio::test]
+    async fn test_acl_enforced() {
+        let dir = tempfile::tempdir().unwrap();
+        let session = |acl: &str, input: &'static [u8]| {
+            let aof = AppendOnlyFile::new(dir.path().join("acl.aof")).unwrap();
+            let state = GlobalState::new(Arc::new(MemTable::new()), aof).with_acl(Acl::parse(acl).unwrap());
+            async move {
+                let mut handler = MemcachedHandler::new(Arc::new(state), 1);
+                let (mut client, server) = tokio::io::duplex(64 * 1024);
+                client.write_all(input).await.unwrap();
+                client.shutdown().await.unwrap();
+                handler.handle_connection(&mut TcpConnection::new(server)).await.unwrap();
+                let mut replies = String::new();
+                client.read_to_string(&mut replies).await.unwrap();
+                replies
+            }
+        };
+        
+        // The default user's rules apply to every op
+        let replies = session("user default on nopass +@read", b"get k\r\nset k 0 0 1\r\nv\r\nflush_all\r\nversion\r\n").await;
+        assert_eq!(replies, "END\r\n\
+            CLIENT_ERROR NOPERM User default has no permissions to run the 'set' command\r\n\
+            CLIENT_ERROR NOPERM User default has no permissions to run the 'flushall' command\r\n\
+            VERSION 0.1.0\r\n");
+        
+        // A default user that needs a password (or is off) can't be reached at all
+        let replies = session("user default on >secret +@all", b"get k\r\nget k\r\n").await;
+        assert_eq!(replies, "SERVER_ERROR authentication required\r\n");
+    }
+    
+    #[test]
+    fn test_trace_line() {
+        let set = MemcachedCommand::Set(b"user:1".to_vec(), 0, 60, b"\x00\x01".to_vec(), true);
+        assert_eq!(set.trace_line(), r#"set "user:1" 0 60 0x0001 noreply"#);
+        assert_eq!(MemcachedCommand::Get(b"a b".to_vec()).trace_line(), r#"get "a b""#);
+        assert_eq!(MemcachedCommand::FlushAll(0, false).trace_line(), "flush_all 0");
+    }
+    
+    #[test]
+    fn test_exptime_relative_and_absolute() {
+        assert_eq!(exptime_ttl(0), None);
+        assert_eq!(exptime_ttl(RELATIVE_EXPTIME_MAX), Some(Duration::from_secs(RELATIVE_EXPTIME_MAX as u64)));
+        
+        // Past 30 days the value is a unix time, so one from 1970 has already passed
+        assert_eq!(exptime_ttl(RELATIVE_EXPTIME_MAX + 1), Some(Duration::ZERO));
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() as u32;
+        let ttl = exptime_ttl(now + 100).unwrap();
+        assert!(ttl > Duration::from_secs(98) && ttl <= Duration::from_secs(100));
+    }
+}
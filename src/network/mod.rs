@@ -1,4 +1,10 @@
 pub mod tcp;
 pub mod redis;
+pub mod commands;
+pub mod reply;
 pub mod memcached;
-
+pub mod client;
+pub mod admin;
+pub mod health;
+#[cfg(feature = "fuzzing")]
+pub mod fuzz;
@@ -2,47 +2,51 @@
 // Redis protocol implementation for compatibility with Redis clients
 
 use std::sync::Arc;
-use std::time::Duration;
-use tokio::io::AsyncReadExt;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite};
 
 use crate::core::state::GlobalState;
-use crate::network::tcp::{TcpConnection, ProtocolHandler};
+use crate::core::pubsub::PubSubMessage;
+use crate::core::watchdog::Slot;
+use crate::util::latency::{LatencyMonitor, EVENT_COMMAND};
+use crate::network::commands::{Blocking, CommandContext, CommandRegistry};
+use crate::network::tcp::{trace_bytes, ArgTooLarge, TcpConnection, ProtocolError, ProtocolHandler};
+use crate::network::reply::{RedisError, Reply};
+
+/// Most command array slots allocated up front - larger arrays grow as elements arrive
+const MULTIBULK_PREALLOC: usize = 1024;
+
+/// Longest count line (`*<n>` / `$<n>`) read before giving up on finding its CRLF
+const MAX_COUNT_LINE: usize = 32;
 
 /// Redis protocol handler
 pub struct RedisHandler {
-    // Shared database state
-    state: Arc<GlobalState>,
-}
-
-/// Redis command parsed from RESP protocol
-#[derive(Debug)]
-enum RedisCommand {
-    // GET key
-    Get(Vec<u8>),
+    // Per-connection state the commands run against
+    ctx: CommandContext,
     
-    // SET key value [EX seconds]
-    Set(Vec<u8>, Vec<u8>, Option<Duration>),
+    // Command table used for dispatch
+    registry: &'static CommandRegistry,
     
-    // DEL key
-    Del(Vec<u8>),
-    
-    // PING
-    Ping,
-    
-    // INFO
-    Info,
+    // This connection's watchdog slot (None = watchdog off)
+    watchdog: Option<Arc<Slot>>,
 }
 
 impl RedisHandler {
-    /// Create new Redis protocol handler
-    pub fn new(state: Arc<GlobalState>) -> Self {
-        Self { state }
+    /// Create new Redis protocol handler for connection `client_id`
+    pub fn new(state: Arc<GlobalState>, client_id: u64) -> Self {
+        let ctx = CommandContext::new(state, client_id);
+        let watchdog = ctx.state.watchdog().map(|watchdog| watchdog.register(ctx.client_id));
+        Self {
+            ctx,
+            registry: CommandRegistry::global(),
+            watchdog,
+        }
     }
     
-    /// Parse Redis command from buffer
-    async fn parse_command(
-        conn: &mut TcpConnection
-    ) -> Result<Option<RedisCommand>, Box<dyn std::error::Error + Send + Sync>> {
+    /// Parse Redis command from buffer into its name and arguments
+    pub(crate) async fn parse_command<S: AsyncRead + AsyncWrite + Unpin>(
+        conn: &mut TcpConnection<S>
+    ) -> Result<Option<(String, Vec<Vec<u8>>)>, Box<dyn std::error::Error + Send + Sync>> {
         // Read first byte to determine RESP type
         let mut type_buf = [0u8; 1];
         let n = conn.read(&mut type_buf).await?;
@@ -55,10 +59,14 @@ impl RedisHandler {
         match type_buf[0] {
             b'*' => {
                 // Array - typical for Redis commands
-                let array_len = Self::parse_integer(conn).await?;
+                let array_len = Self::parse_integer(conn).await?.max(0);
                 
-                // Read array elements
-                let mut parts = Vec::with_capacity(array_len as usize);
+                // Refuse absurd counts, and don't trust even allowed ones for preallocation
+                conn.check_multibulk_len(array_len as u64)?;
+                
+                // Read array elements - an oversized one is skipped so the rest stay framed
+                let mut parts = Vec::with_capacity((array_len as usize).min(MULTIBULK_PREALLOC));
+                let mut oversized = false;
                 for _ in 0..array_len {
                     // Each element is a bulk string
                     let mut bulk_type = [0u8; 1];
@@ -70,55 +78,23 @@ impl RedisHandler {
                     }
                     
                     // Parse bulk string
-                    let bulk = Self::parse_bulk_string(conn).await?;
-                    parts.push(bulk);
+                    match Self::parse_bulk_string(conn).await? {
+                        Some(bulk) => parts.push(bulk),
+                        None => oversized = true,
+                    }
+                }
+                
+                if oversized {
+                    return Err(ArgTooLarge.into());
                 }
                 
-                // Parse command based on parts
+                // Split off the command name - dispatch looks it up in the registry
                 if parts.is_empty() {
                     return Err("Empty command".into());
                 }
                 
-                // Convert first part to uppercase for command name
-                let cmd = parts[0].to_ascii_uppercase();
-                
-                // Parse different commands
-                match cmd.as_slice() {
-                    b"GET" if parts.len() == 2 => {
-                        Ok(Some(RedisCommand::Get(parts[1].clone())))
-                    }
-                    b"SET" if parts.len() >= 3 => {
-                        // Check for EX option
-                        let mut ttl = None;
-                        if parts.len() >= 5 && parts[3].to_ascii_uppercase() == b"EX" {
-                            // Parse seconds
-                            if let Ok(secs) = std::str::from_utf8(&parts[4])
-                                .map_err(|_| "Invalid TTL value")?
-                                .parse::<u64>() {
-                                ttl = Some(Duration::from_secs(secs));
-                            }
-                        }
-                        
-                        Ok(Some(RedisCommand::Set(
-                            parts[1].clone(),
-                            parts[2].clone(),
-                            ttl
-                        )))
-                    }
-                    b"DEL" if parts.len() == 2 => {
-                        Ok(Some(RedisCommand::Del(parts[1].clone())))
-                    }
-                    b"PING" => {
-                        Ok(Some(RedisCommand::Ping))
-                    }
-                    b"INFO" => {
-                        Ok(Some(RedisCommand::Info))
-                    }
-                    _ => {
-                        Err(format!("Unsupported command: {:?}", 
-                            String::from_utf8_lossy(&cmd)).into())
-                    }
-                }
+                let name = String::from_utf8_lossy(&parts.remove(0)).into_owned();
+                Ok(Some((name, parts)))
             }
             _ => {
                 Err(format!("Unsupported RESP type: {}", type_buf[0] as char).into())
@@ -126,9 +102,62 @@ impl RedisHandler {
         }
     }
     
+    /// Finish a command that handed its waiting back to the connection loop
+    async fn block_on(&self, blocking: Blocking) -> Reply {
+        match blocking {
+            Blocking::Replicas { numreplicas, timeout_ms } => {
+                // Count replicas that have caught up with our last write
+                let acked = self.wait_for_replicas(numreplicas, timeout_ms).await;
+                Reply::Integer(acked as i64)
+            }
+            Blocking::Aof { numlocal, numreplicas, timeout_ms } => {
+                let (local, replicas) = self.wait_for_aof(numlocal, numreplicas, timeout_ms).await;
+                Reply::Array(vec![Reply::Integer(local as i64), Reply::Integer(replicas as i64)])
+            }
+        }
+    }
+    
+    /// Block until `numreplicas` replicas acknowledge `offset` or timeout (0 = forever)
+    async fn wait_for_replicas(&self, numreplicas: usize, timeout_ms: u64) -> usize {
+        let offset = self.ctx.last_write_offset;
+        let replication = self.ctx.state.replication();
+        let deadline = (timeout_ms > 0)
+            .then(|| Instant::now() + Duration::from_millis(timeout_ms));
+        
+        loop {
+            let acked = replication.count_acked(offset);
+            if acked >= numreplicas || deadline.is_some_and(|d| Instant::now() >= d) {
+                return acked;
+            }
+            
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    }
+    
+    /// Block until our last write is fsynced locally (when `numlocal` > 0) and acked by
+    /// `numreplicas` replicas, or timeout (0 = forever) - returns (local acks, replica acks)
+    /// Replicas count once they acknowledge the offset; their own fsync is not tracked
+    /// AOF offsets only grow, even across a rewrite, so an older write is never stranded
+    async fn wait_for_aof(&self, numlocal: usize, numreplicas: usize, timeout_ms: u64) -> (usize, usize) {
+        let offset = self.ctx.last_write_offset;
+        let state = &self.ctx.state;
+        let deadline = (timeout_ms > 0)
+            .then(|| Instant::now() + Duration::from_millis(timeout_ms));
+        
+        loop {
+            let local = usize::from(state.aof_synced_offset() >= offset);
+            let replicas = state.replication().count_acked(offset);
+            if (local >= numlocal && replicas >= numreplicas) || deadline.is_some_and(|d| Instant::now() >= d) {
+                return (local, replicas);
+            }
+            
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    }
+    
     /// Parse integer from RESP protocol
-    async fn parse_integer(
-        conn: &mut TcpConnection
+    async fn parse_integer<S: AsyncRead + AsyncWrite + Unpin>(
+        conn: &mut TcpConnection<S>
     ) -> Result<i64, Box<dyn std::error::Error + Send + Sync>> {
         // Read until CRLF
         let mut buf = Vec::new();
@@ -149,6 +178,10 @@ impl RedisHandler {
                 break;
             }
             
+            // No valid count is this long - don't buffer an endless line
+            if buf.len() == MAX_COUNT_LINE {
+                return Err(ProtocolError("Protocol error: too big count string".to_string()).into());
+            }
             buf.push(byte[0]);
         }
         
@@ -162,21 +195,28 @@ impl RedisHandler {
         Ok(num)
     }
     
-    /// Parse bulk string from RESP protocol
-    async fn parse_bulk_string(
-        conn: &mut TcpConnection
-    ) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>> {
+    /// Parse bulk string from RESP protocol (None = over the argument cap, skipped)
+    async fn parse_bulk_string<S: AsyncRead + AsyncWrite + Unpin>(
+        conn: &mut TcpConnection<S>
+    ) -> Result<Option<Vec<u8>>, Box<dyn std::error::Error + Send + Sync>> {
         // Read length
         let length = Self::parse_integer(conn).await?;
         
         if length < 0 {
             // NULL string
-            return Ok(Vec::new());
+            return Ok(Some(Vec::new()));
         }
         
-        // Read string content
-        let mut buf = vec![0u8; length as usize];
-        conn.read_exact(&mut buf).await?;
+        // Refuse absurd lengths before allocating for them
+        conn.check_bulk_len(length as u64)?;
+        
+        // Read string content - no key or value may be this long, so don't keep it
+        let buf = if conn.arg_too_large(length as usize) {
+            conn.skip_bulk(length as usize).await?;
+            None
+        } else {
+            Some(conn.read_bulk(length as usize).await?)
+        };
         
         // Read trailing CRLF
         let mut crlf = [0u8; 2];
@@ -189,35 +229,37 @@ impl RedisHandler {
         Ok(buf)
     }
     
-    /// Write simple string response
-    async fn write_simple_string(
-        conn: &mut TcpConnection, 
-        s: &str
+    /// Write command reply
+    async fn write_reply<S: AsyncRead + AsyncWrite + Unpin>(
+        conn: &mut TcpConnection<S>,
+        reply: &Reply
     ) -> Result<(), std::io::Error> {
-        let mut response = Vec::with_capacity(s.len() + 3);
-        response.push(b'+');
-        response.extend_from_slice(s.as_bytes());
-        response.extend_from_slice(b"\r\n");
-        
-        conn.write_all(&response).await
+        match reply {
+            // Bulk payloads go straight to the socket instead of through a buffer copy
+            Reply::Bulk(bytes) => Self::write_bulk_string(conn, Some(bytes)).await,
+            Reply::SharedBulk(bytes) => Self::write_bulk_string(conn, Some(bytes)).await,
+            reply => {
+                let mut response = Vec::new();
+                reply.encode(&mut response);
+                conn.write_all(&response).await
+            }
+        }
     }
     
     /// Write error response
-    async fn write_error(
-        conn: &mut TcpConnection, 
-        err: &str
+    async fn write_error<S: AsyncRead + AsyncWrite + Unpin>(
+        conn: &mut TcpConnection<S>,
+        err: &RedisError
     ) -> Result<(), std::io::Error> {
-        let mut response = Vec::with_capacity(err.len() + 3);
-        response.push(b'-');
-        response.extend_from_slice(err.as_bytes());
-        response.extend_from_slice(b"\r\n");
+        let mut response = Vec::new();
+        err.encode(&mut response);
         
         conn.write_all(&response).await
     }
     
     /// Write bulk string response
-    async fn write_bulk_string(
-        conn: &mut TcpConnection, 
+    async fn write_bulk_string<S: AsyncRead + AsyncWrite + Unpin>(
+        conn: &mut TcpConnection<S>,
         data: Option<&[u8]>
     ) -> Result<(), std::io::Error> {
         match data {
@@ -236,27 +278,53 @@ impl RedisHandler {
         }
     }
     
-    /// Write integer response
-    async fn write_integer(
-        conn: &mut TcpConnection, 
-        n: i64
-    ) -> Result<(), std::io::Error> {
-        let response = format!(":{}\r\n", n);
-        conn.write_all(response.as_bytes()).await
+    /// Log an outbound reply as it goes on the wire
+    fn trace_reply(client_id: u64, result: &Result<Reply, RedisError>) {
+        let mut response = Vec::new();
+        match result {
+            Ok(reply) => reply.encode(&mut response),
+            Err(e) => e.encode(&mut response),
+        }
+        eprintln!("TRACE client {} < {}", client_id, trace_bytes(&response));
+    }
+    
+    /// Published message pushed to a subscriber (a push frame under RESP3)
+    fn pubsub_message(message: &PubSubMessage, protocol: u8) -> Reply {
+        let mut items = match &message.pattern {
+            Some(pattern) => vec![b"pmessage".to_vec(), pattern.clone()],
+            None => vec![b"message".to_vec()],
+        };
+        items.push(message.channel.clone());
+        items.push(message.payload.clone());
+        Reply::push(items.into_iter().map(Reply::Bulk).collect(), protocol)
     }
 }
 
 impl ProtocolHandler for RedisHandler {
-    async fn handle_connection(
+    async fn handle_connection<S: AsyncRead + AsyncWrite + Unpin>(
         &mut self,
-        conn: &mut TcpConnection
+        conn: &mut TcpConnection<S>
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> { // CRITICAL FIX: Added Send + Sync
         println!("Handling Redis protocol connection");
         
         // Process commands in a loop
         loop {
+            // Deliver pub/sub messages while waiting for the next command
+            let protocol = self.ctx.protocol;
+            if let Some(subscription) = self.ctx.subscription.as_mut() {
+                tokio::select! {
+                    message = subscription.receiver.recv() => {
+                        if let Some(message) = message {
+                            Self::write_reply(conn, &Self::pubsub_message(&message, protocol)).await?;
+                        }
+                        continue;
+                    }
+                    ready = conn.readable() => ready?,
+                }
+            }
+            
             // Parse command
-            let cmd = match Self::parse_command(conn).await {
+            let (name, args) = match Self::parse_command(conn).await {
                 Ok(Some(cmd)) => cmd,
                 Ok(None) => {
                     // Client disconnected
@@ -265,60 +333,195 @@ impl ProtocolHandler for RedisHandler {
                 }
                 Err(e) => {
                     eprintln!("Error parsing command: {}", e);
-                    Self::write_error(conn, &format!("ERR {}", e)).await?;
+                    Self::write_error(conn, &RedisError::from(e.to_string())).await?;
+                    
+                    // The rest of the stream can't be framed - drop the client
+                    if e.is::<ProtocolError>() {
+                        return Ok(());
+                    }
                     continue;
                 }
             };
             
-            // Execute command
-            match cmd {
-                RedisCommand::Get(key) => {
-                    // Get value from storage
-                    let value = self.state.get(&key);
-                    
-                    // Send response
-                    match value {
-                        Some(v) => Self::write_bulk_string(conn, Some(&v)).await?,
-                        None => Self::write_bulk_string(conn, None).await?,
-                    }
-                }
-                RedisCommand::Set(key, value, ttl) => {
-                    // Set value in storage
-                    match self.state.set(&key, value, ttl) {
-                        Ok(_) => Self::write_simple_string(conn, "OK").await?,
-                        Err(e) => Self::write_error(conn, &format!("ERR {}", e)).await?,
-                    }
-                }
-                RedisCommand::Del(key) => {
-                    // Delete value from storage
-                    match self.state.delete(&key) {
-                        Ok(true) => Self::write_integer(conn, 1).await?,
-                        Ok(false) => Self::write_integer(conn, 0).await?,
-                        Err(e) => Self::write_error(conn, &format!("ERR {}", e)).await?,
+            if self.ctx.trace {
+                let line: Vec<String> = std::iter::once(name.clone())
+                    .chain(args.iter().map(|arg| trace_bytes(arg)))
+                    .collect();
+                eprintln!("TRACE client {} > {}", self.ctx.client_id, line.join(" "));
+            }
+            
+            // Execute command - every error reply is encoded here
+            self.ctx.state.record_command();
+            if let Some(slot) = &self.watchdog {
+                slot.begin(&name);
+            }
+            let started = Instant::now();
+            let result = self.registry.dispatch(&name, &args, &mut self.ctx);
+            let elapsed = started.elapsed();
+            LatencyMonitor::global().record(EVENT_COMMAND, elapsed);
+            if let Some(elapsed) = self.watchdog.as_ref().and_then(|slot| slot.end()) {
+                eprintln!("⚠️ WATCHDOG: client {} finished {} after {:?}", self.ctx.client_id, name, elapsed);
+            }
+            let slowlog = self.ctx.state.slowlog();
+            if slowlog.is_slow(elapsed) {
+                let addr = self.ctx.state.clients().get(self.ctx.client_id)
+                    .map(|client| client.addr.to_string())
+                    .unwrap_or_default();
+                slowlog.record(elapsed, &name, &args, addr);
+            }
+            
+            // Blocking waits are expected to be long, so they run outside the watchdog
+            let result = match result {
+                Ok(Reply::Blocked(blocking)) => Ok(self.block_on(blocking).await),
+                result => result,
+            };
+            if self.ctx.trace {
+                Self::trace_reply(self.ctx.client_id, &result);
+            }
+            
+            match result {
+                Ok(Reply::Close) => return Ok(()),
+                Ok(reply) => {
+                    Self::write_reply(conn, &reply).await?;
+                    if reply.closes() {
+                        return Ok(());
                     }
                 }
-                RedisCommand::Ping => {
-                    // Simple ping-pong
-                    Self::write_simple_string(conn, "PONG").await?
-                }
-                RedisCommand::Info => {
-                    // Get system info
-                    let (uptime, reads, writes, deletes, read_lat, write_lat) = 
-                        self.state.get_stats();
-                        
-                    let info = format!(
-                        "# Server\r\nworkingdb_version:0.1.0\r\nuptime_seconds:{}\r\n\
-                         # Stats\r\ntotal_reads:{}\r\ntotal_writes:{}\r\n\
-                         total_deletes:{}\r\navg_read_latency_ns:{}\r\n\
-                         avg_write_latency_ns:{}\r\n",
-                        uptime.as_secs(), reads, writes, deletes, read_lat, write_lat
-                    );
-                    
-                    Self::write_bulk_string(conn, Some(info.as_bytes())).await?
-                }
+                Err(e) => Self::write_error(conn, &e).await?,
             }
         }
         
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::persistence::aof::{AppendOnlyFile, FsyncPolicy};
+    use crate::storage::memory::MemTable;
+    use tokio::io::AsyncWriteExt;
+    use tokio::net::{TcpListener, TcpStream};
+    
+    /// Handler on a fresh state whose AOF is fsynced once a second
+    fn everysec_handler(dir: &tempfile::TempDir) -> RedisHandler {
+        let aof = AppendOnlyFile::with_fsync_policy(dir.path().join("waitaof.aof"), FsyncPolicy::EverySecond).unwrap();
+        let state = Arc::new(GlobalState::new(Arc::new(MemTable::new()), aof));
+        let client_id = state.next_client_id();
+        RedisHandler::new(state, client_id)
+    }
+    
+    /// SET `key` on the handler's connection
+    fn set(handler: &mut RedisHandler, key: &str) {
+        let args = [key.as_bytes().to_vec(), b"v".to_vec()];
+        assert_eq!(handler.registry.dispatch("set", &args, &mut handler.ctx), Ok(Reply::ok()));
+    }
+    
+    /// Run WAITAOF to completion, failing the test if it never returns
+    async fn waitaof(handler: &RedisHandler, numlocal: usize, numreplicas: usize, timeout_ms: u64) -> Reply {
+        let blocking = Blocking::Aof { numlocal, numreplicas, timeout_ms };
+        tokio::time::timeout(Duration::from_secs(5), handler.block_on(blocking)).await
+            .expect("WAITAOF never returned")
+    }
+    
+    #[tokio::test]
+    async fn test_waitaof_blocks_until_fsync() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut handler = everysec_handler(&dir);
+        let acked = |local, replicas| Reply::Array(vec![Reply::Integer(local), Reply::Integer(replicas)]);
+        
+        // The write is buffered until the flush thread's next fsync
+        set(&mut handler, "k");
+        let offset = handler.ctx.last_write_offset;
+        assert!(handler.ctx.state.aof_synced_offset() < offset);
+        assert_eq!(waitaof(&handler, 1, 0, 0).await, acked(1, 0));
+        assert!(handler.ctx.state.aof_synced_offset() >= offset);
+        
+        // Missing replica acks run into the timeout with the local ack counted
+        let started = Instant::now();
+        assert_eq!(waitaof(&handler, 1, 1, 50).await, acked(1, 0));
+        assert!(started.elapsed() >= Duration::from_millis(50));
+    }
+    
+    #[tokio::test]
+    async fn test_waitaof_across_rewrite() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut handler = everysec_handler(&dir);
+        let acked = Reply::Array(vec![Reply::Integer(1), Reply::Integer(0)]);
+        
+        // Offsets carry on past a rewrite, so a write from before it is still covered
+        set(&mut handler, "before");
+        let offset = handler.ctx.last_write_offset;
+        handler.ctx.state.rewrite_aof().unwrap();
+        assert_eq!(waitaof(&handler, 1, 0, 0).await, acked);
+        
+        // A write after the rewrite waits for the new file's fsync
+        set(&mut handler, "after");
+        assert!(handler.ctx.last_write_offset > offset);
+        assert_eq!(waitaof(&handler, 1, 0, 0).await, acked);
+        assert!(handler.ctx.state.aof_synced_offset() >= handler.ctx.last_write_offset);
+    }
+    
+    #[tokio::test]
+    async fn test_oversized_bulk_rejected() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let mut client = TcpStream::connect(listener.local_addr().unwrap()).await.unwrap();
+        let (socket, _) = listener.accept().await.unwrap();
+        let mut conn = TcpConnection::new(socket).with_max_bulk_len(16);
+        
+        client.write_all(b"*2\r\n$3\r\nget\r\n$2\r\nab\r\n").await.unwrap();
+        let (name, args) = RedisHandler::parse_command(&mut conn).await.unwrap().unwrap();
+        assert_eq!((name.as_str(), args), ("get", vec![b"ab".to_vec()]));
+        
+        // A huge declared length fails before any allocation
+        client.write_all(b"*2\r\n$3\r\nget\r\n$4294967295\r\n").await.unwrap();
+        let err = RedisHandler::parse_command(&mut conn).await.unwrap_err();
+        assert!(err.is::<ProtocolError>());
+    }
+    
+    #[tokio::test]
+    async fn test_oversized_arg_skipped() {
+        let input = b"*3\r\n$3\r\nset\r\n$1\r\nk\r\n$10\r\n0123456789\r\n*2\r\n$3\r\nget\r\n$1\r\nk\r\n";
+        let mut conn = TcpConnection::new(tokio::io::join(input.as_slice(), tokio::io::sink())).with_max_arg_len(4);
+        
+        // Refused on the declared length, and the next command still parses
+        let err = RedisHandler::parse_command(&mut conn).await.unwrap_err();
+        assert!(err.is::<ArgTooLarge>());
+        let (name, args) = RedisHandler::parse_command(&mut conn).await.unwrap().unwrap();
+        assert_eq!((name.as_str(), args), ("get", vec![b"k".to_vec()]));
+    }
+    
+    #[tokio::test]
+    async fn test_oversized_multibulk_rejected() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let mut client = TcpStream::connect(listener.local_addr().unwrap()).await.unwrap();
+        let (socket, _) = listener.accept().await.unwrap();
+        let mut conn = TcpConnection::new(socket).with_max_multibulk_len(3);
+        
+        client.write_all(b"*3\r\n$3\r\nset\r\n$1\r\nk\r\n$1\r\nv\r\n").await.unwrap();
+        assert!(RedisHandler::parse_command(&mut conn).await.unwrap().is_some());
+        
+        // Rejected on the count alone - no elements follow
+        client.write_all(b"*100000000\r\n").await.unwrap();
+        let err = RedisHandler::parse_command(&mut conn).await.unwrap_err();
+        assert!(err.is::<ProtocolError>());
+        assert_eq!(err.to_string(), "Protocol error: invalid multibulk length 100000000");
+    }
+    
+    #[tokio::test]
+    async fn test_unterminated_input_bounded() {
+        let parse = |input: Vec<u8>| async move {
+            let mut conn = TcpConnection::new(tokio::io::join(input.as_slice(), tokio::io::sink()));
+            RedisHandler::parse_command(&mut conn).await.map(|_| ())
+        };
+        
+        // A count line that never ends is cut off instead of buffered
+        let err = parse([b"*".as_slice(), &[b'9'; 1000]].concat()).await.unwrap_err();
+        assert_eq!(err.to_string(), "Protocol error: too big count string");
+        assert!(err.is::<ProtocolError>());
+        
+        // An allowed but unsent length runs out of input rather than being allocated
+        let err = parse(b"*1\r\n$536870912\r\nabc".to_vec()).await.unwrap_err();
+        assert_eq!(err.downcast_ref::<std::io::Error>().map(|e| e.kind()), Some(std::io::ErrorKind::UnexpectedEof));
+    }
+}
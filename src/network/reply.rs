@@ -0,0 +1,296 @@
+// RESP replies and error replies shared by the Redis command handlers
+use std::fmt;
+use std::sync::Arc;
+
+use crate::network::commands::Blocking;
+use crate::storage::value::WRONGTYPE;
+
+/// RedisError - Error reply with its standard prefix
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RedisError {
+    // Generic error (ERR)
+    Err(String),
+
+    // Operation against a key holding the wrong kind of value (WRONGTYPE)
+    WrongType,
+
+    // Authentication required (NOAUTH)
+    NoAuth(String),
+
+    // Bad username or password (WRONGPASS)
+    WrongPass(String),
+    
+    // Command not allowed for the connection's user (NOPERM)
+    NoPerm(String),
+    
+    // Write sent to a read-only replica (READONLY)
+    ReadOnly(String),
+    
+    // HELLO asked for a protocol version we don't speak (NOPROTO)
+    NoProto(String),
+    
+    // Command refused because of the memory limit (OOM)
+    Oom(String),
+
+    // Unknown script (NOSCRIPT)
+    NoScript(String),
+
+    // Transaction discarded (EXECABORT)
+    ExecAbort(String),
+}
+
+/// Reply - Value a command handler sends back
+#[derive(Debug, Clone, PartialEq)]
+pub enum Reply {
+    // +status
+    Simple(String),
+
+    // :integer
+    Integer(i64),
+
+    // $bulk string
+    Bulk(Vec<u8>),
+
+    // $bulk string shared with the MemTable (no copy on GET)
+    SharedBulk(Arc<[u8]>),
+
+    // $-1 null bulk string
+    Nil,
+
+    // *array of nested replies
+    Array(Vec<Reply>),
+
+    // %map of key/value pairs (RESP3 only - see `Reply::map`)
+    Map(Vec<(Reply, Reply)>),
+    
+    // >push frame of out-of-band data (RESP3 only - see `Reply::push`)
+    Push(Vec<Reply>),
+    
+    // Several top-level replies in a row (one per SUBSCRIBE channel)
+    Many(Vec<Reply>),
+
+    // Send nothing and close the connection (SHUTDOWN; ends a Many for QUIT)
+    Close,
+    
+    // Reply comes later, once the connection loop finishes waiting (WAIT)
+    Blocked(Blocking),
+}
+
+impl RedisError {
+    /// Standard error code sent before the message
+    pub fn prefix(&self) -> &'static str {
+        match self {
+            RedisError::Err(_) => "ERR",
+            RedisError::WrongType => "WRONGTYPE",
+            RedisError::NoAuth(_) => "NOAUTH",
+            RedisError::WrongPass(_) => "WRONGPASS",
+            RedisError::NoPerm(_) => "NOPERM",
+            RedisError::ReadOnly(_) => "READONLY",
+            RedisError::NoProto(_) => "NOPROTO",
+            RedisError::Oom(_) => "OOM",
+            RedisError::NoScript(_) => "NOSCRIPT",
+            RedisError::ExecAbort(_) => "EXECABORT",
+        }
+    }
+
+    /// Append the RESP error line to `buf`
+    pub fn encode(&self, buf: &mut Vec<u8>) {
+        buf.push(b'-');
+        buf.extend_from_slice(self.to_string().as_bytes());
+        buf.extend_from_slice(b"\r\n");
+    }
+}
+
+impl fmt::Display for RedisError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RedisError::WrongType => f.write_str(WRONGTYPE),
+            RedisError::Err(msg)
+            | RedisError::NoAuth(msg)
+            | RedisError::WrongPass(msg)
+            | RedisError::NoPerm(msg)
+            | RedisError::ReadOnly(msg)
+            | RedisError::NoProto(msg)
+            | RedisError::Oom(msg)
+            | RedisError::NoScript(msg)
+            | RedisError::ExecAbort(msg) => write!(f, "{} {}", self.prefix(), msg),
+        }
+    }
+}
+
+impl std::error::Error for RedisError {}
+
+impl From<String> for RedisError {
+    /// Storage errors may carry a code (e.g. "WRONGTYPE ..."); anything else is ERR
+    fn from(msg: String) -> Self {
+        if msg == WRONGTYPE {
+            return RedisError::WrongType;
+        }
+
+        let coded = |prefix: &str| msg.strip_prefix(prefix).map(str::to_string);
+        if let Some(rest) = coded("OOM ") {
+            RedisError::Oom(rest)
+        } else if let Some(rest) = coded("NOAUTH ") {
+            RedisError::NoAuth(rest)
+        } else if let Some(rest) = coded("WRONGPASS ") {
+            RedisError::WrongPass(rest)
+        } else if let Some(rest) = coded("NOPERM ") {
+            RedisError::NoPerm(rest)
+        } else if let Some(rest) = coded("READONLY ") {
+            RedisError::ReadOnly(rest)
+        } else if let Some(rest) = coded("NOPROTO ") {
+            RedisError::NoProto(rest)
+        } else if let Some(rest) = coded("NOSCRIPT ") {
+            RedisError::NoScript(rest)
+        } else if let Some(rest) = coded("EXECABORT ") {
+            RedisError::ExecAbort(rest)
+        } else {
+            RedisError::Err(msg)
+        }
+    }
+}
+
+impl From<&str> for RedisError {
+    fn from(msg: &str) -> Self {
+        RedisError::from(msg.to_string())
+    }
+}
+
+impl Reply {
+    /// +OK
+    pub fn ok() -> Self {
+        Reply::Simple("OK".to_string())
+    }
+
+    /// Array of bulk strings
+    pub fn bulk_array(items: Vec<Vec<u8>>) -> Self {
+        Reply::Array(items.into_iter().map(Reply::Bulk).collect())
+    }
+
+    /// Bulk string, or nil for None
+    pub fn optional_bulk(value: Option<Vec<u8>>) -> Self {
+        value.map_or(Reply::Nil, Reply::Bulk)
+    }
+
+    /// Map under RESP3, flat key/value array under RESP2
+    pub fn map(pairs: Vec<(Reply, Reply)>, protocol: u8) -> Self {
+        if protocol >= 3 {
+            Reply::Map(pairs)
+        } else {
+            Reply::Array(pairs.into_iter().flat_map(|(key, value)| [key, value]).collect())
+        }
+    }
+    
+    /// Push frame under RESP3, plain array under RESP2 (pub/sub messages and confirmations)
+    pub fn push(items: Vec<Reply>, protocol: u8) -> Self {
+        if protocol >= 3 {
+            Reply::Push(items)
+        } else {
+            Reply::Array(items)
+        }
+    }
+    
+    /// Whether the connection closes once this reply is written
+    pub fn closes(&self) -> bool {
+        match self {
+            Reply::Close => true,
+            Reply::Many(replies) => replies.last().is_some_and(Reply::closes),
+            _ => false,
+        }
+    }
+    
+    /// Append the RESP encoding to `buf`
+    pub fn encode(&self, buf: &mut Vec<u8>) {
+        match self {
+            Reply::Simple(s) => {
+                buf.push(b'+');
+                buf.extend_from_slice(s.as_bytes());
+                buf.extend_from_slice(b"\r\n");
+            }
+            Reply::Integer(n) => buf.extend_from_slice(format!(":{}\r\n", n).as_bytes()),
+            Reply::Bulk(bytes) => Self::encode_bulk(buf, bytes),
+            Reply::SharedBulk(bytes) => Self::encode_bulk(buf, bytes),
+            Reply::Nil => buf.extend_from_slice(b"$-1\r\n"),
+            Reply::Array(items) | Reply::Push(items) => {
+                let kind = if matches!(self, Reply::Push(_)) { '>' } else { '*' };
+                buf.extend_from_slice(format!("{}{}\r\n", kind, items.len()).as_bytes());
+                for item in items {
+                    item.encode(buf);
+                }
+            }
+            Reply::Map(pairs) => {
+                buf.extend_from_slice(format!("%{}\r\n", pairs.len()).as_bytes());
+                for (key, value) in pairs {
+                    key.encode(buf);
+                    value.encode(buf);
+                }
+            }
+            Reply::Many(replies) => {
+                for reply in replies {
+                    reply.encode(buf);
+                }
+            }
+            Reply::Close | Reply::Blocked(_) => {}
+        }
+    }
+
+    /// $<len>\r\n<bytes>\r\n
+    fn encode_bulk(buf: &mut Vec<u8>, bytes: &[u8]) {
+        buf.extend_from_slice(format!("${}\r\n", bytes.len()).as_bytes());
+        buf.extend_from_slice(bytes);
+        buf.extend_from_slice(b"\r\n");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_prefixes() {
+        assert_eq!(RedisError::from(WRONGTYPE.to_string()), RedisError::WrongType);
+        assert_eq!(RedisError::from("OOM command not allowed").to_string(), "OOM command not allowed");
+        assert_eq!(RedisError::from("syntax error").to_string(), "ERR syntax error");
+
+        let mut buf = Vec::new();
+        RedisError::WrongType.encode(&mut buf);
+        assert_eq!(buf, format!("-{}\r\n", WRONGTYPE).into_bytes());
+    }
+
+    #[test]
+    fn test_reply_encoding() {
+        let reply = Reply::Array(vec![
+            Reply::Bulk(b"a".to_vec()),
+            Reply::Nil,
+            Reply::Integer(-3),
+            Reply::SharedBulk(Arc::from(&b"xyz"[..])),
+        ]);
+
+        let mut buf = Vec::new();
+        reply.encode(&mut buf);
+        assert_eq!(buf, b"*4\r\n$1\r\na\r\n$-1\r\n:-3\r\n$3\r\nxyz\r\n".to_vec());
+
+        let mut buf = Vec::new();
+        let quit = Reply::Many(vec![Reply::ok(), Reply::Close]);
+        quit.encode(&mut buf);
+        assert_eq!(buf, b"+OK\r\n".to_vec());
+        assert!(quit.closes());
+        assert!(!Reply::ok().closes());
+        
+        // RESP3 frames fall back to plain arrays for RESP2 connections
+        let message = || vec![Reply::Bulk(b"message".to_vec()), Reply::Bulk(b"ch".to_vec())];
+        for (protocol, expected) in [(2, &b"*2\r\n"[..]), (3, &b">2\r\n"[..])] {
+            let mut buf = Vec::new();
+            Reply::push(message(), protocol).encode(&mut buf);
+            assert_eq!(buf, [expected, b"$7\r\nmessage\r\n$2\r\nch\r\n"].concat());
+        }
+        
+        let mut buf = Vec::new();
+        Reply::map(vec![(Reply::Bulk(b"proto".to_vec()), Reply::Integer(3))], 3).encode(&mut buf);
+        assert_eq!(buf, b"%1\r\n$5\r\nproto\r\n:3\r\n".to_vec());
+        assert_eq!(
+            Reply::map(vec![(Reply::Bulk(b"proto".to_vec()), Reply::Integer(2))], 2),
+            Reply::Array(vec![Reply::Bulk(b"proto".to_vec()), Reply::Integer(2)])
+        );
+    }
+}
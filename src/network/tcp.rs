@@ -1,13 +1,100 @@
 use std::sync::Arc;
 use std::task::{Context, Poll};
 use std::pin::Pin;
+use std::fmt;
 use std::io::{self};
+use std::ops::Range;
+use std::time::{Duration, Instant};
 use tokio::io::ReadBuf;
 use tokio::net::{TcpListener, TcpStream};
 use tokio::io::{AsyncRead, AsyncWrite, AsyncReadExt, AsyncWriteExt};
 
 use crate::core::state::GlobalState;
 
+/// Default cap on simultaneously connected clients
+pub const DEFAULT_MAX_CONNECTIONS: usize = 10_000;
+
+/// Default idle time before TCP keepalive probes start (matches Redis tcp-keepalive)
+pub const DEFAULT_TCP_KEEPALIVE: Duration = Duration::from_secs(300);
+
+/// Default cap on a declared bulk-string or data-block length (Redis proto-max-bulk-len)
+pub const DEFAULT_MAX_BULK_LEN: usize = 512 * 1024 * 1024;
+
+/// Default cap on the element count of a command array
+pub const DEFAULT_MAX_MULTIBULK_LEN: usize = 1024 * 1024;
+
+/// Most bulk bytes allocated up front - longer payloads grow as their bytes arrive
+const BULK_PREALLOC: usize = 64 * 1024;
+
+/// How long a shutdown waits for open connections to finish
+const SHUTDOWN_DRAIN_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Enable SO_KEEPALIVE, probing after `idle` and then every `interval`
+fn set_keepalive(socket: &TcpStream, idle: Duration, interval: Duration) -> io::Result<()> {
+    use std::os::unix::io::AsRawFd;
+    
+    let fd = socket.as_raw_fd();
+    setsockopt(fd, libc::SOL_SOCKET, libc::SO_KEEPALIVE, 1)?;
+    
+    // Probe timing is only tunable per socket on Linux
+    #[cfg(target_os = "linux")]
+    {
+        setsockopt(fd, libc::IPPROTO_TCP, libc::TCP_KEEPIDLE, idle.as_secs().max(1) as libc::c_int)?;
+        setsockopt(fd, libc::IPPROTO_TCP, libc::TCP_KEEPINTVL, interval.as_secs().max(1) as libc::c_int)?;
+    }
+    Ok(())
+}
+
+/// Set an integer socket option
+fn setsockopt(fd: std::os::unix::io::RawFd, level: libc::c_int, name: libc::c_int, value: libc::c_int) -> io::Result<()> {
+    let ret = unsafe {
+        libc::setsockopt(
+            fd,
+            level,
+            name,
+            &value as *const libc::c_int as *const libc::c_void,
+            std::mem::size_of::<libc::c_int>() as libc::socklen_t,
+        )
+    };
+    if ret == 0 { Ok(()) } else { Err(io::Error::last_os_error()) }
+}
+
+/// Framing error that leaves the stream unreadable - handlers reply and close
+#[derive(Debug)]
+pub struct ProtocolError(pub String);
+
+impl fmt::Display for ProtocolError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for ProtocolError {}
+
+/// Argument longer than any key or value may be - its bytes were discarded unread,
+/// so the stream is still framed and the handler just rejects the command
+#[derive(Debug)]
+pub struct ArgTooLarge;
+
+impl fmt::Display for ArgTooLarge {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("value too large")
+    }
+}
+
+impl std::error::Error for ArgTooLarge {}
+
+/// Render bytes for a trace line - quoted text when printable, hex otherwise
+pub(crate) fn trace_bytes(bytes: &[u8]) -> String {
+    let printable = bytes.iter().all(|&b| b.is_ascii_graphic() || matches!(b, b' ' | b'\r' | b'\n'));
+    if printable {
+        format!("\"{}\"", bytes.escape_ascii())
+    } else {
+        let hex: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+        format!("0x{}", hex)
+    }
+}
+
 /// Protocol detection result
 pub enum Protocol {
     Redis,
@@ -27,36 +114,110 @@ pub struct TcpServer {
     
     // Shared database state
     state: Arc<GlobalState>,
+    
+    // Connections beyond this are refused (0 = no limit)
+    max_connections: usize,
+    
+    // Disable Nagle's algorithm on accepted sockets
+    tcp_nodelay: bool,
+    
+    // Idle time before keepalive probes (None = SO_KEEPALIVE off)
+    keepalive: Option<Duration>,
+    
+    // Largest declared bulk length a client may send
+    max_bulk_len: usize,
+    
+    // Most elements a client may declare in a command array
+    max_multibulk_len: usize,
 }
 
 impl TcpServer {
     /// Create new TCP server
     pub fn new(host: String, port: u16, state: Arc<GlobalState>) -> Self {
-        Self { host, port, state }
+        Self {
+            host,
+            port,
+            state,
+            max_connections: DEFAULT_MAX_CONNECTIONS,
+            tcp_nodelay: true,
+            keepalive: Some(DEFAULT_TCP_KEEPALIVE),
+            max_bulk_len: DEFAULT_MAX_BULK_LEN,
+            max_multibulk_len: DEFAULT_MAX_MULTIBULK_LEN,
+        }
+    }
+    
+    /// Limit simultaneously connected clients (0 = no limit)
+    pub fn with_max_connections(mut self, max_connections: usize) -> Self {
+        self.max_connections = max_connections;
+        self
+    }
+    
+    /// Reject declared bulk lengths over `max_bulk_len` before allocating for them
+    pub fn with_max_bulk_len(mut self, max_bulk_len: usize) -> Self {
+        self.max_bulk_len = max_bulk_len;
+        self
+    }
+    
+    /// Reject command arrays declaring more than `max_multibulk_len` elements
+    pub fn with_max_multibulk_len(mut self, max_multibulk_len: usize) -> Self {
+        self.max_multibulk_len = max_multibulk_len;
+        self
     }
     
-    /// Run the server - listen for connections
+    /// Socket options for accepted connections
+    /// Keepalive probes follow the idle time at a third of it, like Redis
+    pub fn with_socket_options(mut self, tcp_nodelay: bool, keepalive: Option<Duration>) -> Self {
+        self.tcp_nodelay = tcp_nodelay;
+        self.keepalive = keepalive;
+        self
+    }
+    
+    /// Run the server - listen for connections until a shutdown is requested
     pub async fn run(&self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         // Bind to address
         let addr = format!("{}:{}", self.host, self.port);
         let listener = TcpListener::bind(&addr).await?;
+        self.state.set_listening(true);
         
         println!("Listening on {}", addr);
         
         // Accept connections
+        let mut shutdown = self.state.shutdown_signal();
         loop {
-            match listener.accept().await {
+            let accepted = tokio::select! {
+                accepted = listener.accept() => accepted,
+                _ = shutdown.wait_for(|requested| *requested) => break,
+            };
+            
+            match accepted {
                 Ok((socket, addr)) => {
-                    println!("New connection from {}", addr);
+                    // Socket tuning failures are logged, not fatal
+                    if let Err(e) = self.configure_socket(&socket) {
+                        eprintln!("Failed to set socket options for {}: {}", addr, e);
+                    }
+                    
+                    // Refuse connections over the limit before spawning anything
+                    let Some(client_id) = self.state.try_open_connection(self.max_connections, addr) else {
+                        eprintln!("Rejected connection from {}: max clients reached", addr);
+                        let _ = socket.try_write(b"-ERR max number of clients reached\r\n");
+                        continue;
+                    };
+                    
+                    println!("New connection from {} (client {})", addr, client_id);
                     
                     // Clone reference to state for the handler task
                     let state = self.state.clone();
+                    let conn = TcpConnection::new(socket)
+                        .with_max_bulk_len(self.max_bulk_len)
+                        .with_max_multibulk_len(self.max_multibulk_len)
+                        .with_max_arg_len(self.state.max_arg_len());
                     
                     // Spawn task for this connection
                     tokio::spawn(async move {
-                        if let Err(e) = Self::handle_connection(socket, state).await {
-                            eprintln!("Connection error: {}", e);
+                        if let Err(e) = Self::handle_connection(conn, state.clone(), client_id).await {
+                            eprintln!("Connection error: client {}: {}", client_id, e);
                         }
+                        state.close_connection(client_id);
                     });
                 }
                 Err(e) => {
@@ -64,16 +225,50 @@ impl TcpServer {
                 }
             }
         }
+        
+        // Stop listening, then give open connections a bounded time to finish
+        drop(listener);
+        self.state.set_listening(false);
+        println!("Shutdown requested, draining connections");
+        self.drain_connections(SHUTDOWN_DRAIN_TIMEOUT).await;
+        
+        // Connections may have written while draining
+        if let Err(e) = self.state.flush() {
+            eprintln!("Failed to flush AOF on shutdown: {}", e);
+        }
+        
+        Ok(())
+    }
+    
+    /// Apply TCP_NODELAY and SO_KEEPALIVE to an accepted socket
+    fn configure_socket(&self, socket: &TcpStream) -> io::Result<()> {
+        socket.set_nodelay(self.tcp_nodelay)?;
+        if let Some(idle) = self.keepalive {
+            set_keepalive(socket, idle, (idle / 3).max(Duration::from_secs(1)))?;
+        }
+        Ok(())
+    }
+    
+    /// Wait until every connection has closed or `timeout` elapses
+    async fn drain_connections(&self, timeout: Duration) {
+        let deadline = Instant::now() + timeout;
+        
+        while self.state.connection_stats().connected_clients > 0 {
+            if Instant::now() >= deadline {
+                eprintln!("Shutdown drain timed out with {} connections open",
+                    self.state.connection_stats().connected_clients);
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
     }
     
     /// Handle a single client connection
-    async fn handle_connection(
-        socket: TcpStream, 
-        state: Arc<GlobalState>
+    async fn handle_connection<S: AsyncRead + AsyncWrite + Unpin>(
+        mut conn: TcpConnection<S>,
+        state: Arc<GlobalState>,
+        client_id: u64
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        // Create connection handler
-        let mut conn = TcpConnection::new(socket);
-        
         // Read initial bytes to detect protocol
         let protocol = conn.detect_protocol().await?;
         
@@ -82,13 +277,13 @@ impl TcpServer {
             Protocol::Redis => {
                 // Use Redis protocol handler
                 use crate::network::redis::RedisHandler;
-                let mut handler = RedisHandler::new(state);
+                let mut handler = RedisHandler::new(state, client_id);
                 handler.handle_connection(&mut conn).await?;
             }
             Protocol::Memcached => {
                 // Use Memcached protocol handler
                 use crate::network::memcached::MemcachedHandler;
-                let mut handler = MemcachedHandler::new(state);
+                let mut handler = MemcachedHandler::new(state, client_id);
                 handler.handle_connection(&mut conn).await?;
             }
             Protocol::SQLite => {
@@ -107,62 +302,159 @@ impl TcpServer {
 }
 
 /// TCP connection wrapper
-pub struct TcpConnection {
+/// Generic over the byte stream so handlers can be driven from memory in tests;
+/// servers and clients use the TcpStream default
+pub struct TcpConnection<S = TcpStream> {
     // Socket for this connection
-    socket: TcpStream,
+    socket: S,
     
     // Read buffer
     buffer: Vec<u8>,
+    
+    // Bytes of `buffer` read ahead of the handler (protocol detection, readable), served first
+    read_ahead: Range<usize>,
+    
+    // Largest declared bulk length the parsers will allocate for
+    max_bulk_len: usize,
+    
+    // Largest declared command array the parsers will read
+    max_multibulk_len: usize,
+    
+    // Longest argument the parsers will buffer - longer ones are skipped
+    max_arg_len: usize,
 }
 
-impl TcpConnection {
+impl<S> TcpConnection<S> {
     /// Create new TCP connection
-    pub fn new(socket: TcpStream) -> Self {
+    pub fn new(socket: S) -> Self {
         Self {
             socket,
             buffer: vec![0; 4096], // 4KB initial buffer
+            read_ahead: 0..0,
+            max_bulk_len: DEFAULT_MAX_BULK_LEN,
+            max_multibulk_len: DEFAULT_MAX_MULTIBULK_LEN,
+            max_arg_len: usize::MAX,
         }
     }
     
-    /// Connect to server
-    pub async fn connect(host: &str, port: u16) -> Result<Self, std::io::Error> {
-        let addr = format!("{}:{}", host, port);
-        let socket = TcpStream::connect(addr).await?;
-        
-        Ok(Self::new(socket))
+    /// Cap declared bulk lengths at `max_bulk_len`
+    pub fn with_max_bulk_len(mut self, max_bulk_len: usize) -> Self {
+        self.max_bulk_len = max_bulk_len;
+        self
+    }
+    
+    /// Fail with a ProtocolError if a peer-declared length is over the cap
+    pub fn check_bulk_len(&self, len: u64) -> Result<(), ProtocolError> {
+        if len > self.max_bulk_len as u64 {
+            return Err(ProtocolError(format!("Protocol error: invalid bulk length {}", len)));
+        }
+        Ok(())
     }
     
+    /// Cap declared command array lengths at `max_multibulk_len`
+    pub fn with_max_multibulk_len(mut self, max_multibulk_len: usize) -> Self {
+        self.max_multibulk_len = max_multibulk_len;
+        self
+    }
+    
+    /// Fail with a ProtocolError if a peer-declared element count is over the cap
+    pub fn check_multibulk_len(&self, len: u64) -> Result<(), ProtocolError> {
+        if len > self.max_multibulk_len as u64 {
+            return Err(ProtocolError(format!("Protocol error: invalid multibulk length {}", len)));
+        }
+        Ok(())
+    }
+    
+    /// Skip (rather than buffer) declared arguments longer than `max_arg_len`
+    pub fn with_max_arg_len(mut self, max_arg_len: usize) -> Self {
+        self.max_arg_len = max_arg_len;
+        self
+    }
+    
+    /// Whether an argument of `len` bytes is over the cap and must be skipped
+    pub fn arg_too_large(&self, len: usize) -> bool {
+        len > self.max_arg_len
+    }
+    
+    /// Move read-ahead bytes into `buf`, returning how many
+    fn take_read_ahead(&mut self, buf: &mut [u8]) -> usize {
+        let n = self.read_ahead.len().min(buf.len());
+        buf[..n].copy_from_slice(&self.buffer[self.read_ahead.start..][..n]);
+        self.read_ahead.start += n;
+        n
+    }
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> TcpConnection<S> {
     /// Read bytes from connection
     pub async fn read(&mut self, buf: &mut [u8]) -> Result<usize, std::io::Error> {
+        let n = self.take_read_ahead(buf);
+        if n > 0 || buf.is_empty() {
+            return Ok(n);
+        }
         self.socket.read(buf).await
     }
     
+    /// Read exactly `len` bytes, growing the buffer as they arrive
+    /// A declared length the peer never sends costs at most BULK_PREALLOC
+    pub async fn read_bulk(&mut self, len: usize) -> Result<Vec<u8>, std::io::Error> {
+        let mut buf = Vec::with_capacity(len.min(BULK_PREALLOC));
+        (&mut *self).take(len as u64).read_to_end(&mut buf).await?;
+        if buf.len() < len {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        Ok(buf)
+    }
+    
+    /// Read and discard exactly `len` bytes without buffering them
+    pub async fn skip_bulk(&mut self, len: usize) -> Result<(), std::io::Error> {
+        let skipped = tokio::io::copy(&mut (&mut *self).take(len as u64), &mut tokio::io::sink()).await?;
+        if skipped < len as u64 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        Ok(())
+    }
+    
     /// Write bytes to connection
     pub async fn write_all(&mut self, buf: &[u8]) -> Result<(), std::io::Error> {
         self.socket.write_all(buf).await
     }
     
+    /// Wait until there is data to read, or the peer closed (cancel-safe)
+    /// Bytes read while waiting are kept and returned by the next read
+    pub async fn readable(&mut self) -> Result<(), std::io::Error> {
+        if self.read_ahead.is_empty() {
+            let n = self.socket.read(&mut self.buffer).await?;
+            self.read_ahead = 0..n;
+        }
+        Ok(())
+    }
+    
     /// Detect protocol based on initial bytes
     pub async fn detect_protocol(&mut self) -> Result<Protocol, std::io::Error> {
-        // Read initial bytes
-        let n = self.socket.peek(&mut self.buffer).await?;
+        // Read initial bytes - they stay buffered for the protocol handler
+        self.readable().await?;
+        let initial = &self.buffer[self.read_ahead.clone()];
         
-        if n == 0 {
+        if initial.is_empty() {
             return Ok(Protocol::Unknown);
         }
         
         // Check for Redis protocol
-        if self.buffer[0] == b'*' || self.buffer[0] == b'$' || 
-           self.buffer[0] == b'+' || self.buffer[0] == b'-' || 
-           self.buffer[0] == b':' {
+        if initial[0] == b'*' || initial[0] == b'$' || 
+           initial[0] == b'+' || initial[0] == b'-' || 
+           initial[0] == b':' {
             return Ok(Protocol::Redis);
         }
         
         // Check for Memcached protocol (text-based)
         // FIXED: Array size mismatch
-        let commands: [&[u8]; 5] = [b"get ", b"set ", b"add ", b"replace ", b"delete "];
+        let commands: [&[u8]; 9] = [
+            b"get ", b"set ", b"add ", b"replace ", b"delete ",
+            b"incr ", b"decr ", b"touch ", b"flush_all",
+        ];
         for cmd in &commands {
-            if self.buffer.starts_with(cmd) {
+            if initial.starts_with(cmd) {
                 return Ok(Protocol::Memcached);
             }
         }
@@ -170,7 +462,7 @@ impl TcpConnection {
         // Check for SQLite protocol
         // Note: SQLite wire protocol detection would be more complex
         // This is a placeholder
-        if self.buffer.len() >= 16 && self.buffer[0] == 0x53 && self.buffer[1] == 0x51 {
+        if initial.len() >= 16 && initial[0] == 0x53 && initial[1] == 0x51 {
             return Ok(Protocol::SQLite);
         }
         
@@ -179,21 +471,36 @@ impl TcpConnection {
     }
 }
 
+impl TcpConnection {
+    /// Connect to server
+    pub async fn connect(host: &str, port: u16) -> Result<Self, std::io::Error> {
+        let addr = format!("{}:{}", host, port);
+        let socket = TcpStream::connect(addr).await?;
+        
+        Ok(Self::new(socket))
+    }
+}
+
 // CRITICAL FIX: Implement AsyncRead trait for TcpConnection
 // This allows using read_exact and other AsyncReadExt methods
-impl AsyncRead for TcpConnection {
+impl<S: AsyncRead + Unpin> AsyncRead for TcpConnection<S> {
     fn poll_read(
         mut self: Pin<&mut Self>,
         cx: &mut Context<'_>,
         buf: &mut ReadBuf<'_>,
     ) -> Poll<io::Result<()>> {
+        let n = self.take_read_ahead(buf.initialize_unfilled());
+        if n > 0 {
+            buf.advance(n);
+            return Poll::Ready(Ok(()));
+        }
         Pin::new(&mut self.socket).poll_read(cx, buf)
     }
 }
 
 // CRITICAL FIX: Implement AsyncWrite trait for TcpConnection
 // This allows using write_all and other AsyncWriteExt methods
-impl AsyncWrite for TcpConnection {
+impl<S: AsyncWrite + Unpin> AsyncWrite for TcpConnection<S> {
     fn poll_write(
         mut self: Pin<&mut Self>,
         cx: &mut Context<'_>,
@@ -220,8 +527,125 @@ impl AsyncWrite for TcpConnection {
 /// Protocol handler trait
 pub trait ProtocolHandler {
     /// Handle a client connection with this protocol
-    async fn handle_connection(
-        &mut self, 
-        conn: &mut TcpConnection
+    async fn handle_connection<S: AsyncRead + AsyncWrite + Unpin>(
+        &mut self,
+        conn: &mut TcpConnection<S>
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync>>;
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::persistence::aof::AppendOnlyFile;
+    use crate::storage::memory::MemTable;
+    use std::os::unix::io::AsRawFd;
+    
+    /// Read an integer socket option
+    fn getsockopt(socket: &TcpStream, level: libc::c_int, name: libc::c_int) -> libc::c_int {
+        let mut value: libc::c_int = 0;
+        let mut len = std::mem::size_of::<libc::c_int>() as libc::socklen_t;
+        let ret = unsafe {
+            libc::getsockopt(socket.as_raw_fd(), level, name, &mut value as *mut libc::c_int as *mut libc::c_void, &mut len)
+        };
+        assert_eq!(ret, 0, "{}", io::Error::last_os_error());
+        value
+    }
+    
+    #[test]
+    fn test_trace_bytes() {
+        assert_eq!(trace_bytes(b"user:1"), r#""user:1""#);
+        assert_eq!(trace_bytes(b"+OK\r\n"), r#""+OK\r\n""#);
+        assert_eq!(trace_bytes(b"a\"b"), r#""a\"b""#);
+        assert_eq!(trace_bytes(&[0x00, 0xff, b'a']), "0x00ff61");
+    }
+    
+    #[tokio::test]
+    async fn test_socket_options() {
+        let dir = tempfile::tempdir().unwrap();
+        let aof = AppendOnlyFile::new(dir.path().join("tcp.aof")).unwrap();
+        let state = Arc::new(GlobalState::new(Arc::new(MemTable::new()), aof));
+        let server = TcpServer::new("127.0.0.1".to_string(), 0, state)
+            .with_socket_options(true, Some(Duration::from_secs(60)));
+        
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let client = TcpStream::connect(listener.local_addr().unwrap()).await.unwrap();
+        let (socket, _) = listener.accept().await.unwrap();
+        
+        assert!(!socket.nodelay().unwrap());
+        assert_eq!(getsockopt(&socket, libc::SOL_SOCKET, libc::SO_KEEPALIVE), 0);
+        server.configure_socket(&socket).unwrap();
+        assert!(socket.nodelay().unwrap());
+        
+        // Keepalive probes start after the idle time, then every third of it
+        assert_eq!(getsockopt(&socket, libc::SOL_SOCKET, libc::SO_KEEPALIVE), 1);
+        #[cfg(target_os = "linux")]
+        {
+            assert_eq!(getsockopt(&socket, libc::IPPROTO_TCP, libc::TCP_KEEPIDLE), 60);
+            assert_eq!(getsockopt(&socket, libc::IPPROTO_TCP, libc::TCP_KEEPINTVL), 20);
+        }
+        
+        // Without a keepalive setting, SO_KEEPALIVE stays off
+        let server = server.with_socket_options(true, None);
+        let other = TcpStream::connect(listener.local_addr().unwrap()).await.unwrap();
+        let (socket, _) = listener.accept().await.unwrap();
+        server.configure_socket(&socket).unwrap();
+        assert_eq!(getsockopt(&socket, libc::SOL_SOCKET, libc::SO_KEEPALIVE), 0);
+        drop((client, other));
+    }
+    
+    #[tokio::test]
+    async fn test_commands_over_memory_stream() {
+        let dir = tempfile::tempdir().unwrap();
+        let aof = AppendOnlyFile::new(dir.path().join("memory.aof")).unwrap();
+        let state = Arc::new(GlobalState::new(Arc::new(MemTable::new()), aof));
+        
+        // No socket - the server end of an in-memory pipe goes through detection and dispatch
+        let (mut client, server) = tokio::io::duplex(64 * 1024);
+        let client_id = state.next_client_id();
+        let handler = tokio::spawn(TcpServer::handle_connection(TcpConnection::new(server), state.clone(), client_id));
+        
+        let commands: [&[&str]; 5] = [&["SET", "k", "v"], &["GET", "k"], &["DEL", "k"], &["GET", "k"], &["INFO", "server"]];
+        for command in commands {
+            let mut frame = format!("*{}\r\n", command.len());
+            for arg in command {
+                frame.push_str(&format!("${}\r\n{}\r\n", arg.len(), arg));
+            }
+            client.write_all(frame.as_bytes()).await.unwrap();
+        }
+        client.shutdown().await.unwrap();
+        
+        let mut replies = Vec::new();
+        client.read_to_end(&mut replies).await.unwrap();
+        handler.await.unwrap().unwrap();
+        
+        let replies = String::from_utf8(replies).unwrap();
+        assert!(replies.starts_with("+OK\r\n$1\r\nv\r\n:1\r\n$-1\r\n$"), "{:?}", replies);
+        assert!(replies.contains("# Server\r\n"));
+        assert!(state.get(b"k").is_none());
+    }
+    
+    #[tokio::test]
+    async fn test_declared_lengths_capped() {
+        let dir = tempfile::tempdir().unwrap();
+        let aof = AppendOnlyFile::new(dir.path().join("limits.aof")).unwrap();
+        let state = Arc::new(GlobalState::new(Arc::new(MemTable::new()), aof));
+        
+        // Each over-cap declaration gets an error and the connection dropped, before
+        // anything is read for the elements it announces
+        for (frame, error) in [
+            ("*3\r\n", "-ERR Protocol error: invalid multibulk length 3\r\n"),
+            ("*1\r\n$17\r\n", "-ERR Protocol error: invalid bulk length 17\r\n"),
+        ] {
+            let (mut client, server) = tokio::io::duplex(64 * 1024);
+            let conn = TcpConnection::new(server).with_max_bulk_len(16).with_max_multibulk_len(2);
+            let handler = tokio::spawn(TcpServer::handle_connection(conn, state.clone(), state.next_client_id()));
+            client.write_all(b"*2\r\n$3\r\nGET\r\n$1\r\nk\r\n").await.unwrap();
+            client.write_all(frame.as_bytes()).await.unwrap();
+            
+            let mut replies = Vec::new();
+            client.read_to_end(&mut replies).await.unwrap();
+            handler.await.unwrap().unwrap();
+            assert_eq!(String::from_utf8(replies).unwrap(), format!("$-1\r\n{}", error));
+        }
+    }
+}
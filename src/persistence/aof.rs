@@ -1,9 +1,19 @@
 use std::fs::{File,OpenOptions};
+use std::collections::VecDeque;
 use std::io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
 use std::path::{Path, PathBuf};
-use std::time::Duration;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+#[cfg(target_os = "linux")]
+use std::os::unix::io::AsRawFd;
 use crate::util::crc64::calculate_crc;
+use crate::storage::engine::StorageEngine;
 use crate::storage::memory::MemTable;
+use crate::storage::value::{Mutation, ValueKind};
+use crate::persistence::flush::FlushCoordinator;
+use crate::persistence::manifest::{self, Segment};
+use crate::util::latency::{LatencyMonitor, EVENT_AOF_FSYNC};
 
 
 /// Command types for AOF entries
@@ -11,7 +21,11 @@ use crate::storage::memory::MemTable;
 enum CommandType {
   Set = 1,
   Delete = 2,
-  // Future command types
+  Expire = 3,
+  Flush = 4,
+  SetValue = 5,
+  Mutate = 6,
+  Sequence = 7,
 }
 
 // AOF entry header - fixed size for easy seeking
@@ -33,23 +47,219 @@ struct EntryHeader {
   ttl_ms: u64,
 }
 
+/// Largest key an entry can hold
+pub const MAX_KEY_SIZE: usize = u16::MAX as usize;
+
+/// Largest value an entry can hold alongside a maximum-size key
+pub const MAX_VALUE_SIZE: usize = u32::MAX as usize - MAX_KEY_SIZE - std::mem::size_of::<EntryHeader>();
+
+/// Entries replayed between progress callbacks
+const REPLAY_PROGRESS_INTERVAL: usize = 100_000;
+
+/// One complete, CRC-verified AOF record
+struct RawEntry {
+  header: EntryHeader,
+  key: Vec<u8>,
+  value: Vec<u8>,
+}
+
+/// Result of reading the next record
+enum NextEntry {
+  // Complete record
+  Entry(RawEntry),
+  // Zeroed header - start of preallocated space
+  Preallocated,
+  // File ends partway through a record (crash mid-write)
+  Torn,
+}
+
+/// Recovery statistics from an AOF replay
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ReplayStats {
+  // Entries applied to the MemTable
+  pub applied: usize,
+  // Writes skipped because the key was tombstoned
+  pub skipped: usize,
+  // Bytes of complete entries read
+  pub bytes_read: u64,
+  // Incomplete (or, in lenient mode, corrupt) records dropped from the end of the file (0 or 1)
+  pub truncated_entries: usize,
+  // Bytes dropped with them
+  pub truncated_bytes: u64,
+  // Corrupt stretches skipped mid-file in lenient mode, each counted as one entry
+  pub corrupt_entries: usize,
+  // Bytes skipped with them
+  pub corrupt_bytes: u64,
+}
+
+/// How replay treats entries that fail their CRC check
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RecoveryMode {
+  /// Refuse to load an AOF with any corrupt entry
+  #[default]
+  Strict,
+  /// Skip corrupt entries, resuming at the next valid one, and truncate a corrupt tail
+  Lenient,
+}
+
+impl RecoveryMode {
+  /// Parse a mode name (strict, lenient)
+  pub fn from_name(name: &str) -> Option<Self> {
+      match name.to_ascii_lowercase().as_str() {
+          "strict" => Some(Self::Strict),
+          "lenient" => Some(Self::Lenient),
+          _ => None,
+      }
+  }
+  
+  /// Mode name
+  pub fn name(&self) -> &'static str {
+      match self {
+          Self::Strict => "strict",
+          Self::Lenient => "lenient",
+      }
+  }
+}
+
+/// A point in the AOF that a snapshot was taken at
+/// Offsets never move backwards, so the mark stays usable until a rewrite compacts
+/// the entries after it into a base segment starting past it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AofMark {
+  // Logical offset - entries before it are in the snapshot
+  pub offset: u64,
+}
+
+/// A decoded AOF entry as yielded by `AppendOnlyFile::iter_entries`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AofEntry {
+  // Logical offset of the entry (across segments)
+  pub offset: u64,
+  // Raw command type byte
+  pub cmd_type: u8,
+  pub key: Vec<u8>,
+  pub value: Vec<u8>,
+  // TTL in milliseconds, 0 for none
+  pub ttl_ms: u64,
+  // When the entry was logged (ms since epoch)
+  pub timestamp_ms: u64,
+}
+
+/// Iterator over the CRC-verified entries of an AOF file
+/// Stops at preallocated space, a torn tail, or the first corrupt entry
+pub struct EntryIter {
+  reader: BufReader<File>,
+  // Position within the current file
+  position: u64,
+  // Length of the current file
+  len: u64,
+  // Logical offset of the current file's first byte
+  base: u64,
+  // Segment files still to read, with their base offsets
+  rest: VecDeque<(PathBuf, u64)>,
+  // Combined length of every file
+  total_len: u64,
+  torn: bool,
+  done: bool,
+}
+
+/// Integrity report for an AOF file, produced without loading it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AofCheck {
+  // Entries with a valid header and CRC
+  pub valid_entries: usize,
+  // Bytes covered by those entries
+  pub valid_bytes: u64,
+  // Physical file length
+  pub file_len: u64,
+  // Offset of the first bad entry, if any
+  pub corruption_offset: Option<u64>,
+  // Whether the damage is an incomplete final entry that truncation removes
+  pub tail_truncatable: bool,
+}
+
+/// Fsync policy for AOF writes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FsyncPolicy {
+  /// Fsync after every entry - safest, slowest
+  Always,
+  /// Fsync once per second on a background thread
+  #[default]
+  EverySecond,
+  /// Leave fsync to the OS
+  No,
+}
+
+impl FsyncPolicy {
+  /// Parse a policy name as Redis spells appendfsync (always, everysec, no)
+  pub fn from_name(name: &str) -> Option<Self> {
+      match name.to_ascii_lowercase().as_str() {
+          "always" => Some(Self::Always),
+          "everysec" => Some(Self::EverySecond),
+          "no" => Some(Self::No),
+          _ => None,
+      }
+  }
+  
+  /// Policy name
+  pub fn name(&self) -> &'static str {
+      match self {
+          Self::Always => "always",
+          Self::EverySecond => "everysec",
+          Self::No => "no",
+      }
+  }
+}
+
+/// Destination for encoded entries
+enum AofWriter {
+  // Write (and optionally fsync) on the caller's thread
+  Direct(BufWriter<File>),
+  // Hand entries to the background flush thread
+  Background(FlushCoordinator),
+  // Opened with `open_readonly` - appends are rejected
+  ReadOnly,
+}
+
 /// AppendOnlyFile - Durability persistence layer
 pub struct AppendOnlyFile {
   // Path to AOF file
   path: PathBuf,
   // Open file handle
   file: File,
-  // Write buffer for batching
-  writer: BufWriter<File>,
-  // Current file position
+  // Write path for new entries
+  writer: AofWriter,
+  // Fsync policy in effect
+  fsync_policy: FsyncPolicy,
+  // Current file position (logical end of written entries)
   position: u64,
+  // Physical file length, may run ahead of position when preallocating
+  allocated: u64,
+  // Preallocation chunk size in bytes (0 = disabled)
+  preallocate_chunk: u64,
   // Count of records replayed during recovery
   replay_count: usize,
+  // Logical offset up to which entries are fsynced (shared with the flush thread)
+  synced: Arc<AtomicU64>,
+  // Set when the flush thread hits a write or fsync error (shared with it)
+  failed: Arc<AtomicBool>,
+  // Segment files in replay order, the last one active (None = single file at `path`)
+  segments: Option<Vec<Segment>>,
+  // Roll over to a new segment once the active one reaches this many bytes (0 = never)
+  segment_size: u64,
+  // What replay does about corrupt entries
+  recovery_mode: RecoveryMode,
 }
 
 impl AppendOnlyFile {
-  /// Create or open AOF file
+  /// Create or open AOF file with the default fsync policy
   pub fn new<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+    Self::with_fsync_policy(path, FsyncPolicy::default())
+  }
+
+  /// Create or open AOF file with a specific fsync policy
+  /// A manifest next to `path` means the AOF is segmented; its last segment is opened
+  pub fn with_fsync_policy<P: AsRef<Path>>(path: P, fsync_policy: FsyncPolicy) -> io::Result<Self> {
     let path_buf = Self::resolve_aof_path(path)?;
     
     // Create directories if needed
@@ -57,110 +267,920 @@ impl AppendOnlyFile {
         std::fs::create_dir_all(parent)?;
     }
 
-    // Open or create file
-    let file = OpenOptions::new()
+    // Open or create file - appends go through a clone sharing its cursor, so start at the end
+    let segments = manifest::load(&path_buf)?;
+    let mut file = OpenOptions::new()
         .read(true)
         .write(true)
         .create(true)
-        .open(&path_buf)?;
+        .truncate(false)
+        .open(Self::active_path(&path_buf, segments.as_deref()))?;
 
-    let position = file.metadata()?.len();
-    let writer = BufWriter::new(file.try_clone()?);
+    let position = file.seek(SeekFrom::End(0))?;
+    let base = segments.as_deref().and_then(<[Segment]>::last).map_or(0, |segment| segment.base);
+    let synced = Arc::new(AtomicU64::new(base + position));
+    let failed = Arc::new(AtomicBool::new(false));
+    let buffered = BufWriter::new(file.try_clone()?);
+    let writer = match fsync_policy {
+        FsyncPolicy::EverySecond => AofWriter::Background(FlushCoordinator::start(buffered, synced.clone(), failed.clone())),
+        FsyncPolicy::Always | FsyncPolicy::No => AofWriter::Direct(buffered),
+    };
 
     Ok(Self {
         path: path_buf,
         file,
         writer,
+        fsync_policy,
         position,
+        allocated: position,
+        preallocate_chunk: 0,
         replay_count: 0,
+        synced,
+        failed,
+        segments,
+        segment_size: 0,
+        recovery_mode: RecoveryMode::default(),
+    })
+  }
+
+  /// Open an existing AOF without write access (e.g. a standby on a read-only mount)
+  /// Replay works as usual; appends fail and the file is never modified
+  pub fn open_readonly<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+    let path_buf = Self::resolve_aof_path(path)?;
+    let segments = manifest::load(&path_buf)?;
+    let file = File::open(Self::active_path(&path_buf, segments.as_deref()))?;
+    let position = file.metadata()?.len();
+    let base = segments.as_deref().and_then(<[Segment]>::last).map_or(0, |segment| segment.base);
+    
+    Ok(Self {
+        path: path_buf,
+        file,
+        writer: AofWriter::ReadOnly,
+        fsync_policy: FsyncPolicy::No,
+        position,
+        allocated: position,
+        preallocate_chunk: 0,
+        replay_count: 0,
+        synced: Arc::new(AtomicU64::new(base + position)),
+        failed: Arc::new(AtomicBool::new(false)),
+        segments,
+        segment_size: 0,
+        recovery_mode: RecoveryMode::default(),
     })
   }
   
+  /// Whether the file was opened with `open_readonly`
+  pub fn is_read_only(&self) -> bool {
+      matches!(self.writer, AofWriter::ReadOnly)
+  }
+  
+  /// Preallocate the file in chunks of `chunk_size` bytes ahead of writes
+  /// Reduces fragmentation and metadata updates; no-op without fallocate
+  pub fn set_preallocation(&mut self, chunk_size: u64) {
+      if cfg!(target_os = "linux") {
+          self.preallocate_chunk = chunk_size;
+      }
+  }
+
+  /// Roll over into numbered segment files once the active file reaches `size` bytes
+  /// (0 = never). A single-file AOF becomes segment 0001 on its first rollover or rewrite
+  pub fn set_segment_size(&mut self, size: u64) {
+      self.segment_size = size;
+  }
+
+  /// Choose whether replay refuses corrupt entries (strict) or skips past them (lenient)
+  pub fn set_recovery_mode(&mut self, mode: RecoveryMode) {
+      self.recovery_mode = mode;
+  }
+
+  /// Segments of a segmented AOF in replay order (None for a single file)
+  pub fn segments(&self) -> Option<&[Segment]> {
+      self.segments.as_deref()
+  }
+
+  /// Logical length of the AOF (end of the last written entry, across segments)
+  /// Never moves backwards - a rewrite continues from the offset it replaced
+  pub fn logical_len(&self) -> u64 {
+      self.segment_base() + self.position
+  }
+  
+  /// Bytes of entries the AOF holds on disk - unlike `logical_len`, drops after a rewrite
+  pub fn size(&self) -> u64 {
+      let first_base = self.segments.as_deref().and_then(<[Segment]>::first).map_or(0, |segment| segment.base);
+      self.logical_len() - first_base
+  }
+
+  /// Offset up to which entries are fsynced, readable without holding the AOF
+  /// With FsyncPolicy::No a written entry counts as synced, as in Redis (WAITAOF)
+  pub fn synced_offset(&self) -> Arc<AtomicU64> {
+      self.synced.clone()
+  }
+
+  /// Physical length of the AOF including preallocated space
+  pub fn allocated_len(&self) -> u64 {
+      self.allocated
+  }
+
+  /// Truncate preallocated space back to the logical length
+  pub fn truncate_to_logical(&mut self) -> io::Result<()> {
+      if self.allocated > self.position && !self.is_read_only() {
+          self.file.set_len(self.position)?;
+          self.allocated = self.position;
+      }
+      Ok(())
+  }
+  
   /// Get count of records replayed during recovery
   pub fn replay_count(&self) -> usize {
       self.replay_count
   }
+
+  /// Get path of the AOF file (the name segments and the manifest derive from)
+  pub fn path(&self) -> &Path {
+      &self.path
+  }
+
+  /// Get fsync policy in effect
+  pub fn fsync_policy(&self) -> FsyncPolicy {
+      self.fsync_policy
+  }
+
+  /// Flush and fsync everything appended so far, regardless of fsync policy
+  pub fn sync(&mut self) -> io::Result<()> {
+      match &mut self.writer {
+          AofWriter::Background(coordinator) => coordinator.sync_now(),
+          AofWriter::Direct(writer) => {
+              writer.flush()?;
+              writer.get_ref().sync_data()?;
+              self.synced.store(self.logical_len(), Ordering::Release);
+              Ok(())
+          }
+          AofWriter::ReadOnly => Ok(()),
+      }
+  }
+  
+  /// Whether the background flush thread has hit a write or fsync error
+  pub fn has_failed(&self) -> bool {
+      self.failed.load(Ordering::Relaxed)
+  }
+  
+  /// Flag behind `has_failed`, readable without holding the AOF
+  pub fn failure_flag(&self) -> Arc<AtomicBool> {
+      self.failed.clone()
+  }
+  
+  /// Number of entries written but not yet fsynced
+  pub fn pending_fsync(&self) -> u64 {
+      match &self.writer {
+          AofWriter::Background(coordinator) => coordinator.pending(),
+          AofWriter::Direct(_) | AofWriter::ReadOnly => 0,
+      }
+  }
   
   /// Append SET command to AOF
   pub fn append_set(&mut self, key: &[u8], value: &[u8], ttl: Option<Duration>) -> io::Result<u64> {
       // Validate inputs
-      if key.len() > u16::MAX as usize {
+      if value.len() > MAX_VALUE_SIZE {
           return Err(io::Error::new(
               io::ErrorKind::InvalidInput,
-              "Key too large"
+              "Value too large"
           ));
       }
       
-      if value.len() > u32::MAX as usize {
+      // Convert TTL to milliseconds
+      let ttl_ms = ttl.map(|d| d.as_millis() as u64).unwrap_or(0);
+      
+      self.append_entry(CommandType::Set, key, value, ttl_ms)
+  }
+  
+  /// Append DELETE command to AOF
+  pub fn append_delete(&mut self, key: &[u8]) -> io::Result<u64> {
+      self.append_entry(CommandType::Delete, key, &[], 0)
+  }
+  
+  /// Append EXPIRE command to AOF (None clears the TTL)
+  pub fn append_expire(&mut self, key: &[u8], ttl: Option<Duration>) -> io::Result<u64> {
+      // Zero TTL means persist, so clamp real TTLs to at least 1ms
+      let ttl_ms = ttl.map(|d| (d.as_millis() as u64).max(1)).unwrap_or(0);
+      
+      self.append_entry(CommandType::Expire, key, &[], ttl_ms)
+  }
+  
+  /// Append a whole typed value (collections written in one piece)
+  pub fn append_value(&mut self, key: &[u8], value: &ValueKind, ttl: Option<Duration>) -> io::Result<u64> {
+      let encoded = value.encode();
+      if encoded.len() > u32::MAX as usize {
           return Err(io::Error::new(
               io::ErrorKind::InvalidInput,
               "Value too large"
           ));
       }
       
-      // Convert TTL to milliseconds
-      let ttl_ms = ttl.map(|d| d.as_millis() as u64).unwrap_or(0);
+      let ttl_ms = ttl.map(|d| (d.as_millis() as u64).max(1)).unwrap_or(0);
+      self.append_entry(CommandType::SetValue, key, &encoded, ttl_ms)
+  }
+  
+  /// Append an incremental collection mutation
+  pub fn append_mutation(&mut self, key: &[u8], mutation: &Mutation) -> io::Result<u64> {
+      let encoded = mutation.encode();
+      if encoded.len() > u32::MAX as usize {
+          return Err(io::Error::new(
+              io::ErrorKind::InvalidInput,
+              "Mutation too large"
+          ));
+      }
       
-      // Create entry header (without CRC for now)
-      let header_size = std::mem::size_of::<EntryHeader>();
-      let total_size = header_size + key.len() + value.len();
+      self.append_entry(CommandType::Mutate, key, &encoded, 0)
+  }
+  
+  /// Append a SEQ high-water mark: ids up to `high_water` of sequence `name` are taken
+  /// Marks live outside the keyspace, so a later FLUSH marker leaves them in place
+  pub fn append_sequence(&mut self, name: &[u8], high_water: u64) -> io::Result<u64> {
+      self.append_entry(CommandType::Sequence, name, &high_water.to_le_bytes(), 0)
+  }
+  
+  /// Append FLUSH marker to AOF - replay clears everything before it
+  pub fn append_flush(&mut self) -> io::Result<u64> {
+      self.append_entry(CommandType::Flush, &[], &[], 0)
+  }
+  
+  /// Replay existing entries from file for recovery
+  pub fn replay_existing_entries(&mut self, mem_table: &dyn StorageEngine) -> io::Result<ReplayStats> {
+      self.replay_with_progress(mem_table, |_, _, _| {})
+  }
+  
+  /// Replay existing entries, calling `progress(replayed, bytes_done, bytes_total)`
+  /// every REPLAY_PROGRESS_INTERVAL entries and once at the end.
+  /// A record cut short by a crash at the end of the file is dropped and the
+  /// file truncated to the last complete entry. A CRC mismatch fails the replay in
+  /// strict mode; lenient mode skips to the next valid entry or truncates a corrupt tail
+  pub fn replay_with_progress(
+      &mut self,
+      mem_table: &dyn StorageEngine,
+      progress: impl FnMut(usize, u64, u64)
+  ) -> io::Result<ReplayStats> {
+    self.replay_from(0, mem_table, progress)
+  }
+  
+  /// Recovery on top of a snapshot: replay only the entries from logical offset `from`
+  /// (a snapshot's mark) onwards, otherwise as `replay_with_progress`
+  pub fn replay_from(
+      &mut self,
+      from: u64,
+      mem_table: &dyn StorageEngine,
+      progress: impl FnMut(usize, u64, u64)
+  ) -> io::Result<ReplayStats> {
+    let result = self.replay_segments(from, mem_table, progress);
+    
+    // Replay reads through the cursor appends share - put it back at the logical end
+    // on every path, or the next append would overwrite the start of the file
+    self.file.seek(SeekFrom::Start(self.position))?;
+    result
+  }
+  
+  /// Body of `replay_from`, leaving the file cursor wherever reading stopped
+  fn replay_segments(
+      &mut self,
+      from: u64,
+      mem_table: &dyn StorageEngine,
+      mut progress: impl FnMut(usize, u64, u64)
+  ) -> io::Result<ReplayStats> {
+    // Sealed segments first - they were fsynced whole when the next one started
+    let mut sealed = ReplayStats::default();
+    let files = self.segment_files();
+    for (i, (path, base)) in files[..files.len() - 1].iter().enumerate() {
+        if files[i + 1].1 <= from {
+            continue;
+        }
+        let file = File::open(path)?;
+        let len = file.metadata()?.len();
+        let start = from.saturating_sub(*base);
+        let stats = Self::replay_file(&file, mem_table, start, len, self.recovery_mode, &mut progress)?;
+        if stats.truncated_entries > 0 {
+            if self.recovery_mode == RecoveryMode::Strict {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Incomplete entry in sealed AOF segment {}", path.display())
+                ));
+            }
+            // Later segments follow, so the bad tail is skipped rather than cut
+            eprintln!("Skipping {} bytes at the end of sealed AOF segment {}", stats.truncated_bytes, path.display());
+            sealed.corrupt_entries += stats.truncated_entries;
+            sealed.corrupt_bytes += stats.truncated_bytes;
+        }
+        self.replay_count += stats.applied;
+        sealed.applied += stats.applied;
+        sealed.skipped += stats.skipped;
+        sealed.bytes_read += stats.bytes_read;
+        sealed.corrupt_entries += stats.corrupt_entries;
+        sealed.corrupt_bytes += stats.corrupt_bytes;
+    }
+    let start = from.saturating_sub(self.segment_base());
+    if start > self.position {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "Replay start is past the end of the AOF"));
+    }
+    if self.position == start {
+        return Ok(sealed);
+    }
+    
+    let mut stats = self.replay_range(mem_table, start, self.position, &mut progress)?;
+    let position = start + stats.bytes_read;
+    
+    // Logical end may be short of the physical end when preallocated
+    self.position = position;
+    self.synced.store(self.logical_len(), Ordering::Release);
+    stats.applied += sealed.applied;
+    stats.skipped += sealed.skipped;
+    stats.bytes_read += sealed.bytes_read;
+    stats.corrupt_entries += sealed.corrupt_entries;
+    stats.corrupt_bytes += sealed.corrupt_bytes;
+    
+    // A torn tail would otherwise sit between the old entries and new ones.
+    // A read-only reader leaves it for the writer to finish or cut
+    if stats.truncated_entries > 0 && !self.is_read_only() {
+        self.file.set_len(position)?;
+        self.allocated = position;
+    }
+    Ok(stats)
+  }
+  
+  /// Fsync and mark the current end of the AOF (for a snapshot of the same moment)
+  pub fn mark(&mut self) -> io::Result<AofMark> {
+      self.sync()?;
+      Ok(AofMark { offset: self.logical_len() })
+  }
+  
+  /// Whether the AOF still holds every entry after `mark`: its offset lies between the
+  /// first segment's base and the end. Checked before replaying, so the end may still
+  /// include preallocated space
+  pub fn matches_mark(&self, mark: &AofMark) -> bool {
+      let first_base = self.segments.as_deref().and_then(<[Segment]>::first).map_or(0, |segment| segment.base);
+      (first_base..=self.logical_len()).contains(&mark.offset)
+  }
+  
+  /// Apply entries appended since logical offset `from` (a previously returned safe offset)
+  /// Stops before a partially written entry without consuming it and returns the
+  /// new safe offset - a standby calls this in a loop to follow the primary's AOF.
+  /// Once the open file is used up it moves on to the segment the writer rolled over to.
+  /// After a rewrite the new file is a full image, so `mem_table` is cleared and rebuilt
+  pub fn replay_since(&mut self, from: u64, mem_table: &dyn StorageEngine) -> io::Result<u64> {
+    let mut offset = from;
+    loop {
+        // Checked before the length: a segment is complete before the manifest moves past it
+        let next = self.next_segment()?;
+        let base = self.segment_base();
+        let file_len = self.file.metadata()?.len();
+        let start = offset.checked_sub(base)
+            .filter(|&start| start <= file_len)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "Replay offset is outside the open AOF file"))?;
+        if start < file_len {
+            let stats = self.replay_range(mem_table, start, file_len, &mut |_, _, _| {})?;
+            offset += stats.bytes_read;
+            self.position = self.position.max(offset - base);
+        }
+        
+        let Some((segments, rewritten)) = next else {
+            return Ok(offset);
+        };
+        if rewritten {
+            mem_table.recover_clear();
+        } else if offset - base < file_len {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "Incomplete entry in sealed AOF segment"));
+        }
+        let Some(&segment) = segments.last() else {
+            return Ok(offset);
+        };
+        self.file = File::open(manifest::segment_path(&self.path, segment.number))?;
+        self.segments = Some(segments);
+        self.position = 0;
+        self.allocated = 0;
+        offset = segment.base;
+    }
+  }
+  
+  /// Where a reader goes after the open file, if the writer has moved past it: the manifest
+  /// up to the next segment, and whether that segment starts a rewrite (a full image)
+  /// rather than continuing the open one
+  fn next_segment(&self) -> io::Result<Option<(Vec<Segment>, bool)>> {
+    let Some(mut segments) = manifest::load(&self.path)? else {
+        return Ok(None);
+    };
+    
+    // A single file is segment 0001 once it is first rolled over
+    let current = self.segments.as_deref().and_then(<[Segment]>::last).copied()
+        .unwrap_or(Segment { number: 1, base: 0 });
+    match segments.iter().position(|segment| *segment == current) {
+        Some(i) if i + 1 < segments.len() => {
+            segments.truncate(i + 2);
+            Ok(Some((segments, false)))
+        }
+        Some(_) => Ok(None),
+        None => {
+            segments.truncate(1);
+            Ok(Some((segments, true)))
+        }
+    }
+  }
+  
+  /// Apply the complete entries in `[start, end)` of the open file
+  fn replay_range(
+      &mut self,
+      mem_table: &dyn StorageEngine,
+      start: u64,
+      end: u64,
+      progress: &mut impl FnMut(usize, u64, u64)
+  ) -> io::Result<ReplayStats> {
+    let stats = Self::replay_file(&self.file, mem_table, start, end, self.recovery_mode, progress)?;
+    self.replay_count += stats.applied;
+    Ok(stats)
+  }
+  
+  /// Apply the complete entries in `[start, end)` of `file`; `bytes_read` is relative to `start`
+  /// and includes corrupt bytes skipped in lenient mode
+  fn replay_file(
+      file: &File,
+      mem_table: &dyn StorageEngine,
+      start: u64,
+      end: u64,
+      mode: RecoveryMode,
+      progress: &mut impl FnMut(usize, u64, u64)
+  ) -> io::Result<ReplayStats> {
+    let mut stats = ReplayStats::default();
+    let mut reader = BufReader::new(file);
+    reader.seek(SeekFrom::Start(start))?;
+    
+    // Reading stops at `end` even if the file has grown since
+    let mut reader = reader.take(end - start);
+    let mut position = start;
+
+    while position < end {
+        let (entry, torn) = match Self::read_entry(&mut reader, position, end) {
+            Ok(NextEntry::Entry(entry)) => (Some(entry), false),
+            // Zeroed header marks the start of preallocated space
+            Ok(NextEntry::Preallocated) => break,
+            Ok(NextEntry::Torn) => (None, true),
+            Err(e) if e.kind() == io::ErrorKind::InvalidData && mode == RecoveryMode::Lenient => (None, false),
+            Err(e) => return Err(e),
+        };
+        let Some(entry) = entry else {
+            // Only the last record can be torn by a crash - a cut-short record with valid
+            // ones after it has a corrupt length field
+            let Some(next) = Self::resync(file, position + 1, end)? else {
+                if !torn {
+                    eprintln!("AOF corrupt at position {} with no valid entry after it, dropping the tail", position);
+                }
+                stats.truncated_entries += 1;
+                stats.truncated_bytes = end - position;
+                break;
+            };
+            if mode == RecoveryMode::Strict {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("AOF entry at position {} runs past a valid entry at {}", position, next)
+                ));
+            }
+            eprintln!("AOF corrupt at position {}, skipped {} bytes to the next valid entry", position, next - position);
+            stats.corrupt_entries += 1;
+            stats.corrupt_bytes += next - position;
+            position = next;
+            stats.bytes_read = position - start;
+            reader.get_mut().seek(SeekFrom::Start(position))?;
+            reader.set_limit(end - position);
+            continue;
+        };
+        let (cmd_type, size, ttl_ms) = (entry.header.cmd_type, entry.header.size, entry.header.ttl_ms);
+        let (key, value) = (entry.key, entry.value);
+        position += size as u64;
+        stats.bytes_read = position - start;
+
+        // Keys deleted within the tombstone window stay deleted - stale writes are skipped
+        let is_write = [CommandType::Set as u8, CommandType::SetValue as u8, CommandType::Mutate as u8]
+            .contains(&cmd_type);
+        if is_write && mem_table.is_tombstoned(&key) {
+            stats.skipped += 1;
+            continue;
+        }
+        
+        // Apply to the storage engine
+        let ttl = (ttl_ms > 0).then(|| Duration::from_millis(ttl_ms));
+        Self::apply_entry(mem_table, cmd_type, &key, value, ttl)?;
+        
+        stats.applied += 1;
+        if stats.applied.is_multiple_of(REPLAY_PROGRESS_INTERVAL) {
+            progress(stats.applied, position, end);
+        }
+    }
+    progress(stats.applied, position, end);
+    Ok(stats)
+  }
+  
+  /// Apply one decoded entry to the storage engine, bypassing logging
+  fn apply_entry(mem_table: &dyn StorageEngine, cmd_type: u8, key: &[u8], value: Vec<u8>, ttl: Option<Duration>) -> io::Result<()> {
+      let applied = match cmd_type {
+          x if x == CommandType::Set as u8 => {
+              mem_table.recover_set(key, value, ttl).map(|_| ())
+          }
+          x if x == CommandType::Delete as u8 => {
+              mem_table.recover_delete(key).map(|_| ())
+          }
+          x if x == CommandType::Expire as u8 => {
+              mem_table.recover_expiry(key, ttl);
+              Ok(())
+          }
+          x if x == CommandType::Flush as u8 => {
+              mem_table.recover_clear();
+              Ok(())
+          }
+          x if x == CommandType::SetValue as u8 => {
+              ValueKind::decode(&value)
+                  .and_then(|value| mem_table.recover_value(key, value, ttl))
+          }
+          x if x == CommandType::Mutate as u8 => {
+              Mutation::decode(&value)
+                  .and_then(|mutation| mem_table.recover_mutation(key, &mutation))
+                  .map(|_| ())
+          }
+          x if x == CommandType::Sequence as u8 => {
+              <[u8; 8]>::try_from(value.as_slice())
+                  .map(|high_water| mem_table.recover_sequence(key, u64::from_le_bytes(high_water)))
+                  .map_err(|_| "malformed sequence mark".to_string())
+          }
+          _ => return Err(io::Error::new(
+              io::ErrorKind::InvalidData,
+              "Unknown command type"
+          ))
+      };
+      applied.map_err(|e| io::Error::new(
+          io::ErrorKind::InvalidData,
+          format!("Replay failed: {}", e)
+      ))
+  }
+  
+  /// Rebuild the dataset as it stood at `timestamp_ms` (ms since epoch) from the AOF at `path`
+  /// Only entries logged at or before that moment are applied, and TTLs count down from
+  /// it - a key whose TTL had run out by then is absent. The file is only read.
+  /// A rewritten AOF stamps its entries with the rewrite time, so history before it is lost
+  pub fn reconstruct_as_of<P: AsRef<Path>>(path: P, timestamp_ms: u64) -> io::Result<MemTable> {
+      let mem_table = MemTable::new();
+      for entry in Self::iter_entries(path)? {
+          let entry = entry?;
+          if entry.timestamp_ms > timestamp_ms {
+              continue;
+          }
+          
+          // TTLs are relative to when the entry was logged
+          let expires_at = (entry.ttl_ms > 0).then(|| entry.timestamp_ms.saturating_add(entry.ttl_ms));
+          if expires_at.is_some_and(|expires_at| expires_at <= timestamp_ms) {
+              Self::apply_entry(&mem_table, CommandType::Delete as u8, &entry.key, Vec::new(), None)?;
+              continue;
+          }
+          let ttl = expires_at.map(|expires_at| Duration::from_millis(expires_at - timestamp_ms));
+          Self::apply_entry(&mem_table, entry.cmd_type, &entry.key, entry.value, ttl)?;
+      }
+      Ok(mem_table)
+  }
+  
+  /// Write a compacted AOF with one record per entry to `path` and fsync it
+  /// Strings are logged as SET, other types as a whole value; returns the bytes written
+  pub fn write_compacted<P: AsRef<Path>>(
+      path: P,
+      entries: impl IntoIterator<Item = (Vec<u8>, ValueKind, Option<Duration>)>
+  ) -> io::Result<u64> {
+      let mut writer = BufWriter::new(File::create(path)?);
+      let mut written = 0;
+      for (key, value, ttl) in entries {
+          let ttl_ms = ttl.map(|d| (d.as_millis() as u64).max(1)).unwrap_or(0);
+          let entry = match value.as_string() {
+              Ok(bytes) => Self::encode_entry(CommandType::Set, &key, &bytes, ttl_ms)?,
+              Err(_) => Self::encode_entry(CommandType::SetValue, &key, &value.encode(), ttl_ms)?,
+          };
+          writer.write_all(&entry)?;
+          written += entry.len() as u64;
+      }
       
-      let mut header = EntryHeader {
-          crc: 0, // Will calculate after preparing full entry
-          size: total_size as u32,
-          cmd_type: CommandType::Set as u8,
-          timestamp: Self::current_timestamp_ms(),
-          key_size: key.len() as u16,
-          value_size: value.len() as u32,
-          ttl_ms,
+      writer.flush()?;
+      writer.get_ref().sync_all()?;
+      Ok(written)
+  }
+  
+  /// Replace this AOF with a compacted file from `write_compacted`
+  /// Entries appended since `from_position` (the logical length when the compacted
+  /// snapshot was taken) are carried over first. Writers must be held off throughout.
+  /// The result becomes the only segment, starting at the logical offset the old files
+  /// ended at - offsets never move backwards, and the manifest keeps that across restarts.
+  /// A single-file AOF is segmented from here on
+  pub fn install_rewrite<P: AsRef<Path>>(&mut self, rewritten: P, from_position: u64) -> io::Result<()> {
+      if self.is_read_only() {
+          return Err(io::Error::new(
+              io::ErrorKind::PermissionDenied,
+              "AOF is opened read-only"
+          ));
+      }
+      let rewritten = rewritten.as_ref();
+      
+      // Everything appended so far must be in the file before the tail is copied
+      self.sync()?;
+      {
+          let mut out = OpenOptions::new().append(true).open(rewritten)?;
+          let end = self.logical_len();
+          let files = self.segment_files();
+          for (i, (path, base)) in files.iter().enumerate() {
+              let next_base = files.get(i + 1).map_or(end, |(_, base)| *base);
+              if next_base <= from_position {
+                  continue;
+              }
+              let start = from_position.max(*base);
+              let mut tail = File::open(path)?;
+              tail.seek(SeekFrom::Start(start - base))?;
+              io::copy(&mut tail.take(next_base - start), &mut out)?;
+          }
+          out.sync_all()?;
+      }
+      
+      // Until the manifest names the new segment, the old files stay in charge
+      let old = self.segment_files();
+      let number = self.segments.as_deref().and_then(<[Segment]>::last).map_or(1, |segment| segment.number + 1);
+      let base = [Segment { number, base: self.logical_len() }];
+      let segment_path = manifest::segment_path(&self.path, number);
+      std::fs::rename(rewritten, &segment_path)?;
+      manifest::sync_parent(&segment_path)?;
+      manifest::store(&self.path, &base)?;
+      self.segments = Some(base.to_vec());
+      let file = OpenOptions::new().read(true).write(true).open(&segment_path)?;
+      self.reopen_writer(file)?;
+      for (path, _) in old {
+          let _ = std::fs::remove_file(path);
+      }
+      Ok(())
+  }
+  
+  /// Seal the active file and continue in a new segment
+  /// A single-file AOF becomes segment 0001 first (linked, listed, then unlinked, so a
+  /// crash at any step leaves either the old file or a complete manifest in charge)
+  fn roll_segment(&mut self) -> io::Result<()> {
+      // Sealed segments are complete on disk and carry no preallocated tail
+      self.sync()?;
+      self.truncate_to_logical()?;
+      
+      let mut segments = match &self.segments {
+          Some(segments) => segments.clone(),
+          None => {
+              let first = manifest::segment_path(&self.path, 1);
+              match std::fs::remove_file(&first) {
+                  Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+                  _ => {}
+              }
+              std::fs::hard_link(&self.path, &first)?;
+              vec![Segment { number: 1, base: 0 }]
+          }
+      };
+      let next = Segment {
+          number: segments.last().map_or(1, |segment| segment.number + 1),
+          base: self.logical_len(),
       };
+      let next_path = manifest::segment_path(&self.path, next.number);
+      let file = OpenOptions::new()
+          .read(true)
+          .write(true)
+          .create(true)
+          .truncate(true)
+          .open(&next_path)?;
       
-      // Prepare full entry in memory for CRC calculation
-      let mut entry_buf = Vec::with_capacity(total_size);
+      // The link and the new file must be on disk before the manifest names them
+      manifest::sync_parent(&next_path)?;
+      segments.push(next);
+      manifest::store(&self.path, &segments)?;
+      if self.segments.is_none() {
+          std::fs::remove_file(&self.path)?;
+      }
       
-      // Write header placeholder
-      let header_bytes = unsafe {
-          std::slice::from_raw_parts(
-              &header as *const EntryHeader as *const u8,
-              header_size
-          )
+      self.segments = Some(segments);
+      self.reopen_writer(file)
+  }
+  
+  /// Continue appending at the end of `file`, whose contents are already fsynced
+  fn reopen_writer(&mut self, mut file: File) -> io::Result<()> {
+      let len = file.seek(SeekFrom::End(0))?;
+      let buffered = BufWriter::new(file.try_clone()?);
+      if let AofWriter::Background(coordinator) = &mut self.writer {
+          coordinator.shutdown();
+      }
+      // A fresh file and flush thread start without the old one's failure
+      self.failed.store(false, Ordering::Relaxed);
+      self.writer = match self.fsync_policy {
+          FsyncPolicy::EverySecond => AofWriter::Background(FlushCoordinator::start(buffered, self.synced.clone(), self.failed.clone())),
+          FsyncPolicy::Always | FsyncPolicy::No => AofWriter::Direct(buffered),
       };
-      entry_buf.extend_from_slice(header_bytes);
+      self.file = file;
+      self.position = len;
+      self.allocated = len;
+      self.synced.store(self.logical_len(), Ordering::Release);
+      Ok(())
+  }
+  
+  /// Logical offset of the active file's first byte
+  fn segment_base(&self) -> u64 {
+      self.segments.as_deref().and_then(<[Segment]>::last).map_or(0, |segment| segment.base)
+  }
+  
+  /// Every file of the AOF with its base offset, in replay order
+  fn segment_files(&self) -> Vec<(PathBuf, u64)> {
+      Self::files_of(&self.path, self.segments.as_deref())
+  }
+  
+  /// Files making up the AOF at `path` given its manifest (None = the single file)
+  fn files_of(path: &Path, segments: Option<&[Segment]>) -> Vec<(PathBuf, u64)> {
+      match segments {
+          Some(segments) => segments.iter()
+              .map(|segment| (manifest::segment_path(path, segment.number), segment.base))
+              .collect(),
+          None => vec![(path.to_path_buf(), 0)],
+      }
+  }
+  
+  /// File new entries go to
+  fn active_path(path: &Path, segments: Option<&[Segment]>) -> PathBuf {
+      match segments.and_then(<[Segment]>::last) {
+          Some(segment) => manifest::segment_path(path, segment.number),
+          None => path.to_path_buf(),
+      }
+  }
+  
+  /// Iterate over the entries of the AOF at `path` (every segment, in order) without
+  /// opening it for writing
+  pub fn iter_entries<P: AsRef<Path>>(path: P) -> io::Result<EntryIter> {
+      let path = Self::resolve_aof_path(path)?;
+      let mut rest: VecDeque<(PathBuf, u64)> = Self::files_of(&path, manifest::load(&path)?.as_deref()).into();
+      let mut total_len = 0;
+      for (path, _) in &rest {
+          total_len += std::fs::metadata(path)?.len();
+      }
       
-      // Write key and value
-      entry_buf.extend_from_slice(key);
-      entry_buf.extend_from_slice(value);
+      let (first, base) = rest.pop_front().unwrap_or((path, 0));
+      let file = File::open(first)?;
+      let len = file.metadata()?.len();
+      Ok(EntryIter {
+          reader: BufReader::new(file),
+          position: 0,
+          len,
+          base,
+          rest,
+          total_len,
+          torn: false,
+          done: false,
+      })
+  }
+  
+  /// Verify every entry's CRC in the AOF at `path`, like fsck
+  pub fn check<P: AsRef<Path>>(path: P) -> io::Result<AofCheck> {
+      let mut entries = Self::iter_entries(path)?;
+      let mut valid_entries = 0;
+      let mut corrupt = false;
+      for entry in entries.by_ref() {
+          match entry {
+              Ok(_) => valid_entries += 1,
+              Err(e) if e.kind() == io::ErrorKind::InvalidData => corrupt = true,
+              Err(e) => return Err(e),
+          }
+      }
       
-      // Calculate CRC over the entry (excluding CRC field itself)
-      let crc = calculate_crc(&entry_buf[8..]); // Skip CRC field
+      let torn = entries.torn_tail();
+      Ok(AofCheck {
+          valid_entries,
+          valid_bytes: entries.position(),
+          file_len: entries.total_len,
+          corruption_offset: (corrupt || torn).then(|| entries.position()),
+          tail_truncatable: torn,
+      })
+  }
+  
+  /// Offset of the first entry in `[from, end)` of `file` with a consistent header and a
+  /// matching CRC - where lenient replay resumes after a corrupt stretch
+  fn resync(file: &File, from: u64, end: u64) -> io::Result<Option<u64>> {
+      let header_size = std::mem::size_of::<EntryHeader>() as u64;
+      let cmd_types = CommandType::Set as u8..=CommandType::Sequence as u8;
+      let mut reader = BufReader::new(file);
+      reader.seek(SeekFrom::Start(from))?;
       
-      // Update header with CRC
-      header.crc = crc;
+      let mut header_buf = [0u8; std::mem::size_of::<EntryHeader>()];
+      for candidate in from..end.saturating_sub(header_size) + 1 {
+          if !Self::read_or_eof(&mut reader, &mut header_buf)? {
+              break;
+          }
+          let header: EntryHeader = unsafe {
+              std::ptr::read_unaligned(header_buf.as_ptr() as *const EntryHeader)
+          };
+          
+          // Cheap checks first - most offsets inside garbage fail them
+          let size = header.size as u64;
+          let consistent = cmd_types.contains(&header.cmd_type)
+              && size == header_size + header.key_size as u64 + header.value_size as u64
+              && candidate + size <= end;
+          if consistent {
+              reader.seek_relative(-(header_size as i64))?;
+              if let Ok(NextEntry::Entry(_)) = Self::read_entry(&mut (&mut reader).take(size), candidate, end) {
+                  return Ok(Some(candidate));
+              }
+              reader.seek(SeekFrom::Start(candidate + 1))?;
+          } else {
+              reader.seek_relative(1 - header_size as i64)?;
+          }
+      }
+      Ok(None)
+  }
+  
+  /// Read and CRC-check the record starting at `position`, in data ending at `end`
+  /// Lengths are checked against each other and `end` before anything is allocated
+  fn read_entry<R: Read>(reader: &mut R, position: u64, end: u64) -> io::Result<NextEntry> {
+      let header_size = std::mem::size_of::<EntryHeader>();
       
-      // Update entry buffer with correct header
-      let header_bytes = unsafe {
-          std::slice::from_raw_parts(
-              &header as *const EntryHeader as *const u8,
-              header_size
-          )
+      // Read and parse header
+      let mut header_buf = [0u8; std::mem::size_of::<EntryHeader>()];
+      if !Self::read_or_eof(reader, &mut header_buf)? {
+          return Ok(NextEntry::Torn);
+      }
+      let header: EntryHeader = unsafe { 
+          std::ptr::read_unaligned(header_buf.as_ptr() as *const EntryHeader)
       };
-      entry_buf[..header_size].copy_from_slice(header_bytes);
+      
+      if header.size == 0 && header.crc == 0 {
+          return Ok(NextEntry::Preallocated);
+      }
+      
+      // Validate entry
+      let size = header.size as u64;
+      if size != header_size as u64 + header.key_size as u64 + header.value_size as u64 {
+          return Self::corrupt_or_torn(reader, format!("Inconsistent AOF entry lengths at position {}", position));
+      }
+      if position + size > end {
+          return Ok(NextEntry::Torn);
+      }
+      
+      // Read key and value
+      let mut key = vec![0u8; header.key_size as usize];
+      let mut value = vec![0u8; header.value_size as usize];
+      if !Self::read_or_eof(reader, &mut key)? || !Self::read_or_eof(reader, &mut value)? {
+          return Ok(NextEntry::Torn);
+      }
+      
+      // Verify CRC
+      let mut crc_data = Vec::with_capacity(header.size as usize);
+      crc_data.extend_from_slice(&header_buf[8..]);
+      crc_data.extend_from_slice(&key);
+      crc_data.extend_from_slice(&value);
+      
+      if calculate_crc(&crc_data) != header.crc {
+          return Self::corrupt_or_torn(reader, format!("CRC mismatch at position {}", position));
+      }
+      
+      Ok(NextEntry::Entry(RawEntry { header, key, value }))
+  }
+  
+  /// A bad record is the torn tail if a zeroed header (or nothing at all) follows it -
+  /// a crash mid-write into preallocated space leaves zeros rather than a short file
+  fn corrupt_or_torn<R: Read>(reader: &mut R, message: String) -> io::Result<NextEntry> {
+      let mut next = Vec::with_capacity(std::mem::size_of::<EntryHeader>());
+      reader.take(std::mem::size_of::<EntryHeader>() as u64).read_to_end(&mut next)?;
+      if next.iter().all(|&b| b == 0) {
+          return Ok(NextEntry::Torn);
+      }
+      Err(io::Error::new(io::ErrorKind::InvalidData, message))
+  }
+  
+  /// Fill `buf`, returning false if the file ends first
+  fn read_or_eof<R: Read>(reader: &mut R, buf: &mut [u8]) -> io::Result<bool> {
+      match reader.read_exact(buf) {
+          Ok(()) => Ok(true),
+          Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => Ok(false),
+          Err(e) => Err(e),
+      }
+  }
+  
+  /// Encode an entry with its header and CRC and append it
+  fn append_entry(&mut self, cmd_type: CommandType, key: &[u8], value: &[u8], ttl_ms: u64) -> io::Result<u64> {
+      let entry_buf = Self::encode_entry(cmd_type, key, value, ttl_ms)?;
+      let total_size = entry_buf.len();
       
       // Append to file
-      self.writer.write_all(&entry_buf)?;
-      self.writer.flush()?;
+      self.write_entry(entry_buf)?;
       
       // Update position and return entry position
-      let entry_pos = self.position;
+      let entry_pos = self.logical_len();
       self.position += total_size as u64;
       
+      // A failed rollover keeps appending to the current file and retries next write
+      if self.segment_size > 0 && self.position >= self.segment_size
+          && let Err(e) = self.roll_segment()
+      {
+          eprintln!("AOF segment rollover failed: {}", e);
+      }
+      
       Ok(entry_pos)
   }
   
-  /// Append DELETE command to AOF
-  pub fn append_delete(&mut self, key: &[u8]) -> io::Result<u64> {
-      // Validate inputs
-      if key.len() > u16::MAX as usize {
+  /// Encode an entry with its header and CRC
+  fn encode_entry(cmd_type: CommandType, key: &[u8], value: &[u8], ttl_ms: u64) -> io::Result<Vec<u8>> {
+      if key.len() > MAX_KEY_SIZE {
           return Err(io::Error::new(
               io::ErrorKind::InvalidInput,
               "Key too large"
@@ -169,16 +1189,16 @@ impl AppendOnlyFile {
       
       // Create entry header (without CRC for now)
       let header_size = std::mem::size_of::<EntryHeader>();
-      let total_size = header_size + key.len();
+      let total_size = header_size + key.len() + value.len();
       
       let mut header = EntryHeader {
           crc: 0, // Will calculate after preparing full entry
           size: total_size as u32,
-          cmd_type: CommandType::Delete as u8,
+          cmd_type: cmd_type as u8,
           timestamp: Self::current_timestamp_ms(),
           key_size: key.len() as u16,
-          value_size: 0,
-          ttl_ms: 0,
+          value_size: value.len() as u32,
+          ttl_ms,
       };
       
       // Prepare full entry in memory for CRC calculation
@@ -193,8 +1213,9 @@ impl AppendOnlyFile {
       };
       entry_buf.extend_from_slice(header_bytes);
       
-      // Write key
+      // Write key and value
       entry_buf.extend_from_slice(key);
+      entry_buf.extend_from_slice(value);
       
       // Calculate CRC over the entry (excluding CRC field itself)
       let crc = calculate_crc(&entry_buf[8..]); // Skip CRC field
@@ -211,103 +1232,76 @@ impl AppendOnlyFile {
       };
       entry_buf[..header_size].copy_from_slice(header_bytes);
       
-      // Append to file
-      self.writer.write_all(&entry_buf)?;
-      self.writer.flush()?;
-      
-      // Update position and return entry position
-      let entry_pos = self.position;
-      self.position += total_size as u64;
-      
-      Ok(entry_pos)
+      Ok(entry_buf)
   }
   
-  /// Replay existing entries from file for recovery
-  pub fn replay_existing_entries(&mut self, mem_table: &MemTable) -> io::Result<()> {
-    if self.position == 0 {
-        return Ok(());
-    }
-
-    let mut reader = BufReader::new(&self.file);
-    reader.seek(SeekFrom::Start(0))?;
-    
-    let header_size = std::mem::size_of::<EntryHeader>();
-    let mut position = 0;
-
-    while position < self.position {
-        // Read and parse header
-        let mut header_buf = [0u8; std::mem::size_of::<EntryHeader>()];
-        reader.read_exact(&mut header_buf)?;
-        let header: EntryHeader = unsafe { 
-            std::ptr::read_unaligned(header_buf.as_ptr() as *const EntryHeader)
-        };
+  /// Write encoded entry according to fsync policy
+  fn write_entry(&mut self, entry_buf: Vec<u8>) -> io::Result<()> {
+      if self.is_read_only() {
+          return Err(io::Error::new(
+              io::ErrorKind::PermissionDenied,
+              "AOF is opened read-only"
+          ));
+      }
+      self.ensure_allocated(entry_buf.len() as u64)?;
+      let end = self.logical_len() + entry_buf.len() as u64;
 
-        // Validate entry
-        if header.size < header_size as u32 {
-            return Err(io::Error::new(
-                io::ErrorKind::InvalidData, 
-                "Corrupted AOF entry"
-            ));
-        }
+      match &mut self.writer {
+          AofWriter::Background(coordinator) => coordinator.submit(entry_buf, end),
+          AofWriter::Direct(writer) => {
+              writer.write_all(&entry_buf)?;
+              writer.flush()?;
+              if self.fsync_policy == FsyncPolicy::Always {
+                  let started = Instant::now();
+                  writer.get_ref().sync_data()?;
+                  LatencyMonitor::global().record(EVENT_AOF_FSYNC, started.elapsed());
+              }
+              self.synced.store(end, Ordering::Release);
+              Ok(())
+          }
+          AofWriter::ReadOnly => unreachable!("checked above"),
+      }
+  }
 
-        // Read key and value
-        let mut key = vec![0u8; header.key_size as usize];
-        reader.read_exact(&mut key)?;
-        
-        let mut value = Vec::new();
-        if header.value_size > 0 {
-            value.resize(header.value_size as usize, 0);
-            reader.read_exact(&mut value)?;
-        }
+  /// Extend preallocated space so `len` more bytes fit past position
+  fn ensure_allocated(&mut self, len: u64) -> io::Result<()> {
+      let needed = self.position + len;
+      if self.preallocate_chunk == 0 || needed <= self.allocated {
+          return Ok(());
+      }
 
-        // Verify CRC
-        let mut crc_data = Vec::with_capacity(header.size as usize);
-        crc_data.extend_from_slice(&header_buf[8..]);
-        crc_data.extend_from_slice(&key);
-        crc_data.extend_from_slice(&value);
-        
-        if calculate_crc(&crc_data) != header.crc {
-            return Err(io::Error::new(
-                io::ErrorKind::InvalidData,
-                format!("CRC mismatch at position {}", position)
-            ));
-        }
+      // Round up to the next chunk boundary
+      let target = needed.div_ceil(self.preallocate_chunk) * self.preallocate_chunk;
+      self.preallocate(target)?;
+      self.allocated = target;
+      Ok(())
+  }
 
-        // Apply to MemTable
-        match header.cmd_type {
-            x if x == CommandType::Set as u8 => {
-                let ttl = Duration::from_millis(header.ttl_ms);
-                mem_table.recover_set(
-                    &key,
-                    value,
-                    if header.ttl_ms > 0 { Some(ttl) } else { None }
-                ).map_err(|e| io::Error::new(
-                    io::ErrorKind::InvalidData,
-                    format!("Replay failed: {}", e)
-                ))?;
-                self.replay_count += 1;
-            }
-            x if x == CommandType::Delete as u8 => {
-                mem_table.recover_delete(&key)
-                    .map_err(|e| io::Error::new(
-                        io::ErrorKind::InvalidData,
-                        format!("Replay failed: {}", e)
-                    ))?;
-                self.replay_count += 1;
-            }
-            _ => return Err(io::Error::new(
-                io::ErrorKind::InvalidData,
-                "Unknown command type"
-            ))
-        }
+  /// Grow the file to `target` bytes without moving the write cursor
+  #[cfg(target_os = "linux")]
+  fn preallocate(&self, target: u64) -> io::Result<()> {
+      let offset = self.allocated as libc::off_t;
+      let len = (target - self.allocated) as libc::off_t;
+      let ret = unsafe { libc::fallocate(self.file.as_raw_fd(), 0, offset, len) };
+      if ret == 0 {
+          return Ok(());
+      }
 
-        position += header.size as u64;
-    }
+      // Filesystems without fallocate support fall back to ftruncate
+      let err = io::Error::last_os_error();
+      if err.raw_os_error() == Some(libc::EOPNOTSUPP) {
+          self.file.set_len(target)
+      } else {
+          Err(err)
+      }
+  }
 
-    // Reset file position for new writes
-    self.file.seek(SeekFrom::End(0))?;
-    Ok(())
+  /// Preallocation is a no-op without fallocate
+  #[cfg(not(target_os = "linux"))]
+  fn preallocate(&self, _target: u64) -> io::Result<()> {
+      Ok(())
   }
+
   /// Resolve AOF file path
   fn resolve_aof_path<P: AsRef<Path>>(path: P) -> io::Result<PathBuf> {
       let path_ref = path.as_ref();
@@ -330,3 +1324,657 @@ impl AppendOnlyFile {
           .as_millis() as u64
   }
 }
+
+impl EntryIter {
+  /// Logical offset just past the last entry yielded
+  pub fn position(&self) -> u64 {
+      self.base + self.position
+  }
+  
+  /// Move on to the next segment file, if there is one
+  fn next_file(&mut self) -> io::Result<bool> {
+      let Some((path, base)) = self.rest.pop_front() else {
+          return Ok(false);
+      };
+      let file = File::open(path)?;
+      self.len = file.metadata()?.len();
+      self.reader = BufReader::new(file);
+      self.position = 0;
+      self.base = base;
+      Ok(true)
+  }
+  
+  /// Whether iteration ended at an incomplete final entry
+  pub fn torn_tail(&self) -> bool {
+      self.torn
+  }
+}
+
+impl Iterator for EntryIter {
+  type Item = io::Result<AofEntry>;
+  
+  fn next(&mut self) -> Option<Self::Item> {
+      while !self.done && self.position >= self.len {
+          match self.next_file() {
+              Ok(true) => {}
+              Ok(false) => return None,
+              Err(e) => {
+                  self.done = true;
+                  return Some(Err(e));
+              }
+          }
+      }
+      if self.done {
+          return None;
+      }
+      
+      match AppendOnlyFile::read_entry(&mut self.reader, self.position, self.len) {
+          Ok(NextEntry::Entry(entry)) => {
+              let offset = self.base + self.position;
+              self.position += entry.header.size as u64;
+              Some(Ok(AofEntry {
+                  offset,
+                  cmd_type: entry.header.cmd_type,
+                  key: entry.key,
+                  value: entry.value,
+                  ttl_ms: entry.header.ttl_ms,
+                  timestamp_ms: entry.header.timestamp,
+              }))
+          }
+          Ok(NextEntry::Preallocated) => {
+              // Only the last file can have preallocated space - skip to the next one
+              if self.rest.is_empty() {
+                  self.done = true;
+                  return None;
+              }
+              self.position = self.len;
+              self.next()
+          }
+          Ok(NextEntry::Torn) => {
+              self.done = true;
+              
+              // Cut short with a valid entry after it - a corrupt length, not a crash
+              match AppendOnlyFile::resync(self.reader.get_ref(), self.position + 1, self.len) {
+                  Ok(None) => {
+                      self.torn = true;
+                      None
+                  }
+                  Ok(Some(next)) => Some(Err(io::Error::new(
+                      io::ErrorKind::InvalidData,
+                      format!("AOF entry at position {} runs past a valid entry at {}", self.base + self.position, self.base + next)
+                  ))),
+                  Err(e) => Some(Err(e)),
+              }
+          }
+          Err(e) => {
+              self.done = true;
+              Some(Err(e))
+          }
+      }
+  }
+}
+
+impl Drop for AppendOnlyFile {
+  fn drop(&mut self) {
+      // Drain pending writes before trimming preallocated space
+      match &mut self.writer {
+          AofWriter::Background(coordinator) => coordinator.shutdown(),
+          AofWriter::Direct(writer) => {
+              let _ = writer.flush();
+          }
+          AofWriter::ReadOnly => {}
+      }
+
+      if let Err(e) = self.truncate_to_logical() {
+          eprintln!("Failed to truncate AOF preallocation: {}", e);
+      }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use tempfile::tempdir;
+  use crate::storage::value::ZAddFlags;
+
+  #[test]
+  fn test_preallocation_round_trip() {
+      let dir = tempdir().unwrap();
+      let path = dir.path().join("prealloc.aof");
+
+      let logical_len = {
+          let mut aof = AppendOnlyFile::with_fsync_policy(&path, FsyncPolicy::No).unwrap();
+          aof.set_preallocation(64 * 1024);
+          aof.append_set(b"a", b"1", None).unwrap();
+          aof.append_set(b"b", b"2", None).unwrap();
+          aof.append_delete(b"a").unwrap();
+
+          if cfg!(target_os = "linux") {
+              assert_eq!(aof.allocated_len(), 64 * 1024);
+              assert_eq!(std::fs::metadata(&path).unwrap().len(), 64 * 1024);
+          }
+          aof.logical_len()
+      };
+
+      // Clean shutdown trims the file back to its logical length
+      assert_eq!(std::fs::metadata(&path).unwrap().len(), logical_len);
+
+      let mem = MemTable::new();
+      let mut aof = AppendOnlyFile::with_fsync_policy(&path, FsyncPolicy::No).unwrap();
+      aof.replay_existing_entries(&mem).unwrap();
+      assert_eq!(aof.replay_count(), 3);
+      assert_eq!(mem.get(b"a"), None);
+      assert_eq!(mem.get(b"b").as_deref(), Some(b"2".as_slice()));
+  }
+
+  #[test]
+  fn test_replay_stops_at_preallocated_tail() {
+      let dir = tempdir().unwrap();
+      let path = dir.path().join("tail.aof");
+
+      {
+          let mut aof = AppendOnlyFile::with_fsync_policy(&path, FsyncPolicy::No).unwrap();
+          aof.append_set(b"k", b"v", None).unwrap();
+      }
+
+      // Simulate a crash that left zeroed preallocated space behind
+      let logical_len = std::fs::metadata(&path).unwrap().len();
+      OpenOptions::new().write(true).open(&path).unwrap().set_len(logical_len + 4096).unwrap();
+
+      let mem = MemTable::new();
+      let mut aof = AppendOnlyFile::with_fsync_policy(&path, FsyncPolicy::No).unwrap();
+      aof.replay_existing_entries(&mem).unwrap();
+      assert_eq!(aof.logical_len(), logical_len);
+      assert_eq!(mem.get(b"k").as_deref(), Some(b"v".as_slice()));
+
+      // A write torn partway into that space leaves zeros where its end should be
+      aof.append_set(b"torn", b"value that never fully landed", None).unwrap();
+      aof.sync().unwrap();
+      drop(aof);
+      let file = OpenOptions::new().write(true).open(&path).unwrap();
+      std::os::unix::fs::FileExt::write_all_at(&file, &[0u8; 16], logical_len + 40).unwrap();
+      file.set_len(logical_len + 4096).unwrap();
+
+      let mem = MemTable::new();
+      let mut aof = AppendOnlyFile::with_fsync_policy(&path, FsyncPolicy::No).unwrap();
+      let stats = aof.replay_with_progress(&mem, |_, _, _| {}).unwrap();
+      assert_eq!((stats.applied, stats.truncated_entries), (1, 1));
+      assert_eq!(aof.logical_len(), logical_len);
+      assert_eq!(mem.get(b"torn"), None);
+  }
+  
+  #[test]
+  fn test_replay_drops_torn_tail() {
+      let dir = tempdir().unwrap();
+      let path = dir.path().join("torn.aof");
+      
+      let good_len = {
+          let mut aof = AppendOnlyFile::with_fsync_policy(&path, FsyncPolicy::No).unwrap();
+          aof.append_set(b"a", b"1", None).unwrap();
+          aof.append_set(b"b", b"2", None).unwrap();
+          let good_len = aof.logical_len();
+          aof.append_set(b"c", b"torn value", None).unwrap();
+          good_len
+      };
+      
+      // Simulate a crash partway through writing the last entry
+      OpenOptions::new().write(true).open(&path).unwrap().set_len(good_len + 20).unwrap();
+      
+      let mem = MemTable::new();
+      let mut calls = Vec::new();
+      let mut aof = AppendOnlyFile::with_fsync_policy(&path, FsyncPolicy::No).unwrap();
+      let stats = aof
+          .replay_with_progress(&mem, |replayed, done, total| calls.push((replayed, done, total)))
+          .unwrap();
+      assert_eq!(stats, ReplayStats {
+          applied: 2,
+          skipped: 0,
+          bytes_read: good_len,
+          truncated_entries: 1,
+          truncated_bytes: 20,
+          corrupt_entries: 0,
+          corrupt_bytes: 0,
+      });
+      assert_eq!(calls, vec![(2, good_len, good_len + 20)]);
+      assert_eq!(mem.get(b"c"), None);
+      
+      // The torn bytes are cut off so new writes follow the last good entry
+      assert_eq!(std::fs::metadata(&path).unwrap().len(), good_len);
+      aof.append_set(b"d", b"4", None).unwrap();
+      drop(aof);
+      let mem = MemTable::new();
+      let mut aof = AppendOnlyFile::with_fsync_policy(&path, FsyncPolicy::No).unwrap();
+      let stats = aof.replay_existing_entries(&mem).unwrap();
+      assert_eq!((stats.applied, stats.truncated_entries), (3, 0));
+      assert_eq!(mem.get(b"d").as_deref(), Some(b"4".as_slice()));
+  }
+  
+  #[test]
+  fn test_check_reports_corruption() {
+      let dir = tempdir().unwrap();
+      let path = dir.path().join("check.aof");
+      let (first_len, good_len) = {
+          let mut aof = AppendOnlyFile::with_fsync_policy(&path, FsyncPolicy::No).unwrap();
+          aof.append_set(b"a", b"1", None).unwrap();
+          let first_len = aof.logical_len();
+          aof.append_set(b"b", b"2", None).unwrap();
+          (first_len, aof.logical_len())
+      };
+      
+      let check = AppendOnlyFile::check(&path).unwrap();
+      assert_eq!(check.valid_entries, 2);
+      assert_eq!(check.corruption_offset, None);
+      
+      // A partial trailing entry can be truncated away
+      OpenOptions::new().write(true).open(&path).unwrap().set_len(good_len + 10).unwrap();
+      let check = AppendOnlyFile::check(&path).unwrap();
+      assert_eq!(check.corruption_offset, Some(good_len));
+      assert!(check.tail_truncatable);
+      
+      // A flipped byte in the middle cannot - an entry follows, not zeroed space
+      let mut bytes = std::fs::read(&path).unwrap();
+      let first = bytes[..first_len as usize].to_vec();
+      bytes.truncate(good_len as usize);
+      bytes.extend_from_slice(&first);
+      bytes[first_len as usize + 36] ^= 0xff;
+      std::fs::write(&path, bytes).unwrap();
+      let check = AppendOnlyFile::check(&path).unwrap();
+      assert_eq!(check.valid_entries, 1);
+      assert_eq!(check.corruption_offset, Some(first_len));
+      assert!(!check.tail_truncatable);
+  }
+  
+  #[test]
+  fn test_recovery_modes() {
+      let dir = tempdir().unwrap();
+      let path = dir.path().join("recovery.aof");
+      let (first_len, second_len) = {
+          let mut aof = AppendOnlyFile::with_fsync_policy(&path, FsyncPolicy::No).unwrap();
+          aof.append_set(b"a", b"1", None).unwrap();
+          let first_len = aof.logical_len();
+          aof.append_set(b"b", b"2", None).unwrap();
+          let second_len = aof.logical_len();
+          aof.append_set(b"c", b"3", None).unwrap();
+          aof.append_set(b"d", b"4", None).unwrap();
+          (first_len, second_len)
+      };
+      
+      // Flip the value byte of "b" and of the last entry
+      let mut bytes = std::fs::read(&path).unwrap();
+      bytes[first_len as usize + 36] ^= 0xff;
+      *bytes.last_mut().unwrap() ^= 0xff;
+      std::fs::write(&path, &bytes).unwrap();
+      
+      let mut aof = AppendOnlyFile::with_fsync_policy(&path, FsyncPolicy::No).unwrap();
+      let err = aof.replay_existing_entries(&MemTable::new()).unwrap_err();
+      assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+      drop(aof);
+      assert_eq!(std::fs::read(&path).unwrap(), bytes);
+      
+      // Lenient skips "b", resumes at "c" and cuts "d" off
+      let mem = MemTable::new();
+      let mut aof = AppendOnlyFile::with_fsync_policy(&path, FsyncPolicy::No).unwrap();
+      aof.set_recovery_mode(RecoveryMode::Lenient);
+      let stats = aof.replay_existing_entries(&mem).unwrap();
+      let entry_len = second_len - first_len;
+      assert_eq!((stats.applied, stats.corrupt_entries, stats.corrupt_bytes), (2, 1, entry_len));
+      assert_eq!((stats.truncated_entries, stats.truncated_bytes), (1, entry_len));
+      assert_eq!(mem.get(b"b"), None);
+      assert_eq!(mem.get(b"c").as_deref(), Some(b"3".as_slice()));
+      assert_eq!(mem.get(b"d"), None);
+      assert_eq!(std::fs::metadata(&path).unwrap().len(), second_len + entry_len);
+  }
+  
+  #[test]
+  fn test_corrupt_length_mid_file() {
+      let dir = tempdir().unwrap();
+      let path = dir.path().join("length.aof");
+      let (first_len, second_len) = {
+          let mut aof = AppendOnlyFile::with_fsync_policy(&path, FsyncPolicy::No).unwrap();
+          aof.append_set(b"a", b"1", None).unwrap();
+          let first_len = aof.logical_len();
+          aof.append_set(b"b", b"2", None).unwrap();
+          let second_len = aof.logical_len();
+          aof.append_set(b"c", b"3", None).unwrap();
+          (first_len, second_len)
+      };
+      
+      // "b" claims a 1 GiB value, consistently in both length fields - it looks cut short
+      let header_size = std::mem::size_of::<EntryHeader>();
+      let value_size = 1u32 << 30;
+      let mut bytes = std::fs::read(&path).unwrap();
+      let b = first_len as usize;
+      bytes[b + 8..b + 12].copy_from_slice(&(header_size as u32 + 1 + value_size).to_le_bytes());
+      bytes[b + 23..b + 27].copy_from_slice(&value_size.to_le_bytes());
+      std::fs::write(&path, &bytes).unwrap();
+      
+      // "c" follows, so it isn't a torn tail: strict refuses it, check won't truncate it
+      let mut aof = AppendOnlyFile::with_fsync_policy(&path, FsyncPolicy::No).unwrap();
+      let err = aof.replay_existing_entries(&MemTable::new()).unwrap_err();
+      assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+      drop(aof);
+      let check = AppendOnlyFile::check(&path).unwrap();
+      assert_eq!((check.valid_entries, check.corruption_offset), (1, Some(first_len)));
+      assert!(!check.tail_truncatable);
+      
+      let mem = MemTable::new();
+      let mut aof = AppendOnlyFile::with_fsync_policy(&path, FsyncPolicy::No).unwrap();
+      aof.set_recovery_mode(RecoveryMode::Lenient);
+      let stats = aof.replay_existing_entries(&mem).unwrap();
+      assert_eq!((stats.applied, stats.corrupt_entries, stats.corrupt_bytes), (2, 1, second_len - first_len));
+      assert_eq!(stats.truncated_entries, 0);
+      assert_eq!(mem.get(b"c").as_deref(), Some(b"3".as_slice()));
+  }
+  
+  #[test]
+  fn test_reconstruct_as_of() {
+      let dir = tempdir().unwrap();
+      let path = dir.path().join("asof.aof");
+      let (before, after) = {
+          let mut aof = AppendOnlyFile::with_fsync_policy(&path, FsyncPolicy::No).unwrap();
+          aof.append_set(b"k", b"old", None).unwrap();
+          aof.append_set(b"short", b"v", Some(Duration::from_millis(1))).unwrap();
+          aof.append_set(b"long", b"v", Some(Duration::from_secs(60))).unwrap();
+          std::thread::sleep(Duration::from_millis(20));
+          let before = AppendOnlyFile::current_timestamp_ms();
+          std::thread::sleep(Duration::from_millis(20));
+          aof.append_set(b"k", b"new", None).unwrap();
+          aof.append_delete(b"long").unwrap();
+          (before, AppendOnlyFile::current_timestamp_ms())
+      };
+      
+      // Earlier view: the first write only, with the short TTL already run out
+      let view = AppendOnlyFile::reconstruct_as_of(&path, before).unwrap();
+      assert_eq!(view.get(b"k").as_deref(), Some(&b"old"[..]));
+      assert_eq!(view.get(b"short"), None);
+      let ttl = view.ttl(b"long").unwrap();
+      assert!(ttl > Duration::from_secs(59) && ttl <= Duration::from_secs(60));
+      
+      let view = AppendOnlyFile::reconstruct_as_of(&path, after).unwrap();
+      assert_eq!(view.get(b"k").as_deref(), Some(&b"new"[..]));
+      assert_eq!(view.get(b"long"), None);
+  }
+  
+  #[test]
+  fn test_rewrite_keeps_tail() {
+      let dir = tempdir().unwrap();
+      let path = dir.path().join("rewrite.aof");
+      let mut aof = AppendOnlyFile::new(&path).unwrap();
+      for i in 0..100 {
+          aof.append_set(b"counter", i.to_string().as_bytes(), None).unwrap();
+      }
+      aof.append_value(b"set", &ValueKind::Set(["a".into()].into_iter().collect()), None).unwrap();
+      let from = aof.logical_len();
+      
+      // Compacted from the state at `from`; later writes arrive before the swap
+      let compacted = dir.path().join("rewrite.aof.tmp");
+      let snapshot = vec![
+          (b"counter".to_vec(), ValueKind::String(b"99"[..].into()), None),
+          (b"set".to_vec(), ValueKind::Set(["a".into()].into_iter().collect()), None),
+      ];
+      AppendOnlyFile::write_compacted(&compacted, snapshot).unwrap();
+      aof.append_set(b"late", b"v", Some(Duration::from_secs(60))).unwrap();
+      let end = aof.logical_len();
+      aof.install_rewrite(&compacted, from).unwrap();
+      aof.append_delete(b"counter").unwrap();
+      
+      // Smaller on disk, but offsets carry on from where the old file ended
+      assert!(aof.size() < from / 10);
+      assert!(aof.logical_len() > end);
+      assert_eq!(aof.segments().unwrap()[0].base, end);
+      assert!(!path.exists());
+      drop(aof);
+      
+      let mem = MemTable::new();
+      AppendOnlyFile::new(&path).unwrap().replay_existing_entries(&mem).unwrap();
+      assert_eq!(mem.get(b"counter"), None);
+      assert_eq!(mem.get(b"late").as_deref(), Some(&b"v"[..]));
+      assert!(mem.ttl(b"late").is_some());
+      assert_eq!(mem.value_type(b"set"), Some("set"));
+  }
+  
+  #[test]
+  fn test_synced_offset() {
+      let dir = tempdir().unwrap();
+      
+      // Background fsync publishes the offset once the entry is on disk
+      let mut aof = AppendOnlyFile::new(dir.path().join("background.aof")).unwrap();
+      let synced = aof.synced_offset();
+      aof.append_set(b"k", b"v", None).unwrap();
+      aof.sync().unwrap();
+      assert_eq!(synced.load(Ordering::Acquire), aof.logical_len());
+      
+      // Always fsyncs inline
+      let mut aof = AppendOnlyFile::with_fsync_policy(dir.path().join("always.aof"), FsyncPolicy::Always).unwrap();
+      aof.append_set(b"k", b"v", None).unwrap();
+      assert!(aof.logical_len() > 0);
+      assert_eq!(aof.synced_offset().load(Ordering::Acquire), aof.logical_len());
+  }
+  
+  #[test]
+  fn test_segment_rollover_and_rewrite() {
+      let dir = tempdir().unwrap();
+      let path = dir.path().join("segmented.aof");
+      let mut aof = AppendOnlyFile::new(&path).unwrap();
+      aof.set_segment_size(256);
+      for i in 0..50 {
+          aof.append_set(format!("key{}", i).as_bytes(), b"value", None).unwrap();
+      }
+      let from = aof.logical_len();
+      let segments = aof.segments().unwrap().to_vec();
+      assert!(segments.len() > 2 && segments[0] == Segment { number: 1, base: 0 });
+      assert!(!path.exists());
+      drop(aof);
+      
+      // Reopening replays every segment in order and offsets stay logical
+      let mem = MemTable::new();
+      let mut aof = AppendOnlyFile::new(&path).unwrap();
+      aof.replay_existing_entries(&mem).unwrap();
+      assert_eq!(aof.logical_len(), from);
+      assert_eq!(mem.get(b"key0").as_deref(), Some(&b"value"[..]));
+      assert_eq!(mem.get(b"key49").as_deref(), Some(&b"value"[..]));
+      let offsets: Vec<u64> = AppendOnlyFile::iter_entries(&path).unwrap().map(|entry| entry.unwrap().offset).collect();
+      assert_eq!(offsets.len(), 50);
+      assert!(offsets.windows(2).all(|pair| pair[0] < pair[1]) && offsets[49] < from);
+      
+      // A rewrite becomes the new base segment and the old ones go away
+      let compacted = dir.path().join("segmented.aof.rewrite");
+      AppendOnlyFile::write_compacted(&compacted, vec![(b"key0".to_vec(), ValueKind::String(b"value"[..].into()), None)]).unwrap();
+      aof.append_set(b"late", b"v", None).unwrap();
+      let end = aof.logical_len();
+      aof.install_rewrite(&compacted, from).unwrap();
+      let base = aof.segments().unwrap()[0];
+      assert_eq!(aof.segments().unwrap().len(), 1);
+      assert_eq!(base.base, end);
+      assert_eq!(aof.logical_len(), end + aof.size());
+      drop(aof);
+      assert!(segments.iter().all(|segment| !manifest::segment_path(&path, segment.number).exists()));
+      
+      let mem = MemTable::new();
+      AppendOnlyFile::new(&path).unwrap().replay_existing_entries(&mem).unwrap();
+      assert_eq!(mem.get(b"late").as_deref(), Some(&b"v"[..]));
+      assert_eq!(mem.get(b"key1"), None);
+  }
+  
+  #[test]
+  fn test_open_readonly() {
+      let dir = tempdir().unwrap();
+      let path = dir.path().join("readonly.aof");
+      {
+          let mut aof = AppendOnlyFile::with_fsync_policy(&path, FsyncPolicy::No).unwrap();
+          aof.append_set(b"k", b"v", None).unwrap();
+      }
+      let mut perms = std::fs::metadata(&path).unwrap().permissions();
+      perms.set_readonly(true);
+      std::fs::set_permissions(&path, perms).unwrap();
+      
+      let mem = MemTable::new();
+      let mut aof = AppendOnlyFile::open_readonly(&path).unwrap();
+      assert_eq!(aof.replay_existing_entries(&mem).unwrap().applied, 1);
+      assert_eq!(mem.get(b"k").as_deref(), Some(b"v".as_slice()));
+      
+      let err = aof.append_set(b"k", b"w", None).unwrap_err();
+      assert_eq!(err.kind(), io::ErrorKind::PermissionDenied);
+      assert_eq!(aof.logical_len(), std::fs::metadata(&path).unwrap().len());
+  }
+  
+  #[test]
+  fn test_replay_since_follows_growth() {
+      let dir = tempdir().unwrap();
+      let path = dir.path().join("follow.aof");
+      let mut primary = AppendOnlyFile::with_fsync_policy(&path, FsyncPolicy::No).unwrap();
+      primary.append_set(b"a", b"1", None).unwrap();
+      
+      let mem = MemTable::new();
+      let mut standby = AppendOnlyFile::open_readonly(&path).unwrap();
+      let position = standby.replay_since(0, &mem).unwrap();
+      assert_eq!(position, primary.logical_len());
+      assert_eq!(mem.get(b"a").as_deref(), Some(b"1".as_slice()));
+      
+      // A half-written entry is left for the next call
+      primary.append_set(b"b", b"2", None).unwrap();
+      let full_len = primary.logical_len();
+      let mut partial = std::fs::read(&path).unwrap();
+      partial.truncate(full_len as usize - 1);
+      let copy = dir.path().join("partial.aof");
+      std::fs::write(&copy, &partial).unwrap();
+      let mut lagging = AppendOnlyFile::open_readonly(&copy).unwrap();
+      assert_eq!(lagging.replay_since(position, &mem).unwrap(), position);
+      
+      // Only the new entry is applied
+      mem.delete(b"a").unwrap();
+      assert_eq!(standby.replay_since(position, &mem).unwrap(), full_len);
+      assert_eq!(mem.get(b"a"), None);
+      assert_eq!(mem.get(b"b").as_deref(), Some(b"2".as_slice()));
+  }
+  
+  #[test]
+  fn test_mark_valid_until_rewrite() {
+      let dir = tempdir().unwrap();
+      let path = dir.path().join("mark.aof");
+      let mut aof = AppendOnlyFile::with_fsync_policy(&path, FsyncPolicy::No).unwrap();
+      aof.append_set(b"a", b"1", None).unwrap();
+      let mark = aof.mark().unwrap();
+      aof.append_set(b"b", b"2", None).unwrap();
+      assert!(aof.matches_mark(&mark));
+      assert!(!aof.matches_mark(&AofMark { offset: aof.logical_len() + 1 }));
+      
+      // A rewrite compacts the entries after the mark into a base segment past it
+      let compacted = dir.path().join("mark.aof.rewrite");
+      AppendOnlyFile::write_compacted(&compacted, vec![(b"a".to_vec(), ValueKind::String(b"1"[..].into()), None)]).unwrap();
+      aof.install_rewrite(&compacted, aof.logical_len()).unwrap();
+      assert!(!aof.matches_mark(&mark));
+      
+      // Marks taken after the rewrite still line up after a reopen
+      let mark = aof.mark().unwrap();
+      drop(aof);
+      assert!(AppendOnlyFile::new(&path).unwrap().matches_mark(&mark));
+  }
+  
+  #[test]
+  fn test_replay_since_follows_segments() {
+      let dir = tempdir().unwrap();
+      let path = dir.path().join("follow.aof");
+      let mut primary = AppendOnlyFile::with_fsync_policy(&path, FsyncPolicy::No).unwrap();
+      primary.set_segment_size(256);
+      primary.append_set(b"first", b"v", None).unwrap();
+      
+      let mem = MemTable::new();
+      let mut standby = AppendOnlyFile::open_readonly(&path).unwrap();
+      assert_eq!(standby.synced_offset().load(Ordering::Acquire), primary.logical_len());
+      let offset = standby.replay_since(0, &mem).unwrap();
+      
+      // The single file and then several segments are rolled over while the standby waits
+      for i in 0..20 {
+          primary.append_set(format!("key{}", i).as_bytes(), b"value", None).unwrap();
+      }
+      assert!(primary.segments().unwrap().len() > 2);
+      let offset = standby.replay_since(offset, &mem).unwrap();
+      assert_eq!(offset, primary.logical_len());
+      assert_eq!(mem.len(), 21);
+      assert_eq!(standby.segments().unwrap().last(), primary.segments().unwrap().last());
+      
+      // A rewrite is picked up as a full image, dropping what it no longer holds
+      let compacted = dir.path().join("follow.aof.rewrite");
+      AppendOnlyFile::write_compacted(&compacted, vec![(b"kept".to_vec(), ValueKind::String(b"v"[..].into()), None)]).unwrap();
+      primary.install_rewrite(&compacted, primary.logical_len()).unwrap();
+      primary.append_set(b"after", b"v", None).unwrap();
+      assert_eq!(standby.replay_since(offset, &mem).unwrap(), primary.logical_len());
+      assert_eq!(mem.len(), 2);
+      assert_eq!(mem.get(b"after").as_deref(), Some(b"v".as_slice()));
+  }
+  
+  #[test]
+  fn test_replay_respects_tombstones() {
+      let dir = tempdir().unwrap();
+      let path = dir.path().join("tombstone.aof");
+      {
+          let mut aof = AppendOnlyFile::with_fsync_policy(&path, FsyncPolicy::No).unwrap();
+          aof.append_set(b"deleted", b"stale", None).unwrap();
+          aof.append_set(b"kept", b"v", None).unwrap();
+      }
+      
+      // A DEL that raced ahead of the replay is not undone by it
+      let mem = MemTable::new().with_tombstone_ttl(Some(Duration::from_secs(60)));
+      mem.delete(b"deleted").unwrap();
+      let mut aof = AppendOnlyFile::with_fsync_policy(&path, FsyncPolicy::No).unwrap();
+      aof.replay_existing_entries(&mem).unwrap();
+      assert_eq!(mem.get(b"deleted"), None);
+      assert_eq!(mem.get(b"kept").as_deref(), Some(b"v".as_slice()));
+      
+      // Without tombstones the replay wins
+      let mem = MemTable::new();
+      mem.delete(b"deleted").unwrap();
+      aof.replay_existing_entries(&mem).unwrap();
+      assert!(mem.get(b"deleted").is_some());
+  }
+  
+  #[test]
+  fn test_replay_expire_and_flush() {
+      let dir = tempdir().unwrap();
+      let path = dir.path().join("flush.aof");
+      
+      {
+          let mut aof = AppendOnlyFile::with_fsync_policy(&path, FsyncPolicy::No).unwrap();
+          aof.append_set(b"gone", b"1", None).unwrap();
+          aof.append_flush().unwrap();
+          aof.append_set(b"kept", b"2", None).unwrap();
+          aof.append_expire(b"kept", Some(Duration::from_secs(60))).unwrap();
+      }
+      
+      // Entries before the flush marker are discarded, the TTL survives
+      let mem = MemTable::new();
+      let mut aof = AppendOnlyFile::with_fsync_policy(&path, FsyncPolicy::No).unwrap();
+      aof.replay_existing_entries(&mem).unwrap();
+      assert_eq!(mem.get(b"gone"), None);
+      assert_eq!(mem.get(b"kept").as_deref(), Some(b"2".as_slice()));
+      assert!(mem.ttl(b"kept").is_some());
+  }
+  
+  #[test]
+  fn test_replay_typed_values() {
+      let dir = tempdir().unwrap();
+      let path = dir.path().join("typed.aof");
+      let members: std::collections::HashSet<Vec<u8>> = [b"x".to_vec(), b"y".to_vec()].into_iter().collect();
+      
+      {
+          let mut aof = AppendOnlyFile::with_fsync_policy(&path, FsyncPolicy::No).unwrap();
+          aof.append_value(b"stored", &ValueKind::Set(members.clone()), None).unwrap();
+          aof.append_mutation(b"added", &Mutation::SAdd(vec![b"a".to_vec(), b"b".to_vec()])).unwrap();
+          aof.append_mutation(b"added", &Mutation::SRem(vec![b"a".to_vec()])).unwrap();
+          
+          // Sorted set updates replay through the same ZADD flags
+          let incr = ZAddFlags { incr: true, ..ZAddFlags::default() };
+          aof.append_mutation(b"ranked", &Mutation::ZAdd(vec![(1.0, b"m".to_vec()), (2.0, b"n".to_vec())], ZAddFlags::default())).unwrap();
+          aof.append_mutation(b"ranked", &Mutation::ZAdd(vec![(2.5, b"m".to_vec())], incr)).unwrap();
+          aof.append_mutation(b"ranked", &Mutation::ZRem(vec![b"n".to_vec()])).unwrap();
+      }
+      
+      let mem = MemTable::new();
+      let mut aof = AppendOnlyFile::with_fsync_policy(&path, FsyncPolicy::No).unwrap();
+      aof.replay_existing_entries(&mem).unwrap();
+      assert_eq!(mem.read_value(b"stored", |v| v.clone()), Some(ValueKind::Set(members)));
+      assert_eq!(mem.read_value(b"added", |v| v.as_set().unwrap().len()), Some(1));
+      assert_eq!(mem.read_value(b"ranked", |v| v.as_sorted_set().unwrap().range(0, -1, false)), Some(vec![(b"m".to_vec(), 3.5)]));
+  }
+}
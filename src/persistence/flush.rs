@@ -0,0 +1,263 @@
+// Background AOF flush coordinator - decouples fsync from the write path
+
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::util::latency::{LatencyMonitor, EVENT_AOF_FSYNC};
+
+/// Interval between background fsyncs
+const SYNC_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Pending entry count that forces an early fsync
+const SYNC_THRESHOLD: u64 = 1024;
+
+/// Messages accepted by the flush thread
+enum FlushMessage {
+    // Encoded AOF entry to append, and the AOF offset just past it
+    Entry(Vec<u8>, u64),
+
+    // Request an immediate fsync, replying once it completes
+    Sync(Sender<io::Result<()>>),
+}
+
+/// FlushCoordinator - owns the AOF writer on a dedicated thread
+/// Entries are written as soon as they arrive, fsync runs once per second
+pub struct FlushCoordinator {
+    // Channel into the flush thread (None once shut down)
+    sender: Option<Sender<FlushMessage>>,
+
+    // Flush thread handle
+    handle: Option<thread::JoinHandle<()>>,
+
+    // Entries written to the OS but not yet fsynced
+    pending: Arc<AtomicU64>,
+
+    // Set when the flush thread hits a write or sync error (shared with the AOF)
+    failed: Arc<AtomicBool>,
+    
+    // AOF offset up to which entries are fsynced (shared with the AOF)
+    synced: Arc<AtomicU64>,
+}
+
+impl FlushCoordinator {
+    /// Spawn flush thread taking ownership of the writer
+    /// `synced` starts at the offset already durable and advances with each fsync;
+    /// `failed` is set on the first write or fsync error
+    pub fn start(writer: BufWriter<File>, synced: Arc<AtomicU64>, failed: Arc<AtomicBool>) -> Self {
+        let (sender, receiver) = mpsc::channel::<FlushMessage>();
+        let pending = Arc::new(AtomicU64::new(0));
+
+        let thread_pending = pending.clone();
+        let thread_failed = failed.clone();
+        let thread_synced = synced.clone();
+
+        let handle = thread::spawn(move || {
+            let mut writer = writer;
+            let mut last_sync = Instant::now();
+            // End offset of the last entry written (None until one is)
+            let mut written = None;
+            let sync = |writer: &BufWriter<File>, written: Option<u64>| {
+                if Self::sync(writer, &thread_pending, &thread_failed) && let Some(end) = written {
+                    thread_synced.store(end, Ordering::Release);
+                }
+            };
+
+            loop {
+                // Wake up at the next sync deadline even if no entries arrive
+                let timeout = SYNC_INTERVAL.saturating_sub(last_sync.elapsed());
+
+                match receiver.recv_timeout(timeout) {
+                    Ok(FlushMessage::Entry(entry, end)) => {
+                        let result = writer.write_all(&entry).and_then(|_| writer.flush());
+                        if let Err(e) = result {
+                            eprintln!("AOF background write failed: {}", e);
+                            thread_failed.store(true, Ordering::Relaxed);
+                            continue;
+                        }
+                        written = Some(end);
+
+                        // Early sync if too many entries are at risk
+                        if thread_pending.fetch_add(1, Ordering::Relaxed) + 1 >= SYNC_THRESHOLD {
+                            sync(&writer, written);
+                            last_sync = Instant::now();
+                        }
+                    }
+                    Ok(FlushMessage::Sync(reply)) => {
+                        let started = Instant::now();
+                        let result = writer.flush().and_then(|_| writer.get_ref().sync_data());
+                        LatencyMonitor::global().record(EVENT_AOF_FSYNC, started.elapsed());
+                        if let Err(e) = &result {
+                            eprintln!("AOF fsync failed: {}", e);
+                            thread_failed.store(true, Ordering::Relaxed);
+                        } else {
+                            thread_pending.store(0, Ordering::Relaxed);
+                            if let Some(end) = written {
+                                thread_synced.store(end, Ordering::Release);
+                            }
+                        }
+                        last_sync = Instant::now();
+                        let _ = reply.send(result);
+                    }
+                    Err(RecvTimeoutError::Timeout) => {
+                        if thread_pending.load(Ordering::Relaxed) > 0 {
+                            sync(&writer, written);
+                        }
+                        last_sync = Instant::now();
+                    }
+                    Err(RecvTimeoutError::Disconnected) => {
+                        // All senders dropped and queue drained - final fsync
+                        let _ = writer.flush();
+                        sync(&writer, written);
+                        break;
+                    }
+                }
+            }
+        });
+
+        Self {
+            sender: Some(sender),
+            handle: Some(handle),
+            pending,
+            failed,
+            synced,
+        }
+    }
+
+    /// Queue an encoded entry for writing - `end` is the AOF offset just past it
+    /// Refused once the flush thread has failed, since earlier entries may be lost
+    pub fn submit(&self, entry: Vec<u8>, end: u64) -> io::Result<()> {
+        self.check_failed()?;
+        match &self.sender {
+            Some(sender) => sender.send(FlushMessage::Entry(entry, end)).map_err(|_| {
+                io::Error::new(io::ErrorKind::BrokenPipe, "AOF flush thread stopped")
+            }),
+            None => Err(io::Error::new(
+                io::ErrorKind::BrokenPipe,
+                "AOF flush thread stopped"
+            )),
+        }
+    }
+
+    /// Block until all previously submitted entries are fsynced
+    pub fn sync_now(&self) -> io::Result<()> {
+        self.check_failed()?;
+        let sender = self.sender.as_ref().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::BrokenPipe, "AOF flush thread stopped")
+        })?;
+
+        let (reply_tx, reply_rx) = mpsc::channel();
+        sender.send(FlushMessage::Sync(reply_tx)).map_err(|_| {
+            io::Error::new(io::ErrorKind::BrokenPipe, "AOF flush thread stopped")
+        })?;
+
+        reply_rx.recv().map_err(|_| {
+            io::Error::new(io::ErrorKind::BrokenPipe, "AOF flush thread stopped")
+        })?
+    }
+
+    /// Number of entries written but not yet fsynced
+    pub fn pending(&self) -> u64 {
+        self.pending.load(Ordering::Relaxed)
+    }
+
+    /// AOF offset up to which entries are fsynced
+    pub fn synced_offset(&self) -> u64 {
+        self.synced.load(Ordering::Acquire)
+    }
+    
+    /// Whether the flush thread has hit an I/O error
+    pub fn has_failed(&self) -> bool {
+        self.failed.load(Ordering::Relaxed)
+    }
+    
+    /// Error out if the flush thread has hit an I/O error
+    fn check_failed(&self) -> io::Result<()> {
+        if self.has_failed() {
+            return Err(io::Error::other("AOF background write or fsync failed earlier"));
+        }
+        Ok(())
+    }
+
+    /// Drain remaining entries, fsync, and stop the flush thread
+    pub fn shutdown(&mut self) {
+        // Dropping the sender lets the thread drain the queue and exit
+        self.sender = None;
+
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+
+    /// Fsync file data and reset pending counter - returns whether the fsync succeeded
+    fn sync(writer: &BufWriter<File>, pending: &AtomicU64, failed: &AtomicBool) -> bool {
+        let started = Instant::now();
+        let result = writer.get_ref().sync_data();
+        LatencyMonitor::global().record(EVENT_AOF_FSYNC, started.elapsed());
+        match result {
+            Ok(_) => {
+                pending.store(0, Ordering::Relaxed);
+                true
+            }
+            Err(e) => {
+                eprintln!("AOF background fsync failed: {}", e);
+                failed.store(true, Ordering::Relaxed);
+                false
+            }
+        }
+    }
+}
+
+impl Drop for FlushCoordinator {
+    fn drop(&mut self) {
+        // Ensure queued entries reach disk
+        self.shutdown();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::OpenOptions;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_shutdown_drains_entries() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("flush.aof");
+        let file = OpenOptions::new().create(true).append(true).open(&path).unwrap();
+
+        let mut coordinator = FlushCoordinator::start(BufWriter::new(file), Arc::new(AtomicU64::new(0)), Arc::new(AtomicBool::new(false)));
+        for i in 0..100u8 {
+            coordinator.submit(vec![i; 10], (i as u64 + 1) * 10).unwrap();
+        }
+        coordinator.sync_now().unwrap();
+        assert_eq!(coordinator.synced_offset(), 1000);
+
+        // Shutdown must write everything and leave nothing pending
+        coordinator.shutdown();
+        assert_eq!(coordinator.pending(), 0);
+        assert_eq!(std::fs::metadata(&path).unwrap().len(), 1000);
+        assert!(coordinator.submit(vec![0], 1001).is_err());
+    }
+
+    #[test]
+    fn test_failure_rejects_later_writes() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("failed.aof");
+        std::fs::write(&path, b"").unwrap();
+
+        // A read-only handle makes every background write fail
+        let file = File::open(&path).unwrap();
+        let coordinator = FlushCoordinator::start(BufWriter::new(file), Arc::new(AtomicU64::new(0)), Arc::new(AtomicBool::new(false)));
+        coordinator.submit(vec![1; 10], 10).unwrap();
+        assert!(coordinator.sync_now().is_err());
+        assert!(coordinator.has_failed());
+        assert!(coordinator.submit(vec![2; 10], 20).is_err());
+        assert!(coordinator.sync_now().is_err());
+    }
+}
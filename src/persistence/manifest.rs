@@ -0,0 +1,103 @@
+// AOF segment manifest - which numbered segment files make up a segmented AOF
+// Stored next to the AOF as `<aof>.manifest`, one `<segment file> <base offset>` line per
+// segment in replay order. The last segment is the one being appended to
+
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// One segment file of a segmented AOF
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Segment {
+    // Sequence number, also the file suffix (workingdb.aof.0001)
+    pub number: u32,
+
+    // Logical AOF offset of the segment's first byte
+    pub base: u64,
+}
+
+/// Path of the manifest for the AOF at `aof_path`
+pub fn manifest_path(aof_path: &Path) -> PathBuf {
+    with_suffix(aof_path, "manifest")
+}
+
+/// Path of segment `number` of the AOF at `aof_path`
+pub fn segment_path(aof_path: &Path, number: u32) -> PathBuf {
+    with_suffix(aof_path, &format!("{:04}", number))
+}
+
+/// Segments listed in the manifest, or None for a single-file AOF (no manifest)
+pub fn load(aof_path: &Path) -> io::Result<Option<Vec<Segment>>> {
+    let text = match fs::read_to_string(manifest_path(aof_path)) {
+        Ok(text) => text,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e),
+    };
+
+    let invalid = |line: &str| io::Error::new(io::ErrorKind::InvalidData, format!("Invalid AOF manifest line: {}", line));
+    let mut segments = Vec::new();
+    for line in text.lines().map(str::trim).filter(|line| !line.is_empty() && !line.starts_with('#')) {
+        let (file, base) = line.split_once(' ').ok_or_else(|| invalid(line))?;
+        let number = file.rsplit('.').next()
+            .and_then(|suffix| suffix.parse().ok())
+            .ok_or_else(|| invalid(line))?;
+        let base = base.trim().parse().map_err(|_| invalid(line))?;
+        segments.push(Segment { number, base });
+    }
+    if segments.is_empty() {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "AOF manifest lists no segments"));
+    }
+    Ok(Some(segments))
+}
+
+/// Atomically replace the manifest with `segments` (write, fsync, rename, fsync directory)
+pub fn store(aof_path: &Path, segments: &[Segment]) -> io::Result<()> {
+    let name = aof_path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+    let mut text = String::from("# segment base_offset\n");
+    for segment in segments {
+        text.push_str(&format!("{}.{:04} {}\n", name, segment.number, segment.base));
+    }
+
+    let path = manifest_path(aof_path);
+    let staged = with_suffix(&path, "tmp");
+    let mut file = File::create(&staged)?;
+    file.write_all(text.as_bytes())?;
+    file.sync_all()?;
+    fs::rename(&staged, &path)?;
+    sync_parent(&path)
+}
+
+/// Fsync the directory holding `path`, so a rename or new link in it survives a crash
+pub fn sync_parent(path: &Path) -> io::Result<()> {
+    let parent = path.parent().filter(|parent| !parent.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    File::open(parent)?.sync_all()
+}
+
+/// `path` with `.suffix` appended to the file name
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_os_string();
+    name.push(".");
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_store_and_load() {
+        let dir = tempfile::tempdir().unwrap();
+        let aof = dir.path().join("workingdb.aof");
+        assert_eq!(load(&aof).unwrap(), None);
+
+        let segments = [Segment { number: 1, base: 0 }, Segment { number: 2, base: 4096 }];
+        store(&aof, &segments).unwrap();
+        assert_eq!(load(&aof).unwrap(), Some(segments.to_vec()));
+        assert!(fs::read_to_string(manifest_path(&aof)).unwrap().contains("workingdb.aof.0002 4096"));
+        assert_eq!(segment_path(&aof, 2), dir.path().join("workingdb.aof.0002"));
+
+        fs::write(manifest_path(&aof), "workingdb.aof.x 0\n").unwrap();
+        assert!(load(&aof).is_err());
+    }
+}
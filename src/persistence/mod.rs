@@ -1,2 +1,4 @@
 pub mod aof;
+pub mod flush;
+pub mod manifest;
 pub mod snapshot;
\ No newline at end of file
@@ -4,10 +4,33 @@ use std::fs::OpenOptions;
 use std::io::{self, BufWriter, Write};
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
-use crate::core::state::GlobalState;
+use crate::persistence::aof::AofMark;
+use crate::storage::memory::MemTable;
+use crate::storage::value::ValueKind;
+use crate::util::crc64::calculate_crc;
 
+/// Snapshot file magic
+const SNAPSHOT_MAGIC: [u8; 8] = *b"WDBSNAP\0";
+
+/// Current format version (version 1 files only held placeholder data, version 2 no AOF mark)
+const SNAPSHOT_VERSION: u32 = 3;
+
+/// Oldest version that can still be restored
+const SNAPSHOT_MIN_VERSION: u32 = 2;
+
+/// `aof_offset` of a snapshot taken without an AOF mark
+const NO_AOF_MARK: u64 = u64::MAX;
+
+/// Access log magic - keys in recency order, framed and checksummed like snapshot data
+const ACCESS_LOG_MAGIC: [u8; 8] = *b"WDBHOT\0\0";
+
+/// Access log file in the snapshot directory (not matched by `list_snapshots`)
+const ACCESS_LOG_FILE: &str = "access-order.wdb";
+
+/// Snapshot entry: key, expiry as unix ms (0 = none), value
+type SnapshotEntry = (Vec<u8>, u64, ValueKind);
 
 /// Snapshot file header
 #[repr(C, packed)]
@@ -27,8 +50,11 @@ struct SnapshotHeader {
     // CRC64 of snapshot data (excluding header)
     data_crc: u64,
     
-    // Reserved for future use
-    reserved: [u8; 16],
+    // AOF mark the snapshot was taken at (NO_AOF_MARK = none; zeroed reserved space before version 3)
+    aof_offset: u64,
+    
+    // Zero - version 3 files written before offsets were monotonic hold an AOF tail checksum
+    reserved: u64,
 }
 
 /// Snapshot manager for database state
@@ -36,13 +62,13 @@ pub struct SnapshotManager {
     // Base directory for snapshots
     snapshot_dir: PathBuf,
     
-    // Database state reference
-    state: Arc<GlobalState>,
+    // Storage engine to snapshot
+    mem_table: Arc<MemTable>,
 }
 
 impl SnapshotManager {
     /// Create new snapshot manager
-    pub fn new<P: AsRef<Path>>(snapshot_dir: P, state: Arc<GlobalState>) -> io::Result<Self> {
+    pub fn new<P: AsRef<Path>>(snapshot_dir: P, mem_table: Arc<MemTable>) -> io::Result<Self> {
         let dir_path = snapshot_dir.as_ref().to_path_buf();
         
         // Create snapshot directory if it doesn't exist
@@ -52,66 +78,140 @@ impl SnapshotManager {
         
         Ok(Self {
             snapshot_dir: dir_path,
-            state,
+            mem_table,
         })
     }
     
     /// Create a new snapshot of current database state
     pub fn create_snapshot(&self) -> io::Result<PathBuf> {
+        self.create_snapshot_with(self.mem_table.snapshot_iter(), None)
+    }
+    
+    /// Create a snapshot from `entries`, the keyspace as of `aof_mark` when given
+    /// Recovery loads such a snapshot and replays only the AOF past the mark
+    pub fn create_snapshot_with(
+        &self,
+        entries: impl IntoIterator<Item = (Vec<u8>, ValueKind, Option<Duration>)>,
+        aof_mark: Option<AofMark>,
+    ) -> io::Result<PathBuf> {
         // Generate snapshot filename with timestamp
-        let timestamp = SystemTime::now()
+        let now = SystemTime::now()
             .duration_since(UNIX_EPOCH)
-            .unwrap_or_default()
-            .as_secs();
+            .unwrap_or_default();
+        let timestamp = now.as_secs();
+        let now_ms = now.as_millis() as u64;
             
         let snapshot_path = self.snapshot_dir
             .join(format!("snapshot-{}.wdb", timestamp));
+        
+        // Point-in-time copy of the data, encoded as
+        // key len (u32) | key | expires at unix ms (u64, 0 = none) | value len (u32) | encoded value
+        // Writes keep being served while the snapshot is read
+        let mut data = Vec::new();
+        let mut kv_count = 0;
+        for (key, value, ttl) in entries {
+            // Absolute expiry so remaining TTLs survive restarts
+            let expires_at_ms = ttl.map_or(0, |ttl| now_ms + (ttl.as_millis() as u64).max(1));
+            let encoded = value.encode();
             
-        // Open snapshot file
-        let file = OpenOptions::new()
-            .write(true)
-            .create(true)
-            .truncate(true)
-            .open(&snapshot_path)?;
-            
-        let mut writer = BufWriter::new(file);
+            data.extend_from_slice(&(key.len() as u32).to_le_bytes());
+            data.extend_from_slice(&key);
+            data.extend_from_slice(&expires_at_ms.to_le_bytes());
+            data.extend_from_slice(&(encoded.len() as u32).to_le_bytes());
+            data.extend_from_slice(&encoded);
+            kv_count += 1;
+        }
         
-        // Create placeholder header (will update later)
         let header = SnapshotHeader {
-            magic: *b"WDBSNAP\0",
-            version: 1,
+            magic: SNAPSHOT_MAGIC,
+            version: SNAPSHOT_VERSION,
             timestamp,
-            kv_count: 0, // Will update later
-            data_crc: 0, // Will update later
-            reserved: [0; 16],
+            kv_count,
+            data_crc: calculate_crc(&data),
+            aof_offset: aof_mark.map_or(NO_AOF_MARK, |mark| mark.offset),
+            reserved: 0,
         };
-        
-        // Write header placeholder
         let header_bytes = unsafe {
             std::slice::from_raw_parts(
                 &header as *const SnapshotHeader as *const u8,
                 std::mem::size_of::<SnapshotHeader>()
             )
         };
-        writer.write_all(header_bytes)?;
-        
-        // TODO: In a real implementation, we would:
-        // 1. Acquire a consistent read view of the database state
-        // 2. Iterate through all key-value pairs
-        // 3. Write them to the snapshot file in a consistent format
-        // 4. Update header with correct kv_count and data_crc
         
-        // For now, just write placeholder content
-        writer.write_all(b"SNAPSHOT DATA PLACEHOLDER")?;
+        // Write to a temp file and rename so a crash never leaves a torn snapshot
+        let temp_path = snapshot_path.with_extension("tmp");
+        let file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&temp_path)?;
+            
+        let mut writer = BufWriter::new(file);
+        writer.write_all(header_bytes)?;
+        writer.write_all(&data)?;
         
         // Ensure everything is written to disk
         writer.flush()?;
+        writer.get_ref().sync_all()?;
+        std::fs::rename(&temp_path, &snapshot_path)?;
         
         println!("Created snapshot: {}", snapshot_path.display());
         
         Ok(snapshot_path)
     }
     
+    /// Save `keys` (most recently accessed first) for the startup warmer, replacing the last log
+    /// Encoded as magic | key count (u64) | CRC64 of data (u64) | data of key len (u32) | key
+    pub fn save_access_log(&self, keys: &[Vec<u8>]) -> io::Result<()> {
+        let mut data = Vec::new();
+        for key in keys {
+            data.extend_from_slice(&(key.len() as u32).to_le_bytes());
+            data.extend_from_slice(key);
+        }
+        
+        let path = self.snapshot_dir.join(ACCESS_LOG_FILE);
+        let temp_path = path.with_extension("tmp");
+        let mut writer = BufWriter::new(std::fs::File::create(&temp_path)?);
+        writer.write_all(&ACCESS_LOG_MAGIC)?;
+        writer.write_all(&(keys.len() as u64).to_le_bytes())?;
+        writer.write_all(&calculate_crc(&data).to_le_bytes())?;
+        writer.write_all(&data)?;
+        writer.flush()?;
+        writer.get_ref().sync_all()?;
+        std::fs::rename(&temp_path, &path)
+    }
+    
+    /// Keys from the last `save_access_log`, most recently accessed first (None = never saved)
+    pub fn load_access_log(&self) -> io::Result<Option<Vec<Vec<u8>>>> {
+        let bytes = match std::fs::read(self.snapshot_dir.join(ACCESS_LOG_FILE)) {
+            Ok(bytes) => bytes,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        if bytes.len() < 24 || bytes[..8] != ACCESS_LOG_MAGIC {
+            return Err(invalid_data("Not an access log"));
+        }
+        
+        let count = u64::from_le_bytes(bytes[8..16].try_into().expect("8 bytes"));
+        let crc = u64::from_le_bytes(bytes[16..24].try_into().expect("8 bytes"));
+        let mut data = &bytes[24..];
+        if calculate_crc(data) != crc {
+            return Err(invalid_data("Access log checksum mismatch"));
+        }
+        
+        let mut keys = Vec::new();
+        while let Some((len, rest)) = data.split_first_chunk::<4>() {
+            let len = u32::from_le_bytes(*len) as usize;
+            let key = rest.get(..len).ok_or_else(|| invalid_data("Access log entry truncated"))?;
+            keys.push(key.to_vec());
+            data = &rest[len..];
+        }
+        if !data.is_empty() || keys.len() as u64 != count {
+            return Err(invalid_data("Access log key count mismatch"));
+        }
+        Ok(Some(keys))
+    }
+    
     /// List available snapshots
     pub fn list_snapshots(&self) -> io::Result<Vec<PathBuf>> {
         let mut snapshots = Vec::new();
@@ -154,20 +254,117 @@ impl SnapshotManager {
         Ok(snapshots)
     }
     
-    /// Restore from snapshot
-    pub fn restore_from_snapshot<P: AsRef<Path>>(&self, _snapshot_path: P) -> io::Result<()> {
-        // TODO: In a real implementation, we would:
-        // 1. Validate snapshot file integrity
-        // 2. Read header
-        // 3. Verify CRC
-        // 4. Load all key-value pairs into memory
-        // 5. Replace current database state
-        
-        // For now, just return not implemented
-        Err(io::Error::new(
-            io::ErrorKind::Unsupported,
-            "Snapshot restoration not implemented yet"
-        ))
+    /// Restore from snapshot, replacing the current database state
+    /// The file is fully validated before live data is touched; returns keys loaded
+    pub fn restore_from_snapshot<P: AsRef<Path>>(&self, snapshot_path: P) -> io::Result<usize> {
+        let (entries, _) = Self::read_snapshot(snapshot_path.as_ref())?;
+        self.load_entries(entries)
+    }
+    
+    /// Restore the newest valid snapshot whose AOF mark passes `usable`
+    /// Snapshots that fail validation are skipped with a warning, ones without a mark
+    /// silently; returns the snapshot, its mark and the keys loaded
+    pub fn restore_latest(
+        &self,
+        usable: impl Fn(&AofMark) -> bool,
+    ) -> io::Result<Option<(PathBuf, AofMark, usize)>> {
+        for path in self.list_snapshots()? {
+            let (entries, mark) = match Self::read_snapshot(&path) {
+                Ok((entries, Some(mark))) => (entries, mark),
+                Ok((_, None)) => continue,
+                Err(e) => {
+                    eprintln!("Skipping snapshot {}: {}", path.display(), e);
+                    continue;
+                }
+            };
+            if usable(&mark) {
+                let loaded = self.load_entries(entries)?;
+                return Ok(Some((path, mark, loaded)));
+            }
+        }
+        Ok(None)
+    }
+    
+    /// Read and fully validate a snapshot file
+    fn read_snapshot(snapshot_path: &Path) -> io::Result<(Vec<SnapshotEntry>, Option<AofMark>)> {
+        let bytes = std::fs::read(snapshot_path)?;
+        let header_size = std::mem::size_of::<SnapshotHeader>();
+        if bytes.len() < header_size {
+            return Err(invalid_data("Snapshot truncated"));
+        }
+        
+        let header = unsafe { std::ptr::read_unaligned(bytes.as_ptr() as *const SnapshotHeader) };
+        let (magic, version, kv_count, data_crc) = (header.magic, header.version, header.kv_count, header.data_crc);
+        if magic != SNAPSHOT_MAGIC {
+            return Err(invalid_data("Not a snapshot file"));
+        }
+        if !(SNAPSHOT_MIN_VERSION..=SNAPSHOT_VERSION).contains(&version) {
+            return Err(invalid_data(&format!("Unsupported snapshot version {}", version)));
+        }
+        
+        let data = &bytes[header_size..];
+        if calculate_crc(data) != data_crc {
+            return Err(invalid_data("Snapshot checksum mismatch"));
+        }
+        
+        let entries = Self::decode_entries(data)?;
+        if entries.len() as u64 != kv_count {
+            return Err(invalid_data("Snapshot key count mismatch"));
+        }
+        
+        let offset = header.aof_offset;
+        let mark = (version >= 3 && offset != NO_AOF_MARK).then_some(AofMark { offset });
+        Ok((entries, mark))
+    }
+    
+    /// Replace the current database state with validated snapshot entries
+    fn load_entries(&self, entries: Vec<SnapshotEntry>) -> io::Result<usize> {
+        // Swap in the snapshot contents, dropping keys that expired since it was taken
+        let now_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as u64;
+        self.mem_table.clear();
+        
+        let mut loaded = 0;
+        for (key, expires_at_ms, value) in entries {
+            let ttl = match expires_at_ms {
+                0 => None,
+                at if at <= now_ms => continue,
+                at => Some(Duration::from_millis(at - now_ms)),
+            };
+            self.mem_table.set_value(&key, value, ttl)
+                .map_err(io::Error::other)?;
+            loaded += 1;
+        }
+        
+        Ok(loaded)
+    }
+    
+    /// Parse the data section written by `create_snapshot`
+    fn decode_entries(mut data: &[u8]) -> io::Result<Vec<SnapshotEntry>> {
+        fn take<'a>(data: &mut &'a [u8], len: usize) -> io::Result<&'a [u8]> {
+            if data.len() < len {
+                return Err(invalid_data("Snapshot entry truncated"));
+            }
+            let (head, tail) = data.split_at(len);
+            *data = tail;
+            Ok(head)
+        }
+        
+        let mut entries = Vec::new();
+        while !data.is_empty() {
+            let key_len = u32::from_le_bytes(take(&mut data, 4)?.try_into().expect("4 bytes")) as usize;
+            let key = take(&mut data, key_len)?.to_vec();
+            let expires_at_ms = u64::from_le_bytes(take(&mut data, 8)?.try_into().expect("8 bytes"));
+            let value_len = u32::from_le_bytes(take(&mut data, 4)?.try_into().expect("4 bytes")) as usize;
+            let value = ValueKind::decode(take(&mut data, value_len)?)
+                .map_err(|e| invalid_data(&e))?;
+            
+            entries.push((key, expires_at_ms, value));
+        }
+        
+        Ok(entries)
     }
     
     /// Clean up old snapshots, keeping only the most recent ones
@@ -199,9 +396,14 @@ impl SnapshotManager {
     }
 }
 
+/// InvalidData error for a malformed snapshot
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.to_string())
+}
+
 #[cfg(test)]
 mod tests {
-  
+    use super::*;
     use tempfile::tempdir;
     
     #[test]
@@ -209,23 +411,46 @@ mod tests {
         // Create temporary directory for test
         let temp_dir = tempdir().unwrap();
         
-        // TODO: This test would be implemented with a mock GlobalState
-        // For now, we're just testing the directory creation logic
-        
         // Ensure directory is created
         let snapshot_dir = temp_dir.path().join("snapshots");
         assert!(!snapshot_dir.exists());
         
-        // Creating a temporary GlobalState for testing
-        // In a real test, this would be mocked
-        /*
-        let state = Arc::new(GlobalState::new(
-            Arc::new(MemTable::new()),
-            // This would need a mock AOF
-        ));
-        
-        let snapshot_manager = SnapshotManager::new(&snapshot_dir, state).unwrap();
+        let snapshot_manager = SnapshotManager::new(&snapshot_dir, Arc::new(MemTable::new())).unwrap();
         assert!(snapshot_dir.exists());
-        */
+        
+        // Snapshot shows up in the listing
+        let path = snapshot_manager.create_snapshot().unwrap();
+        assert_eq!(snapshot_manager.list_snapshots().unwrap(), vec![path]);
     }
-}
\ No newline at end of file
+    
+    #[test]
+    fn test_snapshot_round_trip() {
+        let temp_dir = tempdir().unwrap();
+        let mem_table = Arc::new(MemTable::new());
+        let snapshot_manager = SnapshotManager::new(temp_dir.path(), mem_table.clone()).unwrap();
+        
+        let members: std::collections::HashSet<Vec<u8>> = [b"a".to_vec(), b"b".to_vec()].into_iter().collect();
+        mem_table.set(b"plain", b"value".to_vec(), None).unwrap();
+        mem_table.set(b"ttl", b"soon".to_vec(), Some(Duration::from_secs(100))).unwrap();
+        mem_table.set_value(b"set", ValueKind::Set(members.clone()), None).unwrap();
+        
+        let path = snapshot_manager.create_snapshot().unwrap();
+        
+        // Writes after the snapshot are discarded by the restore
+        mem_table.set(b"later", b"x".to_vec(), None).unwrap();
+        assert_eq!(snapshot_manager.restore_from_snapshot(&path).unwrap(), 3);
+        
+        assert_eq!(mem_table.get(b"plain").as_deref(), Some(b"value".as_slice()));
+        assert_eq!(mem_table.get(b"later"), None);
+        assert_eq!(mem_table.read_value(b"set", |v| v.clone()), Some(ValueKind::Set(members)));
+        let ttl = mem_table.ttl(b"ttl").unwrap();
+        assert!(ttl > Duration::from_secs(98) && ttl <= Duration::from_secs(100));
+        
+        // Corruption is detected before live data is replaced
+        let mut bytes = std::fs::read(&path).unwrap();
+        *bytes.last_mut().unwrap() ^= 0xFF;
+        std::fs::write(&path, bytes).unwrap();
+        assert!(snapshot_manager.restore_from_snapshot(&path).is_err());
+        assert_eq!(mem_table.get(b"plain").as_deref(), Some(b"value".as_slice()));
+    }
+}
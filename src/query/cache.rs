@@ -0,0 +1,201 @@
+// src/query/cache.rs - SELECT RESULT CACHE
+// Results are keyed by normalized query text and tagged with the version of every
+// table they read. Versions are the state's per-table change counts, which every
+// write moves on - SQL or plain commands, expiry once a key is reaped, eviction and
+// FLUSHALL - so older results stop matching
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Mutex, PoisonError};
+
+use super::{QueryResult, Value};
+
+/// Query cache - LRU over SELECT results, bounded by entry count and result bytes
+pub struct QueryCache {
+    // Most results kept
+    max_entries: usize,
+
+    // Most result bytes kept (estimated from the values)
+    max_bytes: usize,
+
+    inner: Mutex<CacheInner>,
+}
+
+/// Cache contents, guarded by one lock
+#[derive(Default)]
+struct CacheInner {
+    // Normalized query -> cached result
+    entries: HashMap<String, CachedResult>,
+
+    // Last use -> query, least recent first (one per entry, clock values are unique)
+    recency: BTreeMap<u64, String>,
+
+    // Sum of entry sizes
+    bytes: usize,
+
+    // Logical clock for LRU recency
+    clock: u64,
+}
+
+/// One cached SELECT
+struct CachedResult {
+    // Tables read and their versions when the result was computed
+    tables: Vec<(String, u64)>,
+
+    result: QueryResult,
+
+    // Estimated size of the result
+    bytes: usize,
+
+    // Clock value at the last hit
+    last_used: u64,
+}
+
+impl QueryCache {
+    /// Create a cache holding at most `max_entries` results and `max_bytes` of result data
+    pub fn new(max_entries: usize, max_bytes: usize) -> Self {
+        Self {
+            max_entries,
+            max_bytes,
+            inner: Mutex::new(CacheInner::default()),
+        }
+    }
+
+    /// Cached result for `query`, if none of its tables changed since it was stored
+    /// `version` gives a table's current version
+    pub fn get(&self, query: &str, version: impl Fn(&str) -> u64) -> Option<QueryResult> {
+        let mut inner = self.lock();
+        let inner = &mut *inner;
+        inner.clock += 1;
+
+        let entry = inner.entries.get_mut(query)?;
+        let fresh = entry.tables.iter().all(|(table, tagged)| version(table) == *tagged);
+        if fresh {
+            inner.recency.remove(&entry.last_used);
+            entry.last_used = inner.clock;
+            inner.recency.insert(entry.last_used, query.to_string());
+            return Some(entry.result.clone());
+        }
+
+        // Stale - drop it now rather than waiting for eviction
+        inner.remove(query);
+        None
+    }
+
+    /// Store a result computed against table `versions`, read before the query ran
+    /// Results larger than the whole cache are not stored
+    pub fn insert(&self, query: String, versions: Vec<(String, u64)>, result: QueryResult) {
+        let bytes = query.len() + result_size(&result);
+        if self.max_entries == 0 || bytes > self.max_bytes {
+            return;
+        }
+
+        let mut inner = self.lock();
+        inner.remove(&query);
+        inner.clock += 1;
+        let last_used = inner.clock;
+        inner.recency.insert(last_used, query.clone());
+        inner.entries.insert(query, CachedResult { tables: versions, result, bytes, last_used });
+        inner.bytes += bytes;
+
+        // Evict least recently used results until both limits hold
+        while inner.entries.len() > self.max_entries || inner.bytes > self.max_bytes {
+            let Some((_, oldest)) = inner.recency.pop_first() else {
+                break;
+            };
+            inner.remove(&oldest);
+        }
+    }
+
+    /// Drop every cached result (FLUSH QUERY CACHE) - returns how many were dropped
+    pub fn flush(&self) -> usize {
+        let mut inner = self.lock();
+        inner.bytes = 0;
+        let dropped = inner.entries.len();
+        inner.entries.clear();
+        inner.recency.clear();
+        dropped
+    }
+
+    /// Number of cached results
+    pub fn len(&self) -> usize {
+        self.lock().entries.len()
+    }
+
+    /// Whether no results are cached
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, CacheInner> {
+        self.inner.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl CacheInner {
+    /// Drop `query`'s entry, if cached
+    fn remove(&mut self, query: &str) {
+        if let Some(entry) = self.entries.remove(query) {
+            self.recency.remove(&entry.last_used);
+            self.bytes -= entry.bytes;
+        }
+    }
+}
+
+/// Rough in-memory size of a result's data
+fn result_size(result: &QueryResult) -> usize {
+    let QueryResult::Rows { columns, rows, .. } = result else {
+        return 0;
+    };
+    let value_size = |value: &Value| match value {
+        Value::Null | Value::Integer(_) | Value::Float(_) => 8,
+        Value::Text(text) => text.len(),
+        Value::Binary(bytes) => bytes.len(),
+    };
+    columns.iter().map(String::len).sum::<usize>()
+        + rows.iter().flatten().map(value_size).sum::<usize>()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rows(values: &[&str]) -> QueryResult {
+        QueryResult::Rows {
+            columns: vec!["value".to_string()],
+            rows: values.iter().map(|value| vec![Value::Text(value.to_string())]).collect(),
+            affected_rows: values.len(),
+        }
+    }
+
+    #[test]
+    fn test_invalidation_and_lru() {
+        let cache = QueryCache::new(2, 1024);
+        let users = || vec![("users".to_string(), 1)];
+        cache.insert("select * from users".to_string(), users(), rows(&["ann"]));
+        assert!(cache.get("select * from users", |_| 1).is_some());
+
+        // A write to the table invalidates the result
+        assert!(cache.get("select * from users", |_| 2).is_none());
+        assert!(cache.is_empty());
+
+        // The least recently used result goes first
+        cache.insert("a".to_string(), users(), rows(&["a"]));
+        cache.insert("b".to_string(), users(), rows(&["b"]));
+        assert!(cache.get("a", |_| 1).is_some());
+        cache.insert("c".to_string(), users(), rows(&["c"]));
+        assert!(cache.get("b", |_| 1).is_none());
+        assert!(cache.get("a", |_| 1).is_some() && cache.get("c", |_| 1).is_some());
+
+        // Replacing an entry keeps one recency slot for it
+        cache.insert("c".to_string(), users(), rows(&["c2"]));
+        cache.insert("d".to_string(), users(), rows(&["d"]));
+        assert!(cache.get("a", |_| 1).is_none());
+        assert_eq!(cache.len(), 2);
+
+        // Byte limit - oversized results are skipped
+        cache.insert("big".to_string(), users(), rows(&[&"x".repeat(2048)]));
+        assert!(cache.get("big", |_| 1).is_none());
+        assert_eq!(cache.flush(), 2);
+        assert!(cache.is_empty());
+    }
+}
@@ -0,0 +1,60 @@
+// Cancellation for running queries - the executor polls the token between scan
+// batches, so cancelling stops the work at its next check rather than immediately
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Shared flag a query's owner sets to make the executor give up
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+/// Cancels its token when dropped - held by whoever waits for the query
+pub struct CancelOnDrop {
+    token: CancelToken,
+}
+
+impl CancelToken {
+    /// Token that has not been cancelled
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Ask every holder of the token to stop
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Release);
+    }
+    
+    /// Whether `cancel` has been called
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Acquire)
+    }
+    
+    /// Guard that cancels this token once it goes out of scope
+    pub fn cancel_on_drop(&self) -> CancelOnDrop {
+        CancelOnDrop { token: self.clone() }
+    }
+}
+
+impl Drop for CancelOnDrop {
+    fn drop(&mut self) {
+        self.token.cancel();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_cancel_on_drop() {
+        let token = CancelToken::new();
+        let worker = token.clone();
+        {
+            let _guard = token.cancel_on_drop();
+            assert!(!worker.is_cancelled());
+        }
+        assert!(worker.is_cancelled());
+    }
+}
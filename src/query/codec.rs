@@ -0,0 +1,120 @@
+// src/query/codec.rs - ROW CODEC
+// Rows with named, typed columns stored as one string value:
+//   magic (0xF7 'R') | per column: name len u32 | name | tag u8 | payload len u32 | payload
+// Every payload is length-prefixed, so readers skip tags they don't know. 0xF7 never
+// starts UTF-8 text, so encoded rows can't be mistaken for plain text values
+
+use super::Value;
+
+/// Leading bytes of an encoded row
+const ROW_MAGIC: [u8; 2] = [0xF7, b'R'];
+
+/// Type tags for column values
+const TAG_NULL: u8 = 0;
+const TAG_INTEGER: u8 = 1;
+const TAG_FLOAT: u8 = 2;
+const TAG_TEXT: u8 = 3;
+const TAG_BINARY: u8 = 4;
+
+/// Serialize named column values
+pub fn encode_row(columns: &[(&str, Value)]) -> Vec<u8> {
+    let mut buf = ROW_MAGIC.to_vec();
+    for (name, value) in columns {
+        let (tag, payload): (u8, &[u8]) = match value {
+            Value::Null => (TAG_NULL, &[]),
+            Value::Integer(n) => (TAG_INTEGER, &n.to_le_bytes()),
+            Value::Float(x) => (TAG_FLOAT, &x.to_le_bytes()),
+            Value::Text(text) => (TAG_TEXT, text.as_bytes()),
+            Value::Binary(bytes) => (TAG_BINARY, bytes),
+        };
+        buf.extend_from_slice(&(name.len() as u32).to_le_bytes());
+        buf.extend_from_slice(name.as_bytes());
+        buf.push(tag);
+        buf.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+        buf.extend_from_slice(payload);
+    }
+    buf
+}
+
+/// Whether `bytes` were produced by `encode_row` (as opposed to a plain string)
+pub fn is_encoded_row(bytes: &[u8]) -> bool {
+    bytes.starts_with(&ROW_MAGIC)
+}
+
+/// Deserialize an encoded row - columns with unknown type tags are skipped
+pub fn decode_row(bytes: &[u8]) -> Result<Vec<(String, Value)>, String> {
+    let mut data = bytes.strip_prefix(&ROW_MAGIC)
+        .ok_or_else(|| "Not an encoded row".to_string())?;
+
+    let mut columns = Vec::new();
+    while !data.is_empty() {
+        let name = take_prefixed(&mut data)?;
+        let name = std::str::from_utf8(name).map_err(|_| "Invalid column name in row".to_string())?;
+        let (&tag, rest) = data.split_first().ok_or_else(|| "Truncated row".to_string())?;
+        data = rest;
+        let payload = take_prefixed(&mut data)?;
+
+        let value = match tag {
+            TAG_NULL => Value::Null,
+            TAG_INTEGER => Value::Integer(i64::from_le_bytes(fixed(payload)?)),
+            TAG_FLOAT => Value::Float(f64::from_le_bytes(fixed(payload)?)),
+            TAG_TEXT => Value::Text(String::from_utf8(payload.to_vec()).map_err(|_| "Invalid text in row".to_string())?),
+            TAG_BINARY => Value::Binary(payload.to_vec()),
+            _ => continue,
+        };
+        columns.push((name.to_string(), value));
+    }
+    Ok(columns)
+}
+
+/// Next u32-length-prefixed field
+fn take_prefixed<'a>(data: &mut &'a [u8]) -> Result<&'a [u8], String> {
+    let truncated = || "Truncated row".to_string();
+    let (len, rest) = data.split_first_chunk::<4>().ok_or_else(truncated)?;
+    let len = u32::from_le_bytes(*len) as usize;
+    if rest.len() < len {
+        return Err(truncated());
+    }
+    let (field, rest) = rest.split_at(len);
+    *data = rest;
+    Ok(field)
+}
+
+/// Fixed-width numeric payload
+fn fixed(payload: &[u8]) -> Result<[u8; 8], String> {
+    payload.try_into().map_err(|_| "Invalid numeric payload in row".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_row_round_trip() {
+        let row = [
+            ("id", Value::Integer(-7)),
+            ("score", Value::Float(2.5)),
+            ("name", Value::Text("Ann".to_string())),
+            ("avatar", Value::Binary(vec![0, 0xFF, 1])),
+            ("email", Value::Null),
+        ];
+        let encoded = encode_row(&row);
+        assert!(is_encoded_row(&encoded));
+        assert!(!is_encoded_row(b"plain"));
+
+        let decoded = decode_row(&encoded).unwrap();
+        assert_eq!(format!("{:?}", decoded), format!("{:?}", row.map(|(name, value)| (name.to_string(), value))));
+
+        // A column with a tag from a newer version is skipped
+        let mut newer = encode_row(&[("a", Value::Integer(1))]);
+        newer.extend_from_slice(&1u32.to_le_bytes());
+        newer.push(b'b');
+        newer.push(99);
+        newer.extend_from_slice(&3u32.to_le_bytes());
+        newer.extend_from_slice(b"???");
+        assert_eq!(format!("{:?}", decode_row(&newer).unwrap()), r#"[("a", Integer(1))]"#);
+
+        assert!(decode_row(&encoded[..encoded.len() - 1]).is_err());
+        assert!(decode_row(b"plain").is_err());
+    }
+}
@@ -1,10 +1,47 @@
 // Query execution engine with runtime optimization
+// A table `t` is the string keys `t:<id>`; each row has a `key` column (the id)
+// plus either the columns of an encoded row (see query::codec) or, for a plain
+// string, a single `value` column. Joined rows qualify every column with its
+// table (`t.key`); unqualified names resolve when only one table has them
+//
+// Rows of one table need not share columns. A column a row doesn't have reads as
+// NULL, exactly like one stored as NULL, so IS NULL matches both and `SELECT *`
+// shows both as NULL
 
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::ops::ControlFlow;
 use std::sync::Arc;
+use std::time::Instant;
 
 use crate::core::state::GlobalState;
-use crate::query::parser::{ParsedQuery, Expr, Literal};
+use crate::query::cancel::CancelToken;
+use crate::query::parser::{ParsedQuery, Expr, Literal, BinaryOperator, UnaryOperator};
+use crate::query::codec::{decode_row, encode_row, is_encoded_row};
 use crate::query::{QueryResult, Value};
+use crate::util::glob::LikePattern;
+
+/// Entries scanned between deadline and cancellation checks - keeps clock reads off the per-row path
+const DEADLINE_CHECK_INTERVAL: usize = 1024;
+
+/// When a running query gives up early
+#[derive(Clone, Copy, Default)]
+struct Limits<'a> {
+    // Fail with "query timed out" once this passes
+    deadline: Option<Instant>,
+    
+    // Fail with "query cancelled" once this is cancelled (the client went away)
+    cancel: Option<&'a CancelToken>,
+}
+
+/// Table row - column values in column order
+#[derive(Debug, Clone)]
+struct Row {
+    columns: Vec<(String, Value)>,
+    
+    // Table of a single-table row, whose column names are unqualified (None once joined)
+    table: Option<Arc<str>>,
+}
 
 /// Execution plan for a query
 #[derive(Debug, Clone)]
@@ -25,6 +62,22 @@ enum ExecutionStep {
         filter: Option<CompiledExpression>,
     },
     
+    // Inner equi-join: build a hash table on the smaller table, probe with the larger
+    // The WHERE filter runs on the joined rows
+    HashJoin {
+        left: String,
+        right: String,
+        left_column: String,
+        right_column: String,
+        filter: Option<CompiledExpression>,
+    },
+    
+    // Count matching rows without materializing them (SELECT COUNT(*))
+    Count {
+        table: String,
+        filter: Option<CompiledExpression>,
+    },
+    
     // Project specific columns
     Project {
         columns: Vec<String>,
@@ -48,14 +101,23 @@ enum ExecutionStep {
         table: String,
     },
     
+    // Drop rows whose projected values were already output (first seen wins)
+    Distinct,
+    
     // Sort results
     Sort {
         columns: Vec<(String, bool)>, // (column, is_ascending)
     },
     
-    // Limit results
+    // Skip `offset` rows, then keep at most `count`
     Limit {
         count: usize,
+        offset: usize,
+    },
+    
+    // Describe a plan instead of running it
+    Explain {
+        plan: Box<ExecutionPlan>,
     },
 }
 
@@ -78,6 +140,35 @@ enum CompiledExpression {
         value: Literal,
     },
     
+    // Column in a list of literals, looked up by hash
+    ColumnIn {
+        column: String,
+        values: ValueSet,
+        negated: bool,
+    },
+    
+    // Column between two literals (inclusive)
+    ColumnBetween {
+        column: String,
+        low: Literal,
+        high: Literal,
+        negated: bool,
+    },
+    
+    // Column IS [NOT] NULL (absent columns are NULL)
+    ColumnIsNull {
+        column: String,
+        negated: bool,
+    },
+    
+    // Column [NOT] LIKE a pattern parsed once
+    Like {
+        column: String,
+        pattern: String,
+        matcher: LikePattern,
+        negated: bool,
+    },
+    
     // Logical AND of compiled expressions
     And {
         left: Box<CompiledExpression>,
@@ -96,6 +187,29 @@ enum CompiledExpression {
     },
 }
 
+/// IN list literals hashed by how compare_values treats them, so a lookup agrees
+/// with comparing against each literal in turn
+#[derive(Debug, Clone)]
+struct ValueSet {
+    // Integer literals, matched exactly by integers
+    integers: HashSet<i64>,
+    
+    // Float literals (f64 bits), matched by any numeric value
+    floats: HashSet<u64>,
+    
+    // Numeric string literals (f64 bits), matched by integer and float values
+    numeric_strings: HashSet<u64>,
+    
+    // String literals, matched bytewise by text and binary values
+    strings: HashSet<Vec<u8>>,
+    
+    // Whether the list holds NULL - then NOT IN is never true
+    has_null: bool,
+    
+    // The literals as written, for EXPLAIN
+    literals: Vec<Literal>,
+}
+
 /// Comparison operators
 #[derive(Debug, Clone, Copy)]
 enum ComparisonOp {
@@ -111,23 +225,42 @@ impl ExecutionPlan {
     /// Create execution plan from parsed query
     pub fn from_parsed_query(query: ParsedQuery) -> Self {
         match query {
-            ParsedQuery::Select { columns, table, where_clause, limit } => {
+            ParsedQuery::Select { distinct, columns, table, join, where_clause, limit, offset } => {
                 // Build steps for SELECT
                 let mut steps = Vec::new();
                 
                 // Scan table with filter if WHERE present
+                // COUNT(*) alone counts during the scan instead
                 let filter = where_clause.map(|wc| compile_expression(&wc.expr));
-                steps.push(ExecutionStep::Scan { 
-                    table, 
-                    filter,
-                });
+                if let Some(join) = join {
+                    steps.push(ExecutionStep::HashJoin {
+                        left: table,
+                        right: join.table,
+                        left_column: join.left_column,
+                        right_column: join.right_column,
+                        filter,
+                    });
+                    steps.push(ExecutionStep::Project { columns: columns.clone() });
+                } else if is_count_star(&columns) {
+                    steps.push(ExecutionStep::Count { table, filter });
+                } else {
+                    steps.push(ExecutionStep::Scan { 
+                        table, 
+                        filter,
+                    });
+                    
+                    // Project columns
+                    steps.push(ExecutionStep::Project { columns: columns.clone() });
+                }
                 
-                // Project columns
-                steps.push(ExecutionStep::Project { columns: columns.clone() });
+                // Dedupe before LIMIT so it counts distinct rows
+                if distinct {
+                    steps.push(ExecutionStep::Distinct);
+                }
                 
-                // Add LIMIT if present
-                if let Some(limit_count) = limit {
-                    steps.push(ExecutionStep::Limit { count: limit_count });
+                // Add LIMIT / OFFSET if present
+                if limit.is_some() || offset > 0 {
+                    steps.push(ExecutionStep::Limit { count: limit.unwrap_or(usize::MAX), offset });
                 }
                 
                 ExecutionPlan {
@@ -197,6 +330,15 @@ impl ExecutionPlan {
                 }
             }
             
+            ParsedQuery::Explain(inner) => {
+                ExecutionPlan {
+                    steps: vec![ExecutionStep::Explain {
+                        plan: Box::new(ExecutionPlan::from_parsed_query(*inner)),
+                    }],
+                    output_columns: vec!["plan".to_string()],
+                }
+            }
+            
             ParsedQuery::CreateTable { table: _, columns: _ } => {
                 // CRITICAL FIX: Changed variable names to _
                 // In a real implementation, we'd handle DDL here
@@ -210,6 +352,89 @@ impl ExecutionPlan {
     }
 }
 
+impl ExecutionPlan {
+    /// One human-readable line per step, as returned by EXPLAIN
+    pub fn describe(&self) -> Vec<String> {
+        let mut lines = Vec::new();
+        for step in &self.steps {
+            match step {
+                ExecutionStep::Scan { table, filter } | ExecutionStep::Count { table, filter } => {
+                    let verb = if matches!(step, ExecutionStep::Count { .. }) { "COUNT ROWS OF" } else { "SCAN TABLE" };
+                    lines.push(format!("{} {}", verb, table));
+                    if let Some(filter) = filter {
+                        lines.push(format!("FILTER {}", filter));
+                    }
+                }
+                ExecutionStep::HashJoin { left, right, left_column, right_column, filter } => {
+                    lines.push(format!("HASH JOIN {}, {} ON {} = {}", left, right, left_column, right_column));
+                    if let Some(filter) = filter {
+                        lines.push(format!("FILTER {}", filter));
+                    }
+                }
+                ExecutionStep::Project { columns } => lines.push(format!("PROJECT {}", columns.join(", "))),
+                ExecutionStep::Insert { table, columns, values } => {
+                    lines.push(format!("INSERT INTO {} ({}) - {} rows", table, columns.join(", "), values.len()));
+                }
+                ExecutionStep::Update { table, assignments } => {
+                    let assignments: Vec<String> = assignments.iter()
+                        .map(|(column, value)| format!("{} = {}", column, value))
+                        .collect();
+                    lines.push(format!("UPDATE {} SET {}", table, assignments.join(", ")));
+                }
+                ExecutionStep::Delete { table } => lines.push(format!("DELETE FROM {}", table)),
+                ExecutionStep::Distinct => lines.push("DISTINCT".to_string()),
+                ExecutionStep::Sort { columns } => {
+                    let keys: Vec<String> = columns.iter()
+                        .map(|(column, ascending)| format!("{} {}", column, if *ascending { "ASC" } else { "DESC" }))
+                        .collect();
+                    lines.push(format!("SORT BY {}", keys.join(", ")));
+                }
+                ExecutionStep::Limit { count, offset: 0 } => lines.push(format!("LIMIT {}", count)),
+                ExecutionStep::Limit { count, offset } => lines.push(format!("LIMIT {} OFFSET {}", count, offset)),
+                ExecutionStep::Explain { plan } => {
+                    lines.push("EXPLAIN".to_string());
+                    lines.extend(plan.describe().into_iter().map(|line| format!("  {}", line)));
+                }
+            }
+        }
+        lines
+    }
+}
+
+/// Compiled filters name the form they took, so EXPLAIN shows which ones were optimized
+impl std::fmt::Display for CompiledExpression {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CompiledExpression::Constant(value) => write!(f, "Constant({})", value),
+            CompiledExpression::ColumnEqValue { column, value } => write!(f, "ColumnEqValue({} = {})", column, value),
+            CompiledExpression::ColumnCompare { column, op, value } => {
+                write!(f, "ColumnCompare({} {} {})", column, op.symbol(), value)
+            }
+            CompiledExpression::ColumnIn { column, values, negated } => {
+                let literals: Vec<String> = values.literals.iter().map(Literal::to_string).collect();
+                write!(f, "ColumnIn({} {}IN ({}))", column, if *negated { "NOT " } else { "" }, literals.join(", "))
+            }
+            CompiledExpression::ColumnBetween { column, low, high, negated } => {
+                write!(f, "ColumnBetween({} {}BETWEEN {} AND {})", column, if *negated { "NOT " } else { "" }, low, high)
+            }
+            CompiledExpression::ColumnIsNull { column, negated } => {
+                write!(f, "ColumnIsNull({} IS {}NULL)", column, if *negated { "NOT " } else { "" })
+            }
+            CompiledExpression::Like { column, pattern, negated, .. } => {
+                write!(f, "Like({} {}LIKE {})", column, if *negated { "NOT " } else { "" }, Literal::String(pattern.clone()))
+            }
+            CompiledExpression::And { left, right } => write!(f, "And({}, {})", left, right),
+            CompiledExpression::Or { left, right } => write!(f, "Or({}, {})", left, right),
+            CompiledExpression::Complex { expr } => write!(f, "Complex({})", expr),
+        }
+    }
+}
+
+/// Whether the projection is exactly COUNT(*)
+fn is_count_star(columns: &[String]) -> bool {
+    matches!(columns, [column] if column.replace(' ', "").eq_ignore_ascii_case("count(*)"))
+}
+
 /// Compile expression into optimized form
 fn compile_expression(expr: &Expr) -> CompiledExpression {
     match expr {
@@ -249,6 +474,23 @@ fn compile_expression(expr: &Expr) -> CompiledExpression {
             }
         }
         
+        // Optimize "column [NOT] LIKE 'pattern'"
+        Expr::BinaryOp {
+            left,
+            op: op @ (BinaryOperator::Like | BinaryOperator::NotLike),
+            right,
+        } if matches!(**left, Expr::Column(_)) && matches!(**right, Expr::Literal(Literal::String(_))) => {
+            let (Expr::Column(column), Expr::Literal(Literal::String(pattern))) = (&**left, &**right) else {
+                unreachable!()
+            };
+            CompiledExpression::Like {
+                column: column.clone(),
+                pattern: pattern.clone(),
+                matcher: LikePattern::new(pattern),
+                negated: matches!(op, BinaryOperator::NotLike),
+            }
+        }
+        
         // CRITICAL FIX: Changed box patterns to match on refs
         Expr::BinaryOp {
             left,
@@ -316,57 +558,833 @@ fn compile_expression(expr: &Expr) -> CompiledExpression {
             }
         }
         
+        // Optimize "column [NOT] IN (literals)"
+        Expr::In { expr: column, list, negated } if matches!(**column, Expr::Column(_)) => {
+            let (Expr::Column(column), Some(literals)) = (&**column, literal_list(list)) else {
+                return CompiledExpression::Complex { expr: expr.clone() };
+            };
+            CompiledExpression::ColumnIn {
+                column: column.clone(),
+                values: ValueSet::new(literals),
+                negated: *negated,
+            }
+        }
+        
+        // Optimize "column IS [NOT] NULL"
+        Expr::IsNull { expr: column, negated } if matches!(**column, Expr::Column(_)) => {
+            let Expr::Column(column) = &**column else {
+                unreachable!()
+            };
+            CompiledExpression::ColumnIsNull { column: column.clone(), negated: *negated }
+        }
+        
+        // Optimize "column [NOT] BETWEEN literal AND literal"
+        Expr::Between { expr: column, low, high, negated } => match (&**column, &**low, &**high) {
+            (Expr::Column(column), Expr::Literal(low), Expr::Literal(high)) => CompiledExpression::ColumnBetween {
+                column: column.clone(),
+                low: low.clone(),
+                high: high.clone(),
+                negated: *negated,
+            },
+            _ => CompiledExpression::Complex { expr: expr.clone() },
+        },
+        
         // For more complex expressions, just wrap them for runtime evaluation
         _ => CompiledExpression::Complex { expr: expr.clone() },
     }
 }
 
-/// Execute a query plan
+/// Execute a query plan, failing with "query timed out" once `deadline` passes and
+/// with "query cancelled" once `cancel` is cancelled - either way without a result
 pub fn execute_plan(
     plan: ExecutionPlan,
-    state: Arc<GlobalState>
+    state: Arc<GlobalState>,
+    deadline: Option<Instant>,
+    cancel: Option<&CancelToken>,
 ) -> Result<QueryResult, String> {
-    // In a real implementation, this would execute the plan steps
-    // For now, just return placeholder result
+    let limits = Limits { deadline, cancel };
+    
+    // EXPLAIN returns the inner plan, one row per line
+    if let [ExecutionStep::Explain { plan: inner }] = plan.steps.as_slice() {
+        let rows: Vec<Vec<Value>> = inner.describe().into_iter().map(|line| vec![Value::Text(line)]).collect();
+        return Ok(QueryResult::Rows {
+            columns: plan.output_columns,
+            affected_rows: rows.len(),
+            rows,
+        });
+    }
+    
+    // DDL is not executed yet
+    if plan.steps.is_empty() {
+        return Ok(QueryResult::Modified {
+            affected_rows: 1, // Placeholder
+        });
+    }
+    
+    // A plain scan applies its LIMIT while scanning: skipped rows are never kept and
+    // the scan stops once enough rows are found
+    let scan_limit = match plan.steps.as_slice() {
+        [ExecutionStep::Scan { .. }, ExecutionStep::Project { .. }, ExecutionStep::Limit { count, offset }] => Some((*count, *offset)),
+        _ => None,
+    };
+    
+    let mut rows = Vec::new();
+    let mut columns = plan.output_columns;
+    let mut affected = None;
+    for step in &plan.steps {
+        match step {
+            ExecutionStep::Scan { table, filter } => {
+                rows.clear();
+                let (count, mut skip) = scan_limit.unwrap_or((usize::MAX, 0));
+                scan_rows(&state, table, filter.as_ref(), limits, |row| {
+                    if rows.len() >= count {
+                        return Ok(ControlFlow::Break(()));
+                    }
+                    match skip {
+                        0 => rows.push(row),
+                        _ => skip -= 1,
+                    }
+                    Ok(ControlFlow::Continue(()))
+                })?;
+            }
+            ExecutionStep::HashJoin { left, right, left_column, right_column, filter } => {
+                rows.clear();
+                let join = JoinSide::pair(left, left_column, right, right_column);
+                hash_join(&state, join, filter.as_ref(), limits, |row| rows.push(row))?;
+            }
+            ExecutionStep::Count { table, filter } => {
+                // Without a filter the keys alone answer it
+                let count = match filter {
+                    None => state.count_strings_with_prefix(&table_prefix(table)),
+                    Some(filter) => {
+                        let mut count = 0;
+                        scan_rows(&state, table, Some(filter), limits, |_| {
+                            count += 1;
+                            Ok(ControlFlow::Continue(()))
+                        })?;
+                        count
+                    }
+                };
+                rows = vec![Row {
+                    columns: vec![(columns[0].clone(), Value::Integer(count as i64))],
+                    table: None,
+                }];
+            }
+            ExecutionStep::Project { columns: projection } if is_count_star(projection) => {
+                // COUNT(*) over rows a join produced
+                columns = projection.clone();
+                rows = vec![Row {
+                    columns: vec![(columns[0].clone(), Value::Integer(rows.len() as i64))],
+                    table: None,
+                }];
+            }
+            ExecutionStep::Project { columns: projection } => {
+                columns = expand_projection(projection, &rows);
+                rows = rows.iter()
+                    .map(|row| Ok(Row {
+                        columns: columns.iter()
+                            .map(|name| Ok((name.clone(), row.get(name)?.clone())))
+                            .collect::<Result<_, String>>()?,
+                        table: row.table.clone(),
+                    }))
+                    .collect::<Result<_, String>>()?;
+            }
+            ExecutionStep::Distinct => {
+                let mut seen = HashSet::new();
+                rows.retain(|row| seen.insert(row.encode_values()));
+            }
+            ExecutionStep::Sort { columns: keys } => {
+                rows.sort_by(|a, b| {
+                    keys.iter()
+                        .map(|(name, ascending)| {
+                            let value = |row: &Row| row.get(name).cloned().unwrap_or(Value::Null);
+                            let ord = compare_values(&value(a), &value(b)).unwrap_or(Ordering::Equal);
+                            if *ascending { ord } else { ord.reverse() }
+                        })
+                        .find(|ord| ord.is_ne())
+                        .unwrap_or(Ordering::Equal)
+                });
+            }
+            ExecutionStep::Limit { .. } if scan_limit.is_some() => {}
+            ExecutionStep::Limit { count, offset } => {
+                rows.drain(..rows.len().min(*offset));
+                rows.truncate(*count);
+            }
+            ExecutionStep::Insert { table, columns: names, values } => {
+                let key_column = names.iter()
+                    .position(|name| name == "key")
+                    .ok_or_else(|| "INSERT needs a key column".to_string())?;
+                
+                // Every row is checked before any is written
+                let mut entries = Vec::with_capacity(values.len());
+                let mut keys = HashSet::new();
+                for tuple in values {
+                    let key = row_key(table, &literal_value(&tuple[key_column]))?;
+                    if !keys.insert(key.clone()) {
+                        return Err(format!("duplicate key: {}", tuple[key_column]));
+                    }
+                    let columns: Vec<(String, Value)> = names.iter()
+                        .zip(tuple)
+                        .filter(|(name, _)| *name != "key")
+                        .map(|(name, literal)| (name.clone(), literal_value(literal)))
+                        .collect();
+                    entries.push((key, stored_bytes(&columns), None));
+                }
+                affected = Some(entries.len());
+                
+                // Existing keys are checked under the write's own locks, so two INSERTs
+                // of the same key can't both succeed
+                let keys: Vec<_> = entries.iter().map(|(key, _, _)| key.clone()).collect();
+                if !state.set_batch_nx(entries)? {
+                    return Err(match keys.iter().zip(values).find(|(key, _)| state.value_type(key).is_some()) {
+                        Some((_, tuple)) => format!("duplicate key: {}", tuple[key_column]),
+                        None => "duplicate key".to_string(),
+                    });
+                }
+            }
+            ExecutionStep::Update { table, assignments } => {
+                // Every row is checked before any is written
+                let updates = rows.iter()
+                    .map(|row| Ok((row_key(table, row.get("key")?)?, stored_bytes(&assign(row, assignments)?))))
+                    .collect::<Result<Vec<_>, String>>()?;
+                let mut updated = 0;
+                for (key, value) in updates {
+                    // Rows deleted since the scan stay deleted; TTLs are kept
+                    let Some(entry) = state.get_full(&key) else {
+                        continue;
+                    };
+                    state.set(&key, value, entry.remaining_ttl)?;
+                    updated += 1;
+                }
+                affected = Some(updated);
+            }
+            ExecutionStep::Delete { table } => {
+                let mut deleted = 0;
+                for row in &rows {
+                    deleted += usize::from(state.delete(&row_key(table, row.get("key")?)?)?);
+                }
+                affected = Some(deleted);
+            }
+            ExecutionStep::Explain { .. } => {}
+        }
+    }
+    
+    if let Some(affected_rows) = affected {
+        return Ok(QueryResult::Modified { affected_rows });
+    }
+    
+    let rows: Vec<Vec<Value>> = rows.into_iter()
+        .map(|row| row.columns.into_iter().map(|(_, value)| value).collect())
+        .collect();
+    Ok(QueryResult::Rows {
+        columns,
+        affected_rows: rows.len(),
+        rows,
+    })
+}
+
+/// Key prefix of a table's rows
+fn table_prefix(table: &str) -> Vec<u8> {
+    format!("{}:", table).into_bytes()
+}
+
+/// Hand each row of `table` that passes `filter` to `visit` until it breaks, checking
+/// `limits` every DEADLINE_CHECK_INTERVAL entries
+/// Every key in the snapshot counts towards the interval, so a small table in a
+/// large keyspace is still bounded
+fn scan_rows(
+    state: &GlobalState,
+    table: &str,
+    filter: Option<&CompiledExpression>,
+    limits: Limits<'_>,
+    mut visit: impl FnMut(Row) -> Result<ControlFlow<()>, String>,
+) -> Result<(), String> {
+    let prefix = table_prefix(table);
+    let name: Arc<str> = Arc::from(table);
     
-    // For SELECT queries
-    if plan.steps.iter().any(|step| matches!(step, ExecutionStep::Project { .. })) {
-        let columns = plan.output_columns;
+    for (scanned, (key, value, _)) in state.snapshot_iter().enumerate() {
+        if scanned % DEADLINE_CHECK_INTERVAL == 0 {
+            if limits.cancel.is_some_and(CancelToken::is_cancelled) {
+                return Err("query cancelled".to_string());
+            }
+            if limits.deadline.is_some_and(|d| Instant::now() >= d) {
+                return Err("query timed out".to_string());
+            }
+        }
         
-        // Create dummy row for demo
-        let row = columns.iter()
-            .map(|col| Value::Text(format!("Value for {}", col)))
-            .collect();
-            
-        let rows = vec![row];
+        // Other tables and non-string values are not rows
+        let Some(id) = key.strip_prefix(prefix.as_slice()) else {
+            continue;
+        };
+        let Ok(bytes) = value.as_string() else {
+            continue;
+        };
         
-        // CRITICAL FIX: Fixed moved value error by cloning rows before use
-        Ok(QueryResult::Rows {
-            columns,
-            rows: rows.clone(),
-            affected_rows: rows.len(),
+        // Plain strings that merely look like encoded rows stay plain
+        let mut columns = vec![("key".to_string(), bytes_value(id))];
+        match is_encoded_row(&bytes).then(|| decode_row(&bytes)) {
+            Some(Ok(decoded)) => columns.extend(decoded.into_iter().filter(|(name, _)| name != "key")),
+            _ => columns.push(("value".to_string(), bytes_value(&bytes))),
+        }
+        let row = Row { columns, table: Some(name.clone()) };
+        if filter.map_or(Ok(true), |filter| filter.matches(&row))? && visit(row)?.is_break() {
+            break;
+        }
+    }
+    
+    Ok(())
+}
+
+/// Stored key of the row with id `id` (integers use their decimal form)
+fn row_key(table: &str, id: &Value) -> Result<Vec<u8>, String> {
+    let mut key = table_prefix(table);
+    match id {
+        Value::Text(id) => key.extend_from_slice(id.as_bytes()),
+        Value::Binary(id) => key.extend_from_slice(id),
+        Value::Integer(n) => key.extend_from_slice(n.to_string().as_bytes()),
+        other => return Err(format!("invalid row key: {:?}", other)),
+    }
+    Ok(key)
+}
+
+/// A row's stored columns after an UPDATE - assigned columns must already exist in
+/// the row, and the key can't change
+fn assign(row: &Row, assignments: &[(String, Literal)]) -> Result<Vec<(String, Value)>, String> {
+    let mut columns: Vec<(String, Value)> = row.columns.iter()
+        .filter(|(name, _)| name != "key")
+        .cloned()
+        .collect();
+    for (column, literal) in assignments {
+        if column == "key" {
+            return Err("column key cannot be updated - it identifies the row".to_string());
+        }
+        let (_, value) = columns.iter_mut()
+            .find(|(name, _)| name == column)
+            .ok_or_else(|| format!("no such column: {}", column))?;
+        *value = literal_value(literal);
+    }
+    Ok(columns)
+}
+
+/// Stored form of a row's columns (all but `key`)
+/// A lone non-NULL `value` column is kept as a plain string, so key/value data stays readable by GET
+fn stored_bytes(columns: &[(String, Value)]) -> Vec<u8> {
+    if let [(name, value)] = columns && name == "value" {
+        match value {
+            Value::Text(text) => return text.as_bytes().to_vec(),
+            Value::Binary(bytes) => return bytes.clone(),
+            Value::Integer(n) => return n.to_string().into_bytes(),
+            Value::Float(x) => return x.to_string().into_bytes(),
+            Value::Null => {}
+        }
+    }
+    let columns: Vec<(&str, Value)> = columns.iter().map(|(name, value)| (name.as_str(), value.clone())).collect();
+    encode_row(&columns)
+}
+
+/// One table of a join and its join column (unqualified)
+struct JoinSide<'a> {
+    table: &'a str,
+    column: &'a str,
+}
+
+impl<'a> JoinSide<'a> {
+    /// Both sides of a join, taking the column part of `table.column` names
+    fn pair(left: &'a str, left_column: &'a str, right: &'a str, right_column: &'a str) -> [Self; 2] {
+        let unqualified = |column: &'a str| column.split_once('.').map_or(column, |(_, name)| name);
+        [
+            JoinSide { table: left, column: unqualified(left_column) },
+            JoinSide { table: right, column: unqualified(right_column) },
+        ]
+    }
+}
+
+/// Inner equi-join of two tables, handing each joined row that passes `filter` to `visit`
+/// The table with fewer keys is loaded into a hash table; the other is streamed against it
+/// Joined rows hold the left table's columns, then the right's, each qualified by table
+fn hash_join(
+    state: &GlobalState,
+    [left, right]: [JoinSide<'_>; 2],
+    filter: Option<&CompiledExpression>,
+    limits: Limits<'_>,
+    mut visit: impl FnMut(Row),
+) -> Result<(), String> {
+    let build_left = state.count_strings_with_prefix(&table_prefix(left.table))
+        <= state.count_strings_with_prefix(&table_prefix(right.table));
+    let (build, probe) = if build_left { (&left, &right) } else { (&right, &left) };
+    
+    // NULL join keys never match, so those rows are left out of the table
+    let mut built: HashMap<Vec<u8>, Vec<Row>> = HashMap::new();
+    scan_rows(state, build.table, None, limits, |row| {
+        if let Some(key) = join_key(row.get(build.column)?) {
+            built.entry(key).or_default().push(row.qualified(build.table));
+        }
+        Ok(ControlFlow::Continue(()))
+    })?;
+    
+    scan_rows(state, probe.table, None, limits, |row| {
+        let Some(matches) = join_key(row.get(probe.column)?).and_then(|key| built.get(&key)) else {
+            return Ok(ControlFlow::Continue(()));
+        };
+        let row = row.qualified(probe.table);
+        for other in matches {
+            let (first, second) = if build_left { (other, &row) } else { (&row, other) };
+            let joined = Row {
+                columns: first.columns.iter().chain(&second.columns).cloned().collect(),
+                table: None,
+            };
+            if filter.map_or(Ok(true), |filter| filter.matches(&joined))? {
+                visit(joined);
+            }
+        }
+        Ok(ControlFlow::Continue(()))
+    })
+}
+
+/// Hash key for a join column - numbers and their text form join equal, NULL joins nothing
+fn join_key(value: &Value) -> Option<Vec<u8>> {
+    match value {
+        Value::Null => None,
+        Value::Integer(n) => Some(n.to_string().into_bytes()),
+        Value::Float(x) => Some(x.to_string().into_bytes()),
+        Value::Text(text) => Some(text.as_bytes().to_vec()),
+        Value::Binary(bytes) => Some(bytes.clone()),
+    }
+}
+
+/// Output column names - `*` stands for every column seen, in first-seen order
+fn expand_projection(projection: &[String], rows: &[Row]) -> Vec<String> {
+    let mut columns: Vec<String> = Vec::new();
+    for name in projection {
+        if name != "*" {
+            columns.push(name.clone());
+            continue;
+        }
+        for (name, _) in rows.iter().flat_map(|row| &row.columns) {
+            if !columns.contains(name) {
+                columns.push(name.clone());
+            }
+        }
+    }
+    columns
+}
+
+/// Stored bytes as Text when they are UTF-8, Binary otherwise
+fn bytes_value(bytes: &[u8]) -> Value {
+    match std::str::from_utf8(bytes) {
+        Ok(text) => Value::Text(text.to_string()),
+        Err(_) => Value::Binary(bytes.to_vec()),
+    }
+}
+
+impl Row {
+    /// Values serialized with type tags and lengths, so equal tuples (and only those) encode equal
+    fn encode_values(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+        for (_, value) in &self.columns {
+            let (tag, bytes): (u8, &[u8]) = match value {
+                Value::Null => (0, &[]),
+                Value::Integer(n) => (1, &n.to_le_bytes()),
+                Value::Float(x) => (2, &x.to_le_bytes()),
+                Value::Text(text) => (3, text.as_bytes()),
+                Value::Binary(bytes) => (4, bytes),
+            };
+            buf.push(tag);
+            buf.extend_from_slice(&(bytes.len() as u64).to_le_bytes());
+            buf.extend_from_slice(bytes);
+        }
+        buf
+    }
+    
+    /// The same row with every column named `table.column`
+    fn qualified(self, table: &str) -> Row {
+        Row {
+            columns: self.columns.into_iter()
+                .map(|(name, value)| (format!("{}.{}", table, name), value))
+                .collect(),
+            table: None,
+        }
+    }
+    
+    /// Column value (missing columns read as NULL, the same as stored NULLs)
+    /// An exact name wins; otherwise `x` finds a unique `table.x` in joined rows and
+    /// `table.x` finds `x` in rows of that table. A joined table's missing column is NULL
+    /// rather than the other table's column of the same name
+    fn get(&self, name: &str) -> Result<&Value, String> {
+        if let Some((_, value)) = self.columns.iter().find(|(column, _)| column == name) {
+            return Ok(value);
+        }
+        
+        if let Some((table, unqualified)) = name.split_once('.') {
+            return match &self.table {
+                Some(own) if **own == *table => self.get(unqualified),
+                Some(_) => Err(format!("no such column: {}", name)),
+                None => Ok(&Value::Null),
+            };
+        }
+        let mut matches = self.columns.iter()
+            .filter(|(column, _)| column.split_once('.').is_some_and(|(_, column)| column == name));
+        match (matches.next(), matches.next()) {
+            (Some(_), Some(_)) => Err(format!("ambiguous column name: {}", name)),
+            (Some((_, value)), None) => Ok(value),
+            (None, _) => Ok(&Value::Null),
+        }
+    }
+}
+
+impl CompiledExpression {
+    /// Whether `row` satisfies the expression (comparisons with NULL are false)
+    fn matches(&self, row: &Row) -> Result<bool, String> {
+        Ok(match self {
+            CompiledExpression::Constant(value) => *value,
+            CompiledExpression::ColumnEqValue { column, value } => {
+                compare_values(row.get(column)?, &literal_value(value)) == Some(Ordering::Equal)
+            }
+            CompiledExpression::ColumnCompare { column, op, value } => {
+                compare_values(row.get(column)?, &literal_value(value)).is_some_and(|ord| op.holds(ord))
+            }
+            CompiledExpression::ColumnIn { column, values, negated } => {
+                // NULL is in no list, and NOT IN a list holding NULL is never true
+                match values.contains(row.get(column)?) {
+                    None => false,
+                    Some(found) if *negated => !found && !values.has_null,
+                    Some(found) => found,
+                }
+            }
+            CompiledExpression::ColumnBetween { column, low, high, negated } => {
+                let value = row.get(column)?;
+                let ge_low = compare_values(value, &literal_value(low)).map(Ordering::is_ge);
+                let le_high = compare_values(value, &literal_value(high)).map(Ordering::is_le);
+                between(ge_low, le_high, *negated) == Some(true)
+            }
+            CompiledExpression::ColumnIsNull { column, negated } => matches!(row.get(column)?, Value::Null) != *negated,
+            CompiledExpression::Like { column, matcher, negated, .. } => {
+                like_text(row.get(column)?).is_some_and(|text| matcher.matches(&text) != *negated)
+            }
+            CompiledExpression::And { left, right } => left.matches(row)? && right.matches(row)?,
+            CompiledExpression::Or { left, right } => left.matches(row)? || right.matches(row)?,
+            CompiledExpression::Complex { expr } => is_truthy(&evaluate(expr, row)?),
         })
-    } else {
-        // For modification queries
-        Ok(QueryResult::Modified {
-            affected_rows: 1, // Placeholder
+    }
+}
+
+impl ValueSet {
+    /// Hash the literals of an IN list
+    fn new(literals: Vec<Literal>) -> Self {
+        let mut set = ValueSet {
+            integers: HashSet::new(),
+            floats: HashSet::new(),
+            numeric_strings: HashSet::new(),
+            strings: HashSet::new(),
+            has_null: false,
+            literals: Vec::new(),
+        };
+        for literal in &literals {
+            match literal {
+                Literal::Null => set.has_null = true,
+                Literal::Integer(n) => {
+                    set.integers.insert(*n);
+                }
+                Literal::Float(x) => set.floats.extend(number_key(*x)),
+                Literal::String(text) => {
+                    set.numeric_strings.extend(numeric(&Value::Text(text.clone())).and_then(number_key));
+                    set.strings.insert(text.as_bytes().to_vec());
+                }
+            }
+        }
+        set.literals = literals;
+        set
+    }
+    
+    /// Whether a literal in the set equals `value` (None for NULL)
+    fn contains(&self, value: &Value) -> Option<bool> {
+        let number = |x: f64| {
+            number_key(x).is_some_and(|key| self.floats.contains(&key))
+                || (x.fract() == 0.0 && self.integers.contains(&(x as i64)))
+        };
+        Some(match value {
+            Value::Null => return None,
+            Value::Integer(n) => {
+                let key = number_key(*n as f64);
+                self.integers.contains(n)
+                    || key.is_some_and(|key| self.floats.contains(&key) || self.numeric_strings.contains(&key))
+            }
+            Value::Float(x) => number(*x) || number_key(*x).is_some_and(|key| self.numeric_strings.contains(&key)),
+            Value::Text(text) => self.strings.contains(text.as_bytes()) || numeric(value).is_some_and(number),
+            Value::Binary(bytes) => self.strings.contains(bytes),
         })
     }
 }
 
+/// Text a value is matched as by LIKE - numbers use their decimal form, NULL has none
+/// Matching is case-sensitive, as text comparison is
+fn like_text(value: &Value) -> Option<std::borrow::Cow<'_, str>> {
+    match value {
+        Value::Null => None,
+        Value::Integer(n) => Some(n.to_string().into()),
+        Value::Float(x) => Some(x.to_string().into()),
+        Value::Text(text) => Some(text.as_str().into()),
+        Value::Binary(bytes) => Some(String::from_utf8_lossy(bytes)),
+    }
+}
+
+/// Hash key for a number - -0.0 and 0.0 share one; NaN equals nothing
+fn number_key(x: f64) -> Option<u64> {
+    (!x.is_nan()).then(|| (x + 0.0).to_bits())
+}
+
+/// List items if every one is a literal
+fn literal_list(list: &[Expr]) -> Option<Vec<Literal>> {
+    list.iter()
+        .map(|item| match item {
+            Expr::Literal(literal) => Some(literal.clone()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// [NOT] BETWEEN from the two bound checks (None = unknown, from NULL)
+fn between(ge_low: Option<bool>, le_high: Option<bool>, negated: bool) -> Option<bool> {
+    // Either bound failing settles it even if the other is unknown
+    let inside = match (ge_low, le_high) {
+        (Some(false), _) | (_, Some(false)) => Some(false),
+        (Some(true), Some(true)) => Some(true),
+        _ => None,
+    };
+    inside.map(|inside| inside != negated)
+}
+
+impl ComparisonOp {
+    /// SQL spelling of the operator
+    fn symbol(self) -> &'static str {
+        match self {
+            ComparisonOp::Eq => "=",
+            ComparisonOp::Ne => "<>",
+            ComparisonOp::Lt => "<",
+            ComparisonOp::Lte => "<=",
+            ComparisonOp::Gt => ">",
+            ComparisonOp::Gte => ">=",
+        }
+    }
+    
+    /// Whether an ordering satisfies the operator
+    fn holds(self, ord: Ordering) -> bool {
+        match self {
+            ComparisonOp::Eq => ord.is_eq(),
+            ComparisonOp::Ne => ord.is_ne(),
+            ComparisonOp::Lt => ord.is_lt(),
+            ComparisonOp::Lte => ord.is_le(),
+            ComparisonOp::Gt => ord.is_gt(),
+            ComparisonOp::Gte => ord.is_ge(),
+        }
+    }
+}
+
+/// Literal as a runtime value
+fn literal_value(literal: &Literal) -> Value {
+    match literal {
+        Literal::Null => Value::Null,
+        Literal::Integer(n) => Value::Integer(*n),
+        Literal::Float(f) => Value::Float(*f),
+        Literal::String(s) => Value::Text(s.clone()),
+    }
+}
+
+/// Numeric view of a value - text that parses as a number counts
+fn numeric(value: &Value) -> Option<f64> {
+    match value {
+        Value::Integer(n) => Some(*n as f64),
+        Value::Float(f) => Some(*f),
+        Value::Text(text) => text.trim().parse::<f64>().ok(),
+        Value::Null | Value::Binary(_) => None,
+    }
+}
+
+/// Order two values (None if either is NULL or they can't be compared)
+/// Numbers compare numerically, including against numeric text; otherwise
+/// text and binary compare bytewise
+fn compare_values(a: &Value, b: &Value) -> Option<Ordering> {
+    match (a, b) {
+        (Value::Null, _) | (_, Value::Null) => None,
+        (Value::Integer(x), Value::Integer(y)) => Some(x.cmp(y)),
+        (Value::Text(x), Value::Text(y)) => Some(x.cmp(y)),
+        (Value::Integer(_) | Value::Float(_), _) | (_, Value::Integer(_) | Value::Float(_)) => {
+            numeric(a)?.partial_cmp(&numeric(b)?)
+        }
+        (Value::Text(x), Value::Binary(y)) => Some(x.as_bytes().cmp(y)),
+        (Value::Binary(x), Value::Text(y)) => Some(x.as_slice().cmp(y.as_bytes())),
+        (Value::Binary(x), Value::Binary(y)) => Some(x.cmp(y)),
+    }
+}
+
+/// SQL truthiness - non-zero numbers (and numeric text) are true, NULL is false
+fn is_truthy(value: &Value) -> bool {
+    numeric(value).is_some_and(|n| n != 0.0)
+}
+
+/// Boolean as a SQL integer
+fn bool_value(value: bool) -> Value {
+    Value::Integer(value as i64)
+}
+
+/// Evaluate an expression against a row - the slow path for uncompiled filters
+fn evaluate(expr: &Expr, row: &Row) -> Result<Value, String> {
+    match expr {
+        Expr::Column(name) => row.get(name).cloned(),
+        Expr::Literal(literal) => Ok(literal_value(literal)),
+        Expr::UnaryOp { op, expr } => {
+            let value = evaluate(expr, row)?;
+            Ok(match (op, value) {
+                (_, Value::Null) => Value::Null,
+                (UnaryOperator::Not, value) => bool_value(!is_truthy(&value)),
+                (UnaryOperator::Negate, Value::Integer(n)) => n.checked_neg().map_or(Value::Null, Value::Integer),
+                (UnaryOperator::Negate, value) => numeric(&value).map_or(Value::Null, |n| Value::Float(-n)),
+            })
+        }
+        Expr::BinaryOp { left, op, right } => {
+            let (left, right) = (evaluate(left, row)?, evaluate(right, row)?);
+            let ordering = || compare_values(&left, &right);
+            Ok(match op {
+                BinaryOperator::And => bool_value(is_truthy(&left) && is_truthy(&right)),
+                BinaryOperator::Or => bool_value(is_truthy(&left) || is_truthy(&right)),
+                BinaryOperator::Equal => ordering().map_or(Value::Null, |ord| bool_value(ord.is_eq())),
+                BinaryOperator::NotEqual => ordering().map_or(Value::Null, |ord| bool_value(ord.is_ne())),
+                BinaryOperator::LessThan => ordering().map_or(Value::Null, |ord| bool_value(ord.is_lt())),
+                BinaryOperator::LessThanOrEqual => ordering().map_or(Value::Null, |ord| bool_value(ord.is_le())),
+                BinaryOperator::GreaterThan => ordering().map_or(Value::Null, |ord| bool_value(ord.is_gt())),
+                BinaryOperator::GreaterThanOrEqual => ordering().map_or(Value::Null, |ord| bool_value(ord.is_ge())),
+                BinaryOperator::Add | BinaryOperator::Subtract | BinaryOperator::Multiply | BinaryOperator::Divide => {
+                    arithmetic(op, &left, &right)
+                }
+                BinaryOperator::Like | BinaryOperator::NotLike => match (like_text(&left), like_text(&right)) {
+                    (Some(text), Some(pattern)) => {
+                        let matched = LikePattern::new(&pattern).matches(&text);
+                        bool_value(matched == matches!(op, BinaryOperator::Like))
+                    }
+                    _ => Value::Null,
+                },
+            })
+        }
+        Expr::Function { name, .. } => Err(format!("unsupported function: {}", name)),
+        Expr::In { expr, list, negated } => {
+            // True on a match; otherwise NULL if the value or any item is NULL
+            let value = evaluate(expr, row)?;
+            let mut unknown = matches!(value, Value::Null);
+            for item in list {
+                match compare_values(&value, &evaluate(item, row)?) {
+                    Some(Ordering::Equal) => return Ok(bool_value(!*negated)),
+                    Some(_) => {}
+                    None => unknown = true,
+                }
+            }
+            Ok(if unknown { Value::Null } else { bool_value(*negated) })
+        }
+        Expr::IsNull { expr, negated } => Ok(bool_value(matches!(evaluate(expr, row)?, Value::Null) != *negated)),
+        Expr::Between { expr, low, high, negated } => {
+            let value = evaluate(expr, row)?;
+            let ge_low = compare_values(&value, &evaluate(low, row)?).map(Ordering::is_ge);
+            let le_high = compare_values(&value, &evaluate(high, row)?).map(Ordering::is_le);
+            Ok(between(ge_low, le_high, *negated).map_or(Value::Null, bool_value))
+        }
+    }
+}
+
+/// Integer arithmetic when both sides are integers, float otherwise
+/// Overflow, division by zero and non-numeric operands give NULL
+fn arithmetic(op: &BinaryOperator, left: &Value, right: &Value) -> Value {
+    if let (Value::Integer(x), Value::Integer(y)) = (left, right) {
+        let result = match op {
+            BinaryOperator::Add => x.checked_add(*y),
+            BinaryOperator::Subtract => x.checked_sub(*y),
+            BinaryOperator::Multiply => x.checked_mul(*y),
+            _ => x.checked_div(*y),
+        };
+        return result.map_or(Value::Null, Value::Integer);
+    }
+    
+    let (Some(x), Some(y)) = (numeric(left), numeric(right)) else {
+        return Value::Null;
+    };
+    match op {
+        BinaryOperator::Add => Value::Float(x + y),
+        BinaryOperator::Subtract => Value::Float(x - y),
+        BinaryOperator::Multiply => Value::Float(x * y),
+        _ if y == 0.0 => Value::Null,
+        _ => Value::Float(x / y),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::query::parser;
+    use crate::persistence::aof::AppendOnlyFile;
+    use crate::query::{parser, QueryProcessor};
+    use crate::storage::memory::MemTable;
+    
+    /// Query processor over a fresh state whose AOF lives in a temporary directory
+    struct Fixture {
+        _dir: tempfile::TempDir,
+        state: Arc<GlobalState>,
+        processor: QueryProcessor,
+    }
+    
+    impl Fixture {
+        fn new() -> Self {
+            let dir = tempfile::tempdir().unwrap();
+            let aof = AppendOnlyFile::new(dir.path().join("query.aof")).unwrap();
+            let state = Arc::new(GlobalState::new(Arc::new(MemTable::new()), aof));
+            Self { _dir: dir, state: state.clone(), processor: QueryProcessor::new(state) }
+        }
+        
+        /// Run a statement that must succeed
+        fn run(&self, query: &str) -> QueryResult {
+            self.processor.execute(query).unwrap_or_else(|e| panic!("{}: {}", query, e))
+        }
+        
+        /// Output columns and rows of a SELECT, each row formatted with `{:?}` and sorted
+        fn select(&self, query: &str) -> (Vec<String>, Vec<String>) {
+            match self.run(query) {
+                QueryResult::Rows { columns, rows, .. } => {
+                    let mut rows: Vec<String> = rows.iter().map(|row| format!("{:?}", row)).collect();
+                    rows.sort();
+                    (columns, rows)
+                }
+                _ => panic!("Expected rows"),
+            }
+        }
+        
+        /// Sorted keys of the `table` rows matching `filter`, as `("1") ("2")`
+        fn keys(&self, table: &str, filter: &str) -> String {
+            match self.run(&format!("SELECT key FROM {} WHERE {}", table, filter)) {
+                QueryResult::Rows { rows, .. } => {
+                    let mut keys: Vec<String> = rows.iter().map(|row| format!("{:?}", row[0])).collect();
+                    keys.sort();
+                    keys.join(" ").replace("Text", "")
+                }
+                _ => panic!("Expected rows"),
+            }
+        }
+        
+        /// Rows changed by a write
+        fn affected(&self, query: &str) -> usize {
+            match self.run(query) {
+                QueryResult::Modified { affected_rows } => affected_rows,
+                _ => panic!("Expected a modification"),
+            }
+        }
+    }
     
     #[test]
     fn test_execution_plan() {
         // Create a simple SELECT query
         let query = parser::ParsedQuery::Select {
+            distinct: false,
             columns: vec!["id".to_string(), "name".to_string()],
             table: "users".to_string(),
+            join: None,
             where_clause: None,
             limit: None,
+            offset: 0,
         };
         
         // Create execution plan
@@ -392,4 +1410,334 @@ mod tests {
             _ => panic!("Expected Project step"),
         }
     }
-}
\ No newline at end of file
+    
+    #[test]
+    fn test_scan_filter_and_deadline() {
+        use crate::query::parser::{BinaryOperator, WhereClause};
+        use std::time::Duration;
+        
+        let fx = Fixture::new();
+        let state = &fx.state;
+        for n in 0..100 {
+            state.set(format!("users:{}", n).as_bytes(), n.to_string().into_bytes(), None).unwrap();
+        }
+        state.set(b"other:1", b"5".to_vec(), None).unwrap();
+        
+        // value >= 95 - numeric text compares as a number
+        let select = parser::ParsedQuery::Select {
+            distinct: false,
+            columns: vec!["key".to_string()],
+            table: "users".to_string(),
+            join: None,
+            where_clause: Some(WhereClause {
+                expr: Expr::BinaryOp {
+                    left: Box::new(Expr::Column("value".to_string())),
+                    op: BinaryOperator::GreaterThanOrEqual,
+                    right: Box::new(Expr::Literal(Literal::Integer(95))),
+                },
+            }),
+            limit: None,
+            offset: 0,
+        };
+        let plan = ExecutionPlan::from_parsed_query(select);
+        match execute_plan(plan.clone(), state.clone(), None, None).unwrap() {
+            QueryResult::Rows { columns, rows, .. } => {
+                assert_eq!(columns, vec!["key".to_string()]);
+                let mut keys: Vec<String> = rows.iter()
+                    .map(|row| match &row[0] { Value::Text(key) => key.clone(), other => panic!("{:?}", other) })
+                    .collect();
+                keys.sort();
+                assert_eq!(keys, vec!["95", "96", "97", "98", "99"]);
+            }
+            _ => panic!("Expected rows"),
+        }
+        
+        // DISTINCT dedupes projected values before LIMIT
+        state.set(b"cities:1", b"Paris".to_vec(), None).unwrap();
+        state.set(b"cities:2", b"Oslo".to_vec(), None).unwrap();
+        state.set(b"cities:3", b"Paris".to_vec(), None).unwrap();
+        let distinct = ExecutionPlan::from_parsed_query(parser::ParsedQuery::Select {
+            distinct: true,
+            columns: vec!["value".to_string()],
+            table: "cities".to_string(),
+            join: None,
+            where_clause: None,
+            limit: Some(2),
+            offset: 0,
+        });
+        match execute_plan(distinct, state.clone(), None, None).unwrap() {
+            QueryResult::Rows { rows, .. } => {
+                let mut cities: Vec<String> = rows.iter().map(|row| format!("{:?}", row)).collect();
+                cities.sort();
+                assert_eq!(cities, vec![r#"[Text("Oslo")]"#, r#"[Text("Paris")]"#]);
+            }
+            _ => panic!("Expected rows"),
+        }
+        
+        // A deadline that has already passed stops the scan
+        let expired = Instant::now() - Duration::from_millis(1);
+        let err = execute_plan(plan.clone(), state.clone(), Some(expired), None).err();
+        assert_eq!(err.as_deref(), Some("query timed out"));
+        
+        // So does a cancelled token, and cancellation is reported first
+        let cancel = CancelToken::new();
+        cancel.cancel();
+        let err = execute_plan(plan, state.clone(), Some(expired), Some(&cancel)).err();
+        assert_eq!(err.as_deref(), Some("query cancelled"));
+        
+        // COUNT(*) plans a single counting step, with or without a filter
+        let count = |where_clause: Option<WhereClause>| {
+            let plan = ExecutionPlan::from_parsed_query(parser::ParsedQuery::Select {
+                distinct: false,
+                columns: vec!["COUNT(*)".to_string()],
+                table: "users".to_string(),
+                join: None,
+                where_clause,
+                limit: None,
+                offset: 0,
+            });
+            assert!(matches!(plan.steps.as_slice(), [ExecutionStep::Count { .. }]));
+            match execute_plan(plan, state.clone(), None, None).unwrap() {
+                QueryResult::Rows { columns, rows, .. } => {
+                    assert_eq!(columns, vec!["COUNT(*)".to_string()]);
+                    match rows.as_slice() {
+                        [row] => match row.as_slice() {
+                            [Value::Integer(n)] => *n,
+                            other => panic!("{:?}", other),
+                        },
+                        other => panic!("{:?}", other),
+                    }
+                }
+                _ => panic!("Expected rows"),
+            }
+        };
+        assert_eq!(count(None), 100);
+        assert_eq!(count(Some(WhereClause {
+            expr: Expr::BinaryOp {
+                left: Box::new(Expr::Column("value".to_string())),
+                op: BinaryOperator::LessThan,
+                right: Box::new(Expr::Literal(Literal::Integer(10))),
+            },
+        })), 10);
+    }
+    
+    #[test]
+    fn test_explain() {
+        use crate::query::parser::{BinaryOperator, WhereClause};
+        
+        let column_filter = |op, right| Some(WhereClause {
+            expr: Expr::BinaryOp {
+                left: Box::new(Expr::Column("value".to_string())),
+                op,
+                right: Box::new(right),
+            },
+        });
+        let explain = |where_clause| {
+            let plan = ExecutionPlan::from_parsed_query(parser::ParsedQuery::Explain(Box::new(parser::ParsedQuery::Select {
+                distinct: false,
+                columns: vec!["key".to_string()],
+                table: "users".to_string(),
+                join: None,
+                where_clause,
+                limit: Some(5),
+                offset: 0,
+            })));
+            match plan.steps.as_slice() {
+                [ExecutionStep::Explain { plan }] => plan.describe(),
+                other => panic!("{:?}", other),
+            }
+        };
+        
+        // Column = literal compiles to the optimized form
+        assert_eq!(explain(column_filter(BinaryOperator::Equal, Expr::Literal(Literal::String("a'b".to_string())))), vec![
+            "SCAN TABLE users",
+            "FILTER ColumnEqValue(value = 'a''b')",
+            "PROJECT key",
+            "LIMIT 5",
+        ]);
+        
+        // Anything else falls back to runtime evaluation
+        let sum = Expr::BinaryOp {
+            left: Box::new(Expr::Column("key".to_string())),
+            op: BinaryOperator::Add,
+            right: Box::new(Expr::Literal(Literal::Integer(1))),
+        };
+        assert_eq!(explain(column_filter(BinaryOperator::LessThan, sum))[1], "FILTER Complex((value < (key + 1)))");
+    }
+    
+    #[test]
+    fn test_hash_join() {
+        let fx = Fixture::new();
+        let state = &fx.state;
+        for (key, value) in [("users:2", "bob"), ("users:3", "cy")] {
+            state.set(key.as_bytes(), value.as_bytes().to_vec(), None).unwrap();
+        }
+        // Only users have a `name` column
+        let ann = encode_row(&[("value", Value::Text("ann".to_string())), ("name", Value::Text("Ann Lee".to_string()))]);
+        state.set(b"users:1", ann, None).unwrap();
+        // Orders point at their user's id
+        for (key, value) in [("orders:10", "1"), ("orders:11", "1"), ("orders:12", "2"), ("orders:13", "9")] {
+            state.set(key.as_bytes(), value.as_bytes().to_vec(), None).unwrap();
+        }
+        
+        let (columns, rows) = fx.select("SELECT users.value, orders.key FROM users JOIN orders ON users.key = orders.value");
+        assert_eq!(columns, vec!["users.value".to_string(), "orders.key".to_string()]);
+        assert_eq!(rows, vec![
+            r#"[Text("ann"), Text("10")]"#,
+            r#"[Text("ann"), Text("11")]"#,
+            r#"[Text("bob"), Text("12")]"#,
+        ]);
+        
+        // WHERE filters joined rows; COUNT(*) counts them
+        let (_, rows) = fx.select("SELECT orders.key FROM orders JOIN users ON orders.value = users.key WHERE users.value = 'bob'");
+        assert_eq!(rows, vec![r#"[Text("12")]"#]);
+        let (_, rows) = fx.select("SELECT COUNT(*) FROM users JOIN orders ON users.key = orders.value");
+        assert_eq!(rows, vec!["[Integer(3)]"]);
+        
+        // Both tables have a `value` column
+        assert!(fx.processor.execute("SELECT value FROM users JOIN orders ON users.key = orders.value").is_err());
+        
+        // A qualifier picks its own table's column, never the other table's
+        let (_, rows) = fx.select("SELECT users.name, orders.name FROM users JOIN orders ON users.key = orders.value WHERE orders.key = '10'");
+        assert_eq!(rows, vec![r#"[Text("Ann Lee"), Null]"#]);
+        let (_, rows) = fx.select("SELECT users.value FROM users WHERE users.key = '2'");
+        assert_eq!(rows, vec![r#"[Text("bob")]"#]);
+        assert!(fx.processor.execute("SELECT orders.value FROM users").is_err());
+    }
+    
+    #[test]
+    fn test_update_and_delete() {
+        use std::time::Duration;
+        
+        let fx = Fixture::new();
+        let state = &fx.state;
+        state.set(b"users:1", b"ann".to_vec(), Some(Duration::from_secs(100))).unwrap();
+        state.set(b"users:2", b"bob".to_vec(), None).unwrap();
+        state.set(b"users:3", b"bob".to_vec(), None).unwrap();
+        state.set(b"other:1", b"bob".to_vec(), None).unwrap();
+        let affected = |query: &str| fx.affected(query);
+        
+        assert_eq!(affected("UPDATE users SET value = 'Bob' WHERE value = 'bob'"), 2);
+        assert_eq!(state.get(b"users:2").as_deref(), Some(&b"Bob"[..]));
+        assert_eq!(state.get(b"other:1").as_deref(), Some(&b"bob"[..]));
+        assert_eq!(affected("UPDATE users SET value = 7 WHERE key = '1'"), 1);
+        assert_eq!(state.get(b"users:1").as_deref(), Some(&b"7"[..]));
+        assert!(state.get_full(b"users:1").unwrap().remaining_ttl.is_some());
+        
+        // Columns are checked against the table schema
+        assert!(fx.processor.execute("UPDATE users SET age = 3").is_err());
+        assert!(fx.processor.execute("UPDATE users SET key = '9'").is_err());
+        
+        assert_eq!(affected("DELETE FROM users WHERE value = 'Bob'"), 2);
+        assert_eq!(affected("DELETE FROM users WHERE value = 'Bob'"), 0);
+        assert_eq!(affected("DELETE FROM users"), 1);
+        assert_eq!(state.count_strings_with_prefix(b"users:"), 0);
+        assert!(state.get(b"other:1").is_some());
+    }
+    
+    #[test]
+    fn test_insert_encoded_rows() {
+        let fx = Fixture::new();
+        let state = &fx.state;
+        assert!(matches!(
+            fx.run("INSERT INTO people (key, name, age) VALUES (1, 'Ann', 31), (2, 'Bob', NULL)"),
+            QueryResult::Modified { affected_rows: 2 }
+        ));
+        assert!(is_encoded_row(&state.get(b"people:1").unwrap()));
+        
+        // Typed columns come back typed
+        assert_eq!(fx.select("SELECT name, age FROM people WHERE age > 30").1, vec![r#"[Text("Ann"), Integer(31)]"#]);
+        fx.run("UPDATE people SET age = 40 WHERE name = 'Bob'");
+        assert_eq!(fx.keys("people", "age = 40"), r#"("2")"#);
+        assert!(fx.processor.execute("UPDATE people SET email = 'x'").is_err_and(|e| e.to_string().contains("no such column")));
+        
+        // Duplicate keys fail the whole statement; key/value rows stay plain strings
+        let err = fx.processor.execute("INSERT INTO people (key, name) VALUES (3, 'Cy'), (1, 'Dup')").err();
+        assert!(err.is_some_and(|e| e.to_string().contains("duplicate key: 1")));
+        assert!(state.get(b"people:3").is_none());
+        fx.run("INSERT INTO notes VALUES ('a', 'hello')");
+        assert_eq!(state.get(b"notes:a").as_deref(), Some(&b"hello"[..]));
+    }
+    
+    #[test]
+    fn test_limit_offset() {
+        let fx = Fixture::new();
+        for n in 0..10 {
+            fx.state.set(format!("items:{}", n).as_bytes(), vec![b'a' + n % 3], None).unwrap();
+        }
+        let select = |query: &str| fx.select(query).1;
+        
+        // Pages cover every row exactly once
+        let mut paged: Vec<String> = (0..4).flat_map(|page| select(&format!("SELECT key FROM items LIMIT {}, 3", page * 3))).collect();
+        assert_eq!(paged.len(), 10);
+        paged.sort();
+        paged.dedup();
+        assert_eq!(paged.len(), 10);
+        
+        assert_eq!(select("SELECT key FROM items LIMIT 3 OFFSET 8").len(), 2);
+        assert!(select("SELECT key FROM items LIMIT 3 OFFSET 50").is_empty());
+        assert!(select("SELECT key FROM items LIMIT 0").is_empty());
+        
+        // After DISTINCT the offset skips distinct rows
+        assert_eq!(select("SELECT DISTINCT value FROM items LIMIT 5 OFFSET 1").len(), 2);
+    }
+    
+    #[test]
+    fn test_in_and_between() {
+        let fx = Fixture::new();
+        fx.run("INSERT INTO users (key, status, age) VALUES \
+            (1, 'a', 17), (2, 'b', 18), (3, 'c', 65.5), (4, NULL, 40), (5, '7', NULL)");
+        let keys = |filter: &str| fx.keys("users", filter);
+        
+        assert_eq!(keys("status IN ('a', 'b', 'z')"), r#"("1") ("2")"#);
+        assert_eq!(keys("status NOT IN ('a', 'b')"), r#"("3") ("5")"#);
+        assert_eq!(keys("status IN (7)"), r#"("5")"#);
+        assert_eq!(keys("status NOT IN ('a', NULL)"), "");
+        assert_eq!(keys("age BETWEEN 18 AND 65"), r#"("2") ("4")"#);
+        assert_eq!(keys("age NOT BETWEEN 18 AND 65"), r#"("1") ("3")"#);
+        
+        // Non-literal lists take the slow path and agree with it
+        assert_eq!(keys("age IN (17, 20 + 20)"), r#"("1") ("4")"#);
+        assert_eq!(keys("age + 0 BETWEEN 18 AND 65"), r#"("2") ("4")"#);
+        
+        let select = parser::parse_query("SELECT key FROM users WHERE status IN ('a') AND age BETWEEN 1 AND 2").unwrap();
+        let filter = ExecutionPlan::from_parsed_query(select).describe().remove(1);
+        assert_eq!(filter, "FILTER And(ColumnIn(status IN ('a')), ColumnBetween(age BETWEEN 1 AND 2))");
+    }
+    
+    #[test]
+    fn test_like() {
+        let fx = Fixture::new();
+        fx.run("INSERT INTO pages (key, path, hits) VALUES \
+            (1, '/foo/bar', 120), (2, '/Foo', 12), (3, '/foo_x', 5), (4, NULL, 125)");
+        let keys = |filter: &str| fx.keys("pages", filter);
+        
+        assert_eq!(keys("path LIKE '/foo%'"), r#"("1") ("3")"#);
+        assert_eq!(keys("path NOT LIKE '/foo%'"), r#"("2")"#);
+        assert_eq!(keys("path LIKE '/foo\\_%'"), r#"("3")"#);
+        assert_eq!(keys("hits LIKE '12_'"), r#"("1") ("4")"#);
+        
+        // Expressions on the left take the slow path
+        assert_eq!(keys("hits + 0 LIKE '12%'"), r#"("1") ("2") ("4")"#);
+    }
+    
+    #[test]
+    fn test_is_null() {
+        let fx = Fixture::new();
+        
+        // Row 1 stores email as NULL, row 2 has no email column, row 3 has one
+        fx.run("INSERT INTO users (key, email) VALUES (1, NULL)");
+        fx.run("INSERT INTO users (key, name) VALUES (2, 'bob')");
+        fx.run("INSERT INTO users (key, email) VALUES (3, 'c@x')");
+        let keys = |filter: &str| fx.keys("users", filter);
+        assert_eq!(keys("email IS NULL"), r#"("1") ("2")"#);
+        assert_eq!(keys("email IS NOT NULL"), r#"("3")"#);
+        assert_eq!(keys("NOT email IS NULL AND name IS NULL"), r#"("3")"#);
+        
+        // Comparisons with NULL are never true, so IS NULL is the only way to find these rows
+        assert_eq!(keys("email = NULL"), "");
+        
+        // Expressions take the slow path - arithmetic on text is NULL
+        assert_eq!(keys("email + 1 IS NULL"), r#"("1") ("2") ("3")"#);
+    }
+}
@@ -3,55 +3,159 @@
 
 pub mod parser;
 pub mod executor;
+pub mod cache;
+pub mod cancel;
+pub mod codec;
 
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use crate::core::state::GlobalState;
+use cache::QueryCache;
+use cancel::CancelToken;
+use parser::ParsedQuery;
 
 /// SQL query processor
 pub struct QueryProcessor {
     // Database state
     state: Arc<GlobalState>,
+    
+    // SELECT result cache (None = every query runs)
+    cache: Option<QueryCache>,
 }
 
 impl QueryProcessor {
     /// Create new query processor
     pub fn new(state: Arc<GlobalState>) -> Self {
-        Self { state }
+        Self { state, cache: None }
+    }
+    
+    /// Cache SELECT results - at most `max_entries` of them and `max_bytes` of result data
+    pub fn with_cache(mut self, max_entries: usize, max_bytes: usize) -> Self {
+        self.cache = Some(QueryCache::new(max_entries, max_bytes));
+        self
+    }
+    
+    /// Result cache, if enabled
+    pub fn cache(&self) -> Option<&QueryCache> {
+        self.cache.as_ref()
     }
     
     /// Execute SQL query
     pub fn execute(&self, query: &str) -> Result<QueryResult, QueryError> {
-        // Parse query
-        let parsed = self.parse(query)?;
+        self.run(query, None, None)
+    }
+    
+    /// Execute SQL query, giving up once it has run for `timeout`
+    pub fn execute_with_timeout(&self, query: &str, timeout: Duration) -> Result<QueryResult, QueryError> {
+        self.run(query, Some(Instant::now() + timeout), None)
+    }
+    
+    /// Execute SQL query, giving up after `timeout` (if any) or once `cancel` is cancelled
+    pub fn execute_cancellable(
+        &self,
+        query: &str,
+        timeout: Option<Duration>,
+        cancel: &CancelToken,
+    ) -> Result<QueryResult, QueryError> {
+        self.run(query, timeout.map(|timeout| Instant::now() + timeout), Some(cancel))
+    }
+    
+    /// Execute SQL query on the blocking pool so the connection task stays responsive
+    /// Dropping the returned future - the connection task ended because its client
+    /// disconnected - cancels the query at its next check instead of finishing the scan
+    pub async fn execute_spawned(
+        self: &Arc<Self>,
+        query: String,
+        timeout: Option<Duration>,
+    ) -> Result<QueryResult, QueryError> {
+        let cancel = CancelToken::new();
+        let _cancel_on_drop = cancel.cancel_on_drop();
+        let processor = self.clone();
+        tokio::task::spawn_blocking(move || processor.execute_cancellable(&query, timeout, &cancel))
+            .await
+            .map_err(|e| QueryError::ExecutionError(format!("query task failed: {}", e)))?
+    }
+    
+    /// Parse, plan and execute with an optional deadline and cancellation token
+    fn run(&self, query: &str, deadline: Option<Instant>, cancel: Option<&CancelToken>) -> Result<QueryResult, QueryError> {
+        let Some(cache) = &self.cache else {
+            return self.run_uncached(self.parse(query)?, deadline, cancel);
+        };
+        
+        // Spelling differences (case, whitespace) share a cache entry
+        let normalized = parser::normalize_query(query).map_err(QueryError::ParseError)?;
+        if normalized == FLUSH_QUERY_CACHE {
+            return Ok(QueryResult::Modified { affected_rows: cache.flush() });
+        }
+        if let Some(result) = cache.get(&normalized, |table| self.state.table_changes(table)) {
+            return Ok(result);
+        }
         
+        // Writes invalidate through the state's change counts, whichever route they take
+        let parsed = self.parse(query)?;
+        match &parsed {
+            ParsedQuery::Select { table, join, .. } => {
+                // Versions are read first, so a write racing the scan leaves the result stale
+                let versions = std::iter::once(table)
+                    .chain(join.as_ref().map(|join| &join.table))
+                    .map(|table| (table.clone(), self.state.table_changes(table)))
+                    .collect();
+                let result = self.run_uncached(parsed, deadline, cancel)?;
+                cache.insert(normalized, versions, result.clone());
+                Ok(result)
+            }
+            _ => self.run_uncached(parsed, deadline, cancel),
+        }
+    }
+    
+    /// Plan and execute a parsed query
+    fn run_uncached(
+        &self,
+        parsed: ParsedQuery,
+        deadline: Option<Instant>,
+        cancel: Option<&CancelToken>,
+    ) -> Result<QueryResult, QueryError> {
         // Plan execution
         let plan = self.plan(parsed)?;
         
         // Execute plan
-        self.execute_plan(plan)
+        self.execute_plan(plan, deadline, cancel)
     }
     
     /// Parse SQL query into abstract syntax tree
-    fn parse(&self, query: &str) -> Result<parser::ParsedQuery, QueryError> {
+    fn parse(&self, query: &str) -> Result<ParsedQuery, QueryError> {
+        if parser::normalize_query(query).is_ok_and(|normalized| normalized == FLUSH_QUERY_CACHE) {
+            // Nothing to flush without a cache
+            return Err(QueryError::PlanningError("query cache is disabled".to_string()));
+        }
         parser::parse_query(query).map_err(|e| QueryError::ParseError(e))
     }
     
     /// Create execution plan for query
-    fn plan(&self, query: parser::ParsedQuery) -> Result<executor::ExecutionPlan, QueryError> {
+    fn plan(&self, query: ParsedQuery) -> Result<executor::ExecutionPlan, QueryError> {
         // In a full implementation, this would involve query optimization
         // For now, just create a basic plan
         Ok(executor::ExecutionPlan::from_parsed_query(query))
     }
     
     /// Execute plan and produce result
-    fn execute_plan(&self, plan: executor::ExecutionPlan) -> Result<QueryResult, QueryError> {
+    fn execute_plan(
+        &self,
+        plan: executor::ExecutionPlan,
+        deadline: Option<Instant>,
+        cancel: Option<&CancelToken>,
+    ) -> Result<QueryResult, QueryError> {
         // Execute the plan
-        executor::execute_plan(plan, self.state.clone())
+        executor::execute_plan(plan, self.state.clone(), deadline, cancel)
             .map_err(|e| QueryError::ExecutionError(e))
     }
 }
 
+/// Control statement dropping every cached SELECT result (normalized form)
+const FLUSH_QUERY_CACHE: &str = "flush query cache";
+
 /// Query result types
+#[derive(Clone)]
 pub enum QueryResult {
     // SELECT result
     Rows {
@@ -101,3 +205,65 @@ impl std::fmt::Display for QueryError {
 
 impl std::error::Error for QueryError {}
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::persistence::aof::AppendOnlyFile;
+    use crate::storage::memory::MemTable;
+    
+    #[test]
+    fn test_select_cache() {
+        let dir = tempfile::tempdir().unwrap();
+        let aof = AppendOnlyFile::new(dir.path().join("cache.aof")).unwrap();
+        let state = Arc::new(GlobalState::new(Arc::new(MemTable::new()), aof));
+        state.set(b"users:1", b"ann".to_vec(), None).unwrap();
+        
+        let processor = QueryProcessor::new(state.clone()).with_cache(16, 1 << 20);
+        let count = |query: &str| match processor.execute(query).unwrap() {
+            QueryResult::Rows { rows, .. } => rows.len(),
+            _ => panic!("Expected rows"),
+        };
+        assert_eq!(count("SELECT * FROM users"), 1);
+        
+        // Same query in other spelling hits the cache; other tables' writes leave it be
+        state.set(b"orders:1", b"1".to_vec(), None).unwrap();
+        assert_eq!(count("select *  from USERS;"), 1);
+        let cache = processor.cache().unwrap();
+        assert_eq!(cache.len(), 1);
+        
+        // Plain commands invalidate the table's results as well as SQL writes
+        state.set(b"users:2", b"bob".to_vec(), None).unwrap();
+        assert_eq!(count("SELECT * FROM users"), 2);
+        processor.execute("DELETE FROM users WHERE key = '2'").unwrap();
+        assert_eq!(count("SELECT * FROM users"), 1);
+        state.delete(b"users:1").unwrap();
+        assert_eq!(count("SELECT * FROM users"), 0);
+        state.set(b"users:3", b"cy".to_vec(), None).unwrap();
+        assert_eq!(count("SELECT * FROM users"), 1);
+        state.flush_all().unwrap();
+        assert_eq!(count("SELECT * FROM users"), 0);
+        
+        assert!(matches!(processor.execute("FLUSH QUERY CACHE").unwrap(), QueryResult::Modified { affected_rows: 1 }));
+        assert!(cache.is_empty());
+        assert!(QueryProcessor::new(state).execute("flush query cache").is_err());
+    }
+    
+    #[tokio::test]
+    async fn test_cancelled_query_returns_no_result() {
+        let dir = tempfile::tempdir().unwrap();
+        let aof = AppendOnlyFile::new(dir.path().join("cancel.aof")).unwrap();
+        let state = Arc::new(GlobalState::new(Arc::new(MemTable::new()), aof));
+        state.set(b"users:1", b"ann".to_vec(), None).unwrap();
+        
+        let processor = Arc::new(QueryProcessor::new(state).with_cache(16, 1 << 20));
+        let cancel = CancelToken::new();
+        cancel.cancel();
+        let err = processor.execute_cancellable("SELECT * FROM users", None, &cancel).err();
+        assert_eq!(err.map(|e| e.to_string()).as_deref(), Some("Execution error: query cancelled"));
+        
+        // Nothing was cached for the cancelled run
+        assert!(processor.cache().unwrap().is_empty());
+        let result = processor.execute_spawned("SELECT * FROM users".to_string(), None).await.unwrap();
+        assert!(matches!(result, QueryResult::Rows { affected_rows: 1, .. }));
+    }
+}
@@ -0,0 +1,136 @@
+// SQL tokenizer - splits query text into keywords, names, literals and symbols
+// Identifiers and keywords are lowercased; string literals keep their case
+
+use std::fmt;
+
+/// Lexical token
+#[derive(Debug, Clone, PartialEq)]
+pub enum Token {
+    // Identifier or keyword (lowercased)
+    Ident(String),
+    
+    // Integer literal
+    Integer(i64),
+    
+    // Float literal
+    Float(f64),
+    
+    // 'quoted string' ('' escapes a quote)
+    String(String),
+    
+    // Punctuation or operator: ( ) , . * + - / = <> != < <= > >=
+    Symbol(&'static str),
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Ident(name) => f.write_str(name),
+            Token::Integer(n) => write!(f, "{}", n),
+            Token::Float(x) => write!(f, "{:?}", x),
+            Token::String(text) => write!(f, "'{}'", text.replace('\'', "''")),
+            Token::Symbol(symbol) => f.write_str(symbol),
+        }
+    }
+}
+
+/// Operators and punctuation, longest first so "<=" wins over "<"
+const SYMBOLS: [&str; 15] = ["<=", ">=", "<>", "!=", "(", ")", ",", ".", "*", "+", "-", "/", "=", "<", ">"];
+
+/// Split `query` into tokens (a trailing semicolon is ignored)
+pub fn tokenize(query: &str) -> Result<Vec<Token>, String> {
+    let query = query.trim().trim_end_matches(';');
+    let bytes = query.as_bytes();
+    let mut tokens = Vec::new();
+    let mut pos = 0;
+    
+    while pos < bytes.len() {
+        let c = bytes[pos];
+        if c.is_ascii_whitespace() {
+            pos += 1;
+        } else if c.is_ascii_alphabetic() || c == b'_' {
+            let start = pos;
+            while pos < bytes.len() && (bytes[pos].is_ascii_alphanumeric() || bytes[pos] == b'_') {
+                pos += 1;
+            }
+            tokens.push(Token::Ident(query[start..pos].to_ascii_lowercase()));
+        } else if c.is_ascii_digit() {
+            let start = pos;
+            while pos < bytes.len() && bytes[pos].is_ascii_digit() {
+                pos += 1;
+            }
+            
+            // A dot followed by digits makes it a float
+            let is_float = pos + 1 < bytes.len() && bytes[pos] == b'.' && bytes[pos + 1].is_ascii_digit();
+            if is_float {
+                pos += 1;
+                while pos < bytes.len() && bytes[pos].is_ascii_digit() {
+                    pos += 1;
+                }
+            }
+            let text = &query[start..pos];
+            tokens.push(if is_float {
+                Token::Float(text.parse().map_err(|_| format!("Invalid number: {}", text))?)
+            } else {
+                Token::Integer(text.parse().map_err(|_| format!("Integer out of range: {}", text))?)
+            });
+        } else if c == b'\'' {
+            let mut text = String::new();
+            pos += 1;
+            loop {
+                let Some(offset) = query[pos..].find('\'') else {
+                    return Err("Unterminated string literal".to_string());
+                };
+                text.push_str(&query[pos..pos + offset]);
+                pos += offset + 1;
+                
+                // '' is an escaped quote, anything else ends the literal
+                if bytes.get(pos) == Some(&b'\'') {
+                    text.push('\'');
+                    pos += 1;
+                } else {
+                    break;
+                }
+            }
+            tokens.push(Token::String(text));
+        } else {
+            let symbol = SYMBOLS.iter()
+                .find(|symbol| query[pos..].starts_with(**symbol))
+                .ok_or_else(|| format!("Unexpected character: {}", query[pos..].chars().next().unwrap_or('?')))?;
+            tokens.push(Token::Symbol(symbol));
+            pos += symbol.len();
+        }
+    }
+    
+    Ok(tokens)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_tokenize() {
+        let tokens = tokenize("SELECT a.x FROM T WHERE n >= -1.5 AND s <> 'It''s' ;").unwrap();
+        assert_eq!(tokens, vec![
+            Token::Ident("select".to_string()),
+            Token::Ident("a".to_string()),
+            Token::Symbol("."),
+            Token::Ident("x".to_string()),
+            Token::Ident("from".to_string()),
+            Token::Ident("t".to_string()),
+            Token::Ident("where".to_string()),
+            Token::Ident("n".to_string()),
+            Token::Symbol(">="),
+            Token::Symbol("-"),
+            Token::Float(1.5),
+            Token::Ident("and".to_string()),
+            Token::Ident("s".to_string()),
+            Token::Symbol("<>"),
+            Token::String("It's".to_string()),
+        ]);
+        
+        assert!(tokenize("SELECT 'open").is_err());
+        assert!(tokenize("SELECT a # b").is_err());
+    }
+}
@@ -3,15 +3,26 @@
 // In a real implementation, we'd use Pest grammar
 // For now, implementing a simple parser
 
+mod lexer;
+
+use std::fmt;
+
+use lexer::{tokenize, Token};
+
 /// Parsed query representation
 #[derive(Debug, Clone)]
 pub enum ParsedQuery {
     // SELECT statement
     Select {
+        distinct: bool,
         columns: Vec<String>,
         table: String,
+        join: Option<Join>,
         where_clause: Option<WhereClause>,
         limit: Option<usize>,
+        
+        // Matching rows skipped before LIMIT counts (0 = none)
+        offset: usize,
     },
     
     // INSERT statement
@@ -39,6 +50,19 @@ pub enum ParsedQuery {
         table: String,
         columns: Vec<ColumnDef>,
     },
+    
+    // EXPLAIN <query> - describe the plan instead of running it
+    Explain(Box<ParsedQuery>),
+}
+
+/// Inner equi-join of the FROM table with a second table
+#[derive(Debug, Clone)]
+pub struct Join {
+    pub table: String,
+    
+    // Qualified join columns (table.column) of the FROM table and the joined table
+    pub left_column: String,
+    pub right_column: String,
 }
 
 /// Column definition for CREATE TABLE
@@ -90,6 +114,27 @@ pub enum Expr {
         name: String,
         args: Vec<Expr>,
     },
+    
+    // expr [NOT] IN (list)
+    In {
+        expr: Box<Expr>,
+        list: Vec<Expr>,
+        negated: bool,
+    },
+    
+    // expr IS [NOT] NULL
+    IsNull {
+        expr: Box<Expr>,
+        negated: bool,
+    },
+    
+    // expr [NOT] BETWEEN low AND high (inclusive)
+    Between {
+        expr: Box<Expr>,
+        low: Box<Expr>,
+        high: Box<Expr>,
+        negated: bool,
+    },
 }
 
 /// Binary operators
@@ -107,6 +152,10 @@ pub enum BinaryOperator {
     GreaterThanOrEqual,
     And,
     Or,
+    
+    // SQL LIKE pattern match (right side is the pattern)
+    Like,
+    NotLike,
 }
 
 /// Unary operators
@@ -125,6 +174,62 @@ pub enum Literal {
     String(String),
 }
 
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Expr::Column(name) => f.write_str(name),
+            Expr::Literal(literal) => write!(f, "{}", literal),
+            Expr::BinaryOp { left, op, right } => write!(f, "({} {} {})", left, op, right),
+            Expr::UnaryOp { op: UnaryOperator::Negate, expr } => write!(f, "-{}", expr),
+            Expr::UnaryOp { op: UnaryOperator::Not, expr } => write!(f, "NOT {}", expr),
+            Expr::Function { name, args } => {
+                let args: Vec<String> = args.iter().map(Expr::to_string).collect();
+                write!(f, "{}({})", name, args.join(", "))
+            }
+            Expr::In { expr, list, negated } => {
+                let list: Vec<String> = list.iter().map(Expr::to_string).collect();
+                write!(f, "({} {}IN ({}))", expr, if *negated { "NOT " } else { "" }, list.join(", "))
+            }
+            Expr::Between { expr, low, high, negated } => {
+                write!(f, "({} {}BETWEEN {} AND {})", expr, if *negated { "NOT " } else { "" }, low, high)
+            }
+            Expr::IsNull { expr, negated } => write!(f, "({} IS {}NULL)", expr, if *negated { "NOT " } else { "" }),
+        }
+    }
+}
+
+impl fmt::Display for BinaryOperator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            BinaryOperator::Add => "+",
+            BinaryOperator::Subtract => "-",
+            BinaryOperator::Multiply => "*",
+            BinaryOperator::Divide => "/",
+            BinaryOperator::Equal => "=",
+            BinaryOperator::NotEqual => "<>",
+            BinaryOperator::LessThan => "<",
+            BinaryOperator::LessThanOrEqual => "<=",
+            BinaryOperator::GreaterThan => ">",
+            BinaryOperator::GreaterThanOrEqual => ">=",
+            BinaryOperator::And => "AND",
+            BinaryOperator::Or => "OR",
+            BinaryOperator::Like => "LIKE",
+            BinaryOperator::NotLike => "NOT LIKE",
+        })
+    }
+}
+
+impl fmt::Display for Literal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Literal::Null => f.write_str("NULL"),
+            Literal::Integer(n) => write!(f, "{}", n),
+            Literal::Float(x) => write!(f, "{:?}", x),
+            Literal::String(s) => write!(f, "'{}'", s.replace('\'', "''")),
+        }
+    }
+}
+
 /// Parse SQL query into AST
 pub fn parse_query(query: &str) -> Result<ParsedQuery, String> {
     // this is a placeholder for a real parser
@@ -133,16 +238,20 @@ pub fn parse_query(query: &str) -> Result<ParsedQuery, String> {
     // Extremely simplified parser for demonstration
     // In reality, this would be much more sophisticated
     
-    let query = query.trim().to_lowercase();
+    let original = query.trim();
+    let query = original.to_lowercase();
     
-    if query.starts_with("select") {
-        parse_select(&query)
+    // Prefixes are ASCII, so their byte lengths match in the original text
+    if query.starts_with("explain ") {
+        Ok(ParsedQuery::Explain(Box::new(parse_query(&original["explain ".len()..])?)))
+    } else if query.starts_with("select") {
+        parse_select(original)
     } else if query.starts_with("insert") {
-        parse_insert(&query)
+        parse_insert(original)
     } else if query.starts_with("update") {
-        parse_update(&query)
+        parse_update(original)
     } else if query.starts_with("delete") {
-        parse_delete(&query)
+        parse_delete(original)
     } else if query.starts_with("create table") {
         parse_create_table(&query)
     } else {
@@ -150,51 +259,42 @@ pub fn parse_query(query: &str) -> Result<ParsedQuery, String> {
     }
 }
 
+/// Canonical text of a query - keywords lowercased, whitespace collapsed, literals kept
+/// Queries that differ only in spelling normalize to the same string
+pub fn normalize_query(query: &str) -> Result<String, String> {
+    let tokens: Vec<String> = tokenize(query)?.iter().map(Token::to_string).collect();
+    Ok(tokens.join(" "))
+}
+
 // Placeholder parsers for different query types
 // These would be much more sophisticated in a real implementation
 
 fn parse_select(query: &str) -> Result<ParsedQuery, String> {
-    // Extremely simplified SELECT parser
-    
-    // For demo purposes, just handle: SELECT col1, col2 FROM table
-    let parts: Vec<&str> = query.split("from").collect();
-    if parts.len() != 2 {
-        return Err("Invalid SELECT query".to_string());
-    }
-    
-    // Extract columns
-    let select_part = parts[0].trim().strip_prefix("select").unwrap_or("").trim();
-    let columns: Vec<String> = select_part
-        .split(',')
-        .map(|s| s.trim().to_string())
-        .collect();
-        
-    // Extract table
-    let table = parts[1].trim().to_string();
-    
-    // For now, no WHERE or LIMIT support
-    
-    Ok(ParsedQuery::Select {
-        columns,
-        table,
-        where_clause: None,
-        limit: None,
-    })
+    let mut parser = Parser::new(query)?;
+    let select = parser.select()?;
+    parser.finish()?;
+    Ok(select)
 }
 
 fn parse_insert(query: &str) -> Result<ParsedQuery, String> {
-    // Placeholder for INSERT parser
-    Err("INSERT parsing not implemented".to_string())
+    let mut parser = Parser::new(query)?;
+    let insert = parser.insert()?;
+    parser.finish()?;
+    Ok(insert)
 }
 
 fn parse_update(query: &str) -> Result<ParsedQuery, String> {
-    // Placeholder for UPDATE parser
-    Err("UPDATE parsing not implemented".to_string())
+    let mut parser = Parser::new(query)?;
+    let update = parser.update()?;
+    parser.finish()?;
+    Ok(update)
 }
 
 fn parse_delete(query: &str) -> Result<ParsedQuery, String> {
-    // Placeholder for DELETE parser
-    Err("DELETE parsing not implemented".to_string())
+    let mut parser = Parser::new(query)?;
+    let delete = parser.delete()?;
+    parser.finish()?;
+    Ok(delete)
 }
 
 fn parse_create_table(query: &str) -> Result<ParsedQuery, String> {
@@ -202,6 +302,471 @@ fn parse_create_table(query: &str) -> Result<ParsedQuery, String> {
     Err("CREATE TABLE parsing not implemented".to_string())
 }
 
+/// Words that end a clause, so they are never read as table names
+const KEYWORDS: [&str; 21] = [
+    "select", "distinct", "from", "join", "inner", "left", "right", "full", "cross",
+    "on", "where", "and", "or", "not", "null", "limit", "offset", "in", "between", "like", "is",
+];
+
+/// Recursive-descent parser over a query's tokens
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn new(query: &str) -> Result<Self, String> {
+        Ok(Self { tokens: tokenize(query)?, pos: 0 })
+    }
+    
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+    
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+    
+    /// Next token as text for error messages
+    fn describe_next(&self) -> String {
+        match self.peek() {
+            Some(Token::Ident(name)) => format!("'{}'", name),
+            Some(Token::Symbol(symbol)) => format!("'{}'", symbol),
+            Some(Token::String(text)) => format!("'{}'", text),
+            Some(Token::Integer(n)) => n.to_string(),
+            Some(Token::Float(x)) => x.to_string(),
+            None => "end of query".to_string(),
+        }
+    }
+    
+    fn at_keyword(&self, keyword: &str) -> bool {
+        matches!(self.peek(), Some(Token::Ident(name)) if name == keyword)
+    }
+    
+    fn eat_keyword(&mut self, keyword: &str) -> bool {
+        let found = self.at_keyword(keyword);
+        self.pos += usize::from(found);
+        found
+    }
+    
+    fn expect_keyword(&mut self, keyword: &str) -> Result<(), String> {
+        if !self.eat_keyword(keyword) {
+            return Err(format!("Expected {} but found {}", keyword.to_uppercase(), self.describe_next()));
+        }
+        Ok(())
+    }
+    
+    fn eat_symbol(&mut self, symbol: &str) -> bool {
+        let found = matches!(self.peek(), Some(Token::Symbol(s)) if *s == symbol);
+        self.pos += usize::from(found);
+        found
+    }
+    
+    fn expect_symbol(&mut self, symbol: &str) -> Result<(), String> {
+        if !self.eat_symbol(symbol) {
+            return Err(format!("Expected '{}' but found {}", symbol, self.describe_next()));
+        }
+        Ok(())
+    }
+    
+    /// Table or column name (keywords are refused)
+    fn name(&mut self) -> Result<String, String> {
+        match self.peek() {
+            Some(Token::Ident(name)) if !KEYWORDS.contains(&name.as_str()) => {
+                let name = name.clone();
+                self.pos += 1;
+                Ok(name)
+            }
+            _ => Err(format!("Expected a name but found {}", self.describe_next())),
+        }
+    }
+    
+    /// column or table.column
+    fn column_name(&mut self) -> Result<String, String> {
+        let name = self.name()?;
+        if self.eat_symbol(".") {
+            return Ok(format!("{}.{}", name, self.name()?));
+        }
+        Ok(name)
+    }
+    
+    /// Fail unless every token was consumed
+    fn finish(&self) -> Result<(), String> {
+        if self.pos < self.tokens.len() {
+            return Err(format!("Unexpected {}", self.describe_next()));
+        }
+        Ok(())
+    }
+    
+    /// SELECT [DISTINCT] columns FROM table [[INNER] JOIN table ON a.x = b.y] [WHERE expr] [LIMIT n [OFFSET m]]
+    fn select(&mut self) -> Result<ParsedQuery, String> {
+        self.expect_keyword("select")?;
+        let distinct = self.eat_keyword("distinct");
+        
+        let mut columns = vec![self.select_item()?];
+        while self.eat_symbol(",") {
+            columns.push(self.select_item()?);
+        }
+        
+        self.expect_keyword("from")?;
+        let table = self.name()?;
+        let join = self.join(&table)?;
+        
+        let where_clause = self.where_clause()?;
+        
+        // LIMIT n [OFFSET m], or MySQL's LIMIT m, n
+        let (mut limit, mut offset) = (None, 0);
+        if self.eat_keyword("limit") {
+            let count = self.count()?;
+            if self.eat_symbol(",") {
+                (limit, offset) = (Some(self.count()?), count);
+            } else {
+                limit = Some(count);
+                if self.eat_keyword("offset") {
+                    offset = self.count()?;
+                }
+            }
+        }
+        
+        // Anything left over is most likely an alias or a clause we don't support
+        if matches!(self.peek(), Some(Token::Ident(name)) if !KEYWORDS.contains(&name.as_str())) {
+            return Err(format!("Unexpected {} (table aliases are not supported)", self.describe_next()));
+        }
+        
+        Ok(ParsedQuery::Select {
+            distinct,
+            columns,
+            table,
+            join,
+            where_clause,
+            limit,
+            offset,
+        })
+    }
+    
+    /// INSERT INTO table [(columns)] VALUES (literals) [, ...] - columns default to (key, value)
+    fn insert(&mut self) -> Result<ParsedQuery, String> {
+        self.expect_keyword("insert")?;
+        self.expect_keyword("into")?;
+        let table = self.name()?;
+        
+        let mut columns = Vec::new();
+        if self.eat_symbol("(") {
+            loop {
+                let column = self.name()?;
+                if columns.contains(&column) {
+                    return Err(format!("Column {} listed twice", column));
+                }
+                columns.push(column);
+                if !self.eat_symbol(",") {
+                    break;
+                }
+            }
+            self.expect_symbol(")")?;
+        } else {
+            columns = vec!["key".to_string(), "value".to_string()];
+        }
+        
+        self.expect_keyword("values")?;
+        let mut values = Vec::new();
+        loop {
+            self.expect_symbol("(")?;
+            let mut tuple = vec![self.literal()?];
+            while self.eat_symbol(",") {
+                tuple.push(self.literal()?);
+            }
+            self.expect_symbol(")")?;
+            if tuple.len() != columns.len() {
+                return Err(format!("Expected {} values but found {}", columns.len(), tuple.len()));
+            }
+            values.push(tuple);
+            if !self.eat_symbol(",") {
+                break;
+            }
+        }
+        
+        Ok(ParsedQuery::Insert { table, columns, values })
+    }
+    
+    /// Literal value, optionally negated
+    fn literal(&mut self) -> Result<Literal, String> {
+        match self.unary()? {
+            Expr::Literal(literal) => Ok(literal),
+            expr => Err(format!("Expected a literal value but found {}", expr)),
+        }
+    }
+    
+    /// UPDATE table SET column = literal [, ...] [WHERE expr]
+    fn update(&mut self) -> Result<ParsedQuery, String> {
+        self.expect_keyword("update")?;
+        let table = self.name()?;
+        self.expect_keyword("set")?;
+        
+        let mut assignments = vec![self.assignment()?];
+        while self.eat_symbol(",") {
+            assignments.push(self.assignment()?);
+        }
+        
+        Ok(ParsedQuery::Update {
+            table,
+            assignments,
+            where_clause: self.where_clause()?,
+        })
+    }
+    
+    /// column = literal
+    fn assignment(&mut self) -> Result<(String, Literal), String> {
+        let column = self.name()?;
+        self.expect_symbol("=")?;
+        Ok((column, self.literal()?))
+    }
+    
+    /// DELETE FROM table [WHERE expr]
+    fn delete(&mut self) -> Result<ParsedQuery, String> {
+        self.expect_keyword("delete")?;
+        self.expect_keyword("from")?;
+        let table = self.name()?;
+        
+        Ok(ParsedQuery::Delete {
+            table,
+            where_clause: self.where_clause()?,
+        })
+    }
+    
+    /// Optional WHERE expr
+    fn where_clause(&mut self) -> Result<Option<WhereClause>, String> {
+        match self.eat_keyword("where") {
+            true => Ok(Some(WhereClause { expr: self.expr()? })),
+            false => Ok(None),
+        }
+    }
+    
+    /// One projected column: *, COUNT(*) or a (qualified) column name
+    fn select_item(&mut self) -> Result<String, String> {
+        if self.eat_symbol("*") {
+            return Ok("*".to_string());
+        }
+        if self.at_keyword("count") && self.tokens.get(self.pos + 1) == Some(&Token::Symbol("(")) {
+            self.pos += 2;
+            self.expect_symbol("*")?;
+            self.expect_symbol(")")?;
+            return Ok("count(*)".to_string());
+        }
+        self.column_name()
+    }
+    
+    /// Optional [INNER] JOIN table ON left.col = right.col - other join shapes are refused
+    fn join(&mut self, left: &str) -> Result<Option<Join>, String> {
+        if ["left", "right", "full", "cross"].iter().any(|kind| self.at_keyword(kind)) {
+            return Err(format!("Unsupported join {} - only INNER JOIN is supported", self.describe_next()));
+        }
+        let inner = self.eat_keyword("inner");
+        if !self.eat_keyword("join") {
+            return match inner {
+                true => Err(format!("Expected JOIN but found {}", self.describe_next())),
+                false => Ok(None),
+            };
+        }
+        
+        let table = self.name()?;
+        if table == left {
+            return Err("Unsupported join: self-joins need table aliases, which are not supported".to_string());
+        }
+        self.expect_keyword("on")?;
+        
+        // ON must equate one column of each table
+        let shape_error = || format!("Unsupported join condition - expected ON {}.<column> = {}.<column>", left, table);
+        let (a, b) = match self.expr()? {
+            Expr::BinaryOp { left: a, op: BinaryOperator::Equal, right: b } => match (*a, *b) {
+                (Expr::Column(a), Expr::Column(b)) => (a, b),
+                _ => return Err(shape_error()),
+            },
+            _ => return Err(shape_error()),
+        };
+        let owner = |column: &str| column.split_once('.').map(|(owner, _)| owner.to_string());
+        let (left_column, right_column) = match (owner(&a), owner(&b)) {
+            (Some(x), Some(y)) if x == left && y == table => (a, b),
+            (Some(x), Some(y)) if x == table && y == left => (b, a),
+            _ => return Err(shape_error()),
+        };
+        
+        Ok(Some(Join { table, left_column, right_column }))
+    }
+    
+    /// Non-negative integer (LIMIT)
+    fn count(&mut self) -> Result<usize, String> {
+        match self.next() {
+            Some(Token::Integer(n)) if n >= 0 => Ok(n as usize),
+            _ => {
+                self.pos -= 1;
+                Err(format!("Expected a non-negative integer but found {}", self.describe_next()))
+            }
+        }
+    }
+    
+    /// Expression, lowest precedence first: OR, AND, NOT, comparison (and IN, BETWEEN), + -, * /, unary minus
+    fn expr(&mut self) -> Result<Expr, String> {
+        let mut left = self.and_expr()?;
+        while self.eat_keyword("or") {
+            let right = self.and_expr()?;
+            left = binary(left, BinaryOperator::Or, right);
+        }
+        Ok(left)
+    }
+    
+    fn and_expr(&mut self) -> Result<Expr, String> {
+        let mut left = self.not_expr()?;
+        while self.eat_keyword("and") {
+            let right = self.not_expr()?;
+            left = binary(left, BinaryOperator::And, right);
+        }
+        Ok(left)
+    }
+    
+    fn not_expr(&mut self) -> Result<Expr, String> {
+        if self.eat_keyword("not") {
+            let expr = self.not_expr()?;
+            return Ok(Expr::UnaryOp { op: UnaryOperator::Not, expr: Box::new(expr) });
+        }
+        self.comparison()
+    }
+    
+    fn comparison(&mut self) -> Result<Expr, String> {
+        let left = self.additive()?;
+        
+        if self.eat_keyword("is") {
+            let negated = self.eat_keyword("not");
+            self.expect_keyword("null")?;
+            return Ok(Expr::IsNull { expr: Box::new(left), negated });
+        }
+        
+        // [NOT] IN (list), [NOT] BETWEEN low AND high and [NOT] LIKE bind like comparisons
+        let negated = self.at_keyword("not")
+            && matches!(self.tokens.get(self.pos + 1), Some(Token::Ident(word)) if ["in", "between", "like"].contains(&word.as_str()));
+        self.pos += usize::from(negated);
+        if self.eat_keyword("like") {
+            let op = if negated { BinaryOperator::NotLike } else { BinaryOperator::Like };
+            return Ok(binary(left, op, self.additive()?));
+        }
+        if self.eat_keyword("in") {
+            self.expect_symbol("(")?;
+            let mut list = vec![self.expr()?];
+            while self.eat_symbol(",") {
+                list.push(self.expr()?);
+            }
+            self.expect_symbol(")")?;
+            return Ok(Expr::In { expr: Box::new(left), list, negated });
+        }
+        if self.eat_keyword("between") {
+            // Bounds stop short of AND, which belongs to BETWEEN here
+            let low = self.additive()?;
+            self.expect_keyword("and")?;
+            let high = self.additive()?;
+            return Ok(Expr::Between { expr: Box::new(left), low: Box::new(low), high: Box::new(high), negated });
+        }
+        
+        let op = match self.peek() {
+            Some(Token::Symbol("=")) => BinaryOperator::Equal,
+            Some(Token::Symbol("<>" | "!=")) => BinaryOperator::NotEqual,
+            Some(Token::Symbol("<")) => BinaryOperator::LessThan,
+            Some(Token::Symbol("<=")) => BinaryOperator::LessThanOrEqual,
+            Some(Token::Symbol(">")) => BinaryOperator::GreaterThan,
+            Some(Token::Symbol(">=")) => BinaryOperator::GreaterThanOrEqual,
+            _ => return Ok(left),
+        };
+        self.pos += 1;
+        let right = self.additive()?;
+        Ok(binary(left, op, right))
+    }
+    
+    fn additive(&mut self) -> Result<Expr, String> {
+        let mut left = self.term()?;
+        loop {
+            let op = match self.peek() {
+                Some(Token::Symbol("+")) => BinaryOperator::Add,
+                Some(Token::Symbol("-")) => BinaryOperator::Subtract,
+                _ => return Ok(left),
+            };
+            self.pos += 1;
+            let right = self.term()?;
+            left = binary(left, op, right);
+        }
+    }
+    
+    fn term(&mut self) -> Result<Expr, String> {
+        let mut left = self.unary()?;
+        loop {
+            let op = match self.peek() {
+                Some(Token::Symbol("*")) => BinaryOperator::Multiply,
+                Some(Token::Symbol("/")) => BinaryOperator::Divide,
+                _ => return Ok(left),
+            };
+            self.pos += 1;
+            let right = self.unary()?;
+            left = binary(left, op, right);
+        }
+    }
+    
+    /// Unary minus - folded into numeric literals so `x > -1` still compiles to a comparison
+    fn unary(&mut self) -> Result<Expr, String> {
+        if !self.eat_symbol("-") {
+            return self.primary();
+        }
+        Ok(match self.unary()? {
+            Expr::Literal(Literal::Integer(n)) => Expr::Literal(Literal::Integer(-n)),
+            Expr::Literal(Literal::Float(x)) => Expr::Literal(Literal::Float(-x)),
+            expr => Expr::UnaryOp { op: UnaryOperator::Negate, expr: Box::new(expr) },
+        })
+    }
+    
+    fn primary(&mut self) -> Result<Expr, String> {
+        match self.peek().cloned() {
+            Some(Token::Integer(n)) => {
+                self.pos += 1;
+                Ok(Expr::Literal(Literal::Integer(n)))
+            }
+            Some(Token::Float(x)) => {
+                self.pos += 1;
+                Ok(Expr::Literal(Literal::Float(x)))
+            }
+            Some(Token::String(text)) => {
+                self.pos += 1;
+                Ok(Expr::Literal(Literal::String(text)))
+            }
+            Some(Token::Symbol("(")) => {
+                self.pos += 1;
+                let expr = self.expr()?;
+                self.expect_symbol(")")?;
+                Ok(expr)
+            }
+            Some(Token::Ident(name)) if name == "null" => {
+                self.pos += 1;
+                Ok(Expr::Literal(Literal::Null))
+            }
+            Some(Token::Ident(name)) if self.tokens.get(self.pos + 1) == Some(&Token::Symbol("(")) => {
+                self.pos += 2;
+                let mut args = Vec::new();
+                if !self.eat_symbol(")") {
+                    args.push(self.expr()?);
+                    while self.eat_symbol(",") {
+                        args.push(self.expr()?);
+                    }
+                    self.expect_symbol(")")?;
+                }
+                Ok(Expr::Function { name, args })
+            }
+            _ => Ok(Expr::Column(self.column_name()?)),
+        }
+    }
+}
+
+/// Binary expression node
+fn binary(left: Expr, op: BinaryOperator, right: Expr) -> Expr {
+    Expr::BinaryOp { left: Box::new(left), op, right: Box::new(right) }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -220,4 +785,121 @@ mod tests {
             panic!("Expected SELECT query");
         }
     }
+    
+    #[test]
+    fn test_parse_join_and_where() {
+        let query = "SELECT users.name, orders.total FROM users JOIN orders ON orders.user_id = users.id WHERE orders.total > -5 AND users.name = 'Ann' LIMIT 10";
+        match parse_query(query) {
+            Ok(ParsedQuery::Select { columns, table, join: Some(join), where_clause: Some(where_clause), limit, .. }) => {
+                assert_eq!(columns, vec!["users.name".to_string(), "orders.total".to_string()]);
+                assert_eq!(table, "users");
+                assert_eq!((join.table.as_str(), join.left_column.as_str(), join.right_column.as_str()), ("orders", "users.id", "orders.user_id"));
+                assert_eq!(where_clause.expr.to_string(), "((orders.total > -5) AND (users.name = 'Ann'))");
+                assert_eq!(limit, Some(10));
+            }
+            other => panic!("Expected SELECT with JOIN, got {:?}", other),
+        }
+        
+        // Unsupported join shapes are refused with a reason
+        assert!(parse_query("SELECT * FROM a LEFT JOIN b ON a.id = b.id").unwrap_err().contains("only INNER JOIN"));
+        assert!(parse_query("SELECT * FROM a JOIN b ON a.id > b.id").unwrap_err().contains("join condition"));
+        assert!(parse_query("SELECT * FROM a JOIN b ON id = aid").unwrap_err().contains("join condition"));
+        assert!(parse_query("SELECT * FROM a x JOIN b ON x.id = b.id").unwrap_err().contains("aliases"));
+    }
+    
+    #[test]
+    fn test_parse_in_between_like() {
+        let filter = |query: &str| match parse_query(query) {
+            Ok(ParsedQuery::Select { where_clause: Some(where_clause), .. }) => where_clause.expr.to_string(),
+            other => panic!("Expected SELECT with WHERE, got {:?}", other),
+        };
+        assert_eq!(filter("SELECT * FROM t WHERE status IN ('a', 'b') AND age BETWEEN 18 AND 65"),
+            "((status IN ('a', 'b')) AND (age BETWEEN 18 AND 65))");
+        assert_eq!(filter("SELECT * FROM t WHERE id NOT IN (1, -2) OR age NOT BETWEEN 1 + 1 AND 9"),
+            "((id NOT IN (1, -2)) OR (age NOT BETWEEN (1 + 1) AND 9))");
+        assert_eq!(filter("SELECT * FROM t WHERE NOT id IN (1)"), "NOT (id IN (1))");
+        assert!(parse_query("SELECT * FROM t WHERE id IN ()").is_err());
+        assert!(parse_query("SELECT * FROM t WHERE id BETWEEN 1").is_err());
+        assert_eq!(filter("SELECT * FROM t WHERE name LIKE 'a%' AND name NOT LIKE '%z'"),
+            "((name LIKE 'a%') AND (name NOT LIKE '%z'))");
+        assert_eq!(filter("SELECT * FROM t WHERE a IS NULL OR b + 1 IS NOT NULL"), "((a IS NULL) OR ((b + 1) IS NOT NULL))");
+        assert!(parse_query("SELECT * FROM t WHERE a IS 1").is_err());
+    }
+    
+    #[test]
+    fn test_parse_limit_offset() {
+        let limits = |query: &str| match parse_query(query) {
+            Ok(ParsedQuery::Select { limit, offset, .. }) => (limit, offset),
+            other => panic!("Expected SELECT, got {:?}", other),
+        };
+        assert_eq!(limits("SELECT * FROM t LIMIT 10 OFFSET 20"), (Some(10), 20));
+        assert_eq!(limits("SELECT * FROM t LIMIT 20, 10"), (Some(10), 20));
+        assert_eq!(limits("SELECT * FROM t LIMIT 10"), (Some(10), 0));
+        assert!(parse_query("SELECT * FROM t OFFSET 5").is_err());
+        assert!(parse_query("SELECT * FROM t LIMIT 5 OFFSET -1").is_err());
+    }
+    
+    #[test]
+    fn test_parse_distinct() {
+        match parse_query("SELECT DISTINCT city FROM users") {
+            Ok(ParsedQuery::Select { distinct, columns, .. }) => {
+                assert!(distinct);
+                assert_eq!(columns, vec!["city".to_string()]);
+            }
+            other => panic!("Expected SELECT, got {:?}", other),
+        }
+        assert!(matches!(parse_query("SELECT distinctive FROM t"), Ok(ParsedQuery::Select { distinct: false, .. })));
+    }
+    
+    #[test]
+    fn test_parse_insert() {
+        match parse_query("INSERT INTO users (key, name, score) VALUES ('1', 'Ann', -1.5), (2, NULL, 3)") {
+            Ok(ParsedQuery::Insert { table, columns, values }) => {
+                assert_eq!(table, "users");
+                assert_eq!(columns, vec!["key", "name", "score"]);
+                let values: Vec<Vec<String>> = values.iter().map(|row| row.iter().map(Literal::to_string).collect()).collect();
+                assert_eq!(values, vec![vec!["'1'", "'Ann'", "-1.5"], vec!["2", "NULL", "3"]]);
+            }
+            other => panic!("Expected INSERT, got {:?}", other),
+        }
+        assert!(matches!(parse_query("insert into t values ('1', 'x')"), Ok(ParsedQuery::Insert { columns, .. }) if columns == ["key", "value"]));
+        assert!(parse_query("INSERT INTO t (key, value) VALUES ('1')").unwrap_err().contains("Expected 2 values"));
+        assert!(parse_query("INSERT INTO t (key, key) VALUES (1, 2)").is_err());
+    }
+    
+    #[test]
+    fn test_parse_update_and_delete() {
+        match parse_query("UPDATE users SET value = 'Ann', score = -2 WHERE key = '1'") {
+            Ok(ParsedQuery::Update { table, assignments, where_clause: Some(where_clause) }) => {
+                assert_eq!(table, "users");
+                let assignments: Vec<String> = assignments.iter().map(|(column, value)| format!("{} = {}", column, value)).collect();
+                assert_eq!(assignments, vec!["value = 'Ann'", "score = -2"]);
+                assert_eq!(where_clause.expr.to_string(), "(key = '1')");
+            }
+            other => panic!("Expected UPDATE, got {:?}", other),
+        }
+        assert!(parse_query("UPDATE users SET value = key").unwrap_err().contains("literal"));
+        
+        assert!(matches!(parse_query("DELETE FROM users"), Ok(ParsedQuery::Delete { where_clause: None, .. })));
+        assert!(matches!(parse_query("delete from users where value = 'x'"), Ok(ParsedQuery::Delete { where_clause: Some(_), .. })));
+        assert!(parse_query("DELETE users").is_err());
+    }
+    
+    #[test]
+    fn test_normalize_query() {
+        assert_eq!(
+            normalize_query("SELECT  value\nFROM Users WHERE value = 'It''s';").unwrap(),
+            "select value from users where value = 'It''s'"
+        );
+        assert_ne!(normalize_query("SELECT * FROM t WHERE v = 'A'"), normalize_query("select * from t where v = 'a'"));
+    }
+    
+    #[test]
+    fn test_parse_explain() {
+        match parse_query("EXPLAIN SELECT id FROM users") {
+            Ok(ParsedQuery::Explain(inner)) => assert!(matches!(*inner, ParsedQuery::Select { .. })),
+            other => panic!("Expected EXPLAIN, got {:?}", other),
+        }
+        assert!(parse_query("EXPLAIN DROP users").is_err());
+    }
 }
\ No newline at end of file
@@ -1,5 +1,6 @@
 use std::fs::{File, OpenOptions};
 use std::io::{Read, Seek, SeekFrom, Write};
+use std::ops::{Deref, DerefMut};
 use std::os::unix::fs::OpenOptionsExt;
 use std::path::Path;
 // CRITICAL FIX: Added libc import instead of nix for simplified dependencies
@@ -17,6 +18,41 @@ pub struct NvmeAccess {
     file: Option<File>,
 }
 
+/// AlignedBuffer - Zeroed heap buffer starting on a block boundary, as direct I/O requires
+pub struct AlignedBuffer {
+    // Backing allocation, one block longer than needed
+    storage: Vec<u8>,
+    
+    // Offset of the first aligned byte in storage
+    start: usize,
+    
+    // Usable length
+    len: usize,
+}
+
+impl AlignedBuffer {
+    /// Allocate `len` zeroed bytes aligned to `align`
+    pub fn new(len: usize, align: usize) -> Self {
+        let storage = vec![0u8; len + align];
+        let start = storage.as_ptr().align_offset(align);
+        Self { storage, start, len }
+    }
+}
+
+impl Deref for AlignedBuffer {
+    type Target = [u8];
+    
+    fn deref(&self) -> &[u8] {
+        &self.storage[self.start..self.start + self.len]
+    }
+}
+
+impl DerefMut for AlignedBuffer {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        &mut self.storage[self.start..self.start + self.len]
+    }
+}
+
 impl NvmeAccess {
     /// Create new NVMe access for device
     pub fn new<P: AsRef<Path>>(device_path: P) -> Result<Self, std::io::Error> {
@@ -53,6 +89,11 @@ impl NvmeAccess {
         Ok(())
     }
     
+    /// Block size offsets and buffers are aligned to
+    pub fn block_size(&self) -> usize {
+        self.block_size
+    }
+    
     /// Close the device if open
     pub fn close(&mut self) {
         self.file = None;
@@ -134,6 +175,23 @@ impl NvmeAccess {
         Ok(())
     }
     
+    /// Raw read of `len` bytes at any offset (internally handles alignment)
+    pub fn raw_read(&mut self, offset: u64, len: usize) -> Result<Vec<u8>, std::io::Error> {
+        let aligned_offset = (offset / self.block_size as u64) * self.block_size as u64;
+        let offset_within_block = (offset - aligned_offset) as usize;
+        
+        let mut aligned_buf = self.create_aligned_buffer(offset_within_block + len)?;
+        let bytes_read = self.read_aligned(aligned_offset, &mut aligned_buf)?;
+        if bytes_read < offset_within_block + len {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::UnexpectedEof,
+                "Read past the end of the device"
+            ));
+        }
+        
+        Ok(aligned_buf[offset_within_block..offset_within_block + len].to_vec())
+    }
+    
     // === PRIVATE HELPERS ===
     
     /// Ensure we have an open file handle
@@ -170,17 +228,11 @@ impl NvmeAccess {
     }
     
     /// Create block-aligned buffer of specified size
-    fn create_aligned_buffer(&self, size: usize) -> Result<Vec<u8>, std::io::Error> {
+    fn create_aligned_buffer(&self, size: usize) -> Result<AlignedBuffer, std::io::Error> {
         // Round up to nearest multiple of block size
         let aligned_size = ((size + self.block_size - 1) / self.block_size) * self.block_size;
         
-        // Allocate aligned memory
-        // Note: In production, we'd use proper aligned allocation
-        // For simplicity, we're just creating a vector and hoping OS aligns it
-        let mut buffer = Vec::with_capacity(aligned_size);
-        buffer.resize(aligned_size, 0);
-        
-        Ok(buffer)
+        Ok(AlignedBuffer::new(aligned_size, self.block_size))
     }
 }
 
@@ -0,0 +1,187 @@
+// Reported encodings (OBJECT ENCODING) and the Redis thresholds behind them
+// Values keep one representation per type; only the name clients see follows the thresholds
+use std::sync::atomic::{AtomicI64, Ordering};
+
+use crate::storage::value::ValueKind;
+
+/// Threshold parameters and their Redis defaults, settable through CONFIG SET
+pub const ENCODING_PARAMS: [(&str, i64); 8] = [
+    ("hash-max-listpack-entries", 128),
+    ("hash-max-listpack-value", 64),
+    ("list-max-listpack-size", -2),
+    ("set-max-intset-entries", 512),
+    ("set-max-listpack-entries", 128),
+    ("set-max-listpack-value", 64),
+    ("zset-max-listpack-entries", 128),
+    ("zset-max-listpack-value", 64),
+];
+
+/// Longest string Redis stores as embstr
+const EMBSTR_MAX_LEN: usize = 44;
+
+/// Listpack bytes per element beyond its data (length prefix and backlen, roughly)
+const LISTPACK_ENTRY_OVERHEAD: usize = 2;
+
+/// Listpack header and terminator bytes
+const LISTPACK_HEADER: usize = 7;
+
+/// EncodingConfig - Current encoding thresholds, indexed like ENCODING_PARAMS
+pub struct EncodingConfig {
+    values: [AtomicI64; ENCODING_PARAMS.len()],
+}
+
+impl EncodingConfig {
+    /// Thresholds at their Redis defaults
+    pub fn new() -> Self {
+        Self {
+            values: ENCODING_PARAMS.map(|(_, default)| AtomicI64::new(default)),
+        }
+    }
+    
+    /// Canonical name of a parameter - case-insensitive, pre-7.0 "ziplist" names accepted
+    pub fn canonical_name(name: &str) -> Option<&'static str> {
+        let name = name.to_ascii_lowercase().replace("ziplist", "listpack");
+        ENCODING_PARAMS.iter().map(|(param, _)| *param).find(|param| *param == name)
+    }
+    
+    /// Current value of a parameter
+    pub fn get(&self, name: &str) -> Option<i64> {
+        let name = Self::canonical_name(name)?;
+        Some(self.value(name))
+    }
+    
+    /// Validate a CONFIG SET argument, returning the canonical name and the value
+    pub fn parse(name: &str, value: &str) -> Result<(&'static str, i64), String> {
+        let name = Self::canonical_name(name).ok_or_else(|| format!("Unknown option '{}'", name))?;
+        let value: i64 = value.parse()
+            .ok()
+            .filter(|&value| value >= 0 || name == "list-max-listpack-size")
+            .ok_or_else(|| format!("argument couldn't be parsed into an integer for '{}'", name))?;
+        Ok((name, value))
+    }
+    
+    /// Set a parameter from its CONFIG SET argument
+    pub fn set(&self, name: &str, value: &str) -> Result<(), String> {
+        let (name, value) = Self::parse(name, value)?;
+        self.store(name, value);
+        Ok(())
+    }
+    
+    /// Set a parameter already checked by `parse`
+    pub fn store(&self, name: &'static str, value: i64) {
+        self.slot(name).store(value, Ordering::Relaxed);
+    }
+    
+    /// Every parameter with its current value
+    pub fn iter(&self) -> impl Iterator<Item = (&'static str, i64)> + '_ {
+        ENCODING_PARAMS.iter().map(|(name, _)| (*name, self.value(name)))
+    }
+    
+    /// Encoding Redis would use for `value` under the current thresholds
+    pub fn encoding(&self, value: &ValueKind) -> &'static str {
+        match value {
+            ValueKind::String(bytes) if is_integer(bytes) => "int",
+            ValueKind::String(bytes) if bytes.len() <= EMBSTR_MAX_LEN => "embstr",
+            ValueKind::String(_) | ValueKind::Compressed(_) => "raw",
+            ValueKind::List(elements) => {
+                let size = self.value("list-max-listpack-size");
+                let fits = if size >= 0 {
+                    elements.len() as i64 <= size
+                } else {
+                    // -1 to -5 limit a node to 4, 8, 16, 32 or 64 KiB
+                    let bytes = LISTPACK_HEADER + elements.iter().map(|e| e.len() + LISTPACK_ENTRY_OVERHEAD).sum::<usize>();
+                    bytes <= 4096 << (-size - 1).clamp(0, 4)
+                };
+                if fits { "listpack" } else { "quicklist" }
+            }
+            ValueKind::Set(members) => {
+                let count = members.len() as i64;
+                if count <= self.value("set-max-intset-entries") && members.iter().all(|m| is_integer(m)) {
+                    "intset"
+                } else if self.fits_listpack(count, members.iter(), "set-max-listpack-entries", "set-max-listpack-value") {
+                    "listpack"
+                } else {
+                    "hashtable"
+                }
+            }
+            ValueKind::SortedSet(zset) => {
+                let members = zset.iter().map(|(member, _)| member);
+                if self.fits_listpack(zset.len() as i64, members, "zset-max-listpack-entries", "zset-max-listpack-value") {
+                    "listpack"
+                } else {
+                    "skiplist"
+                }
+            }
+        }
+    }
+    
+    /// Whether `count` elements, none longer than the value limit, fit a listpack
+    fn fits_listpack<'a>(
+        &self,
+        count: i64,
+        mut elements: impl Iterator<Item = &'a Vec<u8>>,
+        entries: &str,
+        value: &str,
+    ) -> bool {
+        let max_len = self.value(value);
+        count <= self.value(entries) && elements.all(|element| element.len() as i64 <= max_len)
+    }
+    
+    /// Current value of a canonical parameter name
+    fn value(&self, name: &str) -> i64 {
+        self.slot(name).load(Ordering::Relaxed)
+    }
+    
+    /// Storage of a canonical parameter name
+    fn slot(&self, name: &str) -> &AtomicI64 {
+        let index = ENCODING_PARAMS.iter().position(|(param, _)| *param == name).expect("known parameter");
+        &self.values[index]
+    }
+}
+
+impl Default for EncodingConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Whether `bytes` is an integer Redis would store as one (canonical decimal i64)
+fn is_integer(bytes: &[u8]) -> bool {
+    std::str::from_utf8(bytes)
+        .ok()
+        .and_then(|s| s.parse::<i64>().ok())
+        .is_some_and(|n| n.to_string().as_bytes() == bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::{HashSet, VecDeque};
+    use std::sync::Arc;
+    
+    #[test]
+    fn test_encodings_follow_thresholds() {
+        let config = EncodingConfig::new();
+        let string = |s: &str| ValueKind::String(Arc::from(s.as_bytes()));
+        assert_eq!(config.encoding(&string("12345")), "int");
+        assert_eq!(config.encoding(&string("012")), "embstr");
+        assert_eq!(config.encoding(&string(&"x".repeat(45))), "raw");
+        
+        let set = |members: &[&str]| ValueKind::Set(members.iter().map(|m| m.as_bytes().to_vec()).collect::<HashSet<_>>());
+        assert_eq!(config.encoding(&set(&["1", "2", "3"])), "intset");
+        assert_eq!(config.encoding(&set(&["1", "a"])), "listpack");
+        config.set("set-max-intset-entries", "2").unwrap();
+        config.set("SET-MAX-ZIPLIST-ENTRIES", "2").unwrap();
+        assert_eq!(config.get("set-max-listpack-entries"), Some(2));
+        assert_eq!(config.encoding(&set(&["1", "2", "3"])), "hashtable");
+        
+        let list = |len: usize| ValueKind::List(VecDeque::from(vec![b"element".to_vec(); len]));
+        assert_eq!(config.encoding(&list(100)), "listpack");
+        config.set("list-max-listpack-size", "64").unwrap();
+        assert_eq!(config.encoding(&list(100)), "quicklist");
+        
+        assert!(config.set("list-max-listpack-size", "big").is_err());
+        assert!(config.set("set-max-intset-entries", "-1").is_err());
+        assert!(config.set("maxmemory", "1").is_err());
+    }
+}
@@ -0,0 +1,275 @@
+// Storage engine abstraction - the keyspace operations the state layer needs from a backend
+// MemTable is the default engine; GlobalState is generic over it
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::storage::memory::{EvictionPolicy, ExpiredListener, MemTable};
+use crate::storage::value::{Applied, Mutation, ValueKind};
+
+/// Live entry as read by a scan: key, value and remaining TTL
+pub type ScanEntry = (Vec<u8>, ValueKind, Option<Duration>);
+
+/// StorageEngine - Keyspace backend behind GlobalState
+/// Engines never log: GlobalState appends to the AOF, and startup replays it through the
+/// `recover_*` hooks
+pub trait StorageEngine: Send + Sync {
+    /// String value of a live key
+    fn get(&self, key: &[u8]) -> Option<Arc<[u8]>>;
+
+    /// Store a string value, replacing whatever the key held
+    fn set(&self, key: &[u8], value: Vec<u8>, ttl: Option<Duration>) -> Result<(), String>;
+
+    /// Store a string value and return it as stored - the form the AOF logs, which an
+    /// engine that compresses may shrink
+    fn set_string(&self, key: &[u8], value: Vec<u8>, ttl: Option<Duration>) -> Result<ValueKind, String> {
+        let stored = ValueKind::String(Arc::from(value.as_slice()));
+        self.set(key, value, ttl)?;
+        Ok(stored)
+    }
+
+    /// Remove a key, returning whether it existed
+    fn delete(&self, key: &[u8]) -> Result<bool, String>;
+
+    /// Remove a key, freeing a large value off the caller's thread where the engine can
+    fn unlink(&self, key: &[u8]) -> Result<bool, String> {
+        self.delete(key)
+    }
+
+    /// Iterate over live entries as of the call
+    fn scan(&self) -> Box<dyn Iterator<Item = ScanEntry> + '_>;
+
+    /// Number of keys, counting expired ones not yet collected
+    fn len(&self) -> usize;
+
+    /// Whether the engine holds no keys
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Drop expired entries, returning how many were removed
+    fn gc(&self) -> usize;
+
+    /// Estimated bytes held, checked against the memory limit (0 = not tracked)
+    fn used_memory(&self) -> usize {
+        0
+    }
+
+    /// Remove one key chosen by `policy` to free memory (None = nothing to evict)
+    fn evict(&self, _policy: EvictionPolicy) -> Option<Vec<u8>> {
+        None
+    }
+
+    /// Replay hook: store a string value
+    fn recover_set(&self, key: &[u8], value: Vec<u8>, ttl: Option<Duration>) -> Result<(), String>;
+
+    /// Replay hook: store a value of any type
+    fn recover_value(&self, key: &[u8], value: ValueKind, ttl: Option<Duration>) -> Result<(), String>;
+
+    /// Replay hook: remove a key
+    fn recover_delete(&self, key: &[u8]) -> Result<bool, String>;
+
+    /// Replay hook: set or clear a key's TTL
+    fn recover_expiry(&self, key: &[u8], ttl: Option<Duration>) -> bool;
+
+    /// Replay hook: apply a collection mutation
+    fn recover_mutation(&self, key: &[u8], mutation: &Mutation) -> Result<Applied, String>;
+
+    /// Replay hook: remove every key (FLUSHALL)
+    fn recover_clear(&self) -> usize;
+
+    /// Replay hook: a SEQ high-water mark (engines without sequences ignore it)
+    fn recover_sequence(&self, _name: &[u8], _high_water: u64) {}
+
+    /// Whether replay must skip writes to `key` because it was deleted after they were logged
+    fn is_tombstoned(&self, _key: &[u8]) -> bool {
+        false
+    }
+
+    /// Register a listener for keys reaped by expiry; false if the engine has one or can't report
+    fn set_expired_listener(&self, _listener: ExpiredListener) -> bool {
+        false
+    }
+}
+
+impl StorageEngine for MemTable {
+    fn get(&self, key: &[u8]) -> Option<Arc<[u8]>> {
+        MemTable::get(self, key)
+    }
+
+    fn set(&self, key: &[u8], value: Vec<u8>, ttl: Option<Duration>) -> Result<(), String> {
+        MemTable::set(self, key, value, ttl)
+    }
+
+    fn set_string(&self, key: &[u8], value: Vec<u8>, ttl: Option<Duration>) -> Result<ValueKind, String> {
+        let value = self.string_value(value);
+        self.set_value(key, value.clone(), ttl)?;
+        Ok(value)
+    }
+
+    fn delete(&self, key: &[u8]) -> Result<bool, String> {
+        MemTable::delete(self, key)
+    }
+
+    fn unlink(&self, key: &[u8]) -> Result<bool, String> {
+        MemTable::unlink(self, key)
+    }
+
+    fn scan(&self) -> Box<dyn Iterator<Item = ScanEntry> + '_> {
+        Box::new(self.snapshot_iter())
+    }
+
+    fn len(&self) -> usize {
+        MemTable::len(self)
+    }
+
+    fn gc(&self) -> usize {
+        MemTable::gc(self)
+    }
+
+    fn used_memory(&self) -> usize {
+        MemTable::used_memory(self)
+    }
+
+    fn evict(&self, policy: EvictionPolicy) -> Option<Vec<u8>> {
+        MemTable::evict(self, policy)
+    }
+
+    fn recover_set(&self, key: &[u8], value: Vec<u8>, ttl: Option<Duration>) -> Result<(), String> {
+        MemTable::recover_set(self, key, value, ttl)
+    }
+
+    fn recover_value(&self, key: &[u8], value: ValueKind, ttl: Option<Duration>) -> Result<(), String> {
+        self.set_value(key, value, ttl)
+    }
+
+    fn recover_delete(&self, key: &[u8]) -> Result<bool, String> {
+        MemTable::recover_delete(self, key)
+    }
+
+    fn recover_expiry(&self, key: &[u8], ttl: Option<Duration>) -> bool {
+        self.set_expiry(key, ttl)
+    }
+
+    fn recover_mutation(&self, key: &[u8], mutation: &Mutation) -> Result<Applied, String> {
+        self.apply_mutation(key, mutation)
+    }
+
+    fn recover_clear(&self) -> usize {
+        self.clear()
+    }
+
+    fn recover_sequence(&self, name: &[u8], high_water: u64) {
+        self.raise_sequence_mark(name, high_water)
+    }
+
+    fn is_tombstoned(&self, key: &[u8]) -> bool {
+        MemTable::is_tombstoned(self, key)
+    }
+
+    fn set_expired_listener(&self, listener: ExpiredListener) -> bool {
+        MemTable::set_expired_listener(self, listener)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+    use crate::core::state::GlobalState;
+    use crate::persistence::aof::AppendOnlyFile;
+
+    /// Strings-only engine over a plain map, to check GlobalState runs on something else
+    #[derive(Default)]
+    struct MapEngine(Mutex<HashMap<Vec<u8>, Vec<u8>>>);
+
+    impl StorageEngine for MapEngine {
+        fn get(&self, key: &[u8]) -> Option<Arc<[u8]>> {
+            self.0.lock().unwrap().get(key).map(|value| Arc::from(value.as_slice()))
+        }
+
+        fn set(&self, key: &[u8], value: Vec<u8>, _ttl: Option<Duration>) -> Result<(), String> {
+            self.0.lock().unwrap().insert(key.to_vec(), value);
+            Ok(())
+        }
+
+        fn delete(&self, key: &[u8]) -> Result<bool, String> {
+            Ok(self.0.lock().unwrap().remove(key).is_some())
+        }
+
+        fn scan(&self) -> Box<dyn Iterator<Item = ScanEntry> + '_> {
+            let entries: Vec<ScanEntry> = self.0.lock().unwrap().iter()
+                .map(|(key, value)| (key.clone(), ValueKind::String(Arc::from(value.as_slice())), None))
+                .collect();
+            Box::new(entries.into_iter())
+        }
+
+        fn len(&self) -> usize {
+            self.0.lock().unwrap().len()
+        }
+
+        fn gc(&self) -> usize {
+            0
+        }
+
+        fn recover_set(&self, key: &[u8], value: Vec<u8>, ttl: Option<Duration>) -> Result<(), String> {
+            self.set(key, value, ttl)
+        }
+
+        fn recover_value(&self, _key: &[u8], _value: ValueKind, _ttl: Option<Duration>) -> Result<(), String> {
+            Err("strings only".to_string())
+        }
+
+        fn recover_delete(&self, key: &[u8]) -> Result<bool, String> {
+            self.delete(key)
+        }
+
+        fn recover_expiry(&self, key: &[u8], _ttl: Option<Duration>) -> bool {
+            self.0.lock().unwrap().contains_key(key)
+        }
+
+        fn recover_mutation(&self, _key: &[u8], _mutation: &Mutation) -> Result<Applied, String> {
+            Err("strings only".to_string())
+        }
+
+        fn recover_clear(&self) -> usize {
+            self.0.lock().unwrap().drain().count()
+        }
+    }
+
+    #[test]
+    fn test_state_over_another_engine() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("engine.aof");
+        {
+            let state = GlobalState::new(Arc::new(MemTable::new()), AppendOnlyFile::new(&path).unwrap());
+            state.set(b"a", b"1".to_vec(), None).unwrap();
+            state.set(b"b", b"2".to_vec(), None).unwrap();
+            state.delete(b"a").unwrap();
+            state.flush().unwrap();
+            assert_eq!(state.engine().len(), 1);
+        }
+
+        // The log written by the MemTable-backed state replays into any engine
+        let state = GlobalState::new(Arc::new(MapEngine::default()), AppendOnlyFile::new(&path).unwrap());
+        assert_eq!(state.recovery_stats().applied, 3);
+        assert_eq!(state.get(b"b").as_deref(), Some(&b"2"[..]));
+        assert!(state.get(b"a").is_none());
+
+        let engine: &dyn StorageEngine = state.engine().as_ref();
+        assert_eq!(engine.scan().map(|(key, _, _)| key).collect::<Vec<_>>(), vec![b"b".to_vec()]);
+
+        // Writes go through the engine and into the log like they do for a MemTable
+        state.set(b"c", b"3".to_vec(), None).unwrap();
+        assert!(state.delete(b"b").unwrap());
+        assert!(!state.unlink(b"b").unwrap());
+        assert_eq!((state.len(), state.gc()), (1, 0));
+        assert_eq!(state.scan().map(|(key, _, _)| key).collect::<Vec<_>>(), vec![b"c".to_vec()]);
+        state.flush().unwrap();
+        drop(state);
+
+        let state = GlobalState::new(Arc::new(MemTable::new()), AppendOnlyFile::new(&path).unwrap());
+        assert_eq!(state.get(b"c").as_deref(), Some(&b"3"[..]));
+        assert!(state.get(b"b").is_none());
+    }
+}
@@ -1,9 +1,18 @@
-use std::sync::Arc;
+use std::sync::{Arc, Condvar, Mutex, PoisonError};
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::time::{Duration, Instant};
 use std::thread;
 
+use rand::Rng;
+
 use crate::storage::memory::MemTable;
+use crate::util::latency::{LatencyMonitor, EVENT_EXPIRE_CYCLE, EVENT_GC};
+
+/// Share of each active expiry tick a sampled pass may run for (percent, as in Redis)
+const ACTIVE_EXPIRE_BUDGET_PERCENT: u32 = 25;
+
+/// Most idle strings moved to the cold tier per full cycle
+const SPILL_BATCH: usize = 10_000;
 
 /// GarbageCollector - Manages memory cleanup and expired entries
 pub struct GarbageCollector {
@@ -11,7 +20,13 @@ pub struct GarbageCollector {
     mem_table: Arc<MemTable>,
     
     // GC thread control - signal to stop
-    should_stop: Arc<AtomicUsize>,
+    should_stop: Arc<StopSignal>,
+    
+    // Fraction of the interval each sleep is randomly lengthened or shortened by (0.0 = fixed)
+    jitter: f64,
+    
+    // Sampled active expiry passes per second between full cycles (0 = full cycles only)
+    active_expire_hz: u32,
     
     // GC statistics
     stats: GcStats,
@@ -34,6 +49,32 @@ pub struct GcStats {
     pub avg_duration_ms: AtomicUsize,
 }
 
+/// Stop flag the GC thread can wait on, so stopping cuts a sleep short
+#[derive(Debug, Default)]
+struct StopSignal {
+    stopped: Mutex<bool>,
+    wake: Condvar,
+}
+
+impl StopSignal {
+    fn stop(&self) {
+        *self.stopped.lock().unwrap_or_else(PoisonError::into_inner) = true;
+        self.wake.notify_all();
+    }
+    
+    fn is_stopped(&self) -> bool {
+        *self.stopped.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+    
+    /// Sleep up to `timeout` - returns true if stopped before or during the wait
+    fn wait(&self, timeout: Duration) -> bool {
+        let stopped = self.stopped.lock().unwrap_or_else(PoisonError::into_inner);
+        let (stopped, _) = self.wake.wait_timeout_while(stopped, timeout, |stopped| !*stopped)
+            .unwrap_or_else(PoisonError::into_inner);
+        *stopped
+    }
+}
+
 // CRITICAL FIX: Manual Clone implementation for GcStats
 impl Clone for GcStats {
     fn clone(&self) -> Self {
@@ -51,30 +92,65 @@ impl GarbageCollector {
     pub fn new(mem_table: Arc<MemTable>) -> Self {
         Self {
             mem_table,
-            should_stop: Arc::new(AtomicUsize::new(0)),
+            should_stop: Arc::new(StopSignal::default()),
+            jitter: 0.0,
+            active_expire_hz: 0,
             stats: GcStats::default(),
         }
     }
     
+    /// Randomize each background sleep by up to `fraction` of the interval (clamped to 0.0..=1.0)
+    /// Keeps GC pauses on a fleet of nodes from lining up
+    pub fn with_jitter(mut self, fraction: f64) -> Self {
+        self.jitter = if fraction.is_nan() { 0.0 } else { fraction.clamp(0.0, 1.0) };
+        self
+    }
+    
+    /// Between full cycles, run `hz` sampled expiry passes a second (0 disables)
+    /// Keys nobody reads are then reaped, and their expired events fired, close to their
+    /// expiry instead of at the next full scan; each pass gets 25% of its tick
+    pub fn with_active_expiry(mut self, hz: u32) -> Self {
+        self.active_expire_hz = hz;
+        self
+    }
+    
     /// Start background GC thread
     pub fn start_background_gc(&self, interval: Duration) -> thread::JoinHandle<()> {
         // Clone references for the GC thread
         let mem_table = self.mem_table.clone();
         let should_stop = self.should_stop.clone();
         let stats = self.stats.clone();
+        let jitter = self.jitter;
+        let tick = (self.active_expire_hz > 0).then(|| Duration::from_secs(1) / self.active_expire_hz);
         
         // Spawn GC thread
         thread::spawn(move || {
             println!("Starting background GC thread");
+            let mut rng = rand::rng();
+            let mut next_full = Instant::now() + jittered(interval, jitter, &mut rng);
             
-            while should_stop.load(Ordering::Relaxed) == 0 {
-                // Sleep for interval
-                thread::sleep(interval);
+            while !should_stop.is_stopped() {
+                // Sleep until the next full cycle or active expiry tick - stop() wakes the thread early
+                let until_full = next_full.saturating_duration_since(Instant::now());
+                if should_stop.wait(tick.map_or(until_full, |tick| tick.min(until_full))) {
+                    break;
+                }
+                
+                if let Some(tick) = tick && Instant::now() < next_full {
+                    // Sampled pass - bounded by its budget, not the keyspace size
+                    let start = Instant::now();
+                    let expired = mem_table.active_expire_cycle(tick * ACTIVE_EXPIRE_BUDGET_PERCENT / 100);
+                    LatencyMonitor::global().record(EVENT_EXPIRE_CYCLE, start.elapsed());
+                    stats.collected.fetch_add(expired, Ordering::Relaxed);
+                    continue;
+                }
+                next_full = Instant::now() + jittered(interval, jitter, &mut rng);
                 
                 // Run GC cycle
                 let start = Instant::now();
                 let collected = mem_table.gc();
                 let duration = start.elapsed();
+                LatencyMonitor::global().record(EVENT_GC, duration);
                 
                 // Update statistics
                 stats.cycles.fetch_add(1, Ordering::Relaxed);
@@ -96,6 +172,11 @@ impl GarbageCollector {
                     println!("GC cycle complete: {} objects collected in {:?}", 
                         collected, duration);
                 }
+                
+                // Move idle strings to disk (a no-op without a cold tier)
+                if let Err(e) = mem_table.spill_idle(SPILL_BATCH) {
+                    eprintln!("Cold tier spill failed: {}", e);
+                }
             }
             
             println!("Background GC thread stopped");
@@ -104,7 +185,7 @@ impl GarbageCollector {
     
     /// Stop background GC thread
     pub fn stop(&self) {
-        self.should_stop.store(1, Ordering::Relaxed);
+        self.should_stop.stop();
     }
     
     /// Run a single GC cycle manually
@@ -132,6 +213,14 @@ impl GarbageCollector {
     }
 }
 
+/// `interval` moved by a uniformly random amount within +/- `jitter` of itself
+fn jittered(interval: Duration, jitter: f64, rng: &mut impl Rng) -> Duration {
+    if jitter <= 0.0 {
+        return interval;
+    }
+    interval.mul_f64(1.0 + rng.random_range(-jitter..=jitter))
+}
+
 /// Immutable snapshot of GC statistics
 #[derive(Clone, Debug)]
 pub struct GcStatsSnapshot {
@@ -192,5 +281,59 @@ mod tests {
         assert!(stats.last_run.is_some());
     }
     
+    #[test]
+    fn test_jittered_interval() {
+        let mut rng = rand::rng();
+        let interval = Duration::from_secs(10);
+        assert_eq!(jittered(interval, 0.0, &mut rng), interval);
+        
+        let sleeps: Vec<_> = (0..200).map(|_| jittered(interval, 0.2, &mut rng)).collect();
+        assert!(sleeps.iter().all(|&d| d >= Duration::from_secs(8) && d <= Duration::from_secs(12)));
+        assert!(sleeps.iter().any(|&d| d != interval));
+        
+        // Out-of-range fractions are clamped
+        let gc = GarbageCollector::new(Arc::new(MemTable::new())).with_jitter(3.0);
+        assert_eq!(gc.jitter, 1.0);
+    }
+    
+    #[test]
+    fn test_active_expiry_between_full_cycles() {
+        let mem = Arc::new(MemTable::new());
+        let expired = Arc::new(AtomicUsize::new(0));
+        let counter = expired.clone();
+        mem.set_expired_listener(Box::new(move |_| {
+            counter.fetch_add(1, Ordering::Relaxed);
+        }));
+        for i in 0..200 {
+            let ttl = (i % 4 != 0).then(|| Duration::from_millis(20));
+            mem.set(format!("key_{}", i).as_bytes(), b"value".to_vec(), ttl).unwrap();
+        }
+        
+        // The full cycle is an hour away, so only sampled passes can reap the keys
+        let gc = GarbageCollector::new(mem.clone()).with_active_expiry(100);
+        let handle = gc.start_background_gc(Duration::from_secs(3600));
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while mem.len() > 50 && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(10));
+        }
+        gc.stop();
+        handle.join().unwrap();
+        
+        assert_eq!(mem.len(), 50);
+        assert_eq!(expired.load(Ordering::Relaxed), 150);
+    }
+    
+    #[test]
+    fn test_stop_interrupts_sleep() {
+        let gc = GarbageCollector::new(Arc::new(MemTable::new()));
+        let handle = gc.start_background_gc(Duration::from_secs(3600));
+        thread::sleep(Duration::from_millis(20));
+        
+        let start = Instant::now();
+        gc.stop();
+        handle.join().unwrap();
+        assert!(start.elapsed() < Duration::from_secs(1), "stop took {:?}", start.elapsed());
+    }
+    
     // CRITICAL FIX: Removed dangling }.run_now(); syntax error
 }
\ No newline at end of file
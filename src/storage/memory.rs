@@ -1,24 +1,639 @@
-use std::collections::HashMap;
-use std::sync::{Arc, RwLock};
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BinaryHeap, HashMap, HashSet, VecDeque};
+#[allow(deprecated)]
+use std::hash::{Hasher, SipHasher};
+use std::ops::{Deref, DerefMut};
+use std::sync::{mpsc, Arc, LockResult, Mutex, OnceLock, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard, TryLockError, TryLockResult};
+use std::sync::atomic::{AtomicU32, AtomicU64, AtomicUsize, Ordering};
+use std::thread;
 use std::time::{Duration, Instant};
+use crossbeam_utils::sync::{ShardedLock, ShardedLockReadGuard, ShardedLockWriteGuard};
+use indexmap::IndexMap;
+use rand::Rng;
+
+use crate::storage::hll::HyperLogLog;
+use crate::storage::tiered::{ColdTier, ColdView};
+use crate::storage::value::{Applied, Compression, Mutation, SetOp, ValueKind, DEFAULT_MEMORY_SAMPLES};
+
+/// Values at least this large are dropped on the background reclaim thread
+const LAZYFREE_THRESHOLD: usize = 64 * 1024;
+
+/// Attempts to find a non-expired key before RANDOMKEY gives up
+const RANDOM_KEY_RETRIES: usize = 16;
+
+/// Keys sampled per eviction (Redis maxmemory-samples)
+const EVICTION_SAMPLES: usize = 5;
+
+/// Keys with a TTL sampled per partition pass of active expiry (Redis ACTIVE_EXPIRE_CYCLE_KEYS_PER_LOOP)
+const ACTIVE_EXPIRE_SAMPLES: usize = 20;
+
+/// Entries an active expiry pass looks at while finding its samples, so TTL-less partitions stay cheap
+const ACTIVE_EXPIRE_MAX_LOOKUPS: usize = ACTIVE_EXPIRE_SAMPLES * 10;
+
+/// A partition is sampled again while more than this share of its samples had expired (percent)
+const ACTIVE_EXPIRE_STALE_PERCENT: usize = 10;
+
+/// LFU counter of a new key, so it isn't evicted before it has a chance to be read
+pub const LFU_INIT_VAL: u8 = 5;
+
+/// Morris counter scale - higher means more accesses per increment (Redis lfu-log-factor)
+const LFU_LOG_FACTOR: f64 = 10.0;
+
+/// Minutes per LFU counter decrement when a key goes unused (Redis lfu-decay-time)
+const LFU_DECAY_MINUTES: u32 = 1;
+
+/// Fixed bookkeeping bytes per key (key vec header, entry struct, hash table control byte and hash)
+const ENTRY_OVERHEAD: usize = std::mem::size_of::<Vec<u8>>() + std::mem::size_of::<Entry>() + 1 + 8;
+
+/// EntryView - Snapshot of a key's value and metadata taken under one read lock
+#[derive(Debug, Clone)]
+pub struct EntryView {
+    // Stored value (strings are shared, collections are copied)
+    pub value: ValueKind,
+    
+    // Time left before expiry (None = no TTL)
+    pub remaining_ttl: Option<Duration>,
+    
+    // Redis TYPE name
+    pub kind: &'static str,
+    
+    // Last access before this read
+    pub last_access: Instant,
+}
+
+/// TTL histogram buckets: label and exclusive upper bound in seconds (last is open-ended)
+pub const TTL_BUCKETS: [(&str, u64); 5] = [
+    ("lt_1m", 60),
+    ("lt_1h", 3_600),
+    ("lt_1d", 86_400),
+    ("lt_7d", 604_800),
+    ("ge_7d", u64::MAX),
+];
+
+/// Key count and approximate memory for one value type
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TypeSummary {
+    // Live keys of this type
+    pub keys: usize,
+    
+    // Approximate bytes (sampled for collections)
+    pub bytes: usize,
+}
+
+/// KeyspaceSummary - Key counts by type and TTL for admin dashboards
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct KeyspaceSummary {
+    // Live keys
+    pub total_keys: usize,
+    
+    // Keys with / without a TTL
+    pub with_ttl: usize,
+    pub without_ttl: usize,
+    
+    // Remaining TTLs added up, in milliseconds (see `avg_ttl_ms`)
+    pub ttl_ms_total: u64,
+    
+    // Per TYPE name
+    pub by_type: BTreeMap<&'static str, TypeSummary>,
+    
+    // Keys with a TTL, bucketed by remaining time (see TTL_BUCKETS)
+    pub ttl_histogram: [usize; TTL_BUCKETS.len()],
+}
+
+impl KeyspaceSummary {
+    /// Mean remaining TTL of the keys that have one, in milliseconds (0 when none do)
+    pub fn avg_ttl_ms(&self) -> u64 {
+        self.ttl_ms_total.checked_div(self.with_ttl as u64).unwrap_or(0)
+    }
+}
+
+/// Lock traffic on one partition, from `MemTable::partition_stats`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PartitionStat {
+    // Partition index
+    pub index: usize,
+    
+    // Keys stored (including expired ones not yet reaped)
+    pub keys: usize,
+    
+    // Read and write lock acquisitions
+    pub acquisitions: u64,
+    
+    // Acquisitions that found the lock held and had to block
+    pub contended: u64,
+}
+
+/// Which keys `MemTable::evict` picks when memory has to be freed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EvictionPolicy {
+    // Least recently used key (allkeys-lru)
+    AllKeysLru,
+    
+    // Least frequently used key, recency breaking ties (allkeys-lfu)
+    AllKeysLfu,
+}
+
+/// How each partition is locked - chosen at construction, same API either way
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PartitionBackend {
+    // std RwLock - one lock word shared by readers and writers
+    #[default]
+    RwLock,
+    
+    // crossbeam ShardedLock - readers lock a per-thread shard so they never
+    // bounce a shared cache line; writers lock every shard (slower writes)
+    Sharded,
+}
+
+impl PartitionBackend {
+    /// Parse a backend name (rwlock, sharded)
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "rwlock" => Some(Self::RwLock),
+            "sharded" => Some(Self::Sharded),
+            _ => None,
+        }
+    }
+    
+    /// Backend name
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::RwLock => "rwlock",
+            Self::Sharded => "sharded",
+        }
+    }
+}
+
+/// What writes do once the memory limit is reached (Redis maxmemory-policy)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MaxMemoryPolicy {
+    // Refuse writes with an OOM error (noeviction)
+    #[default]
+    NoEviction,
+    
+    // Evict keys until the write fits
+    Evict(EvictionPolicy),
+}
+
+impl MaxMemoryPolicy {
+    /// Parse a Redis policy name (noeviction, allkeys-lru, allkeys-lfu)
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "noeviction" => Some(Self::NoEviction),
+            "allkeys-lru" => Some(Self::Evict(EvictionPolicy::AllKeysLru)),
+            "allkeys-lfu" => Some(Self::Evict(EvictionPolicy::AllKeysLfu)),
+            _ => None,
+        }
+    }
+    
+    /// Redis policy name
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::NoEviction => "noeviction",
+            Self::Evict(EvictionPolicy::AllKeysLru) => "allkeys-lru",
+            Self::Evict(EvictionPolicy::AllKeysLfu) => "allkeys-lfu",
+        }
+    }
+}
+
+/// Callback invoked with each key reaped by expiry
+pub type ExpiredListener = Box<dyn Fn(&[u8]) + Send + Sync>;
+
+/// Whole value and remaining TTL after an in-place string edit, for the AOF
+pub type WrittenString = Option<(Vec<u8>, Option<Duration>)>;
+
+/// Whole value and remaining TTL after an in-place collection edit, for the AOF
+pub type WrittenValue = Option<(ValueKind, Option<Duration>)>;
 
 /// MemTable - Core in-memory storage engine
-/// Multi-partition hash table with lock-free reads
+/// Multi-partition hash table - reads share a lock on one partition (see PartitionBackend)
 pub struct MemTable {
     // Sharded hash tables for parallelism
-    partitions: Vec<Arc<RwLock<HashMap<Vec<u8>, Entry>>>>,
+    partitions: Vec<Arc<Partition>>,
+    
+    // Partition count minus one (count is a power of two)
+    partition_mask: usize,
+    
+    // Secret SipHash key (two 64-bit halves) so partition collisions can't be precomputed
+    hash_keys: (u64, u64),
+    
+    // Recently deleted key -> tombstone expiry, per partition (empty when disabled)
+    tombstones: Vec<Mutex<HashMap<Vec<u8>, Instant>>>,
+    
+    // How long a deleted key is shielded from replayed writes (None = tombstones off)
+    tombstone_ttl: Option<Duration>,
+    
+    // Notified for every key removed because its TTL passed
+    expired_listener: OnceLock<ExpiredListener>,
+    
+    // Next partition active expiry samples (wraps via partition_mask)
+    expire_cursor: AtomicUsize,
+    
+    // Per partition, the entry index its next active expiry pass starts at
+    expire_positions: Vec<AtomicUsize>,
+    
+    // Background reclaim thread for UNLINK (started on first use)
+    reclaimer: OnceLock<mpsc::Sender<Entry>>,
+    
+    // Write epoch - bumped once per write operation, stamped on what it wrote
+    epoch: AtomicU64,
+    
+    // Open snapshot iterators: epoch -> count
+    snapshots: Mutex<BTreeMap<u64, usize>>,
+    
+    // Number of open snapshot iterators - writers check this first
+    open_snapshots: AtomicUsize,
+    
+    // Epoch of the newest open snapshot (u64::MAX while one is opening), so
+    // writers decide what to preserve without the `snapshots` lock
+    newest_snapshot: AtomicU64,
+    
+    // Replaced or removed entries still visible to an open snapshot, per partition
+    versions: Vec<Mutex<Vec<Version>>>,
+    
+    // Transparent compression of large strings (None = off)
+    compression: Option<Compression>,
+    
+    // Strings stored compressed, and their original and compressed bytes (cumulative)
+    compressed_values: AtomicU64,
+    compressed_raw_bytes: AtomicU64,
+    compressed_stored_bytes: AtomicU64,
+    
+    // Estimated bytes held by live entries (sum of their charged sizes)
+    used_memory: AtomicUsize,
+    
+    // Disk tier idle strings are spilled to (None = everything stays in memory)
+    cold: Option<ColdTier>,
+    
+    // SEQ name -> highest id reserved, kept apart from the keyspace so no command,
+    // FLUSHALL or eviction can lower it
+    sequence_marks: Mutex<HashMap<Vec<u8>, u64>>,
+}
+
+/// One partition's key -> entry table, indexable so eviction can sample in O(1)
+type PartitionMap = IndexMap<Vec<u8>, Entry>;
+
+/// Partition - One partition's lock, counting how often taking it had to wait
+struct Partition {
+    map: PartitionLock,
+    acquisitions: AtomicU64,
+    contended: AtomicU64,
+}
+
+/// The lock around a partition, per PartitionBackend
+enum PartitionLock {
+    Std(RwLock<PartitionMap>),
+    Sharded(ShardedLock<PartitionMap>),
+}
+
+/// Shared access to a partition under either backend
+enum PartitionReadGuard<'a> {
+    Std(RwLockReadGuard<'a, PartitionMap>),
+    Sharded(ShardedLockReadGuard<'a, PartitionMap>),
+}
+
+/// Exclusive access to a partition under either backend
+enum PartitionWriteGuard<'a> {
+    Std(RwLockWriteGuard<'a, PartitionMap>),
+    Sharded(ShardedLockWriteGuard<'a, PartitionMap>),
+}
+
+impl Partition {
+    fn new(backend: PartitionBackend) -> Self {
+        let map = match backend {
+            PartitionBackend::RwLock => PartitionLock::Std(RwLock::new(PartitionMap::new())),
+            PartitionBackend::Sharded => PartitionLock::Sharded(ShardedLock::new(PartitionMap::new())),
+        };
+        Self {
+            map,
+            acquisitions: AtomicU64::new(0),
+            contended: AtomicU64::new(0),
+        }
+    }
+    
+    /// Read-lock, trying without blocking first so waits can be counted
+    fn read(&self) -> LockResult<PartitionReadGuard<'_>> {
+        self.acquisitions.fetch_add(1, Ordering::Relaxed);
+        match &self.map {
+            PartitionLock::Std(lock) => map_guard(
+                self.counted(|| lock.try_read(), || lock.read()), PartitionReadGuard::Std),
+            PartitionLock::Sharded(lock) => map_guard(
+                self.counted(|| lock.try_read(), || lock.read()), PartitionReadGuard::Sharded),
+        }
+    }
+    
+    /// Write-lock, trying without blocking first so waits can be counted
+    fn write(&self) -> LockResult<PartitionWriteGuard<'_>> {
+        self.acquisitions.fetch_add(1, Ordering::Relaxed);
+        match &self.map {
+            PartitionLock::Std(lock) => map_guard(
+                self.counted(|| lock.try_write(), || lock.write()), PartitionWriteGuard::Std),
+            PartitionLock::Sharded(lock) => map_guard(
+                self.counted(|| lock.try_write(), || lock.write()), PartitionWriteGuard::Sharded),
+        }
+    }
+    
+    /// Keys stored, read without touching the counters
+    fn len(&self) -> usize {
+        match &self.map {
+            PartitionLock::Std(lock) => lock.read().unwrap_or_else(PoisonError::into_inner).len(),
+            PartitionLock::Sharded(lock) => lock.read().unwrap_or_else(PoisonError::into_inner).len(),
+        }
+    }
+    
+    /// Take the lock without blocking if possible, else count a wait and block
+    fn counted<G>(&self, try_lock: impl FnOnce() -> TryLockResult<G>, lock: impl FnOnce() -> LockResult<G>) -> LockResult<G> {
+        match try_lock() {
+            Ok(guard) => Ok(guard),
+            Err(TryLockError::Poisoned(e)) => Err(e),
+            Err(TryLockError::WouldBlock) => {
+                self.contended.fetch_add(1, Ordering::Relaxed);
+                lock()
+            }
+        }
+    }
+}
+
+/// Wrap a backend guard, keeping poisoning intact
+fn map_guard<G, H>(result: LockResult<G>, wrap: impl FnOnce(G) -> H) -> LockResult<H> {
+    match result {
+        Ok(guard) => Ok(wrap(guard)),
+        Err(e) => Err(PoisonError::new(wrap(e.into_inner()))),
+    }
+}
+
+impl Deref for PartitionReadGuard<'_> {
+    type Target = PartitionMap;
+    
+    fn deref(&self) -> &PartitionMap {
+        match self {
+            Self::Std(guard) => guard,
+            Self::Sharded(guard) => guard,
+        }
+    }
+}
+
+impl Deref for PartitionWriteGuard<'_> {
+    type Target = PartitionMap;
+    
+    fn deref(&self) -> &PartitionMap {
+        match self {
+            Self::Std(guard) => guard,
+            Self::Sharded(guard) => guard,
+        }
+    }
+}
+
+impl DerefMut for PartitionWriteGuard<'_> {
+    fn deref_mut(&mut self) -> &mut PartitionMap {
+        match self {
+            Self::Std(guard) => guard,
+            Self::Sharded(guard) => guard,
+        }
+    }
+}
+
+/// MultiPartitionGuard - Write locks on every partition a multi-key command touches
+/// Locks are taken in ascending partition order, so two guards never deadlock.
+/// Every key passed to the accessors must have been passed to `lock_partitions`
+pub struct MultiPartitionGuard<'a> {
+    // Table the locks belong to (for hashing and reclaiming replaced values)
+    table: &'a MemTable,
+    
+    // Partition index and its write lock, ascending by index
+    guards: Vec<(usize, PartitionWriteGuard<'a>)>,
+    
+    // Expiry reference time for the whole operation
+    now: Instant,
+    
+    // Write epoch shared by every key the operation writes
+    epoch: u64,
+}
+
+/// SnapshotIter - Point-in-time iterator over live entries, from `MemTable::snapshot_iter`
+/// Writes made after it was created are invisible to it, and each key is yielded once.
+/// Partitions are read one at a time, so writers are never blocked for the whole scan
+pub struct SnapshotIter<'a> {
+    // Table being read
+    table: &'a MemTable,
+    
+    // Last write epoch included in the view
+    epoch: u64,
+    
+    // Expiry reference time for the view
+    now: Instant,
+    
+    // Next partition to read
+    next_partition: usize,
+    
+    // Entries of the partition being yielded
+    buffered: std::vec::IntoIter<(Vec<u8>, ValueKind, Option<Duration>)>,
+    
+    // Keys that were on disk when the view was taken, yielded after the partitions
+    cold: Option<ColdView<'a>>,
+}
+
+/// An entry as it was before a write replaced or removed it
+struct Version {
+    key: Vec<u8>,
+    value: ValueKind,
+    expires_at: Option<Instant>,
     
-    // Number of partitions (shards)
-    partition_count: usize,
+    // Epochs over which this was the key's entry: [from, until)
+    from: u64,
+    until: u64,
 }
 
 /// Storage entry - value with metadata
 struct Entry {
-    // Actual value bytes
-    value: Vec<u8>,
+    // Stored value (string or collection)
+    value: ValueKind,
     
     // Optional expiration time
     expires_at: Option<Instant>,
+    
+    // Last access time in ms since clock start (LRU recency)
+    last_access: AtomicU64,
+    
+    // LFU counter (low 8 bits) and access clock minute of its last decay (high 16 bits)
+    lfu: AtomicU32,
+    
+    // Write epoch of the last change to value or expiry
+    epoch: u64,
+    
+    // Bytes charged to the table's used_memory for this entry
+    size: usize,
+}
+
+impl Entry {
+    /// Create entry written at `epoch`, stamped with the current access time
+    fn new(value: ValueKind, expires_at: Option<Instant>, epoch: u64) -> Self {
+        Self {
+            value,
+            expires_at,
+            last_access: AtomicU64::new(clock_ms()),
+            lfu: AtomicU32::new(pack_lfu(clock_minutes(), LFU_INIT_VAL)),
+            epoch,
+            size: 0,
+        }
+    }
+    
+    /// Whether the entry's TTL has passed
+    fn is_expired(&self, now: Instant) -> bool {
+        self.expires_at.is_some_and(|expires| now > expires)
+    }
+    
+    /// Whether a live string unused for at least `idle_ms` (access clock ms at `clock`)
+    fn is_spillable(&self, now: Instant, clock: u64, idle_ms: u64) -> bool {
+        matches!(self.value, ValueKind::String(_) | ValueKind::Compressed(_))
+            && !self.is_expired(now)
+            && clock.saturating_sub(self.last_access.load(Ordering::Relaxed)) >= idle_ms
+    }
+    
+    /// Refresh LRU recency and count an LFU access
+    fn touch(&self) {
+        self.last_access.store(clock_ms(), Ordering::Relaxed);
+        
+        // Racing updates may drop an increment - the counter is approximate anyway
+        let lfu = pack_lfu(clock_minutes(), lfu_log_incr(self.frequency()));
+        if lfu != self.lfu.load(Ordering::Relaxed) {
+            self.lfu.store(lfu, Ordering::Relaxed);
+        }
+    }
+    
+    /// LFU counter after decaying it for the minutes since the last access
+    fn frequency(&self) -> u8 {
+        let lfu = self.lfu.load(Ordering::Relaxed);
+        let (counter, minute) = ((lfu & 0xFF) as u8, (lfu >> 16) as u16);
+        
+        // The minute clock wraps every ~45 days
+        let elapsed = (clock_minutes() as u16).wrapping_sub(minute) as u32;
+        let periods = elapsed / LFU_DECAY_MINUTES;
+        counter.saturating_sub(periods.min(u8::MAX as u32) as u8)
+    }
+    
+    /// Eviction order under `policy` - lowest goes first, expired entries before all
+    fn eviction_rank(&self, policy: EvictionPolicy, now: Instant) -> u64 {
+        if self.is_expired(now) {
+            return 0;
+        }
+        
+        let last_access = self.last_access.load(Ordering::Relaxed) + 1;
+        match policy {
+            EvictionPolicy::AllKeysLru => last_access,
+            EvictionPolicy::AllKeysLfu => ((self.frequency() as u64) << 48) | last_access.min((1 << 48) - 1),
+        }
+    }
+}
+
+/// Normalize a Redis-style inclusive range over `len` bytes or elements
+/// Returns None when the range selects nothing
+fn index_range(len: usize, start: i64, end: i64) -> Option<(usize, usize)> {
+    let len = len as i64;
+    let start = if start < 0 { (len + start).max(0) } else { start };
+    let end = if end < 0 { len + end } else { end.min(len - 1) };
+    
+    if len == 0 || end < 0 || start > end {
+        return None;
+    }
+    Some((start as usize, end as usize))
+}
+
+/// Count set bits a word at a time
+fn popcount(bytes: &[u8]) -> u64 {
+    let chunks = bytes.chunks_exact(8);
+    let tail = chunks.remainder().iter().map(|b| b.count_ones() as u64).sum::<u64>();
+    chunks.map(|chunk| u64::from_ne_bytes(chunk.try_into().unwrap()).count_ones() as u64).sum::<u64>() + tail
+}
+
+/// Pack an LFU counter with the minute it was last decayed
+fn pack_lfu(minute: u32, counter: u8) -> u32 {
+    ((minute & 0xFFFF) << 16) | counter as u32
+}
+
+/// Logarithmic (Morris) increment - the more accesses, the less likely another bump
+fn lfu_log_incr(counter: u8) -> u8 {
+    if counter == u8::MAX {
+        return counter;
+    }
+    
+    // Up to the initial value every access counts - no draw needed
+    let base = counter.saturating_sub(LFU_INIT_VAL) as f64;
+    if base == 0.0 {
+        return counter + 1;
+    }
+    
+    let p = 1.0 / (base * LFU_LOG_FACTOR + 1.0);
+    if rand::random::<f64>() < p {
+        counter + 1
+    } else {
+        counter
+    }
+}
+
+impl MultiPartitionGuard<'_> {
+    /// Live value of a key
+    pub fn get(&self, key: &[u8]) -> Option<&ValueKind> {
+        self.entry(key).map(|entry| &entry.value)
+    }
+    
+    /// Remaining TTL of a live key (None = missing or no TTL)
+    pub fn ttl(&self, key: &[u8]) -> Option<Duration> {
+        self.entry(key)?
+            .expires_at
+            .map(|expires| expires.saturating_duration_since(self.now))
+    }
+    
+    /// Store a value, replacing whatever the key held
+    pub fn set(&mut self, key: &[u8], value: ValueKind, ttl: Option<Duration>) {
+        let (table, epoch) = (self.table, self.epoch);
+        let entry = Entry::new(value, ttl.map(|d| self.now + d), epoch);
+        if let Some(old) = table.insert_entry(self.partition_mut(key), key, entry) {
+            table.reclaim(old);
+        }
+    }
+    
+    /// Remove a key, returning its value if it was live
+    pub fn remove(&mut self, key: &[u8]) -> Option<ValueKind> {
+        let (table, epoch, now) = (self.table, self.epoch, self.now);
+        table.remove_entry(self.partition_mut(key), key, epoch)
+            .filter(|entry| !entry.is_expired(now))
+            .map(|entry| entry.value)
+    }
+    
+    /// Live entry of a key
+    fn entry(&self, key: &[u8]) -> Option<&Entry> {
+        let idx = self.table.partition_index(key);
+        let (_, guard) = self.guards.iter()
+            .find(|(i, _)| *i == idx)
+            .expect("key's partition not locked by this guard");
+        guard.get(key).filter(|entry| !entry.is_expired(self.now))
+    }
+    
+    /// Locked partition holding a key
+    fn partition_mut(&mut self, key: &[u8]) -> &mut PartitionMap {
+        let idx = self.table.partition_index(key);
+        let (_, guard) = self.guards.iter_mut()
+            .find(|(i, _)| *i == idx)
+            .expect("key's partition not locked by this guard");
+        guard
+    }
+}
+
+/// Origin of the MemTable access clock
+fn clock_start() -> Instant {
+    static CLOCK_START: OnceLock<Instant> = OnceLock::new();
+    *CLOCK_START.get_or_init(Instant::now)
+}
+
+/// Milliseconds elapsed on the MemTable access clock
+fn clock_ms() -> u64 {
+    clock_start().elapsed().as_millis() as u64
+}
+
+/// Minutes elapsed on the MemTable access clock (LFU decay resolution)
+fn clock_minutes() -> u32 {
+    (clock_start().elapsed().as_secs() / 60) as u32
 }
 
 impl MemTable {
@@ -34,14 +649,11 @@ impl MemTable {
     }
     pub fn recover_set(&self, key: &[u8], value: Vec<u8>, ttl: Option<Duration>) -> Result<(), String> {
         let partition = self.get_partition_for_key(key);
-        let entry = Entry {
-            value,
-            expires_at: ttl.map(|d| Instant::now() + d)
-        };
-
-        partition.write()
-            .map_err(|e| format!("Lock error: {:?}", e))?
-            .insert(key.to_vec(), entry);
+        let mut guard = partition.write()
+            .map_err(|e| format!("Lock error: {:?}", e))?;
+        
+        let entry = Entry::new(self.string_value(value), ttl.map(|d| Instant::now() + d), self.next_epoch());
+        self.insert_entry(&mut guard, key, entry);
         
         Ok(())
     }
@@ -49,144 +661,1540 @@ impl MemTable {
     /// Special delete for recovery that bypasses AOF logging
     pub fn recover_delete(&self, key: &[u8]) -> Result<bool, String> {
         let partition = self.get_partition_for_key(key);
-        Ok(partition.write()
-            .map_err(|e| format!("Lock error: {:?}", e))?
-            .remove(key)
-            .is_some())
+        let mut guard = partition.write()
+            .map_err(|e| format!("Lock error: {:?}", e))?;
+        Ok(self.remove_entry(&mut guard, key, self.next_epoch()).is_some())
     }
-    /// Create with specific partition count
+    /// Create with specific partition count and a random hash seed
+    /// The count is rounded up to the next power of two so keys map by masking
     pub fn with_partitions(count: usize) -> Self {
+        Self::with_hasher(count, rand::rng().random())
+    }
+    
+    /// Create with specific partition count and hash seed (fixed seeds give reproducible partitioning)
+    pub fn with_hasher(count: usize, seed: u128) -> Self {
+        let count = count.max(1).next_power_of_two();
         let partitions = (0..count)
-            .map(|_| Arc::new(RwLock::new(HashMap::new())))
+            .map(|_| Arc::new(Partition::new(PartitionBackend::default())))
             .collect();
             
         Self {
             partitions,
-            partition_count: count,
+            partition_mask: count - 1,
+            hash_keys: ((seed >> 64) as u64, seed as u64),
+            tombstones: Vec::new(),
+            tombstone_ttl: None,
+            expired_listener: OnceLock::new(),
+            expire_cursor: AtomicUsize::new(0),
+            expire_positions: (0..count).map(|_| AtomicUsize::new(0)).collect(),
+            reclaimer: OnceLock::new(),
+            epoch: AtomicU64::new(0),
+            snapshots: Mutex::new(BTreeMap::new()),
+            open_snapshots: AtomicUsize::new(0),
+            newest_snapshot: AtomicU64::new(0),
+            versions: (0..count).map(|_| Mutex::new(Vec::new())).collect(),
+            compression: None,
+            compressed_values: AtomicU64::new(0),
+            compressed_raw_bytes: AtomicU64::new(0),
+            compressed_stored_bytes: AtomicU64::new(0),
+            used_memory: AtomicUsize::new(0),
+            cold: None,
+            sequence_marks: Mutex::new(HashMap::new()),
+        }
+    }
+    
+    /// Keep a tombstone for `ttl` after each DEL/UNLINK (None disables)
+    /// While it lives, replayed writes for the key are ignored, so a concurrent
+    /// replay cannot resurrect it. Costs memory per recently deleted key
+    pub fn with_tombstone_ttl(mut self, ttl: Option<Duration>) -> Self {
+        self.tombstone_ttl = ttl;
+        self.tombstones = match ttl {
+            Some(_) => (0..self.partition_count()).map(|_| Mutex::new(HashMap::new())).collect(),
+            None => Vec::new(),
+        };
+        self
+    }
+    
+    /// Compress string values over the threshold (None disables)
+    /// Reads decompress transparently, so callers only ever see the original bytes
+    pub fn with_compression(mut self, compression: Option<Compression>) -> Self {
+        self.compression = compression;
+        self
+    }
+    
+    /// Spill idle strings to `cold` (see `spill_idle`) and bring them back when used
+    pub fn with_cold_tier(mut self, cold: ColdTier) -> Self {
+        self.cold = Some(cold);
+        self
+    }
+    
+    /// Disk tier, if one is configured
+    pub fn cold_tier(&self) -> Option<&ColdTier> {
+        self.cold.as_ref()
+    }
+    
+    /// Lock partitions with `backend` - a construction-time choice, so the table must still be empty
+    pub fn with_backend(mut self, backend: PartitionBackend) -> Self {
+        debug_assert!(self.partitions.iter().all(|partition| partition.len() == 0), "backend changed on a populated table");
+        self.partitions = (0..self.partition_count())
+            .map(|_| Arc::new(Partition::new(backend)))
+            .collect();
+        self
+    }
+    
+    /// Build a string value, compressed if configured and worthwhile
+    pub fn string_value(&self, bytes: Vec<u8>) -> ValueKind {
+        let value = ValueKind::string(bytes, self.compression);
+        if let ValueKind::Compressed(compressed) = &value {
+            self.compressed_values.fetch_add(1, Ordering::Relaxed);
+            self.compressed_raw_bytes.fetch_add(compressed.raw_len() as u64, Ordering::Relaxed);
+            self.compressed_stored_bytes.fetch_add(compressed.stored_len() as u64, Ordering::Relaxed);
+        }
+        value
+    }
+    
+    /// Strings compressed so far, with their original and compressed byte totals
+    pub fn compression_stats(&self) -> (u64, u64, u64) {
+        (
+            self.compressed_values.load(Ordering::Relaxed),
+            self.compressed_raw_bytes.load(Ordering::Relaxed),
+            self.compressed_stored_bytes.load(Ordering::Relaxed),
+        )
+    }
+    
+    /// Estimated bytes held by live entries (keys, values and per-entry overhead)
+    pub fn used_memory(&self) -> usize {
+        self.used_memory.load(Ordering::Relaxed)
+    }
+    
+    /// Whether the key was deleted within the tombstone window
+    pub fn is_tombstoned(&self, key: &[u8]) -> bool {
+        let Some(tombstones) = self.tombstones.get(self.partition_index(key)) else {
+            return false;
+        };
+        
+        tombstones.lock()
+            .ok()
+            .and_then(|guard| guard.get(key).copied())
+            .is_some_and(|until| Instant::now() <= until)
+    }
+    
+    /// Register listener for keys reaped by expiry (first registration wins)
+    pub fn set_expired_listener(&self, listener: ExpiredListener) -> bool {
+        self.expired_listener.set(listener).is_ok()
+    }
+    
+    /// Number of keys, counting expired ones GC hasn't reaped yet (one partition locked at a time)
+    pub fn len(&self) -> usize {
+        let cold = self.cold.as_ref().map_or(0, ColdTier::len);
+        self.partitions.iter()
+            .map(|partition| partition.read().unwrap_or_else(PoisonError::into_inner).len())
+            .sum::<usize>() + cold
+    }
+    
+    /// Whether no partition holds a key
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    
+    /// Get partition count (after power-of-two rounding)
+    pub fn partition_count(&self) -> usize {
+        self.partition_mask + 1
+    }
+    
+    /// Lock acquisitions and contention per partition, for spotting hot partitions
+    /// Key counts are read without touching the counters
+    pub fn partition_stats(&self) -> Vec<PartitionStat> {
+        self.partitions.iter()
+            .enumerate()
+            .map(|(index, partition)| PartitionStat {
+                index,
+                keys: partition.len(),
+                acquisitions: partition.acquisitions.load(Ordering::Relaxed),
+                contended: partition.contended.load(Ordering::Relaxed),
+            })
+            .collect()
+    }
+    
+    /// Get string value by key (None for missing keys and non-string values)
+    /// Returns a shared handle, so large values are not copied
+    pub fn get(&self, key: &[u8]) -> Option<Arc<[u8]>> {
+        self.get_string(key).ok().flatten()
+    }
+    
+    /// Get string value by key, or WRONGTYPE if the key holds a collection
+    pub fn get_string(&self, key: &[u8]) -> Result<Option<Arc<[u8]>>, String> {
+        // Get partition for this key
+        let partition = self.get_partition_for_key(key);
+        
+        // Acquire read lock on just this partition
+        if let Ok(guard) = partition.read() {
+            // Check if key exists and is not expired
+            if let Some(entry) = guard.get(key) {
+                // Check expiration
+                if let Some(expires) = entry.expires_at {
+                    if Instant::now() > expires {
+                        // Expired entry - treat as non-existent
+                        return Ok(None);
+                    }
+                }
+                
+                // Return shared value
+                entry.touch();
+                return entry.value.shared_string().map(Some);
+            }
+        }
+        
+        Ok(None)
+    }
+    
+    /// Value, TTL, type and last access of a live key in one lock acquisition
+    /// Counts as an access, so the key's recency is refreshed afterwards
+    pub fn get_full(&self, key: &[u8]) -> Option<EntryView> {
+        let partition = self.get_partition_for_key(key);
+        let guard = partition.read().ok()?;
+        let now = Instant::now();
+        let entry = guard.get(key).filter(|entry| !entry.is_expired(now))?;
+        
+        let last_access_ms = entry.last_access.load(Ordering::Relaxed);
+        let view = EntryView {
+            value: entry.value.clone(),
+            remaining_ttl: entry.expires_at.map(|expires| expires.saturating_duration_since(now)),
+            kind: entry.value.type_name(),
+            last_access: clock_start() + Duration::from_millis(last_access_ms),
+        };
+        entry.touch();
+        
+        Some(view)
+    }
+    
+    /// Set value with optional TTL
+    pub fn set(&self, key: &[u8], value: Vec<u8>, ttl: Option<Duration>) -> Result<(), String> {
+        // Calculate expiration time if TTL provided
+        let expires_at = ttl.map(|duration| Instant::now() + duration);
+        
+        // Get partition for this key
+        let partition = self.get_partition_for_key(key);
+        
+        // Acquire write lock on just this partition
+        if let Ok(mut guard) = partition.write() {
+            // Insert or replace entry
+            let entry = Entry::new(self.string_value(value), expires_at, self.next_epoch());
+            self.insert_entry(&mut guard, key, entry);
+            Ok(())
+        } else {
+            Err("Failed to acquire write lock".to_string())
+        }
+    }
+    
+    /// Store many string values, write-locking each involved partition once
+    /// All partitions are held together so readers see the batch atomically
+    pub fn set_batch(&self, entries: &[(Vec<u8>, Vec<u8>, Option<Duration>)]) -> Result<(), String> {
+        let values: Vec<_> = entries.iter()
+            .map(|(key, value, ttl)| (key.clone(), self.string_value(value.clone()), *ttl))
+            .collect();
+        self.set_values(&values)
+    }
+    
+    /// Store many typed values atomically, like `set_batch`
+    pub fn set_values(&self, entries: &[(Vec<u8>, ValueKind, Option<Duration>)]) -> Result<(), String> {
+        let keys: Vec<&[u8]> = entries.iter().map(|(key, _, _)| key.as_slice()).collect();
+        let mut guard = self.lock_partitions(&keys)?;
+        
+        for (key, value, ttl) in entries {
+            guard.set(key, value.clone(), *ttl);
+        }
+        
+        Ok(())
+    }
+    
+    /// Write-lock the partitions of every key in ascending index order
+    /// Multi-key mutations (MSET, RENAME, S*STORE) go through this so they
+    /// always lock in the same order and can't deadlock each other
+    pub fn lock_partitions(&self, keys: &[&[u8]]) -> Result<MultiPartitionGuard<'_>, String> {
+        let order = self.lock_order(keys);
+        
+        let mut guards = Vec::with_capacity(order.len());
+        for idx in order {
+            let guard = self.partitions[idx].write()
+                .map_err(|_| "Failed to acquire write lock".to_string())?;
+            guards.push((idx, guard));
+        }
+        
+        Ok(MultiPartitionGuard {
+            table: self,
+            guards,
+            now: Instant::now(),
+            epoch: self.next_epoch(),
+        })
+    }
+    
+    /// Delete value by key
+    pub fn delete(&self, key: &[u8]) -> Result<bool, String> {
+        // Get partition for this key
+        let partition = self.get_partition_for_key(key);
+        
+        // Acquire write lock on just this partition
+        if let Ok(mut guard) = partition.write() {
+            // Remove key and return whether it existed
+            let existed = self.remove_entry(&mut guard, key, self.next_epoch()).is_some();
+            self.record_tombstone(key);
+            Ok(existed)
+        } else {
+            Err("Failed to acquire write lock".to_string())
+        }
+    }
+    
+    /// Delete value by key, deferring deallocation of large values
+    pub fn unlink(&self, key: &[u8]) -> Result<bool, String> {
+        let partition = self.get_partition_for_key(key);
+        
+        // Only unhook the entry while holding the lock
+        let removed = {
+            let mut guard = partition.write()
+                .map_err(|_| "Failed to acquire write lock".to_string())?;
+            let removed = self.remove_entry(&mut guard, key, self.next_epoch());
+            self.record_tombstone(key);
+            removed
+        };
+        
+        match removed {
+            Some(entry) => {
+                self.reclaim(entry);
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+    
+    /// Pick a random non-expired key
+    /// Partitions are weighted by entry count so keys are roughly uniform
+    pub fn random_key(&self) -> Option<Vec<u8>> {
+        let mut rng = rand::rng();
+        
+        for _ in 0..RANDOM_KEY_RETRIES {
+            // Snapshot partition sizes to weight the choice
+            let sizes: Vec<usize> = self.partitions.iter()
+                .map(|p| p.read().unwrap_or_else(PoisonError::into_inner).len())
+                .collect();
+            let total: usize = sizes.iter().sum();
+            if total == 0 {
+                return None;
+            }
+            
+            // Map a global index onto (partition, index within partition)
+            let mut target = rng.random_range(0..total);
+            let mut idx = 0;
+            while target >= sizes[idx] {
+                target -= sizes[idx];
+                idx += 1;
+            }
+            
+            let guard = self.partitions[idx].read().ok()?;
+            
+            // Partition may have shrunk since sizes were sampled
+            if guard.is_empty() {
+                continue;
+            }
+            let (key, entry) = guard.get_index(target % guard.len())?;
+            
+            if !entry.is_expired(Instant::now()) {
+                return Some(key.clone());
+            }
+        }
+        
+        None
+    }
+    
+    /// Refresh a key's last access time without reading its value
+    /// Returns false for missing or expired keys
+    pub fn touch(&self, key: &[u8]) -> bool {
+        let partition = self.get_partition_for_key(key);
+        
+        let Ok(guard) = partition.write() else {
+            return false;
+        };
+        
+        match guard.get(key) {
+            Some(entry) if !entry.is_expired(Instant::now()) => {
+                entry.touch();
+                true
+            }
+            _ => false,
+        }
+    }
+    
+    /// Time since a key was last accessed
+    pub fn idle_time(&self, key: &[u8]) -> Option<Duration> {
+        let idx = self.partition_index(key);
+        self.fault_in(idx, key, false);
+        let guard = self.partitions[idx].read().ok()?;
+        let entry = guard.get(key)?;
+        
+        if entry.is_expired(Instant::now()) {
+            return None;
+        }
+        
+        let idle_ms = clock_ms().saturating_sub(entry.last_access.load(Ordering::Relaxed));
+        Some(Duration::from_millis(idle_ms))
+    }
+    
+    /// Logarithmic access frequency (0-255, decayed while idle) - not itself an access
+    pub fn access_frequency(&self, key: &[u8]) -> Option<u8> {
+        let idx = self.partition_index(key);
+        self.fault_in(idx, key, false);
+        let guard = self.partitions[idx].read().ok()?;
+        
+        guard.get(key)
+            .filter(|entry| !entry.is_expired(Instant::now()))
+            .map(|entry| entry.frequency())
+    }
+    
+    /// Remove one key chosen by `policy` and return it (None when empty)
+    /// Like Redis, this samples a few keys from one partition rather than
+    /// finding the global minimum; expired keys are always taken first
+    pub fn evict(&self, policy: EvictionPolicy) -> Option<Vec<u8>> {
+        let mut rng = rand::rng();
+        let start = rng.random_range(0..self.partition_count());
+        
+        for step in 0..self.partition_count() {
+            let partition = &self.partitions[(start + step) & self.partition_mask];
+            
+            let victim = {
+                let guard = partition.read().ok()?;
+                if guard.is_empty() {
+                    continue;
+                }
+                
+                let now = Instant::now();
+                rand::seq::index::sample(&mut rng, guard.len(), EVICTION_SAMPLES.min(guard.len()))
+                    .into_iter()
+                    .filter_map(|index| guard.get_index(index))
+                    .min_by_key(|(_, entry)| entry.eviction_rank(policy, now))
+                    .map(|(key, _)| key.clone())?
+            };
+            
+            // The victim may have been deleted between the read and write locks
+            let removed = {
+                let mut guard = partition.write().ok()?;
+                self.remove_entry(&mut guard, &victim, self.next_epoch())
+            };
+            if let Some(entry) = removed {
+                self.reclaim(entry);
+                return Some(victim);
+            }
+        }
+        
+        None
+    }
+    
+    /// Replace a key's TTL (None removes it)
+    /// Returns false for missing or expired keys
+    pub fn set_expiry(&self, key: &[u8], ttl: Option<Duration>) -> bool {
+        let partition = self.get_partition_for_key(key);
+        
+        let Ok(mut guard) = partition.write() else {
+            return false;
+        };
+        
+        let now = Instant::now();
+        if !guard.get(key).is_some_and(|entry| !entry.is_expired(now)) {
+            return false;
+        }
+        
+        let entry = self.entry_mut(&mut guard, key, self.next_epoch()).expect("entry present");
+        entry.expires_at = ttl.map(|d| now + d);
+        entry.touch();
+        true
+    }
+    
+    /// Remaining time to live of a key (None for missing keys or no TTL)
+    pub fn ttl(&self, key: &[u8]) -> Option<Duration> {
+        let partition = self.get_partition_for_key(key);
+        let guard = partition.read().ok()?;
+        let now = Instant::now();
+        
+        guard.get(key)
+            .filter(|entry| !entry.is_expired(now))
+            .and_then(|entry| entry.expires_at)
+            .map(|expires| expires.saturating_duration_since(now))
+    }
+    
+    /// Remove every key, returning how many were dropped
+    pub fn clear(&self) -> usize {
+        let mut removed = 0;
+        
+        for partition in &self.partitions {
+            if let Ok(mut guard) = partition.write() {
+                removed += guard.len();
+                
+                let epoch = self.next_epoch();
+                for (key, entry) in guard.iter() {
+                    self.preserve(key, entry, epoch);
+                }
+                
+                // Free the old table after releasing the partition lock
+                let old = std::mem::take(&mut *guard);
+                drop(guard);
+                self.used_memory.fetch_sub(old.values().map(|entry| entry.size).sum(), Ordering::Relaxed);
+                drop(old);
+            }
+        }
+        
+        removed + self.cold.as_ref().map_or(0, ColdTier::clear)
+    }
+    
+    /// Highest id reserved for sequence `name` (0 = none yet)
+    pub fn sequence_mark(&self, name: &[u8]) -> u64 {
+        let marks = self.sequence_marks.lock().unwrap_or_else(PoisonError::into_inner);
+        marks.get(name).copied().unwrap_or(0)
+    }
+    
+    /// Raise the mark of sequence `name` to `high_water`; a lower one is ignored
+    pub fn raise_sequence_mark(&self, name: &[u8], high_water: u64) {
+        let mut marks = self.sequence_marks.lock().unwrap_or_else(PoisonError::into_inner);
+        let mark = marks.entry(name.to_vec()).or_insert(0);
+        *mark = (*mark).max(high_water);
+    }
+    
+    /// Every sequence and its mark
+    pub fn sequence_marks(&self) -> Vec<(Vec<u8>, u64)> {
+        let marks = self.sequence_marks.lock().unwrap_or_else(PoisonError::into_inner);
+        marks.iter().map(|(name, &mark)| (name.clone(), mark)).collect()
+    }
+    
+    /// Copy of every live entry with its remaining TTL, as of the call
+    pub fn snapshot_entries(&self) -> Vec<(Vec<u8>, ValueKind, Option<Duration>)> {
+        self.snapshot_iter().collect()
+    }
+    
+    /// Iterate over live entries (key, value, remaining TTL) as of the call
+    /// Concurrent writes are not seen; entries they replace or remove are kept
+    /// aside until every snapshot that can still see them is dropped
+    pub fn snapshot_iter(&self) -> SnapshotIter<'_> {
+        // Keys can't move between tiers while the epoch is taken, so each is seen exactly once
+        let (epoch, cold) = match &self.cold {
+            Some(cold) => {
+                let (epoch, view) = cold.capture(|| self.open_snapshot());
+                (epoch, Some(view))
+            }
+            None => (self.open_snapshot(), None),
+        };
+        
+        SnapshotIter {
+            table: self,
+            epoch,
+            now: Instant::now(),
+            next_partition: 0,
+            buffered: Vec::new().into_iter(),
+            cold,
+        }
+    }
+    
+    /// Register a snapshot at the current epoch, returning it
+    fn open_snapshot(&self) -> u64 {
+        // Counted before the epoch is read, so writers after it preserve (see `preserve`)
+        self.open_snapshots.fetch_add(1, Ordering::SeqCst);
+        // Writers preserve everything while the epoch is being read, see `preserve`
+        let mut open = self.snapshots.lock().unwrap_or_else(PoisonError::into_inner);
+        self.newest_snapshot.store(u64::MAX, Ordering::SeqCst);
+        let epoch = self.epoch.load(Ordering::SeqCst);
+        *open.entry(epoch).or_insert(0) += 1;
+        self.newest_snapshot.store(*open.keys().next_back().expect("just inserted"), Ordering::SeqCst);
+        epoch
+    }
+    
+    /// Up to `limit` live keys, most recently accessed first
+    /// Only the `limit` best candidates are kept while scanning, so memory stays bounded
+    pub fn hottest_keys(&self, limit: usize) -> Vec<Vec<u8>> {
+        let mut hottest = BinaryHeap::with_capacity(limit + 1);
+        for partition in &self.partitions {
+            let guard = partition.read().unwrap_or_else(PoisonError::into_inner);
+            let now = Instant::now();
+            
+            for (key, entry) in guard.iter().filter(|(_, entry)| !entry.is_expired(now)) {
+                let last_access = entry.last_access.load(Ordering::Relaxed);
+                if hottest.len() == limit && hottest.peek().is_none_or(|Reverse((oldest, _))| last_access <= *oldest) {
+                    continue;
+                }
+                hottest.push(Reverse((last_access, key.to_vec())));
+                if hottest.len() > limit {
+                    hottest.pop();
+                }
+            }
+        }
+        
+        hottest.into_sorted_vec().into_iter().map(|Reverse((_, key))| key).collect()
+    }
+    
+    /// Count keys by type and TTL in one O(n) pass
+    /// Partitions are read-locked one at a time, so the totals are not point-in-time
+    pub fn keyspace_summary(&self) -> KeyspaceSummary {
+        let mut summary = KeyspaceSummary::default();
+        
+        for partition in &self.partitions {
+            let guard = partition.read().unwrap_or_else(PoisonError::into_inner);
+            let now = Instant::now();
+            
+            for (key, entry) in guard.iter().filter(|(_, entry)| !entry.is_expired(now)) {
+                summary.total_keys += 1;
+                
+                let by_type = summary.by_type.entry(entry.value.type_name()).or_default();
+                by_type.keys += 1;
+                by_type.bytes += key.len() + entry.value.mem_size(DEFAULT_MEMORY_SAMPLES) + ENTRY_OVERHEAD;
+                
+                match entry.expires_at {
+                    Some(expires) => {
+                        let secs = expires.saturating_duration_since(now).as_secs();
+                        let bucket = TTL_BUCKETS.iter().position(|&(_, bound)| secs < bound)
+                            .unwrap_or(TTL_BUCKETS.len() - 1);
+                        summary.ttl_histogram[bucket] += 1;
+                        summary.with_ttl += 1;
+                        summary.ttl_ms_total += expires.saturating_duration_since(now).as_millis() as u64;
+                    }
+                    None => summary.without_ttl += 1,
+                }
+            }
+        }
+        
+        // Cold keys are strings that hold no memory
+        if let Some(cold) = &self.cold {
+            let now = Instant::now();
+            cold.for_each_live(|_, expires_at| {
+                summary.total_keys += 1;
+                summary.by_type.entry("string").or_default().keys += 1;
+                match expires_at {
+                    Some(expires) => {
+                        let secs = expires.saturating_duration_since(now).as_secs();
+                        let bucket = TTL_BUCKETS.iter().position(|&(_, bound)| secs < bound)
+                            .unwrap_or(TTL_BUCKETS.len() - 1);
+                        summary.ttl_histogram[bucket] += 1;
+                        summary.with_ttl += 1;
+                        summary.ttl_ms_total += expires.saturating_duration_since(now).as_millis() as u64;
+                    }
+                    None => summary.without_ttl += 1,
+                }
+            });
+        }
+        
+        summary
+    }
+    
+    /// Count live string keys starting with `prefix` without copying any values
+    /// Partitions are read-locked one at a time, so the count is not point-in-time
+    pub fn count_strings_with_prefix(&self, prefix: &[u8]) -> usize {
+        let now = Instant::now();
+        let mut cold = 0;
+        if let Some(tier) = &self.cold {
+            tier.for_each_live(|key, _| cold += key.starts_with(prefix) as usize);
+        }
+        
+        cold + self.partitions.iter()
+            .map(|partition| partition.read().unwrap_or_else(PoisonError::into_inner))
+            .map(|guard| {
+                guard.iter()
+                    .filter(|(key, entry)| key.starts_with(prefix) && !entry.is_expired(now))
+                    .filter(|(_, entry)| matches!(entry.value, ValueKind::String(_) | ValueKind::Compressed(_)))
+                    .count()
+            })
+            .sum::<usize>()
+    }
+    
+    /// Atomically add `delta` to a signed 64-bit integer value
+    /// Missing keys start at 0; returns the new value and the key's remaining TTL
+    pub fn incr_by(&self, key: &[u8], delta: i64) -> Result<(i64, Option<Duration>), String> {
+        let mut result = 0;
+        
+        let ttl = self.modify(key, |current| {
+            let value = match current {
+                Some(bytes) => std::str::from_utf8(bytes)
+                    .ok()
+                    .and_then(|s| s.parse::<i64>().ok())
+                    .ok_or_else(|| "value is not an integer or out of range".to_string())?,
+                None => 0,
+            };
+            
+            result = value.checked_add(delta)
+                .ok_or_else(|| "increment or decrement would overflow".to_string())?;
+            Ok(Some(result.to_string().into_bytes()))
+        })?;
+        
+        Ok((result, ttl.flatten()))
+    }
+    
+    /// Atomically adjust an unsigned 64-bit integer value (Memcached semantics)
+    /// Increments wrap around, decrements stop at zero and missing keys are
+    /// left alone; returns the new value and the key's remaining TTL
+    pub fn incr_by_unsigned(&self, key: &[u8], delta: u64, decrement: bool) -> Result<Option<(u64, Option<Duration>)>, String> {
+        let mut result = 0;
+        
+        let ttl = self.modify(key, |current| {
+            let Some(bytes) = current else {
+                return Ok(None);
+            };
+            
+            let value = std::str::from_utf8(bytes)
+                .ok()
+                .and_then(|s| s.trim_end_matches(' ').parse::<u64>().ok())
+                .ok_or_else(|| "cannot increment or decrement non-numeric value".to_string())?;
+            
+            result = if decrement {
+                value.saturating_sub(delta)
+            } else {
+                value.wrapping_add(delta)
+            };
+            Ok(Some(result.to_string().into_bytes()))
+        })?;
+        
+        Ok(ttl.map(|ttl| (result, ttl)))
+    }
+    
+    /// GETRANGE - inclusive byte range, negative indices count from the end
+    /// Missing keys and out-of-range indices give an empty string
+    pub fn get_range(&self, key: &[u8], start: i64, end: i64) -> Result<Vec<u8>, String> {
+        self.read_value(key, |value| {
+            let bytes = value.as_string()?;
+            Ok(index_range(bytes.len(), start, end).map_or_else(Vec::new, |(from, to)| bytes[from..=to].to_vec()))
+        }).unwrap_or(Ok(Vec::new()))
+    }
+    
+    /// STRLEN - byte length of a string, 0 if missing
+    pub fn strlen(&self, key: &[u8]) -> Result<usize, String> {
+        self.read_value(key, ValueKind::string_len).unwrap_or(Ok(0))
+    }
+    
+    /// BITCOUNT - set bits in the whole string or an inclusive byte range
+    pub fn bit_count(&self, key: &[u8], range: Option<(i64, i64)>) -> Result<u64, String> {
+        self.read_value(key, |value| {
+            let bytes = value.as_string()?;
+            let (start, end) = range.unwrap_or((0, -1));
+            Ok(index_range(bytes.len(), start, end).map_or(0, |(from, to)| popcount(&bytes[from..=to])))
+        }).unwrap_or(Ok(0))
+    }
+    
+    /// GETBIT - bit at `offset` (MSB first), 0 past the end or for missing keys
+    pub fn get_bit(&self, key: &[u8], offset: usize) -> Result<u8, String> {
+        self.read_value(key, |value| {
+            let bytes = value.as_string()?;
+            Ok(bytes.get(offset / 8).map_or(0, |byte| (byte >> (7 - offset % 8)) & 1))
+        }).unwrap_or(Ok(0))
+    }
+    
+    /// SETBIT - set or clear the bit at `offset`, growing the value with zero bytes
+    /// Returns the previous bit plus the written value and TTL
+    pub fn set_bit(&self, key: &[u8], offset: usize, on: bool) -> Result<(u8, Vec<u8>, Option<Duration>), String> {
+        let mut old = 0;
+        let mut written = Vec::new();
+        
+        let ttl = self.modify(key, |current| {
+            let mut value = current.unwrap_or_default().to_vec();
+            let index = offset / 8;
+            if value.len() <= index {
+                value.resize(index + 1, 0);
+            }
+            
+            let mask = 1 << (7 - offset % 8);
+            old = u8::from(value[index] & mask != 0);
+            if on {
+                value[index] |= mask;
+            } else {
+                value[index] &= !mask;
+            }
+            
+            written = value.clone();
+            Ok(Some(value))
+        })?;
+        
+        Ok((old, written, ttl.flatten()))
+    }
+    
+    /// SETRANGE - overwrite from `offset`, zero-padding past the current end
+    /// Returns the new length plus the written value and TTL (None if nothing changed)
+    pub fn set_range(&self, key: &[u8], offset: usize, patch: &[u8]) -> Result<(usize, WrittenString), String> {
+        let mut len = 0;
+        let mut written = None;
+        
+        let ttl = self.modify(key, |current| {
+            let current = current.unwrap_or_default();
+            
+            // An empty patch never creates or grows the value
+            if patch.is_empty() {
+                len = current.len();
+                return Ok(None);
+            }
+            
+            let mut value = current.to_vec();
+            let end = offset + patch.len();
+            if value.len() < end {
+                value.resize(end, 0);
+            }
+            value[offset..end].copy_from_slice(patch);
+            
+            len = value.len();
+            written = Some(value.clone());
+            Ok(Some(value))
+        })?;
+        
+        Ok((len, written.zip(ttl)))
+    }
+    
+    /// PFADD - add elements to a HyperLogLog, creating it if missing
+    /// Returns whether the estimate may have changed, plus the value and TTL to log
+    pub fn pf_add(&self, key: &[u8], elements: &[Vec<u8>]) -> Result<(bool, WrittenString), String> {
+        let mut written = None;
+        
+        let ttl = self.modify(key, |current| {
+            let (mut hll, mut changed) = match current {
+                Some(bytes) => (HyperLogLog::from_bytes(bytes)?, false),
+                None => (HyperLogLog::new(), true),
+            };
+            for element in elements {
+                changed |= hll.add(element);
+            }
+            if !changed {
+                return Ok(None);
+            }
+            
+            let value = hll.into_bytes();
+            written = Some(value.clone());
+            Ok(Some(value))
+        })?;
+        
+        Ok((written.is_some(), written.zip(ttl)))
+    }
+    
+    /// PFCOUNT - estimated cardinality of the union of HyperLogLogs
+    pub fn pf_count(&self, keys: &[&[u8]]) -> Result<u64, String> {
+        let mut union = HyperLogLog::new();
+        for key in keys {
+            if let Some(hll) = self.read_value(key, |value| HyperLogLog::from_bytes(&value.as_string()?)) {
+                union.merge(&hll?);
+            }
+        }
+        Ok(union.count())
+    }
+    
+    /// PFMERGE - merge sources into `dst` (including its own registers) under one lock
+    /// `dst` keeps its TTL; returns the stored value and TTL
+    pub fn pf_merge(&self, dst: &[u8], sources: &[&[u8]]) -> Result<(Vec<u8>, Option<Duration>), String> {
+        let mut all_keys = sources.to_vec();
+        all_keys.push(dst);
+        let mut guard = self.lock_partitions(&all_keys)?;
+        
+        let mut merged = HyperLogLog::new();
+        for key in &all_keys {
+            if let Some(value) = guard.get(key) {
+                merged.merge(&HyperLogLog::from_bytes(&value.as_string()?)?);
+            }
+        }
+        
+        let ttl = guard.ttl(dst);
+        let bytes = merged.into_bytes();
+        guard.set(dst, self.string_value(bytes.clone()), ttl);
+        Ok((bytes, ttl))
+    }
+    
+    /// LRANGE - elements in an inclusive index range, negative indices from the tail
+    pub fn list_range(&self, key: &[u8], start: i64, stop: i64) -> Result<Vec<Vec<u8>>, String> {
+        self.read_value(key, |value| {
+            let list = value.as_list()?;
+            Ok(index_range(list.len(), start, stop)
+                .map_or_else(Vec::new, |(from, to)| list.range(from..=to).cloned().collect()))
+        }).unwrap_or(Ok(Vec::new()))
+    }
+    
+    /// LLEN - 0 for missing keys
+    pub fn list_len(&self, key: &[u8]) -> Result<usize, String> {
+        self.read_value(key, |value| value.as_list().map(VecDeque::len)).unwrap_or(Ok(0))
+    }
+    
+    /// LPOS - indexes of matching elements, counted from the head
+    /// `rank` picks the first match (negative scans from the tail);
+    /// at most `count` matches are returned (0 = all)
+    pub fn list_pos(&self, key: &[u8], element: &[u8], rank: i64, count: usize) -> Result<Vec<usize>, String> {
+        self.read_value(key, |value| {
+            let list = value.as_list()?;
+            let matches = |(_, item): &(usize, &Vec<u8>)| item.as_slice() == element;
+            let skip = rank.unsigned_abs() as usize - 1;
+            let limit = if count == 0 { usize::MAX } else { count };
+            
+            Ok(if rank > 0 {
+                list.iter().enumerate().filter(matches).skip(skip).take(limit).map(|(i, _)| i).collect()
+            } else {
+                list.iter().enumerate().rev().filter(matches).skip(skip).take(limit).map(|(i, _)| i).collect()
+            })
+        }).unwrap_or(Ok(Vec::new()))
+    }
+    
+    /// LINSERT - insert before or after the first `pivot`
+    /// Returns the new length (-1 if the pivot is absent, 0 if the key is missing)
+    pub fn list_insert(&self, key: &[u8], before: bool, pivot: &[u8], element: &[u8]) -> Result<(i64, WrittenValue), String> {
+        let result = self.modify_list(key, |list| {
+            let Some(index) = list.iter().position(|item| item.as_slice() == pivot) else {
+                return Ok((-1, false));
+            };
+            list.insert(if before { index } else { index + 1 }, element.to_vec());
+            Ok((list.len() as i64, true))
+        })?;
+        
+        Ok(result.unwrap_or((0, None)))
+    }
+    
+    /// LSET - replace the element at `index` (negative from the tail)
+    pub fn list_set(&self, key: &[u8], index: i64, element: &[u8]) -> Result<WrittenValue, String> {
+        let result = self.modify_list(key, |list| {
+            let len = list.len() as i64;
+            let index = if index < 0 { len + index } else { index };
+            if !(0..len).contains(&index) {
+                return Err("index out of range".to_string());
+            }
+            list[index as usize] = element.to_vec();
+            Ok(((), true))
+        })?;
+        
+        result.map(|(_, written)| written).ok_or_else(|| "no such key".to_string())
+    }
+    
+    /// Estimated bytes a key consumes (key + value + per-entry overhead)
+    /// Returns None for missing or expired keys
+    pub fn entry_size(&self, key: &[u8]) -> Option<usize> {
+        self.entry_size_sampled(key, DEFAULT_MEMORY_SAMPLES)
+    }
+    
+    /// Like `entry_size`, extrapolating collections from `samples` elements (0 = all)
+    pub fn entry_size_sampled(&self, key: &[u8], samples: usize) -> Option<usize> {
+        self.read_value(key, |value| key.len() + value.mem_size(samples) + ENTRY_OVERHEAD)
+    }
+    
+    /// Store a typed value, replacing whatever the key held
+    pub fn set_value(&self, key: &[u8], value: ValueKind, ttl: Option<Duration>) -> Result<(), String> {
+        let partition = self.get_partition_for_key(key);
+        let old = {
+            let mut guard = partition.write()
+                .map_err(|_| "Failed to acquire write lock".to_string())?;
+            let entry = Entry::new(value, ttl.map(|d| Instant::now() + d), self.next_epoch());
+            self.insert_entry(&mut guard, key, entry)
+        };
+        
+        // Replaced collections can be large
+        if let Some(old) = old {
+            self.reclaim(old);
+        }
+        Ok(())
+    }
+    
+    /// Run `f` on a live value under the partition read lock
+    pub fn read_value<R>(&self, key: &[u8], f: impl FnOnce(&ValueKind) -> R) -> Option<R> {
+        let partition = self.get_partition_for_key(key);
+        let guard = partition.read().ok()?;
+        let entry = guard.get(key)?;
+        
+        if entry.is_expired(Instant::now()) {
+            return None;
+        }
+        
+        entry.touch();
+        Some(f(&entry.value))
+    }
+    
+    /// Run `f` on a live key's value without counting it as an access
+    pub fn inspect_value<R>(&self, key: &[u8], f: impl FnOnce(&ValueKind) -> R) -> Option<R> {
+        let partition = self.get_partition_for_key(key);
+        let guard = partition.read().ok()?;
+        
+        guard.get(key)
+            .filter(|entry| !entry.is_expired(Instant::now()))
+            .map(|entry| f(&entry.value))
+    }
+    
+    /// TYPE name of a live key
+    pub fn value_type(&self, key: &[u8]) -> Option<&'static str> {
+        self.inspect_value(key, ValueKind::type_name)
+    }
+    
+    /// Apply a collection mutation under the partition write lock
+    /// Creates the collection if needed and removes it once empty;
+    /// returns how many elements changed
+    pub fn apply_mutation(&self, key: &[u8], mutation: &Mutation) -> Result<Applied, String> {
+        let partition = self.get_partition_for_key(key);
+        let mut guard = partition.write()
+            .map_err(|_| "Failed to acquire write lock".to_string())?;
+        let epoch = self.next_epoch();
+        
+        // Expired entries are replaced like missing ones
+        if guard.get(key).is_some_and(|entry| entry.is_expired(Instant::now())) {
+            self.remove_entry(&mut guard, key, epoch);
+        }
+        
+        if !guard.contains_key(key) {
+            match mutation.empty_value() {
+                Some(value) => {
+                    self.insert_entry(&mut guard, key, Entry::new(value, None, epoch));
+                }
+                None => return Ok(Applied::default()),
+            }
+        }
+        
+        let entry = self.entry_mut(&mut guard, key, epoch).expect("entry present");
+        let applied = mutation.apply(&mut entry.value);
+        entry.touch();
+        self.charge(key, entry);
+        
+        // Already preserved by entry_mut, so removed directly
+        if entry.value.is_empty_collection()
+            && let Some(old) = guard.swap_remove(key)
+        {
+            self.used_memory.fetch_sub(old.size, Ordering::Relaxed);
+        }
+        
+        applied
+    }
+    
+    /// SINTER/SUNION/SDIFF across keys (missing keys are empty sets)
+    pub fn set_combine(&self, op: SetOp, keys: &[&[u8]]) -> Result<HashSet<Vec<u8>>, String> {
+        self.read_sets(keys, |sets| op.combine(sets))
+    }
+    
+    /// SINTERCARD across keys, stopping once `limit` members are found (0 = no limit)
+    pub fn set_intercard(&self, keys: &[&[u8]], limit: usize) -> Result<usize, String> {
+        self.read_sets(keys, |sets| SetOp::inter_card(sets, limit))
+    }
+    
+    /// Run `f` over the sets at `keys` (None for missing keys) with their partitions read-locked
+    fn read_sets<R>(&self, keys: &[&[u8]], f: impl FnOnce(&[Option<&HashSet<Vec<u8>>>]) -> R) -> Result<R, String> {
+        // Read-lock every involved partition in index order
+        let order = self.lock_order(keys);
+        let mut guards = Vec::with_capacity(order.len());
+        for &idx in &order {
+            let guard = self.partitions[idx].read()
+                .map_err(|_| "Failed to acquire read lock".to_string())?;
+            guards.push((idx, guard));
+        }
+        
+        let now = Instant::now();
+        let mut sets = Vec::with_capacity(keys.len());
+        for key in keys {
+            let idx = self.partition_index(key);
+            let (_, guard) = guards.iter().find(|(i, _)| *i == idx).expect("partition locked");
+            match guard.get(*key).filter(|entry| !entry.is_expired(now)) {
+                Some(entry) => sets.push(Some(entry.value.as_set()?)),
+                None => sets.push(None),
+            }
+        }
+        
+        Ok(f(&sets))
+    }
+    
+    /// SINTERSTORE/SUNIONSTORE/SDIFFSTORE - store the combination in `dst`
+    /// All partitions stay write-locked so the sources cannot change mid-way.
+    /// An empty result deletes `dst`; returns the stored set
+    pub fn set_combine_store(&self, op: SetOp, dst: &[u8], keys: &[&[u8]]) -> Result<(HashSet<Vec<u8>>, bool), String> {
+        let mut all_keys = keys.to_vec();
+        all_keys.push(dst);
+        let mut guard = self.lock_partitions(&all_keys)?;
+        
+        let result = {
+            let mut sets = Vec::with_capacity(keys.len());
+            for key in keys {
+                match guard.get(key) {
+                    Some(value) => sets.push(Some(value.as_set()?)),
+                    None => sets.push(None),
+                }
+            }
+            op.combine(&sets)
+        };
+        
+        let removed = if result.is_empty() {
+            guard.remove(dst).is_some()
+        } else {
+            guard.set(dst, ValueKind::Set(result.clone()), None);
+            false
+        };
+        Ok((result, removed))
+    }
+    
+    /// Run garbage collection - clean expired entries
+    pub fn gc(&self) -> usize {
+        let mut total_removed = 0;
+        let now = Instant::now();
+        
+        // Process each partition
+        for partition in &self.partitions {
+            if let Ok(mut guard) = partition.write() {
+                // Find keys to remove
+                let to_remove: Vec<Vec<u8>> = guard
+                    .iter()
+                    .filter_map(|(k, v)| {
+                        if let Some(expires) = v.expires_at {
+                            if now > expires {
+                                return Some(k.clone());
+                            }
+                        }
+                        None
+                    })
+                    .collect();
+                
+                // Remove expired entries
+                let epoch = self.next_epoch();
+                for key in &to_remove {
+                    self.remove_entry(&mut guard, key, epoch);
+                    total_removed += 1;
+                }
+                drop(guard);
+                
+                // Notify outside the partition lock
+                self.notify_expired(&to_remove);
+            }
+        }
+        
+        // Keys that expired while on disk
+        if let Some(cold) = &self.cold {
+            let expired = cold.gc();
+            total_removed += expired.len();
+            self.notify_expired(&expired);
+        }
+        
+        // Drop tombstones whose window has passed
+        for tombstones in &self.tombstones {
+            if let Ok(mut guard) = tombstones.lock() {
+                guard.retain(|_, until| now <= *until);
+            }
+        }
+        
+        total_removed
+    }
+    
+    /// Move up to `max` strings idle for the cold tier's threshold to disk, returning how many moved
+    /// Values are written with no partition locked; a key written meanwhile stays in memory
+    pub fn spill_idle(&self, max: usize) -> std::io::Result<usize> {
+        let Some(cold) = &self.cold else {
+            return Ok(0);
+        };
+        
+        let idle_ms = cold.idle_threshold().as_millis() as u64;
+        let mut spill = cold.begin_spill();
+        let mut spilled = 0;
+        for partition in &self.partitions {
+            if spilled >= max {
+                break;
+            }
+            
+            let candidates: Vec<(Vec<u8>, ValueKind, Option<Instant>, u64)> = {
+                let guard = partition.read().unwrap_or_else(PoisonError::into_inner);
+                let (now, clock) = (Instant::now(), clock_ms());
+                guard.iter()
+                    .filter(|(_, entry)| entry.is_spillable(now, clock, idle_ms))
+                    .take(max - spilled)
+                    .map(|(key, entry)| (key.clone(), entry.value.clone(), entry.expires_at, entry.epoch))
+                    .collect()
+            };
+            if candidates.is_empty() {
+                continue;
+            }
+            
+            let mut written = Vec::with_capacity(candidates.len());
+            for (key, value, expires_at, epoch) in candidates {
+                let bytes = value.as_string().map_err(std::io::Error::other)?;
+                written.push((key.clone(), spill.write(&key, &bytes, expires_at)?, epoch));
+            }
+            
+            let mut guard = partition.write().unwrap_or_else(PoisonError::into_inner);
+            let (now, clock, epoch) = (Instant::now(), clock_ms(), self.next_epoch());
+            for (key, slot, written_at) in written {
+                let unchanged = guard.get(&key)
+                    .is_some_and(|entry| entry.epoch == written_at && entry.is_spillable(now, clock, idle_ms));
+                if unchanged {
+                    spill.commit(key.clone(), slot);
+                    if let Some(entry) = self.remove_entry(&mut guard, &key, epoch) {
+                        self.reclaim(entry);
+                    }
+                    spilled += 1;
+                }
+            }
+        }
+        
+        spill.finish()?;
+        Ok(spilled)
+    }
+    
+    /// Reap expired keys by sampling for at most about `budget` (Redis active expiry)
+    /// Unlike `gc`, cost is bounded by the budget rather than the keyspace: each pass reads
+    /// at most ACTIVE_EXPIRE_MAX_LOOKUPS entries by index, resuming where the partition's last
+    /// pass stopped, so repeated cycles sweep every key. Partitions are visited round robin, and
+    /// one is sampled again while more than ACTIVE_EXPIRE_STALE_PERCENT of its samples had
+    /// expired. Returns keys removed
+    pub fn active_expire_cycle(&self, budget: Duration) -> usize {
+        let deadline = Instant::now() + budget;
+        let mut total_removed = 0;
+        
+        for _ in 0..self.partition_count() {
+            let index = self.expire_cursor.fetch_add(1, Ordering::Relaxed) & self.partition_mask;
+            let partition = &self.partitions[index];
+            
+            loop {
+                let (sampled, candidates) = {
+                    let Ok(guard) = partition.read() else {
+                        break;
+                    };
+                    if guard.is_empty() {
+                        break;
+                    }
+                    
+                    let now = Instant::now();
+                    let len = guard.len();
+                    let start = self.expire_positions[index].load(Ordering::Relaxed) % len;
+                    let mut looked = 0;
+                    let mut samples = Vec::with_capacity(ACTIVE_EXPIRE_SAMPLES);
+                    while looked < ACTIVE_EXPIRE_MAX_LOOKUPS.min(len) && samples.len() < ACTIVE_EXPIRE_SAMPLES {
+                        let (key, entry) = guard.get_index((start + looked) % len).expect("index below len");
+                        looked += 1;
+                        if entry.expires_at.is_some() {
+                            samples.push((key, entry));
+                        }
+                    }
+                    self.expire_positions[index].store((start + looked) % len, Ordering::Relaxed);
+                    let candidates: Vec<Vec<u8>> = samples.iter()
+                        .filter(|(_, entry)| entry.is_expired(now))
+                        .map(|(key, _)| (*key).clone())
+                        .collect();
+                    (samples.len(), candidates)
+                };
+                if candidates.is_empty() {
+                    break;
+                }
+                
+                // A key may have been rewritten or deleted between the read and write locks
+                let removed: Vec<Vec<u8>> = {
+                    let Ok(mut guard) = partition.write() else {
+                        break;
+                    };
+                    let now = Instant::now();
+                    let epoch = self.next_epoch();
+                    candidates.into_iter()
+                        .filter(|key| {
+                            guard.get(key).is_some_and(|entry| entry.is_expired(now))
+                                && self.remove_entry(&mut guard, key, epoch).is_some()
+                        })
+                        .collect()
+                };
+                self.notify_expired(&removed);
+                total_removed += removed.len();
+                
+                if removed.len() * 100 <= sampled * ACTIVE_EXPIRE_STALE_PERCENT || Instant::now() >= deadline {
+                    break;
+                }
+            }
+            
+            if Instant::now() >= deadline {
+                break;
+            }
+        }
+        
+        total_removed
+    }
+    
+    // === PRIVATE HELPERS ===
+    
+    /// Bring `key` back from the cold tier if it is there, before anything reads or writes it
+    /// `touch` refreshes a key already in memory so it isn't spilled mid-operation
+    fn fault_in(&self, idx: usize, key: &[u8], touch: bool) {
+        let Some(cold) = &self.cold else {
+            return;
+        };
+        
+        if let Some(entry) = self.partitions[idx].read().unwrap_or_else(PoisonError::into_inner).get(key) {
+            if touch {
+                entry.last_access.store(clock_ms(), Ordering::Relaxed);
+            }
+            return;
+        }
+        if !cold.may_contain(key) {
+            return;
+        }
+        
+        let restored = cold.take(key, |value, ttl| {
+            let mut guard = self.partitions[idx].write().unwrap_or_else(PoisonError::into_inner);
+            if !guard.contains_key(key) {
+                let entry = Entry::new(self.string_value(value), ttl.map(|d| Instant::now() + d), self.next_epoch());
+                self.insert_entry(&mut guard, key, entry);
+            }
+        });
+        if let Err(e) = restored {
+            eprintln!("Cold tier read failed: {}", e);
+        }
+    }
+    
+    /// Tell the expired listener about reaped keys (call without partition locks held)
+    fn notify_expired(&self, keys: &[Vec<u8>]) {
+        if let Some(listener) = self.expired_listener.get() {
+            for key in keys {
+                listener(key);
+            }
+        }
+    }
+    
+    /// Remember a deleted key when tombstones are enabled
+    /// Called with the partition write lock held (partition before tombstone lock)
+    fn record_tombstone(&self, key: &[u8]) {
+        if let Some(ttl) = self.tombstone_ttl
+            && let Ok(mut guard) = self.tombstones[self.partition_index(key)].lock()
+        {
+            guard.insert(key.to_vec(), Instant::now() + ttl);
+        }
+    }
+    
+    /// Read-modify-write a value under the partition write lock
+    /// `f` sees the current value (None if missing or expired) and returns the
+    /// replacement, or None to leave the key untouched. The TTL of a live key is
+    /// kept. Returns None if nothing was written, else the remaining TTL
+    fn modify<F>(&self, key: &[u8], f: F) -> Result<Option<Option<Duration>>, String>
+    where
+        F: FnOnce(Option<&[u8]>) -> Result<Option<Vec<u8>>, String>,
+    {
+        let partition = self.get_partition_for_key(key);
+        let mut guard = partition.write()
+            .map_err(|_| "Failed to acquire write lock".to_string())?;
+        
+        let now = Instant::now();
+        let live = match guard.get(key).filter(|entry| !entry.is_expired(now)) {
+            Some(entry) => Some(entry.value.as_string()?),
+            None => None,
+        };
+        
+        let Some(value) = f(live.as_deref())? else {
+            return Ok(None);
+        };
+        
+        let epoch = self.next_epoch();
+        if live.is_some() {
+            let entry = self.entry_mut(&mut guard, key, epoch).expect("entry present");
+            entry.value = ValueKind::String(value.into());
+            entry.touch();
+            self.charge(key, entry);
+            Ok(Some(entry.expires_at.map(|expires| expires.saturating_duration_since(now))))
+        } else {
+            let entry = Entry::new(ValueKind::String(value.into()), None, epoch);
+            self.insert_entry(&mut guard, key, entry);
+            Ok(Some(None))
+        }
+    }
+    
+    /// Edit a live list under the partition write lock (None if the key is missing)
+    /// `f` returns its result and whether it changed the list; changed lists
+    /// come back whole with their remaining TTL so the caller can log them
+    fn modify_list<R>(&self, key: &[u8], f: impl FnOnce(&mut VecDeque<Vec<u8>>) -> Result<(R, bool), String>) -> Result<Option<(R, WrittenValue)>, String> {
+        let partition = self.get_partition_for_key(key);
+        let mut guard = partition.write()
+            .map_err(|_| "Failed to acquire write lock".to_string())?;
+        
+        let now = Instant::now();
+        match guard.get(key) {
+            Some(entry) if !entry.is_expired(now) => entry.value.as_list()?,
+            _ => return Ok(None),
+        };
+        
+        let entry = self.entry_mut(&mut guard, key, self.next_epoch()).expect("entry present");
+        let ValueKind::List(list) = &mut entry.value else {
+            unreachable!("checked to be a list above");
+        };
+        let (result, changed) = f(list)?;
+        entry.touch();
+        if !changed {
+            return Ok(Some((result, None)));
         }
+        
+        self.charge(key, entry);
+        let ttl = entry.expires_at.map(|expires| expires.saturating_duration_since(now));
+        Ok(Some((result, Some((entry.value.clone(), ttl)))))
     }
     
-    /// Get partition count
-    pub fn partition_count(&self) -> usize {
-        self.partition_count
+    /// Next write epoch - taken with the written partitions already locked,
+    /// so a snapshot either sees the whole write or none of it
+    fn next_epoch(&self) -> u64 {
+        self.epoch.fetch_add(1, Ordering::SeqCst) + 1
     }
     
-    /// Get value by key
-    pub fn get(&self, key: &[u8]) -> Option<Vec<u8>> {
-        // Get partition for this key
-        let partition = self.get_partition_for_key(key);
-        
-        // Acquire read lock on just this partition
-        if let Ok(guard) = partition.read() {
-            // Check if key exists and is not expired
-            if let Some(entry) = guard.get(key) {
-                // Check expiration
-                if let Some(expires) = entry.expires_at {
-                    if Instant::now() > expires {
-                        // Expired entry - treat as non-existent
-                        return None;
-                    }
-                }
-                
-                // Return cloned value
-                return Some(entry.value.clone());
-            }
+    /// Insert an entry, returning the one it replaced
+    fn insert_entry(&self, partition: &mut PartitionMap, key: &[u8], mut entry: Entry) -> Option<Entry> {
+        let epoch = entry.epoch;
+        self.charge(key, &mut entry);
+        let old = partition.insert(key.to_vec(), entry)?;
+        self.used_memory.fetch_sub(old.size, Ordering::Relaxed);
+        self.preserve(key, &old, epoch);
+        Some(old)
+    }
+    
+    /// Remove an entry in a write at `epoch`
+    fn remove_entry(&self, partition: &mut PartitionMap, key: &[u8], epoch: u64) -> Option<Entry> {
+        let old = partition.swap_remove(key)?;
+        self.used_memory.fetch_sub(old.size, Ordering::Relaxed);
+        self.preserve(key, &old, epoch);
+        Some(old)
+    }
+    
+    /// Re-estimate an entry's size after its value changed, updating used_memory
+    fn charge(&self, key: &[u8], entry: &mut Entry) {
+        let size = key.len() + entry.value.mem_size(DEFAULT_MEMORY_SAMPLES) + ENTRY_OVERHEAD;
+        self.used_memory.fetch_add(size, Ordering::Relaxed);
+        self.used_memory.fetch_sub(entry.size, Ordering::Relaxed);
+        entry.size = size;
+    }
+    
+    /// Entry about to be changed in place by a write at `epoch`
+    fn entry_mut<'p>(&self, partition: &'p mut PartitionMap, key: &[u8], epoch: u64) -> Option<&'p mut Entry> {
+        let entry = partition.get_mut(key)?;
+        self.preserve(key, entry, epoch);
+        entry.epoch = epoch;
+        Some(entry)
+    }
+    
+    /// Keep a copy of `old` if an open snapshot can see it, before a write at `epoch` changes it
+    /// Called with the key's partition write-locked. A writer that finds no snapshot open, or
+    /// only ones older than `old`, took its epoch before any newer snapshot read the counter,
+    /// so that snapshot sees the write. Copies no snapshot ends up needing are pruned on drop
+    fn preserve(&self, key: &[u8], old: &Entry, epoch: u64) {
+        if self.open_snapshots.load(Ordering::SeqCst) == 0 {
+            return;
+        }
+        if self.newest_snapshot.load(Ordering::SeqCst) < old.epoch || old.epoch >= epoch {
+            return;
         }
         
-        None
+        if let Ok(mut versions) = self.versions[self.partition_index(key)].lock() {
+            versions.push(Version {
+                key: key.to_vec(),
+                value: old.value.clone(),
+                expires_at: old.expires_at,
+                from: old.epoch,
+                until: epoch,
+            });
+        }
     }
     
-    /// Set value with optional TTL
-    pub fn set(&self, key: &[u8], value: Vec<u8>, ttl: Option<Duration>) -> Result<(), String> {
-        // Calculate expiration time if TTL provided
-        let expires_at = ttl.map(|duration| Instant::now() + duration);
-        
-        // Get partition for this key
-        let partition = self.get_partition_for_key(key);
+    /// Entries of one partition visible at `epoch`, including preserved versions
+    fn partition_snapshot(&self, idx: usize, epoch: u64, now: Instant) -> Vec<(Vec<u8>, ValueKind, Option<Duration>)> {
+        let live = |expires_at: Option<Instant>| expires_at.is_none_or(|expires| now <= expires);
+        let ttl = |expires_at: Option<Instant>| expires_at.map(|expires| expires.saturating_duration_since(now));
         
-        // Create entry with value and expiration
-        let entry = Entry { value, expires_at };
+        // A panicked writer leaves its entries in place - they still belong in the copy
+        let guard = self.partitions[idx].read().unwrap_or_else(PoisonError::into_inner);
+        let mut entries: Vec<_> = guard.iter()
+            .filter(|(_, entry)| entry.epoch <= epoch && live(entry.expires_at))
+            .map(|(key, entry)| (key.clone(), entry.value.clone(), ttl(entry.expires_at)))
+            .collect();
         
-        // Acquire write lock on just this partition
-        if let Ok(mut guard) = partition.write() {
-            // Insert or replace entry
-            guard.insert(key.to_vec(), entry);
-            Ok(())
-        } else {
-            Err("Failed to acquire write lock".to_string())
+        // Versions are added under the partition lock, so none is missed or doubled
+        if let Ok(versions) = self.versions[idx].lock() {
+            entries.extend(versions.iter()
+                .filter(|version| (version.from..version.until).contains(&epoch) && live(version.expires_at))
+                .map(|version| (version.key.clone(), version.value.clone(), ttl(version.expires_at))));
         }
+        entries
     }
     
-    /// Delete value by key
-    pub fn delete(&self, key: &[u8]) -> Result<bool, String> {
-        // Get partition for this key
-        let partition = self.get_partition_for_key(key);
-        
-        // Acquire write lock on just this partition
-        if let Ok(mut guard) = partition.write() {
-            // Remove key and return whether it existed
-            Ok(guard.remove(key).is_some())
-        } else {
-            Err("Failed to acquire write lock".to_string())
+    /// Drop preserved versions no open snapshot can see
+    fn prune_versions(&self, open: &BTreeMap<u64, usize>) {
+        for versions in &self.versions {
+            if let Ok(mut versions) = versions.lock() {
+                versions.retain(|version| open.range(version.from..version.until).next().is_some());
+            }
         }
     }
     
-    /// Run garbage collection - clean expired entries
-    pub fn gc(&self) -> usize {
-        let mut total_removed = 0;
-        let now = Instant::now();
+    /// Drop entry, handing large values to the reclaim thread
+    fn reclaim(&self, entry: Entry) {
+        if entry.value.mem_size(DEFAULT_MEMORY_SAMPLES) < LAZYFREE_THRESHOLD {
+            return;
+        }
         
-        // Process each partition
-        for partition in &self.partitions {
-            if let Ok(mut guard) = partition.write() {
-                // Find keys to remove
-                let to_remove: Vec<Vec<u8>> = guard
-                    .iter()
-                    .filter_map(|(k, v)| {
-                        if let Some(expires) = v.expires_at {
-                            if now > expires {
-                                return Some(k.clone());
-                            }
-                        }
-                        None
-                    })
-                    .collect();
-                
-                // Remove expired entries
-                for key in to_remove {
-                    guard.remove(&key);
-                    total_removed += 1;
+        let sender = self.reclaimer.get_or_init(|| {
+            let (sender, receiver) = mpsc::channel::<Entry>();
+            thread::spawn(move || {
+                // Entries are freed as they are received
+                for entry in receiver {
+                    drop(entry);
                 }
-            }
-        }
+            });
+            sender
+        });
         
-        total_removed
+        // If the reclaim thread is gone the entry is simply dropped here
+        let _ = sender.send(entry);
     }
     
-    // === PRIVATE HELPERS ===
-    
     /// Get partition for key using consistent hashing
-    fn get_partition_for_key(&self, key: &[u8]) -> Arc<RwLock<HashMap<Vec<u8>, Entry>>> {
-        // Simple hash-based partitioning
-        let hash = self.hash_key(key);
-        let idx = hash % self.partition_count;
+    fn get_partition_for_key(&self, key: &[u8]) -> Arc<Partition> {
+        let idx = self.partition_index(key);
+        self.fault_in(idx, key, true);
         
         // Return reference to the partition
         self.partitions[idx].clone()
     }
     
-    /// Hash function for keys - FNV-1a for speed
-    // CRITICAL FIX: Changed parameter type from [u8] to &[u8]
+    /// Partition index for key
+    fn partition_index(&self, key: &[u8]) -> usize {
+        // Power-of-two count, so masking replaces modulo
+        self.hash_key(key) & self.partition_mask
+    }
+    
+    /// Partitions touched by `keys`, sorted and deduplicated
+    /// Multi-key operations lock in this order to avoid deadlock
+    fn lock_order(&self, keys: &[&[u8]]) -> Vec<usize> {
+        let mut order: Vec<usize> = keys.iter()
+            .map(|key| {
+                let idx = self.partition_index(key);
+                self.fault_in(idx, key, true);
+                idx
+            })
+            .collect();
+        order.sort_unstable();
+        order.dedup();
+        order
+    }
+    
+    /// Hash function for keys - SipHash keyed with the table seed
+    /// Unlike unseeded FNV, attackers can't craft keys that pile into one partition
+    /// (std's SipHasher is deprecated only in favour of DefaultHasher, which can't be keyed)
+    #[allow(deprecated)]
     fn hash_key(&self, key: &[u8]) -> usize {
-        let mut hash: u64 = 14695981039346656037; // FNV offset basis
+        let mut hasher = SipHasher::new_with_keys(self.hash_keys.0, self.hash_keys.1);
+        hasher.write(key);
         
-        // CRITICAL FIX: Fixed key iteration with &[u8]
-        for byte in key {
-            hash ^= *byte as u64;
-            hash = hash.wrapping_mul(1099511628211); // FNV prime
+        hasher.finish() as usize
+    }
+}
+
+impl Iterator for SnapshotIter<'_> {
+    type Item = (Vec<u8>, ValueKind, Option<Duration>);
+    
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(entry) = self.buffered.next() {
+                return Some(entry);
+            }
+            
+            let idx = self.next_partition;
+            if idx >= self.table.partitions.len() {
+                return self.cold.as_mut()?.next();
+            }
+            self.next_partition += 1;
+            self.buffered = self.table.partition_snapshot(idx, self.epoch, self.now).into_iter();
         }
-        
-        hash as usize
+    }
+}
+
+impl Drop for SnapshotIter<'_> {
+    fn drop(&mut self) {
+        let mut open = self.table.snapshots.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(count) = open.get_mut(&self.epoch) {
+            *count -= 1;
+            if *count == 0 {
+                open.remove(&self.epoch);
+            }
+        }
+        self.table.newest_snapshot.store(open.keys().next_back().copied().unwrap_or(0), Ordering::SeqCst);
+        self.table.open_snapshots.fetch_sub(1, Ordering::SeqCst);
+        self.table.prune_versions(&open);
     }
 }
 
@@ -206,7 +2214,26 @@ mod tests {
         
         // Get value
         let result = mem.get(key);
-        assert_eq!(result, Some(value));
+        assert_eq!(result.as_deref(), Some(value.as_slice()));
+    }
+    
+    #[test]
+    fn test_poisoned_partition_still_read() {
+        let mem = MemTable::with_partitions(1);
+        mem.set(b"t:1", b"a".to_vec(), None).unwrap();
+        mem.set(b"t:2", b"b".to_vec(), None).unwrap();
+        let poisoned = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            let _guard = mem.partitions[0].write();
+            panic!("writer panicked");
+        }));
+        assert!(poisoned.is_err());
+        
+        // Counts and snapshots see the entries rather than skipping the partition
+        assert_eq!(mem.len(), 2);
+        assert_eq!(mem.count_strings_with_prefix(b"t:"), 2);
+        assert_eq!(mem.keyspace_summary().total_keys, 2);
+        assert_eq!(mem.snapshot_entries().len(), 2);
+        assert_eq!(mem.hottest_keys(10).len(), 2);
     }
     
     #[test]
@@ -226,6 +2253,198 @@ mod tests {
         assert_eq!(mem.get(key), None);
     }
     
+    #[test]
+    fn test_get_full() {
+        let mem = MemTable::new();
+        assert!(mem.get_full(b"missing").is_none());
+        
+        mem.set(b"k", b"v".to_vec(), Some(Duration::from_secs(30))).unwrap();
+        let view = mem.get_full(b"k").unwrap();
+        assert_eq!(&*view.value.as_string().unwrap(), b"v");
+        assert_eq!(view.kind, "string");
+        assert!(view.remaining_ttl.unwrap() <= Duration::from_secs(30));
+        assert!(view.last_access <= Instant::now());
+        
+        mem.set_value(b"s", ValueKind::Set(HashSet::new()), None).unwrap();
+        let view = mem.get_full(b"s").unwrap();
+        assert_eq!((view.kind, view.remaining_ttl), ("set", None));
+    }
+    
+    #[test]
+    fn test_keyspace_summary() {
+        let mem = MemTable::new();
+        mem.set(b"a", b"1".to_vec(), None).unwrap();
+        mem.set(b"b", b"2".to_vec(), Some(Duration::from_secs(30))).unwrap();
+        mem.set(b"c", b"3".to_vec(), Some(Duration::from_secs(7_200))).unwrap();
+        mem.apply_mutation(b"s", &Mutation::SAdd(vec![b"m".to_vec()])).unwrap();
+        
+        let summary = mem.keyspace_summary();
+        assert_eq!(summary.total_keys, 4);
+        assert_eq!((summary.with_ttl, summary.without_ttl), (2, 2));
+        assert_eq!(summary.by_type["string"].keys, 3);
+        assert_eq!(summary.by_type["set"].keys, 1);
+        assert!(summary.by_type["set"].bytes > ENTRY_OVERHEAD);
+        assert_eq!(summary.ttl_histogram, [1, 0, 1, 0, 0]);
+        
+        // (30s + 7200s) / 2, less the few ms that passed since the SETs
+        let avg_ttl = summary.avg_ttl_ms();
+        assert!(avg_ttl > 3_614_000 && avg_ttl <= 3_615_000, "{}", avg_ttl);
+        assert_eq!(KeyspaceSummary::default().avg_ttl_ms(), 0);
+    }
+    
+    #[test]
+    fn test_set_batch() {
+        let mem = MemTable::with_partitions(4);
+        let entries: Vec<_> = (0..32)
+            .map(|i| (format!("key:{}", i).into_bytes(), vec![i as u8], None))
+            .chain(std::iter::once((b"ttl".to_vec(), b"v".to_vec(), Some(Duration::from_secs(60)))))
+            .collect();
+        
+        mem.set_batch(&entries).unwrap();
+        for i in 0..32 {
+            assert_eq!(mem.get(format!("key:{}", i).as_bytes()).as_deref(), Some([i as u8].as_slice()));
+        }
+        assert!(mem.ttl(b"ttl").is_some());
+        
+        // Later duplicates win, like sequential SETs
+        mem.set_batch(&[(b"dup".to_vec(), b"1".to_vec(), None), (b"dup".to_vec(), b"2".to_vec(), None)]).unwrap();
+        assert_eq!(mem.get(b"dup").as_deref(), Some(b"2".as_slice()));
+    }
+    
+    #[test]
+    fn test_snapshot_iter_is_point_in_time() {
+        let mem = MemTable::with_partitions(4);
+        for i in 0..32 {
+            mem.set(format!("key:{}", i).as_bytes(), vec![i as u8], None).unwrap();
+        }
+        
+        let mut snapshot = mem.snapshot_iter();
+        let mut seen = vec![snapshot.next().unwrap()];
+        
+        // Overwrite, delete, mutate and add keys partway through the scan
+        for i in 0..32 {
+            let key = format!("key:{}", i).into_bytes();
+            match i % 3 {
+                0 => mem.set(&key, b"new".to_vec(), None).unwrap(),
+                1 => { mem.delete(&key).unwrap(); }
+                _ => { mem.incr_by(&key, 1).ok(); }
+            }
+        }
+        mem.set(b"added", b"v".to_vec(), None).unwrap();
+        mem.clear();
+        seen.extend(snapshot.by_ref());
+        assert_eq!(seen.len(), 32);
+        
+        let seen: BTreeMap<_, _> = seen.into_iter()
+            .map(|(key, value, _)| (key, value.as_string().unwrap().to_vec()))
+            .collect();
+        let expected: BTreeMap<_, _> = (0..32)
+            .map(|i| (format!("key:{}", i).into_bytes(), vec![i as u8]))
+            .collect();
+        assert_eq!(seen, expected);
+        
+        // Preserved versions are released with the last snapshot
+        drop(snapshot);
+        assert!(mem.versions.iter().all(|versions| versions.lock().unwrap().is_empty()));
+        assert_eq!(mem.snapshot_iter().count(), 0);
+        
+        // Entries written after every open snapshot aren't copied when they change
+        let snapshot = mem.snapshot_iter();
+        mem.set(b"later", b"1".to_vec(), None).unwrap();
+        mem.set(b"later", b"2".to_vec(), None).unwrap();
+        assert!(mem.versions.iter().all(|versions| versions.lock().unwrap().is_empty()));
+        assert_eq!(snapshot.count(), 0);
+    }
+    
+    #[test]
+    fn test_compression_is_transparent() {
+        let compression = Compression { codec: crate::storage::value::Codec::Lz4, threshold: 64 };
+        let mem = MemTable::new().with_compression(Some(compression));
+        let blob = br#"{"user":"alice","roles":["admin","dev"]}"#.repeat(50);
+        
+        mem.set(b"blob", blob.clone(), None).unwrap();
+        mem.set(b"small", b"tiny".to_vec(), None).unwrap();
+        assert_eq!(mem.get(b"blob").as_deref(), Some(blob.as_slice()));
+        assert_eq!(mem.get(b"small").as_deref(), Some(b"tiny".as_slice()));
+        assert_eq!(mem.value_type(b"blob"), Some("string"));
+        
+        let (values, raw, stored) = mem.compression_stats();
+        assert_eq!((values, raw), (1, blob.len() as u64));
+        assert!(stored * 4 < raw);
+        assert!(mem.entry_size(b"blob").unwrap() < blob.len());
+    }
+    
+    #[test]
+    fn test_partition_count_rounding() {
+        assert_eq!(MemTable::with_partitions(0).partition_count(), 1);
+        assert_eq!(MemTable::with_partitions(6).partition_count(), 8);
+        assert_eq!(MemTable::with_partitions(16).partition_count(), 16);
+        
+        // Every key maps to a valid partition
+        let mem = MemTable::with_partitions(12);
+        for i in 0..1000 {
+            assert!(mem.partition_index(format!("key:{}", i).as_bytes()) < mem.partition_count());
+        }
+    }
+    
+    #[test]
+    fn test_partition_stats() {
+        let mem = MemTable::with_partitions(4);
+        mem.set(b"k", b"v".to_vec(), None).unwrap();
+        let idx = mem.partition_index(b"k");
+        
+        // Hold the partition so the next reader has to wait
+        let held = mem.partitions[idx].write().unwrap();
+        thread::scope(|scope| {
+            let reader = scope.spawn(|| mem.get(b"k"));
+            while mem.partitions[idx].contended.load(Ordering::Relaxed) == 0 {
+                thread::yield_now();
+            }
+            drop(held);
+            assert_eq!(reader.join().unwrap().as_deref(), Some(&b"v"[..]));
+        });
+        
+        let stats = mem.partition_stats();
+        assert_eq!(stats.len(), 4);
+        assert_eq!((stats[idx].keys, stats[idx].acquisitions, stats[idx].contended), (1, 3, 1));
+        assert!(stats.iter().filter(|stat| stat.index != idx).all(|stat| stat.acquisitions == 0));
+    }
+    
+    #[test]
+    fn test_sharded_backend() {
+        let mem = MemTable::with_partitions(4).with_backend(PartitionBackend::Sharded);
+        mem.set(b"a", b"1".to_vec(), None).unwrap();
+        mem.set(b"gone", b"x".to_vec(), Some(Duration::from_millis(1))).unwrap();
+        mem.set_batch(&[(b"b".to_vec(), b"2".to_vec(), None), (b"c".to_vec(), b"3".to_vec(), None)]).unwrap();
+        assert_eq!(mem.incr_by(b"n", 5).unwrap().0, 5);
+        assert!(mem.delete(b"c").unwrap());
+        
+        thread::sleep(Duration::from_millis(5));
+        assert_eq!(mem.gc(), 1);
+        assert_eq!(mem.get(b"a").as_deref(), Some(&b"1"[..]));
+        assert_eq!(mem.get(b"b").as_deref(), Some(&b"2"[..]));
+        assert_eq!(mem.get(b"c"), None);
+        assert_eq!(mem.partition_stats().iter().map(|stat| stat.keys).sum::<usize>(), 3);
+        assert_eq!(PartitionBackend::from_name("SHARDED"), Some(PartitionBackend::Sharded));
+    }
+    
+    #[test]
+    fn test_hash_seed() {
+        let keys: Vec<Vec<u8>> = (0..64).map(|i| format!("key:{}", i).into_bytes()).collect();
+        let layout = |mem: &MemTable| -> Vec<usize> { keys.iter().map(|k| mem.partition_index(k)).collect() };
+        
+        // A pinned seed is deterministic; a different seed reshuffles keys
+        let a = MemTable::with_hasher(16, 42);
+        let b = MemTable::with_hasher(16, 42);
+        let c = MemTable::with_hasher(16, 43);
+        assert_eq!(layout(&a), layout(&b));
+        assert_ne!(layout(&a), layout(&c));
+        
+        // Both key halves count
+        let d = MemTable::with_hasher(16, (1 << 64) | 42);
+        assert_ne!(layout(&a), layout(&d));
+    }
+    
     #[test]
     fn test_ttl() {
         let mem = MemTable::new();
@@ -244,4 +2463,296 @@ mod tests {
         // Should be gone
         assert!(mem.get(key).is_none());
     }
+    
+    #[test]
+    fn test_active_expiry_resumes() {
+        let mem = MemTable::with_partitions(1);
+        for i in 0..1000 {
+            mem.set(format!("live_{}", i).as_bytes(), b"v".to_vec(), Some(Duration::from_secs(3600))).unwrap();
+        }
+        for i in 0..5 {
+            mem.set(format!("stale_{}", i).as_bytes(), b"v".to_vec(), Some(Duration::from_millis(1))).unwrap();
+        }
+        std::thread::sleep(Duration::from_millis(5));
+        
+        // Each pass picks up where the last one stopped, so the stale keys at the end are
+        // reached after a bounded number of cycles rather than by chance
+        let cycles = 1000 / ACTIVE_EXPIRE_SAMPLES + 1;
+        let removed: usize = (0..cycles).map(|_| mem.active_expire_cycle(Duration::from_secs(1))).sum();
+        assert_eq!(removed, 5);
+        assert_eq!(mem.len(), 1000);
+    }
+    
+    #[test]
+    fn test_unlink() {
+        let mem = MemTable::new();
+        mem.set(b"small", b"v".to_vec(), None).unwrap();
+        mem.set(b"large", vec![7u8; LAZYFREE_THRESHOLD * 2], None).unwrap();
+        
+        assert!(mem.unlink(b"small").unwrap());
+        assert!(mem.unlink(b"large").unwrap());
+        assert!(!mem.unlink(b"large").unwrap());
+        assert_eq!(mem.get(b"large"), None);
+    }
+    
+    #[test]
+    fn test_random_key() {
+        let mem = MemTable::new();
+        assert_eq!(mem.random_key(), None);
+        
+        for i in 0..32 {
+            mem.set(format!("key_{}", i).as_bytes(), b"v".to_vec(), None).unwrap();
+        }
+        
+        let key = mem.random_key().unwrap();
+        assert!(mem.get(&key).is_some());
+    }
+    
+    #[test]
+    fn test_touch() {
+        let mem = MemTable::new();
+        mem.set(b"live", b"v".to_vec(), None).unwrap();
+        mem.set(b"dying", b"v".to_vec(), Some(Duration::from_millis(10))).unwrap();
+        
+        std::thread::sleep(Duration::from_millis(30));
+        assert!(mem.idle_time(b"live").unwrap() >= Duration::from_millis(20));
+        
+        // Touch resets idle time, expired and missing keys are skipped
+        assert!(mem.touch(b"live"));
+        assert!(mem.idle_time(b"live").unwrap() < Duration::from_millis(20));
+        assert!(!mem.touch(b"dying"));
+        assert!(!mem.touch(b"missing"));
+    }
+    
+    #[test]
+    fn test_used_memory_accounting() {
+        let mem = MemTable::new();
+        assert_eq!(mem.used_memory(), 0);
+        
+        mem.set(b"a", vec![0; 1000], None).unwrap();
+        let one = mem.used_memory();
+        assert!(one >= 1000);
+        
+        // Overwrites and in-place edits replace the charge instead of adding to it
+        mem.set(b"a", vec![0; 1000], None).unwrap();
+        assert_eq!(mem.used_memory(), one);
+        mem.set_range(b"a", 1999, b"x").unwrap();
+        assert_eq!(mem.used_memory(), one + 1000);
+        
+        mem.apply_mutation(b"s", &Mutation::SAdd(vec![b"m".to_vec()])).unwrap();
+        mem.apply_mutation(b"s", &Mutation::SRem(vec![b"m".to_vec()])).unwrap();
+        assert_eq!(mem.used_memory(), one + 1000);
+        
+        mem.delete(b"a").unwrap();
+        assert_eq!(mem.used_memory(), 0);
+        mem.set(b"b", b"v".to_vec(), None).unwrap();
+        mem.clear();
+        assert_eq!(mem.used_memory(), 0);
+    }
+    
+    #[test]
+    fn test_lfu_eviction() {
+        // One partition and fewer keys than EVICTION_SAMPLES, so every key is a candidate
+        let mem = MemTable::with_partitions(1);
+        mem.set(b"hot", b"v".to_vec(), None).unwrap();
+        mem.set(b"cold", b"v".to_vec(), None).unwrap();
+        assert_eq!(mem.access_frequency(b"cold"), Some(LFU_INIT_VAL));
+        
+        // The first access above the initial value always counts
+        for _ in 0..100 {
+            mem.get(b"hot");
+        }
+        assert!(mem.access_frequency(b"hot").unwrap() > LFU_INIT_VAL);
+        assert_eq!(lfu_log_incr(u8::MAX), u8::MAX);
+        assert_eq!(lfu_log_incr(LFU_INIT_VAL), LFU_INIT_VAL + 1);
+        
+        assert_eq!(mem.evict(EvictionPolicy::AllKeysLfu), Some(b"cold".to_vec()));
+        assert_eq!(mem.evict(EvictionPolicy::AllKeysLfu), Some(b"hot".to_vec()));
+        assert_eq!(mem.evict(EvictionPolicy::AllKeysLru), None);
+        assert_eq!(mem.access_frequency(b"hot"), None);
+    }
+    
+    #[test]
+    fn test_entry_size() {
+        let mem = MemTable::new();
+        mem.set(b"small", b"v".to_vec(), None).unwrap();
+        mem.set(b"large", vec![0u8; 4096], None).unwrap();
+        
+        let small = mem.entry_size(b"small").unwrap();
+        assert!(small > b"small".len() + 1);
+        assert!(mem.entry_size(b"large").unwrap() >= small + 4095);
+        assert_eq!(mem.entry_size(b"missing"), None);
+    }
+    
+    #[test]
+    fn test_set_mutations_and_store() {
+        let mem = MemTable::with_partitions(4);
+        let members = |ms: &[&[u8]]| ms.iter().map(|m| m.to_vec()).collect::<Vec<_>>();
+        
+        assert_eq!(mem.apply_mutation(b"a", &Mutation::SAdd(members(&[b"1", b"2", b"3"]))).unwrap().added, 3);
+        assert_eq!(mem.apply_mutation(b"b", &Mutation::SAdd(members(&[b"2", b"3", b"4"]))).unwrap().added, 3);
+        assert_eq!(mem.value_type(b"a"), Some("set"));
+        
+        // Strings and sets do not mix
+        mem.set(b"s", b"v".to_vec(), None).unwrap();
+        assert!(mem.apply_mutation(b"s", &Mutation::SAdd(members(&[b"x"]))).is_err());
+        assert!(mem.get_string(b"a").is_err());
+        assert!(mem.set_combine(SetOp::Union, &[b"a", b"s"]).is_err());
+        
+        // Store into a source key; empty results delete the destination
+        let (stored, removed) = mem.set_combine_store(SetOp::Inter, b"a", &[b"a", b"b"]).unwrap();
+        assert_eq!((stored.len(), removed), (2, false));
+        assert_eq!(mem.read_value(b"a", |v| v.as_set().unwrap().len()), Some(2));
+        assert!(mem.set_combine_store(SetOp::Diff, b"a", &[b"a", b"b"]).unwrap().1);
+        assert_eq!(mem.value_type(b"a"), None);
+        assert!(!mem.set_combine_store(SetOp::Diff, b"a", &[b"a", b"b"]).unwrap().1);
+        
+        // Removing the last member removes the key
+        assert_eq!(mem.apply_mutation(b"b", &Mutation::SRem(members(&[b"2", b"3", b"4"]))).unwrap().removed, 3);
+        assert_eq!(mem.value_type(b"b"), None);
+    }
+    
+    #[test]
+    fn test_incr_by() {
+        let mem = MemTable::new();
+        
+        // Signed: missing keys start at zero, overflow is rejected
+        assert_eq!(mem.incr_by(b"n", 5).unwrap().0, 5);
+        assert_eq!(mem.incr_by(b"n", -7).unwrap().0, -2);
+        mem.set(b"max", i64::MAX.to_string().into_bytes(), None).unwrap();
+        assert!(mem.incr_by(b"max", 1).is_err());
+        
+        // Unsigned: wraps on incr, clamps decr at zero, skips missing keys
+        mem.set(b"u", b"3".to_vec(), Some(Duration::from_secs(60))).unwrap();
+        let (value, ttl) = mem.incr_by_unsigned(b"u", 10, true).unwrap().unwrap();
+        assert_eq!(value, 0);
+        assert!(ttl.is_some());
+        mem.set(b"u", u64::MAX.to_string().into_bytes(), None).unwrap();
+        assert_eq!(mem.incr_by_unsigned(b"u", 2, false).unwrap().unwrap().0, 1);
+        assert_eq!(mem.incr_by_unsigned(b"missing", 1, false).unwrap(), None);
+        
+        mem.set(b"text", b"abc".to_vec(), None).unwrap();
+        assert!(mem.incr_by_unsigned(b"text", 1, false).is_err());
+        assert_eq!(mem.get(b"text").as_deref(), Some(b"abc".as_slice()));
+    }
+    
+    #[test]
+    fn test_hyperloglog() {
+        let mem = MemTable::new();
+        
+        // Creating counts as a change even with no elements
+        assert!(mem.pf_add(b"a", &[]).unwrap().0);
+        let elements: Vec<Vec<u8>> = (0..100).map(|i| format!("e{}", i).into_bytes()).collect();
+        assert!(mem.pf_add(b"a", &elements[..60]).unwrap().0);
+        assert!(!mem.pf_add(b"a", &elements[..60]).unwrap().0);
+        mem.pf_add(b"b", &elements[40..]).unwrap();
+        
+        assert_eq!(mem.pf_count(&[b"a"]).unwrap(), 60);
+        assert_eq!(mem.pf_count(&[b"a", b"b", b"missing"]).unwrap(), 100);
+        
+        // Merge keeps the destination's TTL and registers
+        mem.set_expiry(b"b", Some(Duration::from_secs(60)));
+        let (_, ttl) = mem.pf_merge(b"b", &[b"a"]).unwrap();
+        assert!(ttl.is_some());
+        assert_eq!(mem.pf_count(&[b"b"]).unwrap(), 100);
+        
+        mem.set(b"plain", b"text".to_vec(), None).unwrap();
+        assert!(mem.pf_add(b"plain", &elements).is_err());
+        assert!(mem.pf_count(&[b"plain"]).is_err());
+    }
+    
+    #[test]
+    fn test_list_edits() {
+        let mem = MemTable::new();
+        let elements = |items: &[&[u8]]| items.iter().map(|item| item.to_vec()).collect::<Vec<_>>();
+        mem.apply_mutation(b"l", &Mutation::RPush(elements(&[b"a", b"b", b"c", b"b", b"b"]))).unwrap();
+        
+        // LPOS: rank picks the nth match, negative ranks scan from the tail
+        assert_eq!(mem.list_pos(b"l", b"b", 1, 1).unwrap(), vec![1]);
+        assert_eq!(mem.list_pos(b"l", b"b", 2, 0).unwrap(), vec![3, 4]);
+        assert_eq!(mem.list_pos(b"l", b"b", -1, 2).unwrap(), vec![4, 3]);
+        assert!(mem.list_pos(b"l", b"z", 1, 0).unwrap().is_empty());
+        
+        // LINSERT: first pivot only; -1 for a missing pivot, 0 for a missing key
+        let (len, written) = mem.list_insert(b"l", true, b"b", b"x").unwrap();
+        assert_eq!(len, 6);
+        assert_eq!(written.unwrap().0.as_list().unwrap()[1], b"x");
+        assert_eq!(mem.list_insert(b"l", false, b"zz", b"y").unwrap(), (-1, None));
+        assert_eq!(mem.list_insert(b"missing", false, b"a", b"y").unwrap(), (0, None));
+        
+        // LSET: negative indexes, range and key errors
+        mem.list_set(b"l", -1, b"end").unwrap();
+        assert_eq!(mem.list_range(b"l", -2, -1).unwrap(), elements(&[b"b", b"end"]));
+        assert!(mem.list_set(b"l", 6, b"v").is_err());
+        assert!(mem.list_set(b"missing", 0, b"v").is_err());
+        assert_eq!(mem.list_len(b"l").unwrap(), 6);
+    }
+    
+    #[test]
+    fn test_get_set_range() {
+        let mem = MemTable::new();
+        mem.set(b"s", b"Hello World".to_vec(), Some(Duration::from_secs(60))).unwrap();
+        
+        assert_eq!(mem.get_range(b"s", 0, 4).unwrap(), b"Hello");
+        assert_eq!(mem.get_range(b"s", -5, -1).unwrap(), b"World");
+        assert_eq!(mem.get_range(b"s", 0, -1).unwrap(), b"Hello World");
+        assert_eq!(mem.get_range(b"s", 6, 100).unwrap(), b"World");
+        assert!(mem.get_range(b"s", 5, 3).unwrap().is_empty());
+        assert!(mem.get_range(b"s", 20, 30).unwrap().is_empty());
+        assert!(mem.get_range(b"missing", 0, -1).unwrap().is_empty());
+        
+        // Overwrite in place keeps the TTL
+        let (len, written) = mem.set_range(b"s", 6, b"Redis").unwrap();
+        assert_eq!(len, 11);
+        let (value, ttl) = written.unwrap();
+        assert_eq!(value, b"Hello Redis");
+        assert!(ttl.is_some());
+        
+        // Missing keys are zero-padded; an empty patch writes nothing
+        assert_eq!(mem.set_range(b"new", 3, b"x").unwrap().0, 4);
+        assert_eq!(mem.get(b"new").as_deref(), Some(b"\0\0\0x".as_slice()));
+        assert_eq!(mem.set_range(b"empty", 5, b"").unwrap(), (0, None));
+        assert!(mem.get(b"empty").is_none());
+        
+        assert_eq!(mem.strlen(b"s").unwrap(), 11);
+        assert_eq!(mem.strlen(b"missing").unwrap(), 0);
+    }
+    
+    #[test]
+    fn test_bit_count() {
+        let mem = MemTable::new();
+        mem.set(b"k", b"foobar".to_vec(), None).unwrap();
+        
+        assert_eq!(mem.bit_count(b"k", None).unwrap(), 26);
+        assert_eq!(mem.bit_count(b"k", Some((0, 0))).unwrap(), 4);
+        assert_eq!(mem.bit_count(b"k", Some((1, 1))).unwrap(), 6);
+        assert_eq!(mem.bit_count(b"k", Some((-2, -1))).unwrap(), 7);
+        assert_eq!(mem.bit_count(b"missing", None).unwrap(), 0);
+        
+        // Word-sized chunks plus a tail
+        mem.set(b"ones", vec![0xFF; 21], None).unwrap();
+        assert_eq!(mem.bit_count(b"ones", None).unwrap(), 168);
+    }
+    
+    #[test]
+    fn test_set_get_bit() {
+        let mem = MemTable::new();
+        
+        // Bit 7 is the low bit of the first byte; far offsets zero-pad
+        assert_eq!(mem.set_bit(b"b", 7, true).unwrap().0, 0);
+        assert_eq!(mem.get(b"b").as_deref(), Some(b"\x01".as_slice()));
+        let (old, value, _) = mem.set_bit(b"b", 100, true).unwrap();
+        assert_eq!(old, 0);
+        assert_eq!(value.len(), 13);
+        
+        assert_eq!(mem.get_bit(b"b", 7).unwrap(), 1);
+        assert_eq!(mem.get_bit(b"b", 6).unwrap(), 0);
+        assert_eq!(mem.get_bit(b"b", 100).unwrap(), 1);
+        assert_eq!(mem.get_bit(b"b", 10_000).unwrap(), 0);
+        assert_eq!(mem.get_bit(b"missing", 0).unwrap(), 0);
+        
+        // Clearing returns the old bit
+        assert_eq!(mem.set_bit(b"b", 100, false).unwrap().0, 1);
+        assert_eq!(mem.bit_count(b"b", None).unwrap(), 1);
+    }
 }
\ No newline at end of file
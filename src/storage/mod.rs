@@ -1,3 +1,9 @@
+pub mod encoding;
+pub mod engine;
 pub mod memory;
+pub mod tiered;
 pub mod disk;
-pub mod gc;
\ No newline at end of file
+pub mod gc;
+pub mod hll;
+pub mod value;
+pub mod zset;
\ No newline at end of file
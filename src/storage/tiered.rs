@@ -0,0 +1,600 @@
+// Tiered storage - idle strings spilled from the MemTable to an on-disk log
+// The AOF stays the source of truth: the cold log only extends memory, so it starts empty
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::hash::{BuildHasher, RandomState};
+use std::io;
+use std::os::unix::fs::FileExt;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU8, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::time::{Duration, Instant};
+
+use crate::storage::disk::NvmeAccess;
+use crate::storage::engine::ScanEntry;
+use crate::storage::value::ValueKind;
+
+/// Cold record header: key length (u32) then value length (u32), followed by both
+const RECORD_HEADER: usize = 8;
+
+/// Counters in the cold key filter (a power of two) - 4 MiB, enough for a few million keys
+const FILTER_SLOTS: usize = 1 << 22;
+
+/// The log is compacted once it is at least this large and no more than half live
+const COMPACT_MIN_BYTES: u64 = 1 << 20;
+
+/// Default idle time before a string is spilled
+pub const DEFAULT_COLD_TIER_IDLE: Duration = Duration::from_secs(300);
+
+/// Where and when a MemTable spills idle strings - spills run on the background GC thread
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ColdTierConfig {
+    // Cold log file, emptied at startup
+    pub path: PathBuf,
+
+    // Strings unused this long are spilled
+    pub idle_threshold: Duration,
+
+    // Bypass the page cache (O_DIRECT)
+    pub direct: bool,
+}
+
+/// ColdTier - Log-structured disk store for the strings a MemTable spilled
+/// A key lives in exactly one tier: `MemTable::spill_idle` moves idle strings here and
+/// any operation on a key brings it back first, so callers never see the difference
+pub struct ColdTier {
+    // Log and index - spills, promotions and compaction hold this lock
+    store: Mutex<ColdStore>,
+
+    // Counting filter over cold keys, so keys that aren't cold skip the lock
+    filter: KeyFilter,
+
+    // Keys in the index, readable without the lock
+    keys: AtomicUsize,
+
+    // Open snapshot views still reading records at their current offsets
+    readers: AtomicUsize,
+
+    // Keys idle at least this long are spilled
+    idle_threshold: Duration,
+}
+
+/// Cold log plus the location of every key living in it
+struct ColdStore {
+    // Spilled records, appended
+    log: ColdLog,
+
+    // Key -> record location (keys absent here are in the MemTable)
+    index: HashMap<Vec<u8>, ColdSlot>,
+
+    // Bytes of the records the index points at - the rest of the log is dead
+    live_bytes: u64,
+
+    // Where the log lives and how it is accessed, for writing a compacted copy
+    path: PathBuf,
+    direct: bool,
+}
+
+/// Where a cold key's value lives in the log
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct ColdSlot {
+    // Offset of the value bytes
+    offset: u64,
+
+    // Value length
+    len: u32,
+
+    // Expiry carried over from the MemTable
+    expires_at: Option<Instant>,
+}
+
+/// Cold log file, through the page cache or with direct I/O
+enum ColdLog {
+    // Regular file read and written with positional I/O
+    Buffered { file: File, len: u64 },
+
+    // O_DIRECT | O_DSYNC access via NvmeAccess - bypasses the page cache
+    Direct { device: NvmeAccess, len: u64 },
+}
+
+/// Counting Bloom filter over cold keys: false means the key is certainly not cold
+/// A saturated counter is never decremented, so removals can't cause a false negative
+struct KeyFilter {
+    counters: Box<[AtomicU8]>,
+    hasher: RandomState,
+}
+
+/// A spill in progress - holds the tier lock so no key moves between tiers meanwhile
+pub(crate) struct ColdSpill<'a> {
+    tier: &'a ColdTier,
+    store: MutexGuard<'a, ColdStore>,
+}
+
+/// Cold keys as of a MemTable snapshot, read from disk as they are iterated
+/// Compaction waits while a view is open, so its records stay where they were
+pub(crate) struct ColdView<'a> {
+    tier: &'a ColdTier,
+    entries: std::vec::IntoIter<(Vec<u8>, ColdSlot)>,
+    now: Instant,
+}
+
+impl ColdLog {
+    /// Create (or empty) the log at `path`
+    fn create(path: &Path, direct: bool) -> io::Result<Self> {
+        let file = OpenOptions::new().read(true).write(true).create(true).truncate(true).open(path)?;
+        if !direct {
+            return Ok(ColdLog::Buffered { file, len: 0 });
+        }
+
+        drop(file);
+        let mut device = NvmeAccess::new(path)?;
+        device.open()?;
+        Ok(ColdLog::Direct { device, len: 0 })
+    }
+
+    /// Append a record, returning its offset
+    fn append(&mut self, record: &[u8]) -> io::Result<u64> {
+        match self {
+            ColdLog::Buffered { file, len } => {
+                let offset = *len;
+                file.write_all_at(record, offset)?;
+                *len += record.len() as u64;
+                Ok(offset)
+            }
+            ColdLog::Direct { device, len } => {
+                let offset = *len;
+                device.raw_write(offset, record)?;
+                *len += record.len() as u64;
+                Ok(offset)
+            }
+        }
+    }
+
+    /// Read `len` bytes at `offset`
+    fn read(&mut self, offset: u64, len: usize) -> io::Result<Vec<u8>> {
+        match self {
+            ColdLog::Buffered { file, .. } => {
+                let mut buf = vec![0u8; len];
+                file.read_exact_at(&mut buf, offset)?;
+                Ok(buf)
+            }
+            ColdLog::Direct { device, .. } => device.raw_read(offset, len),
+        }
+    }
+
+    /// Drop every record
+    fn clear(&mut self) -> io::Result<()> {
+        match self {
+            ColdLog::Buffered { file, len } => {
+                file.set_len(0)?;
+                *len = 0;
+            }
+            ColdLog::Direct { len, .. } => *len = 0,
+        }
+        Ok(())
+    }
+
+    /// Bytes written so far
+    fn len(&self) -> u64 {
+        match self {
+            ColdLog::Buffered { len, .. } | ColdLog::Direct { len, .. } => *len,
+        }
+    }
+}
+
+impl ColdSlot {
+    /// Whether the key expired while cold
+    fn is_expired(&self, now: Instant) -> bool {
+        self.expires_at.is_some_and(|expires| now > expires)
+    }
+
+    /// Remaining TTL as of `now`
+    fn ttl(&self, now: Instant) -> Option<Duration> {
+        self.expires_at.map(|expires| expires.saturating_duration_since(now))
+    }
+
+    /// Size of the whole record for `key`
+    fn record_len(&self, key: &[u8]) -> u64 {
+        (RECORD_HEADER + key.len()) as u64 + self.len as u64
+    }
+}
+
+impl ColdStore {
+    /// Append a record for `key`, returning where its value landed
+    fn write(&mut self, key: &[u8], value: &[u8], expires_at: Option<Instant>) -> io::Result<ColdSlot> {
+        let mut record = Vec::with_capacity(RECORD_HEADER + key.len() + value.len());
+        record.extend_from_slice(&(key.len() as u32).to_le_bytes());
+        record.extend_from_slice(&(value.len() as u32).to_le_bytes());
+        record.extend_from_slice(key);
+        record.extend_from_slice(value);
+
+        let offset = self.log.append(&record)?;
+        Ok(ColdSlot {
+            offset: offset + (RECORD_HEADER + key.len()) as u64,
+            len: value.len() as u32,
+            expires_at,
+        })
+    }
+
+    /// Read a cold value
+    fn read(&mut self, slot: &ColdSlot) -> io::Result<Vec<u8>> {
+        self.log.read(slot.offset, slot.len as usize)
+    }
+
+    /// Copy the live records to a fresh log once dead ones make up half of it
+    /// Offsets change, so this is skipped while a snapshot view is open
+    fn compact_if_due(&mut self, readers: usize) -> io::Result<()> {
+        let total = self.log.len();
+        if readers > 0 || total < COMPACT_MIN_BYTES || total - self.live_bytes < self.live_bytes {
+            return Ok(());
+        }
+        if self.index.is_empty() {
+            return self.log.clear();
+        }
+
+        // Moved slots are applied only once the copy is complete and in place
+        let temp = self.path.with_extension("compact");
+        let mut compacted = ColdStore {
+            log: ColdLog::create(&temp, self.direct)?,
+            index: HashMap::new(),
+            live_bytes: 0,
+            path: temp.clone(),
+            direct: self.direct,
+        };
+        let mut moved = Vec::with_capacity(self.index.len());
+        for (key, slot) in &self.index {
+            let value = self.log.read(slot.offset, slot.len as usize)?;
+            moved.push((key.clone(), compacted.write(key, &value, slot.expires_at)?));
+        }
+        std::fs::rename(&temp, &self.path)?;
+
+        self.log = compacted.log;
+        self.index.extend(moved);
+        Ok(())
+    }
+}
+
+impl KeyFilter {
+    fn new() -> Self {
+        Self {
+            counters: (0..FILTER_SLOTS).map(|_| AtomicU8::new(0)).collect(),
+            hasher: RandomState::new(),
+        }
+    }
+
+    /// The two counters `key` maps to
+    fn slots(&self, key: &[u8]) -> [usize; 2] {
+        let hash = self.hasher.hash_one(key);
+        [hash as usize & (FILTER_SLOTS - 1), (hash >> 32) as usize & (FILTER_SLOTS - 1)]
+    }
+
+    fn insert(&self, key: &[u8]) {
+        for slot in self.slots(key) {
+            let _ = self.counters[slot].fetch_update(Ordering::Relaxed, Ordering::Relaxed, |count| {
+                (count < u8::MAX).then_some(count + 1)
+            });
+        }
+    }
+
+    fn remove(&self, key: &[u8]) {
+        for slot in self.slots(key) {
+            let _ = self.counters[slot].fetch_update(Ordering::Relaxed, Ordering::Relaxed, |count| {
+                (count > 0 && count < u8::MAX).then(|| count - 1)
+            });
+        }
+    }
+
+    fn may_contain(&self, key: &[u8]) -> bool {
+        self.slots(key).iter().all(|&slot| self.counters[slot].load(Ordering::Relaxed) > 0)
+    }
+
+    fn clear(&self) {
+        for counter in self.counters.iter() {
+            counter.store(0, Ordering::Relaxed);
+        }
+    }
+}
+
+impl ColdTierConfig {
+    /// Open the tier this describes
+    pub fn open(&self) -> io::Result<ColdTier> {
+        ColdTier::open(&self.path, self.idle_threshold, self.direct)
+    }
+}
+
+impl ColdTier {
+    /// Cold tier over a log at `path` (emptied on open); `direct` uses O_DIRECT I/O
+    /// The threshold should be far longer than any one command, or a key can be spilled
+    /// between being looked up and being used
+    pub fn open(path: impl AsRef<Path>, idle_threshold: Duration, direct: bool) -> io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        Ok(Self {
+            store: Mutex::new(ColdStore {
+                log: ColdLog::create(&path, direct)?,
+                index: HashMap::new(),
+                live_bytes: 0,
+                path,
+                direct,
+            }),
+            filter: KeyFilter::new(),
+            keys: AtomicUsize::new(0),
+            readers: AtomicUsize::new(0),
+            idle_threshold,
+        })
+    }
+
+    /// How long a string must go unused before it is spilled
+    pub fn idle_threshold(&self) -> Duration {
+        self.idle_threshold
+    }
+
+    /// Keys currently on disk, counting expired ones not yet collected
+    pub fn len(&self) -> usize {
+        self.keys.load(Ordering::Relaxed)
+    }
+
+    /// Whether no key is on disk
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Bytes in the cold log, including dead records not yet compacted away
+    pub fn log_bytes(&self) -> u64 {
+        self.lock().log.len()
+    }
+
+    /// Whether `key` may be on disk - false is certain and takes no lock
+    pub(crate) fn may_contain(&self, key: &[u8]) -> bool {
+        !self.is_empty() && self.filter.may_contain(key)
+    }
+
+    /// Move `key` out of the tier, handing its value and remaining TTL to `restore` while
+    /// the tier is still locked, so nobody finds the key in neither tier
+    /// Returns false if the key wasn't cold (or had expired there)
+    pub(crate) fn take(&self, key: &[u8], restore: impl FnOnce(Vec<u8>, Option<Duration>)) -> io::Result<bool> {
+        let mut store = self.lock();
+        let Some(slot) = store.index.get(key).copied() else {
+            return Ok(false);
+        };
+
+        let now = Instant::now();
+        let value = if slot.is_expired(now) { None } else { Some(store.read(&slot)?) };
+        self.unindex(&mut store, key, &slot);
+        match value {
+            Some(value) => {
+                restore(value, slot.ttl(now));
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// Lock the tier for a spill
+    pub(crate) fn begin_spill(&self) -> ColdSpill<'_> {
+        ColdSpill { tier: self, store: self.lock() }
+    }
+
+    /// Run `f` with the tier locked and return its result along with a view of every live
+    /// cold key as of then
+    pub(crate) fn capture<R>(&self, f: impl FnOnce() -> R) -> (R, ColdView<'_>) {
+        let store = self.lock();
+        let now = Instant::now();
+        let entries: Vec<_> = store.index.iter()
+            .filter(|(_, slot)| !slot.is_expired(now))
+            .map(|(key, slot)| (key.clone(), *slot))
+            .collect();
+        self.readers.fetch_add(1, Ordering::Relaxed);
+        let result = f();
+        drop(store);
+
+        (result, ColdView { tier: self, entries: entries.into_iter(), now })
+    }
+
+    /// Call `f` with every live cold key and its expiry
+    pub(crate) fn for_each_live(&self, mut f: impl FnMut(&[u8], Option<Instant>)) {
+        let store = self.lock();
+        let now = Instant::now();
+        for (key, slot) in store.index.iter().filter(|(_, slot)| !slot.is_expired(now)) {
+            f(key, slot.expires_at);
+        }
+    }
+
+    /// Drop keys that expired while cold, returning them
+    pub(crate) fn gc(&self) -> Vec<Vec<u8>> {
+        let mut store = self.lock();
+        let now = Instant::now();
+        let expired: Vec<(Vec<u8>, ColdSlot)> = store.index.iter()
+            .filter(|(_, slot)| slot.is_expired(now))
+            .map(|(key, slot)| (key.clone(), *slot))
+            .collect();
+        for (key, slot) in &expired {
+            self.unindex(&mut store, key, slot);
+        }
+        expired.into_iter().map(|(key, _)| key).collect()
+    }
+
+    /// Drop every cold key, returning how many there were
+    pub(crate) fn clear(&self) -> usize {
+        let mut store = self.lock();
+        let dropped = store.index.len();
+        store.index.clear();
+        store.live_bytes = 0;
+        self.filter.clear();
+        self.keys.store(0, Ordering::Relaxed);
+
+        // An open view still reads the old records; compaction truncates once it closes
+        if self.readers.load(Ordering::Relaxed) == 0
+            && let Err(e) = store.log.clear()
+        {
+            eprintln!("Failed to truncate cold tier log: {}", e);
+        }
+        dropped
+    }
+
+    /// Forget a key's record
+    fn unindex(&self, store: &mut ColdStore, key: &[u8], slot: &ColdSlot) {
+        if store.index.remove(key).is_some() {
+            store.live_bytes -= slot.record_len(key);
+            self.filter.remove(key);
+            self.keys.fetch_sub(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Lock the log and index, recovering from poisoning
+    fn lock(&self) -> MutexGuard<'_, ColdStore> {
+        self.store.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl ColdSpill<'_> {
+    /// Write `key`'s value to the log; it isn't cold until `commit`
+    pub(crate) fn write(&mut self, key: &[u8], value: &[u8], expires_at: Option<Instant>) -> io::Result<ColdSlot> {
+        self.store.write(key, value, expires_at)
+    }
+
+    /// Make `key` cold at `slot` - call just before removing it from the MemTable
+    pub(crate) fn commit(&mut self, key: Vec<u8>, slot: ColdSlot) {
+        self.store.live_bytes += slot.record_len(&key);
+        self.tier.filter.insert(&key);
+        self.tier.keys.fetch_add(1, Ordering::Relaxed);
+        self.store.index.insert(key, slot);
+    }
+
+    /// End the spill, compacting the log if enough of it is dead
+    pub(crate) fn finish(mut self) -> io::Result<()> {
+        let readers = self.tier.readers.load(Ordering::Relaxed);
+        self.store.compact_if_due(readers)
+    }
+}
+
+impl Iterator for ColdView<'_> {
+    type Item = ScanEntry;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        for (key, slot) in self.entries.by_ref() {
+            match self.tier.lock().read(&slot) {
+                Ok(value) => return Some((key, ValueKind::String(Arc::from(value)), slot.ttl(self.now))),
+                Err(e) => eprintln!("Cold tier read failed: {}", e),
+            }
+        }
+        None
+    }
+}
+
+impl Drop for ColdView<'_> {
+    fn drop(&mut self) {
+        self.tier.readers.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::state::GlobalState;
+    use crate::persistence::aof::AppendOnlyFile;
+    use crate::storage::memory::MemTable;
+
+    /// MemTable with a cold tier under `dir`
+    fn tiered(dir: &Path, idle_threshold: Duration) -> MemTable {
+        MemTable::new().with_cold_tier(ColdTier::open(dir.join("cold.log"), idle_threshold, false).unwrap())
+    }
+
+    #[test]
+    fn test_spill_and_promote() {
+        let dir = tempfile::tempdir().unwrap();
+        let mem = tiered(dir.path(), Duration::from_millis(20));
+        mem.set(b"idle", b"zzz".to_vec(), None).unwrap();
+        mem.set(b"ttl", b"soon".to_vec(), Some(Duration::from_millis(150))).unwrap();
+        mem.set(b"counter", b"41".to_vec(), None).unwrap();
+        mem.set_value(b"set", ValueKind::Set([b"m".to_vec()].into()), None).unwrap();
+        std::thread::sleep(Duration::from_millis(30));
+        mem.set(b"busy", b"b".to_vec(), None).unwrap();
+
+        // Only idle strings move; collections and recently used keys stay in memory
+        assert_eq!(mem.spill_idle(10).unwrap(), 3);
+        let cold = mem.cold_tier().unwrap();
+        assert_eq!((cold.len(), mem.len()), (3, 5));
+        assert_eq!(mem.snapshot_iter().count(), 5);
+
+        // Any operation brings the value back first
+        assert_eq!(mem.get(b"idle").as_deref(), Some(&b"zzz"[..]));
+        assert_eq!(mem.incr_by(b"counter", 1).unwrap().0, 42);
+        assert_eq!(cold.len(), 1);
+        std::thread::sleep(Duration::from_millis(150));
+        assert!(mem.get(b"ttl").is_none());
+        assert_eq!(cold.len(), 0);
+
+        // A missing key never takes the tier lock, even with keys on disk
+        std::thread::sleep(Duration::from_millis(30));
+        assert_eq!(mem.spill_idle(10).unwrap(), 3);
+        let store = cold.lock();
+        assert!(!(0..100).any(|i| cold.may_contain(format!("missing{}", i).as_bytes())));
+        drop(store);
+        assert!(mem.delete(b"idle").unwrap());
+        mem.set(b"busy", b"again".to_vec(), None).unwrap();
+        assert_eq!((cold.len(), mem.len()), (1, 3));
+        assert_eq!(mem.clear(), 3);
+        assert!(cold.is_empty());
+    }
+
+    #[test]
+    fn test_cold_log_compacts() {
+        let dir = tempfile::tempdir().unwrap();
+        let mem = tiered(dir.path(), Duration::ZERO);
+        let value = vec![b'x'; 4096];
+        for i in 0..512 {
+            mem.set(format!("k{}", i).as_bytes(), value.clone(), None).unwrap();
+        }
+        std::thread::sleep(Duration::from_millis(2));
+        assert_eq!(mem.spill_idle(usize::MAX).unwrap(), 512);
+        let cold = mem.cold_tier().unwrap();
+        let full = cold.log_bytes();
+        assert!(full >= COMPACT_MIN_BYTES);
+
+        // Churn leaves dead records behind; the next spill copies out the live ones
+        for i in 0..400 {
+            mem.get(format!("k{}", i).as_bytes()).unwrap();
+        }
+        std::thread::sleep(Duration::from_millis(2));
+        assert_eq!(mem.spill_idle(usize::MAX).unwrap(), 400);
+        assert!(cold.log_bytes() < 2 * full);
+        for i in 0..300 {
+            mem.delete(format!("k{}", i).as_bytes()).unwrap();
+        }
+        mem.set(b"trigger", b"v".to_vec(), None).unwrap();
+        std::thread::sleep(Duration::from_millis(2));
+        mem.spill_idle(usize::MAX).unwrap();
+        assert!(cold.log_bytes() < full / 2);
+        assert_eq!(mem.get(b"k511").as_deref(), Some(&value[..]));
+        assert_eq!(mem.get(b"k300").as_deref(), Some(&value[..]));
+        assert!(mem.get(b"k0").is_none());
+    }
+
+    #[test]
+    fn test_state_serves_cold_keys() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("tiered.aof");
+        {
+            let state = GlobalState::new(Arc::new(MemTable::new()), AppendOnlyFile::new(&path).unwrap());
+            state.set(b"k", b"v".to_vec(), None).unwrap();
+            state.set(b"n", b"1".to_vec(), None).unwrap();
+            state.flush().unwrap();
+        }
+
+        // The server's own state type, so every front end reaches the cold keys
+        let state = GlobalState::new(Arc::new(tiered(dir.path(), Duration::from_millis(1))), AppendOnlyFile::new(&path).unwrap());
+        std::thread::sleep(Duration::from_millis(5));
+        assert_eq!(state.engine().spill_idle(10).unwrap(), 2);
+        assert_eq!(state.get(b"k").as_deref(), Some(&b"v"[..]));
+        assert_eq!(state.incr_by(b"n", 1).unwrap(), 2);
+
+        // Rewrites and snapshots include what is on disk
+        std::thread::sleep(Duration::from_millis(5));
+        assert_eq!(state.engine().spill_idle(10).unwrap(), 2);
+        state.rewrite_aof().unwrap();
+        drop(state);
+        let state = GlobalState::new(Arc::new(MemTable::new()), AppendOnlyFile::new(&path).unwrap());
+        assert_eq!(state.get(b"k").as_deref(), Some(&b"v"[..]));
+        assert_eq!(state.get(b"n").as_deref(), Some(&b"2"[..]));
+    }
+}
@@ -0,0 +1,763 @@
+// Typed values held by the MemTable - strings and collections
+use std::borrow::Cow;
+use std::collections::{HashSet, VecDeque};
+use std::sync::Arc;
+
+use crate::storage::zset::{OrderedFloat, SortedSet};
+use crate::util::lz4;
+
+/// Error for commands run against a key holding another type
+pub const WRONGTYPE: &str = "WRONGTYPE Operation against a key holding the wrong kind of value";
+
+/// Collection elements sampled by MEMORY USAGE by default
+pub const DEFAULT_MEMORY_SAMPLES: usize = 5;
+
+/// Encoding tags for ValueKind
+const TAG_STRING: u8 = 0;
+const TAG_SET: u8 = 1;
+const TAG_ZSET: u8 = 2;
+const TAG_COMPRESSED: u8 = 3;
+const TAG_LIST: u8 = 4;
+
+/// Encoding tags for Mutation
+const OP_SADD: u8 = 1;
+const OP_SREM: u8 = 2;
+const OP_ZADD: u8 = 3;
+const OP_ZREM: u8 = 4;
+const OP_LPUSH: u8 = 5;
+const OP_RPUSH: u8 = 6;
+
+/// ValueKind - Value stored under a key
+#[derive(Debug, Clone, PartialEq)]
+pub enum ValueKind {
+    // Binary-safe string, shared so reads hand out a refcount instead of a copy
+    String(Arc<[u8]>),
+
+    // Unordered collection of unique members
+    Set(HashSet<Vec<u8>>),
+    
+    // Members ordered by score
+    SortedSet(SortedSet),
+    
+    // String kept compressed; reads see the original bytes
+    Compressed(CompressedString),
+    
+    // Ordered elements, pushed and popped at either end
+    List(VecDeque<Vec<u8>>),
+}
+
+/// Codec for compressed string values
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Codec {
+    Lz4,
+}
+
+/// Transparent compression for large string values
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Compression {
+    // Algorithm used
+    pub codec: Codec,
+    
+    // Strings at least this long are compressed
+    pub threshold: usize,
+}
+
+/// Compressed bytes of a string value
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompressedString {
+    codec: Codec,
+    raw_len: usize,
+    data: Arc<[u8]>,
+}
+
+/// Set algebra operation (SINTER/SUNION/SDIFF)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SetOp {
+    Inter,
+    Union,
+    Diff,
+}
+
+/// Mutation - Incremental collection change
+/// Applied identically by live commands and AOF replay
+#[derive(Debug, Clone, PartialEq)]
+pub enum Mutation {
+    // Add members to a set
+    SAdd(Vec<Vec<u8>>),
+
+    // Remove members from a set
+    SRem(Vec<Vec<u8>>),
+    
+    // Add or update scored members, subject to ZADD flags
+    ZAdd(Vec<(f64, Vec<u8>)>, ZAddFlags),
+    
+    // Remove members from a sorted set
+    ZRem(Vec<Vec<u8>>),
+    
+    // Push elements onto the head of a list, one at a time
+    LPush(Vec<Vec<u8>>),
+    
+    // Append elements to the tail of a list
+    RPush(Vec<Vec<u8>>),
+}
+
+/// ZADD condition flags
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ZAddFlags {
+    // Only add new members
+    pub nx: bool,
+    
+    // Only update existing members
+    pub xx: bool,
+    
+    // Only update when the new score is greater
+    pub gt: bool,
+    
+    // Only update when the new score is less
+    pub lt: bool,
+    
+    // Add the score to the current one (ZINCRBY)
+    pub incr: bool,
+}
+
+/// Element counts changed by a mutation
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Applied {
+    // Elements that did not exist before
+    pub added: usize,
+    
+    // Existing elements whose score changed
+    pub updated: usize,
+    
+    // Elements removed
+    pub removed: usize,
+    
+    // Elements left alone because of NX/XX/GT/LT
+    pub skipped: usize,
+}
+
+impl Applied {
+    /// Whether the stored value changed (and so must be logged)
+    pub fn changed(&self) -> bool {
+        self.added + self.updated + self.removed > 0
+    }
+}
+
+impl Codec {
+    /// Parse a codec name ("lz4")
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "lz4" => Some(Codec::Lz4),
+            _ => None,
+        }
+    }
+    
+    /// Encoding byte
+    fn id(self) -> u8 {
+        match self {
+            Codec::Lz4 => 1,
+        }
+    }
+    
+    /// Codec for an encoding byte
+    fn from_id(id: u8) -> Result<Self, String> {
+        match id {
+            1 => Ok(Codec::Lz4),
+            other => Err(format!("Unknown compression codec: {}", other)),
+        }
+    }
+}
+
+impl CompressedString {
+    /// Uncompressed length
+    pub fn raw_len(&self) -> usize {
+        self.raw_len
+    }
+    
+    /// Compressed length
+    pub fn stored_len(&self) -> usize {
+        self.data.len()
+    }
+    
+    /// Original bytes
+    fn decompress(&self) -> Vec<u8> {
+        // Only built by `ValueKind::string` or a CRC-checked decode
+        match self.codec {
+            Codec::Lz4 => lz4::decompress(&self.data, self.raw_len).expect("valid compressed string"),
+        }
+    }
+}
+
+impl ValueKind {
+    /// String value, compressed when `compression` applies and actually saves space
+    pub fn string(bytes: Vec<u8>, compression: Option<Compression>) -> Self {
+        if let Some(compression) = compression
+            && bytes.len() >= compression.threshold
+        {
+            let data = match compression.codec {
+                Codec::Lz4 => lz4::compress(&bytes),
+            };
+            if data.len() < bytes.len() {
+                return ValueKind::Compressed(CompressedString {
+                    codec: compression.codec,
+                    raw_len: bytes.len(),
+                    data: data.into(),
+                });
+            }
+        }
+        ValueKind::String(bytes.into())
+    }
+    
+    /// Redis TYPE name
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            ValueKind::String(_) | ValueKind::Compressed(_) => "string",
+            ValueKind::Set(_) => "set",
+            ValueKind::SortedSet(_) => "zset",
+            ValueKind::List(_) => "list",
+        }
+    }
+
+    /// String bytes (borrowed unless compressed), or WRONGTYPE
+    pub fn as_string(&self) -> Result<Cow<'_, [u8]>, String> {
+        match self {
+            ValueKind::String(bytes) => Ok(Cow::Borrowed(bytes)),
+            ValueKind::Compressed(compressed) => Ok(Cow::Owned(compressed.decompress())),
+            _ => Err(WRONGTYPE.to_string()),
+        }
+    }
+
+    /// Byte length of a string (no decompression), or WRONGTYPE
+    pub fn string_len(&self) -> Result<usize, String> {
+        match self {
+            ValueKind::String(bytes) => Ok(bytes.len()),
+            ValueKind::Compressed(compressed) => Ok(compressed.raw_len()),
+            _ => Err(WRONGTYPE.to_string()),
+        }
+    }
+    
+    /// Shared handle to string bytes, or WRONGTYPE
+    pub fn shared_string(&self) -> Result<Arc<[u8]>, String> {
+        match self {
+            ValueKind::String(bytes) => Ok(bytes.clone()),
+            ValueKind::Compressed(compressed) => Ok(compressed.decompress().into()),
+            _ => Err(WRONGTYPE.to_string()),
+        }
+    }
+    
+    /// Borrow set members, or WRONGTYPE
+    pub fn as_set(&self) -> Result<&HashSet<Vec<u8>>, String> {
+        match self {
+            ValueKind::Set(members) => Ok(members),
+            _ => Err(WRONGTYPE.to_string()),
+        }
+    }
+
+    /// Borrow sorted set, or WRONGTYPE
+    pub fn as_sorted_set(&self) -> Result<&SortedSet, String> {
+        match self {
+            ValueKind::SortedSet(zset) => Ok(zset),
+            _ => Err(WRONGTYPE.to_string()),
+        }
+    }
+    
+    /// Borrow list elements, or WRONGTYPE
+    pub fn as_list(&self) -> Result<&VecDeque<Vec<u8>>, String> {
+        match self {
+            ValueKind::List(list) => Ok(list),
+            _ => Err(WRONGTYPE.to_string()),
+        }
+    }
+    
+    /// Whether this is a collection with no elements left (such keys are removed)
+    pub fn is_empty_collection(&self) -> bool {
+        match self {
+            ValueKind::String(_) | ValueKind::Compressed(_) => false,
+            ValueKind::Set(members) => members.is_empty(),
+            ValueKind::SortedSet(zset) => zset.is_empty(),
+            ValueKind::List(list) => list.is_empty(),
+        }
+    }
+
+    /// Estimated heap bytes, extrapolated from up to `samples` elements (0 = all)
+    pub fn mem_size(&self, samples: usize) -> usize {
+        match self {
+            ValueKind::String(bytes) => bytes.len(),
+            ValueKind::Compressed(compressed) => compressed.data.len(),
+            ValueKind::Set(members) => {
+                let limit = if samples == 0 { members.len() } else { samples.min(members.len()) };
+                if limit == 0 {
+                    return 0;
+                }
+
+                // Each member: vec header + bytes + hash table control byte
+                let sampled: usize = members.iter()
+                    .take(limit)
+                    .map(|m| std::mem::size_of::<Vec<u8>>() + m.capacity() + 1)
+                    .sum();
+                sampled * members.len() / limit
+            }
+            ValueKind::SortedSet(zset) => {
+                let limit = if samples == 0 { zset.len() } else { samples.min(zset.len()) };
+                if limit == 0 {
+                    return 0;
+                }
+                
+                // Each member is stored twice (score index and score map) plus the score
+                let sampled: usize = zset.iter()
+                    .take(limit)
+                    .map(|(m, _)| 2 * (std::mem::size_of::<Vec<u8>>() + m.capacity()) + 2 * std::mem::size_of::<f64>())
+                    .sum();
+                sampled * zset.len() / limit
+            }
+            ValueKind::List(list) => {
+                let limit = if samples == 0 { list.len() } else { samples.min(list.len()) };
+                if limit == 0 {
+                    return 0;
+                }
+                
+                let sampled: usize = list.iter()
+                    .take(limit)
+                    .map(|element| std::mem::size_of::<Vec<u8>>() + element.capacity())
+                    .sum();
+                sampled * list.len() / limit
+            }
+        }
+    }
+
+    /// Serialize for AOF / snapshots: tag byte followed by the payload
+    pub fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+
+        match self {
+            ValueKind::String(bytes) => {
+                buf.push(TAG_STRING);
+                buf.extend_from_slice(bytes);
+            }
+            ValueKind::Compressed(compressed) => {
+                buf.push(TAG_COMPRESSED);
+                buf.push(compressed.codec.id());
+                buf.extend_from_slice(&(compressed.raw_len as u32).to_le_bytes());
+                buf.extend_from_slice(&compressed.data);
+            }
+            ValueKind::Set(members) => {
+                buf.push(TAG_SET);
+                write_members(&mut buf, members.iter());
+            }
+            ValueKind::SortedSet(zset) => {
+                buf.push(TAG_ZSET);
+                buf.extend_from_slice(&(zset.len() as u32).to_le_bytes());
+                for (member, score) in zset.iter() {
+                    write_scored(&mut buf, score, member);
+                }
+            }
+            ValueKind::List(list) => {
+                buf.push(TAG_LIST);
+                write_members(&mut buf, list.iter());
+            }
+        }
+
+        buf
+    }
+
+    /// Deserialize a value produced by `encode`
+    pub fn decode(bytes: &[u8]) -> Result<Self, String> {
+        let (&tag, payload) = bytes.split_first()
+            .ok_or_else(|| "Empty value encoding".to_string())?;
+
+        match tag {
+            TAG_STRING => Ok(ValueKind::String(payload.into())),
+            TAG_COMPRESSED => {
+                let (&codec, rest) = payload.split_first()
+                    .ok_or_else(|| "Truncated compressed value".to_string())?;
+                let (raw_len, data) = rest.split_first_chunk::<4>()
+                    .ok_or_else(|| "Truncated compressed value".to_string())?;
+                let compressed = CompressedString {
+                    codec: Codec::from_id(codec)?,
+                    raw_len: u32::from_le_bytes(*raw_len) as usize,
+                    data: data.into(),
+                };
+                
+                // Validate once here so later reads can't fail
+                match compressed.codec {
+                    Codec::Lz4 => lz4::decompress(&compressed.data, compressed.raw_len)?,
+                };
+                Ok(ValueKind::Compressed(compressed))
+            }
+            TAG_SET => {
+                let mut reader = Reader::new(payload);
+                Ok(ValueKind::Set(reader.members()?.into_iter().collect()))
+            }
+            TAG_ZSET => {
+                let mut reader = Reader::new(payload);
+                let mut zset = SortedSet::new();
+                for (score, member) in reader.scored()? {
+                    let score = OrderedFloat::new(score)
+                        .ok_or_else(|| "NaN score in sorted set encoding".to_string())?;
+                    zset.insert(member, score);
+                }
+                Ok(ValueKind::SortedSet(zset))
+            }
+            TAG_LIST => {
+                let mut reader = Reader::new(payload);
+                Ok(ValueKind::List(reader.members()?.into()))
+            }
+            other => Err(format!("Unknown value tag: {}", other)),
+        }
+    }
+}
+
+impl SetOp {
+    /// Combine sets in order; missing keys count as empty sets
+    /// SDIFF is the first set minus all the others
+    pub fn combine(&self, sets: &[Option<&HashSet<Vec<u8>>>]) -> HashSet<Vec<u8>> {
+        let Some((first, rest)) = sets.split_first() else {
+            return HashSet::new();
+        };
+
+        match self {
+            SetOp::Union => sets.iter()
+                .flatten()
+                .flat_map(|set| set.iter().cloned())
+                .collect(),
+            SetOp::Inter => {
+                // Any missing key empties the intersection
+                if sets.iter().any(|set| set.is_none()) {
+                    return HashSet::new();
+                }
+
+                // Probe from the smallest set
+                let smallest = sets.iter().flatten().min_by_key(|set| set.len()).unwrap();
+                smallest.iter()
+                    .filter(|m| sets.iter().flatten().all(|set| set.contains(*m)))
+                    .cloned()
+                    .collect()
+            }
+            SetOp::Diff => first.map(|first| {
+                first.iter()
+                    .filter(|m| !rest.iter().flatten().any(|set| set.contains(*m)))
+                    .cloned()
+                    .collect()
+            }).unwrap_or_default(),
+        }
+    }
+    
+    /// Size of the intersection without building it, capped at `limit` (0 = no cap)
+    /// Probing stops as soon as the cap is reached
+    pub fn inter_card(sets: &[Option<&HashSet<Vec<u8>>>], limit: usize) -> usize {
+        let Some(sets) = sets.iter().copied().collect::<Option<Vec<_>>>() else {
+            return 0;
+        };
+        let Some(smallest) = sets.iter().min_by_key(|set| set.len()) else {
+            return 0;
+        };
+        
+        let matches = smallest.iter().filter(|m| sets.iter().all(|set| set.contains(*m)));
+        if limit == 0 {
+            matches.count()
+        } else {
+            matches.take(limit).count()
+        }
+    }
+}
+
+impl Mutation {
+    /// Value created when the mutation targets a missing key (None = no-op on missing keys)
+    pub fn empty_value(&self) -> Option<ValueKind> {
+        match self {
+            Mutation::SAdd(_) => Some(ValueKind::Set(HashSet::new())),
+            Mutation::ZAdd(_, flags) if !flags.xx => Some(ValueKind::SortedSet(SortedSet::new())),
+            Mutation::LPush(_) | Mutation::RPush(_) => Some(ValueKind::List(VecDeque::new())),
+            Mutation::SRem(_) | Mutation::ZAdd(..) | Mutation::ZRem(_) => None,
+        }
+    }
+
+    /// Apply to a value, counting the elements it changed
+    pub fn apply(&self, value: &mut ValueKind) -> Result<Applied, String> {
+        let mut applied = Applied::default();
+        
+        match (self, value) {
+            (Mutation::SAdd(members), ValueKind::Set(set)) => {
+                applied.added = members.iter().filter(|m| set.insert(m.to_vec())).count();
+            }
+            (Mutation::SRem(members), ValueKind::Set(set)) => {
+                applied.removed = members.iter().filter(|m| set.remove(*m)).count();
+            }
+            (Mutation::ZAdd(members, flags), ValueKind::SortedSet(zset)) => {
+                for (score, member) in members {
+                    let current = zset.score(member);
+                    let target = match current {
+                        Some(current) if flags.incr => current + score,
+                        _ => *score,
+                    };
+                    let target = OrderedFloat::new(target)
+                        .ok_or_else(|| "resulting score is not a number (NaN)".to_string())?;
+                    
+                    // GT/LT only restrict updates; new members are always eligible
+                    let allowed = match current {
+                        Some(_) if flags.nx => false,
+                        None if flags.xx => false,
+                        Some(current) if flags.gt => target.get() > current,
+                        Some(current) if flags.lt => target.get() < current,
+                        _ => true,
+                    };
+                    if !allowed {
+                        applied.skipped += 1;
+                        continue;
+                    }
+                    
+                    if zset.insert(member.clone(), target) {
+                        applied.added += 1;
+                    } else if current != Some(target.get()) {
+                        applied.updated += 1;
+                    }
+                }
+            }
+            (Mutation::ZRem(members), ValueKind::SortedSet(zset)) => {
+                applied.removed = members.iter().filter(|m| zset.remove(m)).count();
+            }
+            (Mutation::LPush(elements), ValueKind::List(list)) => {
+                for element in elements {
+                    list.push_front(element.clone());
+                }
+                applied.added = elements.len();
+            }
+            (Mutation::RPush(elements), ValueKind::List(list)) => {
+                list.extend(elements.iter().cloned());
+                applied.added = elements.len();
+            }
+            _ => return Err(WRONGTYPE.to_string()),
+        }
+        
+        Ok(applied)
+    }
+
+    /// Serialize for the AOF
+    pub fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+
+        match self {
+            Mutation::SAdd(members) => {
+                buf.push(OP_SADD);
+                write_members(&mut buf, members.iter());
+            }
+            Mutation::SRem(members) => {
+                buf.push(OP_SREM);
+                write_members(&mut buf, members.iter());
+            }
+            Mutation::ZAdd(members, flags) => {
+                buf.push(OP_ZADD);
+                buf.push(flags.to_bits());
+                buf.extend_from_slice(&(members.len() as u32).to_le_bytes());
+                for (score, member) in members {
+                    write_scored(&mut buf, *score, member);
+                }
+            }
+            Mutation::ZRem(members) => {
+                buf.push(OP_ZREM);
+                write_members(&mut buf, members.iter());
+            }
+            Mutation::LPush(elements) => {
+                buf.push(OP_LPUSH);
+                write_members(&mut buf, elements.iter());
+            }
+            Mutation::RPush(elements) => {
+                buf.push(OP_RPUSH);
+                write_members(&mut buf, elements.iter());
+            }
+        }
+
+        buf
+    }
+
+    /// Deserialize a mutation produced by `encode`
+    pub fn decode(bytes: &[u8]) -> Result<Self, String> {
+        let (&op, payload) = bytes.split_first()
+            .ok_or_else(|| "Empty mutation encoding".to_string())?;
+        let mut reader = Reader::new(payload);
+
+        match op {
+            OP_SADD => Ok(Mutation::SAdd(reader.members()?)),
+            OP_SREM => Ok(Mutation::SRem(reader.members()?)),
+            OP_ZADD => {
+                let flags = ZAddFlags::from_bits(reader.bytes(1)?[0]);
+                Ok(Mutation::ZAdd(reader.scored()?, flags))
+            }
+            OP_ZREM => Ok(Mutation::ZRem(reader.members()?)),
+            OP_LPUSH => Ok(Mutation::LPush(reader.members()?)),
+            OP_RPUSH => Ok(Mutation::RPush(reader.members()?)),
+            other => Err(format!("Unknown mutation op: {}", other)),
+        }
+    }
+}
+
+impl ZAddFlags {
+    /// Pack into a byte for the AOF
+    fn to_bits(self) -> u8 {
+        (self.nx as u8) | (self.xx as u8) << 1 | (self.gt as u8) << 2 | (self.lt as u8) << 3 | (self.incr as u8) << 4
+    }
+    
+    /// Unpack from `to_bits`
+    fn from_bits(bits: u8) -> Self {
+        Self {
+            nx: bits & 1 != 0,
+            xx: bits & 1 << 1 != 0,
+            gt: bits & 1 << 2 != 0,
+            lt: bits & 1 << 3 != 0,
+            incr: bits & 1 << 4 != 0,
+        }
+    }
+}
+
+// === ENCODING HELPERS ===
+
+/// Write a u32 count followed by length-prefixed members
+fn write_members<'a>(buf: &mut Vec<u8>, members: impl ExactSizeIterator<Item = &'a Vec<u8>>) {
+    buf.extend_from_slice(&(members.len() as u32).to_le_bytes());
+    for member in members {
+        buf.extend_from_slice(&(member.len() as u32).to_le_bytes());
+        buf.extend_from_slice(member);
+    }
+}
+
+/// Write one score + length-prefixed member
+fn write_scored(buf: &mut Vec<u8>, score: f64, member: &[u8]) {
+    buf.extend_from_slice(&score.to_le_bytes());
+    buf.extend_from_slice(&(member.len() as u32).to_le_bytes());
+    buf.extend_from_slice(member);
+}
+
+/// Cursor over an encoded payload
+struct Reader<'a> {
+    // Remaining bytes
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data }
+    }
+
+    /// Read a little-endian u32
+    fn u32(&mut self) -> Result<u32, String> {
+        let bytes = self.bytes(4)?;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    /// Read `len` raw bytes
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8], String> {
+        if self.data.len() < len {
+            return Err("Truncated value encoding".to_string());
+        }
+        let (head, tail) = self.data.split_at(len);
+        self.data = tail;
+        Ok(head)
+    }
+
+    /// Read a member list written by `write_members`
+    fn members(&mut self) -> Result<Vec<Vec<u8>>, String> {
+        let count = self.u32()? as usize;
+
+        // Cap the preallocation - the count comes from disk
+        let mut members = Vec::with_capacity(count.min(1024));
+        for _ in 0..count {
+            let len = self.u32()? as usize;
+            members.push(self.bytes(len)?.to_vec());
+        }
+
+        Ok(members)
+    }
+    
+    /// Read a u32 count followed by `write_scored` records
+    fn scored(&mut self) -> Result<Vec<(f64, Vec<u8>)>, String> {
+        let count = self.u32()? as usize;
+        
+        let mut members = Vec::with_capacity(count.min(1024));
+        for _ in 0..count {
+            let bytes = self.bytes(8)?;
+            let score = f64::from_le_bytes(bytes.try_into().expect("8 bytes"));
+            let len = self.u32()? as usize;
+            members.push((score, self.bytes(len)?.to_vec()));
+        }
+        
+        Ok(members)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn set(members: &[&[u8]]) -> HashSet<Vec<u8>> {
+        members.iter().map(|m| m.to_vec()).collect()
+    }
+
+    #[test]
+    fn test_set_algebra() {
+        let a = set(&[b"a", b"b", b"c"]);
+        let b = set(&[b"b", b"c", b"d"]);
+
+        assert_eq!(SetOp::Inter.combine(&[Some(&a), Some(&b)]), set(&[b"b", b"c"]));
+        assert_eq!(SetOp::Union.combine(&[Some(&a), None, Some(&b)]).len(), 4);
+        assert_eq!(SetOp::Diff.combine(&[Some(&a), Some(&b)]), set(&[b"a"]));
+
+        // Missing keys behave as empty sets
+        assert!(SetOp::Inter.combine(&[Some(&a), None]).is_empty());
+        assert!(SetOp::Diff.combine(&[None, Some(&a)]).is_empty());
+        assert_eq!(SetOp::Diff.combine(&[Some(&a), None]), a);
+    }
+
+    #[test]
+    fn test_encode_round_trip() {
+        let value = ValueKind::Set(set(&[b"x", b"", b"yz"]));
+        assert_eq!(ValueKind::decode(&value.encode()).unwrap(), value);
+
+        let value = ValueKind::String(b"plain".as_slice().into());
+        assert_eq!(ValueKind::decode(&value.encode()).unwrap(), value);
+
+        let mutation = Mutation::SRem(vec![b"x".to_vec()]);
+        assert_eq!(Mutation::decode(&mutation.encode()).unwrap(), mutation);
+
+        let flags = ZAddFlags { xx: true, gt: true, ..ZAddFlags::default() };
+        let mutation = Mutation::ZAdd(vec![(1.5, b"m".to_vec()), (f64::NEG_INFINITY, b"n".to_vec())], flags);
+        assert_eq!(Mutation::decode(&mutation.encode()).unwrap(), mutation);
+        
+        let mut zset = ValueKind::SortedSet(SortedSet::new());
+        mutation.apply(&mut zset).unwrap();
+        Mutation::ZAdd(vec![(2.0, b"a".to_vec())], ZAddFlags::default()).apply(&mut zset).unwrap();
+        assert_eq!(ValueKind::decode(&zset.encode()).unwrap(), zset);
+        
+        // LPUSH reverses its arguments onto the head, like Redis
+        let mut list = Mutation::RPush(vec![b"b".to_vec()]).empty_value().unwrap();
+        let lpush = Mutation::LPush(vec![b"a".to_vec(), b"z".to_vec()]);
+        Mutation::RPush(vec![b"b".to_vec()]).apply(&mut list).unwrap();
+        lpush.apply(&mut list).unwrap();
+        assert_eq!(list.as_list().unwrap(), &[b"z".to_vec(), b"a".to_vec(), b"b".to_vec()]);
+        assert_eq!(ValueKind::decode(&list.encode()).unwrap(), list);
+        assert_eq!(Mutation::decode(&lpush.encode()).unwrap(), lpush);
+        
+        assert!(ValueKind::decode(&[TAG_SET, 5, 0, 0, 0]).is_err());
+    }
+    
+    #[test]
+    fn test_zadd_flags() {
+        let mut zset = ValueKind::SortedSet(SortedSet::new());
+        let zadd = |score: f64, flags: ZAddFlags| Mutation::ZAdd(vec![(score, b"m".to_vec())], flags);
+        
+        assert_eq!(zadd(5.0, ZAddFlags::default()).apply(&mut zset).unwrap().added, 1);
+        
+        // GT/LT gate updates, NX never updates
+        let gt = ZAddFlags { gt: true, ..ZAddFlags::default() };
+        assert_eq!(zadd(3.0, gt).apply(&mut zset).unwrap().skipped, 1);
+        assert_eq!(zadd(7.0, gt).apply(&mut zset).unwrap().updated, 1);
+        let nx = ZAddFlags { nx: true, ..ZAddFlags::default() };
+        assert_eq!(zadd(1.0, nx).apply(&mut zset).unwrap().skipped, 1);
+        
+        // INCR adds to the current score and rejects NaN results
+        let incr = ZAddFlags { incr: true, ..ZAddFlags::default() };
+        zadd(0.5, incr).apply(&mut zset).unwrap();
+        assert_eq!(zset.as_sorted_set().unwrap().score(b"m"), Some(7.5));
+        zadd(f64::INFINITY, ZAddFlags::default()).apply(&mut zset).unwrap();
+        assert!(zadd(f64::NEG_INFINITY, incr).apply(&mut zset).is_err());
+    }
+}
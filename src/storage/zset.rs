@@ -0,0 +1,246 @@
+// Sorted set value type - members ordered by score, ties ordered by member bytes
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::ops::Bound;
+
+/// f64 with a total order - NaN is rejected on construction
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OrderedFloat(f64);
+
+impl OrderedFloat {
+    /// Wrap a score, rejecting NaN
+    pub fn new(value: f64) -> Option<Self> {
+        if value.is_nan() {
+            None
+        } else {
+            Some(Self(value))
+        }
+    }
+
+    /// Underlying score
+    pub fn get(self) -> f64 {
+        self.0
+    }
+}
+
+impl Eq for OrderedFloat {}
+
+impl PartialOrd for OrderedFloat {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for OrderedFloat {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // Never NaN, so total_cmp only differs from IEEE ordering on -0.0 vs 0.0
+        if self.0 == other.0 {
+            Ordering::Equal
+        } else {
+            self.0.total_cmp(&other.0)
+        }
+    }
+}
+
+/// SortedSet - Score-ordered index plus member -> score map
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SortedSet {
+    // Score -> members holding it (BTreeSet keeps ties in member order)
+    by_score: BTreeMap<OrderedFloat, BTreeSet<Vec<u8>>>,
+
+    // Member -> score for O(1) ZSCORE
+    scores: HashMap<Vec<u8>, OrderedFloat>,
+}
+
+impl SortedSet {
+    /// Create empty sorted set
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of members
+    pub fn len(&self) -> usize {
+        self.scores.len()
+    }
+
+    /// Whether the set has no members
+    pub fn is_empty(&self) -> bool {
+        self.scores.is_empty()
+    }
+
+    /// Score of a member
+    pub fn score(&self, member: &[u8]) -> Option<f64> {
+        self.scores.get(member).map(|s| s.get())
+    }
+
+    /// Insert or update a member, returning true if it was new
+    pub fn insert(&mut self, member: Vec<u8>, score: OrderedFloat) -> bool {
+        let previous = self.scores.insert(member.clone(), score);
+
+        if let Some(old) = previous {
+            if old == score {
+                return false;
+            }
+            self.unindex(&member, old);
+        }
+
+        self.by_score.entry(score).or_default().insert(member);
+        previous.is_none()
+    }
+
+    /// Remove a member, returning whether it existed
+    pub fn remove(&mut self, member: &[u8]) -> bool {
+        match self.scores.remove(member) {
+            Some(score) => {
+                self.unindex(member, score);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// 0-based rank in ascending (or descending) order
+    /// Walks the score index up to the member, so cost grows with rank
+    pub fn rank(&self, member: &[u8], reverse: bool) -> Option<usize> {
+        let score = *self.scores.get(member)?;
+
+        let below: usize = self.by_score.range(..score).map(|(_, ms)| ms.len()).sum();
+        let ties = &self.by_score[&score];
+        let within = ties.range::<[u8], _>((Bound::Unbounded, Bound::Excluded(member))).count();
+        let rank = below + within;
+
+        Some(if reverse { self.len() - 1 - rank } else { rank })
+    }
+
+    /// Members by rank with Redis index semantics (negative counts from the end)
+    pub fn range(&self, start: i64, stop: i64, reverse: bool) -> Vec<(Vec<u8>, f64)> {
+        let len = self.len() as i64;
+        let start = if start < 0 { (len + start).max(0) } else { start };
+        let stop = if stop < 0 { len + stop } else { stop.min(len - 1) };
+
+        if start > stop || start >= len {
+            return Vec::new();
+        }
+        let (skip, take) = (start as usize, (stop - start + 1) as usize);
+
+        if reverse {
+            self.iter().rev().skip(skip).take(take).map(|(m, s)| (m.clone(), s)).collect()
+        } else {
+            self.iter().skip(skip).take(take).map(|(m, s)| (m.clone(), s)).collect()
+        }
+    }
+
+    /// Members with scores between `min` and `max`, in ascending (or descending) order
+    /// Only the score index between the bounds is walked; `offset` and `count` page the result
+    pub fn range_by_score(
+        &self,
+        min: Bound<OrderedFloat>,
+        max: Bound<OrderedFloat>,
+        reverse: bool,
+        offset: usize,
+        count: Option<usize>,
+    ) -> Vec<(Vec<u8>, f64)> {
+        // BTreeMap::range panics on an inverted or empty-exclusive range
+        let inverted = match (min, max) {
+            (Bound::Included(lo), Bound::Included(hi)) => lo > hi,
+            (Bound::Included(lo) | Bound::Excluded(lo), Bound::Included(hi) | Bound::Excluded(hi)) => lo >= hi,
+            _ => false,
+        };
+        if inverted {
+            return Vec::new();
+        }
+        
+        let members = self.by_score.range((min, max))
+            .flat_map(|(score, members)| members.iter().map(move |m| (m, score.get())));
+        let count = count.unwrap_or(usize::MAX);
+        if reverse {
+            members.rev().skip(offset).take(count).map(|(m, s)| (m.clone(), s)).collect()
+        } else {
+            members.skip(offset).take(count).map(|(m, s)| (m.clone(), s)).collect()
+        }
+    }
+    
+    /// Members in ascending order
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = (&Vec<u8>, f64)> {
+        self.by_score.iter()
+            .flat_map(|(score, members)| members.iter().map(move |m| (m, score.get())))
+    }
+
+    /// Drop a member from the score index
+    fn unindex(&mut self, member: &[u8], score: OrderedFloat) {
+        if let Some(members) = self.by_score.get_mut(&score) {
+            members.remove(member);
+            if members.is_empty() {
+                self.by_score.remove(&score);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn score(value: f64) -> OrderedFloat {
+        OrderedFloat::new(value).unwrap()
+    }
+
+    #[test]
+    fn test_rank_and_range() {
+        let mut zset = SortedSet::new();
+        assert!(zset.insert(b"b".to_vec(), score(2.0)));
+        assert!(zset.insert(b"a".to_vec(), score(2.0)));
+        assert!(zset.insert(b"c".to_vec(), score(-1.5)));
+        assert!(zset.insert(b"d".to_vec(), score(f64::INFINITY)));
+
+        // Ties are ordered by member
+        assert_eq!(zset.rank(b"c", false), Some(0));
+        assert_eq!(zset.rank(b"a", false), Some(1));
+        assert_eq!(zset.rank(b"b", false), Some(2));
+        assert_eq!(zset.rank(b"d", true), Some(0));
+
+        let members: Vec<Vec<u8>> = zset.range(0, -1, false).into_iter().map(|(m, _)| m).collect();
+        assert_eq!(members, vec![b"c".to_vec(), b"a".to_vec(), b"b".to_vec(), b"d".to_vec()]);
+        assert_eq!(zset.range(-2, 100, true), vec![(b"a".to_vec(), 2.0), (b"c".to_vec(), -1.5)]);
+        assert!(zset.range(3, 1, false).is_empty());
+
+        // Updating a score moves the member
+        assert!(!zset.insert(b"c".to_vec(), score(10.0)));
+        assert_eq!(zset.rank(b"c", false), Some(2));
+        assert!(zset.remove(b"c"));
+        assert_eq!(zset.len(), 3);
+
+        assert!(OrderedFloat::new(f64::NAN).is_none());
+    }
+    
+    #[test]
+    fn test_range_by_score() {
+        let mut zset = SortedSet::new();
+        for (member, value) in [(&b"a"[..], 1.0), (b"b", 2.0), (b"c", 2.0), (b"d", 3.0), (b"e", f64::INFINITY)] {
+            zset.insert(member.to_vec(), score(value));
+        }
+        let members = |entries: Vec<(Vec<u8>, f64)>| entries.into_iter().map(|(m, _)| m).collect::<Vec<_>>();
+        
+        let all = zset.range_by_score(Bound::Included(score(f64::NEG_INFINITY)), Bound::Included(score(f64::INFINITY)), false, 0, None);
+        assert_eq!(all.len(), 5);
+        assert_eq!(
+            members(zset.range_by_score(Bound::Excluded(score(1.0)), Bound::Included(score(3.0)), false, 0, None)),
+            vec![b"b".to_vec(), b"c".to_vec(), b"d".to_vec()]
+        );
+        
+        // LIMIT pages the matches; reverse walks from max down
+        assert_eq!(
+            members(zset.range_by_score(Bound::Included(score(2.0)), Bound::Excluded(score(f64::INFINITY)), false, 1, Some(1))),
+            vec![b"c".to_vec()]
+        );
+        assert_eq!(
+            zset.range_by_score(Bound::Included(score(1.0)), Bound::Included(score(2.0)), true, 0, Some(2)),
+            vec![(b"c".to_vec(), 2.0), (b"b".to_vec(), 2.0)]
+        );
+        
+        // Inverted and empty exclusive ranges match nothing
+        assert!(zset.range_by_score(Bound::Included(score(3.0)), Bound::Included(score(1.0)), false, 0, None).is_empty());
+        assert!(zset.range_by_score(Bound::Excluded(score(2.0)), Bound::Excluded(score(2.0)), false, 0, None).is_empty());
+        assert_eq!(zset.range_by_score(Bound::Included(score(2.0)), Bound::Included(score(2.0)), false, 0, None).len(), 2);
+    }
+}
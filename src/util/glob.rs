@@ -0,0 +1,226 @@
+// Redis-style glob matching for pattern subscriptions and key filters,
+// and SQL LIKE patterns built on the same backtracking
+
+/// Match `text` against a glob `pattern`
+/// Supports `*`, `?`, `[abc]`, `[^abc]`, `[a-z]` and `\` escapes
+pub fn glob_match(pattern: &[u8], text: &[u8]) -> bool {
+    let mut p = 0;
+    let mut t = 0;
+
+    // Backtrack point for the most recent `*`
+    let mut star: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        if p < pattern.len() {
+            match pattern[p] {
+                b'*' => {
+                    // Collapse consecutive stars, remember where to resume
+                    while p < pattern.len() && pattern[p] == b'*' {
+                        p += 1;
+                    }
+                    if p == pattern.len() {
+                        return true;
+                    }
+                    star = Some((p, t));
+                    continue;
+                }
+                b'?' => {
+                    p += 1;
+                    t += 1;
+                    continue;
+                }
+                b'[' => {
+                    if let Some((true, next)) = match_class(pattern, p, text[t]) {
+                        p = next;
+                        t += 1;
+                        continue;
+                    }
+                }
+                b'\\' if p + 1 < pattern.len() => {
+                    if pattern[p + 1] == text[t] {
+                        p += 2;
+                        t += 1;
+                        continue;
+                    }
+                }
+                c => {
+                    if c == text[t] {
+                        p += 1;
+                        t += 1;
+                        continue;
+                    }
+                }
+            }
+        }
+
+        // Mismatch - let the last star absorb one more byte
+        match star {
+            Some((star_p, star_t)) => {
+                p = star_p;
+                t = star_t + 1;
+                star = Some((star_p, star_t + 1));
+            }
+            None => return false,
+        }
+    }
+
+    // Remaining pattern may only be stars
+    pattern[p..].iter().all(|&c| c == b'*')
+}
+
+/// SQL LIKE pattern, parsed once - `%` matches any run of characters, `_` exactly one
+/// character and `\` makes the next character literal. Matching is case-sensitive
+#[derive(Debug, Clone)]
+pub struct LikePattern {
+    tokens: Vec<LikeToken>,
+}
+
+/// One element of a LIKE pattern
+#[derive(Debug, Clone, PartialEq)]
+enum LikeToken {
+    // %
+    AnyRun,
+
+    // _
+    AnyOne,
+
+    // Literal character
+    Char(char),
+}
+
+impl LikePattern {
+    /// Parse a LIKE pattern (a trailing lone `\` matches itself)
+    pub fn new(pattern: &str) -> Self {
+        let mut tokens = Vec::new();
+        let mut chars = pattern.chars();
+        while let Some(c) = chars.next() {
+            tokens.push(match c {
+                '%' => LikeToken::AnyRun,
+                '_' => LikeToken::AnyOne,
+                '\\' => LikeToken::Char(chars.next().unwrap_or('\\')),
+                c => LikeToken::Char(c),
+            });
+        }
+        Self { tokens }
+    }
+
+    /// Whether the whole of `text` matches - `_` consumes a character, not a byte
+    pub fn matches(&self, text: &str) -> bool {
+        let tokens = &self.tokens;
+        let mut p = 0;
+        let mut t = 0;
+
+        // Backtrack point for the most recent `%`, as in glob_match
+        let mut star: Option<(usize, usize)> = None;
+
+        while let Some(c) = text[t..].chars().next() {
+            match tokens.get(p) {
+                Some(LikeToken::AnyRun) => {
+                    while tokens.get(p) == Some(&LikeToken::AnyRun) {
+                        p += 1;
+                    }
+                    if p == tokens.len() {
+                        return true;
+                    }
+                    star = Some((p, t));
+                    continue;
+                }
+                Some(LikeToken::AnyOne) => {
+                    p += 1;
+                    t += c.len_utf8();
+                    continue;
+                }
+                Some(LikeToken::Char(expected)) if *expected == c => {
+                    p += 1;
+                    t += c.len_utf8();
+                    continue;
+                }
+                _ => {}
+            }
+
+            // Mismatch - let the last `%` absorb one more character
+            let Some((star_p, star_t)) = star else {
+                return false;
+            };
+            let skipped = text[star_t..].chars().next().map_or(1, char::len_utf8);
+            p = star_p;
+            t = star_t + skipped;
+            star = Some((star_p, t));
+        }
+
+        // Remaining pattern may only be `%`
+        tokens[p..].iter().all(|token| *token == LikeToken::AnyRun)
+    }
+}
+
+/// Match a `[...]` class starting at `start`, returning (matched, index after class)
+fn match_class(pattern: &[u8], start: usize, c: u8) -> Option<(bool, usize)> {
+    let mut i = start + 1;
+    let negate = i < pattern.len() && pattern[i] == b'^';
+    if negate {
+        i += 1;
+    }
+
+    let mut matched = false;
+    while i < pattern.len() && pattern[i] != b']' {
+        if pattern[i] == b'\\' && i + 1 < pattern.len() {
+            matched |= pattern[i + 1] == c;
+            i += 2;
+        } else if i + 2 < pattern.len() && pattern[i + 1] == b'-' && pattern[i + 2] != b']' {
+            let (lo, hi) = if pattern[i] <= pattern[i + 2] {
+                (pattern[i], pattern[i + 2])
+            } else {
+                (pattern[i + 2], pattern[i])
+            };
+            matched |= lo <= c && c <= hi;
+            i += 3;
+        } else {
+            matched |= pattern[i] == c;
+            i += 1;
+        }
+    }
+
+    // Unterminated class never matches
+    if i >= pattern.len() {
+        return None;
+    }
+
+    Some((matched != negate, i + 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match(b"*", b"anything"));
+        assert!(glob_match(b"__keyspace@0__:*", b"__keyspace@0__:user:1"));
+        assert!(glob_match(b"h?llo", b"hello"));
+        assert!(glob_match(b"h*llo", b"heeeello"));
+        assert!(glob_match(b"h[ae]llo", b"hallo"));
+        assert!(!glob_match(b"h[ae]llo", b"hillo"));
+        assert!(glob_match(b"h[^e]llo", b"hallo"));
+        assert!(!glob_match(b"h[^e]llo", b"hello"));
+        assert!(glob_match(b"h[a-c]llo", b"hbllo"));
+        assert!(glob_match(b"h\\*llo", b"h*llo"));
+        assert!(!glob_match(b"h\\*llo", b"hello"));
+        assert!(!glob_match(b"a*b", b"acd"));
+        assert!(glob_match(b"", b""));
+    }
+
+    #[test]
+    fn test_like_pattern() {
+        let like = |pattern: &str, text: &str| LikePattern::new(pattern).matches(text);
+        assert!(like("foo%", "foobar"));
+        assert!(like("%bar", "foobar"));
+        assert!(like("f_o%r", "foobar"));
+        assert!(like("%", ""));
+        assert!(like("_é_", "cés"));
+        assert!(!like("foo%", "Foobar"));
+        assert!(!like("f_o", "fo"));
+        assert!(like("100\\%", "100%"));
+        assert!(!like("100\\%", "1000"));
+        assert!(like("a*[b]?", "a*[b]?"));
+    }
+}
@@ -0,0 +1,180 @@
+// Latency monitor - per-event spike history (LATENCY LATEST / HISTORY / RESET)
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, OnceLock, PoisonError};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Samples kept per event, as in Redis
+const HISTORY_LEN: usize = 160;
+
+/// Slow command dispatch
+pub const EVENT_COMMAND: &str = "command";
+
+/// AOF fsync, background or inline
+pub const EVENT_AOF_FSYNC: &str = "aof-fsync";
+
+/// Background GC cycle
+pub const EVENT_GC: &str = "gc";
+
+/// Sampled active expiry pass between GC cycles
+pub const EVENT_EXPIRE_CYCLE: &str = "expire-cycle";
+
+/// LatencyMonitor - Spikes at or over a threshold, tagged by event name
+/// Each event keeps at most one sample per second (the worst) and its all-time max
+pub struct LatencyMonitor {
+    // Spikes of at least this many milliseconds are recorded (0 = off)
+    threshold_ms: AtomicU64,
+
+    // Event name -> history
+    events: Mutex<HashMap<&'static str, EventHistory>>,
+}
+
+/// Samples for one event
+#[derive(Default)]
+struct EventHistory {
+    // (unix seconds, milliseconds), oldest first
+    samples: VecDeque<(u64, u64)>,
+
+    // Worst latency since the last reset
+    max_ms: u64,
+}
+
+/// LATENCY LATEST row
+#[derive(Debug, Clone, PartialEq)]
+pub struct LatestSample {
+    pub event: &'static str,
+
+    // Unix time in seconds of the newest sample
+    pub timestamp: u64,
+
+    pub latest_ms: u64,
+    pub max_ms: u64,
+}
+
+impl LatencyMonitor {
+    /// Create a monitor with recording off
+    pub fn new() -> Self {
+        Self {
+            threshold_ms: AtomicU64::new(0),
+            events: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Process-wide monitor - the AOF flush thread and GC report here too
+    pub fn global() -> &'static Self {
+        static MONITOR: OnceLock<LatencyMonitor> = OnceLock::new();
+        MONITOR.get_or_init(LatencyMonitor::new)
+    }
+
+    /// Record spikes of at least `threshold` (None or under 1ms = off)
+    pub fn set_threshold(&self, threshold: Option<Duration>) {
+        let ms = threshold.map_or(0, |threshold| threshold.as_millis() as u64);
+        self.threshold_ms.store(ms, Ordering::Relaxed);
+    }
+
+    /// Record that `event` took `elapsed` - a single atomic load when under the threshold
+    pub fn record(&self, event: &'static str, elapsed: Duration) {
+        let threshold_ms = self.threshold_ms.load(Ordering::Relaxed);
+        let ms = elapsed.as_millis() as u64;
+        if threshold_ms == 0 || ms < threshold_ms {
+            return;
+        }
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+        self.record_at(event, now, ms);
+    }
+
+    fn record_at(&self, event: &'static str, now: u64, ms: u64) {
+        let mut events = self.lock();
+        let history = events.entry(event).or_default();
+        history.max_ms = history.max_ms.max(ms);
+
+        // Several spikes in the same second keep only the worst
+        match history.samples.back_mut() {
+            Some((second, worst)) if *second == now => *worst = (*worst).max(ms),
+            _ => {
+                if history.samples.len() == HISTORY_LEN {
+                    history.samples.pop_front();
+                }
+                history.samples.push_back((now, ms));
+            }
+        }
+    }
+
+    /// (unix seconds, milliseconds) samples for `event`, oldest first
+    pub fn history(&self, event: &str) -> Vec<(u64, u64)> {
+        self.lock().get(event)
+            .map(|history| history.samples.iter().copied().collect())
+            .unwrap_or_default()
+    }
+
+    /// Newest sample and max of every event, by event name
+    pub fn latest(&self) -> Vec<LatestSample> {
+        let mut latest: Vec<LatestSample> = self.lock().iter()
+            .filter_map(|(&event, history)| {
+                let &(timestamp, latest_ms) = history.samples.back()?;
+                Some(LatestSample { event, timestamp, latest_ms, max_ms: history.max_ms })
+            })
+            .collect();
+        latest.sort_by_key(|sample| sample.event);
+        latest
+    }
+
+    /// Drop the history of `events` (all events when empty) - returns how many were dropped
+    pub fn reset(&self, events: &[&str]) -> usize {
+        let mut histories = self.lock();
+        if events.is_empty() {
+            let dropped = histories.len();
+            histories.clear();
+            return dropped;
+        }
+        events.iter().filter(|event| histories.remove(**event).is_some()).count()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<&'static str, EventHistory>> {
+        self.events.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl Default for LatencyMonitor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_samples_per_event() {
+        let monitor = LatencyMonitor::new();
+
+        // Off until a threshold is set, then only spikes at or over it count
+        monitor.record(EVENT_GC, Duration::from_millis(50));
+        assert!(monitor.latest().is_empty());
+        monitor.set_threshold(Some(Duration::from_millis(10)));
+        monitor.record(EVENT_GC, Duration::from_millis(9));
+        assert!(monitor.latest().is_empty());
+
+        // One sample per second keeps the worst
+        monitor.record_at(EVENT_GC, 100, 20);
+        monitor.record_at(EVENT_GC, 100, 35);
+        monitor.record_at(EVENT_GC, 101, 12);
+        monitor.record_at(EVENT_COMMAND, 101, 15);
+        assert_eq!(monitor.history(EVENT_GC), vec![(100, 35), (101, 12)]);
+        assert_eq!(monitor.latest(), vec![
+            LatestSample { event: EVENT_COMMAND, timestamp: 101, latest_ms: 15, max_ms: 15 },
+            LatestSample { event: EVENT_GC, timestamp: 101, latest_ms: 12, max_ms: 35 },
+        ]);
+
+        // History is bounded
+        for second in 0..200 {
+            monitor.record_at(EVENT_AOF_FSYNC, 1000 + second, 10);
+        }
+        assert_eq!(monitor.history(EVENT_AOF_FSYNC).len(), HISTORY_LEN);
+
+        assert_eq!(monitor.reset(&[EVENT_GC, "nope"]), 1);
+        assert_eq!(monitor.reset(&[]), 2);
+        assert!(monitor.history(EVENT_COMMAND).is_empty());
+    }
+}
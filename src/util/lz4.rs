@@ -0,0 +1,192 @@
+// LZ4 block format - greedy single-pass compressor and bounds-checked decompressor
+
+/// Shortest match worth encoding
+const MIN_MATCH: usize = 4;
+
+/// Hash table size for match finding (2^12 slots)
+const HASH_LOG: u32 = 12;
+
+/// No match may start within this many bytes of the end
+const MF_LIMIT: usize = 12;
+
+/// The final bytes are always literals
+const LAST_LITERALS: usize = 5;
+
+/// Matches reach back at most this far (16-bit offset)
+const MAX_OFFSET: usize = u16::MAX as usize;
+
+/// Compress `input` into an LZ4 block
+pub fn compress(input: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(input.len() / 2 + 16);
+
+    // Slot holds position + 1 of the last 4-byte sequence with that hash (0 = empty)
+    let mut table = vec![0u32; 1 << HASH_LOG];
+    let mut anchor = 0;
+    let mut pos = 0;
+
+    if input.len() > MF_LIMIT {
+        let limit = input.len() - MF_LIMIT;
+        let match_limit = input.len() - LAST_LITERALS;
+
+        while pos < limit {
+            let sequence = read_u32(input, pos);
+            let slot = hash(sequence);
+            let candidate = table[slot] as usize;
+            table[slot] = (pos + 1) as u32;
+
+            if candidate > 0 {
+                let candidate = candidate - 1;
+                if pos - candidate <= MAX_OFFSET && read_u32(input, candidate) == sequence {
+                    let mut len = MIN_MATCH;
+                    while pos + len < match_limit && input[candidate + len] == input[pos + len] {
+                        len += 1;
+                    }
+
+                    write_sequence(&mut out, &input[anchor..pos], (pos - candidate) as u16, len);
+                    pos += len;
+                    anchor = pos;
+                    continue;
+                }
+            }
+            pos += 1;
+        }
+    }
+
+    // Trailing literals close the block
+    let literals = &input[anchor..];
+    out.push((literals.len().min(15) as u8) << 4);
+    if literals.len() >= 15 {
+        write_length(&mut out, literals.len() - 15);
+    }
+    out.extend_from_slice(literals);
+
+    out
+}
+
+/// Decompress an LZ4 block that expands to exactly `raw_len` bytes
+pub fn decompress(input: &[u8], raw_len: usize) -> Result<Vec<u8>, String> {
+    let corrupt = || "Corrupted LZ4 block".to_string();
+    let mut out = Vec::with_capacity(raw_len);
+    let mut i = 0;
+
+    loop {
+        let token = *input.get(i).ok_or_else(corrupt)?;
+        i += 1;
+
+        let mut literal_len = (token >> 4) as usize;
+        if literal_len == 15 {
+            literal_len += read_length(input, &mut i)?;
+        }
+        if out.len() + literal_len > raw_len {
+            return Err(corrupt());
+        }
+        let literals = input.get(i..i + literal_len).ok_or_else(corrupt)?;
+        out.extend_from_slice(literals);
+        i += literal_len;
+
+        // The last sequence has literals only
+        if i == input.len() {
+            break;
+        }
+
+        let offset = input.get(i..i + 2)
+            .map(|b| u16::from_le_bytes([b[0], b[1]]) as usize)
+            .ok_or_else(corrupt)?;
+        i += 2;
+        if offset == 0 || offset > out.len() {
+            return Err(corrupt());
+        }
+
+        let mut match_len = (token & 0x0F) as usize;
+        if match_len == 15 {
+            match_len += read_length(input, &mut i)?;
+        }
+        match_len += MIN_MATCH;
+        if out.len() + match_len > raw_len {
+            return Err(corrupt());
+        }
+
+        // Byte by byte - the match may overlap the bytes it produces
+        let start = out.len() - offset;
+        for k in 0..match_len {
+            let byte = out[start + k];
+            out.push(byte);
+        }
+    }
+
+    if out.len() != raw_len {
+        return Err(corrupt());
+    }
+    Ok(out)
+}
+
+/// Append one literals + match sequence
+fn write_sequence(out: &mut Vec<u8>, literals: &[u8], offset: u16, match_len: usize) {
+    let match_extra = match_len - MIN_MATCH;
+    out.push(((literals.len().min(15) as u8) << 4) | match_extra.min(15) as u8);
+    if literals.len() >= 15 {
+        write_length(out, literals.len() - 15);
+    }
+    out.extend_from_slice(literals);
+    out.extend_from_slice(&offset.to_le_bytes());
+    if match_extra >= 15 {
+        write_length(out, match_extra - 15);
+    }
+}
+
+/// Length continuation bytes: runs of 255 then the remainder
+fn write_length(out: &mut Vec<u8>, mut len: usize) {
+    while len >= 255 {
+        out.push(255);
+        len -= 255;
+    }
+    out.push(len as u8);
+}
+
+/// Read length continuation bytes
+fn read_length(input: &[u8], i: &mut usize) -> Result<usize, String> {
+    let mut len = 0;
+    loop {
+        let byte = *input.get(*i).ok_or_else(|| "Corrupted LZ4 block".to_string())?;
+        *i += 1;
+        len += byte as usize;
+        if byte != 255 {
+            return Ok(len);
+        }
+    }
+}
+
+fn read_u32(input: &[u8], pos: usize) -> u32 {
+    u32::from_le_bytes([input[pos], input[pos + 1], input[pos + 2], input[pos + 3]])
+}
+
+/// Multiplicative hash of a 4-byte sequence into a table slot
+fn hash(sequence: u32) -> usize {
+    (sequence.wrapping_mul(2654435761) >> (32 - HASH_LOG)) as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let json = br#"{"id":1,"name":"widget","tags":["a","b"]},"#.repeat(200);
+        let mut noise = Vec::new();
+        let mut x: u32 = 12345;
+        for _ in 0..5000 {
+            x = x.wrapping_mul(1103515245).wrapping_add(12345);
+            noise.push((x >> 16) as u8);
+        }
+
+        for input in [&b""[..], b"short", &[7u8; 1000], &json, &noise] {
+            let compressed = compress(input);
+            assert_eq!(decompress(&compressed, input.len()).unwrap(), input);
+        }
+
+        // Repetitive data shrinks; truncated blocks are rejected
+        let compressed = compress(&json);
+        assert!(compressed.len() < json.len() / 4);
+        assert!(decompress(&compressed[..compressed.len() - 1], json.len()).is_err());
+    }
+}
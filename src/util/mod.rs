@@ -1,3 +1,9 @@
+pub mod base64;
 pub mod crc64;
+pub mod glob;
+pub mod json;
+pub mod latency;
+pub mod lz4;
 pub mod murmur3;
-pub mod panic;
\ No newline at end of file
+pub mod panic;
+pub mod rate;
\ No newline at end of file
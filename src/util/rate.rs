@@ -0,0 +1,107 @@
+// Sliding-window event rate (instantaneous ops/sec style counters)
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Instant;
+
+/// Seconds averaged over when reporting the rate
+const WINDOW_SECS: u64 = 5;
+
+/// Buckets kept - one extra for the second currently being filled
+const BUCKETS: usize = WINDOW_SECS as usize + 1;
+
+/// RateCounter - Per-second buckets over a short sliding window
+/// Lock-free; concurrent bucket rollover may drop a handful of events
+pub struct RateCounter {
+    // Clock origin for bucket seconds
+    start: Instant,
+
+    // Second each bucket currently counts
+    seconds: [AtomicU64; BUCKETS],
+
+    // Events recorded in each bucket
+    counts: [AtomicU64; BUCKETS],
+}
+
+impl RateCounter {
+    /// Create empty counter
+    pub fn new() -> Self {
+        Self {
+            start: Instant::now(),
+            seconds: std::array::from_fn(|_| AtomicU64::new(0)),
+            counts: std::array::from_fn(|_| AtomicU64::new(0)),
+        }
+    }
+
+    /// Record one event
+    pub fn record(&self) {
+        self.record_at(self.start.elapsed().as_secs());
+    }
+
+    /// Average events per second over the last complete window
+    pub fn per_second(&self) -> u64 {
+        self.per_second_at(self.start.elapsed().as_secs())
+    }
+
+    // === PRIVATE HELPERS ===
+
+    /// Record an event in the bucket for `second`
+    fn record_at(&self, second: u64) {
+        let idx = (second % BUCKETS as u64) as usize;
+
+        // First event of a new second recycles the bucket
+        let previous = self.seconds[idx].load(Ordering::Acquire);
+        if previous != second
+            && self.seconds[idx].compare_exchange(previous, second, Ordering::AcqRel, Ordering::Acquire).is_ok()
+        {
+            self.counts[idx].store(0, Ordering::Release);
+        }
+
+        self.counts[idx].fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Rate over the complete seconds preceding `now`
+    fn per_second_at(&self, now: u64) -> u64 {
+        let oldest = now.saturating_sub(WINDOW_SECS);
+
+        let total: u64 = (0..BUCKETS)
+            .filter(|&i| {
+                let second = self.seconds[i].load(Ordering::Acquire);
+                second >= oldest && second < now
+            })
+            .map(|i| self.counts[i].load(Ordering::Relaxed))
+            .sum();
+
+        total / WINDOW_SECS
+    }
+}
+
+impl Default for RateCounter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sliding_window() {
+        let rate = RateCounter::new();
+
+        // 10 events/sec for seconds 1..=5
+        for second in 1..=5 {
+            for _ in 0..10 {
+                rate.record_at(second);
+            }
+        }
+
+        // The second being filled is not counted yet
+        assert_eq!(rate.per_second_at(5), 8);
+        assert_eq!(rate.per_second_at(6), 10);
+
+        // Old buckets fall out of the window and are recycled
+        rate.record_at(7);
+        assert_eq!(rate.per_second_at(8), 6);
+        assert_eq!(rate.per_second_at(20), 0);
+    }
+}